@@ -0,0 +1,68 @@
+/* Generated from doom-core/src/ffi.rs; edit that instead. */
+#ifndef DOOM_H
+#define DOOM_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct doom_engine doom_engine;
+
+#define DOOM_KEY_RIGHTARROW 174
+#define DOOM_KEY_LEFTARROW 172
+#define DOOM_KEY_UPARROW 173
+#define DOOM_KEY_DOWNARROW 175
+#define DOOM_KEY_ESCAPE 27
+#define DOOM_KEY_ENTER 13
+#define DOOM_KEY_TAB 9
+#define DOOM_KEY_BACKSPACE 127
+#define DOOM_KEY_PAUSE 255
+#define DOOM_KEY_F1 187
+#define DOOM_KEY_F2 188
+#define DOOM_KEY_F3 189
+#define DOOM_KEY_F4 190
+#define DOOM_KEY_F5 191
+#define DOOM_KEY_F6 192
+#define DOOM_KEY_F7 193
+#define DOOM_KEY_F8 194
+#define DOOM_KEY_F9 195
+#define DOOM_KEY_F10 196
+#define DOOM_KEY_F11 215
+#define DOOM_KEY_F12 216
+#define DOOM_KEY_RSHIFT 182
+#define DOOM_KEY_RCTRL 157
+#define DOOM_KEY_RALT 184
+#define DOOM_KEY_HOME 199
+#define DOOM_KEY_END 207
+#define DOOM_KEY_PGUP 201
+#define DOOM_KEY_PGDN 209
+#define DOOM_KEY_INS 210
+#define DOOM_KEY_DEL 211
+
+/* Loads the IWAD and PWADs named on a command line in the usual syntax (argv[0] is
+ * skipped). Returns NULL if they can't be loaded. */
+doom_engine *doom_init(int argc, const char *const *argv);
+
+/* Runs one game tic with the keys currently held. */
+void doom_tick(doom_engine *engine);
+
+/* Draws the current frame and returns its palette indices, row by row. The pointer
+ * stays valid until the next call with this engine. */
+const uint8_t *doom_get_framebuffer(doom_engine *engine, int *width, int *height);
+
+/* The 256 RGB triples the framebuffer indexes. */
+const uint8_t *doom_get_palette(doom_engine *engine);
+
+/* A key went down (pressed != 0) or up, as a DOOM_KEY_* or lower case ASCII code. */
+void doom_key_event(doom_engine *engine, int key, int pressed);
+
+/* Frees the engine. NULL is ignored. */
+void doom_shutdown(doom_engine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
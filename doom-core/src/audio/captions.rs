@@ -0,0 +1,248 @@
+//! Captions for sound effects, for players who can't hear them.
+//!
+//! Whatever starts a sound on the mixer tells `Captions` too, with the sound's name and where
+//! it plays from. Sounds that matter get a line of text, with an arrow saying which way the
+//! sound came from relative to where the player faces: a door opening behind them, a monster
+//! waking up to the left. Each caption has a priority, 1 for the sounds that warn of danger
+//! up to 3 for the everyday ones, and `snd_captions` sets the lowest priority shown, 0 for
+//! none. A caption stays up for three seconds, and a sound heard again while its caption is
+//! up refreshes it instead of adding a line.
+
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::TICRATE;
+use crate::renderer::ViewPoint;
+use crate::util::angle::{Angle, ANG45, ANG90};
+use crate::util::fixed::Fixed;
+
+/// How long a caption stays up.
+pub const CAPTION_TICS: i32 = 3 * TICRATE;
+/// Captions shown at once; a new one pushes the oldest out.
+pub const MAX_CAPTIONS: usize = 4;
+
+/// The captioned sounds: the lump name without `DS`, the text and its priority.
+const CAPTIONS: &[(&str, &str, u8)] = &[
+    ("posit1", "Zombie alerted", 1),
+    ("posit2", "Zombie alerted", 1),
+    ("posit3", "Zombie alerted", 1),
+    ("bgsit1", "Imp screeches", 1),
+    ("bgsit2", "Imp screeches", 1),
+    ("sgtsit", "Demon roars", 1),
+    ("cacsit", "Cacodemon roars", 1),
+    ("brssit", "Baron roars", 1),
+    ("kntsit", "Hell knight roars", 1),
+    ("skesit", "Revenant screams", 1),
+    ("mansit", "Mancubus roars", 1),
+    ("bspsit", "Arachnotron chitters", 1),
+    ("vilsit", "Arch-vile cackles", 1),
+    ("pesit", "Pain elemental moans", 1),
+    ("cybsit", "Cyberdemon roars", 1),
+    ("spisit", "Spider mastermind roars", 1),
+    ("sklatk", "Lost soul charges", 1),
+    ("vilatk", "Arch-vile attacks", 1),
+    ("skeatk", "Homing missile", 1),
+    ("bossit", "Icon of sin awakens", 1),
+    ("bospit", "Cube spat out", 1),
+    ("telept", "Teleport", 1),
+    ("doropn", "Door opens", 2),
+    ("dorcls", "Door closes", 2),
+    ("bdopn", "Door opens", 2),
+    ("bdcls", "Door closes", 2),
+    ("pstart", "Lift starts", 2),
+    ("pstop", "Lift stops", 2),
+    ("swtchn", "Switch", 2),
+    ("swtchx", "Switch", 2),
+    ("firsht", "Fireball", 2),
+    ("stnmov", "Stone grinds", 3),
+    ("itemup", "Item picked up", 3),
+    ("wpnup", "Weapon picked up", 3),
+    ("getpow", "Power-up", 3),
+    ("noway", "Nothing to use", 3),
+    ("oof", "Grunt", 3),
+    ("plpain", "You're hurt", 3),
+];
+
+/// The caption of the sound `name`, and its priority.
+pub fn caption(name: &str) -> Option<(&'static str, u8)> {
+    CAPTIONS
+        .iter()
+        .find(|(sound, _, _)| sound.eq_ignore_ascii_case(name))
+        .map(|&(_, text, priority)| (text, priority))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CaptionOptions {
+    /// The lowest priority captioned, 0 for no captions.
+    pub priority: u8,
+}
+
+impl Configurable for CaptionOptions {
+    fn register(config: &mut Config) {
+        config.register_int("snd_captions", 0, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            priority: config.get_int("snd_captions").clamp(0, 3) as u8,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("snd_captions", i32::from(self.priority));
+    }
+}
+
+/// Where a sound is relative to where the player faces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ahead,
+    Left,
+    Right,
+    Behind,
+}
+
+impl Direction {
+    /// The direction of a sound at `origin`, in quarters around the listener.
+    pub fn of(listener: &ViewPoint, origin: (Fixed, Fixed)) -> Self {
+        let (dx, dy) = (
+            origin.0.wrapping_sub(listener.x),
+            origin.1.wrapping_sub(listener.y),
+        );
+        // As in `spatial::adjust`, nothing here feeds the simulation.
+        let bearing = (f64::from(dy).atan2(f64::from(dx)) / std::f64::consts::TAU * 4_294_967_296.0)
+            .rem_euclid(4_294_967_296.0) as Angle;
+        let relative = bearing.wrapping_sub(listener.angle).wrapping_add(ANG45);
+        match relative / ANG90 {
+            0 => Direction::Ahead,
+            1 => Direction::Left,
+            2 => Direction::Behind,
+            _ => Direction::Right,
+        }
+    }
+
+    /// The arrow drawn with the caption, in characters the HUD font has.
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Direction::Ahead => "^",
+            Direction::Left => "<",
+            Direction::Right => ">",
+            Direction::Behind => "V",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Caption {
+    pub text: &'static str,
+    /// `None` for sounds with no place, like the player's own.
+    pub direction: Option<Direction>,
+    /// The tic it goes away.
+    pub expires: i32,
+}
+
+impl Caption {
+    /// The line as drawn, arrow on the side the sound came from.
+    pub fn line(&self) -> String {
+        match self.direction {
+            None => self.text.to_string(),
+            Some(Direction::Right) => format!("{} >", self.text),
+            Some(direction) => format!("{} {}", direction.arrow(), self.text),
+        }
+    }
+}
+
+/// The captions up on screen.
+#[derive(Clone, Debug, Default)]
+pub struct Captions {
+    pub options: CaptionOptions,
+    /// Oldest first.
+    pub shown: Vec<Caption>,
+}
+
+impl Captions {
+    pub fn new(options: CaptionOptions) -> Self {
+        Self {
+            options,
+            shown: Vec::new(),
+        }
+    }
+
+    /// A sound started on the mixer at tic `tic`. `origin` is where it plays from, if
+    /// anywhere.
+    pub fn sound_started(
+        &mut self,
+        name: &str,
+        origin: Option<(Fixed, Fixed)>,
+        listener: &ViewPoint,
+        tic: i32,
+    ) {
+        let Some((text, priority)) = caption(name) else {
+            return;
+        };
+        if priority > self.options.priority {
+            return;
+        }
+        let caption = Caption {
+            text,
+            direction: origin.map(|origin| Direction::of(listener, origin)),
+            expires: tic + CAPTION_TICS,
+        };
+        self.shown.retain(|shown| shown.text != text);
+        if self.shown.len() == MAX_CAPTIONS {
+            self.shown.remove(0);
+        }
+        self.shown.push(caption);
+    }
+
+    /// Takes down the captions whose time is up.
+    pub fn ticker(&mut self, tic: i32) {
+        self.shown.retain(|caption| caption.expires > tic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::angle::ANG180;
+    use crate::util::fixed::FRACUNIT;
+
+    #[test]
+    fn directions_are_relative_to_the_view() {
+        let listener = ViewPoint::default();
+        let at = |x: i32, y: i32| (x * FRACUNIT, y * FRACUNIT);
+        assert_eq!(Direction::of(&listener, at(100, 10)), Direction::Ahead);
+        assert_eq!(Direction::of(&listener, at(0, 100)), Direction::Left);
+        assert_eq!(Direction::of(&listener, at(-100, 30)), Direction::Behind);
+        assert_eq!(Direction::of(&listener, at(10, -100)), Direction::Right);
+        let facing_west = ViewPoint {
+            angle: ANG180,
+            ..ViewPoint::default()
+        };
+        assert_eq!(Direction::of(&facing_west, at(100, 0)), Direction::Behind);
+    }
+
+    #[test]
+    fn captions_are_filtered_refreshed_and_expire() {
+        let listener = ViewPoint::default();
+        let behind = Some((-100 * FRACUNIT, 0));
+        let mut captions = Captions::new(CaptionOptions { priority: 2 });
+        captions.sound_started("DOROPN", behind, &listener, 0);
+        captions.sound_started("itemup", None, &listener, 0);
+        captions.sound_started("pistol", None, &listener, 0);
+        assert_eq!(captions.shown.len(), 1, "only the door is important enough");
+        assert_eq!(captions.shown[0].line(), "V Door opens");
+
+        captions.sound_started("bgsit1", Some((0, -FRACUNIT)), &listener, 50);
+        captions.sound_started("doropn", None, &listener, 60);
+        let lines: Vec<_> = captions.shown.iter().map(Caption::line).collect();
+        assert_eq!(lines, ["Imp screeches >", "Door opens"]);
+
+        captions.ticker(50 + CAPTION_TICS);
+        assert_eq!(captions.shown.len(), 1);
+        captions.ticker(60 + CAPTION_TICS);
+        assert!(captions.shown.is_empty());
+
+        let mut off = Captions::default();
+        off.sound_started("cybsit", None, &listener, 0);
+        assert!(off.shown.is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+//! Getting the music out of the game: the playing track as a standard MIDI file, and what an
+//! OPL synth does with it as a VGM log of register writes.
+//!
+//! The music code tells `MusicCapture` what it starts, so `musicdump` can write the track,
+//! converted from MUS if needed. An OPL backend hands over every register write and the
+//! samples generated between them while `oplcapture` runs. VGM is used rather than DOSBox's
+//! DRO because its commands address both OPL3 banks directly, without DRO's limited table of
+//! register codes. Players and converters for either format are easy to find.
+
+use super::mus::{self, MIDI_MAGIC, MUS_MAGIC};
+use crate::engine::console::Commands;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// VGM counts time in samples at this rate, whatever rate the synth runs at.
+pub const VGM_RATE: u32 = 44_100;
+const VGM_VERSION: u32 = 0x151;
+/// Header size for version 1.51, which is the first to know about the OPL chips.
+const VGM_HEADER: usize = 0x80;
+const YM3812_CLOCK: u32 = 3_579_545;
+const YMF262_CLOCK: u32 = 14_318_180;
+
+/// Register writes and waits as an OPL backend makes them. Registers 0x100 and up are the
+/// second bank of an OPL3.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OplLog {
+    commands: Vec<u8>,
+    samples: u32,
+    opl3: bool,
+}
+
+impl OplLog {
+    pub fn write(&mut self, register: u16, value: u8) {
+        let command = if register & 0x100 != 0 {
+            self.opl3 = true;
+            0x5f
+        } else if self.opl3 {
+            0x5e
+        } else {
+            0x5a
+        };
+        self.commands
+            .extend_from_slice(&[command, register as u8, value]);
+    }
+
+    /// `samples` at `VGM_RATE` passed since the last write.
+    pub fn wait(&mut self, mut samples: u32) {
+        self.samples += samples;
+        while samples > 0 {
+            let step = samples.min(u32::from(u16::MAX));
+            match step {
+                1..=16 => self.commands.push(0x70 + step as u8 - 1),
+                _ => {
+                    self.commands.push(0x61);
+                    self.commands
+                        .extend_from_slice(&(step as u16).to_le_bytes());
+                }
+            }
+            samples -= step;
+        }
+    }
+
+    /// Length in seconds.
+    pub fn seconds(&self) -> u32 {
+        self.samples / VGM_RATE
+    }
+
+    /// The log as a VGM file. Writes before the first bank two write went out as OPL2 ones,
+    /// which an OPL3 plays the same.
+    pub fn to_vgm(&self) -> Vec<u8> {
+        let mut vgm = vec![0; VGM_HEADER];
+        let mut put = |offset: usize, value: u32| {
+            vgm[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        let length = VGM_HEADER + self.commands.len() + 1;
+        put(0x04, length as u32 - 4);
+        put(0x08, VGM_VERSION);
+        put(0x18, self.samples);
+        // Relative to the field itself.
+        put(0x34, VGM_HEADER as u32 - 0x34);
+        if self.opl3 {
+            put(0x5c, YMF262_CLOCK);
+        } else {
+            put(0x50, YM3812_CLOCK);
+        }
+        vgm[..4].copy_from_slice(b"Vgm ");
+        vgm.extend_from_slice(&self.commands);
+        if self.opl3 {
+            // The OPL2 commands were meant for the YMF262 too.
+            let mut at = VGM_HEADER;
+            while at < vgm.len() {
+                match vgm[at] {
+                    0x5a => {
+                        vgm[at] = 0x5e;
+                        at += 3;
+                    }
+                    0x5e | 0x5f | 0x61 => at += 3,
+                    _ => at += 1,
+                }
+            }
+        }
+        vgm.push(0x66);
+        vgm
+    }
+}
+
+/// The music playing now, and the OPL capture if one is running.
+#[derive(Default)]
+pub struct MusicCapture {
+    playing: Option<(String, Vec<u8>)>,
+    /// Whether the music goes through an OPL synth at all.
+    pub opl_backend: bool,
+    opl: Option<OplLog>,
+}
+
+impl MusicCapture {
+    pub fn music_started(&mut self, lump: &str, data: &[u8]) {
+        self.playing = Some((lump.to_ascii_uppercase(), data.to_vec()));
+    }
+
+    pub fn music_stopped(&mut self) {
+        self.playing = None;
+    }
+
+    pub fn playing(&self) -> Option<&str> {
+        self.playing.as_ref().map(|(lump, _)| lump.as_str())
+    }
+
+    /// The playing track as a MIDI file.
+    pub fn midi(&self) -> Result<Vec<u8>, String> {
+        let (lump, data) = self.playing.as_ref().ok_or("no music is playing")?;
+        if data.starts_with(MIDI_MAGIC) {
+            Ok(data.clone())
+        } else if data.starts_with(MUS_MAGIC) {
+            mus::to_midi(data).map_err(|error| format!("{lump}: {error}"))
+        } else {
+            Err(format!("{lump} is neither MUS nor MIDI"))
+        }
+    }
+
+    /// The OPL backend calls this for every register write.
+    pub fn opl_write(&mut self, register: u16, value: u8) {
+        if let Some(log) = &mut self.opl {
+            log.write(register, value);
+        }
+    }
+
+    /// And this for the samples it generates, at `VGM_RATE`.
+    pub fn opl_wait(&mut self, samples: u32) {
+        if let Some(log) = &mut self.opl {
+            log.wait(samples);
+        }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.opl.is_some()
+    }
+
+    pub fn start_opl(&mut self) -> Result<(), String> {
+        if !self.opl_backend {
+            return Err("music isn't playing through the OPL synth".to_string());
+        }
+        self.opl.get_or_insert_with(OplLog::default);
+        Ok(())
+    }
+
+    pub fn stop_opl(&mut self) -> Option<OplLog> {
+        self.opl.take()
+    }
+}
+
+/// `D_E1M1.mid` for the track that is playing, in the current directory.
+fn default_path(capture: &MusicCapture, extension: &str) -> PathBuf {
+    let lump = capture.playing().unwrap_or("MUSIC");
+    PathBuf::from(format!("{lump}.{extension}"))
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(path, data)
+        .map_err(|error| format!("couldn't write {}: {error}", path.display()))
+}
+
+/// Registers `musicdump [file]` and `oplcapture [file]`, which starts a capture and, run
+/// again, writes it.
+pub fn register_commands(capture: Rc<RefCell<MusicCapture>>, commands: &mut Commands) {
+    let music = capture.clone();
+    commands.register(
+        "musicdump",
+        "musicdump [file]: write the playing music as a MIDI file",
+        move |context, args| {
+            let music = music.borrow();
+            let midi = music.midi()?;
+            let path = args
+                .first()
+                .map_or_else(|| default_path(&music, "mid"), PathBuf::from);
+            write(&path, &midi)?;
+            context.print(format!("wrote {} bytes to {}", midi.len(), path.display()));
+            Ok(())
+        },
+    );
+    commands.register(
+        "oplcapture",
+        "oplcapture [file]: start recording the OPL synth, or stop and write a VGM file",
+        move |context, args| {
+            let mut capture = capture.borrow_mut();
+            let Some(log) = capture.stop_opl() else {
+                capture.start_opl()?;
+                context.print("capturing OPL output, run oplcapture again to stop");
+                return Ok(());
+            };
+            let path = args
+                .first()
+                .map_or_else(|| default_path(&capture, "vgm"), PathBuf::from);
+            write(&path, &log.to_vgm())?;
+            context.print(format!(
+                "wrote {}:{:02} of OPL output to {}",
+                log.seconds() / 60,
+                log.seconds() % 60,
+                path.display()
+            ));
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn opl_logs_become_vgm() {
+        let mut log = OplLog::default();
+        log.write(0x20, 0x01);
+        log.wait(10);
+        log.wait(VGM_RATE * 2);
+        log.write(0xb0, 0x32);
+        let vgm = log.to_vgm();
+        assert_eq!(&vgm[..4], b"Vgm ");
+        assert_eq!(u32_at(&vgm, 0x04) as usize, vgm.len() - 4);
+        assert_eq!(u32_at(&vgm, 0x18), VGM_RATE * 2 + 10);
+        assert_eq!(u32_at(&vgm, 0x50), YM3812_CLOCK);
+        assert_eq!(u32_at(&vgm, 0x5c), 0);
+        let wait = (VGM_RATE * 2 - u32::from(u16::MAX)) as u16;
+        let mut expected = vec![0x5a, 0x20, 0x01, 0x79, 0x61, 0xff, 0xff, 0x61];
+        expected.extend_from_slice(&wait.to_le_bytes());
+        expected.extend_from_slice(&[0x5a, 0xb0, 0x32, 0x66]);
+        assert_eq!(&vgm[VGM_HEADER..], expected);
+        assert_eq!(log.seconds(), 2);
+
+        log.write(0x105, 0x01);
+        let vgm = log.to_vgm();
+        assert_eq!((u32_at(&vgm, 0x50), u32_at(&vgm, 0x5c)), (0, YMF262_CLOCK));
+        assert_eq!(&vgm[VGM_HEADER..VGM_HEADER + 3], [0x5e, 0x20, 0x01]);
+        assert_eq!(&vgm[vgm.len() - 4..], [0x5f, 0x05, 0x01, 0x66]);
+    }
+
+    #[test]
+    fn commands_dump_the_music_and_the_capture() {
+        let dir = std::env::temp_dir().join(format!("doom-capture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let capture = Rc::new(RefCell::new(MusicCapture::default()));
+        let mut commands = Commands::default();
+        register_commands(capture.clone(), &mut commands);
+        let mut config = Config::new();
+
+        assert_eq!(
+            commands.execute("musicdump", &mut config),
+            ["musicdump: no music is playing"]
+        );
+        let midi = b"MThd\0\0\0\x06\0\0\0\x01\0\x46";
+        capture.borrow_mut().music_started("d_runnin", midi);
+        let path = dir.join("runnin.mid");
+        let line = format!("musicdump \"{}\"", path.display());
+        assert_eq!(
+            commands.execute(&line, &mut config),
+            [format!("wrote 14 bytes to {}", path.display())]
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), midi);
+
+        assert_eq!(
+            commands.execute("oplcapture", &mut config),
+            ["oplcapture: music isn't playing through the OPL synth"]
+        );
+        capture.borrow_mut().opl_backend = true;
+        commands.execute("oplcapture", &mut config);
+        assert!(capture.borrow().is_capturing());
+        capture.borrow_mut().opl_write(0x20, 0x01);
+        capture.borrow_mut().opl_wait(VGM_RATE * 61);
+        let path = dir.join("runnin.vgm");
+        let line = format!("oplcapture \"{}\"", path.display());
+        assert_eq!(
+            commands.execute(&line, &mut config),
+            [format!("wrote 1:01 of OPL output to {}", path.display())]
+        );
+        assert!(!capture.borrow().is_capturing());
+        assert!(std::fs::read(&path).unwrap().starts_with(b"Vgm "));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Which mixer channel a new sound gets, and how loud each kind of sound plays.
+//!
+//! Vanilla's `S_getChannel` has eight channels. A sound from an origin that is already
+//! playing replaces it, a free channel is taken next, and failing that the sound evicts one
+//! of no higher priority or isn't played. In a big fight the monsters fill every channel
+//! and the player's own shots are lost, so on top of that some channels can be held for
+//! weapon sounds, and the number of copies of one sound playing at once can be capped: a
+//! tenth imp fireball replaces the oldest instead of taking another channel. Each category
+//! also has its own volume.
+
+use crate::engine::config::{Config, Configurable, Section};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundCategory {
+    /// The player's weapons.
+    Weapon,
+    Monster,
+    /// Doors, lifts and everything else the level plays.
+    Ambient,
+    /// Menus and messages.
+    Ui,
+}
+
+impl SoundCategory {
+    pub const ALL: [SoundCategory; 4] = [
+        SoundCategory::Weapon,
+        SoundCategory::Monster,
+        SoundCategory::Ambient,
+        SoundCategory::Ui,
+    ];
+
+    pub fn config_name(self) -> &'static str {
+        match self {
+            SoundCategory::Weapon => "snd_volume_weapon",
+            SoundCategory::Monster => "snd_volume_monster",
+            SoundCategory::Ambient => "snd_volume_ambient",
+            SoundCategory::Ui => "snd_volume_ui",
+        }
+    }
+}
+
+/// A sound asking for a channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoundRequest {
+    /// Which sound effect, for counting identical ones.
+    pub sound: usize,
+    pub category: SoundCategory,
+    /// Vanilla's `sfxinfo->priority`: lower numbers are more important.
+    pub priority: i32,
+    /// What is making the sound, if anything: the same origin never plays twice at once.
+    pub origin: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MixerPolicy {
+    /// `snd_channels`, vanilla's channel count.
+    pub channels: usize,
+    /// Channels only weapon sounds may take, out of `channels`.
+    pub weapon_channels: usize,
+    /// Copies of one sound allowed at once, 0 for no limit.
+    pub max_identical: usize,
+    /// Percent of the sound effects volume for each of `SoundCategory::ALL`.
+    pub volumes: [u32; 4],
+}
+
+impl Default for MixerPolicy {
+    fn default() -> Self {
+        Self {
+            channels: 8,
+            weapon_channels: 0,
+            max_identical: 0,
+            volumes: [100; 4],
+        }
+    }
+}
+
+impl MixerPolicy {
+    /// `volume` scaled for `category`.
+    pub fn volume(&self, category: SoundCategory, volume: i32) -> i32 {
+        volume * self.volumes[category as usize] as i32 / 100
+    }
+}
+
+impl Configurable for MixerPolicy {
+    fn register(config: &mut Config) {
+        config.register_int("snd_channels", 8, Section::Vanilla);
+        config.register_int("snd_weapon_channels", 0, Section::Extended);
+        config.register_int("snd_max_identical", 0, Section::Extended);
+        for category in SoundCategory::ALL {
+            config.register_int(category.config_name(), 100, Section::Extended);
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let channels = config.get_int("snd_channels").clamp(1, 64) as usize;
+        Self {
+            channels,
+            // At least one channel stays open to everything else.
+            weapon_channels: (config.get_int("snd_weapon_channels").max(0) as usize)
+                .min(channels - 1),
+            max_identical: config.get_int("snd_max_identical").max(0) as usize,
+            volumes: SoundCategory::ALL
+                .map(|category| config.get_int(category.config_name()).clamp(0, 200) as u32),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("snd_channels", self.channels as i32);
+        config.set_int("snd_weapon_channels", self.weapon_channels as i32);
+        config.set_int("snd_max_identical", self.max_identical as i32);
+        for (category, &volume) in SoundCategory::ALL.iter().zip(&self.volumes) {
+            config.set_int(category.config_name(), volume as i32);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Playing {
+    request: SoundRequest,
+    started: u64,
+}
+
+/// The mixer's channels, as the policy hands them out. The first `weapon_channels` are the
+/// reserved ones.
+pub struct Channels {
+    policy: MixerPolicy,
+    slots: Vec<Option<Playing>>,
+    started: u64,
+}
+
+impl Channels {
+    pub fn new(policy: MixerPolicy) -> Self {
+        Self {
+            policy,
+            slots: vec![None; policy.channels],
+            started: 0,
+        }
+    }
+
+    pub fn policy(&self) -> &MixerPolicy {
+        &self.policy
+    }
+
+    /// The channel to play `request` on, which the caller stops first if it was playing
+    /// something. `None` means the sound isn't played.
+    pub fn allocate(&mut self, request: SoundRequest) -> Option<usize> {
+        let channel = self.choose(&request)?;
+        self.started += 1;
+        self.slots[channel] = Some(Playing {
+            request,
+            started: self.started,
+        });
+        Some(channel)
+    }
+
+    /// The mixer says the sound on `channel` ended, or it was stopped.
+    pub fn release(&mut self, channel: usize) {
+        if let Some(slot) = self.slots.get_mut(channel) {
+            *slot = None;
+        }
+    }
+
+    /// `S_StopSound`: stops whatever `origin` is playing. Returns the channel it was on, for
+    /// the caller to stop on the mixer.
+    pub fn stop_origin(&mut self, origin: usize) -> Option<usize> {
+        let channel = self
+            .slots
+            .iter()
+            .position(|slot| slot.is_some_and(|playing| playing.request.origin == Some(origin)))?;
+        self.slots[channel] = None;
+        Some(channel)
+    }
+
+    pub fn playing(&self, channel: usize) -> Option<&SoundRequest> {
+        self.slots
+            .get(channel)?
+            .as_ref()
+            .map(|playing| &playing.request)
+    }
+
+    fn choose(&self, request: &SoundRequest) -> Option<usize> {
+        let playing = |channel: &usize| self.slots[*channel].as_ref();
+        let allowed = || {
+            let first = match request.category {
+                SoundCategory::Weapon => 0,
+                _ => self.policy.weapon_channels,
+            };
+            first..self.slots.len()
+        };
+        if request.origin.is_some() {
+            let same_origin =
+                allowed().find(|c| playing(c).is_some_and(|p| p.request.origin == request.origin));
+            if same_origin.is_some() {
+                return same_origin;
+            }
+        }
+        if self.policy.max_identical > 0 {
+            let identical: Vec<usize> = allowed()
+                .filter(|c| playing(c).is_some_and(|p| p.request.sound == request.sound))
+                .collect();
+            if identical.len() >= self.policy.max_identical {
+                return identical
+                    .into_iter()
+                    .min_by_key(|c| playing(c).unwrap().started);
+            }
+        }
+        if let Some(free) = allowed().find(|c| playing(c).is_none()) {
+            return Some(free);
+        }
+        // Everything is busy: as vanilla, the first channel playing something no more
+        // important than the new sound.
+        allowed().find(|c| playing(c).is_some_and(|p| p.request.priority >= request.priority))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(
+        sound: usize,
+        category: SoundCategory,
+        priority: i32,
+        origin: usize,
+    ) -> SoundRequest {
+        SoundRequest {
+            sound,
+            category,
+            priority,
+            origin: Some(origin),
+        }
+    }
+
+    #[test]
+    fn vanilla_allocation() {
+        let mut channels = Channels::new(MixerPolicy {
+            channels: 2,
+            ..MixerPolicy::default()
+        });
+        let monster = |origin| request(1, SoundCategory::Monster, 70, origin);
+        assert_eq!(channels.allocate(monster(10)), Some(0));
+        assert_eq!(channels.allocate(monster(11)), Some(1));
+        assert_eq!(
+            channels.allocate(monster(10)),
+            Some(0),
+            "same origin replaces"
+        );
+        let important = request(2, SoundCategory::Monster, 60, 12);
+        assert_eq!(
+            channels.allocate(important),
+            Some(0),
+            "the first no more important"
+        );
+        assert_eq!(
+            channels.allocate(request(3, SoundCategory::Ambient, 100, 13)),
+            None
+        );
+        channels.release(0);
+        assert_eq!(
+            channels.allocate(request(3, SoundCategory::Ambient, 100, 13)),
+            Some(0)
+        );
+        assert_eq!(channels.playing(0).unwrap().sound, 3);
+        assert_eq!(channels.stop_origin(13), Some(0));
+        assert_eq!(channels.stop_origin(13), None);
+        assert_eq!(channels.playing(0), None);
+    }
+
+    #[test]
+    fn weapon_channels_and_identical_caps() {
+        let mut channels = Channels::new(MixerPolicy {
+            channels: 4,
+            weapon_channels: 1,
+            max_identical: 2,
+            ..MixerPolicy::default()
+        });
+        let fireball = |origin| request(7, SoundCategory::Monster, 70, origin);
+        assert_eq!(channels.allocate(fireball(1)), Some(1));
+        assert_eq!(channels.allocate(fireball(2)), Some(2));
+        assert_eq!(
+            channels.allocate(fireball(3)),
+            Some(1),
+            "the oldest copy goes"
+        );
+        assert_eq!(
+            channels.allocate(request(8, SoundCategory::Monster, 70, 4)),
+            Some(3)
+        );
+        assert_eq!(
+            channels.allocate(request(9, SoundCategory::Monster, 10, 5)),
+            Some(1)
+        );
+        let pistol = request(1, SoundCategory::Weapon, 64, 0);
+        assert_eq!(
+            channels.allocate(pistol),
+            Some(0),
+            "the reserved channel is free"
+        );
+    }
+
+    #[test]
+    fn config_and_volumes() {
+        let mut config = Config::new();
+        MixerPolicy::register(&mut config);
+        config.set_int("snd_channels", 4);
+        config.set_int("snd_weapon_channels", 9);
+        config.set_int("snd_volume_monster", 50);
+        let policy = MixerPolicy::from_config(&config);
+        assert_eq!((policy.channels, policy.weapon_channels), (4, 3));
+        assert_eq!(policy.volume(SoundCategory::Monster, 127), 63);
+        assert_eq!(policy.volume(SoundCategory::Weapon, 127), 127);
+        policy.to_config(&mut config);
+        assert_eq!(config.get_int("snd_weapon_channels"), 3);
+    }
+}
@@ -0,0 +1,257 @@
+//! The `-jukebox` music browser: every music lump in the loaded WADs, played one at a time
+//! through the music backend, for testing the MUS and MIDI path without starting a game.
+
+use super::mus::{self, MIDI_MAGIC, MUS_MAGIC, MUS_RATE};
+use super::music::{self, MUSIC};
+use crate::game::GameMode;
+use crate::wad::file::Wads;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicFormat {
+    Mus,
+    Midi,
+}
+
+/// A music lump, as the last WAD that has it provides it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Track {
+    pub lump: String,
+    pub format: MusicFormat,
+    /// Play time in seconds, for MUS lumps that convert cleanly.
+    pub seconds: Option<u32>,
+    pub data: Vec<u8>,
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self.format {
+            MusicFormat::Mus => "MUS",
+            MusicFormat::Midi => "MIDI",
+        };
+        write!(f, "{:<8} {format:<4}", self.lump)?;
+        if let Some(seconds) = self.seconds {
+            write!(f, " {}:{:02}", seconds / 60, seconds % 60)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a line typed at the jukebox asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// The current track changed and should be started.
+    Play,
+    List,
+    Quit,
+    Error(String),
+}
+
+pub const HELP: &str = "n next, p previous, a number or lump name to pick, idmusXY, l list, q quit";
+
+pub struct Jukebox {
+    tracks: Vec<Track>,
+    current: usize,
+    mode: GameMode,
+}
+
+impl Jukebox {
+    /// Collects the `D_` lumps that hold MUS or MIDI data: the ones the game knows in its own
+    /// order, then any others the PWADs add, in load order.
+    pub fn scan(wads: &Wads) -> Self {
+        let mut names: Vec<String> = MUSIC.iter().map(|name| music::lump_name(name)).collect();
+        for file in wads.files() {
+            for lump in &file.lumps {
+                if lump.name.starts_with("D_") && !names.contains(&lump.name) {
+                    names.push(lump.name.clone());
+                }
+            }
+        }
+        let tracks = names
+            .into_iter()
+            .filter_map(|name| {
+                let data = &wads.lump(&name)?.data;
+                let (format, seconds) = if data.starts_with(MUS_MAGIC) {
+                    let ticks = mus::length(data).ok();
+                    (MusicFormat::Mus, ticks.map(|ticks| ticks / MUS_RATE))
+                } else if data.starts_with(MIDI_MAGIC) {
+                    (MusicFormat::Midi, None)
+                } else {
+                    return None;
+                };
+                Some(Track {
+                    lump: name,
+                    format,
+                    seconds,
+                    data: data.clone(),
+                })
+            })
+            .collect();
+        Jukebox {
+            tracks,
+            current: 0,
+            mode: GameMode::identify(|name| wads.lump(name).is_some()),
+        }
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn current(&self) -> Option<&Track> {
+        self.tracks.get(self.current)
+    }
+
+    /// The track list, numbered from 1 with the current one marked.
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        for (index, track) in self.tracks.iter().enumerate() {
+            let mark = if index == self.current { '>' } else { ' ' };
+            out.push_str(&format!("{mark}{:3}  {track}\n", index + 1));
+        }
+        out
+    }
+
+    pub fn next(&mut self) {
+        if !self.tracks.is_empty() {
+            self.current = (self.current + 1) % self.tracks.len();
+        }
+    }
+
+    pub fn prev(&mut self) {
+        if !self.tracks.is_empty() {
+            self.current = (self.current + self.tracks.len() - 1) % self.tracks.len();
+        }
+    }
+
+    pub fn select(&mut self, lump: &str) -> bool {
+        match self
+            .tracks
+            .iter()
+            .position(|t| t.lump.eq_ignore_ascii_case(lump))
+        {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn command(&mut self, line: &str) -> Reply {
+        let line = line.trim().to_ascii_lowercase();
+        match line.as_str() {
+            "" | "n" | "next" => self.next(),
+            "p" | "prev" => self.prev(),
+            "l" | "list" => return Reply::List,
+            "q" | "quit" => return Reply::Quit,
+            _ => {
+                if let Ok(number) = line.parse::<usize>() {
+                    if !(1..=self.tracks.len()).contains(&number) {
+                        return Reply::Error(format!("no track {number}"));
+                    }
+                    self.current = number - 1;
+                } else if let Some(digits) = line.strip_prefix("idmus") {
+                    let digits = digits.as_bytes();
+                    let lump = match digits {
+                        [first, second] if digits.iter().all(u8::is_ascii_digit) => {
+                            music::idmus(self.mode, first - b'0', second - b'0')
+                        }
+                        _ => None,
+                    };
+                    let Some(lump) = lump else {
+                        return Reply::Error("impossible selection".to_string());
+                    };
+                    if !self.select(&lump) {
+                        return Reply::Error(format!("{lump} isn't in the loaded WADs"));
+                    }
+                } else if !self.select(&line) {
+                    return Reply::Error(format!("unknown command '{line}' ({HELP})"));
+                }
+            }
+        }
+        Reply::Play
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::file::WadFile;
+    use std::path::Path;
+
+    /// A note held for `ticks`, under 16384.
+    fn mus(ticks: u16) -> Vec<u8> {
+        let score = [
+            0x90,
+            0x3c | 0x80,
+            0x7f,
+            0x80 | (ticks >> 7) as u8,
+            ticks as u8 & 0x7f,
+            0x60,
+        ];
+        let mut lump = MUS_MAGIC.to_vec();
+        lump.extend((score.len() as u16).to_le_bytes());
+        lump.extend(16u16.to_le_bytes());
+        lump.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+        lump.extend(score);
+        lump
+    }
+
+    fn wads(files: &[&[(&str, &[u8])]]) -> Wads {
+        let mut wads = Wads::new();
+        for lumps in files {
+            let bytes = build_wad(b"PWAD", lumps);
+            wads.add(WadFile::parse(Path::new("test.wad"), &bytes).unwrap());
+        }
+        wads
+    }
+
+    #[test]
+    fn tracks_follow_the_music_table_then_load_order() {
+        let wads = wads(&[
+            &[
+                ("E1M1", &[]),
+                ("D_INTER", &mus(70)),
+                ("D_E1M2", &mus(100)),
+                ("D_JUNK", b"not music"),
+            ],
+            &[("D_CUSTOM", b"MThd\0\0\0\x06"), ("D_E1M2", &mus(300))],
+        ]);
+        let jukebox = Jukebox::scan(&wads);
+        let names: Vec<&str> = jukebox.tracks().iter().map(|t| t.lump.as_str()).collect();
+        assert_eq!(names, ["D_E1M2", "D_INTER", "D_CUSTOM"]);
+        assert_eq!(jukebox.tracks()[0].seconds, Some(2), "the PWAD copy wins");
+        assert_eq!(jukebox.tracks()[2].format, MusicFormat::Midi);
+        assert_eq!(jukebox.tracks()[0].to_string(), "D_E1M2   MUS  0:02");
+    }
+
+    #[test]
+    fn commands_move_through_the_list() {
+        let wads = wads(&[&[
+            ("E1M1", &[]),
+            ("D_E1M1", &mus(1)),
+            ("D_E1M9", &mus(1)),
+            ("D_INTER", &mus(1)),
+        ]]);
+        let mut jukebox = Jukebox::scan(&wads);
+        let current = |jukebox: &Jukebox| jukebox.current().unwrap().lump.clone();
+        assert_eq!(jukebox.command("p"), Reply::Play);
+        assert_eq!(current(&jukebox), "D_INTER");
+        assert_eq!(jukebox.command("n"), Reply::Play);
+        assert_eq!(current(&jukebox), "D_E1M1");
+        assert_eq!(jukebox.command("2"), Reply::Play);
+        assert_eq!(current(&jukebox), "D_E1M9");
+        assert_eq!(jukebox.command(" d_inter "), Reply::Play);
+        assert_eq!(jukebox.command("IDMUS11"), Reply::Play);
+        assert_eq!(current(&jukebox), "D_E1M1");
+        assert!(jukebox.listing().starts_with(">  1  D_E1M1"));
+        assert!(matches!(jukebox.command("idmus12"), Reply::Error(_)));
+        assert!(matches!(jukebox.command("idmus51"), Reply::Error(_)));
+        assert!(matches!(jukebox.command("4"), Reply::Error(_)));
+        assert!(matches!(jukebox.command("shuffle"), Reply::Error(_)));
+        assert_eq!(jukebox.command("l"), Reply::List);
+        assert_eq!(jukebox.command("q"), Reply::Quit);
+    }
+}
@@ -0,0 +1,18 @@
+//! Sound effect and music lumps, decoded into what a mixer or a MIDI synth takes.
+//!
+//! Playing them is the platform's job; this only turns the DMX formats stored in the WAD
+//! into plain samples and standard MIDI, which also makes them easy to export.
+
+#[cfg(feature = "std")]
+pub mod captions;
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub mod channels;
+#[cfg(feature = "std")]
+pub mod jukebox;
+pub mod mus;
+pub mod music;
+pub mod sound;
+#[cfg(feature = "std")]
+pub mod spatial;
@@ -0,0 +1,275 @@
+//! MUS, the DMX music format of the `D_*` lumps, converted to a standard MIDI file.
+//!
+//! MUS is MIDI with the running status and the channel numbers squeezed out: 16 channels
+//! with percussion on 15, ticks at 140 Hz, and a byte per event that packs the event type,
+//! the channel and whether a delay follows. The conversion mirrors the `mus2mid` every port
+//! uses. MUS channels get MIDI channels in order of first use, skipping 9, which is
+//! percussion; and each channel starts with an all notes off, since some songs (D_DDTBLU)
+//! play notes on channels they never reset.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+pub const MUS_MAGIC: &[u8; 4] = b"MUS\x1a";
+pub const MIDI_MAGIC: &[u8; 4] = b"MThd";
+
+/// MUS ticks per second. The MIDI file uses 70 ticks per quarter note at 120 bpm to match.
+pub const MUS_RATE: u32 = 140;
+const DIVISION: u16 = 70;
+const TEMPO: u32 = 500_000; // Microseconds per quarter note.
+
+const MUS_PERCUSSION: usize = 15;
+const MIDI_PERCUSSION: u8 = 9;
+
+/// MIDI controller for each MUS controller number. 0 is the program change; 10 to 14 are
+/// the valueless system events.
+const CONTROLLERS: [u8; 15] = [
+    0x00, 0x00, 0x01, 0x07, 0x0a, 0x0b, 0x5b, 0x5d, 0x40, 0x43, 0x78, 0x7b, 0x7e, 0x7f, 0x79,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MusError {
+    NotMus,
+    Truncated,
+    BadEvent(u8),
+}
+
+impl fmt::Display for MusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MusError::NotMus => write!(f, "not a MUS lump"),
+            MusError::Truncated => write!(f, "MUS header is truncated"),
+            MusError::BadEvent(byte) => write!(f, "bad MUS event {byte:#04x}"),
+        }
+    }
+}
+
+/// Converts a MUS lump to a format 0 MIDI file. A score that runs off the end of the lump
+/// without its end event is ended there, the way the DMX player treated it.
+pub fn to_midi(mus: &[u8]) -> Result<Vec<u8>, MusError> {
+    convert(mus).map(|(midi, _)| midi)
+}
+
+/// How long a MUS lump plays before it ends or loops, in MUS ticks.
+pub fn length(mus: &[u8]) -> Result<u32, MusError> {
+    convert(mus).map(|(_, ticks)| ticks)
+}
+
+fn convert(mus: &[u8]) -> Result<(Vec<u8>, u32), MusError> {
+    if !mus.starts_with(MUS_MAGIC) {
+        return Err(MusError::NotMus);
+    }
+    let start = mus.get(6..8).ok_or(MusError::Truncated)?;
+    let start = usize::from(u16::from_le_bytes([start[0], start[1]]));
+    let mut score = mus.get(start..).ok_or(MusError::Truncated)?.iter().copied();
+    let mut next = || score.next();
+
+    let mut track = Track::default();
+    track.event(
+        0xff,
+        &[
+            0x51,
+            0x03,
+            (TEMPO >> 16) as u8,
+            (TEMPO >> 8) as u8,
+            TEMPO as u8,
+        ],
+    );
+    while let Some(descriptor) = next() {
+        let mus_channel = usize::from(descriptor & 0x0f);
+        let bad = MusError::BadEvent(descriptor);
+        match (descriptor >> 4) & 7 {
+            0 => {
+                let key = next().unwrap_or(0) & 0x7f;
+                let channel = track.channel(mus_channel);
+                track.event(0x80 | channel, &[key, 0]);
+            }
+            1 => {
+                let key = next().unwrap_or(0);
+                if key & 0x80 != 0 {
+                    track.velocities[mus_channel] = next().unwrap_or(0) & 0x7f;
+                }
+                let velocity = track.velocities[mus_channel];
+                let channel = track.channel(mus_channel);
+                track.event(0x90 | channel, &[key & 0x7f, velocity]);
+            }
+            2 => {
+                let bend = u16::from(next().unwrap_or(0x80)) * 64;
+                let channel = track.channel(mus_channel);
+                track.event(0xe0 | channel, &[(bend & 0x7f) as u8, (bend >> 7) as u8]);
+            }
+            3 => {
+                let controller = usize::from(next().unwrap_or(0));
+                let midi = *CONTROLLERS
+                    .get(controller)
+                    .filter(|_| controller >= 10)
+                    .ok_or(bad)?;
+                let channel = track.channel(mus_channel);
+                track.event(0xb0 | channel, &[midi, 0]);
+            }
+            4 => {
+                let controller = usize::from(next().unwrap_or(0));
+                let value = next().unwrap_or(0).min(0x7f);
+                let channel = track.channel(mus_channel);
+                match controller {
+                    0 => track.event(0xc0 | channel, &[value]),
+                    1..=9 => track.event(0xb0 | channel, &[CONTROLLERS[controller], value]),
+                    _ => return Err(bad),
+                }
+            }
+            5 => {} // End of measure.
+            6 => break,
+            _ => return Err(bad),
+        }
+        if descriptor & 0x80 != 0 {
+            let mut delay = 0u32;
+            while let Some(byte) = next() {
+                delay = delay.wrapping_mul(128) + u32::from(byte & 0x7f);
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            track.delay += delay;
+            track.elapsed += delay;
+        }
+    }
+    track.event(0xff, &[0x2f, 0x00]);
+
+    let mut midi = Vec::with_capacity(22 + track.data.len());
+    midi.extend(MIDI_MAGIC);
+    midi.extend(6u32.to_be_bytes());
+    midi.extend(0u16.to_be_bytes()); // Format 0: one track.
+    midi.extend(1u16.to_be_bytes());
+    midi.extend(DIVISION.to_be_bytes());
+    midi.extend(b"MTrk");
+    midi.extend((track.data.len() as u32).to_be_bytes());
+    midi.extend(track.data);
+    Ok((midi, track.elapsed))
+}
+
+struct Track {
+    data: Vec<u8>,
+    delay: u32, // Ticks since the last event written.
+    elapsed: u32,
+    channels: [Option<u8>; 16],
+    velocities: [u8; 16],
+}
+
+impl Default for Track {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            delay: 0,
+            elapsed: 0,
+            channels: [None; 16],
+            velocities: [127; 16],
+        }
+    }
+}
+
+impl Track {
+    fn event(&mut self, status: u8, data: &[u8]) {
+        let delay = core::mem::take(&mut self.delay);
+        let mut groups = [0u8; 5];
+        let mut len = 0;
+        let mut value = delay;
+        loop {
+            groups[len] = (value & 0x7f) as u8;
+            len += 1;
+            value >>= 7;
+            if value == 0 {
+                break;
+            }
+        }
+        for i in (0..len).rev() {
+            self.data.push(groups[i] | if i > 0 { 0x80 } else { 0 });
+        }
+        self.data.push(status);
+        self.data.extend(data);
+    }
+
+    /// The MIDI channel for a MUS channel, given out on first use.
+    fn channel(&mut self, mus_channel: usize) -> u8 {
+        if mus_channel == MUS_PERCUSSION {
+            return MIDI_PERCUSSION;
+        }
+        if let Some(channel) = self.channels[mus_channel] {
+            return channel;
+        }
+        let used = self.channels.iter().flatten().count() as u8;
+        let channel = if used >= MIDI_PERCUSSION {
+            used + 1
+        } else {
+            used
+        };
+        self.channels[mus_channel] = Some(channel);
+        self.event(0xb0 | channel, &[0x7b, 0]);
+        channel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn mus(score: &[u8]) -> Vec<u8> {
+        let mut lump = MUS_MAGIC.to_vec();
+        lump.extend((score.len() as u16).to_le_bytes());
+        lump.extend(16u16.to_le_bytes());
+        lump.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+        lump.extend(score);
+        lump
+    }
+
+    #[test]
+    fn converts_to_the_reference_midi() {
+        #[rustfmt::skip]
+        let score = [
+            0x43, 0x00, 0x05,       // ch 3: program 5
+            0x93, 0xbc, 0x64, 0x81, 0x0c, // ch 3: note 60 at 100, then 140 ticks
+            0x03, 0x3c,             // ch 3: release 60
+            0x9f, 0x24, 0x23,       // percussion: note 36, then 35 ticks
+            0x20, 0x80,             // ch 0: pitch wheel centered
+            0x60,                   // score end
+            0x10, 0x40,             // never read
+        ];
+        #[rustfmt::skip]
+        let track = vec![
+            0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // tempo
+            0x00, 0xb0, 0x7b, 0x00, // first use of MUS channel 3 gets MIDI channel 0
+            0x00, 0xc0, 0x05,
+            0x00, 0x90, 0x3c, 0x64,
+            0x81, 0x0c, 0x80, 0x3c, 0x00,
+            0x00, 0x99, 0x24, 0x7f, // percussion keeps the default velocity
+            0x23, 0xb1, 0x7b, 0x00, // MUS channel 0 is the second one used
+            0x00, 0xe1, 0x00, 0x40,
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let mut expected = b"MThd\0\0\0\x06\0\0\0\x01\0\x46MTrk".to_vec();
+        expected.extend((track.len() as u32).to_be_bytes());
+        expected.extend(track);
+        assert_eq!(to_midi(&mus(&score)), Ok(expected));
+        assert_eq!(length(&mus(&score)), Ok(140 + 35));
+    }
+
+    #[test]
+    fn channels_skip_percussion_and_errors_are_reported() {
+        let mut score = Vec::new();
+        for channel in 0..11 {
+            score.extend([0x10 | channel, 0x3c]);
+        }
+        let midi = to_midi(&mus(&score)).unwrap();
+        // The eleventh melodic channel lands on MIDI channel 10.
+        assert!(midi.windows(3).any(|w| w == [0xba, 0x7b, 0x00]));
+        assert!(!midi.windows(2).any(|w| w == [0xb9, 0x7b]));
+
+        assert_eq!(to_midi(b"MThd"), Err(MusError::NotMus));
+        assert_eq!(to_midi(&MUS_MAGIC[..]), Err(MusError::Truncated));
+        assert_eq!(to_midi(&mus(&[0x70])), Err(MusError::BadEvent(0x70)));
+        assert_eq!(
+            to_midi(&mus(&[0x40, 0x0a, 0])),
+            Err(MusError::BadEvent(0x40))
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! The music the games know by number, and the `IDMUS` rules for picking it.
+
+use crate::game::GameMode;
+use alloc::format;
+use alloc::string::String;
+
+/// Vanilla's music table, in `musicenum_t` order. The lumps are these names after `D_`.
+pub const MUSIC: [&str; 67] = [
+    "E1M1", "E1M2", "E1M3", "E1M4", "E1M5", "E1M6", "E1M7", "E1M8", "E1M9", //
+    "E2M1", "E2M2", "E2M3", "E2M4", "E2M5", "E2M6", "E2M7", "E2M8", "E2M9", //
+    "E3M1", "E3M2", "E3M3", "E3M4", "E3M5", "E3M6", "E3M7", "E3M8", "E3M9", //
+    "INTER", "INTRO", "BUNNY", "VICTOR", "INTROA", //
+    "RUNNIN", "STALKS", "COUNTD", "BETWEE", "DOOM", "THE_DA", "SHAWN", "DDTBLU", "IN_CIT", "DEAD",
+    "STLKS2", "THEDA2", "DOOM2", "DDTBL2", "RUNNI2", "DEAD2", "STLKS3", "ROMERO", "SHAWN2",
+    "MESSAG", "COUNT2", "DDTBL3", "AMPIE", "THEDA3", "ADRIAN", "MESSG2", "ROMER2", "TENSE",
+    "SHAWN3", "OPENIN", "EVIL", "ULTIMA", "READ_M", "DM2TTL", "DM2INT",
+];
+
+const RUNNIN: usize = 32;
+
+/// The lump for an entry of `MUSIC`.
+pub fn lump_name(music: &str) -> String {
+    format!("D_{music}")
+}
+
+/// The music `IDMUS` plays for its two digits, or `None` for `STSTR_NOMUS`. Doom II reads
+/// them as a map number from 01 to 35. The other games count `ExMy` from E1M1 through the
+/// table, so E4M1 to E4M5 play the intermission and finale tracks, as in vanilla, and
+/// anything past them is refused.
+pub fn idmus(mode: GameMode, first: u8, second: u8) -> Option<String> {
+    let index = if mode == GameMode::Commercial {
+        let map = usize::from(first) * 10 + usize::from(second);
+        (1..=35).contains(&map).then(|| RUNNIN + map - 1)?
+    } else {
+        let index = usize::from(first).checked_sub(1)? * 9 + usize::from(second).checked_sub(1)?;
+        (index < RUNNIN).then_some(index)?
+    };
+    Some(lump_name(MUSIC[index]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idmus_follows_the_music_table() {
+        assert_eq!(
+            idmus(GameMode::Commercial, 0, 1).as_deref(),
+            Some("D_RUNNIN")
+        );
+        assert_eq!(
+            idmus(GameMode::Commercial, 3, 5).as_deref(),
+            Some("D_DM2INT")
+        );
+        assert_eq!(idmus(GameMode::Commercial, 3, 6), None);
+        assert_eq!(idmus(GameMode::Commercial, 0, 0), None);
+        assert_eq!(idmus(GameMode::Registered, 2, 3).as_deref(), Some("D_E2M3"));
+        assert_eq!(idmus(GameMode::Retail, 4, 1).as_deref(), Some("D_INTER"));
+        assert_eq!(idmus(GameMode::Retail, 4, 5).as_deref(), Some("D_INTROA"));
+        assert_eq!(idmus(GameMode::Retail, 4, 6), None);
+        assert_eq!(idmus(GameMode::Retail, 1, 0), None);
+    }
+}
@@ -0,0 +1,99 @@
+//! DMX digital sound lumps (`DS*`) and WAV output.
+//!
+//! A sound lump is a format number (3), the sample rate, the sample count and then 8-bit
+//! unsigned mono samples. The DMX library padded the samples with 16 bytes on each side that
+//! are not part of the sound; vanilla skips them, so this does too.
+
+use alloc::vec::Vec;
+
+/// The only digital sound format the games shipped.
+pub const DMX_FORMAT: u16 = 3;
+
+const DMX_PADDING: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sound {
+    pub rate: u32,
+    pub samples: Vec<u8>, // 8-bit unsigned, mono.
+}
+
+impl Sound {
+    /// Decodes a `DS*` lump. Returns `None` for other formats, like the PC speaker `DP*`
+    /// lumps, and for sounds whose sample count runs past the end of the lump.
+    pub fn parse(lump: &[u8]) -> Option<Self> {
+        let read_u16 = |at: usize| Some(u16::from_le_bytes(lump.get(at..at + 2)?.try_into().ok()?));
+        if read_u16(0)? != DMX_FORMAT {
+            return None;
+        }
+        let rate = u32::from(read_u16(2)?);
+        let length = u32::from_le_bytes(lump.get(4..8)?.try_into().ok()?) as usize;
+        let data = lump.get(8..8 + length)?;
+        let samples = match length >= 2 * DMX_PADDING {
+            true => &data[DMX_PADDING..length - DMX_PADDING],
+            false => data,
+        };
+        Some(Self {
+            rate,
+            samples: samples.to_vec(),
+        })
+    }
+
+    /// The sound as a WAV file: a RIFF header and the samples as 8-bit PCM.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let len = self.samples.len() as u32;
+        let mut wav = Vec::with_capacity(44 + self.samples.len());
+        wav.extend(b"RIFF");
+        wav.extend((36 + len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes()); // PCM.
+        wav.extend(1u16.to_le_bytes()); // Mono.
+        wav.extend(self.rate.to_le_bytes());
+        wav.extend(self.rate.to_le_bytes()); // Bytes per second.
+        wav.extend(1u16.to_le_bytes()); // Bytes per frame.
+        wav.extend(8u16.to_le_bytes()); // Bits per sample.
+        wav.extend(b"data");
+        wav.extend(len.to_le_bytes());
+        wav.extend(&self.samples);
+        wav
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn lump(samples: &[u8]) -> Vec<u8> {
+        let mut lump = vec![3, 0, 0x11, 0x2b]; // 11025 Hz.
+        lump.extend((samples.len() as u32 + 32).to_le_bytes());
+        lump.extend([0x80; 16]);
+        lump.extend(samples);
+        lump.extend([0x80; 16]);
+        lump
+    }
+
+    #[test]
+    fn padding_is_dropped() {
+        let sound = Sound::parse(&lump(&[1, 2, 3])).unwrap();
+        assert_eq!(sound.rate, 11025);
+        assert_eq!(sound.samples, [1, 2, 3]);
+        assert!(Sound::parse(&[0, 0, 0x8c, 0, 1, 0, 0, 0, 0]).is_none()); // PC speaker.
+        let mut truncated = lump(&[1, 2, 3]);
+        truncated.pop();
+        assert!(Sound::parse(&truncated).is_none());
+    }
+
+    #[test]
+    fn wav_matches_the_reference_header() {
+        let wav = Sound::parse(&lump(&[0x7f, 0x81])).unwrap().to_wav();
+        #[rustfmt::skip]
+        let expected = [
+            b'R', b'I', b'F', b'F', 38, 0, 0, 0, b'W', b'A', b'V', b'E',
+            b'f', b'm', b't', b' ', 16, 0, 0, 0, 1, 0, 1, 0,
+            0x11, 0x2b, 0, 0, 0x11, 0x2b, 0, 0, 1, 0, 8, 0,
+            b'd', b'a', b't', b'a', 2, 0, 0, 0, 0x7f, 0x81,
+        ];
+        assert_eq!(wav, expected);
+    }
+}
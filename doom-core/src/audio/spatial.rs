@@ -0,0 +1,79 @@
+//! How loud a sound is and where it sits in stereo, from where it plays relative to the
+//! listener: vanilla's `S_AdjustSoundParams`.
+//!
+//! Volume falls off linearly from full at 200 units to nothing at 1200, using the same
+//! octagonal distance as the game. On MAP08 of Doom II (E1M8 counts too, vanilla only looks
+//! at the map number) sounds never fade below 15, so the boss fight stays audible.
+//! Separation swings up to 96 either side of center with the sine of the angle.
+
+use crate::renderer::ViewPoint;
+use crate::util::angle::sin_cos;
+use crate::util::fixed::{approx_distance, fixed_mul, Fixed, FRACBITS, FRACUNIT};
+
+pub const CLIPPING_DIST: Fixed = 1200 * FRACUNIT;
+pub const CLOSE_DIST: Fixed = 200 * FRACUNIT;
+const ATTENUATOR: i32 = (CLIPPING_DIST - CLOSE_DIST) >> FRACBITS;
+const STEREO_SWING: Fixed = 96 * FRACUNIT;
+const BOSS_MAP: u8 = 8;
+/// The separation of a sound straight ahead or behind.
+pub const NORM_SEP: i32 = 128;
+
+/// The volume (0 to `volume`) and separation (32 to 224) of a sound at `origin`, or `None`
+/// when it is too far away to hear. `volume` is the sound effects volume it starts from.
+pub fn adjust(
+    listener: &ViewPoint,
+    origin: (Fixed, Fixed),
+    volume: i32,
+    map: u8,
+) -> Option<(i32, i32)> {
+    let (dx, dy) = (
+        origin.0.wrapping_sub(listener.x),
+        origin.1.wrapping_sub(listener.y),
+    );
+    let mut distance = approx_distance(dx, dy);
+    if map != BOSS_MAP && distance > CLIPPING_DIST {
+        return None;
+    }
+    // The angle to the source relative to where the listener faces. Nothing here feeds the
+    // simulation, so floating point stands in for the angle tables.
+    let bearing = (f64::from(dy).atan2(f64::from(dx)) / std::f64::consts::TAU * 4_294_967_296.0)
+        .rem_euclid(4_294_967_296.0) as u32;
+    let (sine, _) = sin_cos(bearing.wrapping_sub(listener.angle));
+    let separation = NORM_SEP - (fixed_mul(STEREO_SWING, sine) >> FRACBITS);
+
+    let volume = if distance < CLOSE_DIST {
+        volume
+    } else if map == BOSS_MAP {
+        distance = distance.min(CLIPPING_DIST);
+        15 + ((volume - 15) * ((CLIPPING_DIST - distance) >> FRACBITS)) / ATTENUATOR
+    } else {
+        (volume * ((CLIPPING_DIST - distance) >> FRACBITS)) / ATTENUATOR
+    };
+    (volume > 0).then_some((volume, separation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::angle::ANG90;
+
+    #[test]
+    fn sounds_fade_with_distance_and_pan_with_angle() {
+        let listener = ViewPoint::default();
+        let at = |x: i32, y: i32| (x * FRACUNIT, y * FRACUNIT);
+        assert_eq!(adjust(&listener, at(100, 0), 127, 1), Some((127, NORM_SEP)));
+        assert_eq!(adjust(&listener, at(700, 0), 127, 1), Some((63, NORM_SEP)));
+        assert_eq!(adjust(&listener, at(1300, 0), 127, 1), None);
+        assert_eq!(adjust(&listener, at(1300, 0), 127, 8), Some((15, NORM_SEP)));
+        assert_eq!(adjust(&listener, at(0, 0), 0, 1), None);
+
+        // Facing east, a sound to the north is on the left, 32, and to the south on the right.
+        assert_eq!(adjust(&listener, at(0, 100), 127, 1), Some((127, 32)));
+        assert_eq!(adjust(&listener, at(0, -100), 127, 1), Some((127, 224)));
+        let north = ViewPoint {
+            angle: ANG90,
+            ..ViewPoint::default()
+        };
+        assert_eq!(adjust(&north, at(0, 100), 127, 1), Some((127, NORM_SEP)));
+    }
+}
@@ -0,0 +1,838 @@
+//! Command line parsing with the vanilla flag names.
+//!
+//! Flags are written with a single dash like vanilla (`-warp 1 3`), but the double dash form
+//! (`--warp`) is accepted too. An argument of the form `@file` is replaced by the contents of
+//! that response file, which is how long command lines were passed under DOS. A few tools run
+//! instead of the game when their name comes first, like `doom-rs bench MAP01`.
+
+use super::profiles;
+use crate::game::compatibility::CompLevel;
+use crate::game::deathmatch::AVG_MINUTES;
+use crate::game::TICRATE;
+use crate::renderer::RendererKind;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many values a flag takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Arity {
+    Switch,
+    One,
+    Many,
+    Range(usize, usize),
+}
+
+struct Flag {
+    name: &'static str,
+    arity: Arity,
+    value: &'static str,
+    help: &'static str,
+}
+
+const fn flag(name: &'static str, arity: Arity, value: &'static str, help: &'static str) -> Flag {
+    Flag {
+        name,
+        arity,
+        value,
+        help,
+    }
+}
+
+#[rustfmt::skip]
+const FLAGS: &[Flag] = &[
+    flag("-iwad", Arity::One, "<file>", "IWAD to load"),
+    flag("-wad", Arity::One, "<file>", "same as -iwad"),
+    flag("-file", Arity::Many, "<files>", "PWADs, PK3s and folders to load on top of the IWAD"),
+    flag("-deh", Arity::Many, "<files>", "DeHackEd or BEX patches to apply"),
+    flag("-nodeh", Arity::Switch, "", "don't apply DEHACKED lumps found in WADs"),
+    flag("-warp", Arity::Range(1, 2), "<e> <m> | <m>", "start directly on a map"),
+    flag("-skill", Arity::One, "<1-5>", "skill level for -warp"),
+    flag("-episode", Arity::One, "<1-4>", "start on the first map of an episode"),
+    flag("-nomonsters", Arity::Switch, "", "don't spawn monsters"),
+    flag("-coopspawns", Arity::Switch, "", "spawn the multiplayer-only things in single player"),
+    flag("-fast", Arity::Switch, "", "fast monsters and projectiles"),
+    flag("-respawn", Arity::Switch, "", "monsters respawn after dying"),
+    flag("-record", Arity::One, "<name>", "record a demo to <name>.lmp"),
+    flag("-playdemo", Arity::One, "<name>", "play back a demo"),
+    flag("-skipsec", Arity::One, "<[m:]s>", "skip ahead in the demo before playing at normal speed"),
+    flag("-skiptic", Arity::One, "<tic>", "same as -skipsec, counted in tics"),
+    flag("-timedemo", Arity::One, "<name>", "play back a demo as fast as possible and time it"),
+    flag("-turbo", Arity::Range(0, 1), "[<10-400>]", "scale player speed, 200 if no value is given"),
+    flag("-deathmatch", Arity::Switch, "", "start a deathmatch game"),
+    flag("-timer", Arity::One, "<minutes>", "end deathmatch levels after this long"),
+    flag("-avg", Arity::Switch, "", "same as -timer 20"),
+    flag("-fraglimit", Arity::One, "<frags>", "end deathmatch levels when a player has this many frags"),
+    flag("-spawnprotect", Arity::One, "<seconds>", "players can't be telefragged this soon after spawning"),
+    flag("-exitkills", Arity::Switch, "", "deathmatch exits kill the player instead of ending the level"),
+    flag("-complevel", Arity::One, "<vanilla|boom|mbf|mbf21>", "compatibility level, demos override it"),
+    flag("-pistolstart", Arity::Switch, "", "start every level with only the pistol"),
+    flag("-nodemoprecache", Arity::Switch, "", "decode graphics and sounds on first use, not at level load"),
+    flag("-levelstat", Arity::Switch, "", "write levelstat.txt when the session ends"),
+    flag("-strict", Arity::Switch, "", "hold every gameplay setting at its vanilla value"),
+    flag("-config", Arity::One, "<file>", "config file to use instead of default.cfg"),
+    flag("-profile", Arity::One, "<name>", "config profile to use, made from the config if new"),
+    flag("-language", Arity::One, "<file>", "string table replacing the English text"),
+    flag("-control", Arity::One, "<address>", "let an agent drive the game over TCP, like 127.0.0.1:5029"),
+    flag("-headless", Arity::Switch, "", "run the game loop with no window or sound, for tests and servers"),
+    flag("-jukebox", Arity::Switch, "", "list and play the music lumps instead of starting the game"),
+    flag("-log", Arity::One, "<spec>", "log levels, like info or warn,wad=debug"),
+    flag("-renderer", Arity::One, "<gl|software>", "renderer to use"),
+    flag("-fullscreen", Arity::Switch, "", "start in fullscreen mode"),
+    flag("-resolution", Arity::One, "<w>x<h>", "window size"),
+    flag("-debug", Arity::Switch, "", "debug logging and the perf overlay"),
+    flag("-help", Arity::Switch, "", "show this text"),
+];
+
+/// The map given to `-warp`: Doom II style (`-warp 7`) or episode and map (`-warp 1 3`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warp {
+    Map(u8),
+    EpisodeMap(u8, u8),
+}
+
+/// A tool to run instead of the game, named by the first argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `bench <map>`: renders a camera path through the map and prints timings as JSON.
+    Bench { map: String },
+    /// `wadinfo <wad> [query]`: prints what's in a WAD without starting the game.
+    WadInfo { wad: PathBuf, query: WadQuery },
+    /// `mapview <map> <file> [size]`: draws the map from above to an SVG or PNG file.
+    MapView {
+        map: String,
+        to: PathBuf,
+        size: Option<usize>,
+    },
+    /// `extract <wad> <folder> [lump...]`: converts lumps to PNG, WAV and MIDI files.
+    Extract {
+        wad: PathBuf,
+        to: PathBuf,
+        lumps: Vec<String>,
+    },
+    /// `audit <demo> <config> <config>`: plays the demo under both configs and reports the
+    /// first tic where they diverge.
+    Audit { demo: String, configs: [PathBuf; 2] },
+}
+
+/// What `wadinfo` shows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum WadQuery {
+    /// Every lump with its offset and size.
+    #[default]
+    Lumps,
+    /// Writes the data of one lump to a file.
+    Dump { lump: String, to: PathBuf },
+    /// Textures, flats and sprites.
+    Inventory,
+    /// Each map, its format and whether it has nodes and a blockmap.
+    Maps,
+    /// Lumps added, removed or changed in another WAD.
+    Diff(PathBuf),
+}
+
+/// Each command's name and what follows it, for the usage text.
+const COMMANDS: &[(&str, &str)] = &[
+    ("bench", "<map>"),
+    (
+        "wadinfo",
+        "<wad> [lumps | dump <lump> <file> | inventory | maps | diff <wad>]",
+    ),
+    ("extract", "<wad> <folder> [lump...]"),
+    ("mapview", "<map> <file.svg|file.png> [size]"),
+    ("audit", "<demo> <config> <config>"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub command: Option<Command>,
+    pub iwad: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
+    pub deh: Vec<PathBuf>,
+    pub nodeh: bool,
+    pub warp: Option<Warp>,
+    pub skill: Option<u8>,
+    pub episode: Option<u8>,
+    pub nomonsters: bool,
+    pub coopspawns: bool,
+    pub fast: bool,
+    pub respawn: bool,
+    pub record: Option<String>,
+    pub playdemo: Option<String>,
+    pub timedemo: Option<String>,
+    pub skiptic: Option<u32>,
+    pub turbo: Option<u32>,
+    pub deathmatch: bool,
+    pub timer: Option<u32>,
+    pub fraglimit: Option<u32>,
+    pub spawnprotect: Option<u32>,
+    pub exitkills: bool,
+    pub complevel: Option<CompLevel>,
+    pub pistolstart: bool,
+    pub nodemoprecache: bool,
+    pub levelstat: bool,
+    pub strict: bool,
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub language: Option<PathBuf>,
+    pub log: Option<String>,
+    pub control: Option<String>,
+    pub headless: bool,
+    pub jukebox: bool,
+    pub renderer: RendererKind,
+    pub fullscreen: bool,
+    pub resolution: Option<(u32, u32)>,
+    pub debug: bool,
+    pub help: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgsError {
+    UnknownFlag {
+        flag: String,
+        suggestion: Option<&'static str>,
+    },
+    UnexpectedValue(String),
+    WrongValueCount {
+        flag: &'static str,
+        usage: &'static str,
+    },
+    InvalidValue {
+        flag: &'static str,
+        value: String,
+        usage: &'static str,
+    },
+    ResponseFile {
+        path: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::UnknownFlag { flag, suggestion } => {
+                write!(f, "unknown option '{flag}'")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean '{suggestion}'?")?;
+                }
+                write!(f, " (use -help to list options)")
+            }
+            ArgsError::UnexpectedValue(value) => {
+                write!(f, "'{value}' does not belong to any option")
+            }
+            ArgsError::WrongValueCount { flag, usage } => {
+                write!(
+                    f,
+                    "wrong number of values for {flag}, expected {flag} {usage}"
+                )
+            }
+            ArgsError::InvalidValue { flag, value, usage } => {
+                write!(
+                    f,
+                    "invalid value '{value}' for {flag}, expected {flag} {usage}"
+                )
+            }
+            ArgsError::ResponseFile { path, error } => {
+                write!(f, "couldn't read response file '{path}': {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse<I, S>(args: I) -> Result<Self, ArgsError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let args = expand_response_files(args.into_iter().map(Into::into).collect())?;
+        let (command, args) = split_command(&args)?;
+        let mut parsed = Args {
+            command,
+            ..Args::default()
+        };
+        for (flag, values) in group(args)? {
+            parsed.apply(flag, &values)?;
+        }
+        Ok(parsed)
+    }
+
+    fn apply(&mut self, flag: &'static Flag, values: &[&str]) -> Result<(), ArgsError> {
+        let invalid = |value: &str| ArgsError::InvalidValue {
+            flag: flag.name,
+            value: value.to_string(),
+            usage: flag.value,
+        };
+        let number = |value: &str, range: std::ops::RangeInclusive<u32>| {
+            value
+                .parse::<u32>()
+                .ok()
+                .filter(|n| range.contains(n))
+                .ok_or_else(|| invalid(value))
+        };
+
+        match flag.name {
+            "-iwad" | "-wad" => self.iwad = Some(PathBuf::from(values[0])),
+            "-file" => self.files.extend(values.iter().map(PathBuf::from)),
+            "-deh" => self.deh.extend(values.iter().map(PathBuf::from)),
+            "-nodeh" => self.nodeh = true,
+            "-warp" => {
+                self.warp = Some(match values {
+                    [map] => Warp::Map(number(map, 1..=99)? as u8),
+                    [episode, map] => {
+                        Warp::EpisodeMap(number(episode, 1..=9)? as u8, number(map, 1..=9)? as u8)
+                    }
+                    _ => unreachable!(),
+                })
+            }
+            "-skill" => self.skill = Some(number(values[0], 1..=5)? as u8),
+            "-episode" => self.episode = Some(number(values[0], 1..=4)? as u8),
+            "-nomonsters" => self.nomonsters = true,
+            "-coopspawns" => self.coopspawns = true,
+            "-fast" => self.fast = true,
+            "-respawn" => self.respawn = true,
+            "-record" => self.record = Some(values[0].to_string()),
+            "-playdemo" => self.playdemo = Some(values[0].to_string()),
+            "-skipsec" => {
+                let seconds = match values[0].split_once(':') {
+                    Some((minutes, seconds)) => minutes
+                        .parse::<u32>()
+                        .ok()
+                        .zip(seconds.parse::<u32>().ok().filter(|&s| s < 60))
+                        .map(|(minutes, seconds)| minutes * 60 + seconds),
+                    None => values[0].parse().ok(),
+                };
+                let tics = seconds.and_then(|seconds| seconds.checked_mul(TICRATE as u32));
+                self.skiptic = Some(tics.ok_or_else(|| invalid(values[0]))?);
+            }
+            "-skiptic" => self.skiptic = Some(number(values[0], 0..=u32::MAX)?),
+            "-timedemo" => self.timedemo = Some(values[0].to_string()),
+            "-turbo" => {
+                self.turbo = Some(match values.first() {
+                    Some(value) => number(value, 10..=400)?,
+                    None => 200,
+                })
+            }
+            "-deathmatch" => self.deathmatch = true,
+            "-timer" => self.timer = Some(number(values[0], 1..=1440)?),
+            "-avg" => self.timer = Some(AVG_MINUTES as u32),
+            "-fraglimit" => self.fraglimit = Some(number(values[0], 1..=999)?),
+            "-spawnprotect" => self.spawnprotect = Some(number(values[0], 1..=10)?),
+            "-exitkills" => self.exitkills = true,
+            "-pistolstart" => self.pistolstart = true,
+            "-nodemoprecache" => self.nodemoprecache = true,
+            "-levelstat" => self.levelstat = true,
+            "-strict" => self.strict = true,
+            "-complevel" => {
+                self.complevel = Some(values[0].parse().map_err(|_| invalid(values[0]))?)
+            }
+            "-config" => self.config = Some(PathBuf::from(values[0])),
+            "-profile" => {
+                if !profiles::is_valid_name(values[0]) {
+                    return Err(invalid(values[0]));
+                }
+                self.profile = Some(values[0].to_string());
+            }
+            "-language" => self.language = Some(PathBuf::from(values[0])),
+            "-log" => self.log = Some(values[0].to_string()),
+            "-control" => self.control = Some(values[0].to_string()),
+            "-headless" => self.headless = true,
+            "-jukebox" => self.jukebox = true,
+            "-renderer" => self.renderer = values[0].parse().map_err(|_| invalid(values[0]))?,
+            "-fullscreen" => self.fullscreen = true,
+            "-resolution" => {
+                let size = values[0]
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|&(w, h)| w > 0 && h > 0);
+                self.resolution = Some(size.ok_or_else(|| invalid(values[0]))?);
+            }
+            "-debug" => self.debug = true,
+            "-help" => self.help = true,
+            name => unreachable!("flag {name} is in FLAGS but not handled"),
+        }
+        Ok(())
+    }
+}
+
+/// A usage text listing every option.
+pub fn usage() -> String {
+    let mut text = String::from("usage: doom-rs [options] [@responsefile]\n");
+    for (name, value) in COMMANDS {
+        text.push_str(&format!("       doom-rs {name} {value} [options]\n"));
+    }
+    text.push_str("\noptions:\n");
+    for flag in FLAGS {
+        let synopsis = format!("{} {}", flag.name, flag.value);
+        text.push_str(&format!("  {synopsis:<30}{}\n", flag.help));
+    }
+    text
+}
+
+/// Takes the command and its value off the front of the arguments, if there is one.
+fn split_command(args: &[String]) -> Result<(Option<Command>, &[String]), ArgsError> {
+    let Some(&(name, usage)) = COMMANDS
+        .iter()
+        .find(|(name, _)| args.first().is_some_and(|arg| arg == name))
+    else {
+        return Ok((None, args));
+    };
+    let value = args
+        .get(1)
+        .filter(|value| !value.starts_with('-'))
+        .ok_or(ArgsError::WrongValueCount { flag: name, usage })?;
+    let rest = &args[2..];
+    let words = rest.iter().take_while(|arg| !arg.starts_with('-')).count();
+    let command = match (name, &rest[..words]) {
+        ("bench", []) => Command::Bench { map: value.clone() },
+        ("wadinfo", query) => {
+            let query = match query {
+                [] => WadQuery::Lumps,
+                [word] if word == "lumps" => WadQuery::Lumps,
+                [word] if word == "inventory" => WadQuery::Inventory,
+                [word] if word == "maps" => WadQuery::Maps,
+                [word, lump, to] if word == "dump" => WadQuery::Dump {
+                    lump: lump.clone(),
+                    to: PathBuf::from(to),
+                },
+                [word, other] if word == "diff" => WadQuery::Diff(PathBuf::from(other)),
+                _ => return Err(ArgsError::WrongValueCount { flag: name, usage }),
+            };
+            Command::WadInfo {
+                wad: PathBuf::from(value),
+                query,
+            }
+        }
+        ("extract", [to, lumps @ ..]) => Command::Extract {
+            wad: PathBuf::from(value),
+            to: PathBuf::from(to),
+            lumps: lumps.to_vec(),
+        },
+        ("extract", []) => return Err(ArgsError::WrongValueCount { flag: name, usage }),
+        ("mapview", [to, size @ ..]) if size.len() <= 1 => Command::MapView {
+            map: value.clone(),
+            to: PathBuf::from(to),
+            size: match size.first() {
+                Some(size) => Some(size.parse().map_err(|_| ArgsError::InvalidValue {
+                    flag: name,
+                    value: size.clone(),
+                    usage,
+                })?),
+                None => None,
+            },
+        },
+        ("mapview", _) => return Err(ArgsError::WrongValueCount { flag: name, usage }),
+        ("audit", [left, right]) => Command::Audit {
+            demo: value.clone(),
+            configs: [PathBuf::from(left), PathBuf::from(right)],
+        },
+        ("audit", _) => return Err(ArgsError::WrongValueCount { flag: name, usage }),
+        (_, [word, ..]) => return Err(ArgsError::UnexpectedValue(word.clone())),
+        (name, _) => unreachable!("command {name} is in COMMANDS but not handled"),
+    };
+    Ok((Some(command), &rest[words..]))
+}
+
+/// Splits the arguments into flags and the values that follow them, checking the counts.
+fn group(args: &[String]) -> Result<Vec<(&'static Flag, Vec<&str>)>, ArgsError> {
+    let mut groups: Vec<(&'static Flag, Vec<&str>)> = Vec::new();
+    for arg in args {
+        if arg.starts_with('-') && arg.len() > 1 {
+            groups.push((lookup(arg)?, Vec::new()));
+        } else if let Some((_, values)) = groups.last_mut() {
+            values.push(arg);
+        } else {
+            return Err(ArgsError::UnexpectedValue(arg.clone()));
+        }
+    }
+
+    for (flag, values) in &groups {
+        let (min, max) = match flag.arity {
+            Arity::Switch => (0, 0),
+            Arity::One => (1, 1),
+            Arity::Many => (1, usize::MAX),
+            Arity::Range(min, max) => (min, max),
+        };
+        if values.len() < min {
+            return Err(ArgsError::WrongValueCount {
+                flag: flag.name,
+                usage: flag.value,
+            });
+        }
+        if values.len() > max {
+            return Err(ArgsError::UnexpectedValue(values[max].to_string()));
+        }
+    }
+    Ok(groups)
+}
+
+fn lookup(arg: &str) -> Result<&'static Flag, ArgsError> {
+    // Accept both -flag and --flag, in any case, like vanilla's case insensitive M_CheckParm.
+    let name = format!("-{}", arg.trim_start_matches('-').to_ascii_lowercase());
+    FLAGS
+        .iter()
+        .find(|flag| flag.name == name)
+        .ok_or_else(|| ArgsError::UnknownFlag {
+            flag: arg.to_string(),
+            suggestion: FLAGS
+                .iter()
+                .map(|flag| (edit_distance(&name, flag.name), flag.name))
+                .filter(|&(distance, _)| distance <= 2)
+                .min()
+                .map(|(_, name)| name),
+        })
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous + (ca != cb) as usize;
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Replaces every `@file` argument with the whitespace separated words in that file. Double
+/// quotes group words containing spaces.
+fn expand_response_files(args: Vec<String>) -> Result<Vec<String>, ArgsError> {
+    let mut expanded = Vec::with_capacity(args.len());
+    for arg in args {
+        let Some(path) = arg.strip_prefix('@') else {
+            expanded.push(arg);
+            continue;
+        };
+        let contents = fs::read_to_string(path).map_err(|error| ArgsError::ResponseFile {
+            path: path.to_string(),
+            error: error.to_string(),
+        })?;
+        expanded.extend(split_words(&contents));
+    }
+    Ok(expanded)
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut quoted, mut in_word) = (false, false);
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                }
+                in_word = false;
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_classic_flags() {
+        let args = Args::parse([
+            "-iwad",
+            "doom2.wad",
+            "-file",
+            "a.wad",
+            "b.wad",
+            "-warp",
+            "7",
+            "-skill",
+            "4",
+            "-fast",
+            "-nomonsters",
+            "-turbo",
+            "-record",
+            "run",
+        ])
+        .unwrap();
+        assert_eq!(args.iwad, Some(PathBuf::from("doom2.wad")));
+        assert_eq!(
+            args.files,
+            vec![PathBuf::from("a.wad"), PathBuf::from("b.wad")]
+        );
+        assert_eq!(args.warp, Some(Warp::Map(7)));
+        assert_eq!(args.skill, Some(4));
+        assert!(args.fast && args.nomonsters && !args.respawn);
+        assert_eq!(args.turbo, Some(200));
+        assert_eq!(args.record.as_deref(), Some("run"));
+    }
+
+    #[test]
+    fn accepts_double_dash_and_episode_warp() {
+        let args =
+            Args::parse(["--wad", "doom.wad", "--warp", "1", "3", "--renderer", "gl"]).unwrap();
+        assert_eq!(args.warp, Some(Warp::EpisodeMap(1, 3)));
+        assert_eq!(args.renderer, RendererKind::Hardware);
+        let args = Args::parse(["-resolution", "1920x1080"]).unwrap();
+        assert_eq!(args.resolution, Some((1920, 1080)));
+    }
+
+    #[test]
+    fn complevel_accepts_numbers_and_names() {
+        assert_eq!(
+            Args::parse(["-complevel", "9"]).unwrap().complevel,
+            Some(CompLevel::Boom)
+        );
+        assert_eq!(
+            Args::parse(["-complevel", "mbf21"]).unwrap().complevel,
+            Some(CompLevel::Mbf21)
+        );
+        assert!(matches!(
+            Args::parse(["-complevel", "42"]),
+            Err(ArgsError::InvalidValue {
+                flag: "-complevel",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn skip_flags_count_in_tics() {
+        assert_eq!(Args::parse(["-skipsec", "90"]).unwrap().skiptic, Some(3150));
+        assert_eq!(
+            Args::parse(["-skipsec", "1:30"]).unwrap().skiptic,
+            Some(3150)
+        );
+        assert_eq!(Args::parse(["-skiptic", "12"]).unwrap().skiptic, Some(12));
+        assert!(Args::parse(["-skipsec", "1:75"]).is_err());
+    }
+
+    #[test]
+    fn deathmatch_rules() {
+        let args = Args::parse([
+            "-deathmatch",
+            "-avg",
+            "-fraglimit",
+            "30",
+            "-spawnprotect",
+            "2",
+            "-exitkills",
+        ])
+        .unwrap();
+        assert_eq!(args.timer, Some(20));
+        assert_eq!((args.fraglimit, args.spawnprotect), (Some(30), Some(2)));
+        assert!(args.deathmatch && args.exitkills);
+        assert_eq!(Args::parse(["-timer", "5"]).unwrap().timer, Some(5));
+        assert!(Args::parse(["-spawnprotect", "60"]).is_err());
+    }
+
+    #[test]
+    fn dehacked_flags() {
+        let args = Args::parse(["-deh", "a.deh", "b.bex", "-nodeh"]).unwrap();
+        assert_eq!(
+            args.deh,
+            vec![PathBuf::from("a.deh"), PathBuf::from("b.bex")]
+        );
+        assert!(args.nodeh);
+    }
+
+    #[test]
+    fn unknown_flags_suggest_the_closest_match() {
+        let error = Args::parse(["-nomonster"]).unwrap_err();
+        assert_eq!(
+            error,
+            ArgsError::UnknownFlag {
+                flag: "-nomonster".into(),
+                suggestion: Some("-nomonsters")
+            }
+        );
+        assert!(error.to_string().contains("did you mean '-nomonsters'"));
+        assert!(matches!(
+            Args::parse(["-frobnicate"]),
+            Err(ArgsError::UnknownFlag {
+                suggestion: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn bad_values_are_reported() {
+        assert!(matches!(
+            Args::parse(["-skill", "9"]),
+            Err(ArgsError::InvalidValue { flag: "-skill", .. })
+        ));
+        assert!(matches!(
+            Args::parse(["-record"]),
+            Err(ArgsError::WrongValueCount {
+                flag: "-record",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Args::parse(["-profile", "../default"]),
+            Err(ArgsError::InvalidValue {
+                flag: "-profile",
+                ..
+            })
+        ));
+        assert_eq!(
+            Args::parse(["-fast", "extra"]),
+            Err(ArgsError::UnexpectedValue("extra".into()))
+        );
+        assert_eq!(
+            Args::parse(["stray"]),
+            Err(ArgsError::UnexpectedValue("stray".into()))
+        );
+    }
+
+    #[test]
+    fn response_files_are_expanded_in_place() {
+        let path = std::env::temp_dir().join(format!("doom-rs-args-{}.rsp", std::process::id()));
+        fs::write(&path, "-file \"my mod.wad\"\n  -respawn\n").unwrap();
+        let args = Args::parse(["-fast".to_string(), format!("@{}", path.display())]).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(args.files, vec![PathBuf::from("my mod.wad")]);
+        assert!(args.fast && args.respawn);
+        assert!(matches!(
+            Args::parse(["@/nonexistent/doom.rsp"]),
+            Err(ArgsError::ResponseFile { .. })
+        ));
+    }
+
+    #[test]
+    fn commands_come_first_with_their_value() {
+        let args = Args::parse(["bench", "MAP07", "-iwad", "doom2.wad"]).unwrap();
+        assert_eq!(
+            args.command,
+            Some(Command::Bench {
+                map: "MAP07".into()
+            })
+        );
+        assert_eq!(args.iwad, Some(PathBuf::from("doom2.wad")));
+        assert!(matches!(
+            Args::parse(["bench", "-iwad", "doom2.wad"]),
+            Err(ArgsError::WrongValueCount { flag: "bench", .. })
+        ));
+        assert_eq!(
+            Args::parse(["-iwad", "doom2.wad", "bench"]),
+            Err(ArgsError::UnexpectedValue("bench".into()))
+        );
+    }
+
+    #[test]
+    fn tool_commands_take_words_after_their_value() {
+        let command = |args: &[&str]| Args::parse(args.iter().copied()).map(|a| a.command);
+        let wadinfo = |query| {
+            Ok(Some(Command::WadInfo {
+                wad: PathBuf::from("mod.wad"),
+                query,
+            }))
+        };
+        assert_eq!(command(&["wadinfo", "mod.wad"]), wadinfo(WadQuery::Lumps));
+        assert_eq!(
+            command(&["wadinfo", "mod.wad", "maps"]),
+            wadinfo(WadQuery::Maps)
+        );
+        assert_eq!(
+            command(&["wadinfo", "mod.wad", "dump", "playpal", "pal.lmp"]),
+            wadinfo(WadQuery::Dump {
+                lump: "playpal".into(),
+                to: PathBuf::from("pal.lmp")
+            })
+        );
+        assert_eq!(
+            command(&["wadinfo", "mod.wad", "diff", "old.wad", "-debug"]),
+            wadinfo(WadQuery::Diff(PathBuf::from("old.wad")))
+        );
+        assert!(matches!(
+            command(&["wadinfo", "mod.wad", "dump", "playpal"]),
+            Err(ArgsError::WrongValueCount {
+                flag: "wadinfo",
+                ..
+            })
+        ));
+        assert_eq!(
+            command(&[
+                "extract",
+                "doom.wad",
+                "out",
+                "PLAYPAL",
+                "-iwad",
+                "doom2.wad"
+            ]),
+            Ok(Some(Command::Extract {
+                wad: PathBuf::from("doom.wad"),
+                to: PathBuf::from("out"),
+                lumps: vec!["PLAYPAL".into()],
+            }))
+        );
+        assert!(matches!(
+            command(&["extract", "doom.wad"]),
+            Err(ArgsError::WrongValueCount {
+                flag: "extract",
+                ..
+            })
+        ));
+        assert_eq!(
+            command(&["mapview", "E1M1", "e1m1.svg", "2048"]),
+            Ok(Some(Command::MapView {
+                map: "E1M1".into(),
+                to: PathBuf::from("e1m1.svg"),
+                size: Some(2048),
+            }))
+        );
+        assert!(matches!(
+            command(&["mapview", "E1M1", "e1m1.svg", "big"]),
+            Err(ArgsError::InvalidValue {
+                flag: "mapview",
+                ..
+            })
+        ));
+        assert_eq!(
+            command(&[
+                "audit",
+                "demo1",
+                "vanilla.cfg",
+                "boom.cfg",
+                "-iwad",
+                "doom.wad"
+            ]),
+            Ok(Some(Command::Audit {
+                demo: "demo1".into(),
+                configs: [PathBuf::from("vanilla.cfg"), PathBuf::from("boom.cfg")],
+            }))
+        );
+        assert!(matches!(
+            command(&["audit", "demo1", "vanilla.cfg"]),
+            Err(ArgsError::WrongValueCount { flag: "audit", .. })
+        ));
+        assert_eq!(
+            command(&["bench", "MAP01", "MAP02"]),
+            Err(ArgsError::UnexpectedValue("MAP02".into()))
+        );
+    }
+
+    #[test]
+    fn usage_lists_every_flag() {
+        let text = usage();
+        assert!(FLAGS.iter().all(|flag| text.contains(flag.name)));
+        assert!(text.contains("doom-rs bench <map>"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Determinism audit: one demo through two engine setups side by side, stopping at the
+//! first tic where they disagree.
+//!
+//! Comparing every part of the state every tic would cost more than the tics, so the runs
+//! only compare `tic_hash` once a second of game time. Once they differ the tic is found by
+//! bisection, replaying both from the start to the midpoint of the last window that agreed
+//! and the one that didn't, and at that tic the per-part hashes name what went wrong.
+
+use super::Engine;
+use crate::game::demo::{Demo, DemoPlayback};
+use crate::game::ticcmd::TicCmd;
+use crate::game::TICRATE;
+use std::fmt;
+
+/// How many tics run between hash comparisons.
+pub const CHECK_INTERVAL: usize = TICRATE as usize;
+
+/// What the audit runs. The engine is the real one; tests stand in their own.
+pub trait Simulation {
+    fn run_tic(&mut self, cmds: &[TicCmd]);
+    fn tic_hash(&self) -> u64;
+    /// Named hashes of the parts of the state, in a fixed order.
+    fn state_hashes(&self) -> Vec<(&'static str, u64)>;
+}
+
+impl Simulation for Engine {
+    fn run_tic(&mut self, cmds: &[TicCmd]) {
+        Engine::run_tic(self, cmds);
+    }
+
+    fn tic_hash(&self) -> u64 {
+        Engine::tic_hash(self)
+    }
+
+    fn state_hashes(&self) -> Vec<(&'static str, u64)> {
+        Engine::state_hashes(self)
+    }
+}
+
+/// The first tic the two runs disagree after, and the first part of the state that differs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub tic: usize,
+    pub field: &'static str,
+    pub left: u64,
+    pub right: u64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "diverged at tic {}: {} is {:016x} on the left and {:016x} on the right",
+            self.tic, self.field, self.left, self.right
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Audit {
+    /// Tics run before the audit stopped: the whole demo when the runs agree.
+    pub tics: usize,
+    pub divergence: Option<Divergence>,
+}
+
+/// Plays `demo` on a simulation from `left` and one from `right`, comparing them every
+/// `interval` tics and after the last.
+pub fn audit<S: Simulation>(
+    demo: &Demo,
+    left: impl Fn() -> S,
+    right: impl Fn() -> S,
+    interval: usize,
+) -> Audit {
+    let mut playback = DemoPlayback::new(demo.clone());
+    let (mut a, mut b) = (left(), right());
+    let (mut tics, mut agreed) = (0, 0);
+    while let Some(cmds) = playback.next_tic() {
+        a.run_tic(cmds);
+        b.run_tic(cmds);
+        tics += 1;
+        if tics % interval.max(1) != 0 {
+            continue;
+        }
+        if a.tic_hash() != b.tic_hash() {
+            break;
+        }
+        agreed = tics;
+    }
+    let divergence =
+        (a.tic_hash() != b.tic_hash()).then(|| bisect(demo, &left, &right, agreed, tics));
+    Audit {
+        tics: divergence
+            .as_ref()
+            .map_or(tics, |divergence| divergence.tic),
+        divergence,
+    }
+}
+
+/// Narrows the divergence down to one tic, knowing the runs agree after `agreed` tics and
+/// disagree after `differed`.
+fn bisect<S: Simulation>(
+    demo: &Demo,
+    left: &impl Fn() -> S,
+    right: &impl Fn() -> S,
+    mut agreed: usize,
+    mut differed: usize,
+) -> Divergence {
+    while differed - agreed > 1 {
+        let middle = agreed + (differed - agreed) / 2;
+        if run(demo, left, middle).tic_hash() == run(demo, right, middle).tic_hash() {
+            agreed = middle;
+        } else {
+            differed = middle;
+        }
+    }
+    let (a, b) = (run(demo, left, differed), run(demo, right, differed));
+    let fields = a.state_hashes().into_iter().zip(b.state_hashes());
+    let (field, left, right) = fields
+        .map(|((field, left), (_, right))| (field, left, right))
+        .find(|(_, left, right)| left != right)
+        .unwrap_or(("tic_hash", a.tic_hash(), b.tic_hash()));
+    Divergence {
+        tic: differed,
+        field,
+        left,
+        right,
+    }
+}
+
+fn run<S: Simulation>(demo: &Demo, make: &impl Fn() -> S, tics: usize) -> S {
+    let mut simulation = make();
+    let mut playback = DemoPlayback::new(demo.clone());
+    for _ in 0..tics {
+        let Some(cmds) = playback.next_tic() else {
+            break;
+        };
+        simulation.run_tic(cmds);
+    }
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::game::demo::{DemoHeader, DemoRecorder};
+    use crate::wad::file::Wads;
+
+    /// Counts tics, and from `skew_at` on counts something else wrong.
+    struct Counter {
+        tics: u64,
+        skew_at: Option<u64>,
+    }
+
+    impl Simulation for Counter {
+        fn run_tic(&mut self, _: &[TicCmd]) {
+            self.tics += 1;
+        }
+
+        fn tic_hash(&self) -> u64 {
+            self.state_hashes()
+                .iter()
+                .fold(0, |hash, (_, part)| hash * 31 + part)
+        }
+
+        fn state_hashes(&self) -> Vec<(&'static str, u64)> {
+            let skewed = self.skew_at.is_some_and(|at| self.tics >= at);
+            vec![("tics", self.tics), ("health", 100 - skewed as u64)]
+        }
+    }
+
+    fn demo(tics: usize) -> Demo {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..tics {
+            recorder.record(&[TicCmd::default()]);
+        }
+        recorder.demo
+    }
+
+    #[test]
+    fn bisection_finds_the_first_tic_that_differs() {
+        let good = || Counter {
+            tics: 0,
+            skew_at: None,
+        };
+        let bad = |at| {
+            move || Counter {
+                tics: 0,
+                skew_at: Some(at),
+            }
+        };
+        let demo = demo(200);
+        for at in [1, 34, 35, 36, 50, 199, 200] {
+            let audit = audit(&demo, good, bad(at), CHECK_INTERVAL);
+            let divergence = audit.divergence.unwrap();
+            assert_eq!((audit.tics, divergence.tic), (at as usize, at as usize));
+            assert_eq!(
+                (divergence.field, divergence.left, divergence.right),
+                ("health", 100, 99)
+            );
+        }
+        assert_eq!(
+            audit(&demo, good, bad(201), 1),
+            Audit {
+                tics: 200,
+                divergence: None,
+            }
+        );
+    }
+
+    #[test]
+    fn identical_engines_agree() {
+        let engine = || Engine::new(Wads::new(), Config::new());
+        let audit = audit(&demo(100), engine, engine, 7);
+        assert_eq!((audit.tics, audit.divergence), (100, None));
+        let divergence = Divergence {
+            tic: 3,
+            field: "ticcmds",
+            left: 1,
+            right: 2,
+        };
+        assert_eq!(
+            divergence.to_string(),
+            "diverged at tic 3: ticcmds is 0000000000000001 on the left and 0000000000000002 on the right"
+        );
+    }
+}
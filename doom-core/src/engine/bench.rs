@@ -0,0 +1,191 @@
+//! `doom-rs bench <map>`: renders a fixed camera path through a map without a window and
+//! prints the timings as JSON.
+//!
+//! The path turns a full circle at the player 1 start, then looks eight ways from the middle
+//! of every sector, so the same map gives the same frames on every run and results from two
+//! commits compare directly. Timings come from `engine::profile`, broken down by
+//! subsystem. Frames go to a GPU backend that draws nothing, so the numbers are the
+//! engine's own work and not the driver's.
+
+use super::profile::{self, Profiler};
+use crate::game::TICRATE;
+use crate::renderer::atlas::Atlas;
+use crate::renderer::hardware::{DrawBatch, GpuBackend, HardwareRenderer};
+use crate::renderer::{Renderer, ViewPoint};
+use crate::util::angle::{Angle, ANG45};
+use crate::util::fixed::to_fixed;
+use crate::wad::map::MapData;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+const VIEWHEIGHT: i32 = 41;
+const SPIN_FRAMES: u32 = 4 * TICRATE as u32;
+/// Every wall texture is packed at this size: the bench measures geometry, not texels.
+const TEXTURE_SIZE: u32 = 64;
+
+/// The views to render, in order.
+pub fn camera_path(map: &MapData) -> Vec<ViewPoint> {
+    let mut path = Vec::new();
+    let start = map.things.iter().find(|thing| thing.kind == 1);
+    if let Some(start) = start {
+        let step = (u32::MAX / SPIN_FRAMES).wrapping_add(1);
+        path.extend((0..SPIN_FRAMES).map(|i| ViewPoint {
+            x: to_fixed(start.x.into()),
+            y: to_fixed(start.y.into()),
+            z: to_fixed(VIEWHEIGHT),
+            angle: (start.angle as u32 / 45 * ANG45).wrapping_add(i.wrapping_mul(step)),
+        }));
+    }
+    for (index, sector) in map.sectors.iter().enumerate() {
+        let Some((x, y)) = sector_center(map, index) else {
+            continue;
+        };
+        let z = to_fixed(i32::from(sector.floor_height) + VIEWHEIGHT);
+        path.extend((0..8).map(|i: Angle| ViewPoint {
+            x,
+            y,
+            z,
+            angle: i * ANG45,
+        }));
+    }
+    path
+}
+
+/// The average of the vertices on the sector's lines.
+fn sector_center(map: &MapData, sector: usize) -> Option<(i32, i32)> {
+    let on_sector = |side: u16| {
+        map.sidedefs
+            .get(side as usize)
+            .is_some_and(|side| side.sector as usize == sector)
+    };
+    let vertices: BTreeSet<u16> = map
+        .linedefs
+        .iter()
+        .filter(|line| on_sector(line.front) || on_sector(line.back))
+        .flat_map(|line| [line.v1, line.v2])
+        .collect();
+    let count = vertices.len() as i64;
+    if count == 0 {
+        return None;
+    }
+    let (x, y) = vertices.iter().fold((0i64, 0i64), |(x, y), &v| {
+        let vertex = map.vertexes[v as usize];
+        (x + i64::from(vertex.x), y + i64::from(vertex.y))
+    });
+    Some((to_fixed((x / count) as i32), to_fixed((y / count) as i32)))
+}
+
+#[derive(Default)]
+struct NullGpu;
+
+impl GpuBackend for NullGpu {
+    fn resize(&mut self, _width: usize, _height: usize) {}
+    fn draw(&mut self, _view: &ViewPoint, _batches: &[DrawBatch]) {}
+}
+
+/// A renderer for `map` that needs no window.
+pub fn headless_renderer(map: &MapData) -> impl Renderer {
+    let textures: BTreeSet<&String> = map
+        .sidedefs
+        .iter()
+        .flat_map(|side| [&side.upper, &side.lower, &side.middle])
+        .filter(|name| !name.is_empty() && name.as_str() != "-")
+        .collect();
+    let images: Vec<(String, u32, u32)> = textures
+        .into_iter()
+        .map(|name| (name.clone(), TEXTURE_SIZE, TEXTURE_SIZE))
+        .collect();
+    let side = (images.len() as f64).sqrt().ceil() as u32 * TEXTURE_SIZE;
+    let atlas = Atlas::pack(side.max(TEXTURE_SIZE), &images).expect("the atlas is sized to fit");
+    HardwareRenderer::new(NullGpu, atlas)
+}
+
+#[derive(Clone, Debug)]
+pub struct BenchReport {
+    pub map: String,
+    pub frames: usize,
+    pub elapsed: Duration,
+    pub profile: Profiler,
+}
+
+impl BenchReport {
+    pub fn fps(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"map\": \"{}\", \"frames\": {}, \"total_ms\": {:.3}, \"fps\": {:.1}, \"subsystems\": {}}}",
+            self.map,
+            self.frames,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.fps(),
+            self.profile.to_json()
+        )
+    }
+}
+
+/// Renders every view of `path` with profiling on.
+pub fn run(
+    name: &str,
+    renderer: &mut impl Renderer,
+    map: &MapData,
+    path: &[ViewPoint],
+) -> BenchReport {
+    renderer.resize(320, 200);
+    profile::reset();
+    profile::set_enabled(true);
+    let start = Instant::now();
+    for view in path {
+        renderer.render(map, view);
+        profile::end_frame();
+    }
+    let elapsed = start.elapsed();
+    profile::set_enabled(false);
+    BenchReport {
+        map: name.to_string(),
+        frames: path.len(),
+        elapsed,
+        profile: profile::totals(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::profile::Subsystem;
+    use crate::util::angle::ANG90;
+    use crate::wad::map::tests::two_room_map;
+    use crate::wad::map::Thing;
+
+    #[test]
+    fn the_path_spins_at_the_start_then_visits_every_sector() {
+        let mut map = two_room_map();
+        map.things.push(Thing {
+            x: 16,
+            y: 32,
+            angle: 90,
+            kind: 1,
+            flags: 7,
+        });
+        let path = camera_path(&map);
+        assert_eq!(path.len(), SPIN_FRAMES as usize + 2 * 8);
+        assert_eq!(path[0].angle, ANG90);
+        assert_eq!(path[0].x, to_fixed(16));
+        let sector = &path[SPIN_FRAMES as usize + 8];
+        assert_eq!((sector.x, sector.z), (to_fixed(32), to_fixed(32 + 41)));
+    }
+
+    #[test]
+    fn reports_every_frame_with_the_subsystem_timings() {
+        let map = two_room_map();
+        let path = camera_path(&map);
+        let report = run("TEST", &mut headless_renderer(&map), &map, &path);
+        assert_eq!(report.frames, 16);
+        assert_eq!(report.profile.frames(), 16);
+        assert!(report.profile.total(Subsystem::Walls) > Duration::ZERO);
+        let json = report.to_json();
+        assert!(json.starts_with("{\"map\": \"TEST\", \"frames\": 16, "));
+        assert!(json.contains("\"subsystems\": {\"game\": "));
+    }
+}
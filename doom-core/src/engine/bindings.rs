@@ -0,0 +1,160 @@
+//! Editing a control's bindings in the config, for the key bindings page of the options
+//! menu.
+//!
+//! A control holds up to two keys, a mouse button and a joystick button (see `controls`).
+//! Binding a new key fills the first free key slot, or replaces the second; a button takes
+//! the control's one button slot of its kind. Clearing empties all four. Nothing stops two
+//! controls sharing a key; `conflicts` lists where they do so the menu can point it out.
+
+use super::config::Config;
+use super::controls::Control;
+use super::input::{key_name, Key};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(Key),
+    Mouse(i32),
+    Joystick(i32),
+}
+
+impl Binding {
+    /// How the menu shows it. Buttons count from 1, as their labels do.
+    pub fn name(self) -> String {
+        match self {
+            Binding::Key(key) => key_name(key),
+            Binding::Mouse(button) => format!("MOUSE {}", button + 1),
+            Binding::Joystick(button) => format!("JOY {}", button + 1),
+        }
+    }
+
+    /// The lowest button down in `buttons`, a mouse or joystick button mask.
+    pub fn button(buttons: i32, joystick: bool) -> Option<Self> {
+        let button = (buttons != 0).then(|| buttons.trailing_zeros() as i32)?;
+        Some(match joystick {
+            true => Binding::Joystick(button),
+            false => Binding::Mouse(button),
+        })
+    }
+}
+
+/// The bindings of `control`, keys first.
+pub fn bindings(config: &Config, control: Control) -> Vec<Binding> {
+    let keys = [control.config_name(), control.alt_config_name()]
+        .map(|name| config.get_int(name))
+        .into_iter()
+        .filter(|&key| key != 0)
+        .map(Binding::Key);
+    let mouse = Some(config.get_int(control.mouse_config_name()))
+        .filter(|&button| button >= 0)
+        .map(Binding::Mouse);
+    let joystick = Some(config.get_int(control.joystick_config_name()))
+        .filter(|&button| button >= 0)
+        .map(Binding::Joystick);
+    keys.chain(mouse).chain(joystick).collect()
+}
+
+/// Adds `binding` to `control`. Returns whether anything changed.
+pub fn bind(config: &mut Config, control: Control, binding: Binding) -> bool {
+    if bindings(config, control).contains(&binding) {
+        return false;
+    }
+    match binding {
+        Binding::Key(key) => {
+            let name = match config.get_int(control.config_name()) {
+                0 => control.config_name(),
+                _ => control.alt_config_name(),
+            };
+            config.set_int(name, key);
+        }
+        Binding::Mouse(button) => config.set_int(control.mouse_config_name(), button),
+        Binding::Joystick(button) => config.set_int(control.joystick_config_name(), button),
+    }
+    true
+}
+
+/// Unbinds everything from `control`.
+pub fn clear(config: &mut Config, control: Control) {
+    config.set_int(control.config_name(), 0);
+    config.set_int(control.alt_config_name(), 0);
+    config.set_int(control.mouse_config_name(), -1);
+    config.set_int(control.joystick_config_name(), -1);
+}
+
+/// The other controls sharing a binding with `control`, with the binding they share.
+pub fn conflicts(config: &Config, control: Control) -> Vec<(Binding, Control)> {
+    let own = bindings(config, control);
+    Control::ALL
+        .into_iter()
+        .filter(|&other| other != control)
+        .flat_map(|other| {
+            bindings(config, other)
+                .into_iter()
+                .filter(|binding| own.contains(binding))
+                .map(move |binding| (binding, other))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Configurable;
+    use crate::engine::controls::Controls;
+    use crate::engine::input::{KEY_RCTRL, KEY_UPARROW};
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        Controls::register(&mut config);
+        config
+    }
+
+    #[test]
+    fn a_control_takes_two_keys_and_a_button_of_each_kind() {
+        let mut config = config();
+        let forward = Control::Forward;
+        assert_eq!(
+            bindings(&config, forward),
+            [Binding::Key(KEY_UPARROW), Binding::Mouse(2)]
+        );
+        assert!(bind(&mut config, forward, Binding::Key(b'w' as Key)));
+        assert!(!bind(&mut config, forward, Binding::Key(b'w' as Key)));
+        assert_eq!(config.get_int("key_up_alt"), b'w' as Key);
+        // A third key replaces the second.
+        bind(&mut config, forward, Binding::Key(b'i' as Key));
+        bind(&mut config, forward, Binding::Joystick(6));
+        let names: Vec<_> = bindings(&config, forward)
+            .into_iter()
+            .map(Binding::name)
+            .collect();
+        assert_eq!(names, ["UP", "I", "MOUSE 3", "JOY 7"]);
+        let controls = Controls::from_config(&config);
+        assert_eq!(controls.alt_key(forward), b'i' as Key);
+        assert_eq!(controls.joystick_button(forward), 6);
+
+        clear(&mut config, forward);
+        assert!(bindings(&config, forward).is_empty());
+        bind(&mut config, forward, Binding::Key(b'w' as Key));
+        assert_eq!(
+            config.get_int("key_up"),
+            b'w' as Key,
+            "the first slot is free"
+        );
+    }
+
+    #[test]
+    fn shared_bindings_are_conflicts() {
+        let mut config = config();
+        assert!(conflicts(&config, Control::Fire).is_empty());
+        bind(&mut config, Control::Use, Binding::Key(KEY_RCTRL));
+        bind(&mut config, Control::Use, Binding::Mouse(0));
+        assert_eq!(
+            conflicts(&config, Control::Fire),
+            [
+                (Binding::Key(KEY_RCTRL), Control::Use),
+                (Binding::Mouse(0), Control::Use)
+            ]
+        );
+        assert_eq!(Binding::button(0b1100, false), Some(Binding::Mouse(2)));
+        assert_eq!(Binding::button(0, true), None);
+    }
+}
@@ -0,0 +1,345 @@
+//! `default.cfg` style configuration.
+//!
+//! The file is a list of `name value` lines, integers bare and strings in double quotes,
+//! exactly like vanilla writes it. Options vanilla doesn't know about go below an `[extended]`
+//! line so the top of the file stays readable by other ports. Every subsystem registers its
+//! options with their defaults; values read from the file override those defaults, and values
+//! changed at runtime (menus, console) are written back by `save`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const EXTENDED_HEADER: &str = "[extended]";
+
+/// Old option names and what they are called now. Applied when a file is loaded, so configs
+/// written by older versions keep their settings.
+const RENAMED: &[(&str, &str)] = &[("fullscreen", "video_fullscreen")];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Int(i32),
+    Str(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Vanilla,
+    Extended,
+}
+
+struct Definition {
+    name: String,
+    default: Value,
+    section: Section,
+}
+
+/// Implemented by subsystems that keep settings in the config file.
+pub trait Configurable: Sized {
+    /// Declares the options and their defaults.
+    fn register(config: &mut Config);
+    fn from_config(config: &Config) -> Self;
+    fn to_config(&self, config: &mut Config);
+}
+
+#[derive(Default)]
+pub struct Config {
+    definitions: Vec<Definition>,
+    values: BTreeMap<String, Value>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, name: &str, default: Value, section: Section) {
+        if self.definitions.iter().any(|def| def.name == name) {
+            return;
+        }
+        self.definitions.push(Definition {
+            name: name.to_string(),
+            default,
+            section,
+        });
+    }
+
+    pub fn register_int(&mut self, name: &str, default: i32, section: Section) {
+        self.register(name, Value::Int(default), section);
+    }
+
+    pub fn register_bool(&mut self, name: &str, default: bool, section: Section) {
+        self.register(name, Value::Int(default as i32), section);
+    }
+
+    pub fn register_str(&mut self, name: &str, default: &str, section: Section) {
+        self.register(name, Value::Str(default.to_string()), section);
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.definition(name).is_some()
+    }
+
+    /// The current value: set explicitly, read from the file, or the registered default.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values
+            .get(name)
+            .or_else(|| self.definition(name).map(|def| &def.default))
+    }
+
+    /// Falls back to the registered default if the stored value has the wrong type.
+    pub fn get_int(&self, name: &str) -> i32 {
+        match self.get(name) {
+            Some(Value::Int(value)) => *value,
+            _ => match self.definition(name).map(|def| &def.default) {
+                Some(Value::Int(value)) => *value,
+                _ => 0,
+            },
+        }
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        self.get_int(name) != 0
+    }
+
+    pub fn get_str(&self, name: &str) -> String {
+        match self.get(name) {
+            Some(Value::Str(value)) => value.clone(),
+            Some(Value::Int(value)) => value.to_string(),
+            None => String::new(),
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn set_int(&mut self, name: &str, value: i32) {
+        self.set(name, Value::Int(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.set(name, Value::Int(value as i32));
+    }
+
+    pub fn set_str(&mut self, name: &str, value: &str) {
+        self.set(name, Value::Str(value.to_string()));
+    }
+
+    /// Names of every registered option, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.definitions.iter().map(|def| def.name.as_str())
+    }
+
+    /// Reads `path`, keeping the registered defaults if it doesn't exist yet. The path is
+    /// remembered for `save` and `reload_if_changed`.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        self.path = Some(path.to_path_buf());
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                self.values = parse(&text);
+                self.modified = modified_time(path);
+                Ok(())
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Reloads the file if it changed on disk since it was last read or written, returning the
+    /// names of the options whose value changed.
+    pub fn reload_if_changed(&mut self) -> io::Result<Vec<String>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(Vec::new());
+        };
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(Vec::new());
+        }
+        let before: Vec<(String, Option<Value>)> = self
+            .definitions
+            .iter()
+            .map(|def| (def.name.clone(), self.get(&def.name).cloned()))
+            .collect();
+        self.load(&path)?;
+        Ok(before
+            .into_iter()
+            .filter(|(name, value)| self.get(name) != value.as_ref())
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Writes every option to the file it was loaded from.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self
+            .path
+            .clone()
+            .ok_or_else(|| io::Error::other("config has no file to save to"))?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&mut self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())?;
+        self.path = Some(path.to_path_buf());
+        self.modified = modified_time(path);
+        Ok(())
+    }
+
+    /// The file contents: vanilla options first, then the extended section. Options that were
+    /// in the file but aren't registered by anything are kept at the end.
+    pub fn serialize(&self) -> String {
+        let mut text = String::new();
+        for section in [Section::Vanilla, Section::Extended] {
+            if section == Section::Extended {
+                text.push_str(&format!("\n{EXTENDED_HEADER}\n"));
+            }
+            for def in self.definitions.iter().filter(|def| def.section == section) {
+                write_line(&mut text, &def.name, self.get(&def.name).unwrap());
+            }
+        }
+        for (name, value) in &self.values {
+            if !self.is_registered(name) {
+                write_line(&mut text, name, value);
+            }
+        }
+        text
+    }
+
+    fn definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|def| def.name == name)
+    }
+}
+
+fn write_line(text: &mut String, name: &str, value: &Value) {
+    match value {
+        Value::Int(value) => text.push_str(&format!("{name:<32}{value}\n")),
+        Value::Str(value) => text.push_str(&format!("{name:<32}\"{value}\"\n")),
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn parse(text: &str) -> BTreeMap<String, Value> {
+    let mut values = BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line == EXTENDED_HEADER {
+            continue;
+        }
+        let (name, raw) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, raw)| (name, raw.trim()));
+        let name = RENAMED
+            .iter()
+            .find(|(old, _)| *old == name)
+            .map_or(name, |(_, new)| new);
+        values.insert(name.to_string(), parse_value(raw));
+    }
+    values
+}
+
+fn parse_value(raw: &str) -> Value {
+    if let Some(quoted) = raw.strip_prefix('"') {
+        return Value::Str(quoted.strip_suffix('"').unwrap_or(quoted).to_string());
+    }
+    let number = match raw.strip_prefix("0x") {
+        Some(hex) => i32::from_str_radix(hex, 16).ok(),
+        None => raw.parse().ok(),
+    };
+    number.map_or_else(|| Value::Str(raw.to_string()), Value::Int)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("doom-rs-{name}-{}.cfg", std::process::id()))
+    }
+
+    fn registered() -> Config {
+        let mut config = Config::new();
+        config.register_int("mouse_sensitivity", 5, Section::Vanilla);
+        config.register_str("chatmacro0", "No", Section::Vanilla);
+        config.register_bool("video_fullscreen", false, Section::Extended);
+        config
+    }
+
+    #[test]
+    fn defaults_apply_until_overridden() {
+        let mut config = registered();
+        assert_eq!(config.get_int("mouse_sensitivity"), 5);
+        assert_eq!(config.get_str("chatmacro0"), "No");
+        config.set_int("mouse_sensitivity", 9);
+        assert_eq!(config.get_int("mouse_sensitivity"), 9);
+        // Wrong type falls back to the default.
+        config.set_str("mouse_sensitivity", "fast");
+        assert_eq!(config.get_int("mouse_sensitivity"), 5);
+    }
+
+    #[test]
+    fn parses_vanilla_and_extended_lines() {
+        let values = parse("mouse_sensitivity\t\t7\nchatmacro0\t\t\"I'm ready\"\n\n[extended]\nkey_hex 0x1d\nfullscreen 1\n");
+        assert_eq!(values["mouse_sensitivity"], Value::Int(7));
+        assert_eq!(values["chatmacro0"], Value::Str("I'm ready".into()));
+        assert_eq!(values["key_hex"], Value::Int(0x1d));
+        // Renamed option migrates to its new name.
+        assert_eq!(values["video_fullscreen"], Value::Int(1));
+    }
+
+    #[test]
+    fn save_groups_sections_and_keeps_unknown_options() {
+        let path = temp_path("save");
+        fs::write(&path, "from_a_newer_version 3\nmouse_sensitivity 2\n").unwrap();
+        let mut config = registered();
+        config.load(&path).unwrap();
+        config.set_bool("video_fullscreen", true);
+        config.save().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let extended = text.find(EXTENDED_HEADER).unwrap();
+        assert!(text.find("mouse_sensitivity").unwrap() < extended);
+        assert!(text.find("video_fullscreen").unwrap() > extended);
+        assert!(text.contains("from_a_newer_version"));
+        assert_eq!(parse(&text)["mouse_sensitivity"], Value::Int(2));
+        assert_eq!(parse(&text)["chatmacro0"], Value::Str("No".into()));
+    }
+
+    #[test]
+    fn missing_file_keeps_defaults() {
+        let mut config = registered();
+        config.load(Path::new("/nonexistent/default.cfg")).unwrap();
+        assert_eq!(config.get_int("mouse_sensitivity"), 5);
+    }
+
+    #[test]
+    fn reload_reports_changed_options() {
+        let path = temp_path("reload");
+        let mut config = registered();
+        config.load(&path).unwrap();
+        config.save().unwrap();
+        assert!(config.reload_if_changed().unwrap().is_empty());
+
+        // Make sure the modification time moves even on coarse grained file systems.
+        fs::write(&path, "mouse_sensitivity 8\n").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+
+        assert_eq!(
+            config.reload_if_changed().unwrap(),
+            vec!["mouse_sensitivity"]
+        );
+        assert_eq!(config.get_int("mouse_sensitivity"), 8);
+        fs::remove_file(&path).unwrap();
+    }
+}
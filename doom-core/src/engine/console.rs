@@ -0,0 +1,526 @@
+//! The drop-down developer console.
+//!
+//! Commands live in a `Commands` registry that subsystems share: the game registers `map` and
+//! `summon`, the cheats register `noclip` and `give`, the perf HUD registers `stat`, and so on,
+//! each with a closure that owns whatever state it needs. Every option registered with the
+//! config is also a cvar: typing its name prints the value, `name value` changes it.
+
+use super::config::{Config, Value};
+use super::input::{Event, Key, KEY_BACKSPACE, KEY_DOWNARROW, KEY_ENTER, KEY_ESCAPE, KEY_RSHIFT};
+use super::input::{KEY_TAB, KEY_UPARROW};
+use super::log;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+pub const KEY_CONSOLE: Key = b'`' as Key;
+
+const MAX_HISTORY: usize = 64;
+const MAX_SCROLLBACK: usize = 256;
+/// How far the console slides per tic, in lines of text.
+const SLIDE_SPEED: usize = 2;
+
+/// What a command gets to work with besides its arguments.
+pub struct CommandContext<'a> {
+    pub config: &'a mut Config,
+    output: &'a mut Vec<String>,
+}
+
+impl CommandContext<'_> {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+    }
+}
+
+/// A command handler. Errors are printed to the console.
+pub type Handler = Box<dyn FnMut(&mut CommandContext, &[&str]) -> Result<(), String>>;
+
+struct Command {
+    help: String,
+    handler: Handler,
+}
+
+#[derive(Default)]
+pub struct Commands {
+    commands: BTreeMap<String, Command>,
+}
+
+/// The registry as handed to subsystems.
+pub type SharedCommands = Rc<RefCell<Commands>>;
+
+impl Commands {
+    pub fn shared() -> SharedCommands {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    /// Adds a command, replacing any earlier one with the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        help: &str,
+        handler: impl FnMut(&mut CommandContext, &[&str]) -> Result<(), String> + 'static,
+    ) {
+        self.commands.insert(
+            name.to_ascii_lowercase(),
+            Command {
+                help: help.to_string(),
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(&name.to_ascii_lowercase());
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(&name.to_ascii_lowercase())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    /// Runs one line, which may hold several commands separated by `;`. Returns what they
+    /// printed.
+    pub fn execute(&mut self, line: &str, config: &mut Config) -> Vec<String> {
+        let mut output = Vec::new();
+        for statement in line.split(';') {
+            let words = tokenize(statement);
+            let Some((&name, args)) = words.split_first() else {
+                continue;
+            };
+            let mut context = CommandContext {
+                config,
+                output: &mut output,
+            };
+            self.run(&name.to_ascii_lowercase(), args, &mut context);
+        }
+        output
+    }
+
+    fn run(&mut self, name: &str, args: &[&str], context: &mut CommandContext) {
+        match name {
+            "help" | "cmdlist" => {
+                let lines: Vec<String> = self
+                    .commands
+                    .iter()
+                    .map(|(name, command)| format!("{name:<16}{}", command.help))
+                    .collect();
+                lines.into_iter().for_each(|line| context.print(line));
+                return;
+            }
+            "cvarlist" => {
+                let lines: Vec<String> = context
+                    .config
+                    .names()
+                    .map(|name| format!("{name:<32}{}", context.config.get_str(name)))
+                    .collect();
+                lines.into_iter().for_each(|line| context.print(line));
+                return;
+            }
+            "echo" => {
+                context.print(args.join(" "));
+                return;
+            }
+            _ => {}
+        }
+        if let Some(command) = self.commands.get_mut(name) {
+            if let Err(error) = (command.handler)(context, args) {
+                context.print(format!("{name}: {error}"));
+            }
+        } else if context.config.is_registered(name) {
+            cvar(name, args, context);
+        } else {
+            context.print(format!("unknown command '{name}'"));
+        }
+    }
+
+    /// Command and cvar names starting with `prefix`, sorted.
+    pub fn completions(&self, prefix: &str, config: &Config) -> Vec<String> {
+        let prefix = prefix.to_ascii_lowercase();
+        let mut names: Vec<String> = ["help", "cmdlist", "cvarlist", "echo"]
+            .into_iter()
+            .chain(self.names())
+            .chain(config.names())
+            .filter(|name| name.starts_with(&prefix))
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Prints or sets a cvar. The new value keeps the type of the registered one.
+fn cvar(name: &str, args: &[&str], context: &mut CommandContext) {
+    if args.is_empty() {
+        let value = context.config.get_str(name);
+        context.print(format!("\"{name}\" is \"{value}\""));
+        return;
+    }
+    let raw = args.join(" ");
+    match context.config.get(name) {
+        Some(Value::Int(_)) => match raw.parse() {
+            Ok(value) => context.config.set_int(name, value),
+            Err(_) => context.print(format!("{name}: '{raw}' is not a number")),
+        },
+        _ => context.config.set_str(name, &raw),
+    }
+}
+
+/// Splits a command on whitespace, keeping double quoted words together.
+pub(crate) fn tokenize(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let (word, tail) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            rest.split_at(end)
+        };
+        words.push(word);
+        rest = tail.trim_start();
+    }
+    words
+}
+
+/// What the shifted keys of a US layout type, since key codes are always unshifted.
+fn shifted(c: u8) -> u8 {
+    match c {
+        b'a'..=b'z' => c.to_ascii_uppercase(),
+        b'1' => b'!',
+        b'2' => b'@',
+        b'3' => b'#',
+        b'4' => b'$',
+        b'5' => b'%',
+        b'6' => b'^',
+        b'7' => b'&',
+        b'8' => b'*',
+        b'9' => b'(',
+        b'0' => b')',
+        b'-' => b'_',
+        b'=' => b'+',
+        b'[' => b'{',
+        b']' => b'}',
+        b';' => b':',
+        b'\'' => b'"',
+        b',' => b'<',
+        b'.' => b'>',
+        b'/' => b'?',
+        b'\\' => b'|',
+        _ => c,
+    }
+}
+
+pub struct Console {
+    commands: SharedCommands,
+    input: String,
+    history: Vec<String>,
+    history_pos: Option<usize>, // Entry being browsed with the arrow keys.
+    scrollback: Vec<String>,
+    open: bool,
+    height: usize, // Lines currently shown while sliding open or closed.
+    max_height: usize,
+    shift: bool,
+    log_seen: u64, // Log records already copied into the scrollback.
+}
+
+impl Console {
+    pub fn new(commands: SharedCommands, max_height: usize) -> Self {
+        Self {
+            commands,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            scrollback: Vec::new(),
+            open: false,
+            height: 0,
+            max_height,
+            shift: false,
+            log_seen: 0,
+        }
+    }
+
+    pub fn commands(&self) -> &SharedCommands {
+        &self.commands
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.history_pos = None;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.scrollback.push(line.into());
+        if self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.remove(0);
+        }
+    }
+
+    /// The newest `rows` lines of output, oldest first.
+    pub fn visible_lines(&self, rows: usize) -> &[String] {
+        &self.scrollback[self.scrollback.len().saturating_sub(rows)..]
+    }
+
+    /// Lines of screen the console covers this frame.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Slides the console towards open or closed and copies in new log messages.
+    pub fn ticker(&mut self) {
+        for record in log::since(&mut self.log_seen) {
+            self.print(record.to_string());
+        }
+        self.height = if self.open {
+            (self.height + SLIDE_SPEED).min(self.max_height)
+        } else {
+            self.height.saturating_sub(SLIDE_SPEED)
+        };
+    }
+
+    /// Runs a line as if it was typed, adding it to the history.
+    pub fn submit(&mut self, line: &str, config: &mut Config) {
+        self.print(format!("] {line}"));
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        let output = self.commands.borrow_mut().execute(line, config);
+        output.into_iter().for_each(|line| self.print(line));
+    }
+
+    /// Eats every key while open. Returns true if the event was used.
+    pub fn responder(&mut self, event: &Event, config: &mut Config) -> bool {
+        match *event {
+            Event::KeyDown(KEY_RSHIFT) => self.shift = true,
+            Event::KeyUp(KEY_RSHIFT) => self.shift = false,
+            _ => {}
+        }
+        let Event::KeyDown(key) = *event else {
+            return self.open && matches!(event, Event::KeyUp(_));
+        };
+        if key == KEY_CONSOLE {
+            self.toggle();
+            return true;
+        }
+        if !self.open {
+            return false;
+        }
+        match key {
+            KEY_ESCAPE => self.toggle(),
+            KEY_ENTER => {
+                let line = std::mem::take(&mut self.input);
+                self.history_pos = None;
+                self.submit(&line, config);
+            }
+            KEY_BACKSPACE => {
+                self.input.pop();
+            }
+            KEY_TAB => self.complete(config),
+            KEY_UPARROW => self.browse_history(-1),
+            KEY_DOWNARROW => self.browse_history(1),
+            0x20..=0x7e => {
+                let c = key as u8;
+                self.input
+                    .push(if self.shift { shifted(c) } else { c } as char);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn browse_history(&mut self, direction: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let pos = match (self.history_pos, direction) {
+            (None, -1) => Some(self.history.len() - 1),
+            (None, _) => None,
+            (Some(pos), -1) => Some(pos.saturating_sub(1)),
+            (Some(pos), _) => Some(pos + 1).filter(|&pos| pos < self.history.len()),
+        };
+        self.history_pos = pos;
+        self.input = pos.map_or_else(String::new, |pos| self.history[pos].clone());
+    }
+
+    /// Completes the command name being typed: fully if it's unambiguous, otherwise as far as
+    /// all candidates agree, listing them.
+    fn complete(&mut self, config: &Config) {
+        if self.input.contains(char::is_whitespace) {
+            return;
+        }
+        let candidates = self.commands.borrow().completions(&self.input, config);
+        match candidates.as_slice() {
+            [] => {}
+            [only] => self.input = format!("{only} "),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                if common > self.input.len() {
+                    self.input = first[..common].to_string();
+                } else {
+                    for name in candidates.clone() {
+                        self.print(format!("  {name}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Section;
+
+    fn setup() -> (Console, Config) {
+        let mut config = Config::new();
+        config.register_int("mouse_sensitivity", 5, Section::Vanilla);
+        config.register_str("screenshot_format", "png", Section::Extended);
+        let commands = Commands::shared();
+        commands
+            .borrow_mut()
+            .register("map", "map <name>: warp to a map", |context, args| {
+                let [name] = args else {
+                    return Err("usage: map <name>".to_string());
+                };
+                context.print(format!("warping to {}", name.to_ascii_uppercase()));
+                Ok(())
+            });
+        commands
+            .borrow_mut()
+            .register("mapinfo", "show the current map", |_, _| Ok(()));
+        (Console::new(commands, 20), config)
+    }
+
+    fn type_line(console: &mut Console, config: &mut Config, text: &str) {
+        for c in text.bytes() {
+            console.responder(&Event::KeyDown(c as Key), config);
+        }
+    }
+
+    #[test]
+    fn commands_and_cvars_execute() {
+        let (console, mut config) = setup();
+        let mut commands = console.commands().borrow_mut();
+        assert_eq!(
+            commands.execute("map map07", &mut config),
+            vec!["warping to MAP07"]
+        );
+        assert_eq!(
+            commands.execute("map", &mut config),
+            vec!["map: usage: map <name>"]
+        );
+        assert_eq!(
+            commands.execute("summon", &mut config),
+            vec!["unknown command 'summon'"]
+        );
+
+        commands.execute(
+            "mouse_sensitivity 9; screenshot_format \"pcx\"",
+            &mut config,
+        );
+        assert_eq!(config.get_int("mouse_sensitivity"), 9);
+        assert_eq!(config.get_str("screenshot_format"), "pcx");
+        assert_eq!(
+            commands.execute("mouse_sensitivity fast", &mut config),
+            vec!["mouse_sensitivity: 'fast' is not a number"]
+        );
+        assert_eq!(
+            commands.execute("mouse_sensitivity", &mut config),
+            vec!["\"mouse_sensitivity\" is \"9\""]
+        );
+    }
+
+    #[test]
+    fn typing_toggles_executes_and_remembers_history() {
+        let (mut console, mut config) = setup();
+        assert!(!console.responder(&Event::KeyDown(b'a' as Key), &mut config));
+        console.responder(&Event::KeyDown(KEY_CONSOLE), &mut config);
+        assert!(console.is_open());
+
+        type_line(&mut console, &mut config, "map e1m1");
+        console.responder(&Event::KeyDown(KEY_ENTER), &mut config);
+        assert_eq!(console.visible_lines(1), ["warping to E1M1"]);
+        type_line(&mut console, &mut config, "echo hi");
+        console.responder(&Event::KeyDown(KEY_ENTER), &mut config);
+
+        console.responder(&Event::KeyDown(KEY_UPARROW), &mut config);
+        console.responder(&Event::KeyDown(KEY_UPARROW), &mut config);
+        assert_eq!(console.input(), "map e1m1");
+        console.responder(&Event::KeyDown(KEY_DOWNARROW), &mut config);
+        assert_eq!(console.input(), "echo hi");
+        console.responder(&Event::KeyDown(KEY_DOWNARROW), &mut config);
+        assert_eq!(console.input(), "");
+    }
+
+    #[test]
+    fn tab_completes_commands_and_cvars() {
+        let (mut console, mut config) = setup();
+        console.toggle();
+        type_line(&mut console, &mut config, "mou");
+        console.responder(&Event::KeyDown(KEY_TAB), &mut config);
+        assert_eq!(console.input(), "mouse_sensitivity ");
+
+        console.input.clear();
+        type_line(&mut console, &mut config, "ma");
+        console.responder(&Event::KeyDown(KEY_TAB), &mut config);
+        assert_eq!(console.input(), "map");
+        // Still ambiguous, so the candidates are listed.
+        console.responder(&Event::KeyDown(KEY_TAB), &mut config);
+        assert_eq!(console.visible_lines(2), ["  map", "  mapinfo"]);
+    }
+
+    #[test]
+    fn slides_open_and_closed() {
+        let (mut console, _) = setup();
+        console.toggle();
+        for _ in 0..20 {
+            console.ticker();
+        }
+        assert_eq!(console.height(), 20);
+        console.toggle();
+        console.ticker();
+        assert_eq!(console.height(), 18);
+    }
+
+    #[test]
+    fn log_messages_reach_the_scrollback() {
+        let (mut console, _) = setup();
+        log::set_echo(false);
+        crate::warn!("test.console", "W_GetNumForName: FOO not found");
+        console.ticker();
+        assert!(console
+            .visible_lines(MAX_SCROLLBACK)
+            .iter()
+            .any(|line| line == "W_GetNumForName: FOO not found"));
+    }
+
+    #[test]
+    fn tokenizer_keeps_quoted_words() {
+        assert_eq!(
+            tokenize("  say \"hello there\" x "),
+            vec!["say", "hello there", "x"]
+        );
+    }
+}
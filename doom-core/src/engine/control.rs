@@ -0,0 +1,212 @@
+//! Driving the game from another program over a local socket, for bots and learning agents.
+//!
+//! The agent connects and the game waits for it: nothing happens between requests, so each
+//! tic takes exactly as long as the agent needs to decide, the way ViZDoom's synchronous
+//! mode works. The protocol is lines of text, with the frame and palette following their
+//! reply line as raw bytes:
+//!
+//! ```text
+//! tic <forward> <side> <turn> <buttons> [count]  -> ok <gametic>
+//! state                                           -> {"gametic": .., "player": .., ...}
+//! frame                                           -> frame <width> <height>, then the pixels
+//! palette                                         -> palette 768, then the RGB triples
+//! quit                                            -> bye
+//! ```
+//!
+//! `tic` runs `count` tics (default 1) with the same command, which is frame skipping. The
+//! commands take the place of the local keyboard entirely: the game never samples input of
+//! its own while an agent is connected.
+
+use super::Engine;
+use crate::game::ticcmd::TicCmd;
+use crate::renderer::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+
+/// The most tics one `tic` request may run.
+pub const MAX_REPEAT: u32 = 35 * 60;
+
+/// Serves one agent over `stream` until it sends `quit` or disconnects.
+pub fn serve(engine: &mut Engine, stream: impl Read + Write) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut last_cmd = TicCmd::default();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let out = reader.get_mut();
+        match words.as_slice() {
+            [] => continue,
+            ["tic", args @ ..] => match parse_tic(args) {
+                Ok((cmd, count)) => {
+                    for _ in 0..count {
+                        engine.run_tic(&[cmd]);
+                    }
+                    last_cmd = cmd;
+                    writeln!(out, "ok {}", engine.gametic())?;
+                }
+                Err(message) => writeln!(out, "error {message}")?,
+            },
+            ["state"] => writeln!(out, "{}", state_json(engine, &last_cmd))?,
+            ["frame"] => {
+                let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+                engine.render_into(&mut frame);
+                writeln!(out, "frame {SCREENWIDTH} {SCREENHEIGHT}")?;
+                out.write_all(&frame)?;
+            }
+            ["palette"] => {
+                let palette: Vec<u8> = (0..=255).flat_map(|i| engine.palette().rgb(i)).collect();
+                writeln!(out, "palette {}", palette.len())?;
+                out.write_all(&palette)?;
+            }
+            ["quit"] => {
+                writeln!(out, "bye")?;
+                return out.flush();
+            }
+            [command, ..] => writeln!(out, "error unknown request '{command}'")?,
+        }
+        out.flush()?;
+    }
+}
+
+fn parse_tic(args: &[&str]) -> Result<(TicCmd, u32), String> {
+    let [forward, side, turn, buttons, rest @ ..] = args else {
+        return Err("usage: tic <forward> <side> <turn> <buttons> [count]".to_string());
+    };
+    fn number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+        word.parse()
+            .map_err(|_| format!("'{word}' is out of range"))
+    }
+    let count = match rest {
+        [] => 1,
+        [count] => number(count)?,
+        _ => return Err("too many values".to_string()),
+    };
+    if !(1..=MAX_REPEAT).contains(&count) {
+        return Err(format!("count must be 1 to {MAX_REPEAT}"));
+    }
+    let cmd = TicCmd {
+        forwardmove: number(forward)?,
+        sidemove: number(side)?,
+        angleturn: number(turn)?,
+        buttons: number(buttons)?,
+        ..TicCmd::default()
+    };
+    Ok((cmd, count))
+}
+
+/// The observation besides the frame. The player and the enemies in view come from the
+/// level, so they stay `null` and empty until a level is running.
+fn state_json(engine: &Engine, last_cmd: &TicCmd) -> String {
+    format!(
+        "{{\"gametic\": {}, \"last_cmd\": [{}, {}, {}, {}], \"player\": null, \"enemies\": []}}",
+        engine.gametic(),
+        last_cmd.forwardmove,
+        last_cmd.sidemove,
+        last_cmd.angleturn,
+        last_cmd.buttons
+    )
+}
+
+/// Waits on `address` (like `127.0.0.1:5029`) for one agent and serves it.
+pub fn listen(engine: &mut Engine, address: impl ToSocketAddrs) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let (stream, _) = listener.accept()?;
+    stream.set_nodelay(true)?;
+    serve(engine, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::wad::file::Wads;
+    use std::io::Cursor;
+    use std::net::TcpStream;
+
+    /// A stream that reads the given requests and collects the replies.
+    struct Script {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Script {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(requests: &str) -> (Engine, Vec<u8>) {
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let mut script = Script {
+            input: Cursor::new(requests.as_bytes().to_vec()),
+            output: Vec::new(),
+        };
+        serve(&mut engine, &mut script).unwrap();
+        (engine, script.output)
+    }
+
+    #[test]
+    fn tics_run_in_lockstep_with_the_requests() {
+        let (engine, output) = run("tic 25 0 -640 1\n\ntic 50 0 0 0 4\nstate\nquit\ntic 1 1 1 1\n");
+        assert_eq!(engine.gametic(), 5);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "ok 1\nok 5\n\
+             {\"gametic\": 5, \"last_cmd\": [50, 0, 0, 0], \"player\": null, \"enemies\": []}\n\
+             bye\n"
+        );
+    }
+
+    #[test]
+    fn bad_requests_get_an_error_and_the_connection_stays_up() {
+        let (engine, output) = run("tic 200 0 0 0\ntic 1 0 0\ntic 1 0 0 0 0\njump\ntic 0 0 0 0\n");
+        assert_eq!(engine.gametic(), 1);
+        let text = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "error '200' is out of range");
+        assert!(lines[1].starts_with("error usage: tic"));
+        assert_eq!(lines[2], "error count must be 1 to 2100");
+        assert_eq!(lines[3], "error unknown request 'jump'");
+        assert_eq!(lines[4], "ok 1");
+    }
+
+    #[test]
+    fn frames_and_palettes_follow_their_header() {
+        let (_, output) = run("frame\npalette\n");
+        let header = b"frame 320 200\n";
+        assert!(output.starts_with(header));
+        let rest = &output[header.len() + SCREENWIDTH * SCREENHEIGHT..];
+        assert!(rest.starts_with(b"palette 768\n"));
+        assert_eq!(rest.len(), b"palette 768\n".len() + 768);
+    }
+
+    #[test]
+    fn agents_connect_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let agent = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"tic 0 0 0 0 3\nquit\n").unwrap();
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply).unwrap();
+            reply
+        });
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let (stream, _) = listener.accept().unwrap();
+        serve(&mut engine, stream).unwrap();
+        assert_eq!(agent.join().unwrap(), "ok 3\nbye\n");
+    }
+}
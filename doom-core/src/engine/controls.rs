@@ -0,0 +1,781 @@
+//! The keys that drive the player: movement, turning and the action buttons.
+//!
+//! Each `Control` keeps vanilla's config name, so `key_up` and friends read from an existing
+//! `default.cfg` unchanged. The fly keys are Heretic's and go in the extended section.
+//! `HeldKeys` tracks which keys are down from the event stream; whatever builds a tic's
+//! input asks it through `Controls`, so rebinding a control never touches the consumers.
+//! Every control can also have a second key (`key_up_alt` and so on) and a mouse and a
+//! joystick button, with vanilla's `mouseb_fire` and `joyb_speed` among them and the rest
+//! named alike. The next and previous weapon actions act once per press rather than while
+//! held. Frontends report the mouse wheel as buttons 3 (up) and 4 (down), which cycle the
+//! weapons by default.
+//!
+//! The quick turn, unbound by default, turns the player around. Half a turn is more than one
+//! ticcmd's `angleturn` holds, so it goes out as `QUICKTURN_TICS` equal steps, each a whole
+//! multiple of 256 so that 1.9 demos, which keep only the high byte, replay it exactly.
+//!
+//! Always run (`autorun`, toggled with Caps Lock) makes running the default and the run key
+//! walk instead. Strict mode leaves it alone: vanilla players get the same with the
+//! `joybspeed` trick, and turbo scales the running speed either way.
+
+use super::config::{Config, Configurable, Section};
+use super::input::*;
+use crate::game::ticcmd::{TicCmd, BT_ATTACK, BT_USE};
+use crate::game::weapons::WeaponChange;
+
+/// Walking and running speeds, as vanilla's `forwardmove`, `sidemove` and `angleturn`.
+const FORWARDMOVE: [i32; 2] = [0x19, 0x32];
+const SIDEMOVE: [i32; 2] = [0x18, 0x28];
+const ANGLETURN: [i16; 2] = [640, 1280];
+/// How many tics a quick turn takes.
+pub const QUICKTURN_TICS: u8 = 4;
+const QUICKTURN_STEP: i16 = (0x8000 / QUICKTURN_TICS as i32) as i16;
+
+/// The config options of each control: its key, second key, mouse button and joystick
+/// button.
+const CONFIG_NAMES: [[&str; 4]; 16] = [
+    ["key_up", "key_up_alt", "mouseb_forward", "joyb_forward"],
+    [
+        "key_down",
+        "key_down_alt",
+        "mouseb_backward",
+        "joyb_backward",
+    ],
+    [
+        "key_left",
+        "key_left_alt",
+        "mouseb_turnleft",
+        "joyb_turnleft",
+    ],
+    [
+        "key_right",
+        "key_right_alt",
+        "mouseb_turnright",
+        "joyb_turnright",
+    ],
+    [
+        "key_strafeleft",
+        "key_strafeleft_alt",
+        "mouseb_strafeleft",
+        "joyb_strafeleft",
+    ],
+    [
+        "key_straferight",
+        "key_straferight_alt",
+        "mouseb_straferight",
+        "joyb_straferight",
+    ],
+    ["key_speed", "key_speed_alt", "mouseb_speed", "joyb_speed"],
+    [
+        "key_strafe",
+        "key_strafe_alt",
+        "mouseb_strafe",
+        "joyb_strafe",
+    ],
+    ["key_fire", "key_fire_alt", "mouseb_fire", "joyb_fire"],
+    ["key_use", "key_use_alt", "mouseb_use", "joyb_use"],
+    ["key_flyup", "key_flyup_alt", "mouseb_flyup", "joyb_flyup"],
+    [
+        "key_flydown",
+        "key_flydown_alt",
+        "mouseb_flydown",
+        "joyb_flydown",
+    ],
+    [
+        "key_nextweapon",
+        "key_nextweapon_alt",
+        "mouseb_nextweapon",
+        "joyb_nextweapon",
+    ],
+    [
+        "key_prevweapon",
+        "key_prevweapon_alt",
+        "mouseb_prevweapon",
+        "joyb_prevweapon",
+    ],
+    [
+        "key_autorun",
+        "key_autorun_alt",
+        "mouseb_autorun",
+        "joyb_autorun",
+    ],
+    [
+        "key_quickturn",
+        "key_quickturn_alt",
+        "mouseb_quickturn",
+        "joyb_quickturn",
+    ],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Forward,
+    Back,
+    TurnLeft,
+    TurnRight,
+    StrafeLeft,
+    StrafeRight,
+    Speed,
+    Strafe,
+    Fire,
+    Use,
+    FlyUp,
+    FlyDown,
+    NextWeapon,
+    PrevWeapon,
+    AutoRun,
+    QuickTurn,
+}
+
+impl Control {
+    pub const ALL: [Control; 16] = [
+        Control::Forward,
+        Control::Back,
+        Control::TurnLeft,
+        Control::TurnRight,
+        Control::StrafeLeft,
+        Control::StrafeRight,
+        Control::Speed,
+        Control::Strafe,
+        Control::Fire,
+        Control::Use,
+        Control::FlyUp,
+        Control::FlyDown,
+        Control::NextWeapon,
+        Control::PrevWeapon,
+        Control::AutoRun,
+        Control::QuickTurn,
+    ];
+
+    pub fn config_name(self) -> &'static str {
+        CONFIG_NAMES[self as usize][0]
+    }
+
+    /// The second key's option.
+    pub fn alt_config_name(self) -> &'static str {
+        CONFIG_NAMES[self as usize][1]
+    }
+
+    pub fn mouse_config_name(self) -> &'static str {
+        CONFIG_NAMES[self as usize][2]
+    }
+
+    pub fn joystick_config_name(self) -> &'static str {
+        CONFIG_NAMES[self as usize][3]
+    }
+
+    /// The name shown in the key bindings menu.
+    pub fn label(self) -> &'static str {
+        match self {
+            Control::Forward => "MOVE FORWARD",
+            Control::Back => "MOVE BACKWARD",
+            Control::TurnLeft => "TURN LEFT",
+            Control::TurnRight => "TURN RIGHT",
+            Control::StrafeLeft => "STRAFE LEFT",
+            Control::StrafeRight => "STRAFE RIGHT",
+            Control::Speed => "RUN",
+            Control::Strafe => "STRAFE ON",
+            Control::Fire => "FIRE",
+            Control::Use => "USE",
+            Control::FlyUp => "FLY UP",
+            Control::FlyDown => "FLY DOWN",
+            Control::NextWeapon => "NEXT WEAPON",
+            Control::PrevWeapon => "PREVIOUS WEAPON",
+            Control::AutoRun => "TOGGLE AUTORUN",
+            Control::QuickTurn => "QUICK TURN",
+        }
+    }
+
+    pub fn default_key(self) -> Key {
+        match self {
+            Control::Forward => KEY_UPARROW,
+            Control::Back => KEY_DOWNARROW,
+            Control::TurnLeft => KEY_LEFTARROW,
+            Control::TurnRight => KEY_RIGHTARROW,
+            Control::StrafeLeft => b',' as Key,
+            Control::StrafeRight => b'.' as Key,
+            Control::Speed => KEY_RSHIFT,
+            Control::Strafe => KEY_RALT,
+            Control::Fire => KEY_RCTRL,
+            Control::Use => b' ' as Key,
+            Control::FlyUp => KEY_PGUP,
+            Control::FlyDown => KEY_PGDN,
+            // Unbound: 0 is no key.
+            Control::NextWeapon | Control::PrevWeapon | Control::QuickTurn => 0,
+            Control::AutoRun => KEY_CAPSLOCK,
+        }
+    }
+
+    /// Vanilla's mouse buttons: fire, strafe and forward on the left, right and middle.
+    pub fn default_mouse_button(self) -> i32 {
+        match self {
+            Control::Fire => 0,
+            Control::Strafe => 1,
+            Control::Forward => 2,
+            Control::PrevWeapon => 3,
+            Control::NextWeapon => 4,
+            _ => -1,
+        }
+    }
+
+    /// Vanilla's joystick buttons.
+    pub fn default_joystick_button(self) -> i32 {
+        match self {
+            Control::Fire => 0,
+            Control::Strafe => 1,
+            Control::Speed => 2,
+            Control::Use => 3,
+            _ => -1,
+        }
+    }
+
+    /// Where the buttons go: vanilla's own in the vanilla section.
+    fn button_sections(self) -> (Section, Section) {
+        let vanilla = |vanilla: bool| match vanilla {
+            true => Section::Vanilla,
+            false => Section::Extended,
+        };
+        (
+            vanilla(matches!(
+                self,
+                Control::Fire | Control::Strafe | Control::Forward
+            )),
+            vanilla(matches!(
+                self,
+                Control::Fire | Control::Strafe | Control::Speed | Control::Use
+            )),
+        )
+    }
+
+    fn section(self) -> Section {
+        match self {
+            Control::FlyUp | Control::FlyDown => Section::Extended,
+            Control::NextWeapon | Control::PrevWeapon => Section::Extended,
+            Control::AutoRun | Control::QuickTurn => Section::Extended,
+            _ => Section::Vanilla,
+        }
+    }
+}
+
+/// Which keys and buttons are down right now, and which went down since the last tic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeldKeys {
+    down: [bool; 256],
+    pressed: [bool; 256],
+    mouse: i32,
+    joystick: i32,
+    mouse_pressed: i32,
+    joystick_pressed: i32,
+}
+
+impl Default for HeldKeys {
+    fn default() -> Self {
+        Self {
+            down: [false; 256],
+            pressed: [false; 256],
+            mouse: 0,
+            joystick: 0,
+            mouse_pressed: 0,
+            joystick_pressed: 0,
+        }
+    }
+}
+
+impl HeldKeys {
+    pub fn responder(&mut self, event: &Event) {
+        match *event {
+            Event::KeyDown(key) => {
+                // Key repeat sends more downs without ups, which aren't new presses.
+                if !self.is_down(key) {
+                    self.set_pressed(key);
+                }
+                self.set(key, true);
+            }
+            Event::KeyUp(key) => self.set(key, false),
+            Event::Mouse { buttons, .. } => {
+                self.mouse_pressed |= buttons & !self.mouse;
+                self.mouse = buttons;
+            }
+            Event::Joystick { buttons, .. } => {
+                self.joystick_pressed |= buttons & !self.joystick;
+                self.joystick = buttons;
+            }
+        }
+    }
+
+    /// Whether `key` went down since the last `end_tic`.
+    pub fn was_pressed(&self, key: Key) -> bool {
+        usize::try_from(key)
+            .ok()
+            .and_then(|key| self.pressed.get(key))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Whether mouse button `button` is held. Negative numbers are no button.
+    pub fn mouse_down(&self, button: i32) -> bool {
+        (0..32).contains(&button) && self.mouse & 1 << button != 0
+    }
+
+    pub fn joystick_down(&self, button: i32) -> bool {
+        (0..32).contains(&button) && self.joystick & 1 << button != 0
+    }
+
+    /// Whether mouse button `button` went down since the last `end_tic`. Negative numbers are
+    /// no button.
+    pub fn mouse_pressed(&self, button: i32) -> bool {
+        (0..32).contains(&button) && self.mouse_pressed & 1 << button != 0
+    }
+
+    pub fn joystick_pressed(&self, button: i32) -> bool {
+        (0..32).contains(&button) && self.joystick_pressed & 1 << button != 0
+    }
+
+    /// Forgets the presses once a tic's input has been built from them.
+    pub fn end_tic(&mut self) {
+        self.pressed = [false; 256];
+        self.mouse_pressed = 0;
+        self.joystick_pressed = 0;
+    }
+
+    pub fn is_down(&self, key: Key) -> bool {
+        usize::try_from(key)
+            .ok()
+            .and_then(|key| self.down.get(key))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Lets go of everything, for when the window loses focus and the key ups never arrive.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn set_pressed(&mut self, key: Key) {
+        if let Some(pressed) = usize::try_from(key)
+            .ok()
+            .and_then(|key| self.pressed.get_mut(key))
+        {
+            *pressed = true;
+        }
+    }
+
+    fn set(&mut self, key: Key, down: bool) {
+        if let Some(held) = usize::try_from(key)
+            .ok()
+            .and_then(|key| self.down.get_mut(key))
+        {
+            *held = down;
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Controls {
+    keys: [Key; Control::ALL.len()],
+    /// Second keys, 0 for none.
+    alt_keys: [Key; Control::ALL.len()],
+    /// Mouse and joystick buttons, -1 for none.
+    mouse_buttons: [i32; Control::ALL.len()],
+    joystick_buttons: [i32; Control::ALL.len()],
+    turbo: i32,
+    pub always_run: bool,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            keys: Control::ALL.map(Control::default_key),
+            alt_keys: [0; Control::ALL.len()],
+            mouse_buttons: Control::ALL.map(Control::default_mouse_button),
+            joystick_buttons: Control::ALL.map(Control::default_joystick_button),
+            turbo: 100,
+            always_run: false,
+        }
+    }
+}
+
+impl Controls {
+    pub fn key(&self, control: Control) -> Key {
+        self.keys[control as usize]
+    }
+
+    pub fn bind(&mut self, control: Control, key: Key) {
+        self.keys[control as usize] = key;
+    }
+
+    pub fn alt_key(&self, control: Control) -> Key {
+        self.alt_keys[control as usize]
+    }
+
+    pub fn bind_alt(&mut self, control: Control, key: Key) {
+        self.alt_keys[control as usize] = key;
+    }
+
+    pub fn mouse_button(&self, control: Control) -> i32 {
+        self.mouse_buttons[control as usize]
+    }
+
+    pub fn bind_mouse(&mut self, control: Control, button: i32) {
+        self.mouse_buttons[control as usize] = button;
+    }
+
+    pub fn joystick_button(&self, control: Control) -> i32 {
+        self.joystick_buttons[control as usize]
+    }
+
+    pub fn bind_joystick(&mut self, control: Control, button: i32) {
+        self.joystick_buttons[control as usize] = button;
+    }
+
+    /// `-turbo`: scales the walking and running speeds, in percent. Vanilla let the running
+    /// speed past 127 wrap around to walking backwards; here it stops at the most a ticcmd
+    /// holds.
+    pub fn set_turbo(&mut self, percent: u32) {
+        self.turbo = percent as i32;
+    }
+
+    pub fn held(&self, control: Control, keys: &HeldKeys) -> bool {
+        keys.is_down(self.key(control))
+            || keys.is_down(self.alt_key(control))
+            || keys.mouse_down(self.mouse_button(control))
+            || keys.joystick_down(self.joystick_button(control))
+    }
+
+    /// Flips always run if its key went down since the last tic. Returns whether it did.
+    pub fn toggle_autorun(&mut self, keys: &HeldKeys) -> bool {
+        let toggle = self.pressed(Control::AutoRun, keys);
+        self.always_run ^= toggle;
+        toggle
+    }
+
+    /// Whether a key or button of `control` went down since the last tic.
+    pub fn pressed(&self, control: Control, keys: &HeldKeys) -> bool {
+        keys.was_pressed(self.key(control))
+            || keys.was_pressed(self.alt_key(control))
+            || keys.mouse_pressed(self.mouse_button(control))
+            || keys.joystick_pressed(self.joystick_button(control))
+    }
+
+    /// The weapon cycling asked for since the last tic, if any. Asking both ways cancels out.
+    fn weapon_change(&self, keys: &HeldKeys) -> Option<WeaponChange> {
+        let next = self.pressed(Control::NextWeapon, keys);
+        let prev = self.pressed(Control::PrevWeapon, keys);
+        match (next, prev) {
+            (true, false) => Some(WeaponChange::Next),
+            (false, true) => Some(WeaponChange::Previous),
+            _ => None,
+        }
+    }
+
+    /// Whether a quick turn was asked for since the last tic.
+    pub fn quick_turn(&self, keys: &HeldKeys) -> bool {
+        self.pressed(Control::QuickTurn, keys)
+    }
+
+    /// 1, -1 or 0 for a pair of opposing controls, cancelling out when both are held.
+    pub fn axis(&self, positive: Control, negative: Control, keys: &HeldKeys) -> i32 {
+        self.held(positive, keys) as i32 - self.held(negative, keys) as i32
+    }
+
+    /// The keyboard half of vanilla's `G_BuildTiccmd`: movement, turning (or strafing while
+    /// the strafe key is held), the fire and use buttons and weapon cycling. The slow start
+    /// when turning and mouse and joystick movement are left to the caller.
+    pub fn build_ticcmd(&self, keys: &HeldKeys) -> TicCmd {
+        let speed = (self.held(Control::Speed, keys) != self.always_run) as usize;
+        let scale = |moves: [i32; 2]| moves.map(|speed| speed * self.turbo / 100);
+        let (forwardmove, sidemove) = (scale(FORWARDMOVE), scale(SIDEMOVE));
+        let turn = self.axis(Control::TurnLeft, Control::TurnRight, keys);
+        let mut side = self.axis(Control::StrafeRight, Control::StrafeLeft, keys) * sidemove[speed];
+        let mut angleturn = 0;
+        if self.held(Control::Strafe, keys) {
+            side -= turn * sidemove[speed];
+        } else {
+            angleturn = turn as i16 * ANGLETURN[speed];
+        }
+        let forward = self.axis(Control::Forward, Control::Back, keys) * forwardmove[speed];
+        let max = forwardmove[1].min(i8::MAX.into());
+        let mut buttons = if self.held(Control::Fire, keys) {
+            BT_ATTACK
+        } else {
+            0
+        } | if self.held(Control::Use, keys) {
+            BT_USE
+        } else {
+            0
+        };
+        if let Some(change) = self.weapon_change(keys) {
+            buttons |= change.encode();
+        }
+        TicCmd {
+            forwardmove: forward.clamp(-max, max) as i8,
+            sidemove: side.clamp(-max, max) as i8,
+            angleturn,
+            buttons,
+            ..TicCmd::default()
+        }
+    }
+}
+
+impl Configurable for Controls {
+    fn register(config: &mut Config) {
+        for control in Control::ALL {
+            config.register_int(
+                control.config_name(),
+                control.default_key(),
+                control.section(),
+            );
+        }
+        for control in Control::ALL {
+            let (mouse, joystick) = control.button_sections();
+            config.register_int(control.alt_config_name(), 0, Section::Extended);
+            config.register_int(
+                control.mouse_config_name(),
+                control.default_mouse_button(),
+                mouse,
+            );
+            config.register_int(
+                control.joystick_config_name(),
+                control.default_joystick_button(),
+                joystick,
+            );
+        }
+        let defaults = Self::default();
+        config.register_bool("autorun", defaults.always_run, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let get = |name: fn(Control) -> &'static str| {
+            Control::ALL.map(|control| config.get_int(name(control)))
+        };
+        Self {
+            keys: get(Control::config_name),
+            alt_keys: get(Control::alt_config_name),
+            mouse_buttons: get(Control::mouse_config_name),
+            joystick_buttons: get(Control::joystick_config_name),
+            always_run: config.get_bool("autorun"),
+            ..Self::default()
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        for control in Control::ALL {
+            config.set_int(control.config_name(), self.key(control));
+            config.set_int(control.alt_config_name(), self.alt_key(control));
+            config.set_int(control.mouse_config_name(), self.mouse_button(control));
+            config.set_int(
+                control.joystick_config_name(),
+                self.joystick_button(control),
+            );
+        }
+        config.set_bool("autorun", self.always_run);
+    }
+}
+
+/// A quick turn under way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuickTurn {
+    remaining: u8,
+}
+
+impl QuickTurn {
+    pub fn turning(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// Starts a turn if `start` and none is under way, and returns what to add to this
+    /// tic's `angleturn`.
+    pub fn tic(&mut self, start: bool) -> i16 {
+        if start && !self.turning() {
+            self.remaining = QUICKTURN_TICS;
+        }
+        if !self.turning() {
+            return 0;
+        }
+        self.remaining -= 1;
+        QUICKTURN_STEP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_keys_follow_the_events_through_the_bindings() {
+        let mut controls = Controls::default();
+        let mut keys = HeldKeys::default();
+        keys.responder(&Event::KeyDown(KEY_UPARROW));
+        keys.responder(&Event::KeyDown(b'.' as Key));
+        assert_eq!(controls.axis(Control::Forward, Control::Back, &keys), 1);
+        assert_eq!(
+            controls.axis(Control::StrafeRight, Control::StrafeLeft, &keys),
+            1
+        );
+        keys.responder(&Event::KeyDown(KEY_DOWNARROW));
+        assert_eq!(controls.axis(Control::Forward, Control::Back, &keys), 0);
+
+        controls.bind(Control::Forward, b'w' as Key);
+        keys.responder(&Event::KeyUp(KEY_DOWNARROW));
+        assert!(!controls.held(Control::Forward, &keys));
+        keys.responder(&Event::KeyDown(-5));
+        keys.clear();
+        assert!(!keys.is_down(KEY_UPARROW));
+    }
+
+    #[test]
+    fn ticcmds_are_built_like_vanilla() {
+        let controls = Controls::default();
+        let mut keys = HeldKeys::default();
+        let press = |keys: &mut HeldKeys, key: Key| keys.responder(&Event::KeyDown(key));
+        press(&mut keys, KEY_UPARROW);
+        press(&mut keys, KEY_LEFTARROW);
+        press(&mut keys, KEY_RCTRL);
+        let cmd = controls.build_ticcmd(&keys);
+        assert_eq!(
+            (cmd.forwardmove, cmd.sidemove, cmd.angleturn),
+            (0x19, 0, 640)
+        );
+        assert_eq!(cmd.buttons, BT_ATTACK);
+
+        press(&mut keys, KEY_RSHIFT);
+        press(&mut keys, KEY_RALT);
+        press(&mut keys, b',' as Key);
+        let cmd = controls.build_ticcmd(&keys);
+        // Strafing left with both the strafe key and the strafe-left key caps at 0x32.
+        assert_eq!(
+            (cmd.forwardmove, cmd.sidemove, cmd.angleturn),
+            (0x32, -0x32, 0)
+        );
+
+        let mut turbo = controls.clone();
+        turbo.set_turbo(200);
+        let cmd = turbo.build_ticcmd(&keys);
+        assert_eq!((cmd.forwardmove, cmd.sidemove), (0x64, -0x64));
+        turbo.set_turbo(400);
+        let cmd = turbo.build_ticcmd(&keys);
+        assert_eq!((cmd.forwardmove, cmd.sidemove), (127, -127));
+    }
+
+    #[test]
+    fn always_run_swaps_the_run_key() {
+        let mut controls = Controls::default();
+        let mut keys = HeldKeys::default();
+        keys.responder(&Event::KeyDown(KEY_UPARROW));
+        keys.responder(&Event::KeyDown(KEY_CAPSLOCK));
+        assert!(controls.toggle_autorun(&keys));
+        assert_eq!(controls.build_ticcmd(&keys).forwardmove, 0x32);
+        keys.end_tic();
+        assert!(!controls.toggle_autorun(&keys));
+        keys.responder(&Event::KeyDown(KEY_RSHIFT));
+        assert_eq!(controls.build_ticcmd(&keys).forwardmove, 0x19);
+        keys.responder(&Event::KeyUp(KEY_RSHIFT));
+        controls.set_turbo(200);
+        assert_eq!(controls.build_ticcmd(&keys).forwardmove, 0x64);
+    }
+
+    #[test]
+    fn the_quick_turn_is_half_a_turn_over_a_few_tics() {
+        let mut controls = Controls::default();
+        controls.bind(Control::QuickTurn, b'q' as Key);
+        controls.bind_joystick(Control::QuickTurn, 2);
+        let mut keys = HeldKeys::default();
+        let mut turn = QuickTurn::default();
+        keys.responder(&Event::KeyDown(b'q' as Key));
+        let mut total = 0u16;
+        for _ in 0..QUICKTURN_TICS {
+            let step = turn.tic(controls.quick_turn(&keys));
+            assert_eq!(step & 0xff, 0, "a 1.9 demo keeps it");
+            total = total.wrapping_add(step as u16);
+            keys.end_tic();
+        }
+        assert_eq!(total, 0x8000);
+        assert!(!turn.turning());
+        assert_eq!(
+            turn.tic(controls.quick_turn(&keys)),
+            0,
+            "held, it doesn't go again"
+        );
+
+        keys.responder(&Event::Joystick {
+            buttons: 1 << 2,
+            x: 0,
+            y: 0,
+        });
+        assert!(turn.tic(controls.quick_turn(&keys)) != 0);
+        // Pressing again mid-turn doesn't stretch it.
+        assert!((1..QUICKTURN_TICS).all(|_| turn.tic(true) != 0));
+        assert!(!turn.turning());
+    }
+
+    #[test]
+    fn weapon_cycling_acts_once_per_press() {
+        let mut controls = Controls::default();
+        controls.bind(Control::NextWeapon, b'e' as Key);
+        controls.bind_joystick(Control::PrevWeapon, 5);
+        let mut keys = HeldKeys::default();
+        let change = |keys: &HeldKeys| WeaponChange::decode(controls.build_ticcmd(keys).buttons);
+        keys.responder(&Event::KeyDown(b'e' as Key));
+        assert_eq!(change(&keys), Some(WeaponChange::Next));
+        keys.end_tic();
+        // Held, or repeated by the keyboard, it doesn't cycle again.
+        keys.responder(&Event::KeyDown(b'e' as Key));
+        assert_eq!(change(&keys), None);
+
+        let wheel_up = Event::Mouse {
+            buttons: 1 << 3,
+            dx: 0,
+            dy: 0,
+        };
+        keys.responder(&wheel_up);
+        assert_eq!(change(&keys), Some(WeaponChange::Previous));
+        keys.end_tic();
+        keys.responder(&Event::Joystick {
+            buttons: 1 << 5,
+            x: 0,
+            y: 0,
+        });
+        keys.responder(&Event::KeyUp(b'e' as Key));
+        keys.responder(&Event::KeyDown(b'e' as Key));
+        assert_eq!(change(&keys), None);
+    }
+
+    #[test]
+    fn second_keys_and_buttons_drive_a_control_too() {
+        let mut controls = Controls::default();
+        controls.bind_alt(Control::Forward, b'w' as Key);
+        let mut keys = HeldKeys::default();
+        keys.responder(&Event::KeyDown(b'w' as Key));
+        assert_eq!(controls.build_ticcmd(&keys).forwardmove, 0x19);
+        keys.responder(&Event::KeyUp(b'w' as Key));
+        keys.responder(&Event::Mouse {
+            buttons: 1,
+            dx: 0,
+            dy: 0,
+        });
+        assert_eq!(
+            controls.build_ticcmd(&keys).buttons,
+            BT_ATTACK,
+            "vanilla's mouse fire"
+        );
+        keys.responder(&Event::Joystick {
+            buttons: 1 << 3,
+            x: 0,
+            y: 0,
+        });
+        assert!(controls.held(Control::Use, &keys));
+    }
+
+    #[test]
+    fn bindings_keep_the_vanilla_names() {
+        let mut config = Config::new();
+        Controls::register(&mut config);
+        assert_eq!(config.get_int("key_up"), KEY_UPARROW);
+        assert!(config.serialize().find("key_flyup") > config.serialize().find("[extended]"));
+        config.set_int("key_up", b'w' as Key);
+        assert_eq!(
+            Controls::from_config(&config).key(Control::Forward),
+            b'w' as Key
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Crash reports: a panic hook that writes down what the game was doing.
+//!
+//! The game loop keeps a `GameSnapshot` up to date as it runs: the map, the tic, where the
+//! console player is, the compatibility level, the WADs and the last second of ticcmds. When
+//! anything panics, the hook writes a report with the panic message, a backtrace and that
+//! snapshot to `crash-YYYYMMDD-HHMMSS.txt`. If rewinding is on, the newest keyframe is also
+//! written next to it as an emergency savegame. The ticcmds and the WAD list together are
+//! usually enough to get a desync or renderer crash to happen again.
+//!
+//! The hook only ever `try_lock`s the snapshot. A panic while the game thread is updating it
+//! still gets a report, just without the game state.
+
+use super::config::{Config, Configurable, Section};
+use crate::game::compatibility::CompLevel;
+use crate::game::rewind::Keyframe;
+use crate::game::ticcmd::TicCmd;
+use crate::game::TICRATE;
+use crate::util::angle::Angle;
+use crate::util::fixed::{Fixed, FRACUNIT};
+use crate::util::time::timestamp;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::{Mutex, TryLockError};
+use std::time::SystemTime;
+
+/// Ticcmds kept for the report: one second.
+pub const TICCMD_HISTORY: usize = TICRATE as usize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlayerPosition {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+    pub angle: Angle,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameSnapshot {
+    pub map: String,
+    pub gametic: i32,
+    pub player: Option<PlayerPosition>,
+    pub complevel: CompLevel,
+    pub wads: Vec<PathBuf>,
+    pub ticcmds: VecDeque<(i32, TicCmd)>,
+    pub keyframe: Option<Keyframe>,
+}
+
+impl GameSnapshot {
+    /// Called once per game tic with the console player's command.
+    pub fn record_tic(&mut self, gametic: i32, cmd: TicCmd) {
+        self.gametic = gametic;
+        if self.ticcmds.len() == TICCMD_HISTORY {
+            self.ticcmds.pop_front();
+        }
+        self.ticcmds.push_back((gametic, cmd));
+    }
+
+    /// The game state part of the report.
+    pub fn describe(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "map: {}", self.map);
+        let _ = writeln!(out, "gametic: {}", self.gametic);
+        if let Some(p) = self.player {
+            let unit = |value: Fixed| f64::from(value) / f64::from(FRACUNIT);
+            let degrees = f64::from(p.angle) * 360.0 / 4_294_967_296.0;
+            let _ = writeln!(
+                out,
+                "player: x {:.3} y {:.3} z {:.3} angle {degrees:.1}",
+                unit(p.x),
+                unit(p.y),
+                unit(p.z)
+            );
+        }
+        let _ = writeln!(out, "complevel: {:?}", self.complevel);
+        let _ = writeln!(out, "wads:");
+        for wad in &self.wads {
+            let _ = writeln!(out, "  {}", wad.display());
+        }
+        let _ = writeln!(out, "last ticcmds (tic: forward side turn buttons):");
+        for (tic, cmd) in &self.ticcmds {
+            let _ = writeln!(
+                out,
+                "  {tic}: {} {} {} {:#04x}",
+                cmd.forwardmove, cmd.sidemove, cmd.angleturn, cmd.buttons
+            );
+        }
+        out
+    }
+}
+
+static SNAPSHOT: Mutex<Option<GameSnapshot>> = Mutex::new(None);
+
+/// Runs `f` on the snapshot the crash report is written from.
+pub fn update(f: impl FnOnce(&mut GameSnapshot)) {
+    let mut snapshot = SNAPSHOT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(snapshot.get_or_insert_with(GameSnapshot::default));
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrashOptions {
+    pub folder: PathBuf,
+    pub emergency_save: bool,
+}
+
+impl Default for CrashOptions {
+    fn default() -> Self {
+        Self {
+            folder: PathBuf::from("crashes"),
+            emergency_save: true,
+        }
+    }
+}
+
+impl Configurable for CrashOptions {
+    fn register(config: &mut Config) {
+        config.register_str("crash_folder", "crashes", Section::Extended);
+        config.register_bool("crash_emergency_save", true, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            folder: PathBuf::from(config.get_str("crash_folder")),
+            emergency_save: config.get_bool("crash_emergency_save"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("crash_folder", &self.folder.to_string_lossy());
+        config.set_bool("crash_emergency_save", self.emergency_save);
+    }
+}
+
+/// The whole report, as written to the file.
+pub fn report(message: &str, snapshot: Option<&GameSnapshot>, backtrace: &str) -> String {
+    let mut out = format!(
+        "doom-rs {} crashed: {message}\n\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    match snapshot {
+        Some(snapshot) => out.push_str(&snapshot.describe()),
+        None => out.push_str("game state unavailable\n"),
+    }
+    let _ = write!(out, "\nbacktrace:\n{backtrace}\n");
+    out
+}
+
+/// Writes the report, and the emergency savegame if there is one, returning the report's path.
+pub fn write(
+    options: &CrashOptions,
+    stamp: &str,
+    report: &str,
+    save: Option<&[u8]>,
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(&options.folder)?;
+    let path = options.folder.join(format!("crash-{stamp}.txt"));
+    fs::write(&path, report)?;
+    if let Some(save) = save.filter(|_| options.emergency_save) {
+        fs::write(options.folder.join(format!("crash-{stamp}.dsg")), save)?;
+    }
+    Ok(path)
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let text = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    match info.location() {
+        Some(location) => format!("{text} at {location}"),
+        None => text.to_string(),
+    }
+}
+
+/// Replaces the panic hook. The default hook still runs first, so the message also reaches
+/// the terminal.
+pub fn install(options: CrashOptions) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let guard = match SNAPSHOT.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        let snapshot = guard.as_ref().and_then(|guard| guard.as_ref());
+        let backtrace = Backtrace::force_capture().to_string();
+        let text = report(&panic_message(info), snapshot, &backtrace);
+        let save = snapshot
+            .and_then(|snapshot| snapshot.keyframe.as_ref())
+            .map(|keyframe| keyframe.data.as_slice());
+        match write(&options, &timestamp(SystemTime::now()), &text, save) {
+            Ok(path) => eprintln!("doom-rs: crash report written to {}", path.display()),
+            Err(error) => eprintln!("doom-rs: couldn't write crash report: {error}"),
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::angle::ANG90;
+
+    #[test]
+    fn only_the_last_second_of_ticcmds_is_kept() {
+        let mut snapshot = GameSnapshot::default();
+        for tic in 0..100 {
+            let cmd = TicCmd {
+                forwardmove: tic as i8,
+                ..TicCmd::default()
+            };
+            snapshot.record_tic(tic, cmd);
+        }
+        assert_eq!(snapshot.gametic, 99);
+        assert_eq!(snapshot.ticcmds.len(), TICCMD_HISTORY);
+        assert_eq!(snapshot.ticcmds.front().unwrap().0, 100 - TICRATE);
+    }
+
+    #[test]
+    fn reports_describe_the_game() {
+        let mut snapshot = GameSnapshot {
+            map: "MAP01".to_string(),
+            player: Some(PlayerPosition {
+                x: -32 * FRACUNIT,
+                y: FRACUNIT / 2,
+                z: 0,
+                angle: ANG90,
+            }),
+            complevel: CompLevel::Boom,
+            wads: vec![PathBuf::from("doom2.wad"), PathBuf::from("scythe.wad")],
+            ..GameSnapshot::default()
+        };
+        let cmd = TicCmd {
+            forwardmove: 50,
+            sidemove: -24,
+            angleturn: 640,
+            buttons: 1,
+            ..TicCmd::default()
+        };
+        snapshot.record_tic(1234, cmd);
+        let text = report("oops at src/main.rs:1:1", Some(&snapshot), "<frames>");
+        assert!(text.contains("crashed: oops at src/main.rs:1:1"));
+        assert!(text.contains("map: MAP01\ngametic: 1234\n"));
+        assert!(text.contains("player: x -32.000 y 0.500 z 0.000 angle 90.0\n"));
+        assert!(text.contains("complevel: Boom\nwads:\n  doom2.wad\n  scythe.wad\n"));
+        assert!(text.contains("  1234: 50 -24 640 0x01\n"));
+        assert!(text.ends_with("backtrace:\n<frames>\n"));
+        assert!(report("oops", None, "").contains("game state unavailable"));
+    }
+
+    #[test]
+    fn the_emergency_save_is_optional() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-crash-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let mut options = CrashOptions {
+            folder: folder.clone(),
+            emergency_save: false,
+        };
+        let path = write(&options, "a", "report", Some(b"save")).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "report");
+        assert!(!folder.join("crash-a.dsg").exists());
+        options.emergency_save = true;
+        write(&options, "b", "report", Some(b"save")).unwrap();
+        assert_eq!(fs::read(folder.join("crash-b.dsg")).unwrap(), b"save");
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! The simulation never touches floating point.
+//!
+//! Vanilla's playsim is 16.16 fixed point throughout, and demos and netgames rely on every
+//! machine computing exactly the same tic from the same commands. Floating point would make
+//! that depend on the compiler, the target and its rounding modes, so the code the
+//! simulation runs is kept free of it: `SIMULATION` lists those sources, and a test reads
+//! them and fails on any `f32` or `f64` type or float literal.
+//!
+//! `util/angle.rs` is left out on purpose: its `sin_cos` works in floating point for the
+//! free camera, which draws the game but never feeds back into it. Code that needs a float
+//! for display, such as a percentage on a menu, belongs outside these paths too.
+//!
+//! The other half of the guarantee is `replay::replay_on_threads`, which plays a demo on
+//! several threads at once with different worker counts and expects one hash from all.
+
+/// The sources of the deterministic simulation, relative to `src`. A folder covers every
+/// `.rs` file below it.
+pub const SIMULATION: &[&str] = &[
+    "game",
+    "util/fixed.rs",
+    "engine/mod.rs",
+    "engine/controls.rs",
+];
+
+/// The floating point types and literals in Rust source, by line, skipping comments,
+/// strings and character literals. A lexer only as careful as the simulation's own code
+/// needs, not a full one.
+pub fn float_uses(source: &str) -> Vec<(usize, String)> {
+    let chars: Vec<char> = source.chars().collect();
+    let at = |i: usize| chars.get(i).copied().unwrap_or('\0');
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut uses = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c == '/' && at(i + 1) == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && at(i + 1) == '*' {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && at(i + 1) == '*' {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && at(i + 1) == '/' {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    line += usize::from(chars[i] == '\n');
+                    i += 1;
+                }
+            }
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                line += usize::from(chars[i] == '\n');
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c == '\'' {
+            // A character literal, or a lifetime or label, which has no closing quote.
+            if at(i + 1) == '\\' {
+                i += 3;
+                while i < chars.len() && chars[i] != '\'' {
+                    i += 1;
+                }
+                i += 1;
+            } else if at(i + 2) == '\'' {
+                i += 3;
+            } else {
+                i += 1;
+            }
+        } else if c.is_ascii_digit() {
+            let start = i;
+            let radix = c == '0' && matches!(at(i + 1), 'x' | 'o' | 'b');
+            // After a single dot the number is a tuple field, as in `pair.0`.
+            let field = start > 0 && at(start - 1) == '.' && (start < 2 || at(start - 2) != '.');
+            loop {
+                let c = at(i);
+                let exponent = !radix && matches!(c, '+' | '-') && matches!(at(i - 1), 'e' | 'E');
+                if is_word(c) || exponent {
+                    i += 1;
+                } else if !radix && !field && c == '.' && at(i + 1) != '.' {
+                    // `1.5` and `1.` are floats, but `1.max(2)` calls a method.
+                    let next = at(i + 1);
+                    if is_word(next) && !next.is_ascii_digit() {
+                        break;
+                    }
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let suffix = literal.trim_start_matches(|c: char| c.is_ascii_digit() || c == '_');
+            let float = literal.contains('.') || suffix.starts_with(['e', 'E', 'f']);
+            if float && !radix && !field {
+                uses.push((line, literal));
+            }
+        } else if is_word(c) {
+            let start = i;
+            while i < chars.len() && is_word(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if word == "f32" || word == "f64" {
+                uses.push((line, word));
+            } else if (word == "r" || word == "br") && matches!(at(i), '#' | '"') {
+                let mut hashes = 0;
+                while at(i) == '#' {
+                    hashes += 1;
+                    i += 1;
+                }
+                if at(i) == '"' {
+                    i += 1;
+                    while i < chars.len() {
+                        if chars[i] == '"' && (1..=hashes).all(|h| at(i + h) == '#') {
+                            i += 1 + hashes;
+                            break;
+                        }
+                        line += usize::from(chars[i] == '\n');
+                        i += 1;
+                    }
+                }
+            }
+        } else {
+            i += 1;
+        }
+    }
+    uses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    fn sources(path: &Path, files: &mut Vec<PathBuf>) {
+        if path.is_dir() {
+            let mut entries: Vec<_> = std::fs::read_dir(path)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            entries.sort();
+            for entry in entries {
+                sources(&entry, files);
+            }
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    #[test]
+    fn floats_are_found_in_code_only() {
+        let source = r##"
+            let a = x as f64; // not f32 here
+            let b = 1.5 + 2e3 + 4f32 + 7.;
+            let c = "3.25 f64" /* 0.5 f32 */;
+            let d = pair.0.1 + 0x1f32 + 10usize + 1_000 + b'.' as u32 + '\'' as u32;
+            for i in 0..2 { let e: &'static str = r#"1.0 "f64""#; }
+            let f = std::f32::consts::PI;
+        "##;
+        let expected = [
+            (2, "f64"),
+            (3, "1.5"),
+            (3, "2e3"),
+            (3, "4f32"),
+            (3, "7."),
+            (7, "f32"),
+        ];
+        assert_eq!(
+            float_uses(source),
+            expected.map(|(line, text)| (line, text.to_string()))
+        );
+    }
+
+    #[test]
+    fn the_simulation_has_no_floating_point() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::new();
+        for path in SIMULATION {
+            let path = root.join(path);
+            assert!(path.exists(), "{} is gone", path.display());
+            sources(&path, &mut files);
+        }
+        let mut found = Vec::new();
+        for file in &files {
+            let source = std::fs::read_to_string(file).unwrap();
+            for (line, text) in float_uses(&source) {
+                let name = file.strip_prefix(&root).unwrap().display();
+                found.push(format!("{name}:{line}: {text}"));
+            }
+        }
+        assert!(
+            found.is_empty(),
+            "floating point in the simulation:\n{}",
+            found.join("\n")
+        );
+    }
+}
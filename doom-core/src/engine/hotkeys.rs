@@ -0,0 +1,276 @@
+//! The function key row and the other shortcuts that work outside the menu.
+//!
+//! Vanilla hard coded these in `M_Responder`; here each one is a bindable `Hotkey` using
+//! Chocolate Doom's config names. `Hotkeys::responder` only decides which action a key
+//! press means in the current state. The menu and game modules carry it out: most open a
+//! menu page or a yes/no prompt, the rest toggle something and print a message.
+
+use crate::engine::config::{Config, Configurable, Section};
+use crate::engine::input::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Help,
+    Save,
+    Load,
+    Sound,
+    Detail,
+    QuickSave,
+    EndGame,
+    Messages,
+    QuickLoad,
+    Quit,
+    Gamma,
+    ScreenLarger,
+    ScreenSmaller,
+    Pause,
+    ExtendedHud,
+    Rewind,
+    Spy,
+    FreeCamera,
+    DemoSlower,
+    DemoFaster,
+    DemoStep,
+    JoinDemo,
+}
+
+impl Hotkey {
+    pub const ALL: [Hotkey; 22] = [
+        Hotkey::Help,
+        Hotkey::Save,
+        Hotkey::Load,
+        Hotkey::Sound,
+        Hotkey::Detail,
+        Hotkey::QuickSave,
+        Hotkey::EndGame,
+        Hotkey::Messages,
+        Hotkey::QuickLoad,
+        Hotkey::Quit,
+        Hotkey::Gamma,
+        Hotkey::ScreenLarger,
+        Hotkey::ScreenSmaller,
+        Hotkey::Pause,
+        Hotkey::ExtendedHud,
+        Hotkey::Rewind,
+        Hotkey::Spy,
+        Hotkey::FreeCamera,
+        Hotkey::DemoSlower,
+        Hotkey::DemoFaster,
+        Hotkey::DemoStep,
+        Hotkey::JoinDemo,
+    ];
+
+    pub fn config_name(self) -> &'static str {
+        match self {
+            Hotkey::Help => "key_menu_help",
+            Hotkey::Save => "key_menu_save",
+            Hotkey::Load => "key_menu_load",
+            Hotkey::Sound => "key_menu_volume",
+            Hotkey::Detail => "key_menu_detail",
+            Hotkey::QuickSave => "key_menu_qsave",
+            Hotkey::EndGame => "key_menu_endgame",
+            Hotkey::Messages => "key_menu_messages",
+            Hotkey::QuickLoad => "key_menu_qload",
+            Hotkey::Quit => "key_menu_quit",
+            Hotkey::Gamma => "key_menu_gamma",
+            Hotkey::ScreenLarger => "key_menu_incscreen",
+            Hotkey::ScreenSmaller => "key_menu_decscreen",
+            Hotkey::Pause => "key_pause",
+            Hotkey::ExtendedHud => "key_hud_extended",
+            Hotkey::Rewind => "key_rewind",
+            Hotkey::Spy => "key_spy",
+            Hotkey::FreeCamera => "key_freecam",
+            Hotkey::DemoSlower => "key_demo_slower",
+            Hotkey::DemoFaster => "key_demo_faster",
+            Hotkey::DemoStep => "key_demo_step",
+            Hotkey::JoinDemo => "key_demo_join",
+        }
+    }
+
+    pub fn default_key(self) -> Key {
+        match self {
+            Hotkey::Help => KEY_F1,
+            Hotkey::Save => KEY_F2,
+            Hotkey::Load => KEY_F3,
+            Hotkey::Sound => KEY_F4,
+            Hotkey::Detail => KEY_F5,
+            Hotkey::QuickSave => KEY_F6,
+            Hotkey::EndGame => KEY_F7,
+            Hotkey::Messages => KEY_F8,
+            Hotkey::QuickLoad => KEY_F9,
+            Hotkey::Quit => KEY_F10,
+            Hotkey::Gamma => KEY_F11,
+            Hotkey::ScreenLarger => KEY_EQUALS,
+            Hotkey::ScreenSmaller => KEY_MINUS,
+            Hotkey::Pause => KEY_PAUSE,
+            Hotkey::ExtendedHud => b'h' as Key,
+            Hotkey::Rewind => KEY_BACKSPACE,
+            Hotkey::Spy => KEY_F12,
+            Hotkey::FreeCamera => b'c' as Key,
+            Hotkey::DemoSlower => b'[' as Key,
+            Hotkey::DemoFaster => b']' as Key,
+            Hotkey::DemoStep => b'\\' as Key,
+            Hotkey::JoinDemo => b'q' as Key,
+        }
+    }
+
+    /// Whether the menu module handles it: by opening a page or asking for confirmation.
+    /// The others are toggles the game module applies at once.
+    pub fn opens_menu(self) -> bool {
+        !matches!(
+            self,
+            Hotkey::Detail
+                | Hotkey::Messages
+                | Hotkey::Gamma
+                | Hotkey::ScreenLarger
+                | Hotkey::ScreenSmaller
+                | Hotkey::Pause
+                | Hotkey::ExtendedHud
+                | Hotkey::Rewind
+                | Hotkey::Spy
+                | Hotkey::FreeCamera
+                | Hotkey::DemoSlower
+                | Hotkey::DemoFaster
+                | Hotkey::DemoStep
+                | Hotkey::JoinDemo
+        )
+    }
+}
+
+/// What else currently wants key presses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HotkeyState {
+    pub menu_active: bool,
+    /// The automap uses the same keys to zoom.
+    pub automap_active: bool,
+    pub chat_active: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hotkeys {
+    keys: [Key; Hotkey::ALL.len()],
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            keys: Hotkey::ALL.map(Hotkey::default_key),
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn key(&self, hotkey: Hotkey) -> Key {
+        self.keys[hotkey as usize]
+    }
+
+    pub fn bind(&mut self, hotkey: Hotkey, key: Key) {
+        self.keys[hotkey as usize] = key;
+    }
+
+    /// The action a key press triggers. While the menu is open only pause gets through, as
+    /// in vanilla where the menu ignores it and the game loop sees it; the screen size keys
+    /// give way to the automap and to typing a chat message, and the letter and punctuation
+    /// keys to typing.
+    pub fn responder(&self, key: Key, state: &HotkeyState) -> Option<Hotkey> {
+        let hotkey = *Hotkey::ALL.iter().find(|&&h| self.key(h) == key)?;
+        match hotkey {
+            Hotkey::Pause => Some(hotkey),
+            _ if state.menu_active => None,
+            Hotkey::ScreenLarger | Hotkey::ScreenSmaller
+                if state.automap_active || state.chat_active =>
+            {
+                None
+            }
+            Hotkey::ExtendedHud
+            | Hotkey::Rewind
+            | Hotkey::Spy
+            | Hotkey::FreeCamera
+            | Hotkey::DemoSlower
+            | Hotkey::DemoFaster
+            | Hotkey::DemoStep
+            | Hotkey::JoinDemo
+                if state.chat_active =>
+            {
+                None
+            }
+            _ => Some(hotkey),
+        }
+    }
+}
+
+impl Configurable for Hotkeys {
+    fn register(config: &mut Config) {
+        for hotkey in Hotkey::ALL {
+            config.register_int(
+                hotkey.config_name(),
+                hotkey.default_key(),
+                Section::Extended,
+            );
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            keys: Hotkey::ALL.map(|hotkey| config.get_int(hotkey.config_name())),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        for hotkey in Hotkey::ALL {
+            config.set_int(hotkey.config_name(), self.key(hotkey));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_row_and_rebinding() {
+        let mut hotkeys = Hotkeys::default();
+        let state = HotkeyState::default();
+        assert_eq!(hotkeys.responder(KEY_F2, &state), Some(Hotkey::Save));
+        assert_eq!(hotkeys.responder(KEY_F11, &state), Some(Hotkey::Gamma));
+        assert_eq!(hotkeys.responder(b'a' as Key, &state), None);
+
+        hotkeys.bind(Hotkey::QuickSave, b'q' as Key);
+        assert_eq!(hotkeys.responder(KEY_F6, &state), None);
+        assert_eq!(
+            hotkeys.responder(b'q' as Key, &state),
+            Some(Hotkey::QuickSave)
+        );
+    }
+
+    #[test]
+    fn menu_automap_and_chat_take_precedence() {
+        let hotkeys = Hotkeys::default();
+        let menu = HotkeyState {
+            menu_active: true,
+            ..HotkeyState::default()
+        };
+        assert_eq!(hotkeys.responder(KEY_F1, &menu), None);
+        assert_eq!(hotkeys.responder(KEY_PAUSE, &menu), Some(Hotkey::Pause));
+
+        let automap = HotkeyState {
+            automap_active: true,
+            ..HotkeyState::default()
+        };
+        assert_eq!(hotkeys.responder(KEY_MINUS, &automap), None);
+        assert_eq!(hotkeys.responder(KEY_F5, &automap), Some(Hotkey::Detail));
+        assert!(Hotkey::Quit.opens_menu() && !Hotkey::Detail.opens_menu());
+    }
+
+    #[test]
+    fn bindings_round_trip_through_config() {
+        let mut config = Config::new();
+        Hotkeys::register(&mut config);
+        assert_eq!(Hotkeys::from_config(&config), Hotkeys::default());
+        let mut hotkeys = Hotkeys::default();
+        hotkeys.bind(Hotkey::Gamma, KEY_F12);
+        hotkeys.to_config(&mut config);
+        assert_eq!(config.get_int("key_menu_gamma"), KEY_F12);
+        assert_eq!(Hotkeys::from_config(&config), hotkeys);
+    }
+}
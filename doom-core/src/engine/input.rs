@@ -0,0 +1,112 @@
+//! Input events as the game sees them, after the platform layer translated them.
+//!
+//! Keys use the vanilla key codes: printable keys are their lower case ASCII value, everything
+//! else lives above 0x7f. Config files store these numbers, so they must stay stable.
+
+pub type Key = i32;
+
+pub const KEY_RIGHTARROW: Key = 0xae;
+pub const KEY_LEFTARROW: Key = 0xac;
+pub const KEY_UPARROW: Key = 0xad;
+pub const KEY_DOWNARROW: Key = 0xaf;
+pub const KEY_ESCAPE: Key = 27;
+pub const KEY_ENTER: Key = 13;
+pub const KEY_TAB: Key = 9;
+pub const KEY_BACKSPACE: Key = 0x7f;
+pub const KEY_PAUSE: Key = 0xff;
+pub const KEY_EQUALS: Key = 0x3d;
+pub const KEY_MINUS: Key = 0x2d;
+
+pub const KEY_F1: Key = 0x80 + 0x3b;
+pub const KEY_F2: Key = 0x80 + 0x3c;
+pub const KEY_F3: Key = 0x80 + 0x3d;
+pub const KEY_F4: Key = 0x80 + 0x3e;
+pub const KEY_F5: Key = 0x80 + 0x3f;
+pub const KEY_F6: Key = 0x80 + 0x40;
+pub const KEY_F7: Key = 0x80 + 0x41;
+pub const KEY_F8: Key = 0x80 + 0x42;
+pub const KEY_F9: Key = 0x80 + 0x43;
+pub const KEY_F10: Key = 0x80 + 0x44;
+pub const KEY_F11: Key = 0x80 + 0x57;
+pub const KEY_F12: Key = 0x80 + 0x58;
+
+pub const KEY_RSHIFT: Key = 0x80 + 0x36;
+pub const KEY_RCTRL: Key = 0x80 + 0x1d;
+pub const KEY_RALT: Key = 0x80 + 0x38;
+pub const KEY_LALT: Key = KEY_RALT;
+pub const KEY_CAPSLOCK: Key = 0x80 + 0x3a;
+pub const KEY_PRTSCR: Key = 0x80 + 0x59;
+
+pub const KEY_HOME: Key = 0x80 + 0x47;
+pub const KEY_END: Key = 0x80 + 0x4f;
+pub const KEY_PGUP: Key = 0x80 + 0x49;
+pub const KEY_PGDN: Key = 0x80 + 0x51;
+pub const KEY_INS: Key = 0x80 + 0x52;
+pub const KEY_DEL: Key = 0x80 + 0x53;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    KeyDown(Key),
+    KeyUp(Key),
+    /// `buttons` is a bit mask, `dx`/`dy` the motion since the last event.
+    Mouse {
+        buttons: i32,
+        dx: i32,
+        dy: i32,
+    },
+    Joystick {
+        buttons: i32,
+        x: i32,
+        y: i32,
+    },
+}
+
+/// Names for the keys that don't print as themselves.
+const KEY_NAMES: [(Key, &str); 34] = [
+    (KEY_RIGHTARROW, "RIGHT"),
+    (KEY_LEFTARROW, "LEFT"),
+    (KEY_UPARROW, "UP"),
+    (KEY_DOWNARROW, "DOWN"),
+    (KEY_ESCAPE, "ESCAPE"),
+    (KEY_ENTER, "ENTER"),
+    (KEY_TAB, "TAB"),
+    (KEY_BACKSPACE, "BACKSPACE"),
+    (KEY_PAUSE, "PAUSE"),
+    (KEY_F1, "F1"),
+    (KEY_F2, "F2"),
+    (KEY_F3, "F3"),
+    (KEY_F4, "F4"),
+    (KEY_F5, "F5"),
+    (KEY_F6, "F6"),
+    (KEY_F7, "F7"),
+    (KEY_F8, "F8"),
+    (KEY_F9, "F9"),
+    (KEY_F10, "F10"),
+    (KEY_F11, "F11"),
+    (KEY_F12, "F12"),
+    (KEY_RSHIFT, "SHIFT"),
+    (KEY_RCTRL, "CTRL"),
+    (KEY_RALT, "ALT"),
+    (KEY_CAPSLOCK, "CAPS LOCK"),
+    (KEY_PRTSCR, "PRINT SCREEN"),
+    (KEY_HOME, "HOME"),
+    (KEY_END, "END"),
+    (KEY_PGUP, "PAGE UP"),
+    (KEY_PGDN, "PAGE DOWN"),
+    (KEY_INS, "INSERT"),
+    (KEY_DEL, "DELETE"),
+    (b' ' as Key, "SPACE"),
+    (0, "---"),
+];
+
+/// How a key is shown in the menus: printable keys as themselves in upper case, the rest by
+/// name, and 0, no key, as dashes.
+pub fn key_name(key: Key) -> String {
+    if let Some((_, name)) = KEY_NAMES.iter().find(|&&(named, _)| named == key) {
+        return name.to_string();
+    }
+    match u8::try_from(key) {
+        Ok(c) if c.is_ascii_graphic() => char::from(c.to_ascii_uppercase()).to_string(),
+        _ => format!("KEY {key}"),
+    }
+}
@@ -0,0 +1,319 @@
+//! Finding netgames on the local network, for the server list in the menu.
+//!
+//! A server binds `Announcer` to the game's port and answers whoever asks. The menu's
+//! `Browser` broadcasts a query to that port on the whole subnet every `SEARCH_INTERVAL` and
+//! lists the servers that answer with their name, map, players and game mode. A server that
+//! stops answering drops off the list after `FORGET_AFTER`. Nothing goes beyond the subnet:
+//! broadcasts aren't routed, which is what keeps this to a LAN.
+//!
+//! Each query and answer is one datagram starting with a four byte tag. Strings are a length
+//! byte then the bytes, so a future field can go on the end without confusing older copies.
+//!
+//! `ServerList` is the menu's side: the highlighted entry, moved with the arrow keys, and
+//! Enter giving the address to join.
+
+use super::input::{Key, KEY_DOWNARROW, KEY_ENTER, KEY_UPARROW};
+use crate::game::reborn::GameMode;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The port netgames are served on unless told otherwise, Chocolate Doom's.
+pub const DEFAULT_PORT: u16 = 2342;
+/// How often the browser asks again.
+pub const SEARCH_INTERVAL: Duration = Duration::from_secs(2);
+/// How long a server stays listed after its last answer.
+pub const FORGET_AFTER: Duration = Duration::from_secs(6);
+
+const QUERY: &[u8; 4] = b"DRSQ";
+const INFO: &[u8; 4] = b"DRSI";
+
+/// What a server tells the browser about its game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerInfo {
+    pub name: String,
+    pub map: String,
+    pub players: u8,
+    pub max_players: u8,
+    pub mode: GameMode,
+    /// The port to join on, which needn't be the one it answered from.
+    pub port: u16,
+}
+
+impl ServerInfo {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = INFO.to_vec();
+        for text in [&self.name, &self.map] {
+            let text = &text.as_bytes()[..text.len().min(255)];
+            bytes.push(text.len() as u8);
+            bytes.extend_from_slice(text);
+        }
+        bytes.extend_from_slice(&[self.players, self.max_players, self.mode as u8]);
+        bytes.extend_from_slice(&self.port.to_le_bytes());
+        bytes
+    }
+
+    /// Reads an answer. Returns `None` for anything else, whatever is on the network.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut rest = bytes.strip_prefix(INFO)?;
+        let mut text = || {
+            let (&len, after) = rest.split_first()?;
+            let (text, after) = after.split_at_checked(usize::from(len))?;
+            rest = after;
+            Some(String::from_utf8_lossy(text).into_owned())
+        };
+        let (name, map) = (text()?, text()?);
+        let [players, max_players, mode, port0, port1, ..] = *rest else {
+            return None;
+        };
+        Some(Self {
+            name,
+            map,
+            players,
+            max_players,
+            mode: match mode {
+                0 => GameMode::Single,
+                1 => GameMode::Coop,
+                2 => GameMode::Deathmatch,
+                _ => return None,
+            },
+            port: u16::from_le_bytes([port0, port1]),
+        })
+    }
+
+    /// The row the server list shows.
+    pub fn summary(&self) -> String {
+        let mode = match self.mode {
+            GameMode::Single => "single",
+            GameMode::Coop => "coop",
+            GameMode::Deathmatch => "deathmatch",
+        };
+        format!(
+            "{} - {} - {}/{} - {mode}",
+            self.name, self.map, self.players, self.max_players
+        )
+    }
+}
+
+/// A server's side: answers queries without blocking the game loop.
+pub struct Announcer {
+    socket: UdpSocket,
+}
+
+impl Announcer {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Answers every query waiting with `info`, returning how many there were. Call once a
+    /// tic or so.
+    pub fn poll(&self, info: &ServerInfo) -> io::Result<usize> {
+        let mut buffer = [0; 64];
+        let mut answered = 0;
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) if buffer[..len].starts_with(QUERY) => {
+                    self.socket.send_to(&info.to_bytes(), from)?;
+                    answered += 1;
+                }
+                Ok(_) => {}
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(answered),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// A server that answered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundServer {
+    /// Where to join: the address it answered from with the port it gave.
+    pub address: SocketAddr,
+    pub info: ServerInfo,
+    pub seen: Instant,
+}
+
+/// The menu's side: asks the subnet and collects the answers.
+pub struct Browser {
+    socket: UdpSocket,
+    /// Where queries go, normally the subnet's broadcast address.
+    target: SocketAddr,
+    last_search: Option<Instant>,
+    pub servers: Vec<FoundServer>,
+}
+
+impl Browser {
+    /// Searches for servers on `port` across the subnet.
+    pub fn new(port: u16) -> io::Result<Self> {
+        Self::with_target(SocketAddrV4::new(Ipv4Addr::BROADCAST, port).into())
+    }
+
+    /// Searches by asking `target` only.
+    pub fn with_target(target: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            target,
+            last_search: None,
+            servers: Vec::new(),
+        })
+    }
+
+    /// Asks again if it's time, takes in the answers that came and forgets servers that have
+    /// gone quiet. Returns whether the list changed.
+    pub fn poll(&mut self, now: Instant) -> io::Result<bool> {
+        if self
+            .last_search
+            .is_none_or(|last| now.duration_since(last) >= SEARCH_INTERVAL)
+        {
+            self.socket.send_to(QUERY, self.target)?;
+            self.last_search = Some(now);
+        }
+        let listed = |servers: &[FoundServer]| {
+            let entries = servers
+                .iter()
+                .map(|server| (server.address, server.info.clone()));
+            entries.collect::<Vec<_>>()
+        };
+        let before = listed(&self.servers);
+        let mut buffer = [0; 600];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            };
+            let Some(info) = ServerInfo::parse(&buffer[..len]) else {
+                continue;
+            };
+            let address = SocketAddr::new(from.ip(), info.port);
+            self.servers.retain(|server| server.address != address);
+            self.servers.push(FoundServer {
+                address,
+                info,
+                seen: now,
+            });
+        }
+        self.servers
+            .retain(|server| now.duration_since(server.seen) < FORGET_AFTER);
+        self.servers.sort_by_key(|server| server.address);
+        Ok(listed(&self.servers) != before)
+    }
+}
+
+/// The highlighted row of the server list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ServerList {
+    pub selected: usize,
+}
+
+impl ServerList {
+    /// Moves the highlight, or on Enter returns the server to join. The highlight stays in
+    /// the list as servers come and go.
+    pub fn responder(&mut self, key: Key, servers: &[FoundServer]) -> Option<SocketAddr> {
+        let last = servers.len().saturating_sub(1);
+        self.selected = self.selected.min(last);
+        match key {
+            KEY_UPARROW => self.selected = self.selected.saturating_sub(1),
+            KEY_DOWNARROW => self.selected = (self.selected + 1).min(last),
+            KEY_ENTER => return servers.get(self.selected).map(|server| server.address),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, port: u16) -> ServerInfo {
+        ServerInfo {
+            name: name.to_string(),
+            map: "MAP01".to_string(),
+            players: 2,
+            max_players: 4,
+            mode: GameMode::Coop,
+            port,
+        }
+    }
+
+    #[test]
+    fn answers_round_trip_and_junk_is_ignored() {
+        let server = info("basement", 2342);
+        let bytes = server.to_bytes();
+        assert_eq!(ServerInfo::parse(&bytes), Some(server.clone()));
+        assert_eq!(server.summary(), "basement - MAP01 - 2/4 - coop");
+        assert_eq!(ServerInfo::parse(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(ServerInfo::parse(QUERY), None);
+        let mut longer = bytes.clone();
+        longer.push(9);
+        assert_eq!(ServerInfo::parse(&longer), Some(server));
+    }
+
+    #[test]
+    fn the_browser_lists_servers_that_answer() {
+        let announcer = Announcer::bind(0).unwrap();
+        let port = announcer.local_addr().unwrap().port();
+        let target = SocketAddrV4::new(Ipv4Addr::LOCALHOST, port).into();
+        let mut browser = Browser::with_target(target).unwrap();
+        let start = Instant::now();
+        browser.poll(start).unwrap();
+
+        let mut answered = 0;
+        for _ in 0..100 {
+            answered += announcer.poll(&info("basement", 2400)).unwrap();
+            if answered > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(answered, 1);
+        let mut changed = false;
+        for _ in 0..100 {
+            changed = browser.poll(start).unwrap();
+            if changed {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(changed);
+        assert_eq!(browser.servers.len(), 1);
+        assert_eq!(browser.servers[0].address.port(), 2400);
+
+        // Gone quiet for long enough, it is forgotten.
+        assert!(browser.poll(start + FORGET_AFTER).unwrap());
+        assert!(browser.servers.is_empty());
+    }
+
+    #[test]
+    fn the_list_picks_a_server_to_join() {
+        let found = |port| FoundServer {
+            address: SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 5), port).into(),
+            info: info("x", port),
+            seen: Instant::now(),
+        };
+        let servers = [found(1), found(2)];
+        let mut list = ServerList::default();
+        assert_eq!(list.responder(KEY_UPARROW, &servers), None);
+        list.responder(KEY_DOWNARROW, &servers);
+        list.responder(KEY_DOWNARROW, &servers);
+        assert_eq!(list.selected, 1);
+        let join = list.responder(KEY_ENTER, &servers);
+        assert_eq!(join.map(|address| address.port()), Some(2));
+        // The list shrank under the highlight.
+        assert_eq!(
+            list.responder(KEY_ENTER, &servers[..1]),
+            Some(servers[0].address)
+        );
+        assert_eq!(list.responder(KEY_ENTER, &[]), None);
+    }
+}
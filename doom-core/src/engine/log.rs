@@ -0,0 +1,351 @@
+//! Engine-wide logging with a level per subsystem.
+//!
+//! Messages go through the `error!` to `trace!` macros, each naming its subsystem (`"wad"`,
+//! `"game"`, `"renderer"`, ...). Whether a message is kept depends on the level set for the
+//! longest matching subsystem name in the spec, so `wad.lumps` falls back to `wad` and then
+//! to the default. The spec comes from `-log`, the `log_level` cvar or the `log` console
+//! command, written like `info,wad=debug,renderer=trace`. Kept messages are printed to the
+//! terminal's stderr and stored in a ring buffer. The console shows the buffer, so anything logged
+//! before it was opened is still there.
+
+use super::config::{Config, Configurable, Section};
+use super::console::Commands;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Records kept for the console.
+pub const RING_SIZE: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = ();
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        [
+            Level::Off,
+            Level::Error,
+            Level::Warn,
+            Level::Info,
+            Level::Debug,
+            Level::Trace,
+        ]
+        .into_iter()
+        .find(|level| level.name().eq_ignore_ascii_case(value))
+        .ok_or(())
+    }
+}
+
+/// Which level each subsystem logs at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogSpec {
+    pub default: Level,
+    pub targets: Vec<(String, Level)>,
+}
+
+impl Default for LogSpec {
+    fn default() -> Self {
+        Self {
+            default: Level::Info,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl LogSpec {
+    pub fn level(&self, target: &str) -> Level {
+        self.targets
+            .iter()
+            .filter(|(name, _)| {
+                target == name
+                    || target
+                        .strip_prefix(name.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .max_by_key(|(name, _)| name.len())
+            .map_or(self.default, |&(_, level)| level)
+    }
+}
+
+impl FromStr for LogSpec {
+    type Err = String;
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut parsed = Self::default();
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let bad = || format!("bad log level '{part}'");
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    let level = level.trim().parse().map_err(|_| bad())?;
+                    let target = target.trim().to_ascii_lowercase();
+                    parsed.targets.retain(|(name, _)| *name != target);
+                    parsed.targets.push((target, level));
+                }
+                None => parsed.default = part.parse().map_err(|_| bad())?,
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for LogSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.default.name())?;
+        for (target, level) in &self.targets {
+            write!(f, ",{target}={}", level.name())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.level {
+            Level::Error | Level::Warn | Level::Info => write!(f, "{}", self.message),
+            _ => write!(f, "[{}] {}", self.target, self.message),
+        }
+    }
+}
+
+struct Logger {
+    spec: Option<LogSpec>, // `None` until first set, meaning the default spec.
+    records: VecDeque<Record>,
+    written: u64, // Records ever stored, so readers can tell which ones they have seen.
+    echo: bool,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    spec: None,
+    records: VecDeque::new(),
+    written: 0,
+    echo: true,
+});
+
+fn logger() -> std::sync::MutexGuard<'static, Logger> {
+    LOGGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn set_spec(spec: LogSpec) {
+    logger().spec = Some(spec);
+}
+
+pub fn spec() -> LogSpec {
+    logger().spec.clone().unwrap_or_default()
+}
+
+/// Turns printing to the terminal on or off; the ring buffer keeps filling either way.
+pub fn set_echo(echo: bool) {
+    logger().echo = echo;
+}
+
+pub fn enabled(target: &str, level: Level) -> bool {
+    let logger = logger();
+    level != Level::Off
+        && level
+            <= logger
+                .spec
+                .as_ref()
+                .map_or(Level::Info, |s| s.level(target))
+}
+
+/// What the macros call.
+pub fn log(level: Level, target: &str, args: fmt::Arguments) {
+    if !enabled(target, level) {
+        return;
+    }
+    let record = Record {
+        level,
+        target: target.to_string(),
+        message: args.to_string(),
+    };
+    let mut logger = logger();
+    if logger.echo {
+        eprintln!("doom-rs: {record}"); // stderr, so stdout stays clean for `bench` output
+    }
+    if logger.records.len() == RING_SIZE {
+        logger.records.pop_front();
+    }
+    logger.records.push_back(record);
+    logger.written += 1;
+}
+
+/// Records stored since `seen` were, oldest first, moving `seen` past them. Records that
+/// dropped out of the ring in between are skipped.
+pub fn since(seen: &mut u64) -> Vec<Record> {
+    let logger = logger();
+    let first = logger.written - logger.records.len() as u64;
+    let skip = seen.saturating_sub(first) as usize;
+    *seen = logger.written;
+    logger.records.iter().skip(skip).cloned().collect()
+}
+
+/// Centers a title in 80 columns the way vanilla prints the game name at startup.
+pub fn banner(title: &str) -> String {
+    format!("{title:^80}").trim_end().to_string()
+}
+
+#[macro_export]
+macro_rules! error {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::engine::log::log($crate::engine::log::Level::Error, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::engine::log::log($crate::engine::log::Level::Warn, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::engine::log::log($crate::engine::log::Level::Info, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::engine::log::log($crate::engine::log::Level::Debug, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::engine::log::log($crate::engine::log::Level::Trace, $target, format_args!($($arg)+))
+    };
+}
+
+/// The `log_level` cvar.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogOptions {
+    pub spec: LogSpec,
+}
+
+impl Configurable for LogOptions {
+    fn register(config: &mut Config) {
+        config.register_str("log_level", "info", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            spec: config.get_str("log_level").parse().unwrap_or_default(),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("log_level", &self.spec.to_string());
+    }
+}
+
+/// Registers `log [spec]`, which prints or changes the levels.
+pub fn register_command(commands: &mut Commands) {
+    commands.register(
+        "log",
+        "log [spec]: show or set log levels, like info,wad=debug",
+        |context, args| {
+            if args.is_empty() {
+                context.print(format!("log levels: {}", spec()));
+                return Ok(());
+            }
+            let new: LogSpec = args.join(",").parse()?;
+            context.print(format!("log levels: {new}"));
+            set_spec(new);
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_longest_matching_target_wins() {
+        let spec: LogSpec = "warn, wad=debug, wad.lumps=trace, game=off"
+            .parse()
+            .unwrap();
+        assert_eq!(spec.level("renderer"), Level::Warn);
+        assert_eq!(spec.level("wad"), Level::Debug);
+        assert_eq!(spec.level("wad.dir"), Level::Debug);
+        assert_eq!(spec.level("wad.lumps"), Level::Trace);
+        assert_eq!(spec.level("wadfile"), Level::Warn);
+        assert_eq!(spec.level("game"), Level::Off);
+        assert_eq!(spec.to_string(), "warn,wad=debug,wad.lumps=trace,game=off");
+        assert!("info,wad=loud".parse::<LogSpec>().is_err());
+    }
+
+    #[test]
+    fn records_land_in_the_ring_for_the_console() {
+        set_echo(false);
+        let mut seen = 0;
+        since(&mut seen);
+        crate::info!("test.ring", "{} lumps", 2306);
+        crate::trace!("test.ring", "not kept at the default level");
+        let records = since(&mut seen);
+        let mine: Vec<String> = records
+            .iter()
+            .filter(|record| record.target == "test.ring")
+            .map(Record::to_string)
+            .collect();
+        assert_eq!(mine, ["2306 lumps"]);
+        assert!(since(&mut seen).iter().all(|r| r.target != "test.ring"));
+    }
+
+    #[test]
+    fn banners_are_centered_in_80_columns() {
+        let banner = banner("DOOM 2: Hell on Earth");
+        assert_eq!(banner.len(), 29 + 21);
+        assert!(banner.starts_with("     ") && banner.ends_with("Earth"));
+    }
+
+    #[test]
+    fn the_console_command_sets_the_spec() {
+        let mut commands = Commands::default();
+        register_command(&mut commands);
+        let mut config = Config::new();
+        assert_eq!(
+            commands.execute("log nonsense", &mut config),
+            ["log: bad log level 'nonsense'"]
+        );
+        LogOptions::register(&mut config);
+        assert_eq!(LogOptions::from_config(&config).spec, LogSpec::default());
+    }
+}
@@ -0,0 +1,409 @@
+//! Menu pages: their items, where each one sits in the 320x200 layout, and what the keyboard
+//! and mouse do to them.
+//!
+//! The layout is vanilla's: items `LINEHEIGHT` apart down from the page's corner, the skull
+//! to their left, and a slider's thermometer on the blank row under it. `Menu::hit_test`
+//! turns a point in that layout into what is there, which is all the mouse needs: moving
+//! over an item selects it, a left click activates it, holding the button on a thermometer
+//! drags the slider along, and a right click backs out. The `Pointer` is kept in 320x200
+//! pixels too, moved by mouse motion or put where the system cursor is with `Pointer::warp`;
+//! `renderer::menu::MenuScale` takes a position on the canvas back to the layout.
+
+use super::config::{Config, Configurable, Section};
+use super::input::*;
+use crate::renderer::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+
+pub const LINEHEIGHT: i32 = 16;
+/// Where the skull is drawn, left of the items.
+pub const SKULLXOFF: i32 = -32;
+/// The width of a thermometer's ends and of each of its steps.
+pub const THERM_STEP: i32 = 8;
+
+pub const LEFT_BUTTON: i32 = 1;
+pub const RIGHT_BUTTON: i32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    /// A row the cursor skips, such as the one under a slider.
+    Blank,
+    Button,
+    /// `value` is one of `0..steps`, shown on a thermometer on the row below.
+    Slider {
+        steps: i32,
+        value: i32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MenuItem {
+    /// The graphic naming the item, `M_` and all.
+    pub patch: &'static str,
+    pub kind: ItemKind,
+}
+
+impl MenuItem {
+    pub const fn button(patch: &'static str) -> Self {
+        Self {
+            patch,
+            kind: ItemKind::Button,
+        }
+    }
+
+    pub const fn slider(patch: &'static str, steps: i32, value: i32) -> Self {
+        Self {
+            patch,
+            kind: ItemKind::Slider { steps, value },
+        }
+    }
+
+    pub const fn blank() -> Self {
+        Self {
+            patch: "",
+            kind: ItemKind::Blank,
+        }
+    }
+}
+
+/// What the player did to the page, for the caller to carry out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    Activate(usize),
+    /// The slider at the index was moved to the value; the menu already shows it.
+    Slide(usize, i32),
+    Back,
+}
+
+/// What is at a point on the page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hit {
+    Item(usize),
+    /// A slider's thermometer, and the value under the point.
+    Slider(usize, i32),
+}
+
+/// The mouse pointer over the menu, in 320x200 pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pointer {
+    pub x: i32,
+    pub y: i32,
+    buttons: i32,
+    /// The slider the left button went down on, while it is held.
+    pub dragging: Option<usize>,
+}
+
+impl Default for Pointer {
+    fn default() -> Self {
+        Self {
+            x: SCREENWIDTH as i32 / 2,
+            y: SCREENHEIGHT as i32 / 2,
+            buttons: 0,
+            dragging: None,
+        }
+    }
+}
+
+impl Pointer {
+    /// Moves by mouse motion, `dy` up the screen as in vanilla's mouse events.
+    pub fn moved(&mut self, dx: i32, dy: i32) {
+        self.warp(self.x.saturating_add(dx), self.y.saturating_sub(dy));
+    }
+
+    /// Puts the pointer at (`x`, `y`), kept on the screen.
+    pub fn warp(&mut self, x: i32, y: i32) {
+        self.x = x.clamp(0, SCREENWIDTH as i32 - 1);
+        self.y = y.clamp(0, SCREENHEIGHT as i32 - 1);
+    }
+
+    /// Moves with a mouse event and returns the buttons that just went down, or `None` for
+    /// other events. Letting go of the left button ends a drag.
+    pub fn responder(&mut self, event: &Event) -> Option<i32> {
+        let Event::Mouse { buttons, dx, dy } = *event else {
+            return None;
+        };
+        self.moved(dx, dy);
+        let pressed = buttons & !self.buttons;
+        self.buttons = buttons;
+        if buttons & LEFT_BUTTON == 0 {
+            self.dragging = None;
+        }
+        Some(pressed)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Menu {
+    pub items: Vec<MenuItem>,
+    /// The top left corner of the first item.
+    pub x: i32,
+    pub y: i32,
+    pub selected: usize,
+}
+
+impl Menu {
+    /// A page with the first item that isn't blank selected.
+    pub fn new(x: i32, y: i32, items: Vec<MenuItem>) -> Self {
+        let selected = items
+            .iter()
+            .position(|item| item.kind != ItemKind::Blank)
+            .unwrap_or(0);
+        Self {
+            items,
+            x,
+            y,
+            selected,
+        }
+    }
+
+    /// The row of item `i`, from the skull's left edge across to the same margin on the
+    /// right, so a click needn't land on the letters.
+    pub fn item_rect(&self, i: usize) -> Rect {
+        let left = (self.x + SKULLXOFF).max(0);
+        Rect {
+            x: left,
+            y: self.y + LINEHEIGHT * i as i32,
+            width: (SCREENWIDTH as i32 - 2 * left).max(0),
+            height: LINEHEIGHT,
+        }
+    }
+
+    /// The thermometer of item `i`, if it is a slider: its ends and a step per value.
+    pub fn thermo_rect(&self, i: usize) -> Option<Rect> {
+        match self.items.get(i)?.kind {
+            ItemKind::Slider { steps, .. } => Some(Rect {
+                x: self.x,
+                y: self.y + LINEHEIGHT * (i as i32 + 1),
+                width: (steps + 2) * THERM_STEP,
+                height: LINEHEIGHT,
+            }),
+            _ => None,
+        }
+    }
+
+    /// What is at (`x`, `y`). Thermometers come first, since they sit on blank rows.
+    pub fn hit_test(&self, x: i32, y: i32) -> Option<Hit> {
+        for (i, item) in self.items.iter().enumerate() {
+            if let (Some(rect), ItemKind::Slider { steps, .. }) = (self.thermo_rect(i), item.kind) {
+                if rect.contains(x, y) {
+                    let value = (x - rect.x - THERM_STEP).div_euclid(THERM_STEP);
+                    return Some(Hit::Slider(i, value.clamp(0, steps - 1)));
+                }
+            }
+        }
+        (0..self.items.len())
+            .find(|&i| self.items[i].kind != ItemKind::Blank && self.item_rect(i).contains(x, y))
+            .map(Hit::Item)
+    }
+
+    /// Sets slider `i` to `value`, if that moves it.
+    fn slide(&mut self, i: usize, value: i32) -> Option<MenuAction> {
+        match &mut self.items[i].kind {
+            ItemKind::Slider { steps, value: old } => {
+                let value = value.clamp(0, *steps - 1);
+                (value != *old).then(|| {
+                    *old = value;
+                    MenuAction::Slide(i, value)
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The next item that isn't blank going `step` rows, wrapping around.
+    fn step_selection(&mut self, step: isize) {
+        let count = self.items.len() as isize;
+        for _ in 0..count {
+            self.selected = (self.selected as isize + step).rem_euclid(count) as usize;
+            if self.items[self.selected].kind != ItemKind::Blank {
+                break;
+            }
+        }
+    }
+
+    /// Handles a key press as `M_Responder` does on a page.
+    pub fn responder(&mut self, key: Key) -> Option<MenuAction> {
+        if self.items.is_empty() {
+            return (key == KEY_ESCAPE || key == KEY_BACKSPACE).then_some(MenuAction::Back);
+        }
+        let selected = self.selected;
+        match (key, self.items[selected].kind) {
+            (KEY_DOWNARROW, _) => self.step_selection(1),
+            (KEY_UPARROW, _) => self.step_selection(-1),
+            (KEY_LEFTARROW, ItemKind::Slider { value, .. }) => {
+                return self.slide(selected, value - 1)
+            }
+            (KEY_RIGHTARROW, ItemKind::Slider { value, .. }) => {
+                return self.slide(selected, value + 1)
+            }
+            (KEY_ENTER, ItemKind::Button) => return Some(MenuAction::Activate(selected)),
+            (KEY_ESCAPE | KEY_BACKSPACE, _) => return Some(MenuAction::Back),
+            _ => {}
+        }
+        None
+    }
+
+    /// Handles a mouse event, moving `pointer` with it. Frontends that know where the system
+    /// cursor is `warp` the pointer there and pass the buttons with no motion.
+    pub fn mouse(&mut self, pointer: &mut Pointer, event: &Event) -> Option<MenuAction> {
+        let pressed = pointer.responder(event)?;
+        if pressed & RIGHT_BUTTON != 0 {
+            return Some(MenuAction::Back);
+        }
+        if let Some(i) = pointer.dragging {
+            let rect = self.thermo_rect(i)?;
+            return self.slide(i, (pointer.x - rect.x - THERM_STEP).div_euclid(THERM_STEP));
+        }
+        match self.hit_test(pointer.x, pointer.y)? {
+            Hit::Slider(i, value) => {
+                self.selected = i;
+                if pressed & LEFT_BUTTON == 0 {
+                    return None;
+                }
+                pointer.dragging = Some(i);
+                self.slide(i, value)
+            }
+            Hit::Item(i) => {
+                self.selected = i;
+                let click = pressed & LEFT_BUTTON != 0;
+                (click && self.items[i].kind == ItemKind::Button).then_some(MenuAction::Activate(i))
+            }
+        }
+    }
+}
+
+/// How the menus take the mouse and are drawn at sizes above 320x200.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MenuOptions {
+    pub mouse: bool,
+    /// Scale the menus by whole multiples only, so every pixel is the same size.
+    pub integer_scale: bool,
+}
+
+impl Default for MenuOptions {
+    fn default() -> Self {
+        Self {
+            mouse: true,
+            integer_scale: false,
+        }
+    }
+}
+
+impl Configurable for MenuOptions {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_bool("menu_mouse", defaults.mouse, Section::Extended);
+        config.register_bool(
+            "menu_integer_scale",
+            defaults.integer_scale,
+            Section::Extended,
+        );
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            mouse: config.get_bool("menu_mouse"),
+            integer_scale: config.get_bool("menu_integer_scale"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("menu_mouse", self.mouse);
+        config.set_bool("menu_integer_scale", self.integer_scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Vanilla's options page, cut down: a button, then a slider and its thermometer row.
+    fn options() -> Menu {
+        Menu::new(
+            60,
+            37,
+            vec![
+                MenuItem::button("M_ENDGAM"),
+                MenuItem::slider("M_SCRNSZ", 9, 4),
+                MenuItem::blank(),
+                MenuItem::button("M_SVOL"),
+            ],
+        )
+    }
+
+    #[test]
+    fn points_hit_items_and_thermometers() {
+        let menu = options();
+        assert_eq!(menu.hit_test(100, 40), Some(Hit::Item(0)));
+        assert_eq!(menu.hit_test(30, 40), Some(Hit::Item(0)), "over the skull");
+        assert_eq!(menu.hit_test(10, 40), None);
+        // The thermometer starts at x 60 with an 8 pixel end; each step is 8 wide.
+        assert_eq!(menu.hit_test(60 + 8 + 3 * 8, 70), Some(Hit::Slider(1, 3)));
+        assert_eq!(menu.hit_test(62, 70), Some(Hit::Slider(1, 0)));
+        assert_eq!(menu.hit_test(150, 70), None, "past its right end");
+        assert_eq!(menu.hit_test(100, 37 + 3 * LINEHEIGHT), Some(Hit::Item(3)));
+    }
+
+    #[test]
+    fn the_mouse_hovers_clicks_and_drags() {
+        let mut menu = options();
+        let mut pointer = Pointer::default();
+        let event = |buttons, dx, dy| Event::Mouse { buttons, dx, dy };
+        pointer.warp(100, 37 + 3 * LINEHEIGHT);
+        assert_eq!(menu.mouse(&mut pointer, &event(0, 0, 0)), None);
+        assert_eq!(menu.selected, 3, "hovering selects");
+        assert_eq!(
+            menu.mouse(&mut pointer, &event(1, 0, 0)),
+            Some(MenuAction::Activate(3))
+        );
+        assert_eq!(menu.mouse(&mut pointer, &event(1, 0, 0)), None, "held");
+
+        // Press on the thermometer's first step, then drag right; dy is up the screen.
+        pointer.warp(70, 0);
+        assert_eq!(menu.mouse(&mut pointer, &event(0, 0, -70)), None);
+        assert_eq!(menu.selected, 1);
+        assert_eq!(
+            menu.mouse(&mut pointer, &event(1, 0, 0)),
+            Some(MenuAction::Slide(1, 0))
+        );
+        // Dragging keeps hold of the slider off its row, and stops at its last step.
+        assert_eq!(
+            menu.mouse(&mut pointer, &event(1, 200, 40)),
+            Some(MenuAction::Slide(1, 8))
+        );
+        assert_eq!(menu.mouse(&mut pointer, &event(0, 0, 0)), None);
+        assert_eq!(
+            menu.mouse(&mut pointer, &event(2, 0, 0)),
+            Some(MenuAction::Back)
+        );
+    }
+
+    #[test]
+    fn keys_skip_blank_rows_and_move_sliders() {
+        let mut menu = options();
+        assert_eq!(menu.responder(KEY_UPARROW), None);
+        assert_eq!(menu.selected, 3, "wraps to the bottom");
+        menu.responder(KEY_UPARROW);
+        assert_eq!(menu.selected, 1, "past the thermometer row");
+        assert_eq!(
+            menu.responder(KEY_RIGHTARROW),
+            Some(MenuAction::Slide(1, 5))
+        );
+        assert_eq!(menu.responder(KEY_ENTER), None);
+        menu.responder(KEY_DOWNARROW);
+        assert_eq!(menu.responder(KEY_ENTER), Some(MenuAction::Activate(3)));
+        assert_eq!(menu.responder(KEY_ESCAPE), Some(MenuAction::Back));
+    }
+}
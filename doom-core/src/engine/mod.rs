@@ -0,0 +1,2279 @@
+pub mod args;
+pub mod audit;
+pub mod bench;
+pub mod bindings;
+pub mod config;
+pub mod console;
+pub mod control;
+pub mod controls;
+pub mod crash;
+pub mod determinism;
+pub mod hotkeys;
+pub mod input;
+pub mod lan;
+pub mod log;
+pub mod memory;
+pub mod menu;
+pub mod options;
+pub mod pacing;
+pub mod profile;
+pub mod profiles;
+pub mod replay;
+pub mod session;
+pub mod speed;
+pub mod title;
+
+use crate::audio::captions::{CaptionOptions, Captions};
+use crate::audio::capture::{self, MusicCapture};
+use crate::audio::channels::{Channels, MixerPolicy, SoundCategory, SoundRequest};
+use crate::audio::jukebox::{Jukebox, Track};
+use crate::game::chat::{ChatInput, ChatMacros, ChatMessage, ChatReceiver, Response};
+use crate::game::cheat::Cheats;
+use crate::game::deathmatch::LevelEnd;
+use crate::game::demo::MAXPLAYERS;
+use crate::game::dropin::{self, Arrival, Departure, DropOptions, Handshake};
+use crate::game::fog::Fog;
+use crate::game::info::GameInfo;
+use crate::game::levelstat::{self, LevelStatLog};
+use crate::game::lifetime::{self, LifetimeStats};
+use crate::game::loading::{LoadPhase, LoadProgress};
+use crate::game::pause::{self, PauseState, PAUSE_PATCH};
+use crate::game::precache::{LevelCache, PrecacheOptions};
+use crate::game::quicksave::{self, QuickAction, QuickSaveOptions, SaveHeader, SaveSlots};
+use crate::game::rewind::{Keyframes, RewindOptions};
+use crate::game::savegame::{Archive, SaveReader, SaveWriter};
+#[cfg(feature = "scripting")]
+use crate::game::script::{ScriptEvent, Scripts};
+use crate::game::sector_sound::{self, Cue, Motion, MoverKind};
+use crate::game::spectator::{Camera, CameraInput, Spectator};
+use crate::game::strings::Strings;
+use crate::game::ticcmd::TicCmd;
+use crate::game::umapinfo::{NextMap, UMapInfo};
+use crate::game::vote::{self, Outcome, PendingBallot, Votes};
+use crate::game::GameMode;
+use crate::renderer::colorblind::{ColorScheme, KeyColor};
+use crate::renderer::crosshair::Crosshair;
+use crate::renderer::debug::{self, DebugOverlays};
+use crate::renderer::disk::{DiskIcon, DiskIconOptions};
+use crate::renderer::draw::{draw_patch, Canvas, Indexed};
+use crate::renderer::flash::{self, Flash, FlashOptions};
+use crate::renderer::fov::FieldOfView;
+use crate::renderer::framebuffer::{Palette, SCREENHEIGHT, SCREENWIDTH};
+use crate::renderer::gamma::{GammaOptions, GammaTable};
+use crate::renderer::hires;
+use crate::renderer::hud::{ExtendedHud, HudFont, LINE_HEIGHT};
+use crate::renderer::lighting::{self, LightOptions};
+use crate::renderer::overlay::{OverlayState, Overlays};
+use crate::renderer::patch::Patch;
+use crate::renderer::perf::{self, PerfCounters, PerfHud};
+use crate::renderer::present::PresentOptions;
+use crate::renderer::screen::Screen;
+use crate::renderer::screenshot::{ScreenshotConfig, Screenshots};
+use crate::renderer::spectre::{FuzzStyle, Spectres};
+use crate::renderer::text::{Text, TextColor, TextColors};
+use crate::renderer::text_entry::{EntryEvent, TextEntry};
+use crate::renderer::thumbnail::{Thumbnail, THUMBNAIL_WIDTH};
+use crate::renderer::tranmap::TranMap;
+use crate::renderer::translation::{Translation, Translations, MF_TRANSSHIFT};
+use crate::renderer::view::{self, BorderPatches, ViewSize};
+use crate::renderer::ViewPoint;
+use crate::util::angle::ANG45;
+use crate::util::fixed::{to_fixed, Fixed, FRACUNIT};
+use crate::wad;
+use crate::wad::file::Wads;
+use config::{Config, Configurable};
+use console::{Commands, Console, SharedCommands};
+use controls::{Controls, HeldKeys, QuickTurn};
+use hotkeys::{Hotkey, HotkeyState, Hotkeys};
+use input::{Event, Key, KEY_ESCAPE};
+use pacing::{Category, Interpolation};
+use session::{CheatAccess, Completion, GameSetup, Session, SessionState};
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+/// How far above the floor the player's eyes are, vanilla's `VIEWHEIGHT`.
+const VIEWHEIGHT: i32 = 41;
+
+/// Where vanilla's save menu lists the slots, `LINEHEIGHT` apart.
+const SAVE_X: i32 = 80;
+const SAVE_Y: i32 = 54;
+
+/// How far the console drops, in lines of text: half the screen.
+const CONSOLE_LINES: usize = SCREENHEIGHT / 2 / LINE_HEIGHT as usize;
+
+/// Takes a frame of palette indices drawn outside `render_into`, and the palette to show it in.
+pub type Presenter = Box<dyn FnMut(&[u8], &Palette)>;
+
+/// The game behind three calls, for frontends and tools that embed it: build one from the
+/// loaded WADs and config, feed it every player's ticcmd once per tic, and have it draw a
+/// frame whenever one is wanted. It never touches a window, the clock only to name a new
+/// savegame, and the filesystem only for the screenshots and savegames it is asked for; the
+/// caller decides when tics happen and what to do with the pixels.
+pub struct Engine {
+    wads: Wads,
+    config: Config,
+    controls: Controls,
+    keys: HeldKeys,
+    quick_turn: QuickTurn,
+    hotkeys: Hotkeys,
+    /// Hotkeys pressed since the frontend last asked, for the ones only it can carry out.
+    frontend_hotkeys: Vec<Hotkey>,
+    info: GameInfo,
+    session: Option<Session>,
+    save_slots: SaveSlots,
+    save_folder: PathBuf,
+    /// The slot a new save's description is being typed for. With no save menu yet this is
+    /// all of it the quick save key brings up.
+    save_entry: Option<(usize, TextEntry)>,
+    /// A question or refusal shown over the game until a key is pressed, vanilla's
+    /// `M_StartMessage`, and the quick save or load waiting on a yes.
+    menu_message: Option<String>,
+    quick_confirm: Option<(Hotkey, usize)>,
+    /// The game as it was every so often on this level, for the rewind key.
+    keyframes: Keyframes,
+    /// Whose eyes the view is through, or the free camera.
+    spectator: Spectator,
+    /// The cheat codes, and the music lumps `IDMUS` picks from.
+    cheats: Cheats,
+    jukebox: Jukebox,
+    /// The track `IDMUS` switched to, until the frontend takes it.
+    music: Option<Track>,
+    map_info: UMapInfo,
+    /// What the level shows and plays, decoded as it loads or on first use.
+    level_cache: LevelCache,
+    precache: PrecacheOptions,
+    /// The map being loaded and how far it has got, while a load runs.
+    loading: Option<(String, u32)>,
+    load_presenter: Option<Presenter>,
+    #[cfg(feature = "scripting")]
+    scripts: Scripts,
+    /// What happened since the last tic, for the script hooks to run on the next one.
+    #[cfg(feature = "scripting")]
+    script_events: Vec<ScriptEvent>,
+    /// Why the level ended on its own, once the deathmatch rules say it has.
+    level_end: Option<LevelEnd>,
+    palette: Palette,
+    /// Every PLAYPAL palette, and the one the screen flashes leave shown.
+    palettes: Vec<Palette>,
+    shown_palette: usize,
+    flash_options: FlashOptions,
+    colormaps: Vec<u8>,
+    doubled_light: bool,
+    tranmap: Option<TranMap>,
+    translations: Translations,
+    color_scheme: ColorScheme,
+    spectres: Spectres,
+    title: Option<Patch>,
+    /// The flat and bevel around a view shrunk below the full width.
+    border_flat: Option<[u8; 4096]>,
+    border_patches: Option<BorderPatches>,
+    font: Option<HudFont>,
+    text_colors: TextColors,
+    /// What is drawn over the view while a game is on.
+    overlays: Overlays,
+    /// What the last frame cost, for the performance overlay.
+    perf_counters: PerfCounters,
+    console: Console,
+    debug_overlays: Rc<RefCell<DebugOverlays>>,
+    music_capture: Rc<RefCell<MusicCapture>>,
+    sound_channels: Channels,
+    captions: Captions,
+    level_stats: Rc<RefCell<LevelStatLog>>,
+    lifetime_stats: Rc<RefCell<LifetimeStats>>,
+    disk: DiskIcon,
+    pause_patch: Option<Patch>,
+    screen: Screen,
+    screenshots: Screenshots,
+    interpolation: Interpolation,
+    /// How far through the last tic the next frame shows the world.
+    frame_fraction: Fixed,
+    gametic: i32,
+    /// Tics played in the level, which stand still while paused.
+    leveltime: i32,
+    pause: PauseState,
+    /// A ballot typed in the console, for the next ticcmd.
+    ballot: PendingBallot,
+    votes: Votes,
+    vote_outcome: Option<Outcome>,
+    chat: ChatInput,
+    chat_macros: ChatMacros,
+    chat_receiver: ChatReceiver,
+    chat_messages: Vec<ChatMessage>,
+    /// Who played the last tic, which is who chat can reach.
+    in_game: [bool; MAXPLAYERS],
+    /// Players who dropped out, whose slots the tic source still fills.
+    departed: [bool; MAXPLAYERS],
+    hash: u64,
+    cmds_hash: u64,
+}
+
+impl Engine {
+    pub fn new(wads: Wads, mut config: Config) -> Self {
+        Controls::register(&mut config);
+        Hotkeys::register(&mut config);
+        LightOptions::register(&mut config);
+        DiskIconOptions::register(&mut config);
+        ChatMacros::register(&mut config);
+        PerfHud::register(&mut config);
+        Crosshair::register(&mut config);
+        FlashOptions::register(&mut config);
+        ColorScheme::register(&mut config);
+        CaptionOptions::register(&mut config);
+        GammaOptions::register(&mut config);
+        QuickSaveOptions::register(&mut config);
+        RewindOptions::register(&mut config);
+        DropOptions::register(&mut config);
+        ViewSize::register(&mut config);
+        ExtendedHud::register(&mut config);
+        ScreenshotConfig::register(&mut config);
+        let controls = Controls::from_config(&config);
+        let hotkeys = Hotkeys::from_config(&config);
+        let chat_macros = ChatMacros::from_config(&config);
+        let flash_options = FlashOptions::from_config(&config);
+        let color_scheme = ColorScheme::from_config(&config);
+        let captions = Captions::new(CaptionOptions::from_config(&config));
+        let quicksave = QuickSaveOptions::from_config(&config);
+        let keyframes = Keyframes::new(RewindOptions::from_config(&config));
+        let mut save_slots = SaveSlots::new(quicksave.confirm);
+        save_slots.read_headers(&quicksave.folder);
+        let screenshots = Screenshots::new(ScreenshotConfig::from_config(&config));
+        let lump = |name: &str| wads.lump(name).map(|lump| lump.data.as_slice());
+        let playpal = lump("PLAYPAL").unwrap_or_default();
+        let palettes: Vec<Palette> = (0..)
+            .map_while(|index| Palette::from_playpal(playpal, index))
+            .collect();
+        let palette = palettes.first().cloned().unwrap_or_default();
+        let mut colormaps = lump("COLORMAP").unwrap_or_default().to_vec();
+        let mut doubled_light = false;
+        if LightOptions::from_config(&config).double_bands {
+            if let Some(doubled) = lighting::double_colormaps(&colormaps, &palette) {
+                colormaps = doubled;
+                doubled_light = true;
+            }
+        }
+        let patch = |name: &str| load_patch(&wads, &palette, name);
+        let title = patch("TITLEPIC");
+        let mode = GameMode::identify(|name| wads.lump(name).is_some());
+        let jukebox = Jukebox::scan(&wads);
+        let border_flat =
+            lump(view::border_flat(mode)).and_then(|flat| flat.get(..4096)?.try_into().ok());
+        let border_patches = BorderPatches::load(patch);
+        let font = HudFont::load(patch);
+        let text_colors = TextColors::load(lump);
+        let overlays = Overlays {
+            crosshair: Crosshair::from_config(&config),
+            hud: ExtendedHud::from_config(&config),
+            perf: PerfHud::from_config(&config),
+            font: font.clone(),
+            numbers: (color_scheme.numbers() != TextColor::Red)
+                .then(|| *text_colors.table(color_scheme.numbers())),
+            ..Overlays::default()
+        };
+        let pause_patch = patch(PAUSE_PATCH);
+        let disk_options = DiskIconOptions::from_config(&config);
+        let disk = DiskIcon::new(&disk_options, patch(disk_options.lump()));
+        let ballot = PendingBallot::default();
+        let debug_overlays = Rc::default();
+        let music_capture = Rc::default();
+        let level_stats = Rc::default();
+        let lifetime_stats = Rc::default();
+        let commands = Commands::shared();
+        {
+            let mut commands = commands.borrow_mut();
+            vote::register_commands(ballot.clone(), &mut commands);
+            log::register_command(&mut commands);
+            perf::register_command(&mut commands);
+            debug::register_command(Rc::clone(&debug_overlays), &mut commands);
+            capture::register_commands(Rc::clone(&music_capture), &mut commands);
+            levelstat::register_command(Rc::clone(&level_stats), &mut commands);
+            lifetime::register_command(Rc::clone(&lifetime_stats), &mut commands);
+        }
+        Self {
+            wads,
+            config,
+            controls,
+            keys: HeldKeys::default(),
+            quick_turn: QuickTurn::default(),
+            hotkeys,
+            frontend_hotkeys: Vec::new(),
+            info: GameInfo::default(),
+            session: None,
+            save_slots,
+            save_folder: quicksave.folder,
+            save_entry: None,
+            menu_message: None,
+            quick_confirm: None,
+            keyframes,
+            spectator: Spectator::new(0),
+            cheats: Cheats::new(mode),
+            jukebox,
+            music: None,
+            map_info: UMapInfo::default(),
+            level_cache: LevelCache::default(),
+            precache: PrecacheOptions::default(),
+            loading: None,
+            load_presenter: None,
+            #[cfg(feature = "scripting")]
+            scripts: Scripts::default(),
+            #[cfg(feature = "scripting")]
+            script_events: Vec::new(),
+            level_end: None,
+            palette,
+            palettes,
+            shown_palette: 0,
+            flash_options,
+            colormaps,
+            doubled_light,
+            tranmap: None,
+            translations: Translations::default(),
+            color_scheme,
+            spectres: Spectres::default(),
+            title,
+            border_flat,
+            border_patches,
+            font,
+            text_colors,
+            overlays,
+            perf_counters: PerfCounters::default(),
+            console: Console::new(commands, CONSOLE_LINES),
+            debug_overlays,
+            music_capture,
+            sound_channels: Channels::new(MixerPolicy::default()),
+            captions,
+            level_stats,
+            lifetime_stats,
+            disk,
+            pause_patch,
+            screen: Screen::new(PresentOptions::default(), SCREENWIDTH, SCREENHEIGHT),
+            screenshots,
+            interpolation: Interpolation::default(),
+            frame_fraction: FRACUNIT,
+            gametic: 0,
+            leveltime: 0,
+            pause: PauseState::default(),
+            ballot,
+            votes: Votes::default(),
+            vote_outcome: None,
+            chat: ChatInput::default(),
+            chat_macros,
+            chat_receiver: ChatReceiver::default(),
+            chat_messages: Vec::new(),
+            in_game: [true, false, false, false],
+            departed: [false; MAXPLAYERS],
+            hash: HASH_SEED,
+            cmds_hash: HASH_SEED,
+        }
+    }
+
+    pub fn wads(&self) -> &Wads {
+        &self.wads
+    }
+
+    /// For reloading changed files between tics; see `assets_changed`.
+    pub fn wads_mut(&mut self) -> &mut Wads {
+        &mut self.wads
+    }
+
+    /// Takes the names of lumps that changed on disk: the level cache forgets what it
+    /// decoded from them, and the title, pause and disk icon graphics are reloaded if any of
+    /// them are among them. Returns whether they were.
+    pub fn assets_changed(&mut self, names: &[String]) -> bool {
+        self.level_cache.invalidate(names);
+        let disk_options = DiskIconOptions::from_config(&self.config);
+        let ours = ["TITLEPIC", PAUSE_PATCH, disk_options.lump()];
+        if !names.iter().any(|name| ours.contains(&name.as_str())) {
+            return false;
+        }
+        let patch = |name: &str| load_patch(&self.wads, &self.palette, name);
+        let (title, pause_patch) = (patch("TITLEPIC"), patch(PAUSE_PATCH));
+        let disk = DiskIcon::new(&disk_options, patch(disk_options.lump()));
+        (self.title, self.pause_patch, self.disk) = (title, pause_patch, disk);
+        true
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    pub fn info(&self) -> &GameInfo {
+        &self.info
+    }
+
+    /// The game's tables, as DeHackEd and the game options left them.
+    pub fn set_info(&mut self, info: GameInfo) {
+        self.info = info;
+    }
+
+    /// The UMAPINFO of the loaded WADs, for where exits lead.
+    pub fn set_map_info(&mut self, map_info: UMapInfo) {
+        self.map_info = map_info;
+    }
+
+    /// A player dropped out of the netgame: out of the game from now on, their body left
+    /// standing or taken away as `net_keep_body` says. `None` if there is no game.
+    pub fn player_left(&mut self, player: usize) -> Option<Departure> {
+        let session = self.session.as_mut()?;
+        *self.departed.get_mut(player)? = true;
+        let options = DropOptions::from_config(&self.config);
+        let departure = session.player_left(player, &mut self.in_game, &options);
+        crate::info!("game", "player {} left the game", player + 1);
+        Some(departure)
+    }
+
+    /// A player let back in plays their old slot again. `None` if there is no game or
+    /// nowhere to put them.
+    pub fn player_joined(&mut self, player: usize) -> Option<Arrival> {
+        let session = self.session.as_mut()?;
+        if player >= MAXPLAYERS {
+            return None;
+        }
+        let arrival = session.player_joined(player, &mut self.in_game, &self.info)?;
+        self.departed[player] = false;
+        crate::info!("game", "player {} is back", player + 1);
+        Some(arrival)
+    }
+
+    /// The answer to a peer asking for its slot back in the netgame `session`: the game as
+    /// it stands, if the slot is free.
+    pub fn answer_rejoin(&self, request: (u32, u8), session: u32) -> Handshake {
+        let state = || {
+            let state = self.session.as_ref().map(Session::state);
+            state.map(|state| state.to_bytes()).unwrap_or_default()
+        };
+        dropin::answer_rejoin(request, session, self.gametic as u32, &self.in_game, state)
+    }
+
+    /// Starts a new game on `map`. Returns false if the map couldn't be loaded.
+    pub fn new_game(&mut self, setup: GameSetup, map: &str) -> bool {
+        self.session = Some(Session::new(setup, map, &self.info));
+        self.enter_level()
+    }
+
+    /// The game being played, if one was started.
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Ends the level and enters `next`, carrying over what the players keep. Returns false
+    /// if there is no game or the map couldn't be loaded.
+    pub fn exit_level(&mut self, next: &str) -> bool {
+        let Some(session) = &mut self.session else {
+            return false;
+        };
+        session.exit_level(next, &self.info);
+        self.enter_level()
+    }
+
+    /// Finishes the level through the exit, or the secret exit, and enters the map it leads
+    /// to unless the game is over. `None` if there is no game.
+    pub fn complete_level(&mut self, secret: bool) -> Option<Completion> {
+        let session = self.session.as_mut()?;
+        let completion = session.complete_level(secret, &self.map_info, &self.info);
+        if completion.next != NextMap::EndGame {
+            self.enter_level();
+        }
+        Some(completion)
+    }
+
+    /// Whether levels are precached as they load; `-nodemoprecache` turns it off.
+    pub fn set_precache(&mut self, precache: PrecacheOptions) {
+        self.precache = precache;
+    }
+
+    /// Loads the session's map into the level cache and starts it.
+    fn enter_level(&mut self) -> bool {
+        let Some(session) = &mut self.session else {
+            return false;
+        };
+        let map = session.map.clone();
+        let (loading, presenter) = (&mut self.loading, &mut self.load_presenter);
+        let (colormaps, font, palette) = (&self.colormaps, self.font.as_ref(), &self.palette);
+        let mut report = |phase: LoadPhase, percent| {
+            crate::debug!("level", "loading {map}: {} {percent}%", phase.name());
+            *loading = Some((map.clone(), percent));
+            if let Some(present) = presenter {
+                let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+                draw_loading(&mut frame, colormaps, font, &map, percent);
+                present(&frame, palette);
+            }
+        };
+        let mut progress = LoadProgress::new(&mut report);
+        let entered = session.enter_level(
+            &self.wads,
+            &self.info,
+            &self.map_info,
+            &self.level_cache,
+            &self.precache,
+            &mut progress,
+        );
+        session.spawn_players(&self.in_game);
+        self.loading = None;
+        self.start_level();
+        entered
+    }
+
+    /// Has each step of a level's load drawn as the loading screen and handed over, with the
+    /// palette to show it in, for a frontend that wants it on screen while the load runs.
+    pub fn set_load_presenter(&mut self, present: Presenter) {
+        self.load_presenter = Some(present);
+    }
+
+    fn start_level(&mut self) {
+        self.leveltime = 0;
+        self.keyframes.clear();
+        if self.spectator.is_free() {
+            self.spectator.camera = Camera::Player(self.spectator.followed);
+        }
+        self.level_end = None;
+        #[cfg(feature = "scripting")]
+        if let Some(session) = &self.session {
+            self.script_events = session.level_events();
+        }
+    }
+
+    /// The hooks run on what happens in the game, from the next tic on.
+    #[cfg(feature = "scripting")]
+    pub fn set_scripts(&mut self, scripts: Scripts) {
+        self.scripts = scripts;
+    }
+
+    /// Runs the hooks of everything that happened since the last tic, printing what went
+    /// wrong to the console.
+    #[cfg(feature = "scripting")]
+    fn run_scripts(&mut self) {
+        let events = core::mem::take(&mut self.script_events);
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let mut game = session::ScriptAccess {
+            session,
+            info: &self.info,
+            console: &mut self.console,
+            gametic: self.gametic,
+        };
+        for event in &events {
+            for error in self.scripts.fire(event, &mut game) {
+                game.console.print(error.to_string());
+            }
+        }
+    }
+
+    /// Set once a deathmatch's frag or time limit is reached, for the frontend to end the
+    /// level.
+    pub fn level_end(&self) -> Option<LevelEnd> {
+        self.level_end
+    }
+
+    pub fn gametic(&self) -> i32 {
+        self.gametic
+    }
+
+    pub fn leveltime(&self) -> i32 {
+        self.leveltime
+    }
+
+    /// Whether the game is paused, and by whom.
+    pub fn pause(&self) -> &PauseState {
+        &self.pause
+    }
+
+    /// The console's command registry, for registering commands backed by state the
+    /// frontend keeps.
+    pub fn commands(&self) -> &SharedCommands {
+        self.console.commands()
+    }
+
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+
+    /// Which debug overlays `r_debug` has switched on, for the view renderer.
+    pub fn debug_overlays(&self) -> Rc<RefCell<DebugOverlays>> {
+        Rc::clone(&self.debug_overlays)
+    }
+
+    /// The cheat codes, for DeHackEd to replace.
+    pub fn cheats(&mut self) -> &mut Cheats {
+        &mut self.cheats
+    }
+
+    /// The music `IDMUS` switched to since the last call, for the frontend to play.
+    pub fn take_music(&mut self) -> Option<Track> {
+        self.music.take()
+    }
+
+    /// What `musicdump` and `oplcapture` write, for the music code to report what it plays.
+    pub fn music_capture(&self) -> Rc<RefCell<MusicCapture>> {
+        Rc::clone(&self.music_capture)
+    }
+
+    /// Hands out the mixer channels by `policy` from now on.
+    pub fn set_mixer_policy(&mut self, policy: MixerPolicy) {
+        self.sound_channels = Channels::new(policy);
+    }
+
+    /// The mixer channels sounds are started on.
+    pub fn sound_channels(&mut self) -> &mut Channels {
+        &mut self.sound_channels
+    }
+
+    /// Starts the sound `name` on a mixer channel, returning which one, or `None` if the
+    /// policy found it none. A sound that gets a channel is captioned, as seen from the view,
+    /// if it is one `snd_captions` shows; `position` is where in the map it plays from.
+    pub fn start_sound(
+        &mut self,
+        name: &str,
+        request: SoundRequest,
+        position: Option<(Fixed, Fixed)>,
+    ) -> Option<usize> {
+        let channel = self.sound_channels.allocate(request)?;
+        let view = self.view_point();
+        self.captions
+            .sound_started(name, position, &view, self.gametic);
+        Some(channel)
+    }
+
+    /// Plays the sound of fog that just appeared, from where it is.
+    fn fog_sound(&mut self, fog: &Fog) {
+        let Some(name) = fog.sound else {
+            return;
+        };
+        let request = self.level_sound(name, fog.priority(), None);
+        self.start_sound(name, request, Some((fog.x, fog.y)));
+    }
+
+    /// A mover in `sector` doing `motion` this tic: its sound from the sector's sound
+    /// origin, or whatever plays there stopped. Returns the channel a new sound went on.
+    pub fn sector_sound(
+        &mut self,
+        sector: usize,
+        kind: MoverKind,
+        motion: Motion,
+    ) -> Option<usize> {
+        let session = self.session.as_ref()?;
+        let position = *session.sound_origins.get(sector)?;
+        let origin = sector_sound::origin(sector);
+        match sector_sound::cue(kind, motion, self.leveltime)? {
+            Cue::Start(name) => {
+                let request = self.level_sound(name, sector_sound::priority(name), Some(origin));
+                self.start_sound(name, request, Some(position))
+            }
+            Cue::Stop => {
+                self.sound_channels.stop_origin(origin);
+                None
+            }
+        }
+    }
+
+    /// A sound of the level, by lump name without `DS`.
+    fn level_sound(&self, name: &str, priority: i32, origin: Option<usize>) -> SoundRequest {
+        SoundRequest {
+            sound: self
+                .info
+                .sounds
+                .iter()
+                .position(|sound| sound == name)
+                .unwrap_or(0),
+            category: SoundCategory::Ambient,
+            priority,
+            origin,
+        }
+    }
+
+    /// Where the view is from: the followed player's eyes or the free camera.
+    fn view_point(&self) -> ViewPoint {
+        self.spectator.view(&self.player_views())
+    }
+
+    /// Where each player sees from. Without the playsim to move them that is their start on
+    /// the level, facing the way it does.
+    fn player_views(&self) -> [ViewPoint; MAXPLAYERS] {
+        let mut views = [ViewPoint::default(); MAXPLAYERS];
+        let things = self
+            .session
+            .iter()
+            .flat_map(|session| session.things.iter());
+        for start in things
+            .flatten()
+            .filter(|thing| (1..=4).contains(&thing.kind))
+        {
+            views[usize::from(start.kind) - 1] = ViewPoint {
+                x: to_fixed(i32::from(start.x)),
+                y: to_fixed(i32::from(start.y)),
+                z: to_fixed(VIEWHEIGHT),
+                angle: ANG45.wrapping_mul((start.angle / 45) as u32),
+            };
+        }
+        views
+    }
+
+    /// The results of each level played, which `levelstat` and `-levelstat` write out.
+    pub fn level_stats(&self) -> Rc<RefCell<LevelStatLog>> {
+        Rc::clone(&self.level_stats)
+    }
+
+    /// The totals of every session, which the frontend loads from and saves to `lifetime.txt`.
+    pub fn lifetime_stats(&self) -> Rc<RefCell<LifetimeStats>> {
+        Rc::clone(&self.lifetime_stats)
+    }
+
+    /// Where `vote::register_commands` puts the ballots typed in the console.
+    pub fn pending_ballot(&self) -> PendingBallot {
+        self.ballot.clone()
+    }
+
+    pub fn votes(&self) -> &Votes {
+        &self.votes
+    }
+
+    /// How the vote that ended on the last tic came out, for the HUD and for carrying it out.
+    pub fn vote_outcome(&self) -> Option<Outcome> {
+        self.vote_outcome
+    }
+
+    /// The message being typed, for the HUD to show while `is_open`.
+    pub fn chat(&self) -> &ChatInput {
+        &self.chat
+    }
+
+    /// The chat messages for the console player that arrived on the last tic.
+    pub fn chat_messages(&self) -> &[ChatMessage] {
+        &self.chat_messages
+    }
+
+    /// The palette `render_into` output is meant to be shown with, before `gamma_table`: the
+    /// first one, or the tint of a screen flash as `screen_flashes` lets it through.
+    pub fn palette(&self) -> &Palette {
+        self.palettes
+            .get(self.shown_palette)
+            .unwrap_or(&self.palette)
+    }
+
+    /// The gamma correction to show `palette` with, from `usegamma` and the continuous
+    /// settings; read each time so the console can change them.
+    pub fn gamma_table(&self) -> GammaTable {
+        GammaOptions::from_config(&self.config).table()
+    }
+
+    /// The light level to draw a sector at, for the view renderer: `steady_lights` holds a
+    /// blinking or strobing light at `steady`.
+    pub fn shown_light(&self, level: i16, steady: Option<i16>) -> i16 {
+        self.flash_options.shown_light(level, steady)
+    }
+
+    /// A hash of everything the simulation has done so far, compared between runs to catch
+    /// desyncs. It covers every player's ticcmd of every tic run; as more of the game
+    /// moves into `run_tic` its state belongs in here too.
+    pub fn tic_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The parts of the state `tic_hash` covers, hashed apiece, so an audit can say which
+    /// one two runs disagree on.
+    pub fn state_hashes(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("gametic", self.gametic as u64),
+            ("leveltime", self.leveltime as u64),
+            ("ticcmds", self.cmds_hash),
+        ]
+    }
+
+    /// Whether running is the default, for the HUD.
+    pub fn always_run(&self) -> bool {
+        self.controls.always_run
+    }
+
+    /// Draws frames between tics as `interpolation` says. Without it each tic is drawn as it
+    /// ended.
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Sets the next frame `fraction` of the way through the last tic, from the pacer's clock.
+    pub fn set_frame_time(&mut self, fraction: Fixed) {
+        self.frame_fraction = self.interpolation.fraction(self.gametic as u64, fraction);
+    }
+
+    /// The fraction the frame draws `category` at, for the view renderer.
+    pub fn frame_fraction(&self, category: Category) -> Fixed {
+        self.interpolation.of(category, self.frame_fraction)
+    }
+
+    /// The render buffer with its view window and projection.
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Applies the field of view to the projection the view is drawn with.
+    pub fn set_fov(&mut self, fov: FieldOfView) {
+        self.screen.set_fov(fov);
+    }
+
+    /// The table translucent drawing blends through. Without one the top pixel wins.
+    pub fn set_tranmap(&mut self, tranmap: TranMap) {
+        self.tranmap = Some(tranmap);
+    }
+
+    /// The player colors, vanilla's with those of any `PCOLORS` lump added.
+    pub fn set_translations(&mut self, translations: Translations) {
+        self.translations = translations;
+    }
+
+    /// The table a player's sprites are drawn through: none for the first player's green,
+    /// then the colors in order, as the player number sits in a mobj's translation bits.
+    pub fn player_translation(&self, player: usize) -> Option<&Translation> {
+        self.translations
+            .for_flags((player as u32) << MF_TRANSSHIFT)
+    }
+
+    /// The table blood sprites are drawn through, if the color scheme changes them.
+    pub fn blood_translation(&self) -> Option<Translation> {
+        self.color_scheme.blood()
+    }
+
+    /// The table the sprite and status bar icon of key `card` (vanilla's `card_t`) are drawn
+    /// through, if the color scheme changes them.
+    pub fn key_translation(&self, card: usize) -> Option<Translation> {
+        self.color_scheme.key(KeyColor::of_card(card))
+    }
+
+    /// How spectres are drawn, as strict mode and demo playback hold it.
+    pub fn set_fuzz_style(&mut self, style: FuzzStyle) {
+        self.spectres = Spectres::new(style);
+    }
+
+    /// The spectre column drawer, with its fuzz position carried between sprites.
+    pub fn spectres(&mut self) -> &mut Spectres {
+        &mut self.spectres
+    }
+
+    /// `-turbo`, in percent of the normal movement speeds.
+    pub fn set_turbo(&mut self, percent: u32) {
+        self.controls.set_turbo(percent);
+    }
+
+    /// Takes a key, mouse or joystick event from the frontend. The screenshot key works
+    /// everywhere. The console's toggle comes next, and while it is open it has the keyboard
+    /// to itself. In a netgame the chat keys come next, and while a message is being typed
+    /// they have the keyboard to themselves. The cheats see every key typed after that.
+    pub fn responder(&mut self, event: &Event) {
+        if self.screenshots.responder(event) {
+            return;
+        }
+        if let Event::KeyDown(key) = *event {
+            if self.save_responder(key) {
+                return;
+            }
+        }
+        if self.console.responder(event, &mut self.config) {
+            // A key let go while the console was open was still held in the game.
+            if let Event::KeyUp(_) = event {
+                self.keys.responder(event);
+            }
+            return;
+        }
+        let players = self.in_game.iter().filter(|&&playing| playing).count();
+        if players > 1 || self.chat.is_open() {
+            let response = self
+                .chat
+                .responder(event, &self.in_game, 0, &self.chat_macros);
+            if response != Response::Ignored {
+                return;
+            }
+        }
+        if let Event::KeyDown(key) = *event {
+            self.cheat_responder(key);
+            let state = HotkeyState {
+                chat_active: self.chat.is_open(),
+                ..HotkeyState::default()
+            };
+            if let Some(hotkey) = self.hotkeys.responder(key, &state) {
+                if self.hotkey(hotkey) {
+                    return;
+                }
+            }
+        }
+        self.keys.responder(event);
+    }
+
+    /// Carries out a hotkey, returning whether it was taken. Pause isn't: it goes out in the
+    /// ticcmd, and to the frontend for pausing demo playback.
+    fn hotkey(&mut self, hotkey: Hotkey) -> bool {
+        match hotkey {
+            Hotkey::Pause => {
+                self.frontend_hotkeys.push(hotkey);
+                false
+            }
+            Hotkey::DemoSlower | Hotkey::DemoFaster | Hotkey::DemoStep | Hotkey::JoinDemo => {
+                self.frontend_hotkeys.push(hotkey);
+                true
+            }
+            Hotkey::Gamma => {
+                let mut gamma = GammaOptions::from_config(&self.config);
+                let message = gamma.cycle();
+                gamma.to_config(&mut self.config);
+                crate::info!("renderer", "{}", Strings::new().get(message));
+                true
+            }
+            Hotkey::ScreenLarger | Hotkey::ScreenSmaller => {
+                // The next frame picks the new size up, as vanilla's does.
+                let mut view = ViewSize::from_config(&self.config);
+                match hotkey {
+                    Hotkey::ScreenLarger => view.grow(),
+                    _ => view.shrink(),
+                };
+                view.to_config(&mut self.config);
+                true
+            }
+            Hotkey::Detail => {
+                let mut view = ViewSize::from_config(&self.config);
+                let message = view.toggle_detail();
+                view.to_config(&mut self.config);
+                crate::info!("renderer", "{}", Strings::new().get(message));
+                true
+            }
+            Hotkey::QuickSave => {
+                let session = self.session.as_ref();
+                let playing = session.is_some_and(|session| session.players[0].health > 0);
+                let action = self.save_slots.quicksave(playing);
+                self.quick_action(hotkey, action);
+                true
+            }
+            Hotkey::QuickLoad => {
+                let netgame = self
+                    .session
+                    .as_ref()
+                    .is_some_and(|session| session.setup.netgame);
+                let action = self.save_slots.quickload(netgame);
+                self.quick_action(hotkey, action);
+                true
+            }
+            Hotkey::Rewind => {
+                if let Some(tic) = self.rewind() {
+                    crate::info!("game", "rewound to tic {tic}");
+                }
+                true
+            }
+            Hotkey::Spy => {
+                self.spectator.spy(&self.in_game);
+                true
+            }
+            Hotkey::FreeCamera => {
+                let session = self.session.as_ref();
+                let allowed = session.is_some_and(|session| {
+                    let setup = &session.setup;
+                    let dead = session.players[0].health <= 0;
+                    Spectator::free_camera_allowed(setup.demo_playback, setup.netgame, dead)
+                });
+                if !allowed && !self.spectator.is_free() {
+                    return false;
+                }
+                let view = self.view_point();
+                self.spectator.toggle_free(view);
+                true
+            }
+            Hotkey::ExtendedHud => {
+                self.overlays.hud.cycle();
+                self.overlays.hud.to_config(&mut self.config);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// `ST_Responder`: every key typed during a game goes to the cheats, and on to the rest
+    /// of the game too. `IDCLEV` starts a new game on its map, as vanilla does.
+    fn cheat_responder(&mut self, key: Key) {
+        let view = self.view_point();
+        let mode = GameMode::identify(|name| self.wads.lump(name).is_some());
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let (netgame, skill) = (session.setup.netgame, session.setup.skill);
+        let mut access = CheatAccess {
+            session,
+            info: &self.info,
+            jukebox: &mut self.jukebox,
+            mode,
+            view,
+            warp: None,
+            music: false,
+        };
+        let message = self.cheats.responder(key, netgame, skill, &mut access);
+        let (warp, music) = (access.warp, access.music);
+        if let Some(message) = message {
+            crate::info!("game", "{}", Strings::new().get(&message));
+        }
+        if music {
+            self.music = self.jukebox.current().cloned();
+        }
+        if let Some(map) = warp {
+            let setup = session.setup.clone();
+            self.new_game(setup, &map);
+        }
+    }
+
+    /// Takes every key while a save description is typed or a message is up.
+    fn save_responder(&mut self, key: Key) -> bool {
+        if self.menu_message.is_some() {
+            let confirm = self.quick_confirm;
+            match key {
+                _ if confirm.is_none() => self.menu_message = None,
+                KEY_ESCAPE => self.menu_message = None,
+                _ if key == Key::from(b'n') => self.menu_message = None,
+                _ if key == Key::from(b'y') => {
+                    self.menu_message = None;
+                    if let Some((hotkey, slot)) = confirm {
+                        self.quick_proceed(hotkey, slot);
+                    }
+                }
+                _ => {}
+            }
+            if self.menu_message.is_none() {
+                self.quick_confirm = None;
+            }
+            return true;
+        }
+        let Some((slot, entry)) = &mut self.save_entry else {
+            return false;
+        };
+        let slot = *slot;
+        match entry.responder(key) {
+            Some(EntryEvent::Accepted(description)) => {
+                self.save_entry = None;
+                self.save_game(slot, description);
+            }
+            Some(EntryEvent::Cancelled) => {
+                self.save_entry = None;
+                self.save_slots.cancelled();
+            }
+            None => {}
+        }
+        true
+    }
+
+    fn quick_action(&mut self, hotkey: Hotkey, action: QuickAction) {
+        let strings = Strings::new();
+        match action {
+            QuickAction::Refused(message) => {
+                self.menu_message = Some(strings.get(message).to_owned());
+            }
+            // There is no save menu to pick the slot in, so the first empty one is named.
+            QuickAction::PickSlot => {
+                let headers = &self.save_slots.headers;
+                let slot = headers.iter().position(Option::is_none).unwrap_or(0);
+                let map = self.session.as_ref().map_or("", |session| &session.map);
+                let suggestion = self.save_slots.suggestion(slot, map, SystemTime::now());
+                let entry = TextEntry::with_text(&suggestion, quicksave::MAX_DESCRIPTION);
+                self.save_entry = Some((slot, entry));
+            }
+            QuickAction::Confirm {
+                prompt,
+                slot,
+                description,
+            } => {
+                self.menu_message = Some(strings.get(prompt).replace("%s", &description));
+                self.quick_confirm = Some((hotkey, slot));
+            }
+            QuickAction::Proceed { slot } => self.quick_proceed(hotkey, slot),
+        }
+    }
+
+    fn quick_proceed(&mut self, hotkey: Hotkey, slot: usize) {
+        if hotkey == Hotkey::QuickSave {
+            let description = self.save_slots.description(slot).unwrap_or_default();
+            self.save_game(slot, description.to_owned());
+        } else {
+            self.load_game(slot);
+        }
+    }
+
+    /// Saves the game to `slot` as `description`, with a thumbnail of the last frame drawn.
+    /// Returns false, having logged why, if there is no game or the file couldn't be written.
+    pub fn save_game(&mut self, slot: usize, description: String) -> bool {
+        let Some(session) = &self.session else {
+            return false;
+        };
+        let header = SaveHeader {
+            description,
+            thumbnail: Some(Thumbnail::capture(&self.screen.frame, &self.palette)),
+        };
+        let mut writer = SaveWriter::new();
+        writer.archive(&header);
+        writer.archive(&session.state());
+        let path = quicksave::save_path(&self.save_folder, slot);
+        let written =
+            fs::create_dir_all(&self.save_folder).and_then(|()| fs::write(&path, writer.data));
+        if let Err(error) = written {
+            crate::warn!("game", "couldn't write {}: {error}", path.display());
+            return false;
+        }
+        crate::info!("game", "{}", Strings::new().get("GGSAVED"));
+        self.save_slots.saved(slot, header);
+        true
+    }
+
+    /// Loads the game saved in `slot`, entering its map first if that isn't the one being
+    /// played. A game is started for it if there is none. Returns false, having logged why,
+    /// if the save couldn't be read or its map loaded.
+    pub fn load_game(&mut self, slot: usize) -> bool {
+        let path = quicksave::save_path(&self.save_folder, slot);
+        let state = fs::read(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|data| {
+                let mut reader = SaveReader::new(&data);
+                reader
+                    .unarchive::<SaveHeader>()
+                    .map_err(|error| error.to_string())?;
+                let state = reader.unarchive::<SessionState>();
+                let state = state.and_then(|state| reader.finish().map(|()| state));
+                state.map_err(|error| error.to_string())
+            });
+        let state = match state {
+            Ok(state) => state,
+            Err(error) => {
+                crate::warn!("game", "couldn't load {}: {error}", path.display());
+                return false;
+            }
+        };
+        if self.session.as_ref().map(|session| &session.map) != Some(&state.map) {
+            match &mut self.session {
+                Some(session) => session.map = state.map.clone(),
+                None => {
+                    let session = Session::new(GameSetup::default(), &state.map, &self.info);
+                    self.session = Some(session);
+                }
+            }
+            if !self.enter_level() {
+                return false;
+            }
+        }
+        if let Some(session) = &mut self.session {
+            self.leveltime = state.stats.level_time;
+            session.restore(state);
+        }
+        self.keyframes.clear();
+        true
+    }
+
+    /// How often the game is kept for the rewind key and how far back it goes, as strict
+    /// mode holds them. Keyframes already taken are dropped.
+    pub fn set_rewind(&mut self, options: RewindOptions) {
+        self.keyframes = Keyframes::new(options);
+    }
+
+    /// Goes back to the newest keyframe at least one interval old. Returns the tic it was
+    /// taken on, or `None` if there is none to go back to.
+    fn rewind(&mut self) -> Option<i32> {
+        let session = self.session.as_mut()?;
+        match self.keyframes.restore::<SessionState>(self.gametic)? {
+            Ok((tic, state)) => {
+                self.leveltime = state.stats.level_time;
+                session.restore(state);
+                Some(tic)
+            }
+            Err(error) => {
+                crate::warn!("game", "couldn't rewind: {error}");
+                None
+            }
+        }
+    }
+
+    /// The hotkeys pressed since the last call that act on what the frontend owns: the demo
+    /// playback controls and joining the demo.
+    pub fn take_hotkeys(&mut self) -> Vec<Hotkey> {
+        core::mem::take(&mut self.frontend_hotkeys)
+    }
+
+    /// The console player's command for the next tic, from the keys held right now and the
+    /// ones pressed since the last command. Pause, votes and chat go out as commands too.
+    /// While the free camera is up the movement keys fly it and move no player.
+    pub fn build_ticcmd(&mut self) -> TicCmd {
+        if self.controls.toggle_autorun(&self.keys) {
+            let always_run = self.controls.always_run;
+            self.config.set_bool("autorun", always_run);
+        }
+        let mut cmd = self.controls.build_ticcmd(&self.keys);
+        let quick_turn = self.quick_turn.tic(self.controls.quick_turn(&self.keys));
+        cmd.angleturn = cmd.angleturn.wrapping_add(quick_turn);
+        if self.spectator.is_free() {
+            // The movement keys fly the free camera instead.
+            cmd = TicCmd::default();
+        }
+        if self.keys.was_pressed(self.hotkeys.key(Hotkey::Pause)) {
+            cmd = pause::with_pause(cmd);
+        } else if let Some(ballot) = self.ballot.take() {
+            cmd = vote::with_ballot(cmd, ballot);
+        }
+        if !vote::is_ballot(&cmd) {
+            // Chat waits a tic behind a ballot, which has the `chatchar`.
+            cmd.chatchar = self.chat.next_char();
+        }
+        self.keys.end_tic();
+        cmd
+    }
+
+    /// Runs one game tic with one command per player in the game, console player first. A
+    /// pause command from any of them pauses or unpauses; while paused only `gametic` moves.
+    /// Votes are counted, chat delivered and script hooks run whether paused or not.
+    pub fn run_tic(&mut self, cmds: &[TicCmd]) {
+        self.console.ticker();
+        if let Some(&cmd) = cmds.first() {
+            crash::update(|snapshot| snapshot.record_tic(self.gametic, cmd));
+        }
+        for cmd in cmds {
+            let [turn_lo, turn_hi] = cmd.angleturn.to_le_bytes();
+            let bytes = [
+                cmd.forwardmove as u8,
+                cmd.sidemove as u8,
+                turn_lo,
+                turn_hi,
+                cmd.buttons,
+            ];
+            self.hash = hash_bytes(self.hash, &bytes);
+            self.cmds_hash = hash_bytes(self.cmds_hash, &bytes);
+        }
+        self.gametic += 1;
+        self.hash = hash_bytes(self.hash, &self.gametic.to_le_bytes());
+        let mut in_game = [false; MAXPLAYERS];
+        in_game[..cmds.len().min(MAXPLAYERS)].fill(true);
+        for (playing, departed) in in_game.iter_mut().zip(self.departed) {
+            *playing &= !departed;
+        }
+        self.vote_outcome = self.votes.ticker(self.gametic, cmds, &in_game);
+        self.captions.ticker(self.gametic);
+        let camera = CameraInput::from_keys(&self.controls, &self.keys);
+        self.spectator.ticker(&camera);
+        self.chat_messages = self.chat_receiver.ticker(cmds, &in_game, 0);
+        self.in_game = in_game;
+        if !self.pause.ticker(cmds) {
+            self.leveltime += 1;
+            if let Some(session) = &mut self.session {
+                session.stats.tic();
+                self.keyframes.ticker(self.gametic, &session.state());
+            }
+        }
+        self.hash = hash_bytes(self.hash, &self.leveltime.to_le_bytes());
+        #[cfg(feature = "scripting")]
+        self.run_scripts();
+        let fog = self
+            .session
+            .as_mut()
+            .map(|session| core::mem::take(&mut session.fog));
+        for fog in fog.into_iter().flatten() {
+            self.fog_sound(&fog);
+        }
+        if let Some(session) = self
+            .session
+            .as_ref()
+            .filter(|session| session.setup.deathmatch)
+        {
+            let rules = &session.setup.deathmatch_rules;
+            let end = rules.level_end(&session.frags, &in_game, self.leveltime);
+            self.level_end = self.level_end.or(end);
+        }
+    }
+
+    /// Draws the current frame as `SCREENWIDTH` x `SCREENHEIGHT` palette indices, row by
+    /// row. While a level loads this is the loading screen. Until there is a level to show
+    /// it is the title screen, sized as `screenblocks` and `detaillevel` say and with the
+    /// overlays and the sound captions over it while a game is on, the chat line being typed
+    /// and any screen flash drawn as a border if that is the style. Then come the savegame
+    /// name being typed or a waiting question, the pause patch while paused, the disk icon
+    /// after a WAD read and the console on top, blended through the translucency table if
+    /// there is one. Panics if `out` is not exactly that size.
+    pub fn render_into(&mut self, out: &mut [u8]) {
+        if let Some((map, percent)) = &self.loading {
+            draw_loading(out, &self.colormaps, self.font.as_ref(), map, *percent);
+            return;
+        }
+        self.screen.frame.fill(0);
+        let format = match self.doubled_light {
+            true => Indexed::doubled(&self.colormaps),
+            false => Indexed::new(&self.colormaps),
+        };
+        let format = match &self.tranmap {
+            Some(tranmap) => format.map(|format| format.with_tranmap(tranmap)),
+            None => format,
+        };
+        let disk_shown = self.disk.update(wad::reads());
+        // `stat` changes the mode through the config.
+        self.overlays.perf.mode = PerfHud::from_config(&self.config).mode;
+        // So do `screenblocks` and `detaillevel`, taking effect here between frames.
+        let view_size = ViewSize::from_config(&self.config);
+        if view_size != *self.screen.view_size() {
+            self.screen.set_view_size(view_size);
+        }
+        // There are no damage or bonus counts without the playsim, so only powers flash.
+        let powers = self
+            .session
+            .as_ref()
+            .map(|session| &session.players[0].powers);
+        let flash = powers.and_then(|powers| Flash::vanilla(0, 0, powers));
+        self.shown_palette = self.flash_options.palette(flash);
+        let overlay_state = self.session.as_ref().map(|session| OverlayState {
+            health: session.players[0].health,
+            stats: session.stats,
+            view: self.view_point(),
+            perf: self.perf_counters,
+            always_run: self.controls.always_run,
+            ..OverlayState::default()
+        });
+        if let Some(format) = format {
+            let mut canvas = Canvas {
+                pixels: self.screen.frame.pixels_mut(),
+                pitch: SCREENWIDTH,
+            };
+            if let Some(title) = &self.title {
+                draw_patch(&format, &mut canvas, 0, 0, title, 0);
+            }
+            if overlay_state.is_some() {
+                // The title stands in for the view, drawn at the detail and size set.
+                let window = &self.screen.window;
+                view::expand_low_detail(&mut canvas, window);
+                if let Some(flat) = &self.border_flat {
+                    let bevel = self.border_patches.as_ref();
+                    view::fill_border(&format, &mut canvas, window, flat, bevel);
+                }
+            }
+            if let Some(state) = &overlay_state {
+                let window = &self.screen.window;
+                self.overlays.draw(&format, &mut canvas, window, state);
+            }
+            if let (Some(font), Some(entry)) = (&self.font, self.chat.entry()) {
+                // `HU_INPUTY`: under the message line.
+                entry.draw(&format, &mut canvas, 0, LINE_HEIGHT, font);
+            }
+            if let (Some(font), Some(_)) = (&self.font, &overlay_state) {
+                let text = Text::new(font, &self.text_colors);
+                let window = &self.screen.window;
+                let bottom = (window.y + window.height) as i32 - LINE_HEIGHT;
+                crate::renderer::captions::draw(
+                    &format,
+                    &mut canvas,
+                    &text,
+                    &self.captions,
+                    bottom,
+                );
+            }
+            if let Some(flash) = self.flash_options.border(flash) {
+                flash::draw_border(&format, &mut canvas, flash);
+            }
+            if let (Some(font), Some((slot, entry))) = (&self.font, &self.save_entry) {
+                let y = SAVE_Y + *slot as i32 * menu::LINEHEIGHT;
+                entry.draw(&format, &mut canvas, SAVE_X, y, font);
+                // Whatever the slot holds now, centered above the slots.
+                let x = (SCREENWIDTH - THUMBNAIL_WIDTH) as i32 / 2;
+                self.save_slots
+                    .draw_thumbnail(&format, &mut canvas, *slot, x, 2);
+            }
+            if let (Some(font), Some(message)) = (&self.font, &self.menu_message) {
+                let text = Text::new(font, &self.text_colors);
+                let lines = message.lines().count() as i32;
+                let top = (SCREENHEIGHT as i32 - lines * LINE_HEIGHT) / 2;
+                for (i, line) in message.lines().enumerate() {
+                    text.draw_centered(&format, &mut canvas, top + i as i32 * LINE_HEIGHT, line);
+                }
+            }
+            if let Some(patch) = self.pause_patch.as_ref().filter(|_| self.pause.paused) {
+                // Centered at the top, where vanilla draws it over the view.
+                let x = (SCREENWIDTH as i32 - i32::from(patch.width)) / 2;
+                draw_patch(
+                    &format,
+                    &mut canvas,
+                    x + i32::from(patch.left_offset),
+                    4,
+                    patch,
+                    0,
+                );
+            }
+            if disk_shown {
+                self.disk.draw(&format, &mut canvas);
+            }
+            if let Some(font) = &self.font {
+                let text = Text::new(font, &self.text_colors);
+                crate::renderer::console::draw(&format, &mut canvas, &text, &self.console);
+            }
+        }
+        out.copy_from_slice(&self.screen.frame.pixels()[..SCREENWIDTH * SCREENHEIGHT]);
+    }
+
+    /// Takes what the frame about to be drawn cost the frontend: the time since the last one
+    /// and the part of it spent running tics. The view renderer's counts stay at zero until
+    /// there is one.
+    pub fn record_frame(&mut self, frame_time: Duration, tic_time: Duration) {
+        self.perf_counters = PerfCounters {
+            frame_time,
+            tic_time,
+            cache_bytes: self.level_cache.bytes(),
+            ..PerfCounters::default()
+        };
+        self.overlays.perf.record(&self.perf_counters);
+    }
+
+    /// Writes the frame `render_into` last drew to the screenshot folder if the screenshot
+    /// key asked for it since. Returns the file written.
+    pub fn capture_screenshot(&mut self) -> Option<PathBuf> {
+        let shot = self
+            .screenshots
+            .capture(&self.screen.frame, &self.palette, self.gametic as u32);
+        match shot {
+            Ok(Some(path)) => {
+                crate::info!("renderer", "wrote {}", path.display());
+                Some(path)
+            }
+            Ok(None) => None,
+            Err(error) => {
+                crate::warn!("renderer", "couldn't write a screenshot: {error}");
+                None
+            }
+        }
+    }
+}
+
+/// The loading screen for `map`, `percent` of the way through, over black.
+fn draw_loading(out: &mut [u8], colormaps: &[u8], font: Option<&HudFont>, map: &str, percent: u32) {
+    out.fill(0);
+    let (Some(format), Some(font)) = (Indexed::new(colormaps), font) else {
+        return;
+    };
+    let mut canvas = Canvas {
+        pixels: out,
+        pitch: SCREENWIDTH,
+    };
+    crate::renderer::loading::draw(&format, &mut canvas, font, map, percent);
+}
+
+/// The graphic `name` as a patch, from its lump or a PNG replacing it.
+fn load_patch(wads: &Wads, palette: &Palette, name: &str) -> Option<Patch> {
+    wads.graphic(name)
+        .and_then(|graphic| hires::load_patch(graphic, palette))
+}
+
+const HASH_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, folded into a running hash.
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::captions::CAPTION_TICS;
+    use crate::engine::input::Key;
+    use crate::game::chat;
+    use crate::game::cheat::{Power, CF_GODMODE};
+    use crate::game::deathmatch::DeathmatchRules;
+    use crate::game::TICRATE;
+    use crate::renderer::draw::INVERSECOLORMAP;
+    use crate::renderer::hud::{HU_FONTEND, HU_FONTSTART};
+    use crate::wad::file::{Lump, WadFile, WadKind};
+    use crate::wad::map::Thing;
+    use crate::wad::replace::Replacements;
+    use std::path::PathBuf;
+
+    fn wads() -> Wads {
+        #[rustfmt::skip]
+        let title = vec![
+            1, 0, 1, 0, 0, 0, 0, 0, // 1x1, no offsets
+            12, 0, 0, 0, // column offset
+            0, 1, 0, 7, 0, 0xff, // one pixel of color 7
+        ];
+        let lump = |name: &str, data: Vec<u8>| Lump {
+            name: name.to_string(),
+            data,
+        };
+        let mut wads = Wads::new();
+        wads.add(WadFile {
+            path: PathBuf::from("test.wad"),
+            kind: WadKind::Iwad,
+            lumps: vec![
+                lump(
+                    "COLORMAP",
+                    (0..=255)
+                        .cycle()
+                        .take((INVERSECOLORMAP + 1) * 256)
+                        .collect(),
+                ),
+                lump("TITLEPIC", title.clone()),
+                lump("M_PAUSE", {
+                    let mut pause = title;
+                    pause[15] = 9;
+                    pause
+                }),
+            ],
+        });
+        wads
+    }
+
+    /// `wads` with a HUD font of 1x1 glyphs in color 5.
+    fn with_font(mut wads: Wads) -> Wads {
+        let glyph = vec![1, 0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0, 1, 0, 5, 0, 0xff];
+        wads.add(WadFile {
+            path: PathBuf::from("font.wad"),
+            kind: WadKind::Pwad,
+            lumps: (HU_FONTSTART..=HU_FONTEND)
+                .map(|c| Lump {
+                    name: format!("STCFN{c:03}"),
+                    data: glyph.clone(),
+                })
+                .collect(),
+        });
+        wads
+    }
+
+    #[test]
+    fn tics_advance_and_frames_show_the_title() {
+        let mut engine = Engine::new(wads(), Config::new());
+        engine.responder(&Event::KeyDown(input::KEY_UPARROW));
+        assert_eq!(engine.build_ticcmd().forwardmove, 0x19);
+        let cmd = engine.build_ticcmd();
+        engine.run_tic(&[cmd]);
+        engine.run_tic(&[]);
+        assert_eq!(engine.gametic(), 2);
+
+        let mut frame = vec![0xffu8; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[0], 7);
+        assert!(frame[1..].iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn pause_goes_through_the_ticcmds() {
+        let mut engine = Engine::new(wads(), Config::new());
+        engine.run_tic(&[TicCmd::default()]);
+        engine.responder(&Event::KeyDown(input::KEY_PAUSE));
+        let pause = engine.build_ticcmd();
+        assert!(pause::is_pause(&pause));
+        engine.run_tic(&[TicCmd::default(), pause]);
+        let cmd = engine.build_ticcmd();
+        engine.run_tic(&[cmd]);
+        assert!(engine.pause().paused);
+        assert_eq!((engine.gametic(), engine.leveltime()), (3, 1));
+
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[4 * SCREENWIDTH + 159], 9);
+
+        // The tic that unpauses runs, as in vanilla.
+        engine.run_tic(&[pause]);
+        assert!(!engine.pause().paused);
+        assert_eq!(engine.leveltime(), 2);
+        engine.render_into(&mut frame);
+        assert_eq!(frame[4 * SCREENWIDTH + 159], 0);
+    }
+
+    #[test]
+    fn votes_go_out_in_the_ticcmds() {
+        let mut engine = Engine::new(wads(), Config::new());
+        *engine.pending_ballot().borrow_mut() =
+            Some(vote::Ballot::Call(vote::Proposal::Kick { player: 1 }));
+        let call = engine.build_ticcmd();
+        assert!(vote::ballot(&engine.build_ticcmd()).is_none());
+        engine.run_tic(&[call, TicCmd::default(), TicCmd::default()]);
+        assert_eq!(engine.votes().current.unwrap().caller, 0);
+        // Player 2 is the one being kicked, so player 3's yes carries it.
+        let yes = vote::with_ballot(TicCmd::default(), vote::Ballot::Yes);
+        engine.run_tic(&[TicCmd::default(), TicCmd::default(), yes]);
+        assert!(engine.votes().current.is_none());
+        let kick = vote::Proposal::Kick { player: 1 };
+        assert_eq!(engine.vote_outcome(), Some(Outcome::Passed(kick)));
+        engine.run_tic(&[TicCmd::default()]);
+        assert_eq!(engine.vote_outcome(), None);
+    }
+
+    #[test]
+    fn sounds_get_channels_by_the_mixer_policy() {
+        let mut engine = Engine::new(wads(), Config::new());
+        engine.set_mixer_policy(MixerPolicy {
+            channels: 2,
+            weapon_channels: 1,
+            ..MixerPolicy::default()
+        });
+        let monster = |origin| SoundRequest {
+            sound: 1,
+            category: SoundCategory::Monster,
+            priority: 64,
+            origin: Some(origin),
+        };
+        let channels = engine.sound_channels();
+        assert_eq!(channels.allocate(monster(1)), Some(1));
+        assert_eq!(
+            channels.allocate(monster(2)),
+            Some(1),
+            "the weapon channel is held"
+        );
+    }
+
+    #[test]
+    fn the_field_of_view_reaches_the_projection() {
+        let mut engine = Engine::new(wads(), Config::new());
+        let vanilla = engine.screen().projection;
+        engine.set_fov(FieldOfView::new(110));
+        assert_eq!(engine.screen().fov().degrees, 110);
+        assert!(engine.screen().projection.projection < vanilla.projection);
+        assert_eq!(engine.screen().frame.width(), SCREENWIDTH);
+    }
+
+    #[test]
+    fn the_console_is_blended_through_the_tranmap() {
+        let mut engine = Engine::new(with_font(wads()), Config::new());
+        engine.responder(&Event::KeyDown(console::KEY_CONSOLE));
+        for _ in 0..CONSOLE_LINES {
+            engine.run_tic(&[TicCmd::default()]);
+        }
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[0], 0, "drawn solid without a table");
+
+        engine.set_tranmap(TranMap::from_bytes(&[42; 256 * 256]).unwrap());
+        engine.render_into(&mut frame);
+        assert_eq!(frame[0], 42);
+    }
+
+    /// `wads` with an empty MAP01.
+    fn with_map(mut wads: Wads) -> Wads {
+        let map = [
+            "MAP01", "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "NODES", "SECTORS",
+        ];
+        wads.add(WadFile {
+            path: PathBuf::from("map.wad"),
+            kind: WadKind::Pwad,
+            lumps: map
+                .iter()
+                .map(|name| Lump {
+                    name: name.to_string(),
+                    data: Vec::new(),
+                })
+                .collect(),
+        });
+        wads
+    }
+
+    #[test]
+    fn levels_load_behind_a_filling_bar() {
+        let mut engine = Engine::new(with_map(with_font(wads())), Config::new());
+        // How much of the bar's first row inside the edge is red, frame by frame.
+        let filled = Rc::new(RefCell::new(Vec::new()));
+        engine.set_load_presenter(Box::new({
+            let filled = Rc::clone(&filled);
+            move |frame, _| {
+                let row = &frame[101 * SCREENWIDTH + 61..102 * SCREENWIDTH];
+                let red = row.iter().take_while(|&&pixel| pixel == 176).count();
+                filled.borrow_mut().push(red);
+            }
+        }));
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        assert_eq!(*filled.borrow(), [0, 19, 49, 59, 198]);
+
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[101 * SCREENWIDTH + 61], 0, "the title once it is in");
+    }
+
+    #[test]
+    fn the_screenshot_key_captures_the_next_frame() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-engine-{}", std::process::id()));
+        let mut config = Config::new();
+        ScreenshotConfig::register(&mut config);
+        config.set_str("screenshot_folder", &folder.to_string_lossy());
+        let mut engine = Engine::new(wads(), config);
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(engine.capture_screenshot(), None);
+
+        engine.responder(&Event::KeyDown(input::KEY_PRTSCR));
+        engine.render_into(&mut frame);
+        let path = engine.capture_screenshot().unwrap();
+        assert!(std::fs::read(&path).unwrap().starts_with(b"\x89PNG"));
+        assert_eq!(engine.capture_screenshot(), None);
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn quick_save_names_a_slot_and_quick_load_brings_it_back() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-saves-{}", std::process::id()));
+        let mut config = Config::new();
+        QuickSaveOptions::register(&mut config);
+        config.set_str("savegame_folder", &folder.to_string_lossy());
+        let mut engine = Engine::new(with_map(with_font(wads())), config);
+        let press = |engine: &mut Engine, key: Key| engine.responder(&Event::KeyDown(key));
+        press(&mut engine, input::KEY_F9);
+        assert!(engine
+            .menu_message
+            .as_ref()
+            .unwrap()
+            .contains("quicksave slot"));
+        press(&mut engine, Key::from(b' '));
+        assert_eq!(engine.menu_message, None);
+
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        press(&mut engine, input::KEY_F6);
+        let (slot, entry) = engine.save_entry.as_ref().unwrap();
+        assert_eq!(*slot, 0);
+        assert!(entry.text().starts_with("MAP01 "));
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        let slot_row = &frame[SAVE_Y as usize * SCREENWIDTH..][..SCREENWIDTH];
+        assert!(slot_row.contains(&5), "the description is drawn");
+        for key in [b'q', b'1', b'\r'] {
+            press(&mut engine, Key::from(key));
+        }
+        assert_eq!(engine.save_entry, None);
+        assert_eq!(engine.save_slots.description(0), Some("Q1"));
+        assert!(folder.join("doomsav0.dsg").exists());
+
+        engine.session.as_mut().unwrap().players[0].health = 10;
+        press(&mut engine, input::KEY_F9);
+        assert!(engine.menu_message.as_ref().unwrap().contains("'Q1'"));
+        press(&mut engine, Key::from(b'y'));
+        assert_eq!(engine.session().unwrap().players[0].health, 100);
+
+        // Quick save goes back to the same slot, once it is confirmed.
+        press(&mut engine, input::KEY_F6);
+        assert_eq!(engine.quick_confirm, Some((Hotkey::QuickSave, 0)));
+        press(&mut engine, input::KEY_ESCAPE);
+        assert_eq!(
+            (engine.menu_message.as_ref(), engine.quick_confirm),
+            (None, None)
+        );
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn the_free_camera_flies_in_a_demo_and_lands_back() {
+        let mut engine = Engine::new(with_map(wads()), Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        let start = engine.player_views()[0];
+        let freecam = Event::KeyDown(Key::from(b'c'));
+        engine.responder(&freecam);
+        assert!(!engine.spectator.is_free(), "alive and not in a demo");
+
+        engine.session.as_mut().unwrap().setup.demo_playback = true;
+        engine.responder(&freecam);
+        assert!(engine.spectator.is_free());
+        engine.responder(&Event::KeyDown(input::KEY_UPARROW));
+        for _ in 0..5 {
+            let cmd = engine.build_ticcmd();
+            assert_eq!(cmd.forwardmove, 0, "the player stays put");
+            engine.run_tic(&[cmd]);
+        }
+        assert_ne!(engine.view_point(), start);
+        engine.responder(&Event::KeyDown(input::KEY_F12));
+        assert_eq!(
+            engine.view_point(),
+            start,
+            "the spy key lands it on the player"
+        );
+    }
+
+    #[test]
+    fn cheats_typed_in_a_game_change_it() {
+        let mut wads = with_map(wads());
+        wads.add(WadFile {
+            path: PathBuf::from("music.wad"),
+            kind: WadKind::Pwad,
+            lumps: vec![Lump {
+                name: "D_RUNNIN".to_string(),
+                data: crate::audio::mus::MUS_MAGIC.to_vec(),
+            }],
+        });
+        let mut engine = Engine::new(wads, Config::new());
+        let type_keys = |engine: &mut Engine, text: &str| {
+            for key in text.bytes() {
+                engine.responder(&Event::KeyDown(Key::from(key)));
+            }
+        };
+        type_keys(&mut engine, "iddqd");
+        assert!(engine.session().is_none(), "only in a game");
+
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        engine.session.as_mut().unwrap().players[0].health = 10;
+        type_keys(&mut engine, "iddqd");
+        let session = engine.session().unwrap();
+        assert_eq!(
+            (session.cheats, session.players[0].health),
+            (CF_GODMODE, 100)
+        );
+
+        type_keys(&mut engine, "idmus01");
+        assert_eq!(engine.take_music().unwrap().lump, "D_RUNNIN");
+        assert_eq!(engine.take_music(), None);
+
+        type_keys(&mut engine, "idclev02");
+        let session = engine.session().unwrap();
+        assert_eq!(session.map, "MAP02");
+        assert_eq!(session.cheats, 0, "a new game");
+    }
+
+    #[test]
+    fn players_drop_out_and_come_back() {
+        let mut engine = Engine::new(with_map(wads()), Config::new());
+        let cmds = [TicCmd::default(); 2];
+        engine.run_tic(&cmds);
+        let mut setup = GameSetup {
+            netgame: true,
+            ..GameSetup::default()
+        };
+        setup.spawn_filter.coop_spawns = true;
+        assert!(engine.new_game(setup, "MAP01"));
+        let session = engine.session.as_mut().unwrap();
+        session.things = vec![1, 2]
+            .into_iter()
+            .map(|kind| {
+                Some(Thing {
+                    x: 0,
+                    y: 0,
+                    angle: 0,
+                    kind,
+                    flags: 7,
+                })
+            })
+            .collect();
+        session.spawn_players(&engine.in_game);
+
+        engine.config.set_bool("net_keep_body", false);
+        assert_eq!(engine.player_left(1), Some(Departure::Removed { body: 1 }));
+        engine.run_tic(&cmds);
+        assert_eq!(
+            engine.in_game[..2],
+            [true, false],
+            "the slot is still filled"
+        );
+        assert!(matches!(
+            engine.answer_rejoin((5, 1), 5),
+            Handshake::Snapshot(snapshot) if SessionState::from_bytes(&snapshot.state).is_ok()
+        ));
+
+        assert_eq!(
+            engine.player_joined(1),
+            Some(Arrival::Spawned { body: 1, start: 1 })
+        );
+        engine.run_tic(&cmds);
+        assert_eq!(engine.in_game[..2], [true, true]);
+        assert!(matches!(
+            engine.answer_rejoin((5, 1), 5),
+            Handshake::Refused(_)
+        ));
+    }
+
+    #[test]
+    fn the_rewind_key_goes_back_to_a_keyframe() {
+        let mut engine = Engine::new(with_map(wads()), Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        for _ in 0..TICRATE {
+            engine.run_tic(&[TicCmd::default()]);
+        }
+        engine.session.as_mut().unwrap().players[0].health = 10;
+        for _ in 0..TICRATE + 5 {
+            engine.run_tic(&[TicCmd::default()]);
+        }
+        engine.responder(&Event::KeyDown(input::KEY_BACKSPACE));
+        let session = engine.session().unwrap();
+        assert_eq!(session.players[0].health, 100);
+        assert_eq!(session.stats.level_time, TICRATE);
+        assert_eq!(engine.leveltime, TICRATE);
+    }
+
+    #[test]
+    fn the_crosshair_is_drawn_while_a_game_is_on() {
+        let mut config = Config::new();
+        Crosshair::register(&mut config);
+        config.set_str("crosshair", "dot");
+        let mut engine = Engine::new(with_map(wads()), config);
+        let center = 84 * SCREENWIDTH + 160;
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[center], 0, "not on the title screen");
+
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        engine.render_into(&mut frame);
+        assert_eq!(frame[center], 176);
+    }
+
+    #[test]
+    fn fog_plays_its_sound_on_the_next_tic() {
+        let mut config = Config::new();
+        CaptionOptions::register(&mut config);
+        config.set_int("snd_captions", 1);
+        let mut engine = Engine::new(with_map(wads()), config);
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        let fog = Fog {
+            kind: crate::game::fog::MT_TFOG,
+            x: to_fixed(20),
+            y: 0,
+            z: 0,
+            sound: Some("telept"),
+        };
+        engine.session.as_mut().unwrap().fog.push(fog);
+        engine.run_tic(&[TicCmd::default()]);
+        assert!(engine.session().unwrap().fog.is_empty());
+        let playing = engine.sound_channels.playing(0).unwrap();
+        assert_eq!(playing.priority, 32);
+        assert_eq!(engine.captions.shown[0].text, "Teleport");
+    }
+
+    #[test]
+    fn movers_sound_from_their_sector_and_stop_there() {
+        let mut engine = Engine::new(with_map(wads()), Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        let crusher = MoverKind::Ceiling { silent: false };
+        assert_eq!(
+            engine.sector_sound(0, crusher, Motion::Moving),
+            None,
+            "no sectors"
+        );
+        engine.session.as_mut().unwrap().sound_origins = vec![(to_fixed(64), 0)];
+        assert_eq!(engine.sector_sound(0, crusher, Motion::Moving), Some(0));
+        let playing = *engine.sound_channels.playing(0).unwrap();
+        assert_eq!(playing.origin, Some(sector_sound::origin(0)));
+        assert_eq!(playing.priority, 119);
+
+        engine.sector_sound(0, crusher, Motion::Finished);
+        assert_eq!(engine.sound_channels.playing(0), None);
+    }
+
+    #[test]
+    fn sounds_started_on_the_mixer_are_captioned() {
+        let mut config = Config::new();
+        CaptionOptions::register(&mut config);
+        config.set_int("snd_captions", 2);
+        let mut engine = Engine::new(with_map(with_font(wads())), config);
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        let request = SoundRequest {
+            sound: 0,
+            category: SoundCategory::Ambient,
+            priority: 100,
+            origin: None,
+        };
+        assert!(engine.start_sound("doropn", request, None).is_some());
+        assert!(engine.start_sound("stnmov", request, None).is_some());
+        // Just above the status bar, where the newest caption is.
+        let caption_rows = 150 * SCREENWIDTH..168 * SCREENWIDTH;
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(
+            engine.captions.shown.len(),
+            1,
+            "stone is below the priority"
+        );
+        assert!(frame[caption_rows.clone()].iter().any(|&pixel| pixel != 0));
+
+        for _ in 0..CAPTION_TICS {
+            engine.run_tic(&[TicCmd::default()]);
+        }
+        engine.render_into(&mut frame);
+        assert!(engine.captions.shown.is_empty());
+        assert!(frame[caption_rows].iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn the_hud_key_cycles_the_extended_hud() {
+        let mut engine = Engine::new(with_map(with_font(wads())), Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        for _ in 0..35 {
+            engine.run_tic(&[TicCmd::default()]);
+        }
+        assert_eq!(engine.session().unwrap().stats.level_time, 35);
+        // The rows just above the status bar, where the extended HUD's lines end.
+        let hud_rows = 150 * SCREENWIDTH..168 * SCREENWIDTH;
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert!(!frame[hud_rows.clone()].contains(&5));
+
+        engine.responder(&Event::KeyDown(b'h' as input::Key));
+        assert_eq!(engine.config().get_int("hud_extended"), 1);
+        engine.render_into(&mut frame);
+        assert!(frame[hud_rows].contains(&5));
+    }
+
+    #[test]
+    fn stat_turns_on_the_performance_overlay() {
+        let mut engine = Engine::new(with_map(with_font(wads())), Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        // The top right corner of the view, where the overlay's lines go.
+        let corner =
+            |frame: &[u8]| (2..20).any(|y| frame[y * SCREENWIDTH + 250..][..68].contains(&5));
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.record_frame(Duration::from_millis(20), Duration::from_millis(1));
+        engine.render_into(&mut frame);
+        assert!(!corner(&frame));
+
+        let commands = Rc::clone(engine.commands());
+        commands
+            .borrow_mut()
+            .execute("stat full", engine.config_mut());
+        engine.record_frame(Duration::from_millis(20), Duration::from_millis(1));
+        engine.render_into(&mut frame);
+        assert!(corner(&frame));
+    }
+
+    #[test]
+    fn the_view_follows_the_size_and_detail_keys() {
+        let mut wads = with_map(wads());
+        wads.add(WadFile {
+            path: PathBuf::from("flat.wad"),
+            kind: WadKind::Pwad,
+            lumps: vec![Lump {
+                name: "GRNROCK".to_string(),
+                data: vec![3; 4096],
+            }],
+        });
+        let mut engine = Engine::new(wads, Config::new());
+        assert!(engine.new_game(GameSetup::default(), "MAP01"));
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(&frame[..2], &[7, 0]);
+
+        // Low detail doubles each column the view drew.
+        engine.responder(&Event::KeyDown(input::KEY_F5));
+        assert_eq!(engine.config().get_int("detaillevel"), 1);
+        engine.render_into(&mut frame);
+        assert_eq!(engine.screen.window.detail_shift, 1);
+        assert_eq!(&frame[..2], &[7, 7]);
+
+        engine.responder(&Event::KeyDown(input::KEY_MINUS));
+        assert_eq!(engine.config().get_int("screenblocks"), 9);
+        engine.render_into(&mut frame);
+        assert_eq!(engine.screen.window.width, 288);
+        assert_eq!(frame[0], 3, "the border flat");
+    }
+
+    #[test]
+    fn the_gamma_key_steps_through_the_levels() {
+        let mut engine = Engine::new(wads(), Config::new());
+        assert_eq!(
+            engine.gamma_table()[0],
+            1,
+            "vanilla's level 0 is not quite off"
+        );
+        engine.responder(&Event::KeyDown(input::KEY_F11));
+        assert_eq!(engine.config().get_int("usegamma"), 1);
+        assert_eq!(engine.gamma_table()[0], 2);
+        for _ in 1..5 {
+            engine.responder(&Event::KeyDown(input::KEY_F11));
+        }
+        assert_eq!(engine.config().get_int("usegamma"), 0);
+    }
+
+    #[test]
+    fn screen_flashes_tint_the_palette_or_the_border() {
+        let mut wads = with_map(wads());
+        wads.add(WadFile {
+            path: PathBuf::from("playpal.wad"),
+            kind: WadKind::Pwad,
+            // Palette n is gray n all through.
+            lumps: vec![Lump {
+                name: "PLAYPAL".to_string(),
+                data: (0..14u8).flat_map(|n| [n; 768]).collect(),
+            }],
+        });
+        for (style, palette, corner) in [("full", 13, 7), ("border", 0, 0x70)] {
+            let mut config = Config::new();
+            FlashOptions::register(&mut config);
+            config.set_str("screen_flashes", style);
+            let mut engine = Engine::new(wads.clone(), config);
+            assert!(engine.new_game(GameSetup::default(), "MAP01"));
+            let session = engine.session.as_mut().unwrap();
+            session.players[0].powers[Power::IronFeet as usize] = 1000;
+            let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+            engine.render_into(&mut frame);
+            assert_eq!(engine.palette().rgb(0), [palette; 3], "{style}");
+            assert_eq!(frame[0], corner, "{style}");
+        }
+    }
+
+    #[test]
+    fn the_color_scheme_recolors_numbers_blood_and_keys() {
+        let mut engine = Engine::new(with_map(with_font(wads())), Config::new());
+        assert_eq!(engine.overlays.numbers, None);
+        assert_eq!(
+            (engine.blood_translation(), engine.key_translation(2)),
+            (None, None)
+        );
+
+        let mut config = Config::new();
+        ColorScheme::register(&mut config);
+        config.set_str("color_scheme", "red-green");
+        engine = Engine::new(with_map(with_font(wads())), config);
+        let yellow = *engine.text_colors.table(TextColor::Yellow);
+        assert_eq!(engine.overlays.numbers, Some(yellow));
+        assert!(engine.blood_translation().is_some());
+        // The red card and the red skull, not the blue card.
+        assert!(engine.key_translation(2).is_some());
+        assert!(engine.key_translation(5).is_some());
+        assert_eq!(engine.key_translation(0), None);
+    }
+
+    #[test]
+    fn spectres_are_drawn_in_the_held_style() {
+        let mut engine = Engine::new(wads(), Config::new());
+        assert_eq!(engine.spectres().style, FuzzStyle::Vanilla);
+        engine.set_fuzz_style(FuzzStyle::Shadow);
+        assert_eq!(engine.spectres().style, FuzzStyle::Shadow);
+    }
+
+    #[test]
+    fn players_are_drawn_in_the_held_colors() {
+        let mut engine = Engine::new(wads(), Config::new());
+        assert_eq!(
+            engine.player_translation(0),
+            None,
+            "the first player stays green"
+        );
+        assert_eq!(engine.player_translation(1).unwrap()[0x70], 0x60);
+
+        let mut translations = Translations::default();
+        translations.add_lump("gray 200 215").unwrap();
+        engine.set_translations(translations);
+        assert_eq!(engine.player_translation(1).unwrap()[0x70], 200);
+        assert_eq!(engine.player_translation(3).unwrap()[0x70], 0x20);
+    }
+
+    #[test]
+    fn the_console_takes_the_keyboard_and_runs_commands() {
+        let mut engine = Engine::new(wads(), Config::new());
+        engine.responder(&Event::KeyDown(input::KEY_UPARROW));
+        engine.responder(&Event::KeyDown(console::KEY_CONSOLE));
+        assert!(engine.console().is_open());
+        for key in "callvote kick 2".bytes() {
+            engine.responder(&Event::KeyDown(key as Key));
+        }
+        engine.responder(&Event::KeyDown(input::KEY_ENTER));
+        engine.responder(&Event::KeyUp(input::KEY_UPARROW));
+        assert_eq!(
+            vote::ballot(&engine.build_ticcmd()),
+            Some(vote::Ballot::Call(vote::Proposal::Kick { player: 1 }))
+        );
+        let cmd = engine.build_ticcmd();
+        assert_eq!(cmd.forwardmove, 0, "the key was let go behind the console");
+        assert!(engine.commands().borrow().contains("stat"));
+        assert!(engine.commands().borrow().contains("r_debug"));
+        assert!(engine.commands().borrow().contains("levelstat"));
+        assert!(engine.commands().borrow().contains("lifetime"));
+
+        engine.responder(&Event::KeyDown(console::KEY_CONSOLE));
+        engine.responder(&Event::KeyDown(input::KEY_UPARROW));
+        assert_eq!(engine.build_ticcmd().forwardmove, 0x19);
+    }
+
+    #[test]
+    fn deathmatch_levels_end_at_the_time_limit() {
+        for deathmatch in [false, true] {
+            let mut engine = Engine::new(wads(), Config::new());
+            let setup = GameSetup {
+                deathmatch,
+                deathmatch_rules: DeathmatchRules {
+                    time_limit: 1,
+                    ..DeathmatchRules::default()
+                },
+                ..GameSetup::default()
+            };
+            assert!(!engine.new_game(setup, "MAP01"), "the test WAD has no maps");
+            for _ in 1..60 * TICRATE {
+                engine.run_tic(&[TicCmd::default(); 2]);
+            }
+            assert_eq!(engine.level_end(), None);
+            engine.run_tic(&[TicCmd::default(); 2]);
+            let ended = deathmatch.then_some(LevelEnd::TimeLimit);
+            assert_eq!(engine.level_end(), ended);
+
+            engine.exit_level("MAP02");
+            assert_eq!((engine.leveltime(), engine.level_end()), (0, None));
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn level_start_hooks_run_on_the_next_tic() {
+        let mut scripted = wads();
+        scripted.add(WadFile {
+            path: PathBuf::from("hooks.wad"),
+            kind: WadKind::Pwad,
+            lumps: vec![Lump {
+                name: "DSCRIPTS".to_string(),
+                data: b"on level_start\nprint \"$map at $gametic\"\nend\n".to_vec(),
+            }],
+        });
+        let (scripts, errors) = Scripts::load(std::path::Path::new("no-scripts"), &scripted, true);
+        assert!(errors.is_empty(), "{errors:?}");
+        let mut engine = Engine::new(wads(), Config::new());
+        engine.set_scripts(scripts);
+
+        // Other tests' log lines can turn up in the console too.
+        let printed = |engine: &Engine| -> Vec<String> {
+            let lines = engine.console().visible_lines(CONSOLE_LINES);
+            lines
+                .iter()
+                .filter(|line| line.starts_with("MAP"))
+                .cloned()
+                .collect()
+        };
+        engine.new_game(GameSetup::default(), "MAP01");
+        assert!(printed(&engine).is_empty());
+        engine.run_tic(&[TicCmd::default()]);
+        engine.run_tic(&[TicCmd::default()]);
+        assert_eq!(printed(&engine), ["MAP01 at 1"]);
+        engine.exit_level("MAP02");
+        engine.run_tic(&[TicCmd::default()]);
+        assert_eq!(printed(&engine), ["MAP01 at 1", "MAP02 at 3"]);
+    }
+
+    #[test]
+    fn chat_goes_out_in_the_ticcmds() {
+        let mut engine = Engine::new(with_font(wads()), Config::new());
+        engine.responder(&Event::KeyDown(chat::CHAT_KEY));
+        assert!(!engine.chat().is_open(), "not in a netgame");
+
+        engine.run_tic(&[TicCmd::default(); 2]);
+        for key in [chat::CHAT_KEY, b'h' as Key] {
+            engine.responder(&Event::KeyDown(key));
+        }
+        // The line being typed is drawn under the message line.
+        let input_rows = LINE_HEIGHT as usize * SCREENWIDTH..2 * LINE_HEIGHT as usize * SCREENWIDTH;
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert!(frame[input_rows].contains(&5));
+        engine.responder(&Event::KeyDown(input::KEY_ENTER));
+        let sent: Vec<_> = (0..4).map(|_| engine.build_ticcmd().chatchar).collect();
+        assert_eq!(sent, [chat::HU_BROADCAST, b'H', 13, 0]);
+
+        for c in sent {
+            let from_2 = TicCmd {
+                chatchar: c,
+                ..TicCmd::default()
+            };
+            engine.run_tic(&[TicCmd::default(), from_2]);
+            if c == 13 {
+                assert_eq!(engine.chat_messages()[0].line(), "Indigo: H");
+            }
+        }
+        assert!(engine.chat_messages().is_empty());
+    }
+
+    #[test]
+    fn changed_graphics_are_reloaded() {
+        let mut engine = Engine::new(wads(), Config::new());
+        let mut file = wads().files()[0].clone();
+        file.lumps[1].data[15] = 4;
+        engine.wads_mut().replace(file, Replacements::new());
+        assert!(!engine.assets_changed(&["COLORMAP".to_string()]));
+        let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+        engine.render_into(&mut frame);
+        assert_eq!(frame[0], 7, "not reloaded until told");
+
+        assert!(engine.assets_changed(&["TITLEPIC".to_string()]));
+        engine.render_into(&mut frame);
+        assert_eq!(frame[0], 4);
+    }
+}
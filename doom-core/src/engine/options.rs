@@ -0,0 +1,797 @@
+//! The options menu: the port's settings on five pages, each a list of labeled widgets that
+//! read and write the config directly.
+//!
+//! Vanilla's options page has room for a handful of graphics, so everything added since
+//! lives here, drawn in the HUD font. A page is chosen from the tabs along the top, with Tab
+//! and the page keys, or by clicking. Left and right change the selected setting; Enter
+//! flips a toggle, steps through a list, or waits for the next key or button to bind to a
+//! control, which Delete unbinds again (see `bindings`). Every change is
+//! written to the config straight away and named in an `OptionsAction::Changed`, so the
+//! caller can rebuild the subsystem reading it with `Configurable::from_config`.
+//!
+//! A binding row lists everything bound to its control. Where another control shares one,
+//! `conflict` names it for a line under the rows.
+//!
+//! The profile row steps through the names in `OptionsMenu::profiles`, filled in by the
+//! caller from `Profiles::list`. Changing it only sets `profile`; the caller then calls
+//! `Profiles::switch` to save the old profile and load the new one.
+//!
+//! Rows are laid out at 320x200 like the rest of the menus and hit-tested the same way,
+//! with the mouse `Pointer` of `menu`.
+
+use super::bindings::{self, Binding};
+use super::config::Config;
+use super::controls::Control;
+use super::input::*;
+use super::menu::{Pointer, Rect, LEFT_BUTTON, RIGHT_BUTTON};
+use super::speed::{MAX_SPEED, MIN_SPEED};
+use crate::audio::channels::SoundCategory;
+use crate::renderer::fov::{MAX_FOV, MIN_FOV};
+use crate::renderer::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+use crate::renderer::gamma::GAMMA_LEVELS;
+use crate::renderer::hud::LINE_HEIGHT;
+use crate::renderer::view::{MAX_BLOCKS, MIN_BLOCKS};
+
+pub const TABS_Y: i32 = 4;
+pub const ROWS_Y: i32 = 20;
+pub const LABEL_X: i32 = 16;
+/// Where values start; sliders are a bar this far in with the number after it.
+pub const VALUE_X: i32 = 192;
+pub const SLIDER_WIDTH: i32 = 80;
+/// The line under the rows, for binding conflicts.
+pub const STATUS_Y: i32 = SCREENHEIGHT as i32 - LINE_HEIGHT;
+/// Rows that fit between the tabs and the status line; longer pages scroll to keep the
+/// selection in view.
+pub const MAX_ROWS: usize = ((STATUS_Y - ROWS_Y) / LINE_HEIGHT) as usize;
+/// Shown in place of the bindings while waiting for a key or button.
+pub const PRESS_A_KEY: &str = "PRESS A KEY OR BUTTON";
+
+/// The window sizes offered, smallest first.
+pub const RESOLUTIONS: [(i32, i32); 11] = [
+    (640, 400),
+    (640, 480),
+    (800, 600),
+    (960, 600),
+    (1024, 768),
+    (1280, 800),
+    (1280, 960),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Widget {
+    /// A boolean option, on or off.
+    Toggle,
+    /// A number from `min` to `max` in steps of `step`, on a bar.
+    Slider { min: i32, max: i32, step: i32 },
+    /// A number whose values have names, the first for 0.
+    Levels(&'static [&'static str]),
+    /// A string option taking one of a fixed set of values.
+    Choice(&'static [&'static str]),
+    /// The keys and buttons of a control, bound by pressing them.
+    Binding(Control),
+    /// The window size from `RESOLUTIONS`, kept in `window_width` and `window_height`.
+    Resolution,
+    /// The config profile, one of `OptionsMenu::profiles`.
+    Profile,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptionItem {
+    pub label: &'static str,
+    /// The config option it shows.
+    pub name: &'static str,
+    pub widget: Widget,
+}
+
+impl OptionItem {
+    const fn new(label: &'static str, name: &'static str, widget: Widget) -> Self {
+        Self {
+            label,
+            name,
+            widget,
+        }
+    }
+
+    /// The value as the menu shows it.
+    pub fn value_text(&self, config: &Config) -> String {
+        match self.widget {
+            Widget::Toggle => match config.get_bool(self.name) {
+                true => "ON".to_string(),
+                false => "OFF".to_string(),
+            },
+            Widget::Slider { .. } => config.get_int(self.name).to_string(),
+            Widget::Levels(names) => {
+                let level = config.get_int(self.name).clamp(0, names.len() as i32 - 1);
+                names[level as usize].to_string()
+            }
+            Widget::Choice(_) | Widget::Profile => config.get_str(self.name).to_ascii_uppercase(),
+            Widget::Binding(control) => {
+                let names: Vec<_> = bindings::bindings(config, control)
+                    .into_iter()
+                    .map(Binding::name)
+                    .collect();
+                match names.is_empty() {
+                    true => key_name(0),
+                    false => names.join(", "),
+                }
+            }
+            Widget::Resolution => format!(
+                "{}x{}",
+                config.get_int("window_width"),
+                config.get_int("window_height")
+            ),
+        }
+    }
+
+    /// Moves the value one step up or down, wrapping around lists and toggles. Returns
+    /// whether it changed.
+    pub fn adjust(&self, config: &mut Config, up: bool) -> bool {
+        let cycle = |index: Option<usize>, len: usize| match (index, up) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        match self.widget {
+            Widget::Toggle => {
+                let on = config.get_bool(self.name);
+                config.set_bool(self.name, !on);
+            }
+            Widget::Slider { step, .. } => {
+                let value = config.get_int(self.name) + if up { step } else { -step };
+                return self.set_slider(config, value);
+            }
+            Widget::Levels(names) => {
+                let level = usize::try_from(config.get_int(self.name)).ok();
+                let level = level.filter(|&level| level < names.len());
+                config.set_int(self.name, cycle(level, names.len()) as i32);
+            }
+            Widget::Choice(values) => {
+                let current = config.get_str(self.name);
+                let index = values.iter().position(|&value| value == current);
+                config.set_str(self.name, values[cycle(index, values.len())]);
+            }
+            Widget::Binding(_) | Widget::Profile => return false,
+            Widget::Resolution => {
+                let size = (
+                    config.get_int("window_width"),
+                    config.get_int("window_height"),
+                );
+                let index = RESOLUTIONS.iter().position(|&known| known == size);
+                let (width, height) = RESOLUTIONS[cycle(index, RESOLUTIONS.len())];
+                config.set_int("window_width", width);
+                config.set_int("window_height", height);
+            }
+        }
+        true
+    }
+
+    /// Sets a slider, snapped to its steps and range. Returns whether it changed.
+    fn set_slider(&self, config: &mut Config, value: i32) -> bool {
+        let Widget::Slider { min, max, step } = self.widget else {
+            return false;
+        };
+        let value = (min + (value - min + step / 2).div_euclid(step) * step).clamp(min, max);
+        if value == config.get_int(self.name) {
+            return false;
+        }
+        config.set_int(self.name, value);
+        true
+    }
+
+    /// The slider value at `x` along its bar.
+    fn slider_value(&self, x: i32) -> i32 {
+        let Widget::Slider { min, max, .. } = self.widget else {
+            return 0;
+        };
+        let along = (x - VALUE_X).clamp(0, SLIDER_WIDTH - 1);
+        min + along * (max - min) / (SLIDER_WIDTH - 1)
+    }
+}
+
+const FLASHES: &[&str] = &["full", "reduced", "border", "off"];
+const SCHEMES: &[&str] = &["vanilla", "red-green", "blue-yellow"];
+const EFFECTS: &[&str] = &["none", "scanlines", "crt"];
+const FUZZ: &[&str] = &["vanilla", "translucent", "shadow"];
+const CROSSHAIRS: &[&str] = &["off", "cross", "dot", "angle"];
+const CAPTIONS: &[&str] = &["OFF", "WARNINGS", "WORLD", "ALL"];
+const HUD_MODES: &[&str] = &["OFF", "STATS", "STATS AND XY"];
+const PERF_MODES: &[&str] = &["OFF", "FPS", "FULL"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Page {
+    Display,
+    Input,
+    Sound,
+    Gameplay,
+    Hud,
+}
+
+impl Page {
+    pub const ALL: [Page; 5] = [
+        Page::Display,
+        Page::Input,
+        Page::Sound,
+        Page::Gameplay,
+        Page::Hud,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Page::Display => "DISPLAY",
+            Page::Input => "INPUT",
+            Page::Sound => "SOUND",
+            Page::Gameplay => "GAMEPLAY",
+            Page::Hud => "HUD",
+        }
+    }
+
+    /// Where the tab naming the page is: the width of the screen shared out between them.
+    pub fn tab_rect(self) -> Rect {
+        let width = SCREENWIDTH as i32 / Self::ALL.len() as i32;
+        Rect {
+            x: self as i32 * width,
+            y: TABS_Y,
+            width,
+            height: LINE_HEIGHT,
+        }
+    }
+
+    pub fn items(self) -> Vec<OptionItem> {
+        use Widget::*;
+        match self {
+            Page::Display => vec![
+                OptionItem::new("FULLSCREEN", "video_fullscreen", Toggle),
+                OptionItem::new("WINDOW SIZE", "window_width", Resolution),
+                OptionItem::new("ASPECT CORRECTION", "video_aspect_correct", Toggle),
+                OptionItem::new("INTEGER SCALING", "video_integer_scale", Toggle),
+                OptionItem::new("WIDESCREEN", "video_widescreen", Toggle),
+                OptionItem::new("VSYNC", "video_vsync", Toggle),
+                OptionItem::new("EFFECT", "video_effect", Choice(EFFECTS)),
+                OptionItem::new(
+                    "FIELD OF VIEW",
+                    "fov",
+                    Slider {
+                        min: MIN_FOV as i32,
+                        max: MAX_FOV as i32,
+                        step: 5,
+                    },
+                ),
+                OptionItem::new(
+                    "GAMMA",
+                    "usegamma",
+                    Slider {
+                        min: 0,
+                        max: GAMMA_LEVELS as i32 - 1,
+                        step: 1,
+                    },
+                ),
+                OptionItem::new("SCREEN FLASHES", "screen_flashes", Choice(FLASHES)),
+                OptionItem::new("STEADY LIGHTS", "steady_lights", Toggle),
+                OptionItem::new("COLOR SCHEME", "color_scheme", Choice(SCHEMES)),
+                OptionItem::new("MENU INTEGER SCALING", "menu_integer_scale", Toggle),
+            ],
+            Page::Input => {
+                let mut items = vec![
+                    OptionItem::new("ALWAYS RUN", "autorun", Toggle),
+                    OptionItem::new("MOUSE IN MENUS", "menu_mouse", Toggle),
+                ];
+                items.extend(Control::ALL.iter().map(|control| {
+                    OptionItem::new(
+                        control.label(),
+                        control.config_name(),
+                        Widget::Binding(*control),
+                    )
+                }));
+                items
+            }
+            Page::Sound => {
+                let volume = Slider {
+                    min: 0,
+                    max: 100,
+                    step: 10,
+                };
+                let mut items = vec![OptionItem::new(
+                    "SOUND CHANNELS",
+                    "snd_channels",
+                    Slider {
+                        min: 1,
+                        max: 32,
+                        step: 1,
+                    },
+                )];
+                items.extend(SoundCategory::ALL.iter().map(|category| {
+                    let label = match category {
+                        SoundCategory::Weapon => "WEAPON VOLUME",
+                        SoundCategory::Monster => "MONSTER VOLUME",
+                        SoundCategory::Ambient => "LEVEL VOLUME",
+                        SoundCategory::Ui => "MENU VOLUME",
+                    };
+                    OptionItem::new(label, category.config_name(), volume)
+                }));
+                items.push(OptionItem::new(
+                    "CAPTIONS",
+                    "snd_captions",
+                    Levels(CAPTIONS),
+                ));
+                items.push(OptionItem::new("PAUSE MUSIC", "pause_music", Toggle));
+                items
+            }
+            Page::Gameplay => vec![
+                OptionItem::new("STRICT MODE", "strict_mode", Toggle),
+                OptionItem::new(
+                    "GAME SPEED",
+                    "game_speed",
+                    Slider {
+                        min: MIN_SPEED as i32,
+                        max: MAX_SPEED as i32,
+                        step: 10,
+                    },
+                ),
+                OptionItem::new("PISTOL START", "pistol_start", Toggle),
+                OptionItem::new("COOP SPAWNS", "coop_spawns", Toggle),
+                OptionItem::new("INTERPOLATION", "interpolate", Toggle),
+                OptionItem::new("SPECTRES", "fuzz_style", Choice(FUZZ)),
+                OptionItem::new("TRANSLUCENT PROJECTILES", "translucent_projectiles", Toggle),
+                OptionItem::new("PROFILE", "profile", Profile),
+            ],
+            Page::Hud => vec![
+                OptionItem::new(
+                    "SCREEN SIZE",
+                    "screenblocks",
+                    Slider {
+                        min: MIN_BLOCKS,
+                        max: MAX_BLOCKS,
+                        step: 1,
+                    },
+                ),
+                OptionItem::new("EXTENDED HUD", "hud_extended", Levels(HUD_MODES)),
+                OptionItem::new("PERFORMANCE", "hud_perf", Levels(PERF_MODES)),
+                OptionItem::new("CROSSHAIR", "crosshair", Choice(CROSSHAIRS)),
+                OptionItem::new("HEALTH CROSSHAIR", "crosshair_health", Toggle),
+                OptionItem::new("DISK ICON", "show_diskicon", Toggle),
+            ],
+        }
+    }
+}
+
+/// What the player did, for the caller to act on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionsAction {
+    /// The option was written to the config.
+    Changed(&'static str),
+    Back,
+}
+
+/// What is at a point on the page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionsHit {
+    Tab(Page),
+    Item(usize),
+    /// A slider's bar, and the value under the point.
+    Slider(usize, i32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionsMenu {
+    pub page: Page,
+    pub items: Vec<OptionItem>,
+    pub selected: usize,
+    /// Waiting for a key or button to bind to the selected item.
+    pub capturing: bool,
+    /// The profiles the profile row offers.
+    pub profiles: Vec<String>,
+    /// The joystick buttons down, to tell presses.
+    joystick_buttons: i32,
+}
+
+impl Default for OptionsMenu {
+    fn default() -> Self {
+        Self::new(Page::Display)
+    }
+}
+
+impl OptionsMenu {
+    pub fn new(page: Page) -> Self {
+        Self {
+            page,
+            items: page.items(),
+            selected: 0,
+            capturing: false,
+            profiles: Vec::new(),
+            joystick_buttons: 0,
+        }
+    }
+
+    /// Switches to `page` with its first item selected.
+    pub fn show(&mut self, page: Page) {
+        *self = Self {
+            profiles: std::mem::take(&mut self.profiles),
+            ..Self::new(page)
+        };
+    }
+
+    /// The index of the item on the top row, scrolled so the selection shows.
+    pub fn first_row(&self) -> usize {
+        (self.selected + 1).saturating_sub(MAX_ROWS)
+    }
+
+    /// The items shown, with the index of each.
+    pub fn visible(&self) -> impl Iterator<Item = (usize, &OptionItem)> {
+        self.items
+            .iter()
+            .enumerate()
+            .skip(self.first_row())
+            .take(MAX_ROWS)
+    }
+
+    /// The row item `i` is drawn on, across the screen, if it is in view.
+    pub fn row_rect(&self, i: usize) -> Option<Rect> {
+        let row = i
+            .checked_sub(self.first_row())
+            .filter(|&row| row < MAX_ROWS)?;
+        Some(Rect {
+            x: 0,
+            y: ROWS_Y + row as i32 * LINE_HEIGHT,
+            width: SCREENWIDTH as i32,
+            height: LINE_HEIGHT,
+        })
+    }
+
+    /// The bar of item `i`, if it is a slider in view.
+    pub fn bar_rect(&self, i: usize) -> Option<Rect> {
+        let row = self.row_rect(i)?;
+        matches!(self.items[i].widget, Widget::Slider { .. }).then_some(Rect {
+            x: VALUE_X,
+            width: SLIDER_WIDTH,
+            ..row
+        })
+    }
+
+    pub fn hit_test(&self, x: i32, y: i32) -> Option<OptionsHit> {
+        if let Some(page) = Page::ALL
+            .into_iter()
+            .find(|page| page.tab_rect().contains(x, y))
+        {
+            return Some(OptionsHit::Tab(page));
+        }
+        let (i, item) = self
+            .visible()
+            .find(|&(i, _)| self.row_rect(i).is_some_and(|row| row.contains(x, y)))?;
+        match self.bar_rect(i) {
+            Some(bar) if bar.contains(x, y) => Some(OptionsHit::Slider(i, item.slider_value(x))),
+            _ => Some(OptionsHit::Item(i)),
+        }
+    }
+
+    /// Enter on the selected item, or a click on it.
+    fn activate(&mut self, config: &mut Config) -> Option<OptionsAction> {
+        let item = self.items[self.selected];
+        match item.widget {
+            Widget::Binding(_) => {
+                self.capturing = true;
+                None
+            }
+            Widget::Slider { .. } => None,
+            _ => self.adjust(config, true),
+        }
+    }
+
+    /// Left or right on the selected item.
+    fn adjust(&mut self, config: &mut Config, up: bool) -> Option<OptionsAction> {
+        let item = self.items[self.selected];
+        if item.widget != Widget::Profile {
+            return item
+                .adjust(config, up)
+                .then_some(OptionsAction::Changed(item.name));
+        }
+        let len = self.profiles.len();
+        if len < 2 {
+            return None;
+        }
+        let current = config.get_str(item.name);
+        let next = match (self.profiles.iter().position(|name| *name == current), up) {
+            (None, _) => 0,
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+        };
+        config.set_str(item.name, &self.profiles[next]);
+        Some(OptionsAction::Changed(item.name))
+    }
+
+    /// Sets slider `i` to `value`.
+    fn slide(&mut self, config: &mut Config, i: usize, value: i32) -> Option<OptionsAction> {
+        let item = self.items[i];
+        item.set_slider(config, value)
+            .then_some(OptionsAction::Changed(item.name))
+    }
+
+    /// Binds what was pressed while capturing.
+    fn capture(&mut self, config: &mut Config, binding: Binding) -> Option<OptionsAction> {
+        self.capturing = false;
+        let item = self.items[self.selected];
+        let Widget::Binding(control) = item.widget else {
+            return None;
+        };
+        bindings::bind(config, control, binding).then_some(OptionsAction::Changed(item.name))
+    }
+
+    /// The first other control sharing a binding with the selected one, as the line under
+    /// the rows puts it.
+    pub fn conflict(&self, config: &Config) -> Option<String> {
+        let Widget::Binding(control) = self.items.get(self.selected)?.widget else {
+            return None;
+        };
+        let (binding, other) = *bindings::conflicts(config, control).first()?;
+        Some(format!("{} ALSO {}", binding.name(), other.label()))
+    }
+
+    /// Handles a key press, writing any change to `config`.
+    pub fn responder(&mut self, key: Key, config: &mut Config) -> Option<OptionsAction> {
+        if self.capturing {
+            if key == KEY_ESCAPE {
+                self.capturing = false;
+                return None;
+            }
+            return self.capture(config, Binding::Key(key));
+        }
+        let page = self.page as usize;
+        let pages = Page::ALL.len();
+        let count = self.items.len();
+        match key {
+            KEY_ESCAPE | KEY_BACKSPACE => return Some(OptionsAction::Back),
+            KEY_TAB | KEY_PGDN => self.show(Page::ALL[(page + 1) % pages]),
+            KEY_PGUP => self.show(Page::ALL[(page + pages - 1) % pages]),
+            KEY_DOWNARROW => self.selected = (self.selected + 1) % count,
+            KEY_UPARROW => self.selected = (self.selected + count - 1) % count,
+            KEY_LEFTARROW | KEY_RIGHTARROW => return self.adjust(config, key == KEY_RIGHTARROW),
+            KEY_ENTER => return self.activate(config),
+            KEY_DEL => {
+                let item = self.items[self.selected];
+                if let Widget::Binding(control) = item.widget {
+                    bindings::clear(config, control);
+                    return Some(OptionsAction::Changed(item.name));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Handles a mouse event, moving `pointer` with it: hovering selects, a click on a tab
+    /// opens its page and a click on an item acts as Enter does, and sliders are dragged.
+    /// While capturing, the next button pressed is bound.
+    pub fn mouse(
+        &mut self,
+        pointer: &mut Pointer,
+        event: &Event,
+        config: &mut Config,
+    ) -> Option<OptionsAction> {
+        let pressed = pointer.responder(event)?;
+        if self.capturing {
+            let binding = Binding::button(pressed, false)?;
+            return self.capture(config, binding);
+        }
+        if pressed & RIGHT_BUTTON != 0 {
+            return Some(OptionsAction::Back);
+        }
+        if let Some(i) = pointer.dragging {
+            let value = self.items[i].slider_value(pointer.x);
+            return self.slide(config, i, value);
+        }
+        let click = pressed & LEFT_BUTTON != 0;
+        match self.hit_test(pointer.x, pointer.y)? {
+            OptionsHit::Tab(page) => {
+                if click && page != self.page {
+                    self.show(page);
+                }
+                None
+            }
+            OptionsHit::Slider(i, value) => {
+                self.selected = i;
+                if !click {
+                    return None;
+                }
+                pointer.dragging = Some(i);
+                self.slide(config, i, value)
+            }
+            OptionsHit::Item(i) => {
+                self.selected = i;
+                match click {
+                    true => self.activate(config),
+                    false => None,
+                }
+            }
+        }
+    }
+
+    /// Handles a joystick event: while capturing, the next button pressed is bound.
+    pub fn joystick(&mut self, event: &Event, config: &mut Config) -> Option<OptionsAction> {
+        let Event::Joystick { buttons, .. } = *event else {
+            return None;
+        };
+        let pressed = buttons & !self.joystick_buttons;
+        self.joystick_buttons = buttons;
+        match self.capturing {
+            true => self.capture(config, Binding::button(pressed, true)?),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::{Configurable, Section};
+    use crate::engine::controls::Controls;
+    use crate::engine::profiles::ProfileOptions;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        Controls::register(&mut config);
+        config.register_int("fov", 90, Section::Extended);
+        config.register_bool("video_fullscreen", false, Section::Extended);
+        config.register_str("video_effect", "none", Section::Extended);
+        config.register_int("window_width", 800, Section::Extended);
+        config.register_int("window_height", 500, Section::Extended);
+        config
+    }
+
+    #[test]
+    fn widgets_write_through_to_the_config() {
+        let mut config = config();
+        let mut menu = OptionsMenu::default();
+        assert_eq!(
+            menu.responder(KEY_ENTER, &mut config),
+            Some(OptionsAction::Changed("video_fullscreen"))
+        );
+        assert!(config.get_bool("video_fullscreen"));
+
+        // An unlisted window size steps onto the list.
+        menu.responder(KEY_DOWNARROW, &mut config);
+        menu.responder(KEY_RIGHTARROW, &mut config);
+        assert_eq!(menu.items[1].value_text(&config), "640x400");
+        menu.responder(KEY_LEFTARROW, &mut config);
+        assert_eq!(menu.items[1].value_text(&config), "3840x2160");
+
+        menu.selected = 6;
+        menu.responder(KEY_LEFTARROW, &mut config);
+        assert_eq!(config.get_str("video_effect"), "crt");
+
+        menu.selected = 7;
+        menu.responder(KEY_RIGHTARROW, &mut config);
+        assert_eq!(config.get_int("fov"), 95);
+        config.set_int("fov", MAX_FOV as i32);
+        assert_eq!(menu.responder(KEY_RIGHTARROW, &mut config), None);
+    }
+
+    #[test]
+    fn keys_are_bound_by_pressing_them() {
+        let mut config = config();
+        let mut menu = OptionsMenu::default();
+        menu.responder(KEY_TAB, &mut config);
+        assert_eq!(menu.page, Page::Input);
+        menu.selected = 2;
+        assert_eq!(menu.items[2].value_text(&config), "UP, MOUSE 3");
+        assert_eq!(menu.responder(KEY_ENTER, &mut config), None);
+        assert!(menu.capturing);
+        assert_eq!(
+            menu.responder(b'w' as Key, &mut config),
+            Some(OptionsAction::Changed("key_up"))
+        );
+        assert_eq!(menu.items[2].value_text(&config), "UP, W, MOUSE 3");
+
+        menu.responder(KEY_ENTER, &mut config);
+        assert_eq!(menu.responder(KEY_ESCAPE, &mut config), None, "cancelled");
+        assert_eq!(config.get_int("key_up_alt"), b'w' as Key);
+        assert_eq!(
+            menu.responder(KEY_DEL, &mut config),
+            Some(OptionsAction::Changed("key_up"))
+        );
+        assert_eq!(menu.items[2].value_text(&config), "---");
+        assert_eq!(
+            menu.responder(KEY_ESCAPE, &mut config),
+            Some(OptionsAction::Back)
+        );
+    }
+
+    #[test]
+    fn buttons_are_captured_and_conflicts_named() {
+        let mut config = config();
+        let mut menu = OptionsMenu::new(Page::Input);
+        let mut pointer = Pointer::default();
+        // Use, on the eleventh row, gets the fire button on both the mouse and the joystick.
+        menu.selected = 11;
+        assert_eq!(menu.conflict(&config), None);
+        menu.responder(KEY_ENTER, &mut config);
+        let click = Event::Mouse {
+            buttons: 1,
+            dx: 0,
+            dy: 0,
+        };
+        assert_eq!(
+            menu.mouse(&mut pointer, &click, &mut config),
+            Some(OptionsAction::Changed("key_use"))
+        );
+        assert!(!menu.capturing);
+        assert_eq!(config.get_int("mouseb_use"), 0);
+        assert_eq!(menu.conflict(&config).as_deref(), Some("MOUSE 1 ALSO FIRE"));
+
+        menu.responder(KEY_ENTER, &mut config);
+        let joystick = |buttons| Event::Joystick {
+            buttons,
+            x: 0,
+            y: 0,
+        };
+        assert_eq!(
+            menu.joystick(&joystick(1 << 5), &mut config),
+            Some(OptionsAction::Changed("key_use"))
+        );
+        assert_eq!(config.get_int("joyb_use"), 5);
+        assert_eq!(menu.joystick(&joystick(1 << 5), &mut config), None);
+    }
+
+    #[test]
+    fn the_mouse_picks_pages_and_drags_sliders() {
+        let mut config = config();
+        let mut menu = OptionsMenu::new(Page::Input);
+        let mut pointer = Pointer::default();
+        let event = |buttons| Event::Mouse {
+            buttons,
+            dx: 0,
+            dy: 0,
+        };
+        let display = Page::Display.tab_rect();
+        pointer.warp(display.x + 4, display.y + 2);
+        menu.mouse(&mut pointer, &event(1), &mut config);
+        assert_eq!(menu.page, Page::Display);
+        menu.mouse(&mut pointer, &event(0), &mut config);
+
+        // The field of view is on the eighth row; the start of its bar is the minimum.
+        let bar = menu.bar_rect(7).unwrap();
+        pointer.warp(bar.x, bar.y + 1);
+        assert_eq!(
+            menu.mouse(&mut pointer, &event(1), &mut config),
+            Some(OptionsAction::Changed("fov"))
+        );
+        assert_eq!((menu.selected, config.get_int("fov")), (7, MIN_FOV as i32));
+        pointer.warp(bar.x + bar.width + 30, 0);
+        menu.mouse(&mut pointer, &event(1), &mut config);
+        assert_eq!(config.get_int("fov"), MAX_FOV as i32, "dragged off the bar");
+        menu.mouse(&mut pointer, &event(0), &mut config);
+
+        let row = menu.row_rect(0).unwrap();
+        pointer.warp(LABEL_X, row.y);
+        menu.mouse(&mut pointer, &event(1), &mut config);
+        assert!(config.get_bool("video_fullscreen"));
+    }
+
+    #[test]
+    fn the_profile_row_steps_through_the_profiles() {
+        let mut config = config();
+        ProfileOptions::register(&mut config);
+        let mut menu = OptionsMenu::new(Page::Gameplay);
+        menu.selected = menu.items.len() - 1;
+        assert_eq!(
+            menu.responder(KEY_RIGHTARROW, &mut config),
+            None,
+            "only one"
+        );
+        menu.profiles = vec!["default".into(), "alice".into(), "bob".into()];
+        // The list survives changing pages.
+        menu.show(Page::Hud);
+        menu.show(Page::Gameplay);
+        menu.selected = menu.items.len() - 1;
+        assert_eq!(
+            menu.responder(KEY_LEFTARROW, &mut config),
+            Some(OptionsAction::Changed("profile"))
+        );
+        assert_eq!(menu.items[menu.selected].value_text(&config), "BOB");
+        menu.responder(KEY_ENTER, &mut config);
+        assert_eq!(config.get_str("profile"), "default");
+    }
+}
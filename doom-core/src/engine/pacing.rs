@@ -0,0 +1,391 @@
+//! Keeping the game loop in step with the clock, vanilla's `TryRunTics` with limits on how it
+//! catches up.
+//!
+//! Each pass of the loop asks `Pacer::tics_due` how many tics to run, runs them and asks
+//! `should_render` whether to draw. Vanilla runs every tic the clock says is due, so after
+//! the machine stalls (a window being dragged, a disk spinning up) the game fast-forwards
+//! through all the time it missed, with the player unable to react. Here at most
+//! `max_catchup_tics` run in one pass and the rest of the stall is written off: the game
+//! simply pauses for it. Falling behind by less than that is caught up without drawing,
+//! but a frame is still drawn at least every `MAX_SKIPPED_FRAMES` passes so the screen never
+//! freezes.
+//!
+//! Frames drawn between tics show the world part of the way from the tic before the last to
+//! the last, by `Interpolation`'s fraction. It never goes back within a tic and is clamped
+//! to the tic, so a late frame, a stall or a jump of the clock can't make anything move
+//! backwards. Strict mode turns interpolation off, drawing each tic as it ended.
+//!
+//! What is interpolated is split into `Category`s with a switch each, since some players want
+//! the view smooth but the weapon bobbing in vanilla's steps. A category switched off is drawn
+//! at `FRACUNIT`, as the tic left it.
+
+use super::config::{Config, Configurable, Section};
+use super::speed::GameSpeed;
+use crate::game::strict::{Feature, StrictMode};
+use crate::util::fixed::{fixed_mul, Fixed, FRACUNIT};
+use std::time::Duration;
+
+/// Passes in a row that may skip drawing to catch up.
+pub const MAX_SKIPPED_FRAMES: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PacingOptions {
+    /// The most tics one pass of the loop runs; a longer stall is skipped.
+    pub max_catchup_tics: u32,
+    pub interpolate: bool,
+    /// Whether each `Category` is interpolated when `interpolate` is on, indexed by it.
+    pub categories: [bool; Category::ALL.len()],
+}
+
+impl Default for PacingOptions {
+    fn default() -> Self {
+        Self {
+            max_catchup_tics: 10,
+            interpolate: true,
+            categories: [true; Category::ALL.len()],
+        }
+    }
+}
+
+impl PacingOptions {
+    /// The options to play with: no interpolation in strict mode.
+    pub fn held(self, strict: &StrictMode) -> Self {
+        Self {
+            interpolate: self.interpolate && strict.allows(Feature::Interpolation),
+            ..self
+        }
+    }
+
+    pub fn interpolates(&self, category: Category) -> bool {
+        self.interpolate && self.categories[category as usize]
+    }
+}
+
+impl Configurable for PacingOptions {
+    fn register(config: &mut Config) {
+        config.register_int("max_catchup_tics", 10, Section::Extended);
+        config.register_bool("interpolate", true, Section::Extended);
+        for category in Category::ALL {
+            config.register_bool(category.key(), true, Section::Extended);
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_catchup_tics: config.get_int("max_catchup_tics").max(1) as u32,
+            interpolate: config.get_bool("interpolate"),
+            categories: Category::ALL.map(|category| config.get_bool(category.key())),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("max_catchup_tics", self.max_catchup_tics as i32);
+        config.set_bool("interpolate", self.interpolate);
+        for category in Category::ALL {
+            config.set_bool(category.key(), self.categories[category as usize]);
+        }
+    }
+}
+
+/// A kind of thing drawn between tics, switched on and off on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// The player's eyes: position, height and angle.
+    View,
+    Mobjs,
+    /// Floor and ceiling heights and scrolling texture offsets.
+    Sectors,
+    /// The weapon sprite's bob and raise.
+    Weapon,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::View,
+        Category::Mobjs,
+        Category::Sectors,
+        Category::Weapon,
+    ];
+
+    /// The config key that switches it.
+    pub fn key(self) -> &'static str {
+        match self {
+            Category::View => "interpolate_view",
+            Category::Mobjs => "interpolate_mobjs",
+            Category::Sectors => "interpolate_sectors",
+            Category::Weapon => "interpolate_weapon",
+        }
+    }
+}
+
+/// Hands out tics as the clock passes. Times are from any fixed starting point, such as an
+/// `Instant` taken at startup.
+#[derive(Clone, Debug)]
+pub struct Pacer {
+    speed: GameSpeed,
+    max_catchup_tics: u32,
+    /// The clock time tic 0 started at, moved on past each stall.
+    origin: Duration,
+    /// Tics handed out so far.
+    tics: u64,
+    skipped_frames: u32,
+    /// Tics written off to stalls, for the performance HUD.
+    pub dropped_tics: u64,
+}
+
+impl Pacer {
+    pub fn new(options: &PacingOptions, speed: GameSpeed, now: Duration) -> Self {
+        Self {
+            speed,
+            max_catchup_tics: options.max_catchup_tics.max(1),
+            origin: now,
+            tics: 0,
+            skipped_frames: 0,
+            dropped_tics: 0,
+        }
+    }
+
+    fn due(&self, now: Duration) -> u64 {
+        self.speed.tics(now.saturating_sub(self.origin))
+    }
+
+    /// How many tics to run now.
+    pub fn tics_due(&mut self, now: Duration) -> u32 {
+        let behind = self.due(now).saturating_sub(self.tics);
+        let run = behind.min(u64::from(self.max_catchup_tics));
+        if run < behind {
+            self.dropped_tics += behind - run;
+            self.origin = now - self.speed.duration(self.tics + run);
+        }
+        self.tics += run;
+        run as u32
+    }
+
+    /// Whether to draw once the tics are run: not while more are already due, unless
+    /// `MAX_SKIPPED_FRAMES` passes in a row have gone undrawn.
+    pub fn should_render(&mut self, now: Duration) -> bool {
+        if self.due(now) > self.tics && self.skipped_frames < MAX_SKIPPED_FRAMES {
+            self.skipped_frames += 1;
+            return false;
+        }
+        self.skipped_frames = 0;
+        true
+    }
+
+    /// How far the clock is past the last tic run, from 0 to `FRACUNIT`.
+    pub fn fraction(&self, now: Duration) -> Fixed {
+        let start = self.origin + self.speed.duration(self.tics);
+        let length = self.speed.duration(self.tics + 1) - self.speed.duration(self.tics);
+        let into = now.saturating_sub(start).min(length);
+        (into.as_micros() * FRACUNIT as u128 / length.as_micros().max(1)) as Fixed
+    }
+
+    /// The tics handed out so far.
+    pub fn tics(&self) -> u64 {
+        self.tics
+    }
+
+    /// When the next tic falls due, for sleeping until then.
+    pub fn next_tic(&self) -> Duration {
+        self.origin + self.speed.duration(self.tics + 1)
+    }
+}
+
+/// The fraction frames draw the world at, between the state before the last tic and after.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Interpolation {
+    pub enabled: bool,
+    pub categories: [bool; Category::ALL.len()],
+    /// The tic and fraction of the last frame.
+    last: Option<(u64, Fixed)>,
+}
+
+impl Interpolation {
+    /// Interpolates every category if `enabled`.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            categories: [true; Category::ALL.len()],
+            last: None,
+        }
+    }
+
+    /// Follows the options, as held by strict mode.
+    pub fn from_options(options: &PacingOptions) -> Self {
+        Self {
+            categories: options.categories,
+            ..Self::new(options.interpolate)
+        }
+    }
+
+    /// The fraction to draw the frame after `tic` with, from the pacer's: `FRACUNIT`
+    /// without interpolation, and never less than the last frame of the same tic drew.
+    pub fn fraction(&mut self, tic: u64, fraction: Fixed) -> Fixed {
+        if !self.enabled {
+            return FRACUNIT;
+        }
+        let mut fraction = fraction.clamp(0, FRACUNIT);
+        if let Some((last_tic, last)) = self.last {
+            if last_tic == tic {
+                fraction = fraction.max(last);
+            }
+        }
+        self.last = Some((tic, fraction));
+        fraction
+    }
+
+    /// The fraction to draw `category` at, given the frame's from `fraction`.
+    pub fn of(&self, category: Category, fraction: Fixed) -> Fixed {
+        match self.categories[category as usize] {
+            true => fraction,
+            false => FRACUNIT,
+        }
+    }
+}
+
+/// The value `fraction` of the way from `from` to `to`.
+pub fn lerp(from: Fixed, to: Fixed, fraction: Fixed) -> Fixed {
+    from.wrapping_add(fixed_mul(to.wrapping_sub(from), fraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixed::to_fixed;
+
+    const TIC: Duration = Duration::from_micros(28_572);
+
+    /// A loop against a clock that `stall` can freeze, counting what it runs and draws.
+    struct Harness {
+        pacer: Pacer,
+        now: Duration,
+        tics: u64,
+        frames: u64,
+    }
+
+    impl Harness {
+        fn new(options: &PacingOptions) -> Self {
+            Self {
+                pacer: Pacer::new(options, GameSpeed::default(), Duration::ZERO),
+                now: Duration::ZERO,
+                tics: 0,
+                frames: 0,
+            }
+        }
+
+        /// One pass of the loop, `step` after the last.
+        fn pass(&mut self, step: Duration) {
+            self.now += step;
+            self.tics += u64::from(self.pacer.tics_due(self.now));
+            if self.pacer.should_render(self.now) {
+                self.frames += 1;
+            }
+        }
+
+        fn stall(&mut self, length: Duration) {
+            self.now += length;
+        }
+    }
+
+    #[test]
+    fn stalls_are_skipped_after_the_catchup_limit() {
+        let mut loop_ = Harness::new(&PacingOptions::default());
+        for _ in 0..35 {
+            loop_.pass(TIC);
+        }
+        assert_eq!((loop_.tics, loop_.frames), (35, 35));
+
+        loop_.stall(Duration::from_secs(2));
+        loop_.pass(Duration::ZERO);
+        assert_eq!(loop_.tics, 45);
+        assert_eq!(loop_.pacer.dropped_tics, 60);
+        // Time carries on from the end of the stall as if it hadn't been.
+        loop_.pass(TIC);
+        assert_eq!(loop_.tics, 46);
+        assert!(loop_.pacer.fraction(loop_.now) < FRACUNIT / 100);
+
+        // A short stall is caught up in one go.
+        loop_.stall(TIC * 5);
+        loop_.pass(Duration::ZERO);
+        assert_eq!(loop_.tics, 51);
+        assert_eq!(loop_.pacer.dropped_tics, 60);
+    }
+
+    #[test]
+    fn frames_are_skipped_while_behind_but_not_for_long() {
+        // Tics that take longer to run than to play: every pass finds more due than the last.
+        let mut pacer = Pacer::new(
+            &PacingOptions::default(),
+            GameSpeed::default(),
+            Duration::ZERO,
+        );
+        let mut now = Duration::ZERO;
+        let (mut skipped, mut most_skipped) = (0, 0);
+        for _ in 0..20 {
+            now += TIC;
+            let run = pacer.tics_due(now);
+            now += TIC * 3 / 2 * run;
+            if pacer.should_render(now) {
+                skipped = 0;
+            } else {
+                skipped += 1;
+                most_skipped = most_skipped.max(skipped);
+            }
+        }
+        assert_eq!(most_skipped, MAX_SKIPPED_FRAMES);
+        // The cap keeps the loop from spiralling further behind.
+        assert!(pacer.dropped_tics > 0);
+    }
+
+    #[test]
+    fn interpolation_never_goes_backwards() {
+        let options = PacingOptions::default();
+        let mut pacer = Pacer::new(&options, GameSpeed::default(), Duration::ZERO);
+        pacer.tics_due(TIC);
+        let half = pacer.fraction(TIC + TIC / 2);
+        assert!((FRACUNIT / 2 - 8..=FRACUNIT / 2 + 8).contains(&half));
+        assert_eq!(pacer.fraction(TIC * 5), FRACUNIT);
+
+        let mut interpolation = Interpolation::new(options.interpolate);
+        assert_eq!(interpolation.fraction(1, half), half);
+        // A late frame of the same tic holds still rather than stepping back.
+        assert_eq!(interpolation.fraction(1, 100), half);
+        assert_eq!(interpolation.fraction(2, 100), 100);
+        assert_eq!(interpolation.fraction(2, -5), 100);
+        assert_eq!(interpolation.fraction(3, FRACUNIT * 2), FRACUNIT);
+
+        assert_eq!(
+            lerp(to_fixed(10), to_fixed(20), FRACUNIT / 4),
+            to_fixed(10) + FRACUNIT * 5 / 2
+        );
+        let strict = StrictMode { enabled: true };
+        let held = options.held(&strict);
+        assert!(!held.interpolate);
+        assert_eq!(
+            Interpolation::new(held.interpolate).fraction(1, 0),
+            FRACUNIT
+        );
+    }
+
+    #[test]
+    fn categories_switch_off_on_their_own() {
+        let mut config = Config::new();
+        PacingOptions::register(&mut config);
+        config.set_bool("interpolate_weapon", false);
+        let options = PacingOptions::from_config(&config);
+        assert!(options.interpolates(Category::View));
+        assert!(!options.interpolates(Category::Weapon));
+
+        let mut interpolation = Interpolation::from_options(&options);
+        let half = interpolation.fraction(1, FRACUNIT / 2);
+        assert_eq!(interpolation.of(Category::Sectors, half), FRACUNIT / 2);
+        assert_eq!(interpolation.of(Category::Weapon, half), FRACUNIT);
+
+        let held = options.held(&StrictMode { enabled: true });
+        assert!(!held.interpolates(Category::View));
+        let mut round_trip = Config::new();
+        PacingOptions::register(&mut round_trip);
+        options.to_config(&mut round_trip);
+        assert_eq!(PacingOptions::from_config(&round_trip), options);
+    }
+}
@@ -0,0 +1,216 @@
+//! Per subsystem timing, for the benchmark and the performance overlay.
+//!
+//! Each thread has one `Profiler`. Code marks a stretch of work with `scope`, which adds the
+//! time to its subsystem when dropped. Routines deep in the renderer can be timed this way
+//! without passing anything down to them. Timing is off until `set_enabled` turns it on for
+//! the thread. Until then a scope only checks a flag, so the draw loops can keep their
+//! scopes in release builds.
+
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Game,
+    Bsp,
+    Walls,
+    Columns,
+    Spans,
+    Sprites,
+    Mixer,
+    Present,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 8] = [
+        Subsystem::Game,
+        Subsystem::Bsp,
+        Subsystem::Walls,
+        Subsystem::Columns,
+        Subsystem::Spans,
+        Subsystem::Sprites,
+        Subsystem::Mixer,
+        Subsystem::Present,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::Game => "game",
+            Subsystem::Bsp => "bsp",
+            Subsystem::Walls => "walls",
+            Subsystem::Columns => "columns",
+            Subsystem::Spans => "spans",
+            Subsystem::Sprites => "sprites",
+            Subsystem::Mixer => "mixer",
+            Subsystem::Present => "present",
+        }
+    }
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::default());
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.set(enabled);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.get()
+}
+
+/// Times until dropped, when profiling is on.
+#[must_use]
+pub struct Scope {
+    subsystem: Subsystem,
+    start: Option<Instant>,
+}
+
+pub fn scope(subsystem: Subsystem) -> Scope {
+    Scope {
+        subsystem,
+        start: enabled().then(Instant::now),
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let elapsed = start.elapsed();
+            PROFILER.with(|profiler| profiler.borrow_mut().add(self.subsystem, elapsed));
+        }
+    }
+}
+
+/// Ends the frame on this thread's profiler, returning what it measured.
+pub fn end_frame() -> Frame {
+    PROFILER.with(|profiler| profiler.borrow_mut().end_frame())
+}
+
+/// This thread's totals so far.
+pub fn totals() -> Profiler {
+    PROFILER.with(|profiler| profiler.borrow().clone())
+}
+
+pub fn reset() {
+    PROFILER.with(|profiler| *profiler.borrow_mut() = Profiler::default());
+}
+
+/// The time each subsystem took in one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub times: [Duration; Subsystem::ALL.len()],
+}
+
+impl Frame {
+    pub fn time(&self, subsystem: Subsystem) -> Duration {
+        self.times[subsystem as usize]
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profiler {
+    current: Frame,
+    totals: Frame,
+    frames: u32,
+    worst: Frame, // Each subsystem's slowest frame, on its own.
+}
+
+impl Profiler {
+    pub fn add(&mut self, subsystem: Subsystem, time: Duration) {
+        self.current.times[subsystem as usize] += time;
+    }
+
+    pub fn end_frame(&mut self) -> Frame {
+        let frame = std::mem::take(&mut self.current);
+        for (i, &time) in frame.times.iter().enumerate() {
+            self.totals.times[i] += time;
+            self.worst.times[i] = self.worst.times[i].max(time);
+        }
+        self.frames += 1;
+        frame
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn total(&self, subsystem: Subsystem) -> Duration {
+        self.totals.time(subsystem)
+    }
+
+    pub fn average(&self, subsystem: Subsystem) -> Duration {
+        self.total(subsystem) / self.frames.max(1)
+    }
+
+    pub fn worst(&self, subsystem: Subsystem) -> Duration {
+        self.worst.time(subsystem)
+    }
+
+    /// `{"game": {"total_ms": ..., "avg_ms": ..., "max_ms": ...}, ...}`, every subsystem
+    /// listed even when nothing was timed, so reports from different builds line up.
+    pub fn to_json(&self) -> String {
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        let mut json = String::from("{");
+        for (i, subsystem) in Subsystem::ALL.into_iter().enumerate() {
+            if i > 0 {
+                json.push_str(", ");
+            }
+            write!(
+                json,
+                "\"{}\": {{\"total_ms\": {:.3}, \"avg_ms\": {:.4}, \"max_ms\": {:.4}}}",
+                subsystem.name(),
+                ms(self.total(subsystem)),
+                ms(self.average(subsystem)),
+                ms(self.worst(subsystem))
+            )
+            .unwrap();
+        }
+        json.push('}');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_accumulate_into_totals_and_worst_cases() {
+        let mut profiler = Profiler::default();
+        profiler.add(Subsystem::Columns, Duration::from_millis(3));
+        profiler.add(Subsystem::Columns, Duration::from_millis(1));
+        assert_eq!(
+            profiler.end_frame().time(Subsystem::Columns),
+            Duration::from_millis(4)
+        );
+        profiler.add(Subsystem::Columns, Duration::from_millis(2));
+        profiler.end_frame();
+        assert_eq!(profiler.frames(), 2);
+        assert_eq!(
+            profiler.average(Subsystem::Columns),
+            Duration::from_millis(3)
+        );
+        assert_eq!(profiler.worst(Subsystem::Columns), Duration::from_millis(4));
+        let json = profiler.to_json();
+        assert!(json.starts_with("{\"game\": {\"total_ms\": 0.000"));
+        assert!(json.contains("\"columns\": {\"total_ms\": 6.000, \"avg_ms\": 3.0000"));
+    }
+
+    #[test]
+    fn scopes_only_time_while_enabled() {
+        reset();
+        drop(scope(Subsystem::Mixer));
+        assert_eq!(end_frame(), Frame::default());
+        set_enabled(true);
+        {
+            let _scope = scope(Subsystem::Mixer);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        set_enabled(false);
+        assert!(end_frame().time(Subsystem::Mixer) >= Duration::from_millis(1));
+        assert_eq!(totals().frames(), 2);
+    }
+}
@@ -0,0 +1,183 @@
+//! Named config profiles, so players sharing an install keep their own bindings, HUD and
+//! sound settings.
+//!
+//! A profile is a whole config file, `<name>.cfg` in a `profiles` folder next to the main
+//! config. The main config itself is the profile called `default`, and its `profile` option
+//! names the one to use, which the menu changes and `-profile` overrides for a run. A profile
+//! that has no file yet starts as a copy of the main config's settings and gets its file on
+//! the first save.
+
+use super::config::{Config, Configurable, Section};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The profile kept in the main config.
+pub const DEFAULT_PROFILE: &str = "default";
+pub const PROFILE_DIR: &str = "profiles";
+/// Longest profile name, so it fits the menu.
+pub const MAX_NAME: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileOptions {
+    /// The profile picked last.
+    pub name: String,
+}
+
+impl Configurable for ProfileOptions {
+    fn register(config: &mut Config) {
+        config.register_str("profile", DEFAULT_PROFILE, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let name = config.get_str("profile");
+        Self {
+            name: match is_valid_name(&name) {
+                true => name,
+                false => DEFAULT_PROFILE.to_string(),
+            },
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("profile", &self.name);
+    }
+}
+
+/// Letters, digits, `-` and `_`, so a name is always a plain file name.
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=MAX_NAME).contains(&name.len())
+        && name
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_')
+}
+
+/// Where the profiles of one main config live.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profiles {
+    main: PathBuf,
+    dir: PathBuf,
+}
+
+impl Profiles {
+    /// The profiles beside the main config at `main`.
+    pub fn new(main: &Path) -> Self {
+        let parent = main.parent().unwrap_or(Path::new(""));
+        Self {
+            main: main.to_path_buf(),
+            dir: parent.join(PROFILE_DIR),
+        }
+    }
+
+    /// The file of profile `name`.
+    pub fn path(&self, name: &str) -> PathBuf {
+        match name {
+            DEFAULT_PROFILE => self.main.clone(),
+            _ => self.dir.join(format!("{name}.cfg")),
+        }
+    }
+
+    /// Every profile with a file, `default` first and the rest by name.
+    pub fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "cfg" {
+                    return None;
+                }
+                let name = path.file_stem()?.to_str()?;
+                (is_valid_name(name) && name != DEFAULT_PROFILE).then(|| name.to_string())
+            })
+            .collect();
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+
+    /// Loads profile `name` over the main config already in `config`, so saving writes to
+    /// the profile's file.
+    pub fn open(&self, config: &mut Config, name: &str) -> io::Result<()> {
+        if !is_valid_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{name}' isn't a profile name"),
+            ));
+        }
+        if name != DEFAULT_PROFILE {
+            fs::create_dir_all(&self.dir)?;
+            config.load(&self.path(name))?;
+        }
+        config.set_str("profile", name);
+        Ok(())
+    }
+
+    /// Saves the open profile, records `name` in the main config for the next start and
+    /// opens it.
+    pub fn switch(&self, config: &mut Config, name: &str) -> io::Result<()> {
+        config.save()?;
+        config.load(&self.main)?;
+        config.set_str("profile", name);
+        config.save()?;
+        self.open(config, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered() -> Config {
+        let mut config = Config::new();
+        ProfileOptions::register(&mut config);
+        config.register_int("key_fire", 157, Section::Vanilla);
+        config.register_int("sfx_volume", 8, Section::Vanilla);
+        config
+    }
+
+    #[test]
+    fn names_are_plain_file_names() {
+        assert!(is_valid_name("player_2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("../default"));
+        assert!(!is_valid_name("a name"));
+        assert!(!is_valid_name(&"x".repeat(MAX_NAME + 1)));
+        let mut config = registered();
+        config.set_str("profile", "no/such");
+        assert_eq!(ProfileOptions::from_config(&config).name, DEFAULT_PROFILE);
+    }
+
+    #[test]
+    fn profiles_keep_their_own_settings() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-profiles-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let main = folder.join("default.cfg");
+        fs::write(&main, "sfx_volume 3\n").unwrap();
+        let profiles = Profiles::new(&main);
+        assert_eq!(profiles.list(), ["default"]);
+
+        // A new profile starts from the main config and is picked again next time.
+        let mut config = registered();
+        config.load(&main).unwrap();
+        profiles.switch(&mut config, "alice").unwrap();
+        config.set_int("key_fire", b'f' as i32);
+        config.save().unwrap();
+        assert_eq!(config.get_int("sfx_volume"), 3);
+        assert_eq!(profiles.list(), ["default", "alice"]);
+
+        let mut next = registered();
+        next.load(&main).unwrap();
+        let name = ProfileOptions::from_config(&next).name;
+        assert_eq!(name, "alice");
+        profiles.open(&mut next, &name).unwrap();
+        assert_eq!(next.get_int("key_fire"), b'f' as i32);
+
+        // The main config never saw the binding.
+        profiles.switch(&mut next, DEFAULT_PROFILE).unwrap();
+        assert_eq!(next.get_int("key_fire"), 157);
+        assert_eq!(next.get_str("profile"), DEFAULT_PROFILE);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+//! Replay regression: play the IWAD's own demos from start to finish and compare where the
+//! game ended up with what a known-good build recorded.
+//!
+//! Tests cannot ship the IWAD, so they look for one through `DOOM_TEST_IWAD` and quietly
+//! pass when it's unset. The references live in `replays.txt` next to the crate's manifest,
+//! one `<demo> <tics> <hash>` line per demo. A change that alters the simulation on purpose
+//! refreshes them with `DOOM_BLESS=1`, and the diff to that file shows up in review.
+//!
+//! `replay_on_threads` checks the other way round: the same demo on several threads at once,
+//! with different worker counts, has to end the same everywhere. It needs no references, so
+//! it runs with every `cargo test` on the demos the tests build themselves.
+
+use super::config::{Config, Configurable};
+use super::Engine;
+use crate::game::demo::{Demo, DemoPlayback};
+use crate::game::precache::PrecacheOptions;
+use crate::wad::file::{WadFile, Wads};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const IWAD_VAR: &str = "DOOM_TEST_IWAD";
+
+/// The demos every IWAD carries and the title screen cycles through.
+pub const DEMOS: [&str; 3] = ["DEMO1", "DEMO2", "DEMO3"];
+
+/// How a demo ended: how many tics it ran and the engine's `tic_hash` after the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub demo: String,
+    pub tics: usize,
+    pub hash: u64,
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {:016x}", self.demo, self.tics, self.hash)
+    }
+}
+
+impl FromStr for Replay {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, String> {
+        let bad = || format!("bad replay reference {line:?}");
+        let mut words = line.split_whitespace();
+        let (Some(demo), Some(tics), Some(hash), None) =
+            (words.next(), words.next(), words.next(), words.next())
+        else {
+            return Err(bad());
+        };
+        Ok(Self {
+            demo: demo.to_ascii_uppercase(),
+            tics: tics.parse().map_err(|_| bad())?,
+            hash: u64::from_str_radix(hash, 16).map_err(|_| bad())?,
+        })
+    }
+}
+
+/// Reads a references file, skipping blank lines and `#` comments.
+pub fn parse_references(text: &str) -> Result<Vec<Replay>, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::parse)
+        .collect()
+}
+
+/// The IWAD named by `DOOM_TEST_IWAD`, or `None` when the variable isn't set.
+pub fn test_iwad() -> Option<io::Result<Wads>> {
+    let path = std::env::var_os(IWAD_VAR)?;
+    Some(WadFile::open(Path::new(&path)).map(|file| {
+        let mut wads = Wads::new();
+        wads.add(file);
+        wads
+    }))
+}
+
+/// Plays the demo lump `demo` on a fresh engine with no window, sound or clock.
+pub fn replay(wads: &Wads, demo: &str) -> Result<Replay, String> {
+    replay_with(wads, demo, Config::new())
+}
+
+fn replay_with(wads: &Wads, demo: &str, config: Config) -> Result<Replay, String> {
+    let lump = wads.lump(demo).ok_or_else(|| format!("no {demo} lump"))?;
+    let parsed = Demo::parse(&lump.data).map_err(|error| format!("{demo}: {error}"))?;
+    let mut playback = DemoPlayback::new(parsed);
+    let mut engine = Engine::new(wads.clone(), config);
+    let mut tics = 0;
+    while let Some(cmds) = playback.next_tic() {
+        engine.run_tic(cmds);
+        tics += 1;
+    }
+    Ok(Replay {
+        demo: demo.to_ascii_uppercase(),
+        tics,
+        hash: engine.tic_hash(),
+    })
+}
+
+/// Plays `demo` once per entry of `threads`, all at the same time on threads of their own,
+/// each with `precache_threads` set to that entry. The simulation must not care where or
+/// beside what it runs, so every run has to end on the same hash; if one doesn't, the
+/// error lists them all.
+pub fn replay_on_threads(wads: &Wads, demo: &str, threads: &[usize]) -> Result<Replay, String> {
+    let results: Vec<Result<Replay, String>> = std::thread::scope(|scope| {
+        let runs: Vec<_> = threads
+            .iter()
+            .map(|&count| {
+                scope.spawn(move || {
+                    let mut config = Config::new();
+                    PrecacheOptions::register(&mut config);
+                    config.set_int("precache_threads", count as i32);
+                    replay_with(wads, demo, config)
+                })
+            })
+            .collect();
+        runs.into_iter()
+            .map(|run| {
+                run.join()
+                    .unwrap_or_else(|_| Err(format!("{demo}: panicked")))
+            })
+            .collect()
+    });
+    let results = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+    let first = results.first().ok_or("no threads to replay on")?;
+    if results.iter().any(|result| result != first) {
+        let runs: Vec<String> = threads
+            .iter()
+            .zip(&results)
+            .map(|(count, result)| format!("{count} threads: {result}"))
+            .collect();
+        return Err(format!("{demo} differs between runs: {}", runs.join(", ")));
+    }
+    Ok(first.clone())
+}
+
+/// Replays each of `DEMOS` the IWAD has and describes every way the results differ from
+/// `references`. A demo with no reference is a difference too.
+pub fn compare(wads: &Wads, references: &[Replay]) -> (Vec<Replay>, Vec<String>) {
+    let mut results = Vec::new();
+    let mut differences = Vec::new();
+    for demo in DEMOS.iter().filter(|demo| wads.lump(demo).is_some()) {
+        let result = match replay(wads, demo) {
+            Ok(result) => result,
+            Err(error) => {
+                differences.push(error);
+                continue;
+            }
+        };
+        match references
+            .iter()
+            .find(|reference| reference.demo == result.demo)
+        {
+            Some(reference) if *reference == result => {}
+            Some(reference) => differences.push(format!("expected {reference}, got {result}")),
+            None => differences.push(format!("no reference for {result}")),
+        }
+        results.push(result);
+    }
+    (results, differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::demo::{DemoHeader, DemoRecorder};
+    use crate::game::ticcmd::TicCmd;
+    use crate::wad::file::tests::build_wad;
+
+    fn wads_with_demo(turn: i16) -> Wads {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..100 {
+            recorder.record(&[TicCmd {
+                forwardmove: 0x19,
+                angleturn: turn,
+                ..TicCmd::default()
+            }]);
+        }
+        let bytes = build_wad(b"IWAD", &[("DEMO1", &recorder.demo.to_bytes())]);
+        let mut wads = Wads::new();
+        wads.add(WadFile::parse(Path::new("test.wad"), &bytes).unwrap());
+        wads
+    }
+
+    #[test]
+    fn replays_are_deterministic() {
+        let wads = wads_with_demo(256);
+        let first = replay(&wads, "DEMO1").unwrap();
+        assert_eq!(first.tics, 100);
+        assert_eq!(replay(&wads, "demo1"), Ok(first.clone()));
+        assert_ne!(
+            replay(&wads_with_demo(512), "DEMO1").unwrap().hash,
+            first.hash
+        );
+        assert_eq!(replay(&wads, "DEMO2"), Err("no DEMO2 lump".to_string()));
+
+        let (results, differences) = compare(&wads, std::slice::from_ref(&first));
+        assert_eq!((results, differences), (vec![first.clone()], vec![]));
+        let stale = Replay { hash: 1, ..first };
+        let (_, differences) = compare(&wads, &[stale]);
+        assert_eq!(differences.len(), 1);
+        assert!(differences[0].starts_with("expected DEMO1 100 0000000000000001, got"));
+    }
+
+    #[test]
+    fn replays_agree_across_threads() {
+        let wads = wads_with_demo(256);
+        let single = replay(&wads, "DEMO1").unwrap();
+        assert_eq!(replay_on_threads(&wads, "DEMO1", &[1, 2, 8]), Ok(single));
+        assert_eq!(
+            replay_on_threads(&wads, "DEMO2", &[1, 2]),
+            Err("no DEMO2 lump".to_string())
+        );
+        assert!(replay_on_threads(&wads, "DEMO1", &[]).is_err());
+    }
+
+    /// The IWAD's demos in `DOOM_TEST_IWAD` on one and on several worker threads.
+    #[test]
+    fn iwad_demos_agree_across_threads() {
+        let Some(wads) = test_iwad() else {
+            return;
+        };
+        let wads = wads.expect("DOOM_TEST_IWAD should name a readable WAD");
+        for demo in DEMOS.iter().filter(|demo| wads.lump(demo).is_some()) {
+            if let Err(error) = replay_on_threads(&wads, demo, &[1, 4]) {
+                panic!("{error}");
+            }
+        }
+    }
+
+    #[test]
+    fn references_round_trip() {
+        let text = "# comment\n\nDEMO1 5026 00000000deadbeef\ndemo2 0 0\n";
+        let references = parse_references(text).unwrap();
+        assert_eq!(references[0].to_string(), "DEMO1 5026 00000000deadbeef");
+        assert_eq!(references[1].demo, "DEMO2");
+        assert!(parse_references("DEMO1 5026").is_err());
+    }
+
+    /// Plays the demos of the IWAD in `DOOM_TEST_IWAD` against `replays.txt`.
+    #[test]
+    fn iwad_demos_match_their_references() {
+        let Some(wads) = test_iwad() else {
+            return;
+        };
+        let wads = wads.expect("DOOM_TEST_IWAD should name a readable WAD");
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("replays.txt");
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        let references = parse_references(&text).unwrap();
+        let (results, differences) = compare(&wads, &references);
+        if std::env::var_os("DOOM_BLESS").is_some() {
+            let mut merged = results.clone();
+            merged.extend(
+                references
+                    .into_iter()
+                    .filter(|reference| !results.iter().any(|r| r.demo == reference.demo)),
+            );
+            merged.sort_by(|a, b| a.demo.cmp(&b.demo));
+            let header = text.lines().take_while(|line| line.starts_with('#'));
+            let lines: Vec<String> = header
+                .map(str::to_string)
+                .chain(merged.iter().map(Replay::to_string))
+                .collect();
+            std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+            return;
+        }
+        assert!(
+            differences.is_empty(),
+            "{}\nrerun with DOOM_BLESS=1 if the change is intended",
+            differences.join("\n")
+        );
+    }
+}
@@ -0,0 +1,1059 @@
+//! The game in progress, between one level and the next: `G_InitNew`, `G_DoCompleted` and
+//! `G_DoLoadLevel` without the playsim.
+//!
+//! A `Session` knows the map being played, how the game was set up and what every player
+//! carries. Exiting a level runs `G_PlayerFinishLevel` for everyone and then pistol start,
+//! if it is on, before the next map is entered; `Inventory::initial` is what a new game and
+//! a pistol start both hand out. Entering a map reads it, precaching what it shows and plays
+//! unless that is off, and keeps the map things the setup's `SpawnFilter` lets through, as
+//! `P_LoadThings` does before spawning them. The kill, item and secret totals are counted
+//! from what spawned, into the `LevelStats` the intermission and the extended HUD both show.
+//! Where an exit leads, and the text screen shown on the way, come from UMAPINFO before the
+//! vanilla rules. The table limits a game plays with follow its compatibility level, with
+//! the player's overrides on top unless a demo is being played back.
+//!
+//! In a netgame every peer has to play by the same setup, so the server sends its own as the
+//! block `GameSetup::encode` makes, with the deathmatch rules at the end, and the peers start
+//! their games from what `decode` reads. The filter's excluded things, the compatibility
+//! level and the limits don't travel, so the peers have to be started with the same ones.
+//!
+//! Players appearing after the first frame, items coming back in `-altdeath` and monsters
+//! rising again on nightmare each leave fog, which waits in `fog` for the engine to play
+//! its sound. The session keeps no geometry, so the floors it goes on are handed in, as are
+//! the trig tables.
+//!
+//! A savegame and a rewind keyframe both keep the `SessionState`: the map, the players, the
+//! things still on it and the counters. Restoring one leaves the setup as it is.
+//!
+//! The players' bodies spawn at their starts as the level is entered. A player dropping out
+//! of a netgame leaves theirs standing or takes it away, as `DropOptions` says, and coming
+//! back steers it again or spawns a new one.
+//!
+//! The cheat codes reach the game through `CheatAccess`: the console player's inventory and
+//! cheat flags, the jukebox for `IDMUS`, and for `IDCLEV` the map a new game is to start on,
+//! at the same skill, as vanilla's `G_DeferedInitNew`.
+//!
+//! With scripting built in, the hooks reach the game through `ScriptAccess`. A thing a hook
+//! removes leaves an empty slot, so the ids of the others stay put.
+
+use super::args::Warp;
+use crate::audio::jukebox::Jukebox;
+use crate::game::cheat::{CheatTarget, Power, CF_GODMODE, CF_NOCLIP};
+use crate::game::compatibility::{CompLevel, Compatibility};
+use crate::game::deathmatch::{self, DeathmatchRules};
+use crate::game::demo::MAXPLAYERS;
+use crate::game::dropin::{self, Arrival, Departure, DropOptions};
+use crate::game::fog::{self, Fog};
+use crate::game::info::{GameInfo, MF_COUNTITEM, MF_COUNTKILL};
+use crate::game::limits::{GameplayLimits, LimitOverrides, OverflowOptions};
+use crate::game::loading::{self, LoadProgress};
+use crate::game::player::{Inventory, PistolStart, NUMCARDS, NUMWEAPONS};
+use crate::game::precache::{LevelCache, PrecacheOptions};
+use crate::game::reborn::Player;
+use crate::game::savegame::{Archive, SaveError, SaveReader, SaveWriter};
+use crate::game::sector_sound;
+use crate::game::spawn::SpawnFilter;
+use crate::game::stats::LevelStats;
+use crate::game::umapinfo::{self, NextMap, Setting, UMapInfo};
+use crate::game::voodoo::PlayerBodies;
+use crate::game::weapons::WP_CHAINSAW;
+use crate::game::{GameMode, Skill, Trig};
+use crate::renderer::ViewPoint;
+use crate::util::fixed::{to_fixed, Fixed};
+use crate::wad::file::Wads;
+use crate::wad::map::Thing;
+#[cfg(feature = "scripting")]
+use {
+    super::console::Console,
+    crate::game::script::{GameAccess, ScriptEvent, ThingId},
+    crate::util::fixed::FRACBITS,
+};
+
+/// Bytes of the block a server sends its peers.
+pub const SETUP_SIZE: usize = 2 + deathmatch::SETUP_SIZE;
+
+const NETGAME: u8 = 1;
+const DEATHMATCH: u8 = 2;
+const NOMONSTERS: u8 = 4;
+const COOP_SPAWNS: u8 = 8;
+const PISTOL_START: u8 = 16;
+
+/// How a game is started, fixed until the next new game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameSetup {
+    pub skill: Skill,
+    pub netgame: bool,
+    pub deathmatch: bool,
+    pub spawn_filter: SpawnFilter,
+    pub pistol_start: PistolStart,
+    pub deathmatch_rules: DeathmatchRules,
+    pub complevel: CompLevel,
+    /// Playing a demo back, which keeps the limits it was recorded under.
+    pub demo_playback: bool,
+    pub limit_overrides: LimitOverrides,
+    pub overflows: OverflowOptions,
+}
+
+impl GameSetup {
+    /// The setup as a server sends it: skill, switches, then the deathmatch rules.
+    pub fn encode(&self) -> [u8; SETUP_SIZE] {
+        let mut block = [0; SETUP_SIZE];
+        block[0] = self.skill as u8;
+        for (on, bit) in [
+            (self.netgame, NETGAME),
+            (self.deathmatch, DEATHMATCH),
+            (self.spawn_filter.nomonsters, NOMONSTERS),
+            (self.spawn_filter.coop_spawns, COOP_SPAWNS),
+            (self.pistol_start.enabled, PISTOL_START),
+        ] {
+            block[1] |= if on { bit } else { 0 };
+        }
+        block[2..].copy_from_slice(&self.deathmatch_rules.encode());
+        block
+    }
+
+    /// Reads a server's setup. Returns `None` if the block is short or has values this
+    /// version doesn't know.
+    pub fn decode(block: &[u8]) -> Option<Self> {
+        let (&[skill, flags], rules) = block.split_first_chunk::<2>()?;
+        let on = |bit: u8| flags & bit != 0;
+        Some(Self {
+            skill: Skill::from_number(skill.checked_add(1)?)?,
+            netgame: on(NETGAME),
+            deathmatch: on(DEATHMATCH),
+            spawn_filter: SpawnFilter {
+                nomonsters: on(NOMONSTERS),
+                coop_spawns: on(COOP_SPAWNS),
+                ..SpawnFilter::default()
+            },
+            pistol_start: PistolStart {
+                enabled: on(PISTOL_START),
+            },
+            deathmatch_rules: DeathmatchRules::decode(rules)?,
+            ..Self::default()
+        })
+    }
+}
+
+/// What finishing a level leads to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub next: NextMap,
+    /// The text screen UMAPINFO puts between the levels, if any.
+    pub text: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub setup: GameSetup,
+    /// The map being played, such as `E1M1` or `MAP01`.
+    pub map: String,
+    pub players: [Inventory; MAXPLAYERS],
+    /// The map things that spawned on the level, `None` where a script removed one. A
+    /// thing's index is its script `ThingId`.
+    pub things: Vec<Option<Thing>>,
+    /// `frags[killer][victim]` on this level, as the deathmatch rules count them.
+    pub frags: [[i32; MAXPLAYERS]; MAXPLAYERS],
+    /// The console player's counts and times, level and run.
+    pub stats: LevelStats,
+    pub limits: GameplayLimits,
+    pub overflows: OverflowOptions,
+    /// The players' mobjs and the starts they came from.
+    pub bodies: PlayerBodies,
+    /// The console player's `CF_` flags, from the cheats.
+    pub cheats: u32,
+    /// Where each sector's movers sound from, `P_GroupLines`' `soundorg`.
+    pub sound_origins: Vec<(Fixed, Fixed)>,
+    /// Fog spawned since the engine last took it, for its sound.
+    pub fog: Vec<Fog>,
+    /// Items picked up, by map thing, with the `leveltime` they went, oldest first:
+    /// `itemrespawnque`.
+    item_queue: Vec<(usize, Thing, i32)>,
+}
+
+impl Session {
+    /// `G_InitNew`: everyone starts `map` with a new game's inventory.
+    pub fn new(setup: GameSetup, map: &str, info: &GameInfo) -> Self {
+        let compat = Compatibility::for_level(setup.complevel);
+        let limits = GameplayLimits::resolve(&compat, &setup.limit_overrides, setup.demo_playback);
+        let overflows = setup.overflows.for_compat(&compat);
+        Self {
+            setup,
+            map: map.to_ascii_uppercase(),
+            players: [Inventory::initial(info); MAXPLAYERS],
+            things: Vec::new(),
+            frags: [[0; MAXPLAYERS]; MAXPLAYERS],
+            stats: LevelStats::default(),
+            limits,
+            overflows,
+            bodies: PlayerBodies::default(),
+            cheats: 0,
+            sound_origins: Vec::new(),
+            fog: Vec::new(),
+            item_queue: Vec::new(),
+        }
+    }
+
+    /// `P_SetupLevel` for the current map, through `load_level`: what the last level decoded
+    /// is dropped from `cache` and the new one's graphics and sounds are precached into it,
+    /// unless `precache` says not to. Returns false, with nothing spawned, if the map is
+    /// missing or broken.
+    pub fn enter_level(
+        &mut self,
+        wads: &Wads,
+        info: &GameInfo,
+        map_info: &UMapInfo,
+        cache: &LevelCache,
+        precache: &PrecacheOptions,
+        progress: &mut LoadProgress,
+    ) -> bool {
+        self.frags = [[0; MAXPLAYERS]; MAXPLAYERS];
+        self.fog.clear();
+        self.item_queue.clear();
+        cache.clear();
+        let sky = umapinfo::sky_texture(map_info, &self.map);
+        let Some(level) =
+            loading::load_level(wads, &self.map, info, &sky, cache, precache, progress)
+        else {
+            self.things.clear();
+            self.sound_origins.clear();
+            return false;
+        };
+        self.sound_origins = sector_sound::sound_origins(&level.map);
+        let setup = &self.setup;
+        self.things = level
+            .map
+            .things
+            .into_iter()
+            .filter(|thing| {
+                let filter = &setup.spawn_filter;
+                filter.spawns(thing, setup.netgame, setup.deathmatch, setup.skill, info)
+            })
+            .map(Some)
+            .collect();
+        let flagged = |flag| {
+            let things = self.things.iter().flatten();
+            let mobjs = things.filter_map(|thing| {
+                let doomednum = i32::from(thing.kind);
+                info.mobjs.iter().find(|mobj| mobj.doomednum == doomednum)
+            });
+            mobjs.filter(|mobj| mobj.flags & flag != 0).count() as i32
+        };
+        let secrets = level
+            .map
+            .sectors
+            .iter()
+            .filter(|sector| sector.special == 9);
+        let (kills, items) = (flagged(MF_COUNTKILL), flagged(MF_COUNTITEM));
+        self.stats.start_level(kills, items, secrets.count() as i32);
+        true
+    }
+
+    /// `G_DoCompleted`: works out where the exit leads and leaves the level for it, unless
+    /// that was the end of the game. The next level is still to be entered.
+    pub fn complete_level(
+        &mut self,
+        secret: bool,
+        map_info: &UMapInfo,
+        info: &GameInfo,
+    ) -> Completion {
+        let next = umapinfo::next_map(map_info, &self.map, secret);
+        let text = map_info.get(&self.map).and_then(|entry| {
+            let secret_text = entry.inter_text_secret.as_ref().filter(|_| secret);
+            match secret_text.or(entry.inter_text.as_ref())? {
+                Setting::Set(lines) => Some(lines.clone()),
+                Setting::Clear => None,
+            }
+        });
+        if let NextMap::Map(map) = &next {
+            self.exit_level(map, info);
+        }
+        Completion { next, text }
+    }
+
+    /// Leaves the level for `next`: keys and powers stay behind, and with pistol start
+    /// everything else does too.
+    pub fn exit_level(&mut self, next: &str, info: &GameInfo) {
+        self.stats.finish_level();
+        for inventory in &mut self.players {
+            inventory.finish_level();
+        }
+        self.setup.pistol_start.enter_level(&mut self.players, info);
+        self.map = next.to_ascii_uppercase();
+    }
+}
+
+impl Session {
+    /// `G_CheckSpot` letting a player in at the map thing `start`, a deathmatch start or
+    /// the one `do_reborn` respawned them at: fog in front of it on `floor`, silent on the
+    /// level's first frame. Returns false if there is no such thing.
+    pub fn spawn_player(
+        &mut self,
+        start: usize,
+        floor: Fixed,
+        first_frame: bool,
+        trig: &impl Trig,
+    ) -> bool {
+        let Some(Some(start)) = self.things.get(start) else {
+            return false;
+        };
+        let spot = (to_fixed(i32::from(start.x)), to_fixed(i32::from(start.y)));
+        let compat = Compatibility::for_level(self.setup.complevel);
+        let fog = fog::player_spawn(spot, start.angle, floor, first_frame, &compat, trig);
+        self.fog.push(fog);
+        true
+    }
+
+    /// An item taken off the level at `leveltime`, queued to come back. Returns it, or
+    /// `None` if it is already gone.
+    pub fn pick_up(&mut self, thing: usize, leveltime: i32) -> Option<Thing> {
+        let item = self.things.get_mut(thing)?.take()?;
+        self.item_queue.push((thing, item, leveltime));
+        Some(item)
+    }
+
+    /// `P_RespawnSpecials`, run every tic in `-altdeath`: the item picked up longest ago
+    /// comes back once it has been gone long enough, with fog on `floor` under its spot.
+    pub fn respawn_specials(&mut self, leveltime: i32, floor: impl Fn(Fixed, Fixed) -> Fixed) {
+        let Some(&(thing, item, removed_at)) = self.item_queue.first() else {
+            return;
+        };
+        if !fog::item_due(leveltime, removed_at) {
+            return;
+        }
+        self.item_queue.remove(0);
+        let spot = (to_fixed(i32::from(item.x)), to_fixed(i32::from(item.y)));
+        self.fog
+            .push(fog::item_respawn(spot, floor(spot.0, spot.1)));
+        self.things[thing] = Some(item);
+    }
+
+    /// `P_NightmareRespawn` for the monster at the map thing `thing`, which died at `corpse`:
+    /// fog there and at its spawn spot, each on the floor under it. Returns false if there is
+    /// no such monster.
+    pub fn respawn_monster(
+        &mut self,
+        thing: usize,
+        corpse: (Fixed, Fixed),
+        floor: impl Fn(Fixed, Fixed) -> Fixed,
+    ) -> bool {
+        let Some(Some(monster)) = self.things.get(thing) else {
+            return false;
+        };
+        let spot = (
+            to_fixed(i32::from(monster.x)),
+            to_fixed(i32::from(monster.y)),
+        );
+        let corpse_floor = floor(corpse.0, corpse.1);
+        let fog = fog::monster_respawn(corpse, corpse_floor, spot, floor(spot.0, spot.1));
+        self.fog.extend(fog);
+        true
+    }
+}
+
+impl Session {
+    /// `P_SpawnMapThing` for the player starts: a body at every start of a player in the
+    /// game.
+    pub fn spawn_players(&mut self, in_game: &[bool; MAXPLAYERS]) {
+        let removed = Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind: 0,
+            flags: 0,
+        };
+        let things: Vec<Thing> = self
+            .things
+            .iter()
+            .map(|thing| thing.unwrap_or(removed))
+            .collect();
+        self.bodies = PlayerBodies::spawn(&things, in_game, self.setup.deathmatch, &self.players);
+    }
+
+    /// `player` dropped out of the game.
+    pub fn player_left(
+        &mut self,
+        player: usize,
+        in_game: &mut [bool; MAXPLAYERS],
+        options: &DropOptions,
+    ) -> Departure {
+        dropin::player_left(player, in_game, &mut self.bodies, options)
+    }
+
+    /// `player` is back in their old slot, with a new life if their body went.
+    pub fn player_joined(
+        &mut self,
+        player: usize,
+        in_game: &mut [bool; MAXPLAYERS],
+        info: &GameInfo,
+    ) -> Option<Arrival> {
+        let mut players = self.players.map(|inventory| Player {
+            inventory,
+            ..Player::new(info)
+        });
+        let arrival = dropin::player_joined(player, in_game, &mut players, &mut self.bodies, info);
+        self.players[player] = players[player].inventory;
+        arrival
+    }
+}
+
+/// What a savegame or a rewind keyframe holds of a session.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionState {
+    pub map: String,
+    pub players: [Inventory; MAXPLAYERS],
+    pub things: Vec<Option<Thing>>,
+    pub frags: [[i32; MAXPLAYERS]; MAXPLAYERS],
+    pub stats: LevelStats,
+}
+
+/// Map names are kept in eight bytes, as lump names are.
+const MAP_NAME_SIZE: usize = 8;
+
+impl Session {
+    pub fn state(&self) -> SessionState {
+        SessionState {
+            map: self.map.clone(),
+            players: self.players,
+            things: self.things.clone(),
+            frags: self.frags,
+            stats: self.stats,
+        }
+    }
+
+    /// Puts the game back as `state` has it. The map must already be the one it was saved
+    /// on, entered so its level is loaded.
+    pub fn restore(&mut self, state: SessionState) {
+        self.map = state.map;
+        self.players = state.players;
+        self.things = state.things;
+        self.frags = state.frags;
+        self.stats = state.stats;
+    }
+}
+
+impl Archive for SessionState {
+    fn archive(&self, writer: &mut SaveWriter) {
+        let mut map = [0; MAP_NAME_SIZE];
+        let len = self.map.len().min(MAP_NAME_SIZE);
+        map[..len].copy_from_slice(&self.map.as_bytes()[..len]);
+        writer.write_bytes(&map);
+        for inventory in &self.players {
+            writer.archive(inventory);
+        }
+        writer.write_u32(self.things.len() as u32);
+        for thing in &self.things {
+            writer.write_bool(thing.is_some());
+            if let Some(thing) = thing {
+                writer.write_i16(thing.x);
+                writer.write_i16(thing.y);
+                writer.write_i16(thing.angle);
+                writer.write_i16(thing.kind as i16);
+                writer.write_i16(thing.flags as i16);
+            }
+        }
+        writer.pad();
+        for &frags in self.frags.iter().flatten() {
+            writer.write_i32(frags);
+        }
+        writer.archive(&self.stats);
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        let map = reader.read_bytes(MAP_NAME_SIZE)?;
+        let len = map.iter().position(|&b| b == 0).unwrap_or(MAP_NAME_SIZE);
+        let map = String::from_utf8_lossy(&map[..len]).into_owned();
+        let mut players = [reader.unarchive()?; MAXPLAYERS];
+        for inventory in &mut players[1..] {
+            *inventory = reader.unarchive()?;
+        }
+        let count = reader.read_u32()? as usize;
+        let mut things = Vec::new();
+        for _ in 0..count {
+            let thing = match reader.read_bool()? {
+                true => Some(Thing {
+                    x: reader.read_i16()?,
+                    y: reader.read_i16()?,
+                    angle: reader.read_i16()?,
+                    kind: reader.read_i16()? as u16,
+                    flags: reader.read_i16()? as u16,
+                }),
+                false => None,
+            };
+            things.push(thing);
+        }
+        reader.pad()?;
+        let mut frags = [[0; MAXPLAYERS]; MAXPLAYERS];
+        for frags in frags.iter_mut().flatten() {
+            *frags = reader.read_i32()?;
+        }
+        Ok(Self {
+            map,
+            players,
+            things,
+            frags,
+            stats: reader.unarchive()?,
+        })
+    }
+}
+
+/// What the script hooks see of the game: the session's players and things, with what they
+/// print going to the console.
+#[cfg(feature = "scripting")]
+pub struct ScriptAccess<'a> {
+    pub session: &'a mut Session,
+    pub info: &'a GameInfo,
+    pub console: &'a mut Console,
+    pub gametic: i32,
+}
+
+#[cfg(feature = "scripting")]
+impl Session {
+    /// The events of entering the level: its start, then every thing that spawned on it.
+    pub fn level_events(&self) -> Vec<ScriptEvent> {
+        let start = ScriptEvent::LevelStart {
+            map: self.map.clone(),
+        };
+        let spawned = self.things.iter().enumerate().filter_map(|(id, thing)| {
+            thing.map(|thing| ScriptEvent::ThingSpawned {
+                thing: id as ThingId,
+                kind: thing.kind,
+                x: Fixed::from(thing.x) << FRACBITS,
+                y: Fixed::from(thing.y) << FRACBITS,
+            })
+        });
+        core::iter::once(start).chain(spawned).collect()
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl GameAccess for ScriptAccess<'_> {
+    fn print(&mut self, message: &str) {
+        self.console.print(message);
+    }
+
+    fn map(&self) -> String {
+        self.session.map.clone()
+    }
+
+    fn gametic(&self) -> i32 {
+        self.gametic
+    }
+
+    fn player(&mut self, player: usize) -> Option<&mut Inventory> {
+        self.session.players.get_mut(player)
+    }
+
+    fn spawn_thing(&mut self, kind: u16, x: Fixed, y: Fixed) -> Option<ThingId> {
+        let known = self
+            .info
+            .mobjs
+            .iter()
+            .any(|mobj| mobj.doomednum == i32::from(kind));
+        if !known {
+            return None;
+        }
+        self.session.things.push(Some(Thing {
+            x: (x >> FRACBITS) as i16,
+            y: (y >> FRACBITS) as i16,
+            angle: 0,
+            kind,
+            flags: 0,
+        }));
+        Some((self.session.things.len() - 1) as ThingId)
+    }
+
+    fn remove_thing(&mut self, thing: ThingId) -> bool {
+        let slot = self.session.things.get_mut(thing as usize);
+        slot.and_then(Option::take).is_some()
+    }
+}
+
+/// What the cheat codes reach of the game. `warp` and `music` say what the engine has to
+/// do once the cheat is done with: start a new game on that map, play the jukebox's track.
+pub struct CheatAccess<'a> {
+    pub session: &'a mut Session,
+    pub info: &'a GameInfo,
+    pub jukebox: &'a mut Jukebox,
+    pub mode: GameMode,
+    /// Where the console player is, for `IDMYPOS`.
+    pub view: ViewPoint,
+    pub warp: Option<String>,
+    pub music: bool,
+}
+
+impl CheatAccess<'_> {
+    fn player(&mut self) -> &mut Inventory {
+        &mut self.session.players[0]
+    }
+}
+
+impl CheatTarget for CheatAccess<'_> {
+    fn toggle_god(&mut self) -> bool {
+        self.session.cheats ^= CF_GODMODE;
+        let on = self.session.cheats & CF_GODMODE != 0;
+        if on {
+            self.session.players[0].health = self.info.misc.god_mode_health;
+        }
+        on
+    }
+
+    fn give_weapons_and_ammo(&mut self, keys: bool) {
+        let misc = self.info.misc;
+        let player = &mut self.session.players[0];
+        (player.armor_points, player.armor_type) = match keys {
+            true => (misc.idkfa_armor, misc.idkfa_armor_class),
+            false => (misc.idfa_armor, misc.idfa_armor_class),
+        };
+        player.weapons = [true; NUMWEAPONS];
+        player.ammo = player.max_ammo;
+        if keys {
+            player.cards = [true; NUMCARDS];
+        }
+    }
+
+    fn toggle_noclip(&mut self) -> bool {
+        self.session.cheats ^= CF_NOCLIP;
+        self.session.cheats & CF_NOCLIP != 0
+    }
+
+    /// Gives `power`, or takes it away: berserk at once, the others on the next tic.
+    fn toggle_power(&mut self, power: Power) {
+        let player = self.player();
+        match player.powers[power as usize] {
+            0 => player.give_power(power),
+            _ if power == Power::Strength => player.powers[power as usize] = 0,
+            _ => player.powers[power as usize] = 1,
+        }
+    }
+
+    fn give_chainsaw(&mut self) {
+        let player = self.player();
+        player.weapons[WP_CHAINSAW] = true;
+        player.powers[Power::Invulnerability as usize] = 1;
+    }
+
+    fn change_level(&mut self, episode: u8, map: u8) {
+        let warp = Warp::EpisodeMap(episode, map);
+        self.warp = Some(start_map(self.mode, Some(warp), None));
+    }
+
+    fn change_music(&mut self, lump: &str) {
+        self.music = self.jukebox.select(lump);
+    }
+
+    fn position(&self) -> String {
+        let ViewPoint { x, y, angle, .. } = self.view;
+        format!("ang=0x{angle:x};x,y=(0x{x:x},0x{y:x})")
+    }
+}
+
+/// The map a new game starts on: `-warp`'s, else the first of `-episode`'s, else the first
+/// map of the game.
+pub fn start_map(mode: GameMode, warp: Option<Warp>, episode: Option<u8>) -> String {
+    match (mode, warp) {
+        (GameMode::Commercial, Some(Warp::Map(map) | Warp::EpisodeMap(_, map))) => {
+            format!("MAP{map:02}")
+        }
+        (GameMode::Commercial, None) => "MAP01".to_string(),
+        (_, Some(Warp::EpisodeMap(episode, map))) => format!("E{episode}M{map}"),
+        // A lone number is the map of the first episode, as in vanilla.
+        (_, Some(Warp::Map(map))) => format!("E1M{map}"),
+        (_, None) => format!("E{}M1", episode.unwrap_or(1).clamp(1, mode.episodes())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::deathmatch::ExitRule;
+    use crate::game::fog::{ITEM_RESPAWN_TICS, MT_IFOG};
+    use crate::game::info::{MobjInfo, MF_COUNTKILL};
+    use crate::game::limits::{OverflowMode, MAXLOSTSOULS};
+    use crate::game::player::NUMCARDS;
+    use crate::game::weapons::WP_SHOTGUN;
+    use crate::util::angle::{sin_cos, Angle};
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::file::{WadFile, Wads};
+    use std::path::Path;
+
+    /// `MAP01` with a player start, a zombieman and a shotgun, on every skill, in a sector
+    /// floored and ceilinged with `FLAT`.
+    fn wads() -> Wads {
+        let things: Vec<u8> = [1u16, 3004, 2001]
+            .iter()
+            .flat_map(|&kind| [0, 0, 0, kind, 7])
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let mut sector = vec![0; 26];
+        sector[4..8].copy_from_slice(b"FLAT");
+        sector[12..16].copy_from_slice(b"FLAT");
+        let bytes = build_wad(
+            b"PWAD",
+            &[
+                ("MAP01", &[]),
+                ("THINGS", &things),
+                ("LINEDEFS", &[]),
+                ("SIDEDEFS", &[]),
+                ("VERTEXES", &[]),
+                ("NODES", &[]),
+                ("SECTORS", &sector),
+                ("FLAT", &[0; 4096]),
+            ],
+        );
+        let mut wads = Wads::new();
+        wads.add(WadFile::parse(Path::new("test.wad"), &bytes).unwrap());
+        wads
+    }
+
+    fn info() -> GameInfo {
+        let mobj = |doomednum, flags| MobjInfo {
+            doomednum,
+            flags,
+            ..MobjInfo::default()
+        };
+        GameInfo {
+            mobjs: vec![mobj(1, 0), mobj(3004, MF_COUNTKILL), mobj(2001, 0)],
+            ..GameInfo::default()
+        }
+    }
+
+    fn enter(session: &mut Session, wads: &Wads, info: &GameInfo) -> bool {
+        let (cache, precache) = (LevelCache::default(), PrecacheOptions::default());
+        let mut report = |_, _| {};
+        let mut progress = LoadProgress::new(&mut report);
+        session.enter_level(
+            wads,
+            info,
+            &UMapInfo::default(),
+            &cache,
+            &precache,
+            &mut progress,
+        )
+    }
+
+    /// The sines as the renderer works them out, for where fog goes.
+    struct SineTrig;
+
+    impl Trig for SineTrig {
+        fn fine(&self, angle: Angle) -> (Fixed, Fixed) {
+            let (sin, cos) = sin_cos(angle);
+            (cos, sin)
+        }
+
+        fn fine_tangent(&self, _index: usize) -> Fixed {
+            0
+        }
+
+        fn point_to_angle(&self, _dx: Fixed, _dy: Fixed) -> Angle {
+            0
+        }
+
+        fn tan_to_angle(&self, _index: usize) -> Angle {
+            0
+        }
+    }
+
+    #[test]
+    fn players_items_and_monsters_coming_back_leave_fog() {
+        let (wads, info) = (wads(), info());
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        assert!(enter(&mut session, &wads, &info));
+        assert_eq!(session.sound_origins, [(0, 0)], "one sector, no lines");
+        let floor = |_, _| to_fixed(-8);
+
+        assert!(session.spawn_player(0, to_fixed(-8), true, &SineTrig));
+        assert!(session.spawn_player(0, to_fixed(-8), false, &SineTrig));
+        let [first, respawn] = [session.fog[0], session.fog[1]];
+        assert_eq!(first.sound, None, "the first frame is quiet");
+        assert_eq!((respawn.x, respawn.z), (to_fixed(20), to_fixed(-8)));
+        assert_eq!(respawn.sound, Some("telept"));
+
+        session.fog.clear();
+        let shotgun = session.pick_up(2, 100).unwrap();
+        assert_eq!(session.pick_up(2, 100), None);
+        session.respawn_specials(99 + ITEM_RESPAWN_TICS, floor);
+        assert_eq!(session.things[2], None);
+        session.respawn_specials(100 + ITEM_RESPAWN_TICS, floor);
+        assert_eq!(session.things[2], Some(shotgun));
+        assert_eq!(session.fog[0].kind, MT_IFOG);
+
+        session.fog.clear();
+        assert!(session.respawn_monster(1, (to_fixed(64), 0), floor));
+        let spots: Vec<_> = session.fog.iter().map(|fog| (fog.x, fog.z)).collect();
+        assert_eq!(spots, [(to_fixed(64), to_fixed(-8)), (0, to_fixed(-8))]);
+        assert!(!session.respawn_monster(3, (0, 0), floor));
+    }
+
+    #[test]
+    fn entering_a_level_precaches_it_unless_told_not_to() {
+        let (wads, info) = (wads(), info());
+        for enabled in [true, false] {
+            let cache = LevelCache::default();
+            cache.sounds.get("DSOLD", |_| Some(Vec::new()));
+            let precache = PrecacheOptions {
+                enabled,
+                threads: 1,
+            };
+            let mut report = |_, _| {};
+            let mut progress = LoadProgress::new(&mut report);
+            let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+            let map_info = UMapInfo::default();
+            assert!(session.enter_level(&wads, &info, &map_info, &cache, &precache, &mut progress));
+            assert!(
+                !cache.sounds.contains("DSOLD"),
+                "the last level's are dropped"
+            );
+            assert_eq!(cache.flats.contains("FLAT"), enabled);
+        }
+    }
+
+    #[test]
+    fn entering_a_level_spawns_what_the_filter_lets_through() {
+        let (wads, info) = (wads(), info());
+        for (nomonsters, spawned) in [(false, vec![1, 3004, 2001]), (true, vec![1, 2001])] {
+            let setup = GameSetup {
+                spawn_filter: SpawnFilter {
+                    nomonsters,
+                    ..SpawnFilter::default()
+                },
+                ..GameSetup::default()
+            };
+            let mut session = Session::new(setup, "MAP01", &info);
+            assert!(enter(&mut session, &wads, &info));
+            let kinds: Vec<u16> = session
+                .things
+                .iter()
+                .flatten()
+                .map(|thing| thing.kind)
+                .collect();
+            assert_eq!(kinds, spawned);
+        }
+
+        let mut session = Session::new(GameSetup::default(), "MAP02", &info);
+        assert!(!enter(&mut session, &wads, &info));
+        assert!(session.things.is_empty());
+    }
+
+    #[test]
+    fn the_level_totals_are_counted_from_what_spawned() {
+        let (wads, info) = (wads(), info());
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        assert!(enter(&mut session, &wads, &info));
+        let stats = session.stats;
+        assert_eq!(
+            (stats.total_kills, stats.total_items, stats.total_secrets),
+            (1, 0, 0)
+        );
+        session.stats.level_time = 350;
+        session.exit_level("MAP01", &info);
+        assert!(enter(&mut session, &wads, &info));
+        assert_eq!(
+            (session.stats.level_time, session.stats.run_time()),
+            (0, 350)
+        );
+    }
+
+    #[test]
+    fn the_state_saves_and_restores_whole() {
+        let (wads, info) = (wads(), info());
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        assert!(enter(&mut session, &wads, &info));
+        session.things[1] = None;
+        session.players[2].health = 42;
+        session.frags[0][3] = 5;
+        session.stats.level_time = 700;
+        let state = session.state();
+        assert_eq!(
+            SessionState::from_bytes(&state.to_bytes()),
+            Ok(state.clone())
+        );
+
+        let mut restored = Session::new(GameSetup::default(), "MAP01", &info);
+        assert!(enter(&mut restored, &wads, &info));
+        restored.restore(state);
+        assert_eq!(restored.state(), session.state());
+        assert_eq!(restored.things.iter().flatten().count(), 2);
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn hooks_see_the_level_and_change_its_things() {
+        use crate::engine::console::Commands;
+        use crate::game::script::{Script, ScriptError};
+
+        let info = info();
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        assert!(enter(&mut session, &wads(), &info));
+        let events = session.level_events();
+        assert_eq!(
+            events[0],
+            ScriptEvent::LevelStart {
+                map: "MAP01".to_string()
+            }
+        );
+        assert_eq!(events.len(), 4, "the start and every map thing");
+
+        let script = Script::parse(
+            "test.ds",
+            "on level_start\n\
+             print \"entered $map\"\n\
+             spawn 2001 64 -32\n\
+             spawn 9999 0 0\n\
+             end\n\
+             on thing_spawned if $type == 3004\n\
+             remove $thing\n\
+             end\n",
+        )
+        .unwrap();
+        let mut console = Console::new(Commands::shared(), 4);
+        let mut game = ScriptAccess {
+            session: &mut session,
+            info: &info,
+            console: &mut console,
+            gametic: 0,
+        };
+        let errors: Vec<ScriptError> = events
+            .iter()
+            .flat_map(|event| script.fire(event, &mut game))
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "no thing type 9999");
+        assert_eq!(console.visible_lines(1), ["entered MAP01"]);
+        let kinds: Vec<Option<u16>> = session
+            .things
+            .iter()
+            .map(|thing| thing.map(|thing| thing.kind))
+            .collect();
+        assert_eq!(kinds, [Some(1), None, Some(2001), Some(2001)]);
+        assert_eq!(
+            (session.things[3].unwrap().x, session.things[3].unwrap().y),
+            (64, -32)
+        );
+    }
+
+    #[test]
+    fn pistol_start_takes_everything_at_the_exit() {
+        let info = GameInfo::default();
+        for enabled in [false, true] {
+            let setup = GameSetup {
+                pistol_start: PistolStart { enabled },
+                ..GameSetup::default()
+            };
+            let mut session = Session::new(setup, "e1m1", &info);
+            assert_eq!(session.map, "E1M1");
+            session.players[1].weapons[WP_SHOTGUN] = true;
+            session.players[1].cards = [true; NUMCARDS];
+
+            session.exit_level("E1M2", &info);
+            assert_eq!(session.map, "E1M2");
+            assert_eq!(session.players[1].cards, [false; NUMCARDS]);
+            assert_eq!(session.players[1].weapons[WP_SHOTGUN], !enabled);
+            if enabled {
+                assert_eq!(session.players[1], Inventory::initial(&info));
+            }
+        }
+    }
+
+    #[test]
+    fn umapinfo_decides_where_exits_lead() {
+        let map_info = UMapInfo::parse(
+            r#"
+            MAP MAP01 { next = "MAP05" intertext = "On to five" intertextsecret = clear }
+            MAP MAP05 { endgame = true }
+            "#,
+        )
+        .unwrap();
+        let info = GameInfo::default();
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        let completion = session.complete_level(false, &map_info, &info);
+        assert_eq!(completion.next, NextMap::Map("MAP05".to_string()));
+        assert_eq!(completion.text, Some(vec!["On to five".to_string()]));
+        assert_eq!(session.map, "MAP05");
+
+        let completion = session.complete_level(true, &map_info, &info);
+        assert_eq!((completion.next, completion.text), (NextMap::EndGame, None));
+        assert_eq!(session.map, "MAP05", "the game is over");
+
+        let mut session = Session::new(GameSetup::default(), "MAP01", &info);
+        let completion = session.complete_level(true, &map_info, &info);
+        assert_eq!(completion.text, None, "cleared for the secret exit");
+        let mut session = Session::new(GameSetup::default(), "MAP02", &info);
+        let completion = session.complete_level(false, &map_info, &info);
+        assert_eq!(completion.next, NextMap::Map("MAP03".to_string()));
+    }
+
+    #[test]
+    fn limits_follow_the_level_and_the_overrides() {
+        let info = GameInfo::default();
+        let overrides = LimitOverrides {
+            lost_souls: Some(false),
+            ..LimitOverrides::default()
+        };
+        for (complevel, demo_playback, lost_souls) in [
+            (CompLevel::Vanilla, false, None),
+            (CompLevel::Vanilla, true, Some(MAXLOSTSOULS)),
+            (CompLevel::Mbf, true, None),
+        ] {
+            let setup = GameSetup {
+                complevel,
+                demo_playback,
+                limit_overrides: overrides,
+                ..GameSetup::default()
+            };
+            let session = Session::new(setup, "MAP01", &info);
+            assert_eq!(session.limits.lost_souls, lost_souls);
+            let emulated = complevel == CompLevel::Vanilla;
+            assert_eq!(
+                session.overflows.intercepts == OverflowMode::Emulate,
+                emulated
+            );
+        }
+    }
+
+    #[test]
+    fn the_setup_goes_to_the_peers_whole() {
+        let setup = GameSetup {
+            skill: Skill::Nightmare,
+            netgame: true,
+            deathmatch: true,
+            spawn_filter: SpawnFilter {
+                nomonsters: true,
+                ..SpawnFilter::default()
+            },
+            pistol_start: PistolStart { enabled: true },
+            deathmatch_rules: DeathmatchRules {
+                frag_limit: 20,
+                time_limit: 10,
+                spawn_protection: 70,
+                exit: ExitRule::Kill,
+            },
+            ..GameSetup::default()
+        };
+        let block = setup.encode();
+        assert_eq!(GameSetup::decode(&block), Some(setup));
+        assert_eq!(GameSetup::decode(&block[..SETUP_SIZE - 1]), None);
+        let mut bad_skill = block;
+        bad_skill[0] = 5;
+        assert_eq!(GameSetup::decode(&bad_skill), None);
+    }
+
+    #[test]
+    fn games_start_where_the_arguments_say() {
+        assert_eq!(start_map(GameMode::Commercial, None, None), "MAP01");
+        assert_eq!(
+            start_map(GameMode::Commercial, Some(Warp::Map(7)), None),
+            "MAP07"
+        );
+        assert_eq!(
+            start_map(GameMode::Retail, Some(Warp::EpisodeMap(4, 2)), None),
+            "E4M2"
+        );
+        assert_eq!(
+            start_map(GameMode::Shareware, Some(Warp::Map(3)), None),
+            "E1M3"
+        );
+        assert_eq!(start_map(GameMode::Registered, None, Some(3)), "E3M1");
+        assert_eq!(start_map(GameMode::Shareware, None, Some(4)), "E1M1");
+    }
+}
@@ -0,0 +1,107 @@
+//! Game speed: running the tic clock slower or faster than 35 Hz, for practising a hard
+//! section at half speed or skimming through a familiar one.
+//!
+//! Only the clock changes, never the length of a tic, so the simulation itself runs exactly
+//! as it would at full speed. It still changes what the player can do with their input,
+//! which makes it a practice tool: strict mode and demo recording keep the clock at 100%.
+
+use super::config::{Config, Configurable, Section};
+use crate::game::strict::{Feature, StrictMode};
+use crate::game::TICRATE;
+use std::time::Duration;
+
+pub const MIN_SPEED: u32 = 50;
+pub const MAX_SPEED: u32 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameSpeed {
+    /// Percent of the normal speed, from `MIN_SPEED` to `MAX_SPEED`.
+    pub percent: u32,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self { percent: 100 }
+    }
+}
+
+impl GameSpeed {
+    pub fn new(percent: u32) -> Self {
+        Self {
+            percent: percent.clamp(MIN_SPEED, MAX_SPEED),
+        }
+    }
+
+    pub fn is_normal(&self) -> bool {
+        self.percent == 100
+    }
+
+    /// The speed to play at: normal whenever strict mode blocks it or a demo is recording.
+    pub fn held(self, strict: &StrictMode, recording: bool) -> Self {
+        if recording || !strict.allows(Feature::GameSpeed) {
+            Self::default()
+        } else {
+            self
+        }
+    }
+
+    /// How many tics are due `elapsed` after the clock started, `I_GetTime` scaled.
+    pub fn tics(&self, elapsed: Duration) -> u64 {
+        let scaled = elapsed.as_micros() * TICRATE as u128 * u128::from(self.percent);
+        (scaled / 100_000_000) as u64
+    }
+
+    /// The clock time `tics` take, the shortest `elapsed` for which `tics` reaches them.
+    pub fn duration(&self, tics: u64) -> Duration {
+        let speed = TICRATE as u128 * u128::from(self.percent);
+        let micros = (u128::from(tics) * 100_000_000).div_ceil(speed);
+        Duration::from_micros(micros as u64)
+    }
+}
+
+impl Configurable for GameSpeed {
+    fn register(config: &mut Config) {
+        config.register_int("game_speed", 100, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self::new(config.get_int("game_speed").max(0) as u32)
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("game_speed", self.percent as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_clock_scales_and_recording_holds_it() {
+        let second = Duration::from_secs(1);
+        assert_eq!(GameSpeed::default().tics(second), 35);
+        assert_eq!(GameSpeed::new(50).tics(second * 2), 35);
+        assert_eq!(GameSpeed::new(200).tics(Duration::from_millis(500)), 35);
+        assert_eq!(GameSpeed::new(1000).percent, MAX_SPEED);
+        assert_eq!(GameSpeed::new(1).percent, MIN_SPEED);
+        for speed in [GameSpeed::default(), GameSpeed::new(73)] {
+            for tics in [1, 35, 1000] {
+                let duration = speed.duration(tics);
+                assert_eq!(speed.tics(duration), tics);
+                assert_eq!(speed.tics(duration - Duration::from_micros(1)), tics - 1);
+            }
+        }
+
+        let half = GameSpeed::new(50);
+        let strict = StrictMode { enabled: true };
+        assert_eq!(half.held(&StrictMode::default(), false), half);
+        assert!(half.held(&StrictMode::default(), true).is_normal());
+        assert!(half.held(&strict, false).is_normal());
+
+        let mut config = Config::new();
+        GameSpeed::register(&mut config);
+        config.set_int("game_speed", -5);
+        assert_eq!(GameSpeed::from_config(&config).percent, MIN_SPEED);
+    }
+}
@@ -0,0 +1,159 @@
+//! What the window shows about the game: its title and its icon.
+//!
+//! The title names the game the IWAD is, the map being played and, while it lasts, the
+//! demo being played back or recorded or the size of the netgame, so a window in the task bar
+//! says what it is doing: `Doom II - MAP07 - demo DEMO2 - doom-rs`. The game loop updates
+//! `WindowTitle` as those change and passes the text to the platform only when it differs
+//! from what the window already shows.
+//!
+//! The icon is drawn in `ICON_ART` below, one character per pixel, so it needs no image file
+//! next to the executable and no decoder to read one.
+
+pub const PROGRAM: &str = "doom-rs";
+
+/// What the session is doing, for the title.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Activity {
+    /// Playing, or at the title screen.
+    #[default]
+    Playing,
+    /// Playing back the named demo.
+    Demo(String),
+    /// Recording the named demo.
+    Recording(String),
+    Netgame {
+        players: usize,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WindowTitle {
+    /// The IWAD's name, such as `Doom II`.
+    pub game: String,
+    /// The map being played, or `None` outside a level.
+    pub map: Option<String>,
+    pub activity: Activity,
+    shown: Option<String>,
+}
+
+impl WindowTitle {
+    pub fn new(game: &str) -> Self {
+        Self {
+            game: game.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut parts = vec![self.game.clone()];
+        parts.extend(self.map.clone());
+        match &self.activity {
+            Activity::Playing => {}
+            Activity::Demo(demo) => parts.push(format!("demo {demo}")),
+            Activity::Recording(demo) => parts.push(format!("recording {demo}")),
+            Activity::Netgame { players } => parts.push(format!("netgame, {players} players")),
+        }
+        parts.push(PROGRAM.to_string());
+        parts.join(" - ")
+    }
+
+    /// The title, if the window doesn't show it yet. The caller passes it on.
+    pub fn changed(&mut self) -> Option<String> {
+        let text = self.text();
+        if self.shown.as_ref() == Some(&text) {
+            return None;
+        }
+        self.shown = Some(text.clone());
+        Some(text)
+    }
+}
+
+/// A window icon, as `0xAARRGGBB` pixels row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Icon {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+/// A red skull on a transparent background.
+const ICON_ART: [&str; 16] = [
+    "................",
+    "....########....",
+    "..##oooooooo##..",
+    ".#oooooooooooo#.",
+    ".#oooooooooooo#.",
+    "#oo####oo####oo#",
+    "#oo#..#oo#..#oo#",
+    "#oo####oo####oo#",
+    "#oooooooooooooo#",
+    ".#oooooo#ooooo#.",
+    "..#ooooo#oooo#..",
+    "...#oo#o#o#o#...",
+    "...#oo#o#o#o#...",
+    "....#########...",
+    "................",
+    "................",
+];
+
+/// The program's icon, at 32x32.
+pub fn program_icon() -> Icon {
+    let scale = 2;
+    let size = ICON_ART.len() * scale;
+    let pixels = (0..size * size)
+        .map(|i| {
+            let (x, y) = (i % size / scale, i / size / scale);
+            match ICON_ART[y].as_bytes()[x] {
+                b'#' => 0xff20_0000,
+                b'o' => 0xffc8_2010,
+                _ => 0,
+            }
+        })
+        .collect();
+    Icon {
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_title_follows_the_session() {
+        let mut title = WindowTitle::new("Doom II");
+        assert_eq!(title.changed().as_deref(), Some("Doom II - doom-rs"));
+        assert_eq!(title.changed(), None);
+
+        title.map = Some("MAP07".to_string());
+        title.activity = Activity::Demo("DEMO2".to_string());
+        assert_eq!(
+            title.changed().as_deref(),
+            Some("Doom II - MAP07 - demo DEMO2 - doom-rs")
+        );
+        title.activity = Activity::Netgame { players: 3 };
+        assert_eq!(
+            title.text(),
+            "Doom II - MAP07 - netgame, 3 players - doom-rs"
+        );
+        title.activity = Activity::Recording("speedrun".to_string());
+        assert_eq!(
+            title.text(),
+            "Doom II - MAP07 - recording speedrun - doom-rs"
+        );
+    }
+
+    #[test]
+    fn the_icon_is_drawn_from_its_art() {
+        assert!(ICON_ART.iter().all(|row| row.len() == ICON_ART.len()));
+        let icon = program_icon();
+        assert_eq!((icon.width, icon.height, icon.pixels.len()), (32, 32, 1024));
+        assert_eq!(icon.pixels[0], 0);
+        // Each art pixel covers two by two icon pixels.
+        assert_eq!(icon.pixels[2 * 32 + 8], 0xff20_0000);
+        assert_eq!(icon.pixels[3 * 32 + 9], 0xff20_0000);
+        assert_eq!(icon.pixels[5 * 32 + 8], 0xffc8_2010);
+    }
+}
@@ -0,0 +1,282 @@
+//! C entry points, for frontends that aren't written in Rust.
+//!
+//! A C, C++ or homebrew shell builds the crate as a library with
+//! `cargo rustc -p doom-core --release --features ffi --crate-type staticlib` (or `cdylib`)
+//! and includes `include/doom.h`. The calls mirror `Engine`: `doom_init` takes the command
+//! line, `doom_key_event` feeds keys, `doom_tick` runs a tic from them and
+//! `doom_get_framebuffer` draws a frame of palette indices to show with `doom_get_palette`.
+//!
+//! The header is generated from the table below; the `header_is_up_to_date` test fails when
+//! the two drift apart, and rewrites the file when run with `DOOM_BLESS=1`. No panic is
+//! allowed to unwind into C: a panicking call returns null or does nothing, after the crash
+//! hook has written its report.
+
+use crate::engine::args::Args;
+use crate::engine::config::Config;
+use crate::engine::input::{self, Event, Key};
+use crate::engine::Engine;
+use crate::renderer::framebuffer::{PALETTE_BYTES, SCREENHEIGHT, SCREENWIDTH};
+use crate::wad::file::{WadFile, Wads};
+use crate::wad::iwad;
+use crate::{error, info};
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{self, AssertUnwindSafe};
+
+/// The opaque handle C holds.
+pub struct DoomEngine {
+    engine: Engine,
+    frame: Vec<u8>,
+    palette: [u8; PALETTE_BYTES],
+}
+
+/// Keys a C frontend has to translate its own key codes to. Printable keys are their lower
+/// case ASCII value.
+const KEYS: [(&str, Key); 30] = [
+    ("RIGHTARROW", input::KEY_RIGHTARROW),
+    ("LEFTARROW", input::KEY_LEFTARROW),
+    ("UPARROW", input::KEY_UPARROW),
+    ("DOWNARROW", input::KEY_DOWNARROW),
+    ("ESCAPE", input::KEY_ESCAPE),
+    ("ENTER", input::KEY_ENTER),
+    ("TAB", input::KEY_TAB),
+    ("BACKSPACE", input::KEY_BACKSPACE),
+    ("PAUSE", input::KEY_PAUSE),
+    ("F1", input::KEY_F1),
+    ("F2", input::KEY_F2),
+    ("F3", input::KEY_F3),
+    ("F4", input::KEY_F4),
+    ("F5", input::KEY_F5),
+    ("F6", input::KEY_F6),
+    ("F7", input::KEY_F7),
+    ("F8", input::KEY_F8),
+    ("F9", input::KEY_F9),
+    ("F10", input::KEY_F10),
+    ("F11", input::KEY_F11),
+    ("F12", input::KEY_F12),
+    ("RSHIFT", input::KEY_RSHIFT),
+    ("RCTRL", input::KEY_RCTRL),
+    ("RALT", input::KEY_RALT),
+    ("HOME", input::KEY_HOME),
+    ("END", input::KEY_END),
+    ("PGUP", input::KEY_PGUP),
+    ("PGDN", input::KEY_PGDN),
+    ("INS", input::KEY_INS),
+    ("DEL", input::KEY_DEL),
+];
+
+/// Every exported function, as declared in the header.
+const PROTOTYPES: [(&str, &str); 6] = [
+    (
+        "Loads the IWAD and PWADs named on a command line in the usual syntax (argv[0] is\n * skipped). Returns NULL if they can't be loaded.",
+        "doom_engine *doom_init(int argc, const char *const *argv);",
+    ),
+    (
+        "Runs one game tic with the keys currently held.",
+        "void doom_tick(doom_engine *engine);",
+    ),
+    (
+        "Draws the current frame and returns its palette indices, row by row. The pointer\n * stays valid until the next call with this engine.",
+        "const uint8_t *doom_get_framebuffer(doom_engine *engine, int *width, int *height);",
+    ),
+    (
+        "The 256 RGB triples the framebuffer indexes.",
+        "const uint8_t *doom_get_palette(doom_engine *engine);",
+    ),
+    (
+        "A key went down (pressed != 0) or up, as a DOOM_KEY_* or lower case ASCII code.",
+        "void doom_key_event(doom_engine *engine, int key, int pressed);",
+    ),
+    (
+        "Frees the engine. NULL is ignored.",
+        "void doom_shutdown(doom_engine *engine);",
+    ),
+];
+
+/// The text of `include/doom.h`.
+pub fn header() -> String {
+    let mut out = String::from(
+        "/* Generated from doom-core/src/ffi.rs; edit that instead. */\n\
+         #ifndef DOOM_H\n#define DOOM_H\n\n#include <stdint.h>\n\n\
+         #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n\
+         typedef struct doom_engine doom_engine;\n\n",
+    );
+    for (name, key) in KEYS {
+        out.push_str(&format!("#define DOOM_KEY_{name} {key}\n"));
+    }
+    for (doc, prototype) in PROTOTYPES {
+        out.push_str(&format!("\n/* {doc} */\n{prototype}\n"));
+    }
+    out.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+    out
+}
+
+fn guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+/// # Safety
+/// `argv` must point to `argc` NUL terminated strings, or be null with `argc` 0.
+#[no_mangle]
+pub unsafe extern "C" fn doom_init(argc: c_int, argv: *const *const c_char) -> *mut DoomEngine {
+    let args: Vec<String> = (1..argc.max(0) as usize)
+        .filter(|_| !argv.is_null())
+        .map(|i| CStr::from_ptr(*argv.add(i)).to_string_lossy().into_owned())
+        .collect();
+    guard(std::ptr::null_mut(), || match init(args) {
+        Ok(engine) => Box::into_raw(Box::new(engine)),
+        Err(message) => {
+            error!("ffi", "{message}");
+            std::ptr::null_mut()
+        }
+    })
+}
+
+fn init(args: Vec<String>) -> Result<DoomEngine, String> {
+    let args = Args::parse(args).map_err(|error| error.to_string())?;
+    let mut config = Config::new();
+    if let Some(path) = &args.config {
+        config.load(path).map_err(|error| error.to_string())?;
+    }
+    let dirs = iwad::search_dirs(&|name| std::env::var(name).ok());
+    let iwad = iwad::locate(args.iwad.as_deref(), &dirs, |_| None).map_err(|e| e.to_string())?;
+    info!("ffi", "using {}", iwad.path.display());
+    let mut wads = Wads::new();
+    for path in std::iter::once(&iwad.path).chain(&args.files) {
+        let file = WadFile::open(path).map_err(|error| error.to_string())?;
+        wads.add(file);
+    }
+    Ok(DoomEngine::new(Engine::new(wads, config)))
+}
+
+impl DoomEngine {
+    fn new(engine: Engine) -> Self {
+        let mut palette = [0; PALETTE_BYTES];
+        for (index, rgb) in palette.chunks_exact_mut(3).enumerate() {
+            rgb.copy_from_slice(&engine.palette().rgb(index as u8));
+        }
+        Self {
+            engine,
+            frame: vec![0; SCREENWIDTH * SCREENHEIGHT],
+            palette,
+        }
+    }
+}
+
+/// # Safety
+/// `engine` must come from `doom_init` and not have been shut down.
+#[no_mangle]
+pub unsafe extern "C" fn doom_tick(engine: *mut DoomEngine) {
+    let Some(doom) = engine.as_mut() else { return };
+    guard((), || {
+        let cmd = doom.engine.build_ticcmd();
+        doom.engine.run_tic(&[cmd]);
+    })
+}
+
+/// # Safety
+/// `engine` must come from `doom_init`; `width` and `height` may be null.
+#[no_mangle]
+pub unsafe extern "C" fn doom_get_framebuffer(
+    engine: *mut DoomEngine,
+    width: *mut c_int,
+    height: *mut c_int,
+) -> *const u8 {
+    let Some(doom) = engine.as_mut() else {
+        return std::ptr::null();
+    };
+    if let Some(width) = width.as_mut() {
+        *width = SCREENWIDTH as c_int;
+    }
+    if let Some(height) = height.as_mut() {
+        *height = SCREENHEIGHT as c_int;
+    }
+    guard(std::ptr::null(), || {
+        doom.engine.render_into(&mut doom.frame);
+        doom.frame.as_ptr()
+    })
+}
+
+/// # Safety
+/// `engine` must come from `doom_init`.
+#[no_mangle]
+pub unsafe extern "C" fn doom_get_palette(engine: *mut DoomEngine) -> *const u8 {
+    engine
+        .as_ref()
+        .map_or(std::ptr::null(), |doom| doom.palette.as_ptr())
+}
+
+/// # Safety
+/// `engine` must come from `doom_init`.
+#[no_mangle]
+pub unsafe extern "C" fn doom_key_event(engine: *mut DoomEngine, key: c_int, pressed: c_int) {
+    let Some(doom) = engine.as_mut() else { return };
+    let event = match pressed {
+        0 => Event::KeyUp(key),
+        _ => Event::KeyDown(key),
+    };
+    guard((), || doom.engine.responder(&event))
+}
+
+/// # Safety
+/// `engine` must come from `doom_init`, or be null, and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn doom_shutdown(engine: *mut DoomEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+    use std::ffi::CString;
+    use std::path::Path;
+
+    #[test]
+    fn header_is_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/doom.h");
+        if std::env::var_os("DOOM_BLESS").is_some() {
+            std::fs::write(&path, header()).unwrap();
+        }
+        let committed = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            committed == header(),
+            "include/doom.h is stale; rerun with DOOM_BLESS=1"
+        );
+    }
+
+    #[test]
+    fn a_frontend_drives_the_engine_through_the_c_calls() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-ffi-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let wad = folder.join("doom2.wad");
+        std::fs::write(&wad, build_wad(b"IWAD", &[("PLAYPAL", &[9; 768])])).unwrap();
+
+        let argv: Vec<CString> = ["doom", "-iwad", wad.to_str().unwrap()]
+            .iter()
+            .map(|arg| CString::new(*arg).unwrap())
+            .collect();
+        let pointers: Vec<*const c_char> = argv.iter().map(|arg| arg.as_ptr()).collect();
+        unsafe {
+            assert!(doom_init(2, pointers.as_ptr()).is_null()); // "-iwad" without its value
+            let engine = doom_init(3, pointers.as_ptr());
+            assert!(!engine.is_null());
+
+            doom_key_event(engine, input::KEY_UPARROW, 1);
+            assert_eq!((*engine).engine.build_ticcmd().forwardmove, 0x19);
+            doom_key_event(engine, input::KEY_UPARROW, 0);
+            doom_tick(engine);
+            assert_eq!((*engine).engine.gametic(), 1);
+
+            let (mut width, mut height) = (0, 0);
+            let pixels = doom_get_framebuffer(engine, &mut width, &mut height);
+            assert_eq!((width, height), (320, 200));
+            assert_eq!(*pixels, 0);
+            assert_eq!(*doom_get_palette(engine).add(767), 9);
+            doom_shutdown(engine);
+            doom_shutdown(std::ptr::null_mut());
+        }
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,601 @@
+//! Boom's map extensions: generalized linedef types, the extra sector special bits, friction
+//! and pushers.
+//!
+//! Generalized linedefs pack a whole mover description into the special number: the range
+//! picks the kind of mover and the bits inside it the trigger, speed, target and so on. They
+//! and the sector bits only mean something at Boom compatibility or above, vanilla treats the
+//! same numbers as unknown specials.
+
+use super::compatibility::CompLevel;
+use crate::util::fixed::{approx_distance, Fixed, FRACBITS, FRACUNIT};
+use crate::wad::map::MapData;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Linedef flag: use actions pass through this line to the ones behind it.
+pub const ML_PASSUSE: u16 = 0x200;
+
+/// Line specials that aren't generalized.
+pub const TRANSLUCENT_LINE: u16 = 260;
+pub const FRICTION_LINE: u16 = 223;
+pub const WIND_LINE: u16 = 224;
+pub const CURRENT_LINE: u16 = 225;
+pub const POINT_PUSHER_LINE: u16 = 226;
+
+/// Thing types of the point pushers controlled by `POINT_PUSHER_LINE`.
+pub const PUSH_THING: u16 = 5001;
+pub const PULL_THING: u16 = 5002;
+
+const GEN_FLOOR_BASE: u16 = 0x6000;
+const GEN_CEILING_BASE: u16 = 0x4000;
+const GEN_DOOR_BASE: u16 = 0x3c00;
+const GEN_LOCKED_BASE: u16 = 0x3800;
+const GEN_LIFT_BASE: u16 = 0x3400;
+const GEN_STAIRS_BASE: u16 = 0x3000;
+const GEN_CRUSHER_BASE: u16 = 0x2f80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activation {
+    Walk,
+    Switch,
+    Gun,
+    Push,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trigger {
+    pub activation: Activation,
+    pub repeatable: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoverSpeed {
+    Slow,
+    Normal,
+    Fast,
+    Turbo,
+}
+
+/// Where a generalized floor moves to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloorTarget {
+    HighestNeighborFloor,
+    LowestNeighborFloor,
+    NextNeighborFloor,
+    LowestNeighborCeiling,
+    Ceiling,
+    ShortestLowerTexture,
+    By24,
+    By32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CeilingTarget {
+    HighestNeighborCeiling,
+    LowestNeighborCeiling,
+    NextNeighborCeiling,
+    HighestNeighborFloor,
+    Floor,
+    ShortestUpperTexture,
+    By24,
+    By32,
+}
+
+/// What a floor or ceiling mover copies from its model sector when it finishes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    None,
+    /// Copy the texture and clear the special.
+    TextureZeroSpecial,
+    Texture,
+    TextureAndSpecial,
+}
+
+/// A floor or ceiling mover. `numeric_model` means the model is the neighbour at the
+/// destination height rather than the sector in front of the trigger line; `monsters` can only
+/// be set when nothing is changed, the two share a bit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mover<T> {
+    pub trigger: Trigger,
+    pub speed: MoverSpeed,
+    pub up: bool,
+    pub target: T,
+    pub change: Change,
+    pub numeric_model: bool,
+    pub monsters: bool,
+    pub crush: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DoorKind {
+    OpenWaitClose,
+    Open,
+    CloseWaitOpen,
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DoorKey {
+    Any,
+    RedCard,
+    BlueCard,
+    YellowCard,
+    RedSkull,
+    BlueSkull,
+    YellowSkull,
+    All,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiftTarget {
+    LowestNeighborFloor,
+    NextNeighborFloor,
+    LowestNeighborCeiling,
+    /// Moves between the lowest and highest neighbour floor until stopped.
+    Perpetual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generalized {
+    Floor(Mover<FloorTarget>),
+    Ceiling(Mover<CeilingTarget>),
+    Door {
+        trigger: Trigger,
+        speed: MoverSpeed,
+        kind: DoorKind,
+        monsters: bool,
+        delay: i32, // Tics
+    },
+    LockedDoor {
+        trigger: Trigger,
+        speed: MoverSpeed,
+        kind: DoorKind,
+        key: DoorKey,
+        /// Either key of the right colour opens it.
+        skull_is_card: bool,
+    },
+    Lift {
+        trigger: Trigger,
+        speed: MoverSpeed,
+        monsters: bool,
+        delay: i32, // Tics
+        target: LiftTarget,
+    },
+    Stairs {
+        trigger: Trigger,
+        speed: MoverSpeed,
+        monsters: bool,
+        step: Fixed,
+        up: bool,
+        ignore_texture: bool,
+    },
+    Crusher {
+        trigger: Trigger,
+        speed: MoverSpeed,
+        monsters: bool,
+        silent: bool,
+    },
+}
+
+fn bits(special: u16, shift: u16, count: u16) -> usize {
+    usize::from((special >> shift) & ((1 << count) - 1))
+}
+
+fn bit(special: u16, shift: u16) -> bool {
+    special & (1 << shift) != 0
+}
+
+fn trigger_and_speed(special: u16) -> (Trigger, MoverSpeed) {
+    let activation = [
+        Activation::Walk,
+        Activation::Switch,
+        Activation::Gun,
+        Activation::Push,
+    ][bits(special, 1, 2)];
+    let speed = [
+        MoverSpeed::Slow,
+        MoverSpeed::Normal,
+        MoverSpeed::Fast,
+        MoverSpeed::Turbo,
+    ][bits(special, 3, 2)];
+    let repeatable = bit(special, 0);
+    (
+        Trigger {
+            activation,
+            repeatable,
+        },
+        speed,
+    )
+}
+
+fn mover<T: Copy>(special: u16, targets: [T; 8]) -> Mover<T> {
+    let (trigger, speed) = trigger_and_speed(special);
+    let change = [
+        Change::None,
+        Change::TextureZeroSpecial,
+        Change::Texture,
+        Change::TextureAndSpecial,
+    ][bits(special, 10, 2)];
+    let model_bit = bit(special, 5);
+    Mover {
+        trigger,
+        speed,
+        up: bit(special, 6),
+        target: targets[bits(special, 7, 3)],
+        change,
+        numeric_model: change != Change::None && model_bit,
+        monsters: change == Change::None && model_bit,
+        crush: bit(special, 12),
+    }
+}
+
+/// Decodes a generalized linedef special. Returns `None` for ordinary specials and for
+/// everything below Boom compatibility.
+pub fn decode_linedef(special: u16, level: CompLevel) -> Option<Generalized> {
+    if !level.boom_specials() || special < GEN_CRUSHER_BASE {
+        return None;
+    }
+    let s = special;
+    let (trigger, speed) = trigger_and_speed(s);
+    let seconds = |table: [i32; 4], shift| table[bits(s, shift, 2)] * 35;
+    Some(if s >= GEN_FLOOR_BASE {
+        Generalized::Floor(mover(
+            s - GEN_FLOOR_BASE,
+            [
+                FloorTarget::HighestNeighborFloor,
+                FloorTarget::LowestNeighborFloor,
+                FloorTarget::NextNeighborFloor,
+                FloorTarget::LowestNeighborCeiling,
+                FloorTarget::Ceiling,
+                FloorTarget::ShortestLowerTexture,
+                FloorTarget::By24,
+                FloorTarget::By32,
+            ],
+        ))
+    } else if s >= GEN_CEILING_BASE {
+        Generalized::Ceiling(mover(
+            s - GEN_CEILING_BASE,
+            [
+                CeilingTarget::HighestNeighborCeiling,
+                CeilingTarget::LowestNeighborCeiling,
+                CeilingTarget::NextNeighborCeiling,
+                CeilingTarget::HighestNeighborFloor,
+                CeilingTarget::Floor,
+                CeilingTarget::ShortestUpperTexture,
+                CeilingTarget::By24,
+                CeilingTarget::By32,
+            ],
+        ))
+    } else if s >= GEN_DOOR_BASE {
+        Generalized::Door {
+            trigger,
+            speed,
+            kind: [
+                DoorKind::OpenWaitClose,
+                DoorKind::Open,
+                DoorKind::CloseWaitOpen,
+                DoorKind::Close,
+            ][bits(s, 5, 2)],
+            monsters: bit(s, 7),
+            delay: seconds([1, 4, 9, 30], 8),
+        }
+    } else if s >= GEN_LOCKED_BASE {
+        Generalized::LockedDoor {
+            trigger,
+            speed,
+            kind: [DoorKind::OpenWaitClose, DoorKind::Open][bits(s, 5, 1)],
+            key: [
+                DoorKey::Any,
+                DoorKey::RedCard,
+                DoorKey::BlueCard,
+                DoorKey::YellowCard,
+                DoorKey::RedSkull,
+                DoorKey::BlueSkull,
+                DoorKey::YellowSkull,
+                DoorKey::All,
+            ][bits(s, 6, 3)],
+            skull_is_card: bit(s, 9),
+        }
+    } else if s >= GEN_LIFT_BASE {
+        Generalized::Lift {
+            trigger,
+            speed,
+            monsters: bit(s, 5),
+            delay: seconds([1, 3, 5, 10], 6),
+            target: [
+                LiftTarget::LowestNeighborFloor,
+                LiftTarget::NextNeighborFloor,
+                LiftTarget::LowestNeighborCeiling,
+                LiftTarget::Perpetual,
+            ][bits(s, 8, 2)],
+        }
+    } else if s >= GEN_STAIRS_BASE {
+        Generalized::Stairs {
+            trigger,
+            speed,
+            monsters: bit(s, 5),
+            step: [4, 8, 16, 24][bits(s, 6, 2)] * FRACUNIT,
+            up: bit(s, 8),
+            ignore_texture: bit(s, 9),
+        }
+    } else {
+        Generalized::Crusher {
+            trigger,
+            speed,
+            monsters: bit(s, 5),
+            silent: bit(s, 6),
+        }
+    })
+}
+
+/// A sector special split into its parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SectorSpecial {
+    /// The vanilla style type (lights, the classic damage floors, secrets).
+    pub kind: u16,
+    /// Generalized damage per 32 tics.
+    pub damage: i32,
+    pub secret: bool,
+    pub friction: bool,
+    pub pusher: bool,
+}
+
+impl SectorSpecial {
+    /// Boom keeps the vanilla types in the low 5 bits and adds flags above them. Vanilla uses
+    /// the whole number as the type.
+    pub fn decode(special: u16, level: CompLevel) -> Self {
+        if !level.boom_specials() {
+            return Self {
+                kind: special,
+                ..Self::default()
+            };
+        }
+        Self {
+            kind: special & 0x1f,
+            damage: [0, 5, 10, 20][bits(special, 5, 2)],
+            secret: bit(special, 7),
+            friction: bit(special, 8),
+            pusher: bit(special, 9),
+        }
+    }
+}
+
+/// Vanilla's friction and how strongly input accelerates the player on it.
+pub const ORIG_FRICTION: Fixed = 0xe800;
+pub const ORIG_FRICTION_FACTOR: i32 = 2048;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Friction {
+    pub friction: Fixed,
+    pub move_factor: i32,
+}
+
+impl Friction {
+    /// The friction set by a `FRICTION_LINE` of the given length: short lines make mud, long
+    /// ones ice. Boom's formula, including its clamping.
+    pub fn from_line(dx: Fixed, dy: Fixed) -> Self {
+        let length = approx_distance(dx, dy) >> FRACBITS;
+        let friction = ((0x1eb8 * length) / 0x80 + 0xd000).clamp(0, FRACUNIT);
+        let move_factor = if friction > ORIG_FRICTION {
+            ((0x10092 - friction) * 0x70) / 0x158
+        } else {
+            ((friction - 0xdb34) * 0xa) / 0x80
+        };
+        Self {
+            friction,
+            move_factor: move_factor.max(32),
+        }
+    }
+}
+
+const PUSH_FACTOR: u32 = 7;
+
+/// Speed along the line's direction for wind and current pushers: the control line's length
+/// in map units, scaled down.
+pub fn pusher_speed(dx: Fixed, dy: Fixed) -> (Fixed, Fixed) {
+    (
+        (dx >> FRACBITS) << (FRACBITS - PUSH_FACTOR),
+        (dy >> FRACBITS) << (FRACBITS - PUSH_FACTOR),
+    )
+}
+
+/// A point pusher or puller's strength: the length of its control line in map units.
+pub fn point_magnitude(dx: Fixed, dy: Fixed) -> i32 {
+    approx_distance(dx, dy) >> FRACBITS
+}
+
+/// How hard a point pusher pushes something `distance` away, falling off linearly to nothing
+/// at twice the magnitude. `None` outside its range.
+pub fn point_push_speed(magnitude: i32, distance: Fixed) -> Option<Fixed> {
+    let speed = (magnitude - ((distance >> FRACBITS) >> 1)) << (FRACBITS - PUSH_FACTOR - 1);
+    (speed > 0).then_some(speed)
+}
+
+/// The table a translucent line's middle texture is drawn with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LineTranMap {
+    /// The one generated from the palette.
+    Main,
+    /// A 64k lump, named where the middle texture would be.
+    Lump(String),
+}
+
+/// Which lines a `TRANSLUCENT_LINE` makes translucent, by line number: itself if it has no
+/// tag, otherwise every line with its tag. `is_tranmap` tells whether a front middle
+/// texture names a translucency lump, which is then used instead of the main table.
+pub fn translucent_lines(
+    map: &MapData,
+    level: CompLevel,
+    is_tranmap: impl Fn(&str) -> bool,
+) -> Vec<Option<LineTranMap>> {
+    let mut tables = alloc::vec![None; map.linedefs.len()];
+    if !level.boom_specials() {
+        return tables;
+    }
+    for (index, line) in map.linedefs.iter().enumerate() {
+        if line.special != TRANSLUCENT_LINE {
+            continue;
+        }
+        let middle = map
+            .sidedefs
+            .get(usize::from(line.front))
+            .map(|side| &side.middle);
+        let table = match middle {
+            Some(name) if is_tranmap(name) => LineTranMap::Lump(name.clone()),
+            _ => LineTranMap::Main,
+        };
+        if line.tag == 0 {
+            tables[index] = Some(table);
+            continue;
+        }
+        for (other, target) in map.linedefs.iter().zip(tables.iter_mut()) {
+            if other.tag == line.tag {
+                *target = Some(table.clone());
+            }
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generalized_specials_need_boom() {
+        assert_eq!(decode_linedef(0x6000, CompLevel::Vanilla), None);
+        assert_eq!(decode_linedef(1, CompLevel::Boom), None);
+    }
+
+    #[test]
+    fn decodes_floors_doors_and_lifts() {
+        // Switch repeatable, fast, up, to the lowest neighbour ceiling, copy texture, crush.
+        let special = GEN_FLOOR_BASE | 0x3 | (2 << 3) | (1 << 6) | (3 << 7) | (2 << 10) | (1 << 12);
+        let Some(Generalized::Floor(floor)) = decode_linedef(special, CompLevel::Boom) else {
+            panic!("not a floor");
+        };
+        assert_eq!(
+            floor.trigger,
+            Trigger {
+                activation: Activation::Switch,
+                repeatable: true
+            }
+        );
+        assert_eq!(floor.speed, MoverSpeed::Fast);
+        assert!(floor.up && floor.crush && !floor.monsters);
+        assert_eq!(floor.target, FloorTarget::LowestNeighborCeiling);
+        assert_eq!(floor.change, Change::Texture);
+
+        // Walk once, open wait close, monsters, 4 second delay.
+        let door = decode_linedef(GEN_DOOR_BASE | (1 << 7) | (1 << 8), CompLevel::Boom);
+        assert_eq!(
+            door,
+            Some(Generalized::Door {
+                trigger: Trigger {
+                    activation: Activation::Walk,
+                    repeatable: false
+                },
+                speed: MoverSpeed::Slow,
+                kind: DoorKind::OpenWaitClose,
+                monsters: true,
+                delay: 4 * 35,
+            })
+        );
+
+        let lift = decode_linedef(GEN_LIFT_BASE | (3 << 8), CompLevel::Boom);
+        assert!(matches!(
+            lift,
+            Some(Generalized::Lift {
+                target: LiftTarget::Perpetual,
+                delay: 35,
+                ..
+            })
+        ));
+        let locked = decode_linedef(GEN_LOCKED_BASE | (5 << 6), CompLevel::Boom);
+        assert!(matches!(
+            locked,
+            Some(Generalized::LockedDoor {
+                key: DoorKey::BlueSkull,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sector_bits() {
+        let special = 9 | (2 << 5) | 0x80 | 0x100;
+        assert_eq!(
+            SectorSpecial::decode(special, CompLevel::Boom),
+            SectorSpecial {
+                kind: 9,
+                damage: 10,
+                secret: true,
+                friction: true,
+                pusher: false
+            }
+        );
+        assert_eq!(
+            SectorSpecial::decode(special, CompLevel::Vanilla).kind,
+            special
+        );
+    }
+
+    #[test]
+    fn friction_and_pushers() {
+        // A line of about 100 units gives Boom's default friction back.
+        let normal = Friction::from_line(100 * FRACUNIT, 0);
+        assert_eq!(normal.friction, 0xe7ff);
+        let ice = Friction::from_line(200 * FRACUNIT, 0);
+        assert!(ice.friction > ORIG_FRICTION && ice.move_factor < ORIG_FRICTION_FACTOR);
+
+        assert_eq!(point_magnitude(64 * FRACUNIT, 0), 64);
+        assert_eq!(point_push_speed(64, 0), Some(64 << 8));
+        assert_eq!(point_push_speed(64, 128 * FRACUNIT), None);
+        assert_eq!(
+            pusher_speed(128 * FRACUNIT, -128 * FRACUNIT),
+            (FRACUNIT, -FRACUNIT)
+        );
+    }
+
+    #[test]
+    fn translucent_lines_follow_tags() {
+        use crate::wad::map::{LineDef, SideDef, NO_SIDEDEF};
+        let side = |middle: &str| SideDef {
+            x_offset: 0,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: middle.into(),
+            sector: 0,
+        };
+        let line = |special, tag, front| LineDef {
+            v1: 0,
+            v2: 0,
+            flags: 0,
+            special,
+            tag,
+            front,
+            back: NO_SIDEDEF,
+        };
+        let map = MapData {
+            linedefs: alloc::vec![
+                line(TRANSLUCENT_LINE, 0, 0),
+                line(TRANSLUCENT_LINE, 7, 1),
+                line(0, 7, 0),
+                line(0, 3, 0),
+            ],
+            sidedefs: alloc::vec![side("MIDGRATE"), side("WATERMAP")],
+            ..MapData::default()
+        };
+        let is_tranmap = |name: &str| name == "WATERMAP";
+        let water = Some(LineTranMap::Lump("WATERMAP".into()));
+        assert_eq!(
+            translucent_lines(&map, CompLevel::Boom, is_tranmap),
+            [Some(LineTranMap::Main), water.clone(), water, None]
+        );
+        assert_eq!(
+            translucent_lines(&map, CompLevel::Vanilla, is_tranmap),
+            [None, None, None, None]
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! Netgame chat: typing messages, the Alt+digit macros, and who gets to read them.
+//!
+//! As in vanilla, a message travels one character per tic in `TicCmd::chatchar`, so it goes
+//! wherever the commands go: to the other players, and into demos of the game. Opening chat
+//! sends the destination first, the player number plus one or `HU_BROADCAST` for everyone,
+//! then every key typed, then Enter. Each receiver builds up every player's line from those
+//! characters and shows it on Enter if it was meant for them, in the sender's color. `t`
+//! talks to everyone; `g`, `i`, `b` and `r` to Green, Indigo, Brown or Red alone once there
+//! are more than two players.
+//!
+//! The line being typed is a `TextEntry`, as savegame names are, fed the keys after
+//! vanilla's `shiftxform`.
+//!
+//! While typing, Alt and a digit sends that `chatmacroN` from the config instead. Escape
+//! sends an Escape too, so the others drop what was typed; vanilla left it in their buffers
+//! to turn up in front of the next message.
+
+use super::demo::MAXPLAYERS;
+use super::ticcmd::TicCmd;
+use super::vote;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::engine::input::{
+    Event, Key, KEY_BACKSPACE, KEY_ENTER, KEY_ESCAPE, KEY_RALT, KEY_RSHIFT,
+};
+use crate::renderer::text::TextColor;
+use crate::renderer::text_entry::{EntryEvent, TextEntry};
+use std::collections::VecDeque;
+
+/// The destination that means everyone. Anything above it is a character of the message.
+pub const HU_BROADCAST: u8 = 5;
+/// `HU_MAXLINELENGTH`.
+pub const MAX_LINE: usize = 80;
+pub const CHAT_KEY: Key = b't' as Key;
+/// Talking to one player, by the first letter of their color.
+pub const DESTINATION_KEYS: [Key; MAXPLAYERS] =
+    [b'g' as Key, b'i' as Key, b'b' as Key, b'r' as Key];
+pub const PLAYER_NAMES: [&str; MAXPLAYERS] = ["Green", "Indigo", "Brown", "Red"];
+
+/// The vanilla macros, `HUSTR_CHATMACRO0` to `9`.
+const DEFAULT_MACROS: [&str; 10] = [
+    "No",
+    "I'm ready to kick butt!",
+    "I'm OK.",
+    "I'm not looking too good!",
+    "Help!",
+    "You suck!",
+    "Next time, scumbag...",
+    "Come here!",
+    "I'll take care of it.",
+    "Yes",
+];
+
+/// The color a player's messages are shown in, their suit's.
+pub fn player_color(player: usize) -> TextColor {
+    match player {
+        0 => TextColor::Green,
+        1 => TextColor::Blue,
+        2 => TextColor::Brown,
+        _ => TextColor::Red,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMacros {
+    pub macros: [String; 10],
+}
+
+impl Default for ChatMacros {
+    fn default() -> Self {
+        Self {
+            macros: DEFAULT_MACROS.map(String::from),
+        }
+    }
+}
+
+impl Configurable for ChatMacros {
+    fn register(config: &mut Config) {
+        for (i, text) in DEFAULT_MACROS.iter().enumerate() {
+            config.register_str(&format!("chatmacro{i}"), text, Section::Vanilla);
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            macros: std::array::from_fn(|i| config.get_str(&format!("chatmacro{i}"))),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        for (i, text) in self.macros.iter().enumerate() {
+            config.set_str(&format!("chatmacro{i}"), text);
+        }
+    }
+}
+
+/// `HUlib_keyInIText`: what a chat character does to a line being typed. Returns whether
+/// it was one the line takes.
+fn key_in(line: &mut String, c: u8) -> bool {
+    match c {
+        b' '..=b'_' => {
+            if line.len() < MAX_LINE {
+                line.push(char::from(c));
+            }
+        }
+        _ if Key::from(c) == KEY_BACKSPACE => {
+            line.pop();
+        }
+        _ => return Key::from(c) == KEY_ENTER,
+    }
+    true
+}
+
+/// What a key did while chatting, for the player's message line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// Not a chat key; the game should have it.
+    Ignored,
+    Eaten,
+    /// Eaten, and a message went: the text to show.
+    Sent(String),
+    /// A destination key naming the player themself: the `Strings` name of the retort.
+    TalkToSelf(&'static str),
+}
+
+/// The console player's side: the keys while typing, and the characters waiting to go out.
+#[derive(Clone, Debug)]
+pub struct ChatInput {
+    /// Where the message being typed is going, while chat is open.
+    destination: Option<u8>,
+    entry: TextEntry,
+    alt_down: bool,
+    shift_down: bool,
+    /// Times the player tried to talk to themself, for ever ruder retorts.
+    nobrainers: u32,
+    queue: VecDeque<u8>,
+}
+
+impl Default for ChatInput {
+    fn default() -> Self {
+        Self {
+            destination: None,
+            entry: TextEntry::new(MAX_LINE),
+            alt_down: false,
+            shift_down: false,
+            nobrainers: 0,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl ChatInput {
+    pub fn is_open(&self) -> bool {
+        self.destination.is_some()
+    }
+
+    /// The message being typed, while chat is open, for the HUD to draw.
+    pub fn entry(&self) -> Option<&TextEntry> {
+        self.destination.map(|_| &self.entry)
+    }
+
+    pub fn line(&self) -> &str {
+        self.entry.text()
+    }
+
+    /// `HU_Responder`. `in_game` and `console_player` say who can be talked to.
+    pub fn responder(
+        &mut self,
+        event: &Event,
+        in_game: &[bool; MAXPLAYERS],
+        console_player: usize,
+        macros: &ChatMacros,
+    ) -> Response {
+        let key = match *event {
+            Event::KeyDown(key) => key,
+            Event::KeyUp(key) => {
+                match key {
+                    KEY_RALT => self.alt_down = false,
+                    KEY_RSHIFT => self.shift_down = false,
+                    _ => {}
+                }
+                return Response::Ignored;
+            }
+            _ => return Response::Ignored,
+        };
+        match key {
+            KEY_RALT => self.alt_down = true,
+            KEY_RSHIFT => self.shift_down = true,
+            _ => {}
+        }
+        let Some(destination) = self.destination else {
+            return self.open(key, in_game, console_player);
+        };
+        if self.alt_down {
+            let Some(text) = (key - b'0' as Key)
+                .try_into()
+                .ok()
+                .and_then(|i: usize| macros.macros.get(i))
+            else {
+                return Response::Ignored;
+            };
+            // The Enter first finishes off anything already typed, as vanilla does.
+            self.queue.push_back(KEY_ENTER as u8);
+            self.queue.push_back(destination);
+            self.queue.extend(text.to_ascii_uppercase().bytes());
+            self.queue.push_back(KEY_ENTER as u8);
+            self.destination = None;
+            return Response::Sent(text.clone());
+        }
+        let Ok(mut c) = u8::try_from(key) else {
+            return Response::Ignored;
+        };
+        if self.shift_down || c.is_ascii_lowercase() {
+            c = shift(c);
+        }
+        let key = Key::from(c);
+        if !matches!(key, KEY_ESCAPE | KEY_ENTER | KEY_BACKSPACE) && !(b' '..=b'_').contains(&c) {
+            return Response::Ignored;
+        }
+        self.queue.push_back(c);
+        let event = self.entry.responder(key);
+        // Enter on an empty line closes chat all the same.
+        if event.is_none() && key != KEY_ENTER {
+            return Response::Eaten;
+        }
+        self.destination = None;
+        self.entry = TextEntry::new(MAX_LINE);
+        match event {
+            Some(EntryEvent::Accepted(text)) => Response::Sent(text),
+            _ => Response::Eaten,
+        }
+    }
+
+    fn open(&mut self, key: Key, in_game: &[bool; MAXPLAYERS], console_player: usize) -> Response {
+        let destination = if key == CHAT_KEY {
+            HU_BROADCAST
+        } else {
+            let players = in_game.iter().filter(|&&playing| playing).count();
+            match DESTINATION_KEYS.iter().position(|&k| k == key) {
+                Some(player) if players > 2 && player == console_player => {
+                    self.nobrainers += 1;
+                    return Response::TalkToSelf(match self.nobrainers {
+                        ..3 => "HUSTR_TALKTOSELF1",
+                        3..6 => "HUSTR_TALKTOSELF2",
+                        6..9 => "HUSTR_TALKTOSELF3",
+                        9..32 => "HUSTR_TALKTOSELF4",
+                        _ => "HUSTR_TALKTOSELF5",
+                    });
+                }
+                Some(player) if players > 2 && in_game[player] => player as u8 + 1,
+                _ => return Response::Ignored,
+            }
+        };
+        self.destination = Some(destination);
+        self.entry = TextEntry::new(MAX_LINE);
+        self.queue.push_back(destination);
+        Response::Eaten
+    }
+
+    /// The character for the next ticcmd, 0 if there is none waiting.
+    pub fn next_char(&mut self) -> u8 {
+        self.queue.pop_front().unwrap_or(0)
+    }
+}
+
+/// Vanilla's `shiftxform`, for the keys a chat line takes.
+fn shift(c: u8) -> u8 {
+    match c {
+        b'a'..=b'z' => c.to_ascii_uppercase(),
+        b'1' => b'!',
+        b'2' => b'@',
+        b'3' => b'#',
+        b'4' => b'$',
+        b'5' => b'%',
+        b'6' => b'^',
+        b'7' => b'&',
+        b'8' => b'*',
+        b'9' => b'(',
+        b'0' => b')',
+        b'\'' => b'"',
+        b',' => b'<',
+        b'.' => b'>',
+        b'/' => b'?',
+        b';' => b':',
+        b'=' => b'+',
+        b'-' => b'_',
+        _ => c,
+    }
+}
+
+/// A message that arrived for the console player.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub from: usize,
+    /// Sent to everyone rather than to the console player alone.
+    pub to_all: bool,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// The line the HUD shows, `HUlib_addMessageToSText`'s prefix and all.
+    pub fn line(&self) -> String {
+        format!("{}: {}", PLAYER_NAMES[self.from], self.text)
+    }
+
+    pub fn color(&self) -> TextColor {
+        player_color(self.from)
+    }
+}
+
+/// Every player's message as it comes in, the half of `HU_Ticker` that reads `chatchar`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChatReceiver {
+    lines: [String; MAXPLAYERS],
+    destinations: [u8; MAXPLAYERS],
+}
+
+impl ChatReceiver {
+    /// Takes a tic's commands, one per player. Returns the messages finished this tic that
+    /// were meant for `console_player`.
+    pub fn ticker(
+        &mut self,
+        cmds: &[TicCmd],
+        in_game: &[bool; MAXPLAYERS],
+        console_player: usize,
+    ) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+        for (player, cmd) in cmds.iter().enumerate().take(MAXPLAYERS) {
+            let c = cmd.chatchar;
+            if !in_game[player] || player == console_player || c == 0 || vote::is_ballot(cmd) {
+                continue;
+            }
+            let line = &mut self.lines[player];
+            if c <= HU_BROADCAST {
+                self.destinations[player] = c;
+            } else if Key::from(c) == KEY_ESCAPE {
+                line.clear();
+            } else if key_in(line, c) && Key::from(c) == KEY_ENTER {
+                let to = self.destinations[player];
+                if !line.is_empty() && (to == console_player as u8 + 1 || to == HU_BROADCAST) {
+                    messages.push(ChatMessage {
+                        from: player,
+                        to_all: to == HU_BROADCAST,
+                        text: line.clone(),
+                    });
+                }
+                line.clear();
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(input: &mut ChatInput, keys: &str) -> Response {
+        let in_game = [true, true, true, false];
+        let mut response = Response::Ignored;
+        for key in keys.bytes() {
+            let key = if key == b'\n' {
+                KEY_ENTER
+            } else {
+                Key::from(key)
+            };
+            let event = Event::KeyDown(key);
+            response = input.responder(&event, &in_game, 0, &ChatMacros::default());
+        }
+        response
+    }
+
+    /// Sends what `input` has queued through ticcmds to player `to`, as player 1.
+    fn deliver(input: &mut ChatInput, to: usize) -> Vec<ChatMessage> {
+        let mut receiver = ChatReceiver::default();
+        let mut messages = Vec::new();
+        loop {
+            let c = input.next_char();
+            if c == 0 {
+                return messages;
+            }
+            let cmds = [
+                TicCmd::default(),
+                TicCmd {
+                    chatchar: c,
+                    ..TicCmd::default()
+                },
+            ];
+            let mut in_game = [true; MAXPLAYERS];
+            in_game[3] = false;
+            messages.extend(receiver.ticker(&cmds[..], &in_game, to));
+        }
+    }
+
+    #[test]
+    fn messages_reach_only_who_they_are_for() {
+        let mut input = ChatInput::default();
+        assert_eq!(
+            type_keys(&mut input, "x"),
+            Response::Ignored,
+            "chat is closed"
+        );
+        assert_eq!(type_keys(&mut input, "thi"), Response::Eaten);
+        assert_eq!(input.line(), "HI");
+        assert_eq!(
+            type_keys(&mut input, "\n"),
+            Response::Sent("HI".to_string())
+        );
+        assert!(!input.is_open());
+        let message = ChatMessage {
+            from: 1,
+            to_all: true,
+            text: "HI".to_string(),
+        };
+        assert_eq!(deliver(&mut input, 0), std::slice::from_ref(&message));
+        assert_eq!(message.line(), "Indigo: HI");
+        assert_eq!(message.color(), TextColor::Blue);
+
+        // Brown alone.
+        type_keys(&mut input, "bpsst\n");
+        let mut copy = input.clone();
+        assert_eq!(deliver(&mut input, 2).len(), 1);
+        assert!(deliver(&mut copy, 0).is_empty());
+
+        assert_eq!(
+            type_keys(&mut input, "g"),
+            Response::TalkToSelf("HUSTR_TALKTOSELF1")
+        );
+        assert_eq!(
+            type_keys(&mut input, "r"),
+            Response::Ignored,
+            "red isn't playing"
+        );
+    }
+
+    #[test]
+    fn macros_and_escape() {
+        let mut input = ChatInput::default();
+        let in_game = [true, true, false, false];
+        let mut macros = ChatMacros::default();
+        macros.macros[3] = "Medic!".to_string();
+        let press = |input: &mut ChatInput, key| {
+            input.responder(&Event::KeyDown(key), &in_game, 0, &macros)
+        };
+        press(&mut input, CHAT_KEY);
+        press(&mut input, b'n' as Key);
+        press(&mut input, KEY_ESCAPE);
+        assert!(!input.is_open());
+        press(&mut input, CHAT_KEY);
+        press(&mut input, KEY_RALT);
+        assert_eq!(
+            press(&mut input, b'3' as Key),
+            Response::Sent("Medic!".to_string())
+        );
+        let messages = deliver(&mut input, 0);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "MEDIC!", "the escaped N is gone");
+    }
+
+    #[test]
+    fn macros_come_from_the_config() {
+        let mut config = Config::new();
+        ChatMacros::register(&mut config);
+        assert_eq!(ChatMacros::from_config(&config), ChatMacros::default());
+        config.set_str("chatmacro5", "gg");
+        assert_eq!(ChatMacros::from_config(&config).macros[5], "gg");
+    }
+
+    #[test]
+    fn vote_subjects_are_not_chat() {
+        let mut receiver = ChatReceiver::default();
+        let in_game = [true; MAXPLAYERS];
+        let mut send = |cmd: TicCmd| receiver.ticker(&[TicCmd::default(), cmd], &in_game, 0);
+        let chat = |c| TicCmd {
+            chatchar: c,
+            ..TicCmd::default()
+        };
+        send(chat(HU_BROADCAST));
+        send(chat(b'G'));
+        let kick = vote::Proposal::Kick { player: 2 };
+        // Its subject, 2, would otherwise send the rest to Indigo alone.
+        assert!(send(vote::with_ballot(chat(0), vote::Ballot::Call(kick))).is_empty());
+        send(chat(b'G'));
+        let messages = send(chat(KEY_ENTER as u8));
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].to_all);
+        assert_eq!(messages[0].text, "GG");
+    }
+}
@@ -0,0 +1,414 @@
+//! The vanilla cheat codes.
+//!
+//! Each cheat is a sequence of keys, optionally followed by parameter keys (the two digits of
+//! `IDCLEV`, the power letter of `IDBEHOLD`). Every key typed during play is fed to all of them
+//! at once. The sequences are plain strings so DeHackEd patches can replace them.
+
+use super::{GameMode, Skill};
+use crate::audio::music;
+use crate::engine::input::Key;
+
+/// `player->cheats` bits.
+pub const CF_NOCLIP: u32 = 1;
+pub const CF_GODMODE: u32 = 2;
+
+/// Power-ups in vanilla's `pw_` order, which is also the order of the `IDBEHOLD` letters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Power {
+    Invulnerability,
+    Strength,
+    Invisibility,
+    IronFeet,
+    AllMap,
+    Infrared,
+}
+
+impl Power {
+    pub const ALL: [Power; 6] = [
+        Power::Invulnerability,
+        Power::Strength,
+        Power::Invisibility,
+        Power::IronFeet,
+        Power::AllMap,
+        Power::Infrared,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheatKind {
+    God,
+    Ammo,
+    AmmoAndKeys,
+    Music,
+    NoClip,
+    /// Doom II's name for the no clipping cheat.
+    CommercialNoClip,
+    Behold(Power),
+    BeholdMenu,
+    Choppers,
+    ChangeLevel,
+    MyPosition,
+}
+
+/// What the cheats do to the game. Implemented by the player and game state; the toggles
+/// return the new state so the right message can be shown.
+pub trait CheatTarget {
+    fn toggle_god(&mut self) -> bool;
+    fn give_weapons_and_ammo(&mut self, keys: bool);
+    fn toggle_noclip(&mut self) -> bool;
+    fn toggle_power(&mut self, power: Power);
+    fn give_chainsaw(&mut self);
+    fn change_level(&mut self, episode: u8, map: u8);
+    /// Starts the music lump `IDMUS` picked, `D_E1M1` and so on.
+    fn change_music(&mut self, lump: &str);
+    /// The `IDMYPOS` message, `ang=0x...;x,y=(0x...,0x...)` in vanilla.
+    fn position(&self) -> String;
+}
+
+struct Sequence {
+    kind: CheatKind,
+    keys: Vec<u8>,
+    parameters: usize,
+    chars_read: usize,
+    param_buf: Vec<u8>,
+}
+
+impl Sequence {
+    fn new(kind: CheatKind, keys: &str, parameters: usize) -> Self {
+        Self {
+            kind,
+            keys: keys.as_bytes().to_vec(),
+            parameters,
+            chars_read: 0,
+            param_buf: Vec::new(),
+        }
+    }
+
+    /// `cht_CheckCheat`. A wrong key starts the sequence over without being checked against
+    /// its first letter, exactly like vanilla (so `iiddqd` doesn't count).
+    fn check(&mut self, key: u8) -> bool {
+        if self.chars_read < self.keys.len() {
+            if key == self.keys[self.chars_read] {
+                self.chars_read += 1;
+            } else {
+                self.chars_read = 0;
+            }
+            self.param_buf.clear();
+        } else if self.param_buf.len() < self.parameters {
+            self.param_buf.push(key);
+        }
+        if self.chars_read == self.keys.len() && self.param_buf.len() == self.parameters {
+            self.chars_read = 0;
+            return true;
+        }
+        false
+    }
+}
+
+pub struct Cheats {
+    sequences: Vec<Sequence>,
+    mode: GameMode,
+}
+
+impl Cheats {
+    pub fn new(mode: GameMode) -> Self {
+        let mut sequences = vec![
+            Sequence::new(CheatKind::God, "iddqd", 0),
+            Sequence::new(CheatKind::Ammo, "idfa", 0),
+            Sequence::new(CheatKind::AmmoAndKeys, "idkfa", 0),
+            Sequence::new(CheatKind::Music, "idmus", 2),
+            Sequence::new(CheatKind::NoClip, "idspispopd", 0),
+            Sequence::new(CheatKind::CommercialNoClip, "idclip", 0),
+        ];
+        for (power, letter) in Power::ALL.into_iter().zip("vsiral".chars()) {
+            sequences.push(Sequence::new(
+                CheatKind::Behold(power),
+                &format!("idbehold{letter}"),
+                0,
+            ));
+        }
+        sequences.extend([
+            Sequence::new(CheatKind::BeholdMenu, "idbehold", 0),
+            Sequence::new(CheatKind::Choppers, "idchoppers", 0),
+            Sequence::new(CheatKind::ChangeLevel, "idclev", 2),
+            Sequence::new(CheatKind::MyPosition, "idmypos", 0),
+        ]);
+        Self { sequences, mode }
+    }
+
+    /// Replaces the key sequence of a cheat, for DeHackEd's `[CHEATS]`. Parameters stay as they
+    /// are.
+    pub fn set_sequence(&mut self, kind: CheatKind, keys: &str) {
+        if let Some(sequence) = self.sequences.iter_mut().find(|s| s.kind == kind) {
+            sequence.keys = keys.to_ascii_lowercase().into_bytes();
+            sequence.chars_read = 0;
+        }
+    }
+
+    /// Feeds a key typed during play. Returns the message to show if a cheat fired, as a
+    /// `strings` entry name (or the text itself for `IDMYPOS`). Everything
+    /// but `IDCLEV` is off in Nightmare and netgames, and `IDCLEV` is off in netgames.
+    pub fn responder(
+        &mut self,
+        key: Key,
+        netgame: bool,
+        skill: Skill,
+        target: &mut dyn CheatTarget,
+    ) -> Option<String> {
+        let key = u8::try_from(key).ok()?;
+        let mut message = None;
+        let mode = self.mode;
+        for sequence in &mut self.sequences {
+            if !sequence.check(key) {
+                continue;
+            }
+            let allowed = match sequence.kind {
+                CheatKind::ChangeLevel => !netgame,
+                _ => !netgame && skill != Skill::Nightmare,
+            };
+            if allowed {
+                let param = [sequence.param_buf.first(), sequence.param_buf.get(1)];
+                let digits = match param {
+                    [Some(a), Some(b)] if a.is_ascii_digit() && b.is_ascii_digit() => {
+                        Some((a - b'0', b - b'0'))
+                    }
+                    _ => None,
+                };
+                message = apply(sequence.kind, mode, digits, target).or(message);
+            }
+        }
+        message
+    }
+}
+
+fn apply(
+    kind: CheatKind,
+    mode: GameMode,
+    digits: Option<(u8, u8)>,
+    target: &mut dyn CheatTarget,
+) -> Option<String> {
+    let message = match kind {
+        CheatKind::God => {
+            if target.toggle_god() {
+                "STSTR_DQDON"
+            } else {
+                "STSTR_DQDOFF"
+            }
+        }
+        CheatKind::Ammo => {
+            target.give_weapons_and_ammo(false);
+            "STSTR_FAADDED"
+        }
+        CheatKind::AmmoAndKeys => {
+            target.give_weapons_and_ammo(true);
+            "STSTR_KFAADDED"
+        }
+        CheatKind::NoClip | CheatKind::CommercialNoClip => {
+            if target.toggle_noclip() {
+                "STSTR_NCON"
+            } else {
+                "STSTR_NCOFF"
+            }
+        }
+        CheatKind::Behold(power) => {
+            target.toggle_power(power);
+            "STSTR_BEHOLDX"
+        }
+        CheatKind::BeholdMenu => "STSTR_BEHOLD",
+        CheatKind::Choppers => {
+            target.give_chainsaw();
+            "STSTR_CHOPPERS"
+        }
+        CheatKind::MyPosition => return Some(target.position()),
+        CheatKind::Music => {
+            let Some(lump) = digits.and_then(|(first, second)| music::idmus(mode, first, second))
+            else {
+                return Some("STSTR_NOMUS".to_string());
+            };
+            target.change_music(&lump);
+            "STSTR_MUS"
+        }
+        CheatKind::ChangeLevel => {
+            let (episode, map) = digits.filter(|&d| valid_level(mode, d))?;
+            let (episode, map) = episode_map(mode, episode, map);
+            target.change_level(episode, map);
+            "STSTR_CLEV"
+        }
+    };
+    Some(message.to_string())
+}
+
+/// Doom II reads both digits as the map number and has a single episode.
+fn episode_map(mode: GameMode, first: u8, second: u8) -> (u8, u8) {
+    if mode == GameMode::Commercial {
+        (1, first * 10 + second)
+    } else {
+        (first, second)
+    }
+}
+
+/// `IDCLEV` silently ignores maps the game doesn't have.
+fn valid_level(mode: GameMode, (first, second): (u8, u8)) -> bool {
+    if mode == GameMode::Commercial {
+        (1..=40).contains(&(first * 10 + second))
+    } else {
+        (1..=mode.episodes()).contains(&first) && (1..=9).contains(&second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Player {
+        god: bool,
+        noclip: bool,
+        keys: bool,
+        powers: Vec<Power>,
+        level: Option<(u8, u8)>,
+        music: Option<String>,
+    }
+
+    impl CheatTarget for Player {
+        fn toggle_god(&mut self) -> bool {
+            self.god = !self.god;
+            self.god
+        }
+        fn give_weapons_and_ammo(&mut self, keys: bool) {
+            self.keys |= keys;
+        }
+        fn toggle_noclip(&mut self) -> bool {
+            self.noclip = !self.noclip;
+            self.noclip
+        }
+        fn toggle_power(&mut self, power: Power) {
+            self.powers.push(power);
+        }
+        fn give_chainsaw(&mut self) {}
+        fn change_level(&mut self, episode: u8, map: u8) {
+            self.level = Some((episode, map));
+        }
+        fn change_music(&mut self, lump: &str) {
+            self.music = Some(lump.to_string());
+        }
+        fn position(&self) -> String {
+            "ang=0x0;x,y=(0x0,0x0)".to_string()
+        }
+    }
+
+    fn type_keys(
+        cheats: &mut Cheats,
+        text: &str,
+        netgame: bool,
+        skill: Skill,
+        player: &mut Player,
+    ) -> Option<String> {
+        text.bytes()
+            .filter_map(|c| cheats.responder(c as Key, netgame, skill, player))
+            .last()
+    }
+
+    #[test]
+    fn simple_cheats_toggle_and_report() {
+        let mut cheats = Cheats::new(GameMode::Commercial);
+        let mut player = Player::default();
+        let skill = Skill::Medium;
+        assert_eq!(
+            type_keys(&mut cheats, "iddqd", false, skill, &mut player).as_deref(),
+            Some("STSTR_DQDON")
+        );
+        assert_eq!(
+            type_keys(&mut cheats, "iddqd", false, skill, &mut player).as_deref(),
+            Some("STSTR_DQDOFF")
+        );
+        assert_eq!(
+            type_keys(&mut cheats, "idkfa", false, skill, &mut player).as_deref(),
+            Some("STSTR_KFAADDED")
+        );
+        assert!(player.keys);
+        assert_eq!(
+            type_keys(&mut cheats, "idclip", false, skill, &mut player).as_deref(),
+            Some("STSTR_NCON")
+        );
+        // A wrong key restarts without counting as the first letter.
+        assert_eq!(
+            type_keys(&mut cheats, "iiddqd", false, skill, &mut player),
+            None
+        );
+    }
+
+    #[test]
+    fn parameterized_cheats() {
+        let mut cheats = Cheats::new(GameMode::Registered);
+        let mut player = Player::default();
+        let skill = Skill::Hard;
+        assert_eq!(
+            type_keys(&mut cheats, "idclev23", false, skill, &mut player).as_deref(),
+            Some("STSTR_CLEV")
+        );
+        assert_eq!(player.level, Some((2, 3)));
+        // No episode 4 in the registered game.
+        assert_eq!(
+            type_keys(&mut cheats, "idclev41", false, skill, &mut player),
+            None
+        );
+        assert_eq!(
+            type_keys(&mut cheats, "idmus19", false, skill, &mut player).as_deref(),
+            Some("STSTR_MUS")
+        );
+        assert_eq!(player.music.as_deref(), Some("D_E1M9"));
+        assert_eq!(
+            type_keys(&mut cheats, "idmus50", false, skill, &mut player).as_deref(),
+            Some("STSTR_NOMUS")
+        );
+        assert_eq!(
+            type_keys(&mut cheats, "idbehold", false, skill, &mut player).as_deref(),
+            Some("STSTR_BEHOLD")
+        );
+        assert_eq!(
+            cheats
+                .responder(b'r' as Key, false, skill, &mut player)
+                .as_deref(),
+            Some("STSTR_BEHOLDX")
+        );
+        assert_eq!(player.powers, vec![Power::IronFeet]);
+    }
+
+    #[test]
+    fn nightmare_and_netgames_disable_cheats() {
+        let mut cheats = Cheats::new(GameMode::Commercial);
+        let mut player = Player::default();
+        assert_eq!(
+            type_keys(&mut cheats, "iddqd", false, Skill::Nightmare, &mut player),
+            None
+        );
+        assert!(!player.god);
+        // Changing level still works in Nightmare, but not in a netgame.
+        assert!(type_keys(
+            &mut cheats,
+            "idclev07",
+            false,
+            Skill::Nightmare,
+            &mut player
+        )
+        .is_some());
+        assert_eq!(
+            type_keys(&mut cheats, "idclev08", true, Skill::Medium, &mut player),
+            None
+        );
+        assert_eq!(player.level, Some((1, 7)));
+    }
+
+    #[test]
+    fn sequences_can_be_replaced() {
+        let mut cheats = Cheats::new(GameMode::Commercial);
+        let mut player = Player::default();
+        cheats.set_sequence(CheatKind::God, "GODMODE");
+        assert_eq!(
+            type_keys(&mut cheats, "iddqd", false, Skill::Medium, &mut player),
+            None
+        );
+        assert!(type_keys(&mut cheats, "godmode", false, Skill::Medium, &mut player).is_some());
+    }
+}
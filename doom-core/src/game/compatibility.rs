@@ -0,0 +1,255 @@
+//! Which engine's behaviour to reproduce.
+//!
+//! Every place where Boom, MBF or MBF21 changed how the game plays checks a flag in
+//! `Compatibility` instead of the level directly, so the flags can be reasoned about (and
+//! later overridden) one at a time. The level is picked with `-complevel`, recorded in demos,
+//! and a demo being played back always wins over the command line.
+
+use core::str::FromStr;
+
+/// The compatibility level. Vanilla is the default so demos and maps made for the original
+/// exe behave exactly as they did there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompLevel {
+    /// Doom 1.9, including Ultimate and Final Doom.
+    #[default]
+    Vanilla,
+    Boom,
+    Mbf,
+    Mbf21,
+}
+
+impl CompLevel {
+    /// Generalized linedefs, the extended sector bits, friction and pushers.
+    pub fn boom_specials(self) -> bool {
+        self >= CompLevel::Boom
+    }
+
+    /// The version byte at the start of a demo recorded at this level.
+    pub fn demo_version(self) -> u8 {
+        match self {
+            CompLevel::Vanilla => 109,
+            CompLevel::Boom => 202,
+            CompLevel::Mbf => 203,
+            CompLevel::Mbf21 => 221,
+        }
+    }
+
+    /// The level a demo version byte stands for. Vanilla 1.9 demos (109) and the versions
+    /// before it that share the same playsim (1.666 to 1.8) are all vanilla.
+    pub fn from_demo_version(version: u8) -> Option<Self> {
+        match version {
+            106..=109 => Some(CompLevel::Vanilla),
+            200..=202 => Some(CompLevel::Boom),
+            203 => Some(CompLevel::Mbf),
+            221 => Some(CompLevel::Mbf21),
+            _ => None,
+        }
+    }
+}
+
+/// Accepts PrBoom+ style numbers (`2`, `9`, `11`, `21`) and names (`vanilla`, `boom`, `mbf`,
+/// `mbf21`).
+impl FromStr for CompLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_ascii_lowercase().as_str() {
+            "2" | "3" | "4" | "vanilla" | "doom" => Ok(CompLevel::Vanilla),
+            "7" | "8" | "9" | "boom" => Ok(CompLevel::Boom),
+            "11" | "mbf" => Ok(CompLevel::Mbf),
+            "21" | "mbf21" => Ok(CompLevel::Mbf21),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The individual behaviours that differ between levels. `true` always means "the newer,
+/// fixed behaviour" or "the feature exists".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compatibility {
+    pub level: CompLevel,
+    /// Generalized linedefs and sector bits.
+    pub boom_specials: bool,
+    pub friction: bool,
+    pub pushers: bool,
+    /// Translucent lines and the `TRANSLUCENT` thing flag.
+    pub translucency: bool,
+    /// A tag 0 special no longer affects every untagged sector.
+    pub zero_tags_fixed: bool,
+    /// Arch-viles no longer raise crushed monsters as ghosts.
+    pub vile_ghosts_fixed: bool,
+    /// Doors opened by a key card on a closing door don't get stuck.
+    pub door_stuck_fixed: bool,
+    /// God mode also survives telefrags and damage over 1000.
+    pub god_absolute: bool,
+    /// Pain elementals may spawn more than 20 lost souls.
+    pub no_lost_soul_limit: bool,
+    /// Lost souls bounce off floors and ceilings instead of sticking.
+    pub lost_souls_bounce: bool,
+    /// Pain elementals don't spawn lost souls on the far side of a wall.
+    pub skull_walls_fixed: bool,
+    /// Monsters may step off ledges and avoid hazards (MBF's AI changes).
+    pub mbf_monster_ai: bool,
+    /// MBF code pointers in DeHackEd patches.
+    pub mbf_code_pointers: bool,
+    /// Things overlapping a block boundary are found from every block they touch.
+    pub blockmap_fixed: bool,
+    /// MBF21 thing and weapon flags, extended code pointer arguments.
+    pub mbf21_features: bool,
+    /// Emulate what vanilla did when its fixed size tables overflowed.
+    pub emulate_overflows: bool,
+}
+
+impl Compatibility {
+    pub fn for_level(level: CompLevel) -> Self {
+        let boom = level >= CompLevel::Boom;
+        let mbf = level >= CompLevel::Mbf;
+        let mbf21 = level >= CompLevel::Mbf21;
+        Self {
+            level,
+            boom_specials: boom,
+            friction: boom,
+            pushers: boom,
+            translucency: boom,
+            zero_tags_fixed: boom,
+            vile_ghosts_fixed: boom,
+            door_stuck_fixed: boom,
+            god_absolute: boom,
+            no_lost_soul_limit: mbf,
+            lost_souls_bounce: mbf,
+            skull_walls_fixed: mbf,
+            mbf_monster_ai: mbf,
+            mbf_code_pointers: mbf,
+            blockmap_fixed: mbf21,
+            mbf21_features: mbf21,
+            emulate_overflows: !boom,
+        }
+    }
+}
+
+impl Default for Compatibility {
+    fn default() -> Self {
+        Self::for_level(CompLevel::default())
+    }
+}
+
+/// What a demo records about the game it was recorded in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DemoHeader {
+    pub level: CompLevel,
+    pub skill: u8, // 0 based, like the byte in the file.
+    pub episode: u8,
+    pub map: u8,
+    pub deathmatch: u8,
+    pub respawn: bool,
+    pub fast: bool,
+    pub nomonsters: bool,
+    pub console_player: u8,
+    pub players: [bool; 4],
+}
+
+impl DemoHeader {
+    pub const SIZE: usize = 13;
+
+    /// The vanilla 1.9 header. Other levels write their own version byte in front of the same
+    /// fields; Boom's option block isn't recorded yet, so those demos are for this port only.
+    pub fn write(&self) -> [u8; Self::SIZE] {
+        let [p0, p1, p2, p3] = self.players.map(u8::from);
+        [
+            self.level.demo_version(),
+            self.skill,
+            self.episode,
+            self.map,
+            self.deathmatch,
+            self.respawn as u8,
+            self.fast as u8,
+            self.nomonsters as u8,
+            self.console_player,
+            p0,
+            p1,
+            p2,
+            p3,
+        ]
+    }
+
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let header: &[u8; Self::SIZE] = bytes.get(..Self::SIZE)?.try_into().ok()?;
+        Some(Self {
+            level: CompLevel::from_demo_version(header[0])?,
+            skill: header[1],
+            episode: header[2],
+            map: header[3],
+            deathmatch: header[4],
+            respawn: header[5] != 0,
+            fast: header[6] != 0,
+            nomonsters: header[7] != 0,
+            console_player: header[8],
+            players: [header[9], header[10], header[11], header[12]].map(|b| b != 0),
+        })
+    }
+}
+
+/// The level to run at: a demo being played back forces the level it was recorded with,
+/// otherwise `-complevel`, otherwise vanilla.
+pub fn effective_level(requested: Option<CompLevel>, demo: Option<&DemoHeader>) -> CompLevel {
+    demo.map(|demo| demo.level)
+        .or(requested)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_enable_features_cumulatively() {
+        let vanilla = Compatibility::for_level(CompLevel::Vanilla);
+        assert!(vanilla.emulate_overflows && !vanilla.boom_specials);
+        let boom = Compatibility::for_level(CompLevel::Boom);
+        assert!(boom.boom_specials && boom.zero_tags_fixed && !boom.no_lost_soul_limit);
+        let mbf21 = Compatibility::for_level(CompLevel::Mbf21);
+        assert!(mbf21.boom_specials && mbf21.mbf_monster_ai && mbf21.blockmap_fixed);
+        assert!(!mbf21.emulate_overflows);
+    }
+
+    #[test]
+    fn parses_numbers_and_names() {
+        assert_eq!("2".parse(), Ok(CompLevel::Vanilla));
+        assert_eq!("Boom".parse(), Ok(CompLevel::Boom));
+        assert_eq!("11".parse(), Ok(CompLevel::Mbf));
+        assert_eq!("mbf21".parse(), Ok(CompLevel::Mbf21));
+        assert_eq!("17".parse::<CompLevel>(), Err(()));
+    }
+
+    #[test]
+    fn demo_header_round_trip_and_forced_level() {
+        let header = DemoHeader {
+            level: CompLevel::Mbf,
+            skill: 3,
+            episode: 1,
+            map: 7,
+            players: [true, false, false, false],
+            ..DemoHeader::default()
+        };
+        let bytes = header.write();
+        assert_eq!(bytes[0], 203);
+        assert_eq!(DemoHeader::parse(&bytes), Some(header));
+        assert_eq!(
+            effective_level(Some(CompLevel::Vanilla), Some(&header)),
+            CompLevel::Mbf
+        );
+        assert_eq!(
+            effective_level(Some(CompLevel::Boom), None),
+            CompLevel::Boom
+        );
+
+        // A real vanilla 1.9 header.
+        let vanilla = [109, 2, 1, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0];
+        assert_eq!(
+            DemoHeader::parse(&vanilla).unwrap().level,
+            CompLevel::Vanilla
+        );
+        assert_eq!(DemoHeader::parse(&[150; 13]), None);
+    }
+}
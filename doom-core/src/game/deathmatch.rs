@@ -0,0 +1,233 @@
+//! The rules a deathmatch server sets: when the level ends, who a respawn may telefrag and
+//! what the exit does.
+//!
+//! Vanilla has only `-timer` (and `-avg`, twenty minutes), counted from the start of the
+//! level. A frag limit ends the level as soon as a player's frag count reaches it. Spawn
+//! protection keeps a player who just spawned from being telefragged by the next player
+//! spawning on the same start, for a short window. With exits set to kill, an exit switch
+//! kills whoever uses it, as some servers prefer to a level change nobody agreed to.
+//!
+//! None of this is in the demo header, so like vanilla's `-timer` a demo recorded with these
+//! only plays back with the same settings. In a netgame the server's rules are what counts:
+//! `encode` packs them into the settings block it sends with the game setup, and every peer
+//! plays by what `decode` gets back.
+
+use super::demo::MAXPLAYERS;
+use super::TICRATE;
+use crate::engine::config::{Config, Configurable, Section};
+
+/// What `-avg` sets the timer to, in minutes.
+pub const AVG_MINUTES: i32 = 20;
+/// Bytes of the settings block.
+pub const SETUP_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExitRule {
+    /// Vanilla: the exit ends the level for everyone.
+    #[default]
+    EndLevel,
+    Kill,
+}
+
+/// Why a deathmatch level ended on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelEnd {
+    FragLimit { player: usize },
+    TimeLimit,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeathmatchRules {
+    /// Frags that end the level, 0 for no limit.
+    pub frag_limit: i32,
+    /// Minutes the level lasts, 0 for no limit.
+    pub time_limit: i32,
+    /// Tics after spawning that a player can't be telefragged.
+    pub spawn_protection: i32,
+    pub exit: ExitRule,
+}
+
+/// A player's frag count as the status bar shows it: kills of other players, minus
+/// suicides.
+pub fn frag_count(frags: &[[i32; MAXPLAYERS]; MAXPLAYERS], player: usize) -> i32 {
+    let kills: i32 = (0..MAXPLAYERS)
+        .filter(|&other| other != player)
+        .map(|other| frags[player][other])
+        .sum();
+    kills - frags[player][player]
+}
+
+impl DeathmatchRules {
+    /// Whether anything differs from vanilla, which a demo header can't record.
+    pub fn changes_sync(&self) -> bool {
+        self.frag_limit != 0 || self.spawn_protection != 0 || self.exit != ExitRule::EndLevel
+    }
+
+    /// Checked after every tic: whether the level ends now. The lowest numbered player to
+    /// reach the frag limit is the one named.
+    pub fn level_end(
+        &self,
+        frags: &[[i32; MAXPLAYERS]; MAXPLAYERS],
+        in_game: &[bool; MAXPLAYERS],
+        level_time: i32,
+    ) -> Option<LevelEnd> {
+        if self.frag_limit > 0 {
+            let winner = (0..MAXPLAYERS)
+                .find(|&player| in_game[player] && frag_count(frags, player) >= self.frag_limit);
+            if let Some(player) = winner {
+                return Some(LevelEnd::FragLimit { player });
+            }
+        }
+        let limit = self.time_limit * 60 * TICRATE;
+        (limit > 0 && level_time >= limit).then_some(LevelEnd::TimeLimit)
+    }
+
+    /// Whether a player spawning onto a player who spawned at `spawned_at` telefrags them.
+    /// If not, the spawn has to look for another start.
+    pub fn telefrag_allowed(&self, spawned_at: i32, level_time: i32) -> bool {
+        level_time - spawned_at >= self.spawn_protection
+    }
+
+    /// What the exit does. Outside deathmatch it always ends the level.
+    pub fn exit(&self, deathmatch: bool) -> ExitRule {
+        if deathmatch {
+            self.exit
+        } else {
+            ExitRule::EndLevel
+        }
+    }
+
+    /// The rules as the game setup sends them.
+    pub fn encode(&self) -> [u8; SETUP_SIZE] {
+        let mut block = [0; SETUP_SIZE];
+        block[0..2].copy_from_slice(&(self.frag_limit as u16).to_le_bytes());
+        block[2..4].copy_from_slice(&(self.time_limit as u16).to_le_bytes());
+        block[4..6].copy_from_slice(&(self.spawn_protection as u16).to_le_bytes());
+        block[6] = self.exit as u8;
+        block
+    }
+
+    /// Reads a settings block. Returns `None` if it is short or has an exit rule this
+    /// version doesn't know.
+    pub fn decode(block: &[u8]) -> Option<Self> {
+        let block: &[u8; SETUP_SIZE] = block.get(..SETUP_SIZE)?.try_into().ok()?;
+        let word = |at: usize| i32::from(u16::from_le_bytes([block[at], block[at + 1]]));
+        Some(Self {
+            frag_limit: word(0),
+            time_limit: word(2),
+            spawn_protection: word(4),
+            exit: match block[6] {
+                0 => ExitRule::EndLevel,
+                1 => ExitRule::Kill,
+                _ => return None,
+            },
+        })
+    }
+}
+
+impl Configurable for DeathmatchRules {
+    fn register(config: &mut Config) {
+        config.register_int("dm_frag_limit", 0, Section::Extended);
+        config.register_int("dm_time_limit", 0, Section::Extended);
+        config.register_int("dm_spawn_protection", 0, Section::Extended);
+        config.register_bool("dm_exit_kills", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            frag_limit: config.get_int("dm_frag_limit").clamp(0, 999),
+            time_limit: config.get_int("dm_time_limit").clamp(0, 1440),
+            spawn_protection: config.get_int("dm_spawn_protection").clamp(0, 10 * TICRATE),
+            exit: if config.get_bool("dm_exit_kills") {
+                ExitRule::Kill
+            } else {
+                ExitRule::EndLevel
+            },
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("dm_frag_limit", self.frag_limit);
+        config.set_int("dm_time_limit", self.time_limit);
+        config.set_int("dm_spawn_protection", self.spawn_protection);
+        config.set_bool("dm_exit_kills", self.exit == ExitRule::Kill);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_end_the_level() {
+        let rules = DeathmatchRules {
+            frag_limit: 10,
+            time_limit: 5,
+            ..DeathmatchRules::default()
+        };
+        let mut frags = [[0; MAXPLAYERS]; MAXPLAYERS];
+        let in_game = [true, true, true, false];
+        frags[1][0] = 8;
+        frags[1][2] = 3;
+        frags[1][1] = 1;
+        frags[3][0] = 20;
+        assert_eq!(frag_count(&frags, 1), 10);
+        assert_eq!(
+            rules.level_end(&frags, &in_game, 0),
+            Some(LevelEnd::FragLimit { player: 1 })
+        );
+        frags[1][1] = 2;
+        assert_eq!(
+            rules.level_end(&frags, &in_game, 5 * 60 * TICRATE - 1),
+            None
+        );
+        assert_eq!(
+            rules.level_end(&frags, &in_game, 5 * 60 * TICRATE),
+            Some(LevelEnd::TimeLimit)
+        );
+        assert_eq!(
+            DeathmatchRules::default().level_end(&frags, &in_game, i32::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn protection_and_exits() {
+        let rules = DeathmatchRules {
+            spawn_protection: 2 * TICRATE,
+            exit: ExitRule::Kill,
+            ..DeathmatchRules::default()
+        };
+        assert!(!rules.telefrag_allowed(100, 100 + TICRATE));
+        assert!(rules.telefrag_allowed(100, 100 + 2 * TICRATE));
+        assert!(DeathmatchRules::default().telefrag_allowed(100, 100));
+        assert_eq!(rules.exit(true), ExitRule::Kill);
+        assert_eq!(rules.exit(false), ExitRule::EndLevel);
+        assert!(rules.changes_sync());
+        let timer = DeathmatchRules {
+            time_limit: AVG_MINUTES,
+            ..DeathmatchRules::default()
+        };
+        assert!(!timer.changes_sync());
+    }
+
+    #[test]
+    fn rules_cross_the_setup_and_the_config() {
+        let rules = DeathmatchRules {
+            frag_limit: 50,
+            time_limit: 20,
+            spawn_protection: 35,
+            exit: ExitRule::Kill,
+        };
+        assert_eq!(DeathmatchRules::decode(&rules.encode()), Some(rules));
+        assert_eq!(DeathmatchRules::decode(&rules.encode()[..7]), None);
+        let mut block = rules.encode();
+        block[6] = 9;
+        assert_eq!(DeathmatchRules::decode(&block), None);
+
+        let mut config = Config::new();
+        DeathmatchRules::register(&mut config);
+        rules.to_config(&mut config);
+        assert_eq!(DeathmatchRules::from_config(&config), rules);
+    }
+}
@@ -0,0 +1,653 @@
+//! DeHackEd (`.deh`) and Boom extended (`.bex`) patches.
+//!
+//! A patch is a list of blocks (`Thing 1 (Zombieman)`, `Frame 123`, `Text 4 4`, ...) whose
+//! `Field = value` lines edit the info tables in place. Patches are forgiving by tradition:
+//! anything that can't be applied becomes a warning instead of failing the whole file, since
+//! most of them were written against one particular exe and contain some noise.
+//!
+//! Thing numbers are 1 based like in the DeHackEd editor; frames, weapons and ammo are 0 based.
+//! Text and strings aren't applied here, they are collected for the string table.
+
+use super::cheat::{CheatKind, Cheats, Power};
+use super::info::{Action, GameInfo, MOBJ_FLAG_NAMES};
+use crate::wad::file::{WadKind, Wads};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A `[PARS]` entry; `episode` is `None` for Doom II style maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParTime {
+    pub episode: Option<u8>,
+    pub map: u8,
+    pub seconds: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Block {
+    None,
+    Thing(usize),
+    Frame(usize),
+    Pointer(usize),
+    Weapon(usize),
+    Ammo(usize),
+    Misc,
+    Cheat,
+    Strings,
+    Pars,
+    CodePointers,
+    Sprites,
+    Ignored,
+}
+
+const CHEAT_NAMES: &[(&str, CheatKind)] = &[
+    ("change music", CheatKind::Music),
+    ("chainsaw", CheatKind::Choppers),
+    ("god mode", CheatKind::God),
+    ("ammo & keys", CheatKind::AmmoAndKeys),
+    ("ammo", CheatKind::Ammo),
+    ("no clipping 1", CheatKind::NoClip),
+    ("no clipping 2", CheatKind::CommercialNoClip),
+    ("invincibility", CheatKind::Behold(Power::Invulnerability)),
+    ("berserk", CheatKind::Behold(Power::Strength)),
+    ("invisibility", CheatKind::Behold(Power::Invisibility)),
+    ("radiation suit", CheatKind::Behold(Power::IronFeet)),
+    ("auto-map", CheatKind::Behold(Power::AllMap)),
+    ("lite-amp goggles", CheatKind::Behold(Power::Infrared)),
+    ("behold menu", CheatKind::BeholdMenu),
+    ("level warp", CheatKind::ChangeLevel),
+    ("player position", CheatKind::MyPosition),
+];
+
+/// Everything loaded from patches so far. Several patches can be loaded in a row; later ones
+/// win.
+pub struct Dehacked {
+    /// The code pointers before any patch, which `Pointer` blocks copy from.
+    original_actions: Vec<Option<Action>>,
+    /// BEX `[STRINGS]`, by mnemonic.
+    pub strings: BTreeMap<String, String>,
+    /// `Text` replacements that weren't sprite or sound names: (original, replacement).
+    pub text: Vec<(String, String)>,
+    pub cheats: Vec<(CheatKind, String)>,
+    pub pars: Vec<ParTime>,
+    pub warnings: Vec<String>,
+    source: String, // Name of the patch being loaded, for warnings.
+}
+
+impl Dehacked {
+    pub fn new(info: &GameInfo) -> Self {
+        Self {
+            original_actions: info.states.iter().map(|state| state.action).collect(),
+            strings: BTreeMap::new(),
+            text: Vec::new(),
+            cheats: Vec::new(),
+            pars: Vec::new(),
+            warnings: Vec::new(),
+            source: String::new(),
+        }
+    }
+
+    /// Applies the patches a game starts with: the `-deh` files in command line order, then
+    /// the `DEHACKED` lumps of the PWADs in load order unless `nodeh` is set. IWADs are skipped,
+    /// their lumps (Freedoom has one) aren't meant for vanilla style loading.
+    pub fn load_startup_patches(
+        &mut self,
+        files: &[PathBuf],
+        wads: &Wads,
+        nodeh: bool,
+        info: &mut GameInfo,
+    ) -> io::Result<()> {
+        for path in files {
+            self.load_file(path, info)?;
+        }
+        if nodeh {
+            return Ok(());
+        }
+        for (file, lump) in wads.lumps_named("DEHACKED") {
+            if file.kind == WadKind::Pwad {
+                let source = format!("{}:DEHACKED", file.path.display());
+                self.load_from(&source, &String::from_utf8_lossy(&lump.data), info);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load_file(&mut self, path: &Path, info: &mut GameInfo) -> io::Result<()> {
+        let bytes = fs::read(path)?;
+        self.load_from(
+            &path.display().to_string(),
+            &String::from_utf8_lossy(&bytes),
+            info,
+        );
+        Ok(())
+    }
+
+    /// Applies a patch, naming it `source` in warnings.
+    pub fn load_from(&mut self, source: &str, patch: &str, info: &mut GameInfo) {
+        self.source = source.to_string();
+        self.load(patch, info);
+        self.source.clear();
+    }
+
+    /// Applies a patch to `info`.
+    pub fn load(&mut self, patch: &str, info: &mut GameInfo) {
+        let patch = patch.replace('\r', "");
+        let mut rest = patch.as_str();
+        let mut block = Block::None;
+        let mut number = 0;
+
+        while !rest.is_empty() {
+            let (line, tail) = rest.split_once('\n').unwrap_or((rest, ""));
+            rest = tail;
+            number += 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let (key, value) = (key.trim(), value.trim());
+                if block == Block::Strings {
+                    rest = self.bex_string(key, value, rest);
+                } else {
+                    self.field(block, key, value, info, number);
+                }
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default().to_ascii_lowercase();
+            let index = || words.clone().next().and_then(|n| n.parse::<usize>().ok());
+            block = match keyword.as_str() {
+                "par" if block == Block::Pars => {
+                    self.par(line, number);
+                    Block::Pars
+                }
+                "thing" => match index() {
+                    Some(n) if n >= 1 => Block::Thing(n - 1),
+                    _ => self.bad_header(line, number),
+                },
+                "frame" => index().map_or_else(|| self.bad_header(line, number), Block::Frame),
+                "weapon" => index().map_or_else(|| self.bad_header(line, number), Block::Weapon),
+                "ammo" => index().map_or_else(|| self.bad_header(line, number), Block::Ammo),
+                "pointer" => {
+                    // "Pointer 12 (Frame 34)": the frame number is what matters.
+                    let frame = line
+                        .to_ascii_lowercase()
+                        .split_once("frame")
+                        .and_then(|(_, n)| n.trim().trim_end_matches(')').trim().parse().ok());
+                    frame.map_or_else(|| self.bad_header(line, number), Block::Pointer)
+                }
+                "text" => {
+                    let lengths: Vec<usize> = words.filter_map(|n| n.parse().ok()).collect();
+                    match lengths[..] {
+                        [old, new] => rest = self.text_block(rest, old, new, info, number),
+                        _ => {
+                            self.bad_header(line, number);
+                        }
+                    }
+                    Block::None
+                }
+                "misc" => Block::Misc,
+                "cheat" => Block::Cheat,
+                "sound" | "sprite" | "patch" | "doom" => Block::Ignored,
+                "[strings]" => Block::Strings,
+                "[pars]" => Block::Pars,
+                "[codeptr]" => Block::CodePointers,
+                "[sprites]" => Block::Sprites,
+                _ if keyword.starts_with('[') => Block::Ignored,
+                _ => {
+                    self.warn(number, format!("unknown line '{line}'"));
+                    block
+                }
+            };
+        }
+    }
+
+    /// Hands the replaced cheat sequences to the cheat matcher.
+    pub fn apply_cheats(&self, cheats: &mut Cheats) {
+        for (kind, sequence) in &self.cheats {
+            cheats.set_sequence(*kind, sequence);
+        }
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        if self.source.is_empty() {
+            self.warnings.push(format!("line {line}: {message}"));
+        } else {
+            let source = &self.source;
+            self.warnings
+                .push(format!("{source}: line {line}: {message}"));
+        }
+    }
+
+    fn bad_header(&mut self, line: &str, number: usize) -> Block {
+        self.warn(number, format!("bad block header '{line}'"));
+        Block::Ignored
+    }
+
+    fn field(&mut self, block: Block, key: &str, value: &str, info: &mut GameInfo, line: usize) {
+        let lower = key.to_ascii_lowercase();
+        let key = lower.as_str();
+        let int = || value.parse::<i64>().ok().map(|v| v as i32);
+        let applied = match block {
+            Block::Thing(n) => match info.mobjs.get_mut(n) {
+                Some(mobj) if key == "bits" => match parse_bits(value) {
+                    Some(bits) => {
+                        mobj.flags = bits;
+                        true
+                    }
+                    None => false,
+                },
+                Some(mobj) => int().is_some_and(|v| {
+                    let state = v.max(0) as usize;
+                    match key {
+                        "id #" => mobj.doomednum = v,
+                        "initial frame" => mobj.spawn_state = state,
+                        "hit points" => mobj.spawn_health = v,
+                        "first moving frame" => mobj.see_state = state,
+                        "alert sound" => mobj.see_sound = state,
+                        "reaction time" => mobj.reaction_time = v,
+                        "attack sound" => mobj.attack_sound = state,
+                        "injury frame" => mobj.pain_state = state,
+                        "pain chance" => mobj.pain_chance = v,
+                        "pain sound" => mobj.pain_sound = state,
+                        "close attack frame" => mobj.melee_state = state,
+                        "far attack frame" => mobj.missile_state = state,
+                        "death frame" => mobj.death_state = state,
+                        "exploding frame" => mobj.xdeath_state = state,
+                        "death sound" => mobj.death_sound = state,
+                        "speed" => mobj.speed = v,
+                        "width" => mobj.radius = v,
+                        "height" => mobj.height = v,
+                        "mass" => mobj.mass = v,
+                        "missile damage" => mobj.damage = v,
+                        "action sound" => mobj.active_sound = state,
+                        "respawn frame" => mobj.raise_state = state,
+                        _ => return false,
+                    }
+                    true
+                }),
+                None => false,
+            },
+            Block::Frame(n) => match (info.states.get_mut(n), int()) {
+                (Some(state), Some(v)) => {
+                    match key {
+                        "sprite number" => state.sprite = v.max(0) as usize,
+                        "sprite subnumber" => state.frame = v,
+                        "duration" => state.tics = v,
+                        "next frame" => state.next_state = v.max(0) as usize,
+                        "unknown 1" => state.misc1 = v,
+                        "unknown 2" => state.misc2 = v,
+                        _ => return self.warn(line, format!("unknown frame field '{key}'")),
+                    }
+                    true
+                }
+                _ => false,
+            },
+            Block::Pointer(n) => {
+                let source = int().and_then(|v| self.original_actions.get(v as usize).copied());
+                match (key, info.states.get_mut(n), source) {
+                    ("codep frame", Some(state), Some(action)) => {
+                        state.action = action;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Block::Weapon(n) => match (info.weapons.get_mut(n), int()) {
+                (Some(weapon), Some(v)) => {
+                    let v = v.max(0) as usize;
+                    match key {
+                        "ammo type" => weapon.ammo = v,
+                        "deselect frame" => weapon.down_state = v,
+                        "select frame" => weapon.up_state = v,
+                        "bobbing frame" => weapon.ready_state = v,
+                        "shooting frame" => weapon.attack_state = v,
+                        "firing frame" => weapon.flash_state = v,
+                        _ => return self.warn(line, format!("unknown weapon field '{key}'")),
+                    }
+                    true
+                }
+                _ => false,
+            },
+            Block::Ammo(n) if n < info.max_ammo.len() => match (key, int()) {
+                ("max ammo", Some(v)) => {
+                    info.max_ammo[n] = v;
+                    true
+                }
+                ("per ammo", Some(v)) => {
+                    info.clip_ammo[n] = v;
+                    true
+                }
+                _ => false,
+            },
+            Block::Misc => int().is_some_and(|v| {
+                let misc = &mut info.misc;
+                match key {
+                    "initial health" => misc.initial_health = v,
+                    "initial bullets" => misc.initial_bullets = v,
+                    "max health" => misc.max_health = v,
+                    "max armor" => misc.max_armor = v,
+                    "green armor class" => misc.green_armor_class = v,
+                    "blue armor class" => misc.blue_armor_class = v,
+                    "max soulsphere" => misc.max_soulsphere = v,
+                    "soulsphere health" => misc.soulsphere_health = v,
+                    "megasphere health" => misc.megasphere_health = v,
+                    "god mode health" => misc.god_mode_health = v,
+                    "idfa armor" => misc.idfa_armor = v,
+                    "idfa armor class" => misc.idfa_armor_class = v,
+                    "idkfa armor" => misc.idkfa_armor = v,
+                    "idkfa armor class" => misc.idkfa_armor_class = v,
+                    "bfg cells/shot" => misc.bfg_cells_per_shot = v,
+                    // The original exe tested for these two magic numbers.
+                    "monsters infight" => misc.monsters_infight = v == 202,
+                    _ => return false,
+                }
+                true
+            }),
+            Block::Cheat => match CHEAT_NAMES.iter().find(|(name, _)| *name == key) {
+                Some(&(_, kind)) => {
+                    self.cheats.push((kind, value.to_string()));
+                    true
+                }
+                None => false,
+            },
+            Block::CodePointers => {
+                let frame = key
+                    .strip_prefix("frame")
+                    .and_then(|n| n.trim().parse::<usize>().ok());
+                let action = match value.to_ascii_uppercase().as_str() {
+                    "NULL" | "A_NULL" => Some(None),
+                    _ => Action::from_name(value).map(Some),
+                };
+                match (frame.and_then(|n| info.states.get_mut(n)), action) {
+                    (Some(state), Some(action)) => {
+                        state.action = action;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Block::Sprites => {
+                let old = key.to_ascii_uppercase();
+                match info.sprites.iter_mut().find(|sprite| **sprite == old) {
+                    Some(sprite) if value.len() == 4 => {
+                        *sprite = value.to_ascii_uppercase();
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Block::Ignored => true,
+            _ => false,
+        };
+        if !applied {
+            self.warn(line, format!("can't apply '{key} = {value}'"));
+        }
+    }
+
+    /// A `[STRINGS]` entry. Values continue on the next line after a trailing backslash and
+    /// `\n` stands for a line break. Returns the text after the entry.
+    fn bex_string<'a>(&mut self, key: &str, value: &str, mut rest: &'a str) -> &'a str {
+        let mut text = String::new();
+        let mut part = value;
+        while let Some(continued) = part.strip_suffix('\\') {
+            text.push_str(continued);
+            let (line, tail) = rest.split_once('\n').unwrap_or((rest, ""));
+            rest = tail;
+            part = line.trim();
+        }
+        text.push_str(part);
+        self.strings
+            .insert(key.to_ascii_uppercase(), text.replace("\\n", "\n"));
+        rest
+    }
+
+    fn par(&mut self, line: &str, number: usize) {
+        let values: Vec<i32> = line
+            .split_whitespace()
+            .skip(1)
+            .map_while(|n| n.parse().ok())
+            .collect();
+        let par = match values[..] {
+            [episode, map, seconds] => ParTime {
+                episode: Some(episode as u8),
+                map: map as u8,
+                seconds,
+            },
+            [map, seconds] => ParTime {
+                episode: None,
+                map: map as u8,
+                seconds,
+            },
+            _ => return self.warn(number, format!("bad par line '{line}'")),
+        };
+        self.pars.push(par);
+    }
+
+    /// `Text old new`: the next `old` characters are the original text and the `new` after
+    /// them the replacement, line breaks included. Sprite and sound names are replaced right
+    /// away.
+    fn text_block<'a>(
+        &mut self,
+        rest: &'a str,
+        old_len: usize,
+        new_len: usize,
+        info: &mut GameInfo,
+        line: usize,
+    ) -> &'a str {
+        let split = |text: &'a str, count: usize| {
+            let end = text
+                .char_indices()
+                .nth(count)
+                .map_or(text.len(), |(i, _)| i);
+            text.split_at(end)
+        };
+        let (old, tail) = split(rest, old_len);
+        let (new, tail) = split(tail, new_len);
+        if old.chars().count() != old_len || new.chars().count() != new_len {
+            self.warn(
+                line,
+                "text block runs past the end of the patch".to_string(),
+            );
+        }
+
+        let sprite = (old_len == 4 && new_len == 4)
+            .then(|| info.sprites.iter_mut().find(|s| s.as_str() == old))
+            .flatten();
+        if let Some(sprite) = sprite {
+            *sprite = new.to_string();
+        } else if let Some(sound) = info.sounds.iter_mut().find(|s| s.as_str() == old) {
+            *sound = new.to_string();
+        } else {
+            self.text.push((old.to_string(), new.to_string()));
+        }
+        tail
+    }
+}
+
+/// A `Bits` value: a number, or BEX flag names joined by `+`, `|`, commas or spaces.
+fn parse_bits(value: &str) -> Option<u32> {
+    if let Ok(number) = value.parse::<i64>() {
+        return Some(number as u32);
+    }
+    value
+        .split(|c: char| c == '+' || c == '|' || c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .try_fold(0, |bits, name| {
+            let name = name.to_ascii_uppercase();
+            let name = name.strip_prefix("MF_").unwrap_or(&name);
+            MOBJ_FLAG_NAMES
+                .iter()
+                .find(|(flag, _)| *flag == name)
+                .map(|(_, bit)| bits | bit)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::{MobjInfo, State, WeaponInfo, MF_COUNTKILL, MF_SHOOTABLE, MF_SOLID};
+
+    fn info() -> GameInfo {
+        GameInfo {
+            states: vec![
+                State::default(),
+                State {
+                    action: Some(Action::Look),
+                    ..State::default()
+                },
+                State {
+                    action: Some(Action::Chase),
+                    ..State::default()
+                },
+            ],
+            mobjs: vec![MobjInfo::default(); 2],
+            weapons: vec![WeaponInfo::default(); 2],
+            sprites: vec!["TROO".into(), "POSS".into()],
+            sounds: vec!["pistol".into()],
+            ..GameInfo::default()
+        }
+    }
+
+    #[test]
+    fn thing_frame_weapon_and_misc_edits() {
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        deh.load(
+            "Patch File for DeHackEd v3.0\r\nDoom version = 19\r\nPatch format = 6\r\n\r\n\
+             Thing 2 (Shotgun guy)\r\nHit points = 300\r\nSpeed = 12\r\nBits = 4194310\r\n\r\n\
+             Frame 1\r\nDuration = -1\r\nNext frame = 2\r\n\r\n\
+             Weapon 1 (Pistol)\r\nAmmo type = 0\r\nShooting frame = 2\r\n\r\n\
+             Ammo 1 (Shells)\r\nMax ammo = 100\r\n\r\n\
+             Misc 0\r\nInitial Health = 50\r\nMonsters Infight = 202\r\n",
+            &mut info,
+        );
+        assert!(deh.warnings.is_empty(), "{:?}", deh.warnings);
+        assert_eq!(info.mobjs[1].spawn_health, 300);
+        assert_eq!(info.mobjs[1].speed, 12);
+        assert_eq!(info.mobjs[1].flags, MF_SOLID | MF_SHOOTABLE | MF_COUNTKILL);
+        assert_eq!((info.states[1].tics, info.states[1].next_state), (-1, 2));
+        assert_eq!(info.weapons[1].attack_state, 2);
+        assert_eq!(info.max_ammo[1], 100);
+        assert_eq!(info.misc.initial_health, 50);
+        assert!(info.misc.monsters_infight);
+    }
+
+    #[test]
+    fn code_pointers_copy_original_actions() {
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        // Swap the two pointers: both copies read the unpatched table.
+        deh.load(
+            "Pointer 0 (Frame 1)\nCodep Frame = 2\n\nPointer 1 (Frame 2)\nCodep Frame = 1\n",
+            &mut info,
+        );
+        assert_eq!(info.states[1].action, Some(Action::Chase));
+        assert_eq!(info.states[2].action, Some(Action::Look));
+
+        deh.load(
+            "[CODEPTR]\nFRAME 0 = A_FaceTarget\nFrame 1 = NULL\n",
+            &mut info,
+        );
+        assert_eq!(info.states[0].action, Some(Action::FaceTarget));
+        assert_eq!(info.states[1].action, None);
+    }
+
+    #[test]
+    fn text_blocks_replace_sprites_and_collect_strings() {
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        deh.load(
+            "Text 4 4\nTROOBOSS\nText 6 4\npistolshot\nText 16 9\nDegreelessness!\nGod mode!\n",
+            &mut info,
+        );
+        assert_eq!(info.sprites[0], "BOSS");
+        assert_eq!(info.sounds[0], "shot");
+        assert_eq!(
+            deh.text,
+            vec![("Degreelessness!\n".to_string(), "God mode!".to_string())]
+        );
+    }
+
+    #[test]
+    fn bex_sections() {
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        deh.load(
+            "[STRINGS]\nGOTARMOR = Armor!\nE1TEXT = Once you beat \\\n  the big badasses\\n...\n\n\
+             [PARS]\npar 1 1 45\npar 7 120\n\n\
+             Thing 1\nBits = SOLID+SHOOTABLE|MF_COUNTKILL\n\n\
+             Cheat 0\nGod mode = iamgod\n",
+            &mut info,
+        );
+        assert!(deh.warnings.is_empty(), "{:?}", deh.warnings);
+        assert_eq!(deh.strings["GOTARMOR"], "Armor!");
+        assert_eq!(deh.strings["E1TEXT"], "Once you beat the big badasses\n...");
+        assert_eq!(
+            deh.pars,
+            vec![
+                ParTime {
+                    episode: Some(1),
+                    map: 1,
+                    seconds: 45
+                },
+                ParTime {
+                    episode: None,
+                    map: 7,
+                    seconds: 120
+                },
+            ]
+        );
+        assert_eq!(info.mobjs[0].flags, MF_SOLID | MF_SHOOTABLE | MF_COUNTKILL);
+        assert_eq!(deh.cheats, vec![(CheatKind::God, "iamgod".to_string())]);
+    }
+
+    #[test]
+    fn startup_applies_files_then_pwad_lumps_in_order() {
+        use crate::wad::file::{tests::build_wad, WadFile};
+
+        let patch = std::env::temp_dir().join(format!("doom-rs-{}.deh", std::process::id()));
+        fs::write(&patch, "Thing 1\nHit points = 10\nSpeed = 1\n").unwrap();
+        let mut wads = Wads::new();
+        let iwad = build_wad(b"IWAD", &[("DEHACKED", b"Thing 1\nSpeed = 99\n")]);
+        let pwad = build_wad(
+            b"PWAD",
+            &[("DEHACKED", b"Thing 1\nHit points = 20\nMass = x\n")],
+        );
+        wads.add(WadFile::parse(Path::new("freedoom2.wad"), &iwad).unwrap());
+        wads.add(WadFile::parse(Path::new("mod.wad"), &pwad).unwrap());
+
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        deh.load_startup_patches(std::slice::from_ref(&patch), &wads, false, &mut info)
+            .unwrap();
+        // The PWAD lump comes after the file, and the IWAD lump is ignored.
+        assert_eq!((info.mobjs[0].spawn_health, info.mobjs[0].speed), (20, 1));
+        assert_eq!(
+            deh.warnings,
+            vec!["mod.wad:DEHACKED: line 3: can't apply 'mass = x'"]
+        );
+
+        let mut info = self::info();
+        let mut deh = Dehacked::new(&info);
+        deh.load_startup_patches(std::slice::from_ref(&patch), &wads, true, &mut info)
+            .unwrap();
+        assert_eq!(info.mobjs[0].spawn_health, 10);
+        fs::remove_file(patch).unwrap();
+    }
+
+    #[test]
+    fn bad_lines_are_warnings() {
+        let mut info = info();
+        let mut deh = Dehacked::new(&info);
+        deh.load(
+            "Thing 99\nHit points = 5\n\nFrame 0\nColour = 3\nnonsense\n",
+            &mut info,
+        );
+        assert_eq!(deh.warnings.len(), 3);
+        assert_eq!(info, self::info());
+    }
+}
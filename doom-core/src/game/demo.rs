@@ -0,0 +1,773 @@
+//! LMP demos: reading, writing and the playback controls.
+//!
+//! A demo is a header with the game settings followed by one `TicCmd` per player in the game
+//! per tic, ended by a 0x80 byte. Doom 1.9 (version 109) wrote a 13 byte header and turns
+//! truncated to their high byte; 1.91's longtics (version 111) keeps all 16 bits. Demos from
+//! before 1.4 have no version byte at all and a 7 byte header.
+//!
+//! Vanilla leaves netgame chat out of demos. Ours keep it after the marker, where vanilla
+//! stops reading: `CHAT`, then six bytes per character sent, the tic as a little-endian
+//! `u32`, the player's slot in that tic and the character. Demos with no chat end at the
+//! marker as before.
+//!
+//! Playback can pause, step a tic at a time, run at a fraction or multiple of normal speed,
+//! and skip ahead to a tic. A fast-forwarded frame runs several tics and draws once, and a
+//! skip draws nothing until it arrives, so long demos get where they're going at the speed
+//! of the simulation alone.
+//!
+//! Playback and local play feed the game through the same `TicSource`, which is what lets a
+//! player join a demo: the join key, or the end of the demo with `demo_join_at_end`, swaps
+//! playback for local input on the next tic. With `-record` the recording starts as a copy of
+//! the tics played so far, so the new demo replays the old one and continues seamlessly.
+//!
+//! In a netgame the tic source is also where the network reports peers dropping out and
+//! coming back, queued for the game loop, and where the loop leaves the answers to send.
+
+use super::dropin::{Handshake, PeerEvent};
+use super::ticcmd::TicCmd;
+use super::Skill;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::wad::file::Wads;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const MAXPLAYERS: usize = 4;
+pub const DEMOMARKER: u8 = 0x80;
+pub const VERSION: u8 = 109;
+pub const VERSION_LONGTICS: u8 = 111;
+const CHAT_FOOTER: &[u8; 4] = b"CHAT";
+
+/// Playback speeds in percent of normal, slowest first.
+pub const SPEEDS: [i32; 8] = [25, 50, 100, 200, 400, 800, 1600, 3200];
+const NORMAL_SPEED: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DemoHeader {
+    pub version: Option<u8>, // None for the headers from before 1.4.
+    pub skill: Skill,
+    pub episode: u8,
+    pub map: u8,
+    pub deathmatch: u8, // 2 is altdeath.
+    pub respawn: bool,
+    pub fast: bool,
+    pub nomonsters: bool,
+    pub console_player: usize,
+    pub in_game: [bool; MAXPLAYERS],
+}
+
+impl Default for DemoHeader {
+    fn default() -> Self {
+        Self {
+            version: Some(VERSION),
+            skill: Skill::default(),
+            episode: 1,
+            map: 1,
+            deathmatch: 0,
+            respawn: false,
+            fast: false,
+            nomonsters: false,
+            console_player: 0,
+            in_game: [true, false, false, false],
+        }
+    }
+}
+
+impl DemoHeader {
+    pub fn longtics(&self) -> bool {
+        self.version == Some(VERSION_LONGTICS)
+    }
+
+    pub fn players(&self) -> usize {
+        self.in_game.iter().filter(|&&in_game| in_game).count()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DemoError {
+    UnknownVersion(u8),
+    Truncated,
+    NoPlayers,
+}
+
+impl fmt::Display for DemoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DemoError::UnknownVersion(version) => write!(f, "unknown demo version {version}"),
+            DemoError::Truncated => write!(f, "demo ends in the middle of a tic"),
+            DemoError::NoPlayers => write!(f, "demo has no players"),
+        }
+    }
+}
+
+impl std::error::Error for DemoError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Demo {
+    pub header: DemoHeader,
+    pub tics: Vec<Vec<TicCmd>>, // One command per player in the game, in player order.
+}
+
+impl Demo {
+    pub fn parse(bytes: &[u8]) -> Result<Self, DemoError> {
+        let first = *bytes.first().ok_or(DemoError::Truncated)?;
+        let (version, settings) = match first {
+            0..=4 => (None, bytes),
+            104..=VERSION | VERSION_LONGTICS => (Some(first), &bytes[1..]),
+            version => return Err(DemoError::UnknownVersion(version)),
+        };
+        let header_len = if version.is_some() { 12 } else { 7 };
+        let settings = settings.get(..header_len).ok_or(DemoError::Truncated)?;
+        let byte = |i: usize| settings[i];
+        let header = match version {
+            Some(_) => DemoHeader {
+                version,
+                skill: byte(0)
+                    .checked_add(1)
+                    .and_then(Skill::from_number)
+                    .unwrap_or_default(),
+                episode: byte(1),
+                map: byte(2),
+                deathmatch: byte(3),
+                respawn: byte(4) != 0,
+                fast: byte(5) != 0,
+                nomonsters: byte(6) != 0,
+                console_player: usize::from(byte(7)) % MAXPLAYERS,
+                in_game: [byte(8) != 0, byte(9) != 0, byte(10) != 0, byte(11) != 0],
+            },
+            None => DemoHeader {
+                version,
+                skill: byte(0)
+                    .checked_add(1)
+                    .and_then(Skill::from_number)
+                    .unwrap_or_default(),
+                episode: byte(1),
+                map: byte(2),
+                in_game: [byte(3) != 0, byte(4) != 0, byte(5) != 0, byte(6) != 0],
+                ..DemoHeader::default()
+            },
+        };
+        if header.players() == 0 {
+            return Err(DemoError::NoPlayers);
+        }
+
+        let cmd_len = if header.longtics() { 5 } else { 4 };
+        let mut data = &bytes[usize::from(version.is_some()) + header_len..];
+        let mut tics: Vec<Vec<TicCmd>> = Vec::new();
+        while data.first().is_some_and(|&b| b != DEMOMARKER) {
+            let size = cmd_len * header.players();
+            let tic = data.get(..size).ok_or(DemoError::Truncated)?;
+            tics.push(tic.chunks(cmd_len).map(decode_cmd).collect());
+            data = &data[size..];
+        }
+        let footer = data
+            .get(1..)
+            .and_then(|rest| rest.strip_prefix(CHAT_FOOTER));
+        for record in footer.unwrap_or_default().chunks_exact(6) {
+            let tic = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+            let cmd = tics
+                .get_mut(tic as usize)
+                .and_then(|cmds| cmds.get_mut(usize::from(record[4])));
+            if let Some(cmd) = cmd {
+                cmd.chatchar = record[5];
+            }
+        }
+        Ok(Self { header, tics })
+    }
+
+    /// The demo as vanilla writes it, marker included, and the chat after it if any.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let header = &self.header;
+        let mut bytes = Vec::new();
+        if let Some(version) = header.version {
+            bytes.push(version);
+        }
+        bytes.extend([header.skill as u8, header.episode, header.map]);
+        if header.version.is_some() {
+            bytes.extend([
+                header.deathmatch,
+                header.respawn as u8,
+                header.fast as u8,
+                header.nomonsters as u8,
+                header.console_player as u8,
+            ]);
+        }
+        bytes.extend(header.in_game.map(u8::from));
+        for cmd in self.tics.iter().flatten() {
+            encode_cmd(cmd, header.longtics(), &mut bytes);
+        }
+        bytes.push(DEMOMARKER);
+        let mut chat = Vec::new();
+        for (tic, cmds) in self.tics.iter().enumerate() {
+            for (slot, cmd) in cmds.iter().enumerate() {
+                if cmd.chatchar != 0 {
+                    chat.extend((tic as u32).to_le_bytes());
+                    chat.extend([slot as u8, cmd.chatchar]);
+                }
+            }
+        }
+        if !chat.is_empty() {
+            bytes.extend(CHAT_FOOTER);
+            bytes.extend(chat);
+        }
+        bytes
+    }
+
+    pub fn duration(&self) -> usize {
+        self.tics.len()
+    }
+}
+
+/// The demo `-playdemo` names: a lump of that name if there is one, as in vanilla, or else a
+/// file, with `.lmp` added when the name has no extension.
+pub fn read(name: &str, wads: &Wads) -> io::Result<Vec<u8>> {
+    if let Some(lump) = wads.lump(name).filter(|_| name.len() <= 8) {
+        return Ok(lump.data.clone());
+    }
+    let path = Path::new(name);
+    match path.extension() {
+        Some(_) => fs::read(path),
+        None => fs::read(path.with_extension("lmp")),
+    }
+}
+
+/// Writes a recording where `-record` says: `name.lmp`, as `G_RecordDemo` names it. Returns
+/// the path written.
+pub fn write(name: &str, demo: &Demo) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!("{name}.lmp"));
+    fs::write(&path, demo.to_bytes())?;
+    Ok(path)
+}
+
+fn decode_cmd(bytes: &[u8]) -> TicCmd {
+    let angleturn = match bytes.len() {
+        5 => i16::from_le_bytes([bytes[2], bytes[3]]),
+        _ => i16::from(bytes[2] as i8) << 8,
+    };
+    TicCmd {
+        forwardmove: bytes[0] as i8,
+        sidemove: bytes[1] as i8,
+        angleturn,
+        buttons: bytes[bytes.len() - 1],
+        ..TicCmd::default()
+    }
+}
+
+/// `G_WriteDemoTiccmd`. Short tics keep the high byte of the turn, rounded, as vanilla does.
+fn encode_cmd(cmd: &TicCmd, longtics: bool, bytes: &mut Vec<u8>) {
+    bytes.extend([cmd.forwardmove as u8, cmd.sidemove as u8]);
+    match longtics {
+        true => bytes.extend(cmd.angleturn.to_le_bytes()),
+        false => bytes.push((cmd.angleturn.wrapping_add(128) >> 8) as u8),
+    }
+    bytes.push(cmd.buttons);
+}
+
+/// What the game loop should do this frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlaybackFrame {
+    pub tics: i32,
+    pub render: bool, // False while skipping ahead.
+}
+
+#[derive(Clone, Debug)]
+pub struct DemoPlayback {
+    pub demo: Demo,
+    position: usize,
+    paused: bool,
+    step: bool,
+    speed: usize,   // Index into `SPEEDS`.
+    remainder: i32, // Percent of a tic carried over between frames.
+    skip_to: Option<usize>,
+}
+
+impl DemoPlayback {
+    pub fn new(demo: Demo) -> Self {
+        Self {
+            demo,
+            position: 0,
+            paused: false,
+            step: false,
+            speed: NORMAL_SPEED,
+            remainder: 0,
+            skip_to: None,
+        }
+    }
+
+    /// The tic the next command comes from.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position >= self.demo.tics.len()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> i32 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn skipping(&self) -> bool {
+        self.skip_to.is_some()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.remainder = 0;
+    }
+
+    /// Runs a single tic on the next frame, pausing if playing.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step = true;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// `-skiptic`: plays on without drawing until `tic`. Only forwards, since going back
+    /// means replaying from the start.
+    pub fn skip_to(&mut self, tic: usize) {
+        self.skip_to = (tic > self.position).then_some(tic);
+    }
+
+    /// How many tics to run for `realtics` of wall clock time, and whether to draw.
+    pub fn frame(&mut self, realtics: i32) -> PlaybackFrame {
+        if let Some(target) = self.skip_to {
+            if target > self.position && !self.finished() {
+                let tics = (target - self.position) as i32;
+                return PlaybackFrame {
+                    tics,
+                    render: false,
+                };
+            }
+            self.skip_to = None;
+        }
+        let tics = if self.paused {
+            std::mem::take(&mut self.step) as i32
+        } else {
+            self.remainder += realtics * self.speed();
+            let tics = self.remainder / 100;
+            self.remainder %= 100;
+            tics
+        };
+        PlaybackFrame { tics, render: true }
+    }
+
+    /// The commands for the next tic, or `None` once the demo is over.
+    pub fn next_tic(&mut self) -> Option<&[TicCmd]> {
+        let tic = self.demo.tics.get(self.position)?;
+        self.position += 1;
+        if self.skip_to.is_some_and(|target| self.position >= target) {
+            self.skip_to = None;
+        }
+        Some(tic)
+    }
+}
+
+/// Collects the commands of a game being recorded.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DemoRecorder {
+    pub demo: Demo,
+}
+
+impl DemoRecorder {
+    pub fn new(header: DemoHeader) -> Self {
+        Self {
+            demo: Demo {
+                header,
+                tics: Vec::new(),
+            },
+        }
+    }
+
+    /// A recording that starts with what has been played back so far.
+    pub fn continuing(playback: &DemoPlayback) -> Self {
+        Self {
+            demo: Demo {
+                header: playback.demo.header,
+                tics: playback.demo.tics[..playback.position].to_vec(),
+            },
+        }
+    }
+
+    pub fn record(&mut self, cmds: &[TicCmd]) {
+        self.demo.tics.push(cmds.to_vec());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JoinOptions {
+    pub at_end: bool, // Take over when playback ends instead of stopping.
+}
+
+impl Configurable for JoinOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("demo_join_at_end", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            at_end: config.get_bool("demo_join_at_end"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("demo_join_at_end", self.at_end);
+    }
+}
+
+/// Where each tic's commands come from: a demo being played back, or the players' input.
+/// Whatever comes out is recorded if a recording is running.
+#[derive(Clone, Debug, Default)]
+pub struct TicSource {
+    pub playback: Option<DemoPlayback>,
+    pub recorder: Option<DemoRecorder>,
+    pub join: JoinOptions,
+    record_on_join: bool, // `-record` given with `-playdemo`: the recording starts on join.
+    /// The netgame's id, which a peer asking to rejoin has to give.
+    pub session: u32,
+    peer_events: Vec<PeerEvent>,
+    /// Handshake answers for the network to send.
+    pub replies: Vec<Handshake>,
+}
+
+impl TicSource {
+    /// Local play, recording if `recorder` is given.
+    pub fn local(recorder: Option<DemoRecorder>) -> Self {
+        Self {
+            recorder,
+            ..Self::default()
+        }
+    }
+
+    /// Demo playback. With `record_on_join` the game is recorded from the demo's start once
+    /// a player joins.
+    pub fn playback(playback: DemoPlayback, join: JoinOptions, record_on_join: bool) -> Self {
+        Self {
+            playback: Some(playback),
+            join,
+            record_on_join,
+            ..Self::default()
+        }
+    }
+
+    /// The network reports a peer leaving, coming back or asking to.
+    pub fn peer_event(&mut self, event: PeerEvent) {
+        self.peer_events.push(event);
+    }
+
+    /// What happened to the peers since the last call.
+    pub fn take_peer_events(&mut self) -> Vec<PeerEvent> {
+        std::mem::take(&mut self.peer_events)
+    }
+
+    pub fn playing_back(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// The join key: from the next tic the console player's input drives the game. Other
+    /// players in the demo stand still, having nobody to play them.
+    pub fn join(&mut self) {
+        let Some(playback) = self.playback.take() else {
+            return;
+        };
+        if self.record_on_join {
+            self.recorder = Some(DemoRecorder::continuing(&playback));
+        }
+    }
+
+    /// The commands for the next tic, one per player in the game. `local` is the console
+    /// player's input. Returns `None` when a demo ends without being joined.
+    pub fn tic(&mut self, local: TicCmd) -> Option<Vec<TicCmd>> {
+        if let Some(playback) = &mut self.playback {
+            if let Some(cmds) = playback.next_tic() {
+                let cmds = cmds.to_vec();
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.record(&cmds);
+                }
+                return Some(cmds);
+            }
+            if !self.join.at_end {
+                return None;
+            }
+            self.join();
+        }
+        let header = self.recorder.as_ref().map(|recorder| recorder.demo.header);
+        let header = header.unwrap_or_default();
+        let mut cmds = vec![TicCmd::default(); header.players()];
+        let console = header.in_game[..header.console_player]
+            .iter()
+            .filter(|&&in_game| in_game)
+            .count();
+        if let Some(cmd) = cmds.get_mut(console) {
+            *cmd = local;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&cmds);
+        }
+        Some(cmds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::TICRATE;
+    use crate::util::prop::{check, ensure, Gen};
+
+    fn demo(longtics: bool) -> Demo {
+        let cmd = |n: i8| TicCmd {
+            forwardmove: n,
+            sidemove: -n,
+            angleturn: if longtics { 0x1234 } else { 0x1200 },
+            buttons: 1,
+            ..TicCmd::default()
+        };
+        Demo {
+            header: DemoHeader {
+                version: Some(if longtics { VERSION_LONGTICS } else { VERSION }),
+                skill: Skill::Hard,
+                map: 7,
+                in_game: [true, true, false, false],
+                ..DemoHeader::default()
+            },
+            tics: (0..TICRATE as i8 * 3)
+                .map(|n| vec![cmd(n), cmd(-n)])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn demos_round_trip_in_both_formats() {
+        for longtics in [false, true] {
+            let demo = demo(longtics);
+            let bytes = demo.to_bytes();
+            assert_eq!(
+                bytes.len(),
+                13 + demo.tics.len() * 2 * (4 + longtics as usize) + 1
+            );
+            assert_eq!(bytes[1], 3);
+            assert_eq!(Demo::parse(&bytes), Ok(demo));
+        }
+        assert_eq!(
+            Demo::parse(&[110, 2, 1, 1]),
+            Err(DemoError::UnknownVersion(110))
+        );
+        let bytes = demo(false).to_bytes();
+        assert_eq!(
+            Demo::parse(&bytes[..bytes.len() - 3]),
+            Err(DemoError::Truncated)
+        );
+        // A skill byte past nightmare plays at the default skill instead of overflowing.
+        let mut bytes = demo(false).to_bytes();
+        bytes[1] = 255;
+        assert_eq!(Demo::parse(&bytes).unwrap().header.skill, Skill::default());
+    }
+
+    #[test]
+    fn chat_is_kept_after_the_marker() {
+        let mut demo = demo(false);
+        let plain = demo.to_bytes();
+        demo.tics[5][1].chatchar = b'H';
+        demo.tics[6][1].chatchar = 13;
+        let bytes = demo.to_bytes();
+        assert_eq!(
+            bytes[..plain.len()],
+            plain[..],
+            "vanilla reads it as before"
+        );
+        assert_eq!(&bytes[plain.len()..plain.len() + 4], b"CHAT");
+        assert_eq!(bytes.len(), plain.len() + 4 + 2 * 6);
+        assert_eq!(Demo::parse(&bytes), Ok(demo));
+    }
+
+    #[test]
+    fn any_demo_survives_a_round_trip() {
+        check("demo round trip", 200, |gen| {
+            let longtics = gen.bool();
+            let mut in_game = [gen.bool(), gen.bool(), gen.bool(), gen.bool()];
+            in_game[gen.range(0, 3) as usize] = true;
+            let header = DemoHeader {
+                version: Some(if longtics { VERSION_LONGTICS } else { VERSION }),
+                episode: gen.range(1, 4) as u8,
+                map: gen.range(1, 32) as u8,
+                in_game,
+                ..DemoHeader::default()
+            };
+            let cmd = |gen: &mut Gen| TicCmd {
+                // -128 is the end-of-demo marker when it is the first byte of a tic.
+                forwardmove: gen.range(-127, 127) as i8,
+                sidemove: gen.i32() as i8,
+                angleturn: gen.i32() as i16,
+                buttons: gen.i32() as u8,
+                ..TicCmd::default()
+            };
+            let tics: Vec<Vec<TicCmd>> = (0..gen.range(0, 100))
+                .map(|_| (0..header.players()).map(|_| cmd(gen)).collect())
+                .collect();
+            let demo = Demo { header, tics };
+            let parsed = Demo::parse(&demo.to_bytes()).map_err(|error| error.to_string())?;
+            ensure(parsed.header == demo.header, || {
+                format!("{:?}", parsed.header)
+            })?;
+            for (tic, (got, sent)) in parsed.tics.iter().zip(&demo.tics).enumerate() {
+                for (got, sent) in got.iter().zip(sent) {
+                    let turn = match longtics {
+                        true => sent.angleturn,
+                        false => (sent.angleturn.wrapping_add(128) >> 8) << 8,
+                    };
+                    let expected = TicCmd {
+                        angleturn: turn,
+                        ..*sent
+                    };
+                    ensure(*got == expected, || {
+                        format!("tic {tic}: {got:?} for {sent:?}")
+                    })?;
+                }
+            }
+            ensure(parsed.duration() == demo.duration(), || {
+                "duration".to_string()
+            })
+        });
+    }
+
+    #[test]
+    fn old_headers_and_short_turns() {
+        let demo = Demo::parse(&[2, 1, 3, 1, 0, 0, 0, 10, 0, 0x80, 1, 0x80]).unwrap();
+        assert_eq!(demo.header.version, None);
+        assert_eq!((demo.header.episode, demo.header.map), (1, 3));
+        assert_eq!(demo.header.skill, Skill::Medium);
+        assert_eq!(demo.tics[0][0].angleturn, -0x8000);
+
+        let mut bytes = Vec::new();
+        let turn = TicCmd {
+            angleturn: 0x12c0,
+            ..TicCmd::default()
+        };
+        encode_cmd(&turn, false, &mut bytes);
+        assert_eq!(bytes[2], 0x13);
+    }
+
+    #[test]
+    fn speed_pause_and_step() {
+        let mut playback = DemoPlayback::new(demo(false));
+        assert_eq!(playback.frame(1).tics, 1);
+        playback.slower();
+        assert_eq!(playback.frame(1).tics, 0);
+        assert_eq!(playback.frame(1).tics, 1);
+        playback.faster();
+        playback.faster();
+        playback.faster();
+        assert_eq!(playback.speed(), 400);
+        assert_eq!(
+            playback.frame(2),
+            PlaybackFrame {
+                tics: 8,
+                render: true
+            }
+        );
+
+        playback.toggle_pause();
+        assert_eq!(playback.frame(5).tics, 0);
+        playback.step();
+        assert_eq!(playback.frame(5).tics, 1);
+        assert_eq!(playback.frame(5).tics, 0);
+        assert!(playback.paused());
+    }
+
+    #[test]
+    fn joining_splices_local_input_onto_the_played_tics() {
+        let local = TicCmd {
+            forwardmove: 50,
+            ..TicCmd::default()
+        };
+        let played = demo(false);
+        let mut source = TicSource::playback(
+            DemoPlayback::new(played.clone()),
+            JoinOptions::default(),
+            true,
+        );
+        for tic in &played.tics[..10] {
+            assert_eq!(source.tic(local).as_ref(), Some(tic));
+        }
+        source.join();
+        assert!(!source.playing_back());
+        assert_eq!(source.tic(local).unwrap(), vec![local, TicCmd::default()]);
+        let recorded = &source.recorder.as_ref().unwrap().demo;
+        assert_eq!(recorded.header, played.header);
+        assert_eq!(recorded.tics[..10], played.tics[..10]);
+        assert_eq!(recorded.tics.len(), 11);
+
+        // Without join_at_end, the end of the demo is the end of the game.
+        let mut source = TicSource::playback(
+            DemoPlayback::new(played.clone()),
+            JoinOptions::default(),
+            false,
+        );
+        while source.tic(local).is_some() {}
+        let mut source = TicSource::playback(
+            DemoPlayback::new(played.clone()),
+            JoinOptions { at_end: true },
+            false,
+        );
+        for _ in 0..played.duration() {
+            source.tic(local);
+        }
+        assert_eq!(source.tic(local).unwrap()[0], local);
+        assert!(source.recorder.is_none());
+    }
+
+    #[test]
+    fn recordings_are_written_where_playdemo_finds_them() {
+        let name = std::env::temp_dir().join(format!("doom-rs-demo-{}", std::process::id()));
+        let name = name.to_str().unwrap();
+        let recorded = demo(true);
+        let path = write(name, &recorded).unwrap();
+        assert_eq!(path.extension().unwrap(), "lmp");
+        let bytes = read(name, &Wads::new()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(Demo::parse(&bytes).unwrap(), recorded);
+    }
+
+    #[test]
+    fn skipping_runs_without_drawing_until_the_target() {
+        let mut playback = DemoPlayback::new(demo(false));
+        playback.skip_to(2 * TICRATE as usize);
+        let frame = playback.frame(1);
+        assert_eq!(
+            frame,
+            PlaybackFrame {
+                tics: 70,
+                render: false
+            }
+        );
+        for _ in 0..frame.tics {
+            playback.next_tic().unwrap();
+        }
+        assert!(!playback.skipping());
+        assert_eq!(
+            playback.frame(1),
+            PlaybackFrame {
+                tics: 1,
+                render: true
+            }
+        );
+
+        playback.skip_to(1000);
+        while playback.next_tic().is_some() {}
+        assert!(playback.finished());
+        assert!(playback.frame(1).render);
+        assert!(!playback.skipping());
+    }
+}
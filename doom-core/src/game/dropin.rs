@@ -0,0 +1,347 @@
+//! Players leaving a netgame and coming back while it runs.
+//!
+//! Vanilla takes a player who quits out of `playeringame` and carries on with the others.
+//! Their mobj stays where it stood, thinking no more, a body that can still be shot and
+//! telefragged. With `net_keep_body` off it is removed instead, for deathmatches where a
+//! frozen target is a free frag.
+//!
+//! A player can come back into their old slot. The client sends `Handshake::Rejoin` with the
+//! session it was part of, and the server answers with a `Snapshot`: the tic it is at, who is
+//! in the game and the whole game state written by the savegame layer. The client loads
+//! that and runs tics from there like everyone else. Back in the game, a player whose body
+//! was kept steers it again; otherwise they spawn at their start as if reborn.
+//!
+//! The network layer tells the `TicSource` about the peers as `PeerEvent`s, and the game
+//! loop hands them on to the engine, which sends back the answers to rejoin requests.
+
+use super::demo::MAXPLAYERS;
+use super::info::GameInfo;
+use super::reborn::Player;
+use super::savegame::{Archive, SaveError, SaveReader, SaveWriter};
+use super::voodoo::PlayerBodies;
+use crate::engine::config::{Config, Configurable, Section};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DropOptions {
+    /// Leave a departed player's body standing, as vanilla does, rather than removing it.
+    pub keep_body: bool,
+}
+
+impl Default for DropOptions {
+    fn default() -> Self {
+        Self { keep_body: true }
+    }
+}
+
+impl Configurable for DropOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("net_keep_body", true, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            keep_body: config.get_bool("net_keep_body"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("net_keep_body", self.keep_body);
+    }
+}
+
+/// What the network says about a peer, for the game loop to act on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerEvent {
+    /// The peer playing `player` dropped out.
+    Left(usize),
+    /// A peer let back in has loaded the snapshot and plays `player` again.
+    Joined(usize),
+    /// A peer asks for its slot back, with the session it was part of.
+    Rejoin { session: u32, player: u8 },
+}
+
+/// What became of a departed player's body, for the caller to do to the mobj.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Departure {
+    /// Left standing; nothing steers it until the player is back.
+    Idle { body: usize },
+    /// Taken out of `PlayerBodies`; the caller removes the mobj.
+    Removed { body: usize },
+    /// The player had no body, being dead and reborn or in a deathmatch between spawns.
+    NoBody,
+}
+
+/// Takes `player` out of the game.
+pub fn player_left(
+    player: usize,
+    in_game: &mut [bool; MAXPLAYERS],
+    bodies: &mut PlayerBodies,
+    options: &DropOptions,
+) -> Departure {
+    in_game[player] = false;
+    match bodies.mo[player] {
+        None => Departure::NoBody,
+        Some(body) if options.keep_body => Departure::Idle { body },
+        Some(body) => {
+            bodies.remove(body);
+            Departure::Removed { body }
+        }
+    }
+}
+
+/// How a player came back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arrival {
+    /// Steering the body they left behind.
+    Resumed { body: usize },
+    /// A new body at their start, with the inventory of a new life.
+    Spawned { body: usize, start: usize },
+}
+
+/// Puts `player` back in the game. Returns `None` if they have no body to go back to and
+/// the map no start for them.
+pub fn player_joined(
+    player: usize,
+    in_game: &mut [bool; MAXPLAYERS],
+    players: &mut [Player; MAXPLAYERS],
+    bodies: &mut PlayerBodies,
+    info: &GameInfo,
+) -> Option<Arrival> {
+    let arrival = match bodies.mo[player] {
+        Some(body) => Arrival::Resumed { body },
+        None => {
+            let start = bodies.starts[player]?;
+            players[player].reborn(info);
+            let body = bodies.spawn_player(player, start, &players[player].inventory);
+            Arrival::Spawned { body, start }
+        }
+    };
+    in_game[player] = true;
+    Some(arrival)
+}
+
+/// The game as the server has it, for a player coming back.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub gametic: u32,
+    pub in_game: [bool; MAXPLAYERS],
+    /// The slot the player gets back.
+    pub player: u8,
+    /// The game state, as a savegame writes it.
+    pub state: Vec<u8>,
+}
+
+/// Why the server turned a player away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Refusal {
+    /// The client was in another game, or one since restarted.
+    WrongSession,
+    /// Somebody is playing in that slot.
+    SlotTaken,
+    NoSuchSlot,
+}
+
+impl Refusal {
+    pub fn message(self) -> &'static str {
+        match self {
+            Refusal::WrongSession => "that game has ended",
+            Refusal::SlotTaken => "that player is still in the game",
+            Refusal::NoSuchSlot => "no such player",
+        }
+    }
+}
+
+/// The messages of the reconnect handshake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Handshake {
+    Rejoin { session: u32, player: u8 },
+    Snapshot(Snapshot),
+    Refused(Refusal),
+}
+
+/// The server's answer to `Rejoin`, calling `state` for the game state only when the player
+/// is let back in.
+pub fn answer_rejoin(
+    request: (u32, u8),
+    session: u32,
+    gametic: u32,
+    in_game: &[bool; MAXPLAYERS],
+    state: impl FnOnce() -> Vec<u8>,
+) -> Handshake {
+    let (asked_session, player) = request;
+    let refusal = match in_game.get(usize::from(player)) {
+        _ if asked_session != session => Refusal::WrongSession,
+        None => Refusal::NoSuchSlot,
+        Some(true) => Refusal::SlotTaken,
+        Some(false) => {
+            return Handshake::Snapshot(Snapshot {
+                gametic,
+                in_game: *in_game,
+                player,
+                state: state(),
+            })
+        }
+    };
+    Handshake::Refused(refusal)
+}
+
+impl Archive for Handshake {
+    fn archive(&self, writer: &mut SaveWriter) {
+        match self {
+            Handshake::Rejoin { session, player } => {
+                writer.write_u8(0);
+                writer.write_u32(*session);
+                writer.write_u8(*player);
+            }
+            Handshake::Snapshot(snapshot) => {
+                writer.write_u8(1);
+                writer.write_u32(snapshot.gametic);
+                for &playing in &snapshot.in_game {
+                    writer.write_bool(playing);
+                }
+                writer.write_u8(snapshot.player);
+                writer.write_u32(snapshot.state.len() as u32);
+                writer.write_bytes(&snapshot.state);
+            }
+            Handshake::Refused(refusal) => {
+                writer.write_u8(2);
+                writer.write_u8(*refusal as u8);
+            }
+        }
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        match reader.read_u8()? {
+            0 => Ok(Handshake::Rejoin {
+                session: reader.read_u32()?,
+                player: reader.read_u8()?,
+            }),
+            1 => {
+                let gametic = reader.read_u32()?;
+                let mut in_game = [false; MAXPLAYERS];
+                for playing in &mut in_game {
+                    *playing = reader.read_bool()?;
+                }
+                let player = reader.read_u8()?;
+                let len = reader.read_u32()? as usize;
+                Ok(Handshake::Snapshot(Snapshot {
+                    gametic,
+                    in_game,
+                    player,
+                    state: reader.read_bytes(len)?.to_vec(),
+                }))
+            }
+            2 => match reader.read_u8()? {
+                0 => Ok(Handshake::Refused(Refusal::WrongSession)),
+                1 => Ok(Handshake::Refused(Refusal::SlotTaken)),
+                2 => Ok(Handshake::Refused(Refusal::NoSuchSlot)),
+                other => Err(reader.error(format!("bad refusal {other}"))),
+            },
+            other => Err(reader.error(format!("bad handshake message {other}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::Inventory;
+    use crate::wad::map::Thing;
+
+    fn game(info: &GameInfo) -> ([Player; MAXPLAYERS], PlayerBodies) {
+        let start = |kind| Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind,
+            flags: 7,
+        };
+        let players = [Player::new(info); MAXPLAYERS];
+        let inventories = [Inventory::initial(info); MAXPLAYERS];
+        let things = [start(1), start(2), start(3)];
+        let bodies = PlayerBodies::spawn(&things, &[true, true, true, false], false, &inventories);
+        (players, bodies)
+    }
+
+    #[test]
+    fn departed_bodies_stay_idle_or_go() {
+        let info = GameInfo::default();
+        let (mut players, mut bodies) = game(&info);
+        let mut in_game = [true, true, true, false];
+        players[1].tally.kills = 4;
+
+        let keep = DropOptions::default();
+        assert_eq!(
+            player_left(1, &mut in_game, &mut bodies, &keep),
+            Departure::Idle { body: 1 }
+        );
+        assert_eq!(in_game, [true, false, true, false]);
+        let back = player_joined(1, &mut in_game, &mut players, &mut bodies, &info);
+        assert_eq!(back, Some(Arrival::Resumed { body: 1 }));
+        assert_eq!(players[1].tally.kills, 4);
+
+        let remove = DropOptions { keep_body: false };
+        let left = player_left(0, &mut in_game, &mut bodies, &remove);
+        assert_eq!(left, Departure::Removed { body: 0 });
+        assert_eq!(bodies.bodies.len(), 2);
+        assert_eq!(
+            bodies.mo[..3],
+            [None, Some(0), Some(1)],
+            "the others still line up"
+        );
+        let back = player_joined(0, &mut in_game, &mut players, &mut bodies, &info);
+        assert_eq!(back, Some(Arrival::Spawned { body: 2, start: 0 }));
+        assert_eq!(bodies.mo[0], Some(2));
+
+        // Player 4 has no start on this map.
+        assert_eq!(
+            player_left(3, &mut in_game, &mut bodies, &keep),
+            Departure::NoBody
+        );
+        assert_eq!(
+            player_joined(3, &mut in_game, &mut players, &mut bodies, &info),
+            None
+        );
+        assert!(!in_game[3]);
+    }
+
+    #[test]
+    fn only_empty_slots_of_the_same_session_are_given_back() {
+        let in_game = [true, false, true, false];
+        let state = || vec![1, 2, 3];
+        let answer = answer_rejoin((7, 1), 7, 350, &in_game, state);
+        assert_eq!(
+            answer,
+            Handshake::Snapshot(Snapshot {
+                gametic: 350,
+                in_game,
+                player: 1,
+                state: vec![1, 2, 3],
+            })
+        );
+        let refused = |request| match answer_rejoin(request, 7, 350, &in_game, || unreachable!()) {
+            Handshake::Refused(refusal) => refusal,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(refused((6, 1)), Refusal::WrongSession);
+        assert_eq!(refused((7, 0)), Refusal::SlotTaken);
+        assert_eq!(refused((7, 9)), Refusal::NoSuchSlot);
+    }
+
+    #[test]
+    fn handshake_messages_round_trip() {
+        let messages = [
+            Handshake::Rejoin {
+                session: 0xdead_beef,
+                player: 2,
+            },
+            answer_rejoin((1, 3), 1, 99, &[true, true, true, false], || vec![9; 40]),
+            Handshake::Refused(Refusal::SlotTaken),
+        ];
+        for message in messages {
+            assert_eq!(Handshake::from_bytes(&message.to_bytes()), Ok(message));
+        }
+        assert!(Handshake::from_bytes(&[3]).is_err());
+        assert!(Handshake::from_bytes(&[1, 0, 0, 0, 0, 1, 0, 0, 0, 2, 50, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! What the player has seen of the level, for the automap and the explored percentage.
+//!
+//! Vanilla marks a line `ML_MAPPED` when `R_StoreWallRange` draws a seg of it, and the
+//! automap shows only marked lines. That is kept, and every seg drawn also marks the sectors
+//! on both sides as seen. With `automap_reveal` set to `sectors`, seeing any wall of a sector
+//! maps all of it, so a room seen through a door shows up whole instead of as the walls that
+//! happened to be in view. The marks live in the line flags, so a savegame keeps them as it
+//! always has, and the seen sectors are rebuilt from them on load.
+
+use crate::engine::config::{Config, Configurable, Section};
+use crate::wad::map::{MapData, ML_DONTDRAW, ML_MAPPED, NO_SIDEDEF};
+
+/// The sector special that counts as a secret when it is entered.
+pub const SECRET_SECTOR: i16 = 9;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RevealMode {
+    /// Vanilla: the lines that were drawn.
+    #[default]
+    Lines,
+    /// Every line of a sector once any of it was drawn.
+    Sectors,
+}
+
+impl Configurable for RevealMode {
+    fn register(config: &mut Config) {
+        config.register_str("automap_reveal", "lines", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        match config.get_str("automap_reveal").as_str() {
+            "sectors" => RevealMode::Sectors,
+            _ => RevealMode::Lines,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        let name = match self {
+            RevealMode::Lines => "lines",
+            RevealMode::Sectors => "sectors",
+        };
+        config.set_str("automap_reveal", name);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Explored {
+    pub mode: RevealMode,
+    seen: Vec<bool>,
+    sector_lines: Vec<Vec<usize>>, // The lines with a side in each sector.
+}
+
+/// The sectors either side of `line`.
+fn sides(map: &MapData, line: usize) -> impl Iterator<Item = usize> + '_ {
+    let line = &map.linedefs[line];
+    [line.front, line.back]
+        .into_iter()
+        .filter(|&side| side != NO_SIDEDEF)
+        .map(|side| usize::from(map.sidedefs[usize::from(side)].sector))
+}
+
+impl Explored {
+    /// Starts tracking `map`, taking whatever its lines already have marked as seen.
+    pub fn new(map: &MapData, mode: RevealMode) -> Self {
+        let mut explored = Self {
+            mode,
+            seen: vec![false; map.sectors.len()],
+            sector_lines: vec![Vec::new(); map.sectors.len()],
+        };
+        for (index, line) in map.linedefs.iter().enumerate() {
+            for sector in sides(map, index) {
+                explored.sector_lines[sector].push(index);
+                if line.flags & ML_MAPPED != 0 {
+                    explored.seen[sector] = true;
+                }
+            }
+        }
+        explored
+    }
+
+    /// The renderer drew a seg of `line`.
+    pub fn seg_drawn(&mut self, map: &mut MapData, line: usize) {
+        if map.linedefs[line].flags & ML_MAPPED != 0 && self.mode == RevealMode::Lines {
+            return;
+        }
+        map.linedefs[line].flags |= ML_MAPPED;
+        let newly_seen: Vec<usize> = sides(map, line)
+            .filter(|&sector| !self.seen[sector])
+            .collect();
+        for sector in newly_seen {
+            self.seen[sector] = true;
+            if self.mode == RevealMode::Sectors {
+                for &index in &self.sector_lines[sector] {
+                    map.linedefs[index].flags |= ML_MAPPED;
+                }
+            }
+        }
+    }
+
+    pub fn sector_seen(&self, sector: usize) -> bool {
+        self.seen.get(sector).copied().unwrap_or(false)
+    }
+
+    /// How much of the map has been seen, as a percentage of the lines the automap can show.
+    pub fn percent(&self, map: &MapData) -> i32 {
+        let drawable = map.linedefs.iter().filter(|l| l.flags & ML_DONTDRAW == 0);
+        let (mapped, total) = drawable.fold((0, 0), |(mapped, total), line| {
+            (mapped + i32::from(line.flags & ML_MAPPED != 0), total + 1)
+        });
+        mapped * 100 / total.max(1)
+    }
+
+    /// Secret sectors that have been seen: the intermission can tell the secrets the player
+    /// walked past from the ones they never found.
+    pub fn secrets_seen(&self, map: &MapData) -> i32 {
+        let seen =
+            map.sectors.iter().enumerate().filter(|(index, sector)| {
+                sector.special == SECRET_SECTOR && self.sector_seen(*index)
+            });
+        seen.count() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::map::{LineDef, Sector, SideDef};
+
+    /// Two rooms and a secret closet: lines 0 and 1 belong to room 0, line 2 joins it to room
+    /// 1, lines 3 and 4 are room 1's, and line 5 joins room 1 to the closet, sector 2.
+    fn map() -> MapData {
+        let sector = |special| Sector {
+            floor_height: 0,
+            ceiling_height: 128,
+            floor_pic: String::new(),
+            ceiling_pic: String::new(),
+            light: 160,
+            special,
+            tag: 0,
+        };
+        let side = |sector| SideDef {
+            x_offset: 0,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: String::new(),
+            sector,
+        };
+        let line = |front, back, flags| LineDef {
+            v1: 0,
+            v2: 0,
+            flags,
+            special: 0,
+            tag: 0,
+            front,
+            back,
+        };
+        MapData {
+            linedefs: vec![
+                line(0, NO_SIDEDEF, 0),
+                line(0, NO_SIDEDEF, 0),
+                line(0, 1, 0),
+                line(1, NO_SIDEDEF, 0),
+                line(1, NO_SIDEDEF, ML_DONTDRAW),
+                line(1, 2, 0),
+            ],
+            sidedefs: vec![side(0), side(1), side(2)],
+            sectors: vec![sector(0), sector(0), sector(SECRET_SECTOR)],
+            ..MapData::default()
+        }
+    }
+
+    fn mapped(map: &MapData) -> Vec<usize> {
+        (0..map.linedefs.len())
+            .filter(|&i| map.linedefs[i].flags & ML_MAPPED != 0)
+            .collect()
+    }
+
+    #[test]
+    fn lines_mode_marks_what_was_drawn() {
+        let mut map = map();
+        let mut explored = Explored::new(&map, RevealMode::Lines);
+        explored.seg_drawn(&mut map, 2);
+        explored.seg_drawn(&mut map, 2);
+        assert_eq!(mapped(&map), [2]);
+        assert!(explored.sector_seen(0) && explored.sector_seen(1));
+        assert!(!explored.sector_seen(2));
+        assert_eq!(explored.percent(&map), 20);
+        explored.seg_drawn(&mut map, 5);
+        assert_eq!(explored.secrets_seen(&map), 1);
+
+        // A loaded game brings its marks with it.
+        let reloaded = Explored::new(&map, RevealMode::Lines);
+        assert!(reloaded.sector_seen(2));
+    }
+
+    #[test]
+    fn sectors_mode_maps_whole_rooms() {
+        let mut map = map();
+        let mut explored = Explored::new(&map, RevealMode::Sectors);
+        explored.seg_drawn(&mut map, 0);
+        assert_eq!(mapped(&map), [0, 1, 2]);
+        explored.seg_drawn(&mut map, 2);
+        assert_eq!(mapped(&map), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(explored.percent(&map), 100);
+        assert_eq!(explored.secrets_seen(&map), 0);
+
+        let mut config = Config::new();
+        RevealMode::register(&mut config);
+        assert_eq!(RevealMode::from_config(&config), RevealMode::Lines);
+        RevealMode::Sectors.to_config(&mut config);
+        assert_eq!(RevealMode::from_config(&config), RevealMode::Sectors);
+    }
+}
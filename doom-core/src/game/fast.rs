@@ -0,0 +1,135 @@
+//! Fast monsters: `-fast`, and always in Nightmare.
+//!
+//! Vanilla halved the demon and spectre frame durations and sped up three projectiles by
+//! editing the tables in place, then doubled the tics back on leaving Nightmare. That loses
+//! odd durations a DeHackEd patch set (3 becomes 1, then 2), so here the patched values are
+//! remembered when the game starts and each switch is computed from them.
+
+use crate::game::info::GameInfo;
+use crate::game::Skill;
+use crate::util::fixed::{to_fixed, Fixed};
+
+/// The demon frames from S_SARG_RUN1 to S_SARG_PAIN2, which the spectre shares.
+pub const FAST_STATES: std::ops::RangeInclusive<usize> = 477..=489;
+
+pub const MT_BRUISERSHOT: usize = 16;
+pub const MT_TROOPSHOT: usize = 31;
+pub const MT_HEADSHOT: usize = 32;
+
+/// The projectiles that speed up, and their fast speed.
+pub const FAST_PROJECTILES: [(usize, i32); 3] =
+    [(MT_BRUISERSHOT, 20), (MT_TROOPSHOT, 20), (MT_HEADSHOT, 20)];
+
+/// Whether monsters are fast. Also what `A_Chase` checks to skip turning after an attack and
+/// to attack again without waiting for `movecount`.
+pub fn fast_monsters(fast: bool, skill: Skill) -> bool {
+    fast || skill == Skill::Nightmare
+}
+
+pub struct FastMonsters {
+    tics: Vec<i32>,
+    speeds: [Fixed; FAST_PROJECTILES.len()],
+    active: bool,
+}
+
+impl FastMonsters {
+    /// Remembers the normal speed values. Call after DeHackEd patches are applied.
+    pub fn new(info: &GameInfo) -> Self {
+        let tics = FAST_STATES
+            .filter_map(|i| info.states.get(i).map(|state| state.tics))
+            .collect();
+        let speeds =
+            FAST_PROJECTILES.map(|(mobj, _)| info.mobjs.get(mobj).map_or(0, |mobj| mobj.speed));
+        Self {
+            tics,
+            speeds,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Switches the tables to fast or normal, as `G_InitNew` does for each new game.
+    pub fn set(&mut self, info: &mut GameInfo, fast: bool) {
+        if fast == self.active {
+            return;
+        }
+        self.active = fast;
+        for (state, &tics) in info
+            .states
+            .iter_mut()
+            .skip(*FAST_STATES.start())
+            .zip(&self.tics)
+        {
+            // Halving keeps -1 (forever) as -1.
+            state.tics = if fast { tics >> 1 } else { tics };
+        }
+        for (&(mobj, speed), &normal) in FAST_PROJECTILES.iter().zip(&self.speeds) {
+            if let Some(mobj) = info.mobjs.get_mut(mobj) {
+                mobj.speed = if fast { to_fixed(speed) } else { normal };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::{MobjInfo, State};
+
+    fn info() -> GameInfo {
+        GameInfo {
+            states: vec![
+                State {
+                    tics: 3,
+                    ..State::default()
+                };
+                500
+            ],
+            mobjs: vec![
+                MobjInfo {
+                    speed: to_fixed(10),
+                    ..MobjInfo::default()
+                };
+                40
+            ],
+            ..GameInfo::default()
+        }
+    }
+
+    #[test]
+    fn nightmare_is_always_fast() {
+        assert!(fast_monsters(false, Skill::Nightmare));
+        assert!(fast_monsters(true, Skill::Easy));
+        assert!(!fast_monsters(false, Skill::Hard));
+    }
+
+    #[test]
+    fn switching_restores_patched_values_exactly() {
+        let mut info = info();
+        info.states[480].tics = 7; // As a DeHackEd patch might set it.
+        let mut fast = FastMonsters::new(&info);
+        fast.set(&mut info, true);
+        assert_eq!(info.states[477].tics, 1);
+        assert_eq!(info.states[480].tics, 3);
+        assert_eq!(info.states[476].tics, 3);
+        assert_eq!(info.states[490].tics, 3);
+        assert_eq!(info.mobjs[MT_TROOPSHOT].speed, to_fixed(20));
+        assert_eq!(info.mobjs[MT_TROOPSHOT + 2].speed, to_fixed(10));
+
+        fast.set(&mut info, false);
+        assert_eq!(info.states[480].tics, 7);
+        assert_eq!(info.states[477].tics, 3);
+        assert_eq!(info.mobjs[MT_BRUISERSHOT].speed, to_fixed(10));
+    }
+
+    #[test]
+    fn missing_tables_are_left_alone() {
+        let mut info = GameInfo::default();
+        let mut fast = FastMonsters::new(&info);
+        fast.set(&mut info, true);
+        assert!(fast.is_active() && info.states.is_empty());
+    }
+}
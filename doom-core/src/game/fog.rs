@@ -0,0 +1,255 @@
+//! Teleport and item fog: the flashes where things teleport, respawn or appear in deathmatch.
+//!
+//! Fog is a mobj that is nothing but frames and a sound. Its info has `MF_NOBLOCKMAP`, so it
+//! is never linked into the blockmap and nothing can bump into it, shoot it or pick it up.
+//! `effect_only` tells such mobjs apart, so spawning them can skip the collision setup. Each
+//! function here is one place vanilla spawns fog, and says where it goes and what it sounds
+//! like. Heights are given outright rather than as `ONFLOORZ`, as vanilla passes them.
+
+use super::compatibility::Compatibility;
+use super::info::{MobjInfo, MF_NOBLOCKMAP, MF_SHOOTABLE, MF_SOLID, MF_SPECIAL};
+use super::{Trig, TICRATE};
+use crate::util::angle::{Angle, ANG45};
+use crate::util::fixed::Fixed;
+
+pub const MT_TFOG: usize = 39;
+pub const MT_IFOG: usize = 40;
+
+/// How far in front of an arrival the teleport fog appears, multiplied straight onto the
+/// table values.
+const FOG_DISTANCE: Fixed = 20;
+const TELEPORT_SOUND: &str = "telept";
+const ITEM_SOUND: &str = "itmbk";
+/// The `sfxinfo` priorities of those.
+const TELEPORT_PRIORITY: i32 = 32;
+const ITEM_PRIORITY: i32 = 100;
+/// How long a picked up item stays gone in `-altdeath`.
+pub const ITEM_RESPAWN_TICS: i32 = 30 * TICRATE;
+
+/// A fog mobj to spawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fog {
+    /// `MT_TFOG` or `MT_IFOG`.
+    pub kind: usize,
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+    /// The sound it starts, by lump name without `DS`.
+    pub sound: Option<&'static str>,
+}
+
+impl Fog {
+    fn teleport(x: Fixed, y: Fixed, z: Fixed) -> Self {
+        Self {
+            kind: MT_TFOG,
+            x,
+            y,
+            z,
+            sound: Some(TELEPORT_SOUND),
+        }
+    }
+
+    /// How much its sound matters to the channel allocator, lower first.
+    pub fn priority(&self) -> i32 {
+        match self.kind {
+            MT_IFOG => ITEM_PRIORITY,
+            _ => TELEPORT_PRIORITY,
+        }
+    }
+}
+
+/// Whether mobjs of `info` are effects only: left out of the blockmap, and neither solid,
+/// shootable nor something to pick up.
+pub fn effect_only(info: &MobjInfo) -> bool {
+    info.flags & MF_NOBLOCKMAP != 0 && info.flags & (MF_SOLID | MF_SHOOTABLE | MF_SPECIAL) == 0
+}
+
+/// `EV_Teleport`: fog where the thing left, at the height it left from, and 20 units in front
+/// of the exit along its angle, at `z`, the floor the thing was put down on.
+pub fn teleport(
+    from: (Fixed, Fixed, Fixed),
+    exit: (Fixed, Fixed),
+    exit_angle: Angle,
+    z: Fixed,
+    trig: &impl Trig,
+) -> [Fog; 2] {
+    let (cos, sin) = trig.fine(exit_angle);
+    [
+        Fog::teleport(from.0, from.1, from.2),
+        Fog::teleport(
+            exit.0.wrapping_add(FOG_DISTANCE.wrapping_mul(cos)),
+            exit.1.wrapping_add(FOG_DISTANCE.wrapping_mul(sin)),
+            z,
+        ),
+    ]
+}
+
+/// `P_NightmareRespawn`: fog on the floor under the corpse, and right on the monster's spawn
+/// spot, with no step forward, on the floor there.
+pub fn monster_respawn(
+    corpse: (Fixed, Fixed),
+    corpse_floor: Fixed,
+    spot: (Fixed, Fixed),
+    spot_floor: Fixed,
+) -> [Fog; 2] {
+    [
+        Fog::teleport(corpse.0, corpse.1, corpse_floor),
+        Fog::teleport(spot.0, spot.1, spot_floor),
+    ]
+}
+
+/// Whether an item removed at `removed_at` is back at `leveltime`.
+pub fn item_due(leveltime: i32, removed_at: i32) -> bool {
+    leveltime - removed_at >= ITEM_RESPAWN_TICS
+}
+
+/// `P_RespawnSpecials`: item fog on the floor of the item's spot, with its own sound. The
+/// item itself is spawned on the floor or ceiling as its info says.
+pub fn item_respawn(spot: (Fixed, Fixed), floor: Fixed) -> Fog {
+    Fog {
+        kind: MT_IFOG,
+        x: spot.0,
+        y: spot.1,
+        z: floor,
+        sound: Some(ITEM_SOUND),
+    }
+}
+
+/// `G_CheckSpot`: fog 20 units in front of a player start, on its floor, for a deathmatch
+/// spawn or a co-op respawn. `degrees` is the start's angle, snapped down to a multiple of 45.
+/// Vanilla keeps quiet on the level's first frame, when every player appears at once.
+///
+/// The DOS exe works out the table index as a signed number, so the starts facing west to
+/// southeast read before the start of `finesine` and get `finetangent` values instead. Facing
+/// west that puts the fog tens of thousands of units away, out of earshot, so the player
+/// appears silently; `emulate_overflows` keeps it so.
+pub fn player_spawn(
+    spot: (Fixed, Fixed),
+    degrees: i16,
+    floor: Fixed,
+    first_frame: bool,
+    compat: &Compatibility,
+    trig: &impl Trig,
+) -> Fog {
+    let octant = i32::from(degrees) / 45;
+    let (cos, sin) = match octant {
+        4 if compat.emulate_overflows => (trig.fine_tangent(2048), trig.fine_tangent(0)),
+        5 if compat.emulate_overflows => (trig.fine_tangent(3072), trig.fine_tangent(1024)),
+        6 if compat.emulate_overflows => (trig.fine(0).1, trig.fine_tangent(2048)),
+        7 if compat.emulate_overflows => (trig.fine(ANG45).1, trig.fine_tangent(3072)),
+        _ => trig.fine(ANG45.wrapping_mul(octant as u32)),
+    };
+    Fog {
+        sound: (!first_frame).then_some(TELEPORT_SOUND),
+        ..Fog::teleport(
+            spot.0.wrapping_add(FOG_DISTANCE.wrapping_mul(cos)),
+            spot.1.wrapping_add(FOG_DISTANCE.wrapping_mul(sin)),
+            floor,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::compatibility::CompLevel;
+    use crate::game::info::MF_NOGRAVITY;
+    use crate::util::angle::{ANG180, ANG90};
+    use crate::util::fixed::{to_fixed, FRACUNIT};
+
+    /// The compass points, and a few `finetangent` entries.
+    struct CompassTrig;
+
+    impl Trig for CompassTrig {
+        fn fine(&self, angle: Angle) -> (Fixed, Fixed) {
+            match angle {
+                0 => (FRACUNIT, 25),
+                ANG45 => (46341, 46340),
+                ANG90 => (25, FRACUNIT),
+                ANG180 => (-FRACUNIT, 25),
+                _ => (0, 0),
+            }
+        }
+
+        fn fine_tangent(&self, index: usize) -> Fixed {
+            match index {
+                0 => -170_910_304,
+                2048 => 25,
+                _ => 0,
+            }
+        }
+
+        fn point_to_angle(&self, _dx: Fixed, _dy: Fixed) -> Angle {
+            0
+        }
+
+        fn tan_to_angle(&self, _index: usize) -> Angle {
+            0
+        }
+    }
+
+    #[test]
+    fn fog_is_an_effect_only() {
+        let fog = MobjInfo {
+            flags: MF_NOBLOCKMAP | MF_NOGRAVITY,
+            ..MobjInfo::default()
+        };
+        assert!(effect_only(&fog));
+        let barrel = MobjInfo {
+            flags: MF_SOLID | MF_SHOOTABLE,
+            ..MobjInfo::default()
+        };
+        assert!(!effect_only(&barrel));
+
+        let item = item_respawn((to_fixed(64), to_fixed(32)), to_fixed(-8));
+        assert_eq!(
+            (item.kind, item.z, item.sound),
+            (MT_IFOG, to_fixed(-8), Some("itmbk"))
+        );
+        assert!(!item_due(ITEM_RESPAWN_TICS - 1, 0));
+        assert!(item_due(100 + ITEM_RESPAWN_TICS, 100));
+    }
+
+    #[test]
+    fn teleports_leave_fog_at_both_ends() {
+        let [left, arrived] = teleport(
+            (to_fixed(10), to_fixed(20), to_fixed(48)),
+            (to_fixed(512), to_fixed(256)),
+            ANG90,
+            to_fixed(16),
+            &CompassTrig,
+        );
+        assert_eq!(
+            (left.x, left.y, left.z),
+            (to_fixed(10), to_fixed(20), to_fixed(48))
+        );
+        assert_eq!(
+            (arrived.x, arrived.y, arrived.z),
+            (to_fixed(512) + 500, to_fixed(276), to_fixed(16))
+        );
+        assert_eq!(arrived.sound, Some("telept"));
+
+        let [corpse, spot] = monster_respawn((0, 0), to_fixed(-24), (to_fixed(96), 0), 0);
+        assert_eq!((corpse.z, spot.x, spot.z), (to_fixed(-24), to_fixed(96), 0));
+    }
+
+    #[test]
+    fn west_facing_starts_spawn_silently_far_away() {
+        let vanilla = Compatibility::for_level(CompLevel::Vanilla);
+        let boom = Compatibility::for_level(CompLevel::Boom);
+        let spot = (to_fixed(100), to_fixed(100));
+        let east = player_spawn(spot, 0, 0, false, &vanilla, &CompassTrig);
+        assert_eq!((east.x, east.y), (to_fixed(120), to_fixed(100) + 500));
+        let first = player_spawn(spot, 44, 0, true, &vanilla, &CompassTrig);
+        assert_eq!((first.x, first.sound), (to_fixed(120), None));
+
+        let west = player_spawn(spot, 180, 0, false, &vanilla, &CompassTrig);
+        assert_eq!(west.x, to_fixed(100) + 500);
+        assert_eq!(
+            west.y,
+            to_fixed(100).wrapping_add(20i32.wrapping_mul(-170_910_304))
+        );
+        let fixed = player_spawn(spot, 180, 0, false, &boom, &CompassTrig);
+        assert_eq!((fixed.x, fixed.y), (to_fixed(80), to_fixed(100) + 500));
+    }
+}
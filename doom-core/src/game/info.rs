@@ -0,0 +1,238 @@
+//! The thing, state and weapon tables, vanilla's `info.c` and friends.
+//!
+//! They are plain data indexed by number, exactly like vanilla, because DeHackEd patches
+//! address every entry and field by its vanilla number. Code pointers are an `Action` enum
+//! rather than function pointers so patches can name them and reassign them between states.
+
+use crate::util::fixed::Fixed;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+macro_rules! actions {
+    ($($name:ident),* $(,)?) => {
+        /// The code pointers of vanilla 1.9, named after their `A_` functions.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Action {
+            $($name),*
+        }
+
+        impl Action {
+            pub const ALL: &'static [Action] = &[$(Action::$name),*];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Action::$name => stringify!($name)),*
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    Light0, WeaponReady, Lower, Raise, Punch, ReFire, FirePistol, Light1, FireShotgun, Light2,
+    FireShotgun2, CheckReload, OpenShotgun2, LoadShotgun2, CloseShotgun2, FireCGun, GunFlash,
+    FireMissile, Saw, FirePlasma, BFGsound, FireBFG, BFGSpray, Explode, Pain, PlayerScream, Fall,
+    XScream, Look, Chase, FaceTarget, PosAttack, Scream, SPosAttack, VileChase, VileStart,
+    VileTarget, VileAttack, StartFire, Fire, FireCrackle, Tracer, SkelWhoosh, SkelFist,
+    SkelMissile, FatRaise, FatAttack1, FatAttack2, FatAttack3, BossDeath, CPosAttack, CPosRefire,
+    TroopAttack, SargAttack, HeadAttack, BruisAttack, SkullAttack, Metal, SpidRefire, BabyMetal,
+    BspiAttack, Hoof, CyberAttack, PainAttack, PainDie, KeenDie, BrainPain, BrainScream,
+    BrainDie, BrainAwake, BrainSpit, SpawnSound, SpawnFly, BrainExplode,
+}
+
+impl Action {
+    /// Looks up a code pointer by name, with or without the `A_` prefix, as BEX writes them.
+    pub fn from_name(name: &str) -> Option<Action> {
+        let name = name.strip_prefix("A_").unwrap_or(name);
+        Action::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    pub sprite: usize,
+    pub frame: i32, // Bit 15 set means full bright.
+    pub tics: i32,
+    pub action: Option<Action>,
+    pub next_state: usize,
+    pub misc1: i32,
+    pub misc2: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MobjInfo {
+    pub doomednum: i32,
+    pub spawn_state: usize,
+    pub spawn_health: i32,
+    pub see_state: usize,
+    pub see_sound: usize,
+    pub reaction_time: i32,
+    pub attack_sound: usize,
+    pub pain_state: usize,
+    pub pain_chance: i32,
+    pub pain_sound: usize,
+    pub melee_state: usize,
+    pub missile_state: usize,
+    pub death_state: usize,
+    pub xdeath_state: usize,
+    pub death_sound: usize,
+    pub speed: i32,
+    pub radius: Fixed,
+    pub height: Fixed,
+    pub mass: i32,
+    pub damage: i32,
+    pub active_sound: usize,
+    pub flags: u32,
+    pub raise_state: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeaponInfo {
+    pub ammo: usize, // Index into the ammo tables; 5 is "none" (fist, chainsaw).
+    pub up_state: usize,
+    pub down_state: usize,
+    pub ready_state: usize,
+    pub attack_state: usize,
+    pub flash_state: usize,
+}
+
+/// The odd numbers vanilla hard codes in the player code, all patchable through `Misc`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MiscInfo {
+    pub initial_health: i32,
+    pub initial_bullets: i32,
+    pub max_health: i32,
+    pub max_armor: i32,
+    pub green_armor_class: i32,
+    pub blue_armor_class: i32,
+    pub max_soulsphere: i32,
+    pub soulsphere_health: i32,
+    pub megasphere_health: i32,
+    pub god_mode_health: i32,
+    pub idfa_armor: i32,
+    pub idfa_armor_class: i32,
+    pub idkfa_armor: i32,
+    pub idkfa_armor_class: i32,
+    pub bfg_cells_per_shot: i32,
+    pub monsters_infight: bool,
+}
+
+impl Default for MiscInfo {
+    fn default() -> Self {
+        Self {
+            initial_health: 100,
+            initial_bullets: 50,
+            max_health: 200,
+            max_armor: 200,
+            green_armor_class: 1,
+            blue_armor_class: 2,
+            max_soulsphere: 200,
+            soulsphere_health: 100,
+            megasphere_health: 200,
+            god_mode_health: 100,
+            idfa_armor: 200,
+            idfa_armor_class: 2,
+            idkfa_armor: 200,
+            idkfa_armor_class: 2,
+            bfg_cells_per_shot: 40,
+            monsters_infight: false,
+        }
+    }
+}
+
+pub const NUMAMMO: usize = 4;
+
+pub const MF_SPECIAL: u32 = 0x1;
+pub const MF_SOLID: u32 = 0x2;
+pub const MF_SHOOTABLE: u32 = 0x4;
+pub const MF_NOSECTOR: u32 = 0x8;
+pub const MF_NOBLOCKMAP: u32 = 0x10;
+pub const MF_AMBUSH: u32 = 0x20;
+pub const MF_JUSTHIT: u32 = 0x40;
+pub const MF_JUSTATTACKED: u32 = 0x80;
+pub const MF_SPAWNCEILING: u32 = 0x100;
+pub const MF_NOGRAVITY: u32 = 0x200;
+pub const MF_DROPOFF: u32 = 0x400;
+pub const MF_PICKUP: u32 = 0x800;
+pub const MF_NOCLIP: u32 = 0x1000;
+pub const MF_SLIDE: u32 = 0x2000;
+pub const MF_FLOAT: u32 = 0x4000;
+pub const MF_TELEPORT: u32 = 0x8000;
+pub const MF_MISSILE: u32 = 0x10000;
+pub const MF_DROPPED: u32 = 0x20000;
+pub const MF_SHADOW: u32 = 0x40000;
+pub const MF_NOBLOOD: u32 = 0x80000;
+pub const MF_CORPSE: u32 = 0x100000;
+pub const MF_INFLOAT: u32 = 0x200000;
+pub const MF_COUNTKILL: u32 = 0x400000;
+pub const MF_COUNTITEM: u32 = 0x800000;
+pub const MF_SKULLFLY: u32 = 0x1000000;
+pub const MF_NOTDMATCH: u32 = 0x2000000;
+pub const MF_TRANSLATION: u32 = 0xc000000;
+/// Boom's translucency bit.
+pub const MF_TRANSLUCENT: u32 = 0x80000000;
+
+/// Flag names as BEX uses them in `Bits` fields.
+pub const MOBJ_FLAG_NAMES: &[(&str, u32)] = &[
+    ("SPECIAL", MF_SPECIAL),
+    ("SOLID", MF_SOLID),
+    ("SHOOTABLE", MF_SHOOTABLE),
+    ("NOSECTOR", MF_NOSECTOR),
+    ("NOBLOCKMAP", MF_NOBLOCKMAP),
+    ("AMBUSH", MF_AMBUSH),
+    ("JUSTHIT", MF_JUSTHIT),
+    ("JUSTATTACKED", MF_JUSTATTACKED),
+    ("SPAWNCEILING", MF_SPAWNCEILING),
+    ("NOGRAVITY", MF_NOGRAVITY),
+    ("DROPOFF", MF_DROPOFF),
+    ("PICKUP", MF_PICKUP),
+    ("NOCLIP", MF_NOCLIP),
+    ("SLIDE", MF_SLIDE),
+    ("FLOAT", MF_FLOAT),
+    ("TELEPORT", MF_TELEPORT),
+    ("MISSILE", MF_MISSILE),
+    ("DROPPED", MF_DROPPED),
+    ("SHADOW", MF_SHADOW),
+    ("NOBLOOD", MF_NOBLOOD),
+    ("CORPSE", MF_CORPSE),
+    ("INFLOAT", MF_INFLOAT),
+    ("COUNTKILL", MF_COUNTKILL),
+    ("COUNTITEM", MF_COUNTITEM),
+    ("SKULLFLY", MF_SKULLFLY),
+    ("NOTDMATCH", MF_NOTDMATCH),
+    ("TRANSLATION", MF_TRANSLATION),
+    ("TRANSLATION1", 0x4000000),
+    ("TRANSLATION2", 0x8000000),
+    ("TRANSLUCENT", MF_TRANSLUCENT),
+];
+
+/// Everything a DeHackEd patch can change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameInfo {
+    pub states: Vec<State>,
+    pub mobjs: Vec<MobjInfo>,
+    pub weapons: Vec<WeaponInfo>,
+    pub max_ammo: [i32; NUMAMMO],
+    pub clip_ammo: [i32; NUMAMMO],
+    pub misc: MiscInfo,
+    pub sprites: Vec<String>,
+    pub sounds: Vec<String>,
+}
+
+impl Default for GameInfo {
+    fn default() -> Self {
+        Self {
+            states: Vec::new(),
+            mobjs: Vec::new(),
+            weapons: Vec::new(),
+            max_ammo: [200, 50, 300, 50],
+            clip_ammo: [10, 4, 20, 1],
+            misc: MiscInfo::default(),
+            sprites: Vec::new(),
+            sounds: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! The times on the intermission screen: level time, par time and total time.
+//!
+//! The level and total times are `LevelStats`, counted in tics and saved with the game, and
+//! shown in whole seconds. Vanilla draws times as `mm:ss` and gives up past 59:59, showing
+//! the `WISUCKS` patch instead. Level times keep that, since it's part of the game's
+//! character. Total times over a long run reach an hour easily, so they grow an hours field
+//! instead.
+//!
+//! Par times come from UMAPINFO first, then BEX `[PARS]`, then vanilla's tables. Vanilla has
+//! none for episode 4, so it shows no par there unless one of the overrides supplies it.
+
+use super::dehacked::ParTime;
+use super::stats::LevelStats;
+use super::umapinfo::MapEntry;
+use super::{GameMode, TICRATE};
+
+/// `pars[episode][map]`, in seconds.
+const PARS: [[i32; 10]; 4] = [
+    [0; 10],
+    [0, 30, 75, 120, 90, 165, 180, 180, 30, 165],
+    [0, 90, 90, 90, 120, 90, 360, 240, 30, 170],
+    [0, 90, 45, 90, 150, 90, 90, 165, 30, 135],
+];
+
+/// `cpars[map - 1]`, in seconds.
+const CPARS: [i32; 32] = [
+    30, 90, 120, 120, 90, 150, 120, 120, 270, 90, // 1-10
+    210, 150, 150, 150, 210, 150, 420, 150, 210, 150, // 11-20
+    240, 150, 180, 150, 150, 300, 330, 420, 300, 180, // 21-30
+    120, 30, // 31-32
+];
+
+/// The last time `WI_drawTime` can show; anything longer sucks.
+pub const MAX_CLOCK: i32 = 61 * 59;
+
+/// The par time for a map in seconds, or `None` if the intermission shows no par.
+pub fn par_time(
+    mode: GameMode,
+    episode: u8,
+    map: u8,
+    bex: &[ParTime],
+    umapinfo: Option<&MapEntry>,
+) -> Option<i32> {
+    if let Some(seconds) = umapinfo.and_then(|entry| entry.par_time) {
+        return Some(seconds);
+    }
+    let commercial = mode == GameMode::Commercial;
+    let patched = bex.iter().rev().find(|par| {
+        par.map == map
+            && match par.episode {
+                Some(e) => !commercial && e == episode,
+                None => commercial,
+            }
+    });
+    if let Some(par) = patched {
+        return Some(par.seconds);
+    }
+    match commercial {
+        true => (map as usize)
+            .checked_sub(1)
+            .and_then(|i| CPARS.get(i))
+            .copied(),
+        false if (1..=3).contains(&episode) => PARS[episode as usize].get(map as usize).copied(),
+        false => None,
+    }
+}
+
+/// How a time is drawn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeText {
+    /// Digits and colons, right aligned, as `WI_drawTime` builds them: `:05`, `01:05`.
+    Clock(String),
+    Sucks,
+}
+
+/// `WI_drawTime`: every field two digits wide, a leading colon when there are only seconds.
+/// Past `MAX_CLOCK` seconds it sucks, unless `hours` allows another field. Negative times
+/// aren't drawn.
+pub fn time_text(seconds: i32, hours: bool) -> Option<TimeText> {
+    if seconds < 0 {
+        return None;
+    }
+    if seconds > MAX_CLOCK && !hours {
+        return Some(TimeText::Sucks);
+    }
+    let mut fields = Vec::new();
+    let mut div = 1;
+    loop {
+        fields.push(format!("{:02}", seconds / div % 60));
+        div *= 60;
+        if seconds / div == 0 {
+            break;
+        }
+    }
+    fields.reverse();
+    let mut text = fields.join(":");
+    if fields.len() == 1 {
+        text.insert(0, ':');
+    }
+    Some(TimeText::Clock(text))
+}
+
+/// The three times for the tally screen, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntermissionTimes {
+    pub time: i32,
+    pub total: i32,
+    pub par: Option<i32>,
+}
+
+impl IntermissionTimes {
+    /// Call when the level is exited, before its time is added to the total.
+    pub fn new(stats: &LevelStats, par: Option<i32>) -> Self {
+        Self {
+            time: stats.level_time / TICRATE,
+            total: stats.run_time() / TICRATE,
+            par,
+        }
+    }
+
+    pub fn time_text(&self) -> Option<TimeText> {
+        time_text(self.time, false)
+    }
+
+    pub fn total_text(&self) -> Option<TimeText> {
+        time_text(self.total, true)
+    }
+
+    pub fn par_text(&self) -> Option<TimeText> {
+        time_text(self.par?, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanilla_pars_and_overrides() {
+        assert_eq!(par_time(GameMode::Retail, 1, 1, &[], None), Some(30));
+        assert_eq!(par_time(GameMode::Retail, 3, 9, &[], None), Some(135));
+        assert_eq!(par_time(GameMode::Retail, 4, 1, &[], None), None);
+        assert_eq!(par_time(GameMode::Commercial, 1, 7, &[], None), Some(120));
+        assert_eq!(par_time(GameMode::Commercial, 1, 33, &[], None), None);
+
+        let bex = [
+            ParTime {
+                episode: Some(4),
+                map: 1,
+                seconds: 60,
+            },
+            ParTime {
+                episode: None,
+                map: 7,
+                seconds: 99,
+            },
+        ];
+        assert_eq!(par_time(GameMode::Retail, 4, 1, &bex, None), Some(60));
+        assert_eq!(par_time(GameMode::Commercial, 1, 7, &bex, None), Some(99));
+        let entry = MapEntry {
+            par_time: Some(45),
+            ..MapEntry::default()
+        };
+        assert_eq!(
+            par_time(GameMode::Commercial, 1, 7, &bex, Some(&entry)),
+            Some(45)
+        );
+    }
+
+    #[test]
+    fn times_draw_like_wi_draw_time() {
+        let clock = |s: &str| Some(TimeText::Clock(s.to_string()));
+        assert_eq!(time_text(5, false), clock(":05"));
+        assert_eq!(time_text(65, false), clock("01:05"));
+        assert_eq!(time_text(MAX_CLOCK, false), clock("59:59"));
+        assert_eq!(time_text(MAX_CLOCK + 1, false), Some(TimeText::Sucks));
+        assert_eq!(time_text(3600 * 2 + 5, true), clock("02:00:05"));
+        assert_eq!(time_text(-1, false), None);
+    }
+
+    #[test]
+    fn times_come_from_the_level_counters() {
+        let stats = LevelStats {
+            level_time: TICRATE * 4000,
+            total_time: TICRATE * 100,
+            ..LevelStats::default()
+        };
+        let times = IntermissionTimes::new(&stats, None);
+        assert_eq!(times.time_text(), Some(TimeText::Sucks));
+        assert_eq!(times.total_text(), Some(TimeText::Clock("01:08:20".into())));
+        assert_eq!(times.par_text(), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Per level results across a playthrough, and the `levelstat.txt` speedrunners submit.
+//!
+//! Each exit records the map, the level's `LevelStats` and how often the player died on it.
+//! The file uses PrBoom+'s line format, which existing tooling parses:
+//!
+//! ```text
+//! E1M1 - 0:21.43 (0:21)  K: 5/20  I: 3/5  S: 1/2
+//! ```
+//!
+//! A trailing `s` on the map marks a secret exit, and the time in brackets is the run's total.
+//! Deaths aren't part of that format, so they are only shown by the console command. The
+//! file is written when the session ends with `-levelstat`, or at any time with the
+//! `levelstat` command.
+
+use super::stats::{format_seconds, format_time, LevelStats};
+use crate::engine::console::Commands;
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+pub const LEVELSTAT_FILE: &str = "levelstat.txt";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelRecord {
+    pub map: String,
+    pub secret_exit: bool,
+    pub stats: LevelStats,
+    pub deaths: i32,
+}
+
+impl LevelRecord {
+    pub fn line(&self) -> String {
+        let stats = &self.stats;
+        format!(
+            "{}{} - {} ({})  K: {}/{}  I: {}/{}  S: {}/{}",
+            self.map,
+            if self.secret_exit { "s" } else { "" },
+            format_time(stats.level_time),
+            format_seconds(stats.run_time()),
+            stats.kills,
+            stats.total_kills,
+            stats.items,
+            stats.total_items,
+            stats.secrets,
+            stats.total_secrets
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelStatLog {
+    pub records: Vec<LevelRecord>,
+    deaths: i32, // On the level being played.
+}
+
+impl LevelStatLog {
+    pub fn player_died(&mut self) {
+        self.deaths += 1;
+    }
+
+    /// Records an exit. Call before `LevelStats::finish_level`, with the level's time not
+    /// yet added to the total.
+    pub fn level_completed(&mut self, map: &str, secret_exit: bool, stats: &LevelStats) {
+        self.records.push(LevelRecord {
+            map: map.to_ascii_uppercase(),
+            secret_exit,
+            stats: *stats,
+            deaths: self.deaths,
+        });
+        self.deaths = 0;
+    }
+
+    pub fn deaths(&self) -> i32 {
+        self.records.iter().map(|r| r.deaths).sum::<i32>() + self.deaths
+    }
+
+    pub fn text(&self) -> String {
+        self.records
+            .iter()
+            .map(|record| record.line() + "\n")
+            .collect()
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.text())
+    }
+}
+
+/// Registers `levelstat [file]`, which writes the file and prints a summary.
+pub fn register_command(log: Rc<RefCell<LevelStatLog>>, commands: &mut Commands) {
+    commands.register(
+        "levelstat",
+        "levelstat [file]: write the level times so far to levelstat.txt",
+        move |context, args| {
+            let path = Path::new(args.first().copied().unwrap_or(LEVELSTAT_FILE));
+            let log = log.borrow();
+            log.write(path)
+                .map_err(|error| format!("couldn't write {}: {error}", path.display()))?;
+            for record in &log.records {
+                context.print(format!("{}  D: {}", record.line(), record.deaths));
+            }
+            context.print(format!(
+                "{} levels, {} deaths, written to {}",
+                log.records.len(),
+                log.deaths(),
+                path.display()
+            ));
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::game::TICRATE;
+
+    fn playthrough() -> LevelStatLog {
+        let mut log = LevelStatLog::default();
+        let mut stats = LevelStats::default();
+        stats.start_level(20, 5, 2);
+        stats.kills = 5;
+        stats.items = 3;
+        stats.secrets = 1;
+        stats.level_time = TICRATE * 21 + 15;
+        log.player_died();
+        log.level_completed("e1m1", false, &stats);
+        stats.finish_level();
+        stats.start_level(0, 0, 0);
+        stats.level_time = TICRATE * 61;
+        log.level_completed("E1M2", true, &stats);
+        log
+    }
+
+    #[test]
+    fn lines_use_the_prboom_format() {
+        let log = playthrough();
+        assert_eq!(
+            log.text(),
+            "E1M1 - 0:21.43 (0:21)  K: 5/20  I: 3/5  S: 1/2\n\
+             E1M2s - 1:01.00 (1:22)  K: 0/0  I: 0/0  S: 0/0\n"
+        );
+        assert_eq!(log.records[0].deaths, 1);
+        assert_eq!(log.deaths(), 1);
+    }
+
+    #[test]
+    fn the_console_command_writes_the_file() {
+        let path = std::env::temp_dir().join(format!("levelstat-{}.txt", std::process::id()));
+        let log = Rc::new(RefCell::new(playthrough()));
+        let mut commands = Commands::default();
+        register_command(log.clone(), &mut commands);
+        let output = commands.execute(&format!("levelstat {}", path.display()), &mut Config::new());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), log.borrow().text());
+        assert_eq!(
+            output[0],
+            "E1M1 - 0:21.43 (0:21)  K: 5/20  I: 3/5  S: 1/2  D: 1"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
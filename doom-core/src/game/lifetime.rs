@@ -0,0 +1,305 @@
+//! Statistics kept across sessions: what the player has killed and with what, how often they
+//! died, what they picked up and how long they have played.
+//!
+//! The game reports events as they happen: `P_KillMobj` calls `monster_killed` with the
+//! killer's ready weapon, `P_TouchSpecialThing` calls `item_picked_up`, and so on. Kills are
+//! counted by editor number, so they stay attached to the same monster when a DeHackEd patch
+//! renumbers the thing table. The totals live in `lifetime.txt`, one `key value` line each,
+//! read at startup and written when the session ends. Keys this version doesn't know are
+//! dropped and a broken line is skipped, so an old or hand edited file never stops the game.
+//!
+//! The `lifetime` command prints them, and the results screen after the last level shows the
+//! same lines.
+
+use super::player::NUMWEAPONS;
+use super::TICRATE;
+use crate::engine::console::Commands;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+pub const LIFETIME_FILE: &str = "lifetime.txt";
+
+/// The monsters by editor number, as the stats name them.
+const MONSTER_NAMES: [(i32, &str); 19] = [
+    (3004, "zombieman"),
+    (9, "shotgun guy"),
+    (65, "chaingunner"),
+    (84, "wolfenstein ss"),
+    (3001, "imp"),
+    (3002, "demon"),
+    (58, "spectre"),
+    (3006, "lost soul"),
+    (3005, "cacodemon"),
+    (69, "hell knight"),
+    (3003, "baron of hell"),
+    (68, "arachnotron"),
+    (71, "pain elemental"),
+    (66, "revenant"),
+    (67, "mancubus"),
+    (64, "arch-vile"),
+    (7, "spider mastermind"),
+    (16, "cyberdemon"),
+    (72, "commander keen"),
+];
+
+/// Indexed by `WP_*`.
+const WEAPON_NAMES: [&str; NUMWEAPONS] = [
+    "fist",
+    "pistol",
+    "shotgun",
+    "chaingun",
+    "rocket launcher",
+    "plasma rifle",
+    "bfg 9000",
+    "chainsaw",
+    "super shotgun",
+];
+
+pub fn monster_name(doomednum: i32) -> String {
+    MONSTER_NAMES
+        .iter()
+        .find(|(number, _)| *number == doomednum)
+        .map_or_else(
+            || format!("thing {doomednum}"),
+            |(_, name)| name.to_string(),
+        )
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LifetimeStats {
+    /// Kills by the monster's editor number.
+    pub kills: BTreeMap<i32, u64>,
+    /// Kills by the player's weapon, indexed by `WP_*`.
+    pub weapon_kills: [u64; NUMWEAPONS],
+    pub deaths: u64,
+    pub items: u64,
+    pub secrets: u64,
+    pub levels: u64,
+    pub play_tics: u64,
+}
+
+impl LifetimeStats {
+    /// A monster died. `weapon` is the killer's ready weapon when a player killed it, and
+    /// `None` for infighting, crushers and barrels.
+    pub fn monster_killed(&mut self, doomednum: i32, weapon: Option<usize>) {
+        *self.kills.entry(doomednum).or_default() += 1;
+        if let Some(count) = weapon.and_then(|w| self.weapon_kills.get_mut(w)) {
+            *count += 1;
+        }
+    }
+
+    pub fn player_died(&mut self) {
+        self.deaths += 1;
+    }
+
+    pub fn item_picked_up(&mut self) {
+        self.items += 1;
+    }
+
+    pub fn secret_found(&mut self) {
+        self.secrets += 1;
+    }
+
+    pub fn level_completed(&mut self) {
+        self.levels += 1;
+    }
+
+    /// A game tic was played.
+    pub fn tic(&mut self) {
+        self.play_tics += 1;
+    }
+
+    pub fn total_kills(&self) -> u64 {
+        self.kills.values().sum()
+    }
+
+    /// The weapon with the most kills. Ties go to the lower slot, as the fist comes before
+    /// the chainsaw.
+    pub fn favorite_weapon(&self) -> Option<usize> {
+        (0..NUMWEAPONS)
+            .filter(|&w| self.weapon_kills[w] > 0)
+            .max_by_key(|&w| (self.weapon_kills[w], std::cmp::Reverse(w)))
+    }
+
+    /// The summary the command and the results screen show, most killed monsters first.
+    pub fn lines(&self) -> Vec<String> {
+        let favorite = self
+            .favorite_weapon()
+            .map_or("none", |weapon| WEAPON_NAMES[weapon]);
+        let seconds = self.play_tics / TICRATE as u64;
+        let mut lines = vec![
+            format!(
+                "time played  {}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+            format!("levels  {}  deaths  {}", self.levels, self.deaths),
+            format!("items  {}  secrets  {}", self.items, self.secrets),
+            format!("kills  {}  favorite weapon  {favorite}", self.total_kills()),
+        ];
+        let mut kills: Vec<_> = self.kills.iter().collect();
+        kills.sort_by_key(|&(&doomednum, &count)| (std::cmp::Reverse(count), doomednum));
+        lines.extend(
+            kills
+                .into_iter()
+                .map(|(&doomednum, count)| format!("  {}  {count}", monster_name(doomednum))),
+        );
+        lines
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in [
+            ("deaths", self.deaths),
+            ("items", self.items),
+            ("secrets", self.secrets),
+            ("levels", self.levels),
+            ("play_tics", self.play_tics),
+        ] {
+            text += &format!("{key} {value}\n");
+        }
+        for (weapon, count) in self.weapon_kills.iter().enumerate() {
+            text += &format!("weapon_kills.{weapon} {count}\n");
+        }
+        for (doomednum, count) in &self.kills {
+            text += &format!("kills.{doomednum} {count}\n");
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut stats = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.trim().split_once(' ') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<u64>() else {
+                continue;
+            };
+            match key {
+                "deaths" => stats.deaths = value,
+                "items" => stats.items = value,
+                "secrets" => stats.secrets = value,
+                "levels" => stats.levels = value,
+                "play_tics" => stats.play_tics = value,
+                _ => {
+                    if let Some(weapon) = key.strip_prefix("weapon_kills.") {
+                        if let Some(count) = weapon
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|w| stats.weapon_kills.get_mut(w))
+                        {
+                            *count = value;
+                        }
+                    } else if let Some(doomednum) = key.strip_prefix("kills.") {
+                        if let Ok(doomednum) = doomednum.parse() {
+                            stats.kills.insert(doomednum, value);
+                        }
+                    }
+                }
+            }
+        }
+        stats
+    }
+
+    /// Reads the file, or starts from nothing if there isn't one yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.text())
+    }
+}
+
+/// Registers `lifetime`, which prints the statistics.
+pub fn register_command(stats: Rc<RefCell<LifetimeStats>>, commands: &mut Commands) {
+    commands.register(
+        "lifetime",
+        "lifetime: show the statistics of every session so far",
+        move |context, _| {
+            for line in stats.borrow().lines() {
+                context.print(line);
+            }
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::game::player::WP_PISTOL;
+    use crate::game::weapons::{WP_CHAINSAW, WP_SHOTGUN};
+
+    fn session() -> LifetimeStats {
+        let mut stats = LifetimeStats::default();
+        stats.monster_killed(3001, Some(WP_SHOTGUN));
+        stats.monster_killed(3001, Some(WP_SHOTGUN));
+        stats.monster_killed(3004, Some(WP_PISTOL));
+        stats.monster_killed(3005, None);
+        stats.monster_killed(3005, Some(WP_CHAINSAW));
+        stats.player_died();
+        stats.item_picked_up();
+        stats.secret_found();
+        stats.level_completed();
+        for _ in 0..TICRATE * 75 {
+            stats.tic();
+        }
+        stats
+    }
+
+    #[test]
+    fn the_summary_names_monsters_and_the_favorite_weapon() {
+        let stats = session();
+        assert_eq!(stats.total_kills(), 5);
+        assert_eq!(stats.favorite_weapon(), Some(WP_SHOTGUN));
+        assert_eq!(
+            stats.lines(),
+            vec![
+                "time played  0:01:15",
+                "levels  1  deaths  1",
+                "items  1  secrets  1",
+                "kills  5  favorite weapon  shotgun",
+                "  imp  2",
+                "  cacodemon  2",
+                "  zombieman  1",
+            ]
+        );
+        assert_eq!(monster_name(5), "thing 5");
+        assert_eq!(LifetimeStats::default().favorite_weapon(), None);
+    }
+
+    #[test]
+    fn the_file_round_trips_and_tolerates_damage() {
+        let stats = session();
+        assert_eq!(LifetimeStats::parse(&stats.text()), stats);
+        let damaged = "deaths 4\nkills.3001 x\nweapon_kills.99 3\nsomething new 1\nkills.16 2\n";
+        let parsed = LifetimeStats::parse(damaged);
+        assert_eq!(parsed.deaths, 4);
+        assert_eq!(parsed.kills, BTreeMap::from([(16, 2)]));
+
+        let path = std::env::temp_dir().join(format!("lifetime-{}.txt", std::process::id()));
+        assert_eq!(
+            LifetimeStats::load(&path).unwrap(),
+            LifetimeStats::default()
+        );
+        stats.write(&path).unwrap();
+        assert_eq!(LifetimeStats::load(&path).unwrap(), stats);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut commands = Commands::default();
+        register_command(Rc::new(RefCell::new(stats)), &mut commands);
+        let output = commands.execute("lifetime", &mut Config::new());
+        assert_eq!(output[3], "kills  5  favorite weapon  shotgun");
+    }
+}
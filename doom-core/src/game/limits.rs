@@ -0,0 +1,561 @@
+//! Vanilla's fixed size tables and what happens when they fill up.
+//!
+//! The original exe stored visplanes, intercepts and crossed special lines in static arrays
+//! and never checked two of them, so overflowing one wrote over whatever variables the
+//! linker put next. Some demos only sync if those writes are reproduced, while most players
+//! would rather the limits didn't exist. Each table gets an `OverflowMode`, and the
+//! emulations here recreate the clobbered values without touching real memory.
+//!
+//! The limits that change gameplay (lost souls, moving sectors, crossed lines, openings) are
+//! set by the compatibility level instead, with per limit overrides for normal play.
+
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::compatibility::Compatibility;
+use std::fmt;
+use std::str::FromStr;
+
+pub const MAXVISPLANES: usize = 128;
+pub const MAXINTERCEPTS: usize = 128;
+pub const MAXSPECIALCROSS: usize = 8;
+pub const SAVEGAMESIZE: usize = 0x2c000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Stop with vanilla's error message.
+    Crash,
+    /// Keep the vanilla limit and reproduce what overflowing it did.
+    Emulate,
+    /// Let the table grow as needed.
+    Remove,
+}
+
+impl OverflowMode {
+    fn name(self) -> &'static str {
+        match self {
+            OverflowMode::Crash => "crash",
+            OverflowMode::Emulate => "emulate",
+            OverflowMode::Remove => "remove",
+        }
+    }
+}
+
+impl FromStr for OverflowMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_ascii_lowercase().as_str() {
+            "crash" => Ok(OverflowMode::Crash),
+            "emulate" => Ok(OverflowMode::Emulate),
+            "remove" => Ok(OverflowMode::Remove),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Which table ran out, for the error in `Crash` mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Visplanes,
+    Intercepts,
+    SpecialCross,
+    SaveBuffer,
+}
+
+impl Limit {
+    pub fn size(self) -> usize {
+        match self {
+            Limit::Visplanes => MAXVISPLANES,
+            Limit::Intercepts => MAXINTERCEPTS,
+            Limit::SpecialCross => MAXSPECIALCROSS,
+            Limit::SaveBuffer => SAVEGAMESIZE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitError(pub Limit);
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The messages vanilla printed, so reports can be matched against its behaviour.
+        f.write_str(match self.0 {
+            Limit::Visplanes => "R_FindPlane: no more visplanes",
+            Limit::Intercepts => "P_PathTraverse: intercepts overflow",
+            Limit::SpecialCross => "PIT_CheckLine: spechit overflow",
+            Limit::SaveBuffer => "Savegame buffer overrun",
+        })
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// What to do with the entry about to be added at index `len`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admit {
+    /// Store it normally.
+    Store,
+    /// Past the vanilla limit in `Emulate` mode: run the overflow emulation and don't store
+    /// it in the table.
+    Overflow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OverflowOptions {
+    pub visplanes: OverflowMode,
+    pub intercepts: OverflowMode,
+    pub spechit: OverflowMode,
+    pub save_buffer: OverflowMode,
+}
+
+impl Default for OverflowOptions {
+    fn default() -> Self {
+        // Emulating the overflows that affect demo sync costs nothing when they don't
+        // happen; the limits that only ever end the game are removed.
+        Self {
+            visplanes: OverflowMode::Remove,
+            intercepts: OverflowMode::Emulate,
+            spechit: OverflowMode::Emulate,
+            save_buffer: OverflowMode::Remove,
+        }
+    }
+}
+
+impl OverflowOptions {
+    pub fn mode(&self, limit: Limit) -> OverflowMode {
+        match limit {
+            Limit::Visplanes => self.visplanes,
+            Limit::Intercepts => self.intercepts,
+            Limit::SpecialCross => self.spechit,
+            Limit::SaveBuffer => self.save_buffer,
+        }
+    }
+
+    /// The options to play with at a compatibility level: emulation only means something at
+    /// levels that emulate the overflows, everywhere else it becomes `Remove`.
+    pub fn for_compat(&self, compat: &Compatibility) -> Self {
+        let adjust = |mode| match mode {
+            OverflowMode::Emulate if !compat.emulate_overflows => OverflowMode::Remove,
+            mode => mode,
+        };
+        Self {
+            visplanes: adjust(self.visplanes),
+            intercepts: adjust(self.intercepts),
+            spechit: adjust(self.spechit),
+            save_buffer: adjust(self.save_buffer),
+        }
+    }
+
+    /// Checks adding an entry at index `len` of a table.
+    pub fn admit(&self, limit: Limit, len: usize) -> Result<Admit, LimitError> {
+        if len < limit.size() {
+            return Ok(Admit::Store);
+        }
+        match self.mode(limit) {
+            OverflowMode::Crash => Err(LimitError(limit)),
+            OverflowMode::Emulate => Ok(Admit::Overflow),
+            OverflowMode::Remove => Ok(Admit::Store),
+        }
+    }
+}
+
+const CONFIG_NAMES: [(&str, Limit); 4] = [
+    ("overflow_visplanes", Limit::Visplanes),
+    ("overflow_intercepts", Limit::Intercepts),
+    ("overflow_spechit", Limit::SpecialCross),
+    ("overflow_savegame", Limit::SaveBuffer),
+];
+
+impl Configurable for OverflowOptions {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        for (name, limit) in CONFIG_NAMES {
+            config.register_str(name, defaults.mode(limit).name(), Section::Extended);
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let defaults = Self::default();
+        let mode = |limit: Limit| {
+            let (name, _) = CONFIG_NAMES.iter().find(|(_, l)| *l == limit).unwrap();
+            config.get_str(name).parse().unwrap_or(defaults.mode(limit))
+        };
+        Self {
+            visplanes: mode(Limit::Visplanes),
+            intercepts: mode(Limit::Intercepts),
+            spechit: mode(Limit::SpecialCross),
+            save_buffer: mode(Limit::SaveBuffer),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        for (name, limit) in CONFIG_NAMES {
+            config.set_str(name, self.mode(limit).name());
+        }
+    }
+}
+
+/// An engine variable that sat after `intercepts[]` in the vanilla exe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterceptsVariable {
+    LowFloor,
+    OpenBottom,
+    OpenTop,
+    OpenRange,
+    BulletSlope,
+    /// A 32 bit word of `playerstarts[]`: four 10 byte map things, so a word can straddle
+    /// two of them.
+    PlayerStarts(usize),
+    BmapWidth,
+    BmapOrgX,
+    BmapOrgY,
+    BmapHeight,
+}
+
+/// The memory after `intercepts[]`: (size in bytes, variable if it matters to the game).
+/// From the layout Chocolate Doom and PrBoom+ reconstructed from the 1.9 exe.
+const INTERCEPTS_OVERRUN: &[(usize, Option<InterceptsVariable>)] = &[
+    (4, None),
+    (4, None), // earlyout
+    (4, None), // intercept_p
+    (4, Some(InterceptsVariable::LowFloor)),
+    (4, Some(InterceptsVariable::OpenBottom)),
+    (4, Some(InterceptsVariable::OpenTop)),
+    (4, Some(InterceptsVariable::OpenRange)),
+    (4, None),
+    (120, None), // activeplats
+    (8, None),
+    (4, Some(InterceptsVariable::BulletSlope)),
+    (4, None), // swingx
+    (4, None), // swingy
+    (4, None),
+    (40, Some(InterceptsVariable::PlayerStarts(0))),
+    (4, None), // blocklinks
+    (4, Some(InterceptsVariable::BmapWidth)),
+    (4, None), // blockmap
+    (4, Some(InterceptsVariable::BmapOrgX)),
+    (4, Some(InterceptsVariable::BmapOrgY)),
+    (4, None), // blockmaplump
+    (4, Some(InterceptsVariable::BmapHeight)),
+];
+
+/// The writes storing intercept number `index` (at or past `MAXINTERCEPTS`) made. An
+/// intercept is `frac`, `isaline` and a pointer, 12 bytes; `pointer` is the address the thing
+/// or line would have had, which is how overflows turned monsters into ghosts. Words landing
+/// on variables the game doesn't care about, or past the known layout, are dropped.
+pub fn intercepts_overrun(
+    index: usize,
+    frac: i32,
+    is_line: bool,
+    pointer: i32,
+) -> Vec<(InterceptsVariable, i32)> {
+    let Some(offset) = index.checked_sub(MAXINTERCEPTS).map(|i| i * 12) else {
+        return Vec::new();
+    };
+    let words = [frac, i32::from(is_line), pointer];
+    words
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &value)| {
+            let mut address = offset + i * 4;
+            for &(size, variable) in INTERCEPTS_OVERRUN {
+                if address < size {
+                    return match variable? {
+                        InterceptsVariable::PlayerStarts(_) => {
+                            Some((InterceptsVariable::PlayerStarts(address / 4), value))
+                        }
+                        variable => Some((variable, value)),
+                    };
+                }
+                address -= size;
+            }
+            None
+        })
+        .collect()
+}
+
+/// What crossing more than `MAXSPECIALCROSS` special lines overwrote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpechitClobber {
+    /// `tmbbox[i]`, the bounding box of the move being checked.
+    BoundingBox(usize, i32),
+    CrushChange(i32),
+    NoFit(i32),
+}
+
+/// The address vanilla's `lines[]` usually started at, which PrBoom+ found to sync the most
+/// demos.
+pub const DEFAULT_SPECHIT_MAGIC: i32 = 0x01c09c98;
+
+/// The write adding the line numbered `line` as the `count`th crossed special made, once the
+/// count is past the limit. Only the first few extra lines have known effects.
+pub fn spechit_overrun(count: usize, line: usize, base: i32) -> Option<SpechitClobber> {
+    let address = base.wrapping_add((line as i32).wrapping_mul(0x3e));
+    match count {
+        9..=12 => Some(SpechitClobber::BoundingBox(count - 9, address)),
+        13 => Some(SpechitClobber::CrushChange(address)),
+        14 => Some(SpechitClobber::NoFit(address)),
+        _ => None,
+    }
+}
+
+pub const MAXLOSTSOULS: usize = 20; // Lost souls a pain elemental may add to the level.
+pub const MAXPLATS: usize = 30;
+pub const MAXCEILINGS: usize = 30;
+pub const MAXOPENINGS: usize = 320 * 64;
+
+/// The limits that change how the game plays rather than only what it can display. `None`
+/// means unlimited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameplayLimits {
+    pub lost_souls: Option<usize>,
+    pub plats: Option<usize>,
+    pub ceilings: Option<usize>,
+    /// Crossing more than this many special lines in one move runs into `OverflowOptions`.
+    pub special_cross: Option<usize>,
+    pub openings: Option<usize>,
+}
+
+impl GameplayLimits {
+    pub const VANILLA: Self = Self {
+        lost_souls: Some(MAXLOSTSOULS),
+        plats: Some(MAXPLATS),
+        ceilings: Some(MAXCEILINGS),
+        special_cross: Some(MAXSPECIALCROSS),
+        openings: Some(MAXOPENINGS),
+    };
+
+    /// The limits a compatibility level plays with: Boom removed the moving sector and line
+    /// limits, MBF the lost soul one.
+    pub fn for_compat(compat: &Compatibility) -> Self {
+        let boom = compat.boom_specials;
+        Self {
+            lost_souls: (!compat.no_lost_soul_limit).then_some(MAXLOSTSOULS),
+            plats: (!boom).then_some(MAXPLATS),
+            ceilings: (!boom).then_some(MAXCEILINGS),
+            special_cross: (!boom).then_some(MAXSPECIALCROSS),
+            openings: (!boom).then_some(MAXOPENINGS),
+        }
+    }
+
+    /// The level's limits with the player's overrides applied. A demo being played back
+    /// ignores the overrides, since it only syncs with the limits it was recorded under.
+    pub fn resolve(
+        compat: &Compatibility,
+        overrides: &LimitOverrides,
+        demo_playback: bool,
+    ) -> Self {
+        let defaults = Self::for_compat(compat);
+        if demo_playback {
+            return defaults;
+        }
+        let pick =
+            |enforce: Option<bool>, default: Option<usize>, vanilla: Option<usize>| match enforce {
+                Some(true) => vanilla,
+                Some(false) => None,
+                None => default,
+            };
+        let vanilla = Self::VANILLA;
+        Self {
+            lost_souls: pick(
+                overrides.lost_souls,
+                defaults.lost_souls,
+                vanilla.lost_souls,
+            ),
+            plats: pick(overrides.plats, defaults.plats, vanilla.plats),
+            ceilings: pick(overrides.ceilings, defaults.ceilings, vanilla.ceilings),
+            special_cross: pick(
+                overrides.special_cross,
+                defaults.special_cross,
+                vanilla.special_cross,
+            ),
+            openings: pick(overrides.openings, defaults.openings, vanilla.openings),
+        }
+    }
+}
+
+/// Per limit overrides: `Some(true)` enforces the vanilla limit, `Some(false)` removes it,
+/// `None` follows the compatibility level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LimitOverrides {
+    pub lost_souls: Option<bool>,
+    pub plats: Option<bool>,
+    pub ceilings: Option<bool>,
+    pub special_cross: Option<bool>,
+    pub openings: Option<bool>,
+}
+
+impl LimitOverrides {
+    fn fields(&mut self) -> [(&'static str, &mut Option<bool>); 5] {
+        [
+            ("limit_lost_souls", &mut self.lost_souls),
+            ("limit_plats", &mut self.plats),
+            ("limit_ceilings", &mut self.ceilings),
+            ("limit_spechit", &mut self.special_cross),
+            ("limit_openings", &mut self.openings),
+        ]
+    }
+}
+
+/// In the config each override is -1 (follow the level), 0 (removed) or 1 (vanilla).
+impl Configurable for LimitOverrides {
+    fn register(config: &mut Config) {
+        for (name, _) in Self::default().fields() {
+            config.register_int(name, -1, Section::Extended);
+        }
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let mut overrides = Self::default();
+        for (name, value) in overrides.fields() {
+            *value = match config.get_int(name) {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            };
+        }
+        overrides
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        let mut overrides = *self;
+        for (name, value) in overrides.fields() {
+            config.set_int(name, value.map_or(-1, i32::from));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::compatibility::CompLevel;
+
+    #[test]
+    fn modes_decide_what_happens_past_the_limit() {
+        let options = OverflowOptions {
+            visplanes: OverflowMode::Crash,
+            ..OverflowOptions::default()
+        };
+        assert_eq!(options.admit(Limit::Visplanes, 127), Ok(Admit::Store));
+        let error = options.admit(Limit::Visplanes, 128).unwrap_err();
+        assert_eq!(error.to_string(), "R_FindPlane: no more visplanes");
+        assert_eq!(options.admit(Limit::Intercepts, 200), Ok(Admit::Overflow));
+        assert_eq!(
+            options.admit(Limit::SaveBuffer, SAVEGAMESIZE),
+            Ok(Admit::Store)
+        );
+
+        let boom = options.for_compat(&Compatibility::for_level(CompLevel::Boom));
+        assert_eq!(boom.intercepts, OverflowMode::Remove);
+        assert_eq!(boom.visplanes, OverflowMode::Crash);
+    }
+
+    #[test]
+    fn options_round_trip_through_config() {
+        let mut config = Config::new();
+        OverflowOptions::register(&mut config);
+        assert_eq!(
+            OverflowOptions::from_config(&config),
+            OverflowOptions::default()
+        );
+        let options = OverflowOptions {
+            spechit: OverflowMode::Crash,
+            ..OverflowOptions::default()
+        };
+        options.to_config(&mut config);
+        assert_eq!(config.get_str("overflow_spechit"), "crash");
+        assert_eq!(OverflowOptions::from_config(&config), options);
+    }
+
+    #[test]
+    fn intercepts_overrun_lands_on_the_vanilla_layout() {
+        assert!(intercepts_overrun(MAXINTERCEPTS - 1, 1, true, 2).is_empty());
+        // The first extra intercept covers the three unused words before lowfloor.
+        assert!(intercepts_overrun(MAXINTERCEPTS, 1, true, 2).is_empty());
+        // The second one's words are lowfloor, openbottom and opentop.
+        assert_eq!(
+            intercepts_overrun(MAXINTERCEPTS + 1, 7, false, 9),
+            vec![
+                (InterceptsVariable::LowFloor, 7),
+                (InterceptsVariable::OpenBottom, 0),
+                (InterceptsVariable::OpenTop, 9),
+            ]
+        );
+        // playerstarts begins 176 bytes in, at the pointer of the fifteenth extra intercept.
+        assert_eq!(
+            intercepts_overrun(MAXINTERCEPTS + 14, 1, true, 5),
+            vec![(InterceptsVariable::PlayerStarts(0), 5)]
+        );
+        assert_eq!(
+            intercepts_overrun(MAXINTERCEPTS + 15, 1, true, 5),
+            vec![
+                (InterceptsVariable::PlayerStarts(1), 1),
+                (InterceptsVariable::PlayerStarts(2), 1),
+                (InterceptsVariable::PlayerStarts(3), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn spechit_overrun_clobbers_bbox_then_flags() {
+        let base = DEFAULT_SPECHIT_MAGIC;
+        assert_eq!(spechit_overrun(8, 0, base), None);
+        assert_eq!(
+            spechit_overrun(9, 2, base),
+            Some(SpechitClobber::BoundingBox(0, base + 0x7c))
+        );
+        assert_eq!(
+            spechit_overrun(14, 0, base),
+            Some(SpechitClobber::NoFit(base))
+        );
+        assert_eq!(spechit_overrun(15, 0, base), None);
+    }
+    #[test]
+    fn gameplay_limits_follow_level_overrides_and_demos() {
+        let vanilla = Compatibility::for_level(CompLevel::Vanilla);
+        let mbf = Compatibility::for_level(CompLevel::Mbf);
+        let none = LimitOverrides::default();
+        assert_eq!(
+            GameplayLimits::resolve(&vanilla, &none, false),
+            GameplayLimits::VANILLA
+        );
+        let boom = GameplayLimits::for_compat(&Compatibility::for_level(CompLevel::Boom));
+        assert_eq!(boom.lost_souls, Some(MAXLOSTSOULS));
+        assert_eq!(boom.plats, None);
+        assert_eq!(GameplayLimits::for_compat(&mbf).lost_souls, None);
+
+        let overrides = LimitOverrides {
+            lost_souls: Some(true),
+            plats: Some(false),
+            ..LimitOverrides::default()
+        };
+        let limits = GameplayLimits::resolve(&mbf, &overrides, false);
+        assert_eq!(limits.lost_souls, Some(MAXLOSTSOULS));
+        assert_eq!(
+            GameplayLimits::resolve(&vanilla, &overrides, false).plats,
+            None
+        );
+        // Playback ignores the overrides.
+        assert_eq!(
+            GameplayLimits::resolve(&vanilla, &overrides, true),
+            GameplayLimits::VANILLA
+        );
+    }
+
+    #[test]
+    fn limit_overrides_round_trip_through_config() {
+        let mut config = Config::new();
+        LimitOverrides::register(&mut config);
+        assert_eq!(
+            LimitOverrides::from_config(&config),
+            LimitOverrides::default()
+        );
+        let overrides = LimitOverrides {
+            openings: Some(false),
+            ceilings: Some(true),
+            ..LimitOverrides::default()
+        };
+        overrides.to_config(&mut config);
+        assert_eq!(config.get_int("limit_openings"), 0);
+        assert_eq!(LimitOverrides::from_config(&config), overrides);
+    }
+}
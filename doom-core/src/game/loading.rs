@@ -0,0 +1,226 @@
+//! Level loading with progress reports, for a loading bar.
+//!
+//! Vanilla's `P_SetupLevel` read a map in one go with nothing on screen but the disk icon,
+//! which was fine for E1M1 and leaves a limit-removing map with a hundred thousand nodes
+//! looking frozen for seconds. `load_level` goes through the same steps in phases (the map
+//! lumps, the BSP nodes, the blockmap and the precache), and tells a `LoadProgress` how far
+//! each has got. The progress turns that into one percentage for the whole load, weighted by
+//! how long each phase usually takes, and passes it on only when it moves.
+
+use super::info::GameInfo;
+use super::precache::{LevelCache, PrecacheList, PrecacheOptions};
+use super::traverse::Blockmap;
+use crate::wad::file::Wads;
+use crate::wad::map::{MapData, Node, NODE_SIZE};
+use crate::wad::source::LumpSource;
+
+/// Nodes read between progress reports.
+const NODES_STEP: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadPhase {
+    Map,
+    Nodes,
+    Blockmap,
+    Precache,
+}
+
+impl LoadPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoadPhase::Map => "map",
+            LoadPhase::Nodes => "nodes",
+            LoadPhase::Blockmap => "blockmap",
+            LoadPhase::Precache => "precache",
+        }
+    }
+
+    /// Where the phase starts and ends on the bar, in percent.
+    fn span(self) -> (u32, u32) {
+        match self {
+            LoadPhase::Map => (0, 10),
+            LoadPhase::Nodes => (10, 25),
+            LoadPhase::Blockmap => (25, 30),
+            LoadPhase::Precache => (30, 100),
+        }
+    }
+}
+
+/// Collects a load's progress and reports it to `report` as a phase and an overall percentage.
+pub struct LoadProgress<'a> {
+    report: &'a mut dyn FnMut(LoadPhase, u32),
+    percent: Option<u32>,
+}
+
+impl<'a> LoadProgress<'a> {
+    pub fn new(report: &'a mut dyn FnMut(LoadPhase, u32)) -> Self {
+        Self {
+            report,
+            percent: None,
+        }
+    }
+
+    /// Notes that `done` of the `total` steps of `phase` are through. An empty phase is
+    /// complete.
+    pub fn update(&mut self, phase: LoadPhase, done: usize, total: usize) {
+        let (start, end) = phase.span();
+        let through = match total {
+            0 => end - start,
+            _ => ((end - start) as usize * done.min(total) / total) as u32,
+        };
+        let percent = start + through;
+        if self.percent.is_none_or(|last| percent > last) {
+            self.percent = Some(percent);
+            (self.report)(phase, percent);
+        }
+    }
+
+    /// The last percentage reported, 0 before any.
+    pub fn percent(&self) -> u32 {
+        self.percent.unwrap_or(0)
+    }
+}
+
+/// What `load_level` reads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    pub map: MapData,
+    pub nodes: Vec<Node>,
+    /// `None` when the WAD has no usable BLOCKMAP.
+    pub blockmap: Option<Blockmap>,
+    /// How many lumps the precache decoded.
+    pub precached: usize,
+}
+
+/// Loads the map `name`, precaching into `cache` unless `precache` turns it off. Returns
+/// `None` if a lump the map needs is missing or malformed.
+pub fn load_level(
+    wads: &Wads,
+    name: &str,
+    info: &GameInfo,
+    sky: &str,
+    cache: &LevelCache,
+    precache: &PrecacheOptions,
+    progress: &mut LoadProgress,
+) -> Option<Level> {
+    progress.update(LoadPhase::Map, 0, 1);
+    let map = MapData::load(wads, name)?;
+    progress.update(LoadPhase::Map, 1, 1);
+
+    let lump = wads.map_lump(name, "NODES")?;
+    if !lump.len().is_multiple_of(NODE_SIZE) {
+        return None;
+    }
+    let count = lump.len() / NODE_SIZE;
+    let mut nodes = Vec::with_capacity(count);
+    for chunk in lump.chunks(NODES_STEP * NODE_SIZE) {
+        nodes.extend(MapData::parse_nodes(chunk)?);
+        progress.update(LoadPhase::Nodes, nodes.len(), count);
+    }
+    progress.update(LoadPhase::Nodes, count, count);
+
+    let blockmap = wads.map_lump(name, "BLOCKMAP").and_then(Blockmap::parse);
+    progress.update(LoadPhase::Blockmap, 1, 1);
+
+    let mut precached = 0;
+    if precache.enabled {
+        let list = PrecacheList::for_level(&map, info, sky);
+        precached =
+            cache.precache_reporting(&list, wads, precache.worker_count(), &mut |done, total| {
+                progress.update(LoadPhase::Precache, done, total)
+            });
+    }
+    progress.update(LoadPhase::Precache, 1, 1);
+
+    Some(Level {
+        map,
+        nodes,
+        blockmap,
+        precached,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::file::WadFile;
+    use std::path::Path;
+
+    fn wads(nodes: &[u8]) -> Wads {
+        let bytes = build_wad(
+            b"PWAD",
+            &[
+                ("MAP01", &[]),
+                ("THINGS", &[]),
+                ("LINEDEFS", &[]),
+                ("SIDEDEFS", &[]),
+                ("VERTEXES", &[]),
+                ("SEGS", &[]),
+                ("SSECTORS", &[]),
+                ("NODES", nodes),
+                ("SECTORS", &[]),
+                ("REJECT", &[]),
+                ("BLOCKMAP", &[0; 8]),
+            ],
+        );
+        let mut wads = Wads::new();
+        wads.add(WadFile::parse(Path::new("test.wad"), &bytes).unwrap());
+        wads
+    }
+
+    fn load(wads: &Wads) -> (Option<Level>, Vec<(LoadPhase, u32)>) {
+        let mut reports = Vec::new();
+        let mut report = |phase, percent| reports.push((phase, percent));
+        let mut progress = LoadProgress::new(&mut report);
+        let level = load_level(
+            wads,
+            "MAP01",
+            &GameInfo::default(),
+            "SKY1",
+            &LevelCache::default(),
+            &PrecacheOptions::default(),
+            &mut progress,
+        );
+        (level, reports)
+    }
+
+    #[test]
+    fn progress_moves_forward_through_the_phases() {
+        let (level, reports) = load(&wads(&[0; 10_000 * NODE_SIZE]));
+        let level = level.unwrap();
+        assert_eq!(level.nodes.len(), 10_000);
+        assert_eq!(level.blockmap.unwrap().columns, 0);
+        assert_eq!(
+            reports,
+            [
+                (LoadPhase::Map, 0),
+                (LoadPhase::Map, 10),
+                (LoadPhase::Nodes, 16),
+                (LoadPhase::Nodes, 22),
+                (LoadPhase::Nodes, 25),
+                (LoadPhase::Blockmap, 30),
+                (LoadPhase::Precache, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn progress_reports_only_when_it_moves() {
+        let mut reports = Vec::new();
+        let mut report = |_, percent| reports.push(percent);
+        let mut progress = LoadProgress::new(&mut report);
+        for done in 0..=1000 {
+            progress.update(LoadPhase::Precache, done, 1000);
+        }
+        progress.update(LoadPhase::Precache, 0, 0);
+        assert_eq!(progress.percent(), 100);
+        assert_eq!(reports, (30..=100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn broken_nodes_fail_the_load() {
+        assert!(load(&wads(&[0; NODE_SIZE * 3])).0.is_some());
+        assert!(load(&wads(&[0; NODE_SIZE * 3 - 1])).0.is_none());
+    }
+}
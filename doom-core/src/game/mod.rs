@@ -0,0 +1,156 @@
+//! Game rules and state: everything between reading input and drawing the frame.
+
+use crate::util::angle::Angle;
+use crate::util::fixed::Fixed;
+
+#[cfg(feature = "std")]
+pub mod boom;
+#[cfg(feature = "std")]
+pub mod chat;
+#[cfg(feature = "std")]
+pub mod cheat;
+pub mod compatibility;
+#[cfg(feature = "std")]
+pub mod deathmatch;
+#[cfg(feature = "std")]
+pub mod dehacked;
+#[cfg(feature = "std")]
+pub mod demo;
+#[cfg(feature = "std")]
+pub mod dropin;
+#[cfg(feature = "std")]
+pub mod explored;
+#[cfg(feature = "std")]
+pub mod fast;
+pub mod fog;
+pub mod info;
+#[cfg(feature = "std")]
+pub mod intermission;
+#[cfg(feature = "std")]
+pub mod levelstat;
+#[cfg(feature = "std")]
+pub mod lifetime;
+#[cfg(feature = "std")]
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod loading;
+pub mod monsters;
+#[cfg(feature = "std")]
+pub mod pause;
+#[cfg(feature = "std")]
+pub mod player;
+#[cfg(feature = "std")]
+pub mod precache;
+#[cfg(feature = "std")]
+pub mod quicksave;
+#[cfg(feature = "std")]
+pub mod reborn;
+#[cfg(feature = "std")]
+pub mod rewind;
+pub mod savegame;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "std")]
+pub mod scroll;
+pub mod sector_sound;
+#[cfg(feature = "std")]
+pub mod spawn;
+#[cfg(feature = "std")]
+pub mod spectator;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod strict;
+#[cfg(feature = "std")]
+pub mod strings;
+pub mod ticcmd;
+#[cfg(feature = "std")]
+pub mod traverse;
+#[cfg(feature = "std")]
+pub mod umapinfo;
+#[cfg(feature = "std")]
+pub mod voodoo;
+#[cfg(feature = "std")]
+pub mod vote;
+#[cfg(feature = "std")]
+pub mod weapons;
+
+/// Game tics per second.
+pub const TICRATE: i32 = 35;
+
+/// Vanilla's trig tables, for the code working out where things go. The tree doesn't carry
+/// `finesine`, `finetangent` and `tantoangle` yet; whoever calls that code hands them in,
+/// and only the tables themselves give results that match vanilla to the bit.
+pub trait Trig {
+    /// `finecosine` and `finesine` at `angle`.
+    fn fine(&self, angle: Angle) -> (Fixed, Fixed);
+    /// `finetangent`, for the spots where vanilla reads it by running off the end of another
+    /// table.
+    fn fine_tangent(&self, index: usize) -> Fixed;
+    /// `R_PointToAngle2` from the origin to (`dx`, `dy`).
+    fn point_to_angle(&self, dx: Fixed, dy: Fixed) -> Angle;
+    /// `tantoangle`, indexed by a slope of 0 to 1 in 2048 steps.
+    fn tan_to_angle(&self, index: usize) -> Angle;
+}
+
+/// Which flavour of the game the IWAD provides, as vanilla's `gamemode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// Episode 1 only.
+    Shareware,
+    /// Episodes 1 to 3.
+    Registered,
+    /// The Ultimate Doom, with episode 4.
+    Retail,
+    /// Doom II and Final Doom: no episodes, MAP01 to MAP32.
+    #[default]
+    Commercial,
+}
+
+impl GameMode {
+    pub fn episodes(self) -> u8 {
+        match self {
+            GameMode::Shareware | GameMode::Commercial => 1,
+            GameMode::Registered => 3,
+            GameMode::Retail => 4,
+        }
+    }
+
+    /// Tells the games apart by their maps, as `D_IdentifyVersion` does once it knows the
+    /// IWAD is Doom rather than Doom II.
+    pub fn identify(has_lump: impl Fn(&str) -> bool) -> GameMode {
+        if has_lump("MAP01") {
+            GameMode::Commercial
+        } else if has_lump("E4M1") {
+            GameMode::Retail
+        } else if has_lump("E3M1") {
+            GameMode::Registered
+        } else {
+            GameMode::Shareware
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Skill {
+    Baby,
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Nightmare,
+}
+
+impl Skill {
+    /// From the 1 based number `-skill` takes.
+    pub fn from_number(number: u8) -> Option<Self> {
+        [
+            Skill::Baby,
+            Skill::Easy,
+            Skill::Medium,
+            Skill::Hard,
+            Skill::Nightmare,
+        ]
+        .get(usize::from(number).checked_sub(1)?)
+        .copied()
+    }
+}
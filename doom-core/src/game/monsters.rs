@@ -0,0 +1,579 @@
+//! The monsters with mechanics of their own beyond walking and attacking: the arch-vile's
+//! resurrections and fire, the pain elemental's lost souls, the lost soul's charge and the
+//! revenant's homing missiles.
+//!
+//! Each function is one piece of a vanilla code pointer or of the movement code it relies on,
+//! quirks included, since demos go out of sync on any difference. They work on copies of the
+//! mobjs involved as `Actor`s and leave spawning, sounds and state changes to the caller. The
+//! trig tables come from the caller through `Trig` and `P_Random` as `random`, so results
+//! match vanilla's to the bit as long as those are vanilla's own.
+
+use super::compatibility::Compatibility;
+use super::info::{MobjInfo, MF_CORPSE, MF_SKULLFLY, MF_SOLID};
+use super::Trig;
+use crate::util::angle::Angle;
+use crate::util::fixed::{approx_distance, fixed_mul, Fixed, FRACBITS, FRACUNIT};
+
+pub const MT_VILE: usize = 3;
+pub const MT_FIRE: usize = 4;
+pub const MT_TRACER: usize = 6;
+pub const MT_SMOKE: usize = 7;
+pub const MT_SKULL: usize = 18;
+pub const MT_PAIN: usize = 22;
+
+/// The move direction of a monster that isn't walking anywhere.
+pub const DI_NODIR: usize = 8;
+/// `xspeed` and `yspeed`: one step in each move direction, east first and turning left.
+const XSPEED: [Fixed; 8] = [FRACUNIT, 47000, 0, -47000, -FRACUNIT, -47000, 0, 47000];
+const YSPEED: [Fixed; 8] = [0, 47000, FRACUNIT, 47000, 0, -47000, -FRACUNIT, -47000];
+const MAXRADIUS: Fixed = 32 * FRACUNIT;
+const MAPBLOCKSHIFT: u32 = FRACBITS + 7;
+
+/// How far from its victim the arch-vile's fire burns.
+const FIRE_DISTANCE: Fixed = 24 * FRACUNIT;
+pub const VILE_DAMAGE: i32 = 20;
+/// The blast's damage at its center, as `P_RadiusAttack` takes it.
+pub const VILE_BLAST: i32 = 70;
+
+/// Damage that kills a lost soul which doesn't fit where it was spawned.
+pub const SKULL_SPAWN_KILL: i32 = 10000;
+pub const SKULLSPEED: Fixed = 20 * FRACUNIT;
+
+/// The most a revenant missile turns each time it steers.
+pub const TRACEANGLE: Angle = 0xc00_0000;
+
+/// The parts of a mobj these mechanics read and change.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Actor {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+    pub momx: Fixed,
+    pub momy: Fixed,
+    pub momz: Fixed,
+    pub angle: Angle,
+    pub radius: Fixed,
+    pub height: Fixed,
+    pub flags: u32,
+    pub health: i32,
+}
+
+/// Where `A_VileChase` looks for corpses: a step ahead in the direction the vile is walking,
+/// `speed` being its info speed. A vile with no direction doesn't look.
+pub fn vile_try(vile: &Actor, speed: i32, movedir: usize) -> Option<(Fixed, Fixed)> {
+    let (dx, dy) = (XSPEED.get(movedir)?, YSPEED[movedir]);
+    Some((
+        vile.x.wrapping_add(speed.wrapping_mul(*dx)),
+        vile.y.wrapping_add(speed.wrapping_mul(dy)),
+    ))
+}
+
+/// The blockmap blocks searched around `at`, in order: each column from the left, bottom to
+/// top. The first corpse `vile_check` accepts is the one raised, so the order matters.
+pub fn vile_blocks(at: (Fixed, Fixed), origin: (Fixed, Fixed)) -> impl Iterator<Item = (i32, i32)> {
+    let block = |v: Fixed, o: Fixed, d: Fixed| v.wrapping_sub(o).wrapping_add(d) >> MAPBLOCKSHIFT;
+    let xs = block(at.0, origin.0, -2 * MAXRADIUS)..=block(at.0, origin.0, 2 * MAXRADIUS);
+    let ys = block(at.1, origin.1, -2 * MAXRADIUS)..=block(at.1, origin.1, 2 * MAXRADIUS);
+    xs.flat_map(move |x| ys.clone().map(move |y| (x, y)))
+}
+
+/// `PIT_VileCheck`: whether `corpse` can be raised by a vile about to step to `at`. `tics` is
+/// what is left of its frame, and `fits` is `P_CheckPosition` for a probe of it.
+///
+/// A corpse close enough to be considered has its momentum cleared even when it can't be
+/// raised. Vanilla probes with the corpse four times as tall, which for one flattened by a
+/// crusher is still nothing, so those always fit; Boom probes with its spawn size.
+pub fn vile_check(
+    corpse: &mut Actor,
+    tics: i32,
+    info: &MobjInfo,
+    vile_radius: Fixed,
+    at: (Fixed, Fixed),
+    compat: &Compatibility,
+    fits: impl FnOnce(&Actor) -> bool,
+) -> bool {
+    if corpse.flags & MF_CORPSE == 0 || tics != -1 || info.raise_state == 0 {
+        return false;
+    }
+    let reach = info.radius + vile_radius;
+    if corpse.x.wrapping_sub(at.0).wrapping_abs() > reach
+        || corpse.y.wrapping_sub(at.1).wrapping_abs() > reach
+    {
+        return false;
+    }
+    corpse.momx = 0;
+    corpse.momy = 0;
+    let probe = match compat.vile_ghosts_fixed {
+        true => Actor {
+            height: info.height,
+            radius: info.radius,
+            flags: corpse.flags | MF_SOLID,
+            ..*corpse
+        },
+        false => Actor {
+            height: corpse.height << 2,
+            ..*corpse
+        },
+    };
+    fits(&probe)
+}
+
+/// What raising does to the corpse besides putting it in its raise state. A corpse keeps a
+/// quarter of its height, which vanilla multiplies back; a crushed one has none left and
+/// comes back as a ghost that can't be hit or blocked. Boom restores the spawn size instead.
+pub fn vile_raise(corpse: &mut Actor, info: &MobjInfo, compat: &Compatibility) {
+    if compat.vile_ghosts_fixed {
+        corpse.height = info.height;
+        corpse.radius = info.radius;
+    } else {
+        corpse.height <<= 2;
+    }
+    corpse.flags = info.flags;
+    corpse.health = info.spawn_health;
+}
+
+/// Where `A_VileTarget` spawns the fire. Vanilla passes the target's x for both coordinates;
+/// `A_Fire` moves the fire right away, but it keeps the floor and ceiling heights of the
+/// spot it was spawned at.
+pub fn vile_fire_spawn(target: &Actor) -> (Fixed, Fixed, Fixed) {
+    (target.x, target.x, target.z)
+}
+
+/// `A_Fire`: in front of the victim, at its feet. The caller leaves the fire where it is if
+/// the vile can't see the victim.
+pub fn fire_position(victim: &Actor, trig: &impl Trig) -> (Fixed, Fixed, Fixed) {
+    let (cos, sin) = trig.fine(victim.angle);
+    (
+        victim.x.wrapping_add(fixed_mul(FIRE_DISTANCE, cos)),
+        victim.y.wrapping_add(fixed_mul(FIRE_DISTANCE, sin)),
+        victim.z,
+    )
+}
+
+/// `A_VileAttack` after the damage: the victim's new upward momentum, which replaces the
+/// thrust the damage gave rather than adding to it. A patched mass of 0 divided by zero in
+/// vanilla; here it launches nothing.
+pub fn vile_launch(mass: i32) -> Fixed {
+    (1000 * FRACUNIT).checked_div(mass).unwrap_or(0)
+}
+
+/// Where the fire goes for the blast: between the victim and the vile, facing as the vile
+/// does. Vanilla moves it without relinking it, so the blockmap still has it where it burned.
+pub fn vile_blast_position(vile: &Actor, victim: &Actor, trig: &impl Trig) -> (Fixed, Fixed) {
+    let (cos, sin) = trig.fine(vile.angle);
+    (
+        victim.x.wrapping_sub(fixed_mul(FIRE_DISTANCE, cos)),
+        victim.y.wrapping_sub(fixed_mul(FIRE_DISTANCE, sin)),
+    )
+}
+
+/// Whether a pain elemental may add a lost soul to a level that has `souls` of them, under
+/// the limit from `GameplayLimits`. Vanilla only gives up once there are more than 20, so
+/// it stops at 21.
+pub fn soul_allowed(souls: usize, limit: Option<usize>) -> bool {
+    limit.is_none_or(|limit| souls <= limit)
+}
+
+/// `A_PainShootSkull`: where the lost soul appears, far enough along `angle` to clear both
+/// radii. There is no check for walls in between unless `skull_walls_fixed` is set, so the
+/// caller asks its map then. A soul that doesn't fit where it appears is damaged by
+/// `SKULL_SPAWN_KILL`, and `A_PainDie` shoots three of them, at 90, 180 and 270 degrees.
+pub fn skull_spawn(
+    pain: &Actor,
+    angle: Angle,
+    pain_radius: Fixed,
+    skull_radius: Fixed,
+    trig: &impl Trig,
+) -> (Fixed, Fixed, Fixed) {
+    let prestep = 4 * FRACUNIT + 3 * (pain_radius + skull_radius) / 2;
+    let (cos, sin) = trig.fine(angle);
+    (
+        pain.x.wrapping_add(fixed_mul(prestep, cos)),
+        pain.y.wrapping_add(fixed_mul(prestep, sin)),
+        pain.z + 8 * FRACUNIT,
+    )
+}
+
+/// `A_SkullAttack`, after `A_FaceTarget`: the soul flies the way it faces at `SKULLSPEED`,
+/// climbing or diving to reach the middle of the target by the time it covers the distance.
+pub fn skull_attack(soul: &mut Actor, target: &Actor, trig: &impl Trig) {
+    soul.flags |= MF_SKULLFLY;
+    let (cos, sin) = trig.fine(soul.angle);
+    soul.momx = fixed_mul(SKULLSPEED, cos);
+    soul.momy = fixed_mul(SKULLSPEED, sin);
+    let distance = approx_distance(target.x.wrapping_sub(soul.x), target.y.wrapping_sub(soul.y));
+    let tics = (distance / SKULLSPEED).max(1);
+    soul.momz = (target.z + (target.height >> 1) - soul.z) / tics;
+}
+
+/// Ends a charge, as the soul returns to its spawn state.
+fn skull_stop(soul: &mut Actor) {
+    soul.flags &= !MF_SKULLFLY;
+    soul.momx = 0;
+    soul.momy = 0;
+    soul.momz = 0;
+}
+
+/// `PIT_CheckThing` for a charging soul running into something solid: the damage it does,
+/// 1 to 8 times its info damage. The charge ends and the caller sets the spawn state.
+pub fn skull_slam(soul: &mut Actor, damage: i32, random: &mut impl FnMut() -> i32) -> i32 {
+    let damage = (random() % 8 + 1) * damage;
+    skull_stop(soul);
+    damage
+}
+
+/// `P_XYMovement` for a charging soul: once a wall has stopped it, the charge ends on the
+/// next tic. Returns whether it did, for the caller to set the spawn state.
+pub fn skull_stalled(soul: &mut Actor) -> bool {
+    let stalled = soul.flags & MF_SKULLFLY != 0 && soul.momx == 0 && soul.momy == 0;
+    if stalled {
+        skull_stop(soul);
+    }
+    stalled
+}
+
+/// Whether a charging soul bounces off the floor. The Doom II 1.9 exe lost the bounce, so
+/// souls skim along the floor there, but the Ultimate Doom and Final Doom exes have it, which
+/// `ultimate_exe` says. MBF bounces whatever the game.
+pub fn skull_bounces(compat: &Compatibility, ultimate_exe: bool) -> bool {
+    compat.lost_souls_bounce || ultimate_exe
+}
+
+/// `P_ZMovement` for a soul that went through the floor at `floorz`.
+pub fn skull_floor(soul: &mut Actor, floorz: Fixed, bounce: bool) {
+    if soul.flags & MF_SKULLFLY != 0 && bounce {
+        soul.momz = -soul.momz;
+    }
+    soul.momz = soul.momz.max(0);
+    soul.z = floorz;
+}
+
+/// `P_ZMovement` for a soul that went through the ceiling at `ceilingz`. Vanilla stops the
+/// climb before turning it around, so there is nothing to turn and a charging soul sticks to
+/// the ceiling; MBF turns it around first.
+pub fn skull_ceiling(soul: &mut Actor, ceilingz: Fixed, compat: &Compatibility) {
+    let charging = soul.flags & MF_SKULLFLY != 0;
+    if charging && compat.lost_souls_bounce {
+        soul.momz = -soul.momz;
+    }
+    soul.momz = soul.momz.min(0);
+    soul.z = ceilingz - soul.height;
+    if charging && !compat.lost_souls_bounce {
+        soul.momz = -soul.momz;
+    }
+}
+
+/// Whether `A_Tracer` does anything this tic. It tests `gametic` rather than the level's
+/// time, and the missile's frames call it every two tics, so a missile either steers on every
+/// other call or never: whether a revenant's missile homes is decided by the tic it was
+/// fired on.
+pub fn tracer_tic(gametic: i32) -> bool {
+    gametic & 3 == 0
+}
+
+/// The puff and smoke `A_Tracer` leaves behind a missile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trail {
+    /// `P_SpawnPuff` at the missile's position: its z nudged at random, and its tics.
+    pub puff_z: Fixed,
+    pub puff_tics: i32,
+    /// Smoke where the missile was a tic ago, and its tics.
+    pub smoke: (Fixed, Fixed, Fixed),
+    pub smoke_tics: i32,
+}
+
+/// The trail, with the random numbers taken in vanilla's order. `puff_tics` and `smoke_tics`
+/// are the lengths of their spawn frames. The puff starts at `S_PUFF3` if the last line
+/// attack anyone made was at melee range, since `P_SpawnPuff` checks that leftover global;
+/// that is up to the caller.
+pub fn tracer_trail(
+    missile: &Actor,
+    puff_tics: i32,
+    smoke_tics: i32,
+    random: &mut impl FnMut() -> i32,
+) -> Trail {
+    let nudge = random();
+    let puff_z = missile.z + ((nudge - random()) << 10);
+    let puff_tics = (puff_tics - (random() & 3)).max(1);
+    let smoke_tics = (smoke_tics - (random() & 3)).max(1);
+    Trail {
+        puff_z,
+        puff_tics,
+        smoke: (
+            missile.x.wrapping_sub(missile.momx),
+            missile.y.wrapping_sub(missile.momy),
+            missile.z,
+        ),
+        smoke_tics,
+    }
+}
+
+/// `A_Tracer`'s steering toward a living `target`: a turn of at most `TRACEANGLE`, full
+/// speed the new way, and an eighth of a unit per tic up or down toward 40 units above the
+/// target's feet. `speed` is the missile's info speed.
+pub fn tracer_steer(missile: &mut Actor, speed: Fixed, target: &Actor, trig: &impl Trig) {
+    let dx = target.x.wrapping_sub(missile.x);
+    let dy = target.y.wrapping_sub(missile.y);
+    let exact = trig.point_to_angle(dx, dy);
+    if exact != missile.angle {
+        if exact.wrapping_sub(missile.angle) > 0x8000_0000 {
+            missile.angle = missile.angle.wrapping_sub(TRACEANGLE);
+            if exact.wrapping_sub(missile.angle) < 0x8000_0000 {
+                missile.angle = exact;
+            }
+        } else {
+            missile.angle = missile.angle.wrapping_add(TRACEANGLE);
+            if exact.wrapping_sub(missile.angle) > 0x8000_0000 {
+                missile.angle = exact;
+            }
+        }
+    }
+    let (cos, sin) = trig.fine(missile.angle);
+    missile.momx = fixed_mul(speed, cos);
+    missile.momy = fixed_mul(speed, sin);
+    let tics = (approx_distance(dx, dy) / speed).max(1);
+    let slope = (target.z + 40 * FRACUNIT - missile.z) / tics;
+    if slope < missile.momz {
+        missile.momz -= FRACUNIT / 8;
+    } else {
+        missile.momz += FRACUNIT / 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::compatibility::CompLevel;
+    use crate::util::angle::{ANG180, ANG270, ANG45, ANG90};
+    use crate::util::fixed::to_fixed;
+    use alloc::vec::Vec;
+
+    /// Rounds every angle to the nearest compass point, which is all the tests aim at.
+    struct CompassTrig;
+
+    impl Trig for CompassTrig {
+        fn fine(&self, angle: Angle) -> (Fixed, Fixed) {
+            match angle.wrapping_add(ANG45) >> 30 {
+                0 => (FRACUNIT, 0),
+                1 => (0, FRACUNIT),
+                2 => (-FRACUNIT, 0),
+                _ => (0, -FRACUNIT),
+            }
+        }
+
+        fn point_to_angle(&self, dx: Fixed, dy: Fixed) -> Angle {
+            match (dx.abs() >= dy.abs(), dx >= 0, dy >= 0) {
+                (true, true, _) => 0,
+                (true, false, _) => ANG180,
+                (false, _, true) => ANG90,
+                (false, _, false) => ANG270,
+            }
+        }
+
+        fn fine_tangent(&self, _index: usize) -> Fixed {
+            0
+        }
+
+        fn tan_to_angle(&self, _index: usize) -> Angle {
+            0
+        }
+    }
+
+    fn imp() -> MobjInfo {
+        MobjInfo {
+            spawn_health: 60,
+            radius: to_fixed(20),
+            height: to_fixed(56),
+            flags: MF_SOLID,
+            raise_state: 455,
+            ..MobjInfo::default()
+        }
+    }
+
+    #[test]
+    fn crushed_corpses_come_back_as_ghosts() {
+        let vanilla = Compatibility::for_level(CompLevel::Vanilla);
+        let boom = Compatibility::for_level(CompLevel::Boom);
+        let vile = Actor::default();
+        let at = vile_try(&vile, 15, 0).unwrap();
+        assert_eq!(at, (15 * FRACUNIT, 0));
+        assert_eq!(vile_try(&vile, 15, DI_NODIR), None);
+        let blocks: Vec<_> = vile_blocks(at, (-to_fixed(128), -to_fixed(128))).collect();
+        assert_eq!(blocks, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        // A crushed corpse has no size left; vanilla's probe of it fits anywhere.
+        let crushed = Actor {
+            x: to_fixed(30),
+            momx: FRACUNIT,
+            flags: MF_CORPSE,
+            ..Actor::default()
+        };
+        let mut corpse = crushed;
+        let open = |probe: &Actor| probe.height == 0;
+        assert!(vile_check(
+            &mut corpse,
+            -1,
+            &imp(),
+            to_fixed(20),
+            at,
+            &vanilla,
+            open
+        ));
+        vile_raise(&mut corpse, &imp(), &vanilla);
+        assert_eq!((corpse.height, corpse.radius, corpse.health), (0, 0, 60));
+
+        let mut corpse = crushed;
+        assert!(!vile_check(
+            &mut corpse,
+            -1,
+            &imp(),
+            to_fixed(20),
+            at,
+            &boom,
+            open
+        ));
+        assert_eq!(corpse.momx, 0, "stopped even though it wasn't raised");
+        vile_raise(&mut corpse, &imp(), &boom);
+        assert_eq!(corpse.height, to_fixed(56));
+
+        // Still in its death frames, or out of reach.
+        let mut corpse = crushed;
+        assert!(!vile_check(
+            &mut corpse,
+            5,
+            &imp(),
+            to_fixed(20),
+            at,
+            &vanilla,
+            open
+        ));
+        corpse.x = to_fixed(60);
+        assert!(!vile_check(
+            &mut corpse,
+            -1,
+            &imp(),
+            to_fixed(20),
+            at,
+            &vanilla,
+            open
+        ));
+        assert_eq!(corpse.momx, FRACUNIT);
+    }
+
+    #[test]
+    fn the_vile_fire_and_blast() {
+        let victim = Actor {
+            x: to_fixed(100),
+            y: to_fixed(-40),
+            z: to_fixed(8),
+            angle: ANG90,
+            ..Actor::default()
+        };
+        assert_eq!(
+            vile_fire_spawn(&victim),
+            (to_fixed(100), to_fixed(100), to_fixed(8))
+        );
+        assert_eq!(
+            fire_position(&victim, &CompassTrig),
+            (to_fixed(100), to_fixed(-16), to_fixed(8))
+        );
+        let vile = Actor::default();
+        assert_eq!(
+            vile_blast_position(&vile, &victim, &CompassTrig),
+            (to_fixed(76), to_fixed(-40))
+        );
+        assert_eq!(vile_launch(100), to_fixed(10));
+        assert_eq!(vile_launch(0), 0);
+    }
+
+    #[test]
+    fn pain_elementals_stop_at_twenty_one_souls() {
+        assert!(soul_allowed(20, Some(20)), "vanilla's off by one");
+        assert!(!soul_allowed(21, Some(20)));
+        assert!(soul_allowed(500, None));
+        let pain = Actor {
+            z: to_fixed(10),
+            ..Actor::default()
+        };
+        // 4 + 3 * (31 + 16) / 2 = 74.5 units ahead, 8 up.
+        let at = skull_spawn(&pain, ANG180, to_fixed(31), to_fixed(16), &CompassTrig);
+        assert_eq!(at, (-(74 * FRACUNIT + FRACUNIT / 2), 0, to_fixed(18)));
+    }
+
+    #[test]
+    fn lost_souls_charge_and_bounce() {
+        let vanilla = Compatibility::for_level(CompLevel::Vanilla);
+        let mbf = Compatibility::for_level(CompLevel::Mbf);
+        let mut soul = Actor {
+            height: to_fixed(56),
+            ..Actor::default()
+        };
+        let target = Actor {
+            x: to_fixed(200),
+            z: to_fixed(28),
+            height: to_fixed(56),
+            ..Actor::default()
+        };
+        skull_attack(&mut soul, &target, &CompassTrig);
+        assert_eq!((soul.momx, soul.momy), (SKULLSPEED, 0));
+        // Ten tics to cover 200 units, rising 56.
+        assert_eq!(soul.momz, to_fixed(56) / 10);
+        assert_ne!(soul.flags & MF_SKULLFLY, 0);
+
+        let diving = Actor {
+            momz: -FRACUNIT,
+            ..soul
+        };
+        let mut skimming = diving;
+        skull_floor(&mut skimming, 0, skull_bounces(&vanilla, false));
+        assert_eq!(skimming.momz, 0, "Doom II 1.9");
+        let mut bouncing = diving;
+        skull_floor(&mut bouncing, 0, skull_bounces(&vanilla, true));
+        assert_eq!(bouncing.momz, FRACUNIT, "Ultimate Doom");
+
+        let mut stuck = soul;
+        skull_ceiling(&mut stuck, to_fixed(128), &vanilla);
+        assert_eq!((stuck.momz, stuck.z), (0, to_fixed(72)));
+        let mut turned = soul;
+        skull_ceiling(&mut turned, to_fixed(128), &mbf);
+        assert_eq!(turned.momz, -soul.momz);
+
+        let mut rolls = [13, 0].into_iter();
+        assert_eq!(skull_slam(&mut soul, 3, &mut || rolls.next().unwrap()), 18);
+        assert_eq!((soul.flags & MF_SKULLFLY, soul.momx), (0, 0));
+        assert!(!skull_stalled(&mut soul));
+    }
+
+    #[test]
+    fn revenant_missiles_home_on_every_fourth_tic() {
+        // Fired on an odd tic, the missile's calls never land on a multiple of four.
+        assert!((0..20).map(|i| 1 + 2 * i).all(|tic| !tracer_tic(tic)));
+        let steering: Vec<_> = (0..4).map(|i| tracer_tic(2 * i)).collect();
+        assert_eq!(steering, [true, false, true, false]);
+
+        let speed = to_fixed(10);
+        let mut missile = Actor {
+            z: to_fixed(32),
+            ..Actor::default()
+        };
+        let target = Actor {
+            y: to_fixed(100),
+            ..Actor::default()
+        };
+        tracer_steer(&mut missile, speed, &target, &CompassTrig);
+        assert_eq!(missile.angle, TRACEANGLE, "turns at most TRACEANGLE");
+        assert_eq!(missile.momz, FRACUNIT / 8, "climbing toward 40 units up");
+        for _ in 0..5 {
+            tracer_steer(&mut missile, speed, &target, &CompassTrig);
+        }
+        assert_eq!(missile.angle, ANG90, "then holds the exact angle");
+
+        missile.momx = to_fixed(10);
+        missile.momy = 0;
+        let mut rolls = [200, 100, 7, 2].into_iter();
+        let trail = tracer_trail(&missile, 4, 4, &mut || rolls.next().unwrap());
+        assert_eq!(trail.puff_z, to_fixed(32) + (100 << 10));
+        assert_eq!((trail.puff_tics, trail.smoke_tics), (1, 2));
+        assert_eq!(trail.smoke, (-to_fixed(10), 0, to_fixed(32)));
+    }
+}
@@ -0,0 +1,121 @@
+//! Pausing: a ticcmd like any other, so demos and netgames pause together.
+//!
+//! As in vanilla, pressing Pause doesn't stop anything by itself. The next ticcmd carries
+//! `BT_SPECIAL | BTS_PAUSE` instead of its buttons, and `G_Ticker` flips the pause for every
+//! player's command that has it. That way every node of a netgame pauses on the same tic,
+//! whoever pressed the key, and a demo records the pause and plays it back where it happened.
+//! While paused the tics keep coming, so the game can be unpaused, but the thinkers and the
+//! level time stand still. Drawing and the menu carry on, and the music too with `pause_music`.
+
+use super::ticcmd::{TicCmd, BTS_PAUSE, BT_SPECIAL, BT_SPECIALMASK};
+use crate::engine::config::{Config, Configurable, Section};
+
+/// The patch drawn at the top of the view while paused.
+pub const PAUSE_PATCH: &str = "M_PAUSE";
+
+/// `cmd` with its buttons replaced by the pause request, as `G_BuildTiccmd` sends it.
+pub fn with_pause(cmd: TicCmd) -> TicCmd {
+    TicCmd {
+        buttons: BT_SPECIAL | BTS_PAUSE,
+        ..cmd
+    }
+}
+
+pub fn is_pause(cmd: &TicCmd) -> bool {
+    cmd.buttons & BT_SPECIAL != 0 && cmd.buttons & BT_SPECIALMASK == BTS_PAUSE
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PauseState {
+    pub paused: bool,
+    /// The player whose command paused the game last, by their place in the tic's commands.
+    pub by: Option<usize>,
+}
+
+impl PauseState {
+    /// Takes one tic's commands, returning whether the game is paused for it. Two players
+    /// pausing on the same tic cancel out, as in vanilla.
+    pub fn ticker(&mut self, cmds: &[TicCmd]) -> bool {
+        for (player, cmd) in cmds.iter().enumerate() {
+            if is_pause(cmd) {
+                self.paused = !self.paused;
+                self.by = self.paused.then_some(player);
+            }
+        }
+        self.paused
+    }
+
+    /// Who paused, for the netgame message under the pause patch. Nothing in single player,
+    /// where the patch says it all.
+    pub fn message(&self, players: usize) -> Option<String> {
+        match (self.paused, self.by) {
+            (true, Some(player)) if players > 1 => Some(format!("paused by player {}", player + 1)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PauseOptions {
+    /// Keeps the music playing while paused; vanilla stops it.
+    pub music: bool,
+}
+
+impl Configurable for PauseOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("pause_music", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            music: config.get_bool("pause_music"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("pause_music", self.music);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::ticcmd::{BT_ATTACK, BT_CHANGE};
+
+    #[test]
+    fn the_pause_request_replaces_the_buttons() {
+        let cmd = TicCmd {
+            forwardmove: 25,
+            buttons: BT_ATTACK | BT_CHANGE,
+            ..TicCmd::default()
+        };
+        let paused = with_pause(cmd);
+        assert!(is_pause(&paused));
+        assert_eq!(
+            (paused.forwardmove, paused.buttons),
+            (25, BT_SPECIAL | BTS_PAUSE)
+        );
+        assert!(!is_pause(&cmd));
+        // A save request is special too, but not a pause.
+        assert!(!is_pause(&TicCmd {
+            buttons: BT_SPECIAL | 2,
+            ..TicCmd::default()
+        }));
+    }
+
+    #[test]
+    fn any_player_can_pause_and_unpause() {
+        let pause = with_pause(TicCmd::default());
+        let idle = TicCmd::default();
+        let mut state = PauseState::default();
+        assert!(!state.ticker(&[idle, idle]));
+        assert!(state.ticker(&[idle, pause]));
+        assert_eq!(state.message(2).as_deref(), Some("paused by player 2"));
+        assert_eq!(state.message(1), None);
+        assert!(state.ticker(&[idle, idle]));
+        assert!(!state.ticker(&[pause, idle]));
+        assert_eq!(state.message(2), None);
+        // Both at once cancel out.
+        assert!(!state.ticker(&[pause, pause]));
+    }
+}
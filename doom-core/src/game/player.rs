@@ -0,0 +1,251 @@
+//! What a player carries: health, armor, weapons, ammo, keys and powers.
+//!
+//! `Inventory::initial` is what `G_PlayerReborn` hands a new player. The level transition
+//! decides what carries over into the next level: keys and powers never do, and with pistol
+//! start nothing does. Pistol start lives here, in the transition, instead of killing and
+//! respawning the player. Every player in a coop game gets it equally, and a savegame made
+//! mid-level keeps its inventory when loaded, since loading doesn't enter a level.
+
+use super::cheat::Power;
+use super::info::{GameInfo, NUMAMMO};
+use super::savegame::{Archive, SaveError, SaveReader, SaveWriter};
+use super::TICRATE;
+use crate::engine::config::{Config, Configurable, Section};
+
+pub const NUMWEAPONS: usize = 9;
+pub const NUMCARDS: usize = 6;
+pub const NUMPOWERS: usize = 6;
+
+pub const WP_FIST: usize = 0;
+pub const WP_PISTOL: usize = 1;
+pub const AM_CLIP: usize = 0;
+/// How long the timed powers last.
+pub const INVULNTICS: i32 = 30 * TICRATE;
+pub const INVISTICS: i32 = 60 * TICRATE;
+pub const INFRATICS: i32 = 120 * TICRATE;
+pub const IRONTICS: i32 = 60 * TICRATE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Inventory {
+    pub health: i32,
+    pub armor_points: i32,
+    pub armor_type: i32, // 0 none, 1 green, 2 blue.
+    pub weapons: [bool; NUMWEAPONS],
+    pub ready_weapon: usize,
+    pub ammo: [i32; NUMAMMO],
+    pub max_ammo: [i32; NUMAMMO], // Doubled by the backpack.
+    pub backpack: bool,
+    pub cards: [bool; NUMCARDS],
+    pub powers: [i32; NUMPOWERS], // Tics left, or 1 for the ones that last the level.
+}
+
+impl Inventory {
+    /// `G_PlayerReborn`: a pistol, fists and the starting bullets.
+    pub fn initial(info: &GameInfo) -> Self {
+        let mut weapons = [false; NUMWEAPONS];
+        weapons[WP_FIST] = true;
+        weapons[WP_PISTOL] = true;
+        let mut ammo = [0; NUMAMMO];
+        ammo[AM_CLIP] = info.misc.initial_bullets;
+        Self {
+            health: info.misc.initial_health,
+            armor_points: 0,
+            armor_type: 0,
+            weapons,
+            ready_weapon: WP_PISTOL,
+            ammo,
+            max_ammo: info.max_ammo,
+            backpack: false,
+            cards: [false; NUMCARDS],
+            powers: [0; NUMPOWERS],
+        }
+    }
+
+    /// `P_GivePower`: a timed power runs from full, the others last the level. Berserk heals
+    /// up to 100 health too.
+    pub fn give_power(&mut self, power: Power) {
+        self.powers[power as usize] = match power {
+            Power::Invulnerability => INVULNTICS,
+            Power::Invisibility => INVISTICS,
+            Power::Infrared => INFRATICS,
+            Power::IronFeet => IRONTICS,
+            Power::Strength => {
+                self.health = self.health.max(100);
+                1
+            }
+            Power::AllMap => 1,
+        };
+    }
+
+    /// `G_PlayerFinishLevel`: keys and powers stay behind when the player exits.
+    pub fn finish_level(&mut self) {
+        self.cards = [false; NUMCARDS];
+        self.powers = [0; NUMPOWERS];
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PistolStart {
+    pub enabled: bool,
+}
+
+impl PistolStart {
+    /// Called as a level is entered from the previous one, for every player in the game.
+    pub fn enter_level<'a>(
+        &self,
+        players: impl IntoIterator<Item = &'a mut Inventory>,
+        info: &GameInfo,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        for inventory in players {
+            *inventory = Inventory::initial(info);
+        }
+    }
+}
+
+impl Configurable for PistolStart {
+    fn register(config: &mut Config) {
+        config.register_bool("pistol_start", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.get_bool("pistol_start"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("pistol_start", self.enabled);
+    }
+}
+
+impl Archive for Inventory {
+    fn archive(&self, writer: &mut SaveWriter) {
+        writer.write_i32(self.health);
+        writer.write_i32(self.armor_points);
+        writer.write_i32(self.armor_type);
+        for &owned in &self.weapons {
+            writer.write_bool(owned);
+        }
+        writer.write_u8(self.ready_weapon as u8);
+        writer.pad();
+        for &count in self.ammo.iter().chain(&self.max_ammo) {
+            writer.write_i32(count);
+        }
+        writer.write_bool(self.backpack);
+        for &owned in &self.cards {
+            writer.write_bool(owned);
+        }
+        writer.pad();
+        for &tics in &self.powers {
+            writer.write_i32(tics);
+        }
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        let health = reader.read_i32()?;
+        let armor_points = reader.read_i32()?;
+        let armor_type = reader.read_i32()?;
+        let mut weapons = [false; NUMWEAPONS];
+        for owned in &mut weapons {
+            *owned = reader.read_bool()?;
+        }
+        let ready_weapon = reader.read_u8()? as usize;
+        if ready_weapon >= NUMWEAPONS {
+            return Err(reader.error(format!("bad weapon {ready_weapon}")));
+        }
+        reader.pad()?;
+        let mut ammo = [0; NUMAMMO];
+        for count in &mut ammo {
+            *count = reader.read_i32()?;
+        }
+        let mut max_ammo = [0; NUMAMMO];
+        for count in &mut max_ammo {
+            *count = reader.read_i32()?;
+        }
+        let backpack = reader.read_bool()?;
+        let mut cards = [false; NUMCARDS];
+        for owned in &mut cards {
+            *owned = reader.read_bool()?;
+        }
+        reader.pad()?;
+        let mut powers = [0; NUMPOWERS];
+        for tics in &mut powers {
+            *tics = reader.read_i32()?;
+        }
+        Ok(Self {
+            health,
+            armor_points,
+            armor_type,
+            weapons,
+            ready_weapon,
+            ammo,
+            max_ammo,
+            backpack,
+            cards,
+            powers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stocked(info: &GameInfo) -> Inventory {
+        let mut inventory = Inventory::initial(info);
+        inventory.health = 150;
+        inventory.weapons[5] = true;
+        inventory.ready_weapon = 5;
+        inventory.ammo = [120, 40, 20, 300];
+        inventory.backpack = true;
+        inventory.cards[0] = true;
+        inventory.powers[1] = 1;
+        inventory
+    }
+
+    #[test]
+    fn levels_keep_the_inventory_unless_pistol_starting() {
+        let info = GameInfo::default();
+        let mut players = [stocked(&info), stocked(&info)];
+        for player in &mut players {
+            player.finish_level();
+        }
+        PistolStart::default().enter_level(&mut players, &info);
+        assert_eq!(players[0].health, 150);
+        assert!(!players[0].cards[0]);
+        assert_eq!(players[0].powers, [0; NUMPOWERS]);
+
+        PistolStart { enabled: true }.enter_level(&mut players, &info);
+        for player in &players {
+            assert_eq!(*player, Inventory::initial(&info));
+            assert_eq!(player.ammo, [50, 0, 0, 0]);
+            assert_eq!(player.ready_weapon, WP_PISTOL);
+        }
+    }
+
+    #[test]
+    fn powers_run_their_time_or_the_level() {
+        let mut inventory = Inventory::initial(&GameInfo::default());
+        inventory.health = 20;
+        inventory.give_power(Power::IronFeet);
+        inventory.give_power(Power::Strength);
+        assert_eq!(inventory.powers[Power::IronFeet as usize], 60 * TICRATE);
+        assert_eq!(inventory.powers[Power::Strength as usize], 1);
+        assert_eq!(inventory.health, 100, "berserk heals");
+    }
+
+    #[test]
+    fn inventory_round_trips_through_a_savegame() {
+        let info = GameInfo::default();
+        let inventory = stocked(&info);
+        let bytes = inventory.to_bytes();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(Inventory::from_bytes(&bytes), Ok(inventory));
+        let mut bad = bytes;
+        bad[12 + NUMWEAPONS] = 9;
+        assert!(Inventory::from_bytes(&bad).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! Level precaching: decoding everything a map uses before its first tic.
+//!
+//! Vanilla's `R_PrecacheLevel` read the flats, wall textures and sprites of a level into the
+//! zone cache, so nothing had to come off the disk mid-game. Here `PrecacheList` collects the
+//! same names, plus the sounds of the things on the map, and `LevelCache::precache` decodes
+//! them on worker threads while the level loads. Sprites count every frame of every state a
+//! thing can reach, so a monster's death frames are ready before it dies. Wall textures are
+//! listed but not decoded yet, since composing them needs the TEXTURE1 reader. `-nodemoprecache`
+//! turns it off and leaves decoding to first use.
+
+use super::info::GameInfo;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::renderer::patch::Patch;
+use crate::wad::cache::LumpCache;
+use crate::wad::file::Wads;
+use crate::wad::map::MapData;
+use std::collections::BTreeSet;
+use std::thread;
+
+const FLAT_SIZE: usize = 64 * 64;
+
+/// Lumps each worker decodes between progress reports.
+const PRECACHE_BATCH: usize = 16;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrecacheList {
+    pub textures: BTreeSet<String>,
+    pub flats: BTreeSet<String>,
+    pub sprites: BTreeSet<String>, // The four letter sprite names; every frame is loaded.
+    pub sounds: BTreeSet<String>,  // Lump names, `DS` included.
+}
+
+impl PrecacheList {
+    pub fn for_level(map: &MapData, info: &GameInfo, sky: &str) -> Self {
+        let mut list = Self::default();
+        for side in &map.sidedefs {
+            for texture in [&side.upper, &side.lower, &side.middle] {
+                if !texture.is_empty() && texture != "-" {
+                    list.textures.insert(texture.clone());
+                }
+            }
+        }
+        list.textures.insert(sky.to_string());
+        for sector in &map.sectors {
+            list.flats.insert(sector.floor_pic.clone());
+            list.flats.insert(sector.ceiling_pic.clone());
+        }
+
+        let kinds: BTreeSet<i32> = map.things.iter().map(|thing| thing.kind as i32).collect();
+        let mut states = BTreeSet::new();
+        for mobj in info
+            .mobjs
+            .iter()
+            .filter(|mobj| kinds.contains(&mobj.doomednum))
+        {
+            for start in [
+                mobj.spawn_state,
+                mobj.see_state,
+                mobj.pain_state,
+                mobj.melee_state,
+                mobj.missile_state,
+                mobj.death_state,
+                mobj.xdeath_state,
+                mobj.raise_state,
+            ] {
+                // State 0 is S_NULL, where a chain ends.
+                let mut state = start;
+                while state != 0 && state < info.states.len() && states.insert(state) {
+                    state = info.states[state].next_state;
+                }
+            }
+            for sound in [
+                mobj.see_sound,
+                mobj.attack_sound,
+                mobj.pain_sound,
+                mobj.death_sound,
+                mobj.active_sound,
+            ] {
+                if let Some(name) = info.sounds.get(sound).filter(|_| sound != 0) {
+                    list.sounds
+                        .insert(format!("DS{}", name.to_ascii_uppercase()));
+                }
+            }
+        }
+        for &state in &states {
+            if let Some(sprite) = info.sprites.get(info.states[state].sprite) {
+                list.sprites.insert(sprite.to_ascii_uppercase());
+            }
+        }
+        list
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrecacheOptions {
+    pub enabled: bool,
+    pub threads: usize, // 0 uses one per core.
+}
+
+impl Default for PrecacheOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threads: 0,
+        }
+    }
+}
+
+impl PrecacheOptions {
+    pub fn worker_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |count| count.get()),
+            threads => threads,
+        }
+    }
+}
+
+impl Configurable for PrecacheOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("precache_level", true, Section::Extended);
+        config.register_int("precache_threads", 0, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.get_bool("precache_level"),
+            threads: config.get_int("precache_threads").max(0) as usize,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("precache_level", self.enabled);
+        config.set_int("precache_threads", self.threads as i32);
+    }
+}
+
+/// The decoded resources of the current level, filled by `precache` or on first use.
+#[derive(Debug, Default)]
+pub struct LevelCache {
+    pub patches: LumpCache<Patch>,
+    pub flats: LumpCache<Vec<u8>>,
+    pub sounds: LumpCache<Vec<u8>>,
+}
+
+impl LevelCache {
+    /// Decodes everything on the list, returning how many lumps were decoded.
+    pub fn precache(&self, list: &PrecacheList, wads: &Wads, threads: usize) -> usize {
+        self.precache_reporting(list, wads, threads, &mut |_, _| {})
+    }
+
+    /// `precache`, telling `progress` how many of the lumps are done after each batch. The
+    /// workers decode a batch at a time so the calling thread can report between them.
+    pub fn precache_reporting(
+        &self,
+        list: &PrecacheList,
+        wads: &Wads,
+        threads: usize,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> usize {
+        let raw = |name: &str| wads.lump(name).map(|lump| lump.data.clone());
+        let flats: Vec<&str> = list.flats.iter().map(String::as_str).collect();
+        let sounds: Vec<&str> = list.sounds.iter().map(String::as_str).collect();
+        let sprite_frames: Vec<&str> = wads
+            .files()
+            .iter()
+            .flat_map(|file| &file.lumps)
+            .map(|lump| lump.name.as_str())
+            .filter(|name| name.len() > 4 && list.sprites.contains(&name[..4]))
+            .collect();
+
+        let total = flats.len() + sounds.len() + sprite_frames.len();
+        let batch = threads.max(1) * PRECACHE_BATCH;
+        let (mut done, mut decoded) = (0, 0);
+        progress(done, total);
+        let flat = |name: &str| raw(name).filter(|data| data.len() >= FLAT_SIZE);
+        for names in flats.chunks(batch) {
+            decoded += self.flats.fill(names, threads, flat);
+            done += names.len();
+            progress(done, total);
+        }
+        for names in sounds.chunks(batch) {
+            decoded += self.sounds.fill(names, threads, raw);
+            done += names.len();
+            progress(done, total);
+        }
+        for names in sprite_frames.chunks(batch) {
+            decoded += self
+                .patches
+                .fill(names, threads, |name| Patch::parse(&wads.lump(name)?.data));
+            done += names.len();
+            progress(done, total);
+        }
+        decoded
+    }
+
+    /// The bytes of pixels and samples held, for the performance overlay.
+    pub fn bytes(&self) -> usize {
+        let pixels = |patch: &Patch| {
+            let posts = patch.columns.iter().flatten();
+            posts.map(|post| post.pixels.len()).sum()
+        };
+        self.patches.bytes(pixels) + self.flats.bytes(Vec::len) + self.sounds.bytes(Vec::len)
+    }
+
+    pub fn clear(&self) {
+        self.patches.clear();
+        self.flats.clear();
+        self.sounds.clear();
+    }
+
+    /// Forgets whatever was decoded from the lumps `names`, after they changed on disk.
+    /// Returns how many decoded lumps were dropped.
+    pub fn invalidate(&self, names: &[String]) -> usize {
+        names
+            .iter()
+            .map(|name| {
+                usize::from(self.patches.remove(name))
+                    + usize::from(self.flats.remove(name))
+                    + usize::from(self.sounds.remove(name))
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::{MobjInfo, State};
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::file::WadFile;
+    use crate::wad::map::tests::two_room_map;
+    use crate::wad::map::Thing;
+    use std::path::Path;
+
+    fn info() -> GameInfo {
+        let state = |sprite: usize, next_state: usize| State {
+            sprite,
+            next_state,
+            ..State::default()
+        };
+        GameInfo {
+            states: vec![state(0, 0), state(1, 2), state(1, 1), state(2, 0)],
+            mobjs: vec![MobjInfo {
+                doomednum: 3004,
+                spawn_state: 1,
+                death_state: 3,
+                see_sound: 1,
+                ..MobjInfo::default()
+            }],
+            sprites: vec!["TROO".into(), "POSS".into(), "PLAY".into()],
+            sounds: vec!["".into(), "posit1".into()],
+            ..GameInfo::default()
+        }
+    }
+
+    #[test]
+    fn lists_what_the_map_and_its_things_use() {
+        let mut map = two_room_map();
+        map.things.push(Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind: 3004,
+            flags: 7,
+        });
+        let list = PrecacheList::for_level(&map, &info(), "SKY1");
+        assert_eq!(
+            list.textures.iter().collect::<Vec<_>>(),
+            ["SKY1", "STARTAN3", "STEP1"]
+        );
+        assert_eq!(list.flats.len(), 2);
+        assert_eq!(list.sprites.iter().collect::<Vec<_>>(), ["PLAY", "POSS"]);
+        assert_eq!(list.sounds.iter().collect::<Vec<_>>(), ["DSPOSIT1"]);
+    }
+
+    #[test]
+    fn precache_decodes_on_workers() {
+        let patch = [1, 0, 1, 0, 0, 0, 0, 0, 12, 0, 0, 0, 0, 1, 0, 5, 0, 0xff];
+        let bytes = build_wad(
+            b"PWAD",
+            &[
+                ("POSSA1", &patch),
+                ("POSSB1", &patch),
+                ("TROOA1", &patch),
+                ("FLOOR4_8", &[0; FLAT_SIZE]),
+                ("DSPOSIT1", &[3, 0]),
+            ],
+        );
+        let mut wads = Wads::new();
+        wads.add(WadFile::parse(Path::new("test.wad"), &bytes).unwrap());
+        let list = PrecacheList {
+            flats: ["FLOOR4_8".to_string(), "CEIL3_5".to_string()].into(),
+            sprites: ["POSS".to_string()].into(),
+            sounds: ["DSPOSIT1".to_string()].into(),
+            ..PrecacheList::default()
+        };
+        let cache = LevelCache::default();
+        let mut reports = Vec::new();
+        let decoded = cache.precache_reporting(&list, &wads, 3, &mut |done, total| {
+            reports.push((done, total));
+        });
+        assert_eq!(decoded, 5);
+        assert_eq!(reports, [(0, 5), (2, 5), (3, 5), (5, 5)]);
+        assert!(cache.patches.contains("POSSB1") && !cache.patches.contains("TROOA1"));
+        assert!(cache.flats.get("CEIL3_5", |_| unreachable!()).is_none());
+        assert_eq!(cache.patches.get("POSSA1", |_| None).unwrap().width, 1);
+
+        let changed = ["POSSA1", "DSPOSIT1", "TROOA1"].map(String::from);
+        assert_eq!(cache.invalidate(&changed), 2);
+        assert!(!cache.patches.contains("POSSA1") && cache.patches.contains("POSSB1"));
+    }
+}
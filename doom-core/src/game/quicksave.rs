@@ -0,0 +1,360 @@
+//! Savegame slots, their descriptions, and quick save and quick load.
+//!
+//! Vanilla's quick save asks for a slot the first time and then asks "quicksave over your game
+//! named ...?" every time after; quick load asks the same way. With `quicksave_confirm` off
+//! both go straight through once a slot is picked. A slot is still picked through the save
+//! menu, so the first quick save of a session always shows it.
+//!
+//! Vanilla leaves a new description blank. Here the name is typed in the HUD's `TextEntry`,
+//! started with a description of the map and the time, which Enter keeps as it is.
+//!
+//! A savegame starts with a `SaveHeader`: vanilla's zero padded description, then a
+//! thumbnail of the frame it was saved from. The menus read just the header of each file and
+//! draw the thumbnail of the highlighted slot. Slot n is saved as vanilla names it,
+//! `doomsav<n>.dsg`, in `savegame_folder`.
+
+use super::savegame::{Archive, SaveError, SaveReader, SaveWriter};
+use crate::engine::config::{Config, Configurable, Section};
+use crate::renderer::draw::{Canvas, PixelFormat};
+use crate::renderer::thumbnail::Thumbnail;
+use crate::util::time::date_time;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Vanilla's `SAVESTRINGSIZE`, including the terminating zero.
+pub const SAVESTRINGSIZE: usize = 24;
+/// The slots the load and save menus show.
+pub const SAVE_SLOTS: usize = 6;
+
+/// Vanilla's `SAVEGAMENAME`.
+pub const SAVEGAMENAME: &str = "doomsav";
+
+/// The longest description that fits in a savegame.
+pub const MAX_DESCRIPTION: usize = SAVESTRINGSIZE - 1;
+
+/// A description for a new save: the map and the time, such as `MAP07 2026-10-15 21:04`.
+pub fn describe(map: &str, time: SystemTime) -> String {
+    let mut description = format!("{} {}", map.to_uppercase(), date_time(time));
+    description.truncate(MAX_DESCRIPTION);
+    description
+}
+
+/// Where the save in `slot` is kept.
+pub fn save_path(folder: &Path, slot: usize) -> PathBuf {
+    folder.join(format!("{SAVEGAMENAME}{slot}.dsg"))
+}
+
+/// The start of a savegame, enough for the menus to show it without loading it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveHeader {
+    pub description: String,
+    pub thumbnail: Option<Thumbnail>,
+}
+
+impl SaveHeader {
+    /// Reads the header of a whole savegame, leaving the rest.
+    pub fn peek(data: &[u8]) -> Result<Self, SaveError> {
+        SaveReader::new(data).unarchive()
+    }
+}
+
+impl Archive for SaveHeader {
+    fn archive(&self, writer: &mut SaveWriter) {
+        let mut description = [0; SAVESTRINGSIZE];
+        let len = self.description.len().min(MAX_DESCRIPTION);
+        description[..len].copy_from_slice(&self.description.as_bytes()[..len]);
+        writer.write_bytes(&description);
+        writer.write_bool(self.thumbnail.is_some());
+        if let Some(thumbnail) = &self.thumbnail {
+            writer.archive(thumbnail);
+        }
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        let description = reader.read_bytes(SAVESTRINGSIZE)?;
+        let len = description
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_DESCRIPTION);
+        Ok(Self {
+            description: String::from_utf8_lossy(&description[..len]).into_owned(),
+            thumbnail: match reader.read_bool()? {
+                true => Some(reader.unarchive()?),
+                false => None,
+            },
+        })
+    }
+}
+
+/// What a quick save or quick load key press leads to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuickAction {
+    /// Not possible right now: the `strings` entry explaining why.
+    Refused(&'static str),
+    /// No slot yet: open the save menu, which remembers the slot chosen.
+    PickSlot,
+    /// Ask first, with the `strings` prompt whose `%s` is the description.
+    Confirm {
+        prompt: &'static str,
+        slot: usize,
+        description: String,
+    },
+    Proceed {
+        slot: usize,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveSlots {
+    /// The header of the save in each slot, `None` for an empty one.
+    pub headers: [Option<SaveHeader>; SAVE_SLOTS],
+    pub quicksave_slot: Option<usize>,
+    /// The next save from the menu also picks the quick save slot.
+    picking_quicksave: bool,
+    pub confirm: bool,
+}
+
+impl SaveSlots {
+    pub fn new(confirm: bool) -> Self {
+        Self {
+            confirm,
+            ..Self::default()
+        }
+    }
+
+    /// `M_ReadSaveStrings`: the header of every save in `folder`. A slot whose file is
+    /// missing or unreadable is empty.
+    pub fn read_headers(&mut self, folder: &Path) {
+        for (slot, header) in self.headers.iter_mut().enumerate() {
+            let data = fs::read(save_path(folder, slot));
+            *header = data.ok().and_then(|data| SaveHeader::peek(&data).ok());
+        }
+    }
+
+    /// `M_QuickSave`. `playing` is false when there is no game to save, or the player is
+    /// dead.
+    pub fn quicksave(&mut self, playing: bool) -> QuickAction {
+        if !playing {
+            return QuickAction::Refused("SAVEDEAD");
+        }
+        match self.quicksave_slot {
+            None => {
+                self.picking_quicksave = true;
+                QuickAction::PickSlot
+            }
+            Some(slot) => self.confirm_or_proceed("QSPROMPT", slot),
+        }
+    }
+
+    /// `M_QuickLoad`.
+    pub fn quickload(&self, netgame: bool) -> QuickAction {
+        if netgame {
+            return QuickAction::Refused("QLOADNET");
+        }
+        match self.quicksave_slot {
+            None => QuickAction::Refused("QSAVESPOT"),
+            Some(slot) => self.confirm_or_proceed("QLPROMPT", slot),
+        }
+    }
+
+    fn confirm_or_proceed(&self, prompt: &'static str, slot: usize) -> QuickAction {
+        if !self.confirm {
+            return QuickAction::Proceed { slot };
+        }
+        QuickAction::Confirm {
+            prompt,
+            slot,
+            description: self.description(slot).unwrap_or_default().to_owned(),
+        }
+    }
+
+    pub fn description(&self, slot: usize) -> Option<&str> {
+        self.headers[slot].as_ref().map(|h| h.description.as_str())
+    }
+
+    /// What the name entry for `slot` starts with: its description, or a new one.
+    pub fn suggestion(&self, slot: usize, map: &str, time: SystemTime) -> String {
+        self.description(slot)
+            .map_or_else(|| describe(map, time), str::to_owned)
+    }
+
+    /// Draws the thumbnail of the save in `slot`, if it has one.
+    pub fn draw_thumbnail<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        slot: usize,
+        x: i32,
+        y: i32,
+    ) {
+        let thumbnail = self.headers[slot]
+            .as_ref()
+            .and_then(|h| h.thumbnail.as_ref());
+        if let Some(thumbnail) = thumbnail {
+            thumbnail.draw(format, canvas, x, y);
+        }
+    }
+
+    /// Records a save made to `slot`.
+    pub fn saved(&mut self, slot: usize, mut header: SaveHeader) {
+        header.description.truncate(MAX_DESCRIPTION);
+        self.headers[slot] = Some(header);
+        if self.picking_quicksave {
+            self.picking_quicksave = false;
+            self.quicksave_slot = Some(slot);
+        }
+    }
+
+    /// The save menu was left without saving.
+    pub fn cancelled(&mut self) {
+        self.picking_quicksave = false;
+    }
+}
+
+/// The quick save and quick load settings, and where saves go.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuickSaveOptions {
+    pub confirm: bool,
+    pub folder: PathBuf,
+}
+
+impl Default for QuickSaveOptions {
+    fn default() -> Self {
+        Self {
+            confirm: true,
+            folder: PathBuf::from("."),
+        }
+    }
+}
+
+impl Configurable for QuickSaveOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("quicksave_confirm", true, Section::Extended);
+        config.register_str("savegame_folder", ".", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            confirm: config.get_bool("quicksave_confirm"),
+            folder: PathBuf::from(config.get_str("savegame_folder")),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("quicksave_confirm", self.confirm);
+        config.set_str("savegame_folder", &self.folder.to_string_lossy());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn header(description: &str) -> SaveHeader {
+        SaveHeader {
+            description: description.to_owned(),
+            thumbnail: None,
+        }
+    }
+
+    fn time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(951_782_400 + 3661)
+    }
+
+    #[test]
+    fn descriptions_name_the_map_and_time() {
+        assert_eq!(describe("e1m1", time()), "E1M1 2000-02-29 01:01");
+        assert_eq!(
+            describe("A_VERY_LONG_MAP", time()).len(),
+            SAVESTRINGSIZE - 1
+        );
+
+        let mut slots = SaveSlots::new(true);
+        assert_eq!(
+            slots.suggestion(2, "MAP01", time()),
+            "MAP01 2000-02-29 01:01"
+        );
+        slots.saved(2, header("BEFORE THE CYBERDEMON"));
+        assert_eq!(
+            slots.suggestion(2, "MAP01", time()),
+            "BEFORE THE CYBERDEMON"
+        );
+    }
+
+    #[test]
+    fn headers_keep_vanilla_descriptions_and_thumbnails() {
+        let saved = SaveHeader {
+            description: "A DESCRIPTION THAT IS FAR TOO LONG".to_owned(),
+            thumbnail: Some(Thumbnail {
+                width: 2,
+                height: 1,
+                pixels: vec![4, 5],
+            }),
+        };
+        let mut bytes = saved.to_bytes();
+        assert_eq!(&bytes[..4], b"A DE");
+        assert_eq!(bytes[SAVESTRINGSIZE - 1], 0);
+        bytes.extend_from_slice(b"the rest of the savegame");
+        let header = SaveHeader::peek(&bytes).unwrap();
+        assert_eq!(header.description, "A DESCRIPTION THAT IS F");
+        assert_eq!(header.thumbnail, saved.thumbnail);
+
+        let mut slots = SaveSlots::default();
+        slots.saved(4, saved);
+        assert_eq!(slots.headers[4], Some(header));
+        let format = crate::renderer::draw::TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = [0u32; 4];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 2,
+        };
+        slots.draw_thumbnail(&format, &mut canvas, 4, 0, 1);
+        slots.draw_thumbnail(&format, &mut canvas, 3, 0, 0);
+        assert_eq!(pixels, [0, 0, 4, 5]);
+    }
+
+    #[test]
+    fn the_first_quick_save_picks_a_slot() {
+        let mut slots = SaveSlots::new(true);
+        assert_eq!(slots.quicksave(false), QuickAction::Refused("SAVEDEAD"));
+        assert_eq!(slots.quickload(false), QuickAction::Refused("QSAVESPOT"));
+        assert_eq!(slots.quicksave(true), QuickAction::PickSlot);
+        slots.cancelled();
+        slots.saved(1, header("ELSEWHERE"));
+        assert_eq!(slots.quicksave_slot, None);
+
+        assert_eq!(slots.quicksave(true), QuickAction::PickSlot);
+        slots.saved(3, header("QUICK"));
+        assert_eq!(slots.quicksave_slot, Some(3));
+        assert_eq!(
+            slots.quicksave(true),
+            QuickAction::Confirm {
+                prompt: "QSPROMPT",
+                slot: 3,
+                description: "QUICK".to_owned(),
+            }
+        );
+        assert_eq!(slots.quickload(true), QuickAction::Refused("QLOADNET"));
+    }
+
+    #[test]
+    fn confirmation_can_be_turned_off() {
+        let mut config = Config::new();
+        QuickSaveOptions::register(&mut config);
+        assert!(QuickSaveOptions::from_config(&config).confirm);
+        QuickSaveOptions {
+            confirm: false,
+            ..QuickSaveOptions::default()
+        }
+        .to_config(&mut config);
+        let options = QuickSaveOptions::from_config(&config);
+
+        let mut slots = SaveSlots::new(options.confirm);
+        slots.quicksave(true);
+        slots.saved(0, header("QUICK"));
+        assert_eq!(slots.quicksave(true), QuickAction::Proceed { slot: 0 });
+        assert_eq!(slots.quickload(false), QuickAction::Proceed { slot: 0 });
+    }
+}
@@ -0,0 +1,284 @@
+//! Dying and coming back: `G_PlayerReborn` and `G_DoReborn`.
+//!
+//! A dead player's `P_DeathThink` waits for use, which marks them for reborn, and the next
+//! `G_Ticker` brings them back. How depends on the game. In single player the level starts
+//! over from the player's start with the inventory of a new game, pistol and all, while the
+//! skill and `-respawn` (or nightmare's monster respawning) carry on as before, since no new
+//! game is started. In coop the corpse stays where it fell, cut loose from its voodoo dolls,
+//! and the player comes back at their own start, or the first free start of any player if
+//! something stands on it. In deathmatch the caller spawns them at a deathmatch start.
+//!
+//! Whatever the mode the new life starts with `Inventory::initial`: weapons, ammo and the
+//! backpack are lost, the tally of frags, kills, items and secrets is kept. If every start is
+//! blocked vanilla spawns the player inside whatever is there, which demos depend on;
+//! `RebornRules::telefrag` kills it instead.
+
+use super::demo::MAXPLAYERS;
+use super::info::GameInfo;
+use super::player::Inventory;
+use super::ticcmd::{TicCmd, BT_USE};
+use super::voodoo::PlayerBodies;
+
+/// `playerstate_t`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlayerState {
+    #[default]
+    Live,
+    Dead,
+    /// Waiting for `G_Ticker` to bring them back.
+    Reborn,
+}
+
+/// The counts a player keeps from one life to the next.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub frags: [i32; MAXPLAYERS],
+    pub kills: i32,
+    pub items: i32,
+    pub secrets: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Player {
+    pub state: PlayerState,
+    pub inventory: Inventory,
+    pub tally: Tally,
+    /// Buttons held since before the last press counted, vanilla's `attackdown` and `usedown`.
+    pub attack_down: bool,
+    pub use_down: bool,
+}
+
+impl Player {
+    pub fn new(info: &GameInfo) -> Self {
+        Self {
+            state: PlayerState::Live,
+            inventory: Inventory::initial(info),
+            tally: Tally::default(),
+            attack_down: false,
+            use_down: false,
+        }
+    }
+
+    /// `G_PlayerReborn`: a new life with the starting inventory and the old tally. The use
+    /// press that asked for it counts as held, so it doesn't also fire or open a door.
+    pub fn reborn(&mut self, info: &GameInfo) {
+        *self = Self {
+            tally: self.tally,
+            attack_down: true,
+            use_down: true,
+            ..Self::new(info)
+        };
+    }
+
+    /// The end of `P_DeathThink`: use asks to come back.
+    pub fn death_think(&mut self, cmd: &TicCmd) {
+        if self.state == PlayerState::Dead && cmd.buttons & BT_USE != 0 {
+            self.state = PlayerState::Reborn;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Single,
+    Coop,
+    Deathmatch,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RebornRules {
+    pub mode: GameMode,
+    /// A player coming back onto a blocked start telefrags what's there.
+    pub telefrag: bool,
+}
+
+/// What `do_reborn` did, for the caller to finish.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reborn {
+    /// Load the level again; everyone's inventory has been reset for it.
+    RestartLevel,
+    /// The player has a new body at the map thing `start`, where `Session::spawn_player`
+    /// puts the fog. With `telefrag` the caller kills whatever the start was blocked by.
+    Respawned {
+        body: usize,
+        start: usize,
+        telefrag: bool,
+    },
+    /// Spawn the player at a deathmatch start, fog and all; the corpse has been left behind.
+    Deathmatch,
+}
+
+/// `G_DoReborn` for `player`, whose state is `Reborn`. `free` says whether a player start,
+/// by map thing, has room for a player right now. Returns `None` if the map has no start to
+/// put the player at.
+pub fn do_reborn(
+    player: usize,
+    players: &mut [Player; MAXPLAYERS],
+    bodies: &mut PlayerBodies,
+    rules: &RebornRules,
+    info: &GameInfo,
+    free: impl Fn(usize) -> bool,
+) -> Option<Reborn> {
+    if rules.mode == GameMode::Single {
+        restart_level(players, info);
+        return Some(Reborn::RestartLevel);
+    }
+    players[player].reborn(info);
+    bodies.leave_corpse(player);
+    if rules.mode == GameMode::Deathmatch {
+        return Some(Reborn::Deathmatch);
+    }
+    let own = bodies.starts[player];
+    let open = own
+        .into_iter()
+        .chain(bodies.starts.iter().flatten().copied())
+        .find(|&start| free(start));
+    let (start, telefrag) = match open {
+        Some(start) => (start, false),
+        None => (own?, rules.telefrag),
+    };
+    let body = bodies.spawn_player(player, start, &players[player].inventory);
+    Some(Reborn::Respawned {
+        body,
+        start,
+        telefrag,
+    })
+}
+
+/// What loading the level again does to the players, `G_DoLoadLevel` and `P_SetupLevel`:
+/// the dead are reborn, and everyone's tally starts again from zero.
+pub fn restart_level(players: &mut [Player; MAXPLAYERS], info: &GameInfo) {
+    for player in players {
+        if player.state != PlayerState::Live {
+            player.reborn(info);
+        }
+        player.tally = Tally::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::player::WP_PISTOL;
+    use crate::wad::map::Thing;
+
+    fn start(kind: u16) -> Thing {
+        Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind,
+            flags: 7,
+        }
+    }
+
+    fn game(info: &GameInfo) -> ([Player; MAXPLAYERS], PlayerBodies) {
+        let mut players = [Player::new(info); MAXPLAYERS];
+        for player in &mut players {
+            player.inventory.weapons[5] = true;
+            player.inventory.ready_weapon = 5;
+            player.tally.kills = 7;
+            player.tally.frags[1] = 2;
+        }
+        let things = [start(1), start(2), start(3)];
+        let inventories = players.map(|player| player.inventory);
+        let bodies = PlayerBodies::spawn(&things, &[true, true, false, false], false, &inventories);
+        (players, bodies)
+    }
+
+    #[test]
+    fn use_brings_a_dead_player_back_without_firing() {
+        let info = GameInfo::default();
+        let mut player = Player::new(&info);
+        let press = TicCmd {
+            buttons: BT_USE,
+            ..TicCmd::default()
+        };
+        player.death_think(&press);
+        assert_eq!(player.state, PlayerState::Live);
+        player.state = PlayerState::Dead;
+        player.death_think(&TicCmd::default());
+        assert_eq!(player.state, PlayerState::Dead);
+        player.death_think(&press);
+        assert_eq!(player.state, PlayerState::Reborn);
+
+        player.tally.secrets = 3;
+        player.inventory.health = 0;
+        player.reborn(&info);
+        assert_eq!(player.state, PlayerState::Live);
+        assert_eq!(player.inventory, Inventory::initial(&info));
+        assert_eq!(player.tally.secrets, 3);
+        assert!(player.attack_down && player.use_down);
+    }
+
+    #[test]
+    fn single_player_restarts_the_level_with_a_pistol() {
+        let info = GameInfo::default();
+        let (mut players, mut bodies) = game(&info);
+        players[0].state = PlayerState::Reborn;
+        let rules = RebornRules::default();
+        let reborn = do_reborn(0, &mut players, &mut bodies, &rules, &info, |_| true);
+        assert_eq!(reborn, Some(Reborn::RestartLevel));
+        assert_eq!(players[0].inventory.ready_weapon, WP_PISTOL);
+        assert_eq!(players[0].tally, Tally::default());
+        assert_eq!(bodies.bodies.len(), 2, "the level reload spawns the bodies");
+    }
+
+    #[test]
+    fn coop_players_respawn_at_the_first_free_start() {
+        let info = GameInfo::default();
+        let (mut players, mut bodies) = game(&info);
+        let mut rules = RebornRules {
+            mode: GameMode::Coop,
+            telefrag: false,
+        };
+        let reborn = do_reborn(1, &mut players, &mut bodies, &rules, &info, |_| true);
+        assert_eq!(
+            reborn,
+            Some(Reborn::Respawned {
+                body: 2,
+                start: 1,
+                telefrag: false
+            })
+        );
+        assert_eq!(bodies.bodies[1].player, None, "the corpse stays behind");
+        assert_eq!(players[1].inventory, Inventory::initial(&info));
+        assert_eq!((players[1].tally.kills, players[1].tally.frags[1]), (7, 2));
+        // Player 0 keeps everything.
+        assert_eq!(players[0].inventory.ready_weapon, 5);
+
+        // Their own start is taken: the next free one of anybody's will do.
+        let reborn = do_reborn(1, &mut players, &mut bodies, &rules, &info, |start| {
+            start == 2
+        });
+        assert!(matches!(reborn, Some(Reborn::Respawned { start: 2, .. })));
+        // Every start taken: back at their own, stuck or telefragging.
+        let reborn = do_reborn(1, &mut players, &mut bodies, &rules, &info, |_| false);
+        assert!(matches!(
+            reborn,
+            Some(Reborn::Respawned {
+                start: 1,
+                telefrag: false,
+                ..
+            })
+        ));
+        rules.telefrag = true;
+        let reborn = do_reborn(1, &mut players, &mut bodies, &rules, &info, |_| false);
+        assert!(matches!(
+            reborn,
+            Some(Reborn::Respawned {
+                start: 1,
+                telefrag: true,
+                ..
+            })
+        ));
+
+        rules.mode = GameMode::Deathmatch;
+        let before = bodies.bodies.len();
+        let reborn = do_reborn(0, &mut players, &mut bodies, &rules, &info, |_| true);
+        assert_eq!(reborn, Some(Reborn::Deathmatch));
+        assert_eq!(bodies.bodies.len(), before);
+        assert_eq!(bodies.bodies[0].player, None);
+    }
+}
@@ -0,0 +1,215 @@
+//! Rewind: the game state is snapshotted every so often into a ring buffer, and a key goes
+//! back to an earlier snapshot.
+//!
+//! Keyframes are savegames in memory, written with the same `Archive` code as save files.
+//! Anything a savegame restores correctly, a rewind restores too. A keyframe is taken every
+//! `interval` tics and the oldest is dropped once `depth` are kept. Each press of the key goes
+//! back at least one interval, so holding it walks back through the buffer. Taking a
+//! keyframe costs a full serialization every interval, and that cost is measured so it can be
+//! shown next to the frame time. Rewinding changes the game's history, so strict mode refuses
+//! it.
+
+use super::savegame::{Archive, SaveError};
+use super::strict::{Feature, StrictMode};
+use super::TICRATE;
+use crate::engine::config::{Config, Configurable, Section};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keyframe {
+    pub tic: i32, // The game tic the state was saved on.
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RewindOptions {
+    pub interval: i32, // Tics between keyframes.
+    pub depth: usize,  // Keyframes kept; 0 turns rewinding off.
+}
+
+impl Default for RewindOptions {
+    fn default() -> Self {
+        Self {
+            interval: TICRATE,
+            depth: 60,
+        }
+    }
+}
+
+impl RewindOptions {
+    /// The options to play with: no keyframes in strict mode.
+    pub fn held(self, strict: &StrictMode) -> Self {
+        Self {
+            depth: if strict.allows(Feature::Rewind) {
+                self.depth
+            } else {
+                0
+            },
+            ..self
+        }
+    }
+}
+
+impl Configurable for RewindOptions {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_int("rewind_interval", defaults.interval, Section::Extended);
+        config.register_int("rewind_depth", defaults.depth as i32, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            interval: config.get_int("rewind_interval").max(1),
+            depth: config.get_int("rewind_depth").max(0) as usize,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("rewind_interval", self.interval);
+        config.set_int("rewind_depth", self.depth as i32);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Keyframes {
+    options: RewindOptions,
+    frames: VecDeque<Keyframe>,
+    last_cost: Duration, // How long the last keyframe took to serialize.
+}
+
+impl Keyframes {
+    pub fn new(options: RewindOptions) -> Self {
+        Self {
+            options,
+            frames: VecDeque::with_capacity(options.depth),
+            last_cost: Duration::ZERO,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn last_cost(&self) -> Duration {
+        self.last_cost
+    }
+
+    /// Bytes held by the buffer, for the memory readout.
+    pub fn memory(&self) -> usize {
+        self.frames.iter().map(|frame| frame.data.len()).sum()
+    }
+
+    /// The newest keyframe, without rewinding to it.
+    pub fn latest(&self) -> Option<&Keyframe> {
+        self.frames.back()
+    }
+
+    /// Called after every game tic: takes a keyframe when one is due.
+    pub fn ticker(&mut self, tic: i32, state: &impl Archive) {
+        if self.options.depth == 0 || tic % self.options.interval != 0 {
+            return;
+        }
+        if self.frames.back().is_some_and(|frame| frame.tic >= tic) {
+            return;
+        }
+        let start = Instant::now();
+        let data = state.to_bytes();
+        self.last_cost = start.elapsed();
+        if self.frames.len() == self.options.depth {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(Keyframe { tic, data });
+    }
+
+    /// The rewind key: the newest keyframe at least one interval before `tic`. Newer ones
+    /// are dropped, since the game continues from the one returned. The returned keyframe is
+    /// kept, so a second press goes back further.
+    pub fn rewind(&mut self, tic: i32) -> Option<&Keyframe> {
+        let target = tic - self.options.interval;
+        while self.frames.back().is_some_and(|frame| frame.tic > target) {
+            self.frames.pop_back();
+        }
+        self.frames.back()
+    }
+
+    /// Rewinds and decodes the state in one go.
+    pub fn restore<T: Archive>(&mut self, tic: i32) -> Option<Result<(i32, T), SaveError>> {
+        let frame = self.rewind(tic)?;
+        Some(T::from_bytes(&frame.data).map(|state| (frame.tic, state)))
+    }
+
+    /// Forgets everything, for a new level or a loaded game.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stats::LevelStats;
+
+    fn run(keyframes: &mut Keyframes, stats: &mut LevelStats, tics: i32) {
+        for _ in 0..tics {
+            stats.tic();
+            keyframes.ticker(stats.level_time, stats);
+        }
+    }
+
+    #[test]
+    fn keyframes_are_taken_every_interval_and_capped() {
+        let mut keyframes = Keyframes::new(RewindOptions {
+            interval: 10,
+            depth: 3,
+        });
+        let mut stats = LevelStats::default();
+        run(&mut keyframes, &mut stats, 45);
+        assert_eq!(keyframes.len(), 3);
+        assert_eq!(keyframes.memory(), 3 * 32);
+        let tics: Vec<i32> = keyframes.frames.iter().map(|f| f.tic).collect();
+        assert_eq!(tics, [20, 30, 40]);
+
+        let mut off = Keyframes::new(RewindOptions {
+            interval: 10,
+            depth: 0,
+        });
+        run(&mut off, &mut LevelStats::default(), 45);
+        assert!(off.is_empty());
+
+        let strict = StrictMode { enabled: true };
+        assert_eq!(RewindOptions::default().held(&strict).depth, 0);
+        let relaxed = StrictMode::default();
+        assert_eq!(
+            RewindOptions::default().held(&relaxed),
+            RewindOptions::default()
+        );
+    }
+
+    #[test]
+    fn each_press_goes_back_at_least_one_interval() {
+        let mut keyframes = Keyframes::new(RewindOptions {
+            interval: 10,
+            depth: 10,
+        });
+        let mut stats = LevelStats::default();
+        run(&mut keyframes, &mut stats, 41);
+        let (tic, restored) = keyframes.restore::<LevelStats>(41).unwrap().unwrap();
+        assert_eq!((tic, restored.level_time), (30, 30));
+        let (tic, _) = keyframes.restore::<LevelStats>(tic).unwrap().unwrap();
+        assert_eq!(tic, 20);
+
+        // Play on from the restored state: new keyframes follow the old ones.
+        let mut stats = LevelStats {
+            level_time: tic,
+            ..LevelStats::default()
+        };
+        run(&mut keyframes, &mut stats, 10);
+        assert_eq!(keyframes.frames.back().unwrap().tic, 30);
+        assert!(keyframes.rewind(5).is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! The serialization layer under savegames, rewind keyframes and anything else that needs
+//! the game state as bytes.
+//!
+//! Vanilla wrote its structs straight from memory with `memcpy`, padding to four bytes
+//! between sections. Here every piece of state implements `Archive` and writes its fields
+//! one by one, little endian, through `SaveWriter`. The layout is explicit and doesn't depend
+//! on how Rust lays out a struct. Reading goes through `SaveReader`, which fails with the
+//! offset instead of reading past the end, so a truncated or foreign file is an error and
+//! never garbage state.
+
+use super::stats::LevelStats;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveWriter {
+    pub data: Vec<u8>,
+}
+
+impl SaveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_i16(&mut self, value: i16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Vanilla's `PADSAVEP`: zeroes up to the next multiple of four.
+    pub fn pad(&mut self) {
+        let padding = (4 - self.data.len() % 4) % 4;
+        self.data.resize(self.data.len() + padding, 0);
+    }
+
+    pub fn archive(&mut self, value: &impl Archive) {
+        value.archive(self);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bad savegame at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl core::error::Error for SaveError {}
+
+pub struct SaveReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SaveReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> SaveError {
+        SaveError {
+            offset: self.offset,
+            message: message.into(),
+        }
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], SaveError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(self.error(format!("{value} is not a boolean"))),
+        }
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16, SaveError> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, SaveError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn pad(&mut self) -> Result<(), SaveError> {
+        let padding = (4 - self.offset % 4) % 4;
+        self.read_bytes(padding).map(|_| ())
+    }
+
+    pub fn unarchive<T: Archive>(&mut self) -> Result<T, SaveError> {
+        T::unarchive(self)
+    }
+
+    /// Fails unless everything has been read, as trailing data means a layout mismatch.
+    pub fn finish(&self) -> Result<(), SaveError> {
+        match self.offset == self.data.len() {
+            true => Ok(()),
+            false => Err(self.error("unexpected data after the end")),
+        }
+    }
+}
+
+/// State that goes into a savegame.
+pub trait Archive: Sized {
+    fn archive(&self, writer: &mut SaveWriter);
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = SaveWriter::new();
+        self.archive(&mut writer);
+        writer.data
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, SaveError> {
+        let mut reader = SaveReader::new(data);
+        let value = Self::unarchive(&mut reader)?;
+        reader.finish()?;
+        Ok(value)
+    }
+}
+
+impl Archive for LevelStats {
+    fn archive(&self, writer: &mut SaveWriter) {
+        for value in [
+            self.kills,
+            self.items,
+            self.secrets,
+            self.total_kills,
+            self.total_items,
+            self.total_secrets,
+            self.level_time,
+            self.total_time,
+        ] {
+            writer.write_i32(value);
+        }
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        Ok(Self {
+            kills: reader.read_i32()?,
+            items: reader.read_i32()?,
+            secrets: reader.read_i32()?,
+            total_kills: reader.read_i32()?,
+            total_items: reader.read_i32()?,
+            total_secrets: reader.read_i32()?,
+            level_time: reader.read_i32()?,
+            total_time: reader.read_i32()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_with_vanilla_padding() {
+        let mut writer = SaveWriter::new();
+        writer.write_u8(7);
+        writer.pad();
+        writer.write_i32(-2);
+        writer.write_bool(true);
+        writer.write_i16(300);
+        assert_eq!(writer.data, [7, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff, 1, 44, 1]);
+
+        let mut reader = SaveReader::new(&writer.data);
+        assert_eq!(reader.read_u8(), Ok(7));
+        reader.pad().unwrap();
+        assert_eq!(reader.read_i32(), Ok(-2));
+        assert_eq!(reader.read_bool(), Ok(true));
+        assert_eq!(reader.read_i16(), Ok(300));
+        assert!(reader.finish().is_ok());
+    }
+
+    #[test]
+    fn short_or_long_data_is_an_error() {
+        let stats = LevelStats {
+            kills: 3,
+            level_time: 1234,
+            ..LevelStats::default()
+        };
+        let bytes = stats.to_bytes();
+        assert_eq!(LevelStats::from_bytes(&bytes), Ok(stats));
+        let error = LevelStats::from_bytes(&bytes[..10]).unwrap_err();
+        assert_eq!(error.offset, 8);
+        let mut long = bytes.clone();
+        long.push(0);
+        assert!(LevelStats::from_bytes(&long).is_err());
+        assert!(SaveReader::new(&[2]).read_bool().is_err());
+    }
+}
@@ -0,0 +1,713 @@
+//! Scripting hooks: small scripts that run when something happens in the game.
+//!
+//! A script is a list of hooks. Each names an event, optionally a condition on the event's
+//! values, and the commands to run:
+//!
+//! ```text
+//! # Every kill of a shotgun guy heals the player a little.
+//! on thing_killed if $type == 9
+//!     give_health $killer 5 100
+//!     print "Got one at $x,$y"
+//! end
+//! ```
+//!
+//! Commands can only go through `GameAccess`, so a script can read and change what the API
+//! allows and nothing else; there are no loops, so a hook always finishes. Scripts are read
+//! from `*.ds` files in the scripts folder and from every `DSCRIPTS` lump. Hooks change the
+//! game, so they would desync a demo played back without them; while recording at the
+//! vanilla complevel they are switched off entirely.
+
+use super::compatibility::CompLevel;
+use super::info::NUMAMMO;
+use super::player::Inventory;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::engine::console::tokenize;
+use crate::util::fixed::{Fixed, FRACUNIT};
+use crate::wad::file::Wads;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub type ThingId = u32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptEvent {
+    LevelStart {
+        map: String,
+    },
+    ThingSpawned {
+        thing: ThingId,
+        kind: u16, // The editor number.
+        x: Fixed,
+        y: Fixed,
+    },
+    ThingKilled {
+        thing: ThingId,
+        kind: u16,
+        x: Fixed,
+        y: Fixed,
+        killer: Option<usize>, // The player who killed it.
+    },
+    PlayerDamaged {
+        player: usize,
+        damage: i32,
+        source: Option<ThingId>,
+    },
+    LineCrossed {
+        line: usize,
+        special: u16,
+        thing: ThingId,
+        player: Option<usize>,
+    },
+}
+
+impl ScriptEvent {
+    pub const NAMES: [&'static str; 5] = [
+        "level_start",
+        "thing_spawned",
+        "thing_killed",
+        "player_damaged",
+        "line_crossed",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        let index = match self {
+            ScriptEvent::LevelStart { .. } => 0,
+            ScriptEvent::ThingSpawned { .. } => 1,
+            ScriptEvent::ThingKilled { .. } => 2,
+            ScriptEvent::PlayerDamaged { .. } => 3,
+            ScriptEvent::LineCrossed { .. } => 4,
+        };
+        Self::NAMES[index]
+    }
+
+    /// The `$` values a hook sees. Coordinates are in whole map units; a missing player
+    /// or thing is `-1`.
+    pub fn vars(&self) -> Vec<(&'static str, String)> {
+        let unit = |value: Fixed| (value / FRACUNIT).to_string();
+        let player = |player: Option<usize>| player.map_or(-1, |p| p as i64).to_string();
+        match self {
+            ScriptEvent::LevelStart { map } => vec![("map", map.clone())],
+            ScriptEvent::ThingSpawned { thing, kind, x, y } => vec![
+                ("thing", thing.to_string()),
+                ("type", kind.to_string()),
+                ("x", unit(*x)),
+                ("y", unit(*y)),
+            ],
+            ScriptEvent::ThingKilled {
+                thing,
+                kind,
+                x,
+                y,
+                killer,
+            } => vec![
+                ("thing", thing.to_string()),
+                ("type", kind.to_string()),
+                ("x", unit(*x)),
+                ("y", unit(*y)),
+                ("killer", player(*killer)),
+            ],
+            ScriptEvent::PlayerDamaged {
+                player: p,
+                damage,
+                source,
+            } => vec![
+                ("player", p.to_string()),
+                ("damage", damage.to_string()),
+                ("source", source.map_or(-1, i64::from).to_string()),
+            ],
+            ScriptEvent::LineCrossed {
+                line,
+                special,
+                thing,
+                player: p,
+            } => vec![
+                ("line", line.to_string()),
+                ("special", special.to_string()),
+                ("thing", thing.to_string()),
+                ("player", player(*p)),
+            ],
+        }
+    }
+}
+
+/// Everything a script may touch. The game implements it over the live level.
+pub trait GameAccess {
+    fn print(&mut self, message: &str);
+    fn map(&self) -> String;
+    fn gametic(&self) -> i32;
+    fn player(&mut self, player: usize) -> Option<&mut Inventory>;
+    /// Spawns a thing by editor number at a map position. `None` if the number is unknown.
+    fn spawn_thing(&mut self, kind: u16, x: Fixed, y: Fixed) -> Option<ThingId>;
+    fn remove_thing(&mut self, thing: ThingId) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Condition {
+    left: String,
+    op: Op,
+    right: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Statement {
+    line: usize,
+    words: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Hook {
+    event: &'static str,
+    condition: Option<Condition>,
+    body: Vec<Statement>,
+}
+
+/// The commands scripts can run, with how many arguments each takes.
+const COMMANDS: [(&str, usize, &str); 8] = [
+    ("print", 1, "print <text>"),
+    (
+        "let",
+        3,
+        "let <var> <health|armor|ammo> <player> [ammo type]",
+    ),
+    ("set_health", 2, "set_health <player> <health>"),
+    ("give_health", 3, "give_health <player> <amount> <max>"),
+    ("give_armor", 3, "give_armor <player> <amount> <type>"),
+    ("give_ammo", 3, "give_ammo <player> <type> <amount>"),
+    ("spawn", 3, "spawn <type> <x> <y>"),
+    ("remove", 1, "remove <thing>"),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    pub source: String, // File or lump the script came from.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.source, self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+    source: String,
+    hooks: Vec<Hook>,
+}
+
+impl Script {
+    pub fn parse(source: &str, text: &str) -> Result<Self, ScriptError> {
+        let error = |line: usize, message: String| ScriptError {
+            source: source.to_string(),
+            line,
+            message,
+        };
+        let mut hooks = Vec::new();
+        let mut open: Option<(usize, Hook)> = None;
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            let words = tokenize(line);
+            let Some(&first) = words.first() else {
+                continue;
+            };
+            match (&mut open, first) {
+                (None, "on") => {
+                    let name = words.get(1).copied().unwrap_or("");
+                    let event = ScriptEvent::NAMES
+                        .into_iter()
+                        .find(|event| *event == name)
+                        .ok_or_else(|| error(number, format!("unknown event '{name}'")))?;
+                    let condition = match &words[2..] {
+                        [] => None,
+                        ["if", left, op, right] => Some(Condition {
+                            left: left.to_string(),
+                            op: parse_op(op)
+                                .ok_or_else(|| error(number, format!("bad operator '{op}'")))?,
+                            right: right.to_string(),
+                        }),
+                        _ => return Err(error(number, "expected 'if <a> <op> <b>'".into())),
+                    };
+                    let body = Vec::new();
+                    open = Some((
+                        number,
+                        Hook {
+                            event,
+                            condition,
+                            body,
+                        },
+                    ));
+                }
+                (None, _) => return Err(error(number, format!("expected 'on', found '{first}'"))),
+                (Some(_), "end") => hooks.push(open.take().unwrap().1),
+                (Some((_, hook)), _) => {
+                    let (_, arity, usage) = COMMANDS
+                        .into_iter()
+                        .find(|(name, ..)| *name == first)
+                        .ok_or_else(|| error(number, format!("unknown command '{first}'")))?;
+                    let extra = usize::from(first == "let"); // The optional ammo type.
+                    if !(arity..=arity + extra).contains(&(words.len() - 1)) {
+                        return Err(error(number, format!("usage: {usage}")));
+                    }
+                    hook.body.push(Statement {
+                        line: number,
+                        words: words.iter().map(|word| word.to_string()).collect(),
+                    });
+                }
+            }
+        }
+        if let Some((line, _)) = open {
+            return Err(error(line, "'on' without 'end'".into()));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            hooks,
+        })
+    }
+
+    /// Runs every hook for `event`. A failing command stops its hook, not the others.
+    pub fn fire(&self, event: &ScriptEvent, game: &mut dyn GameAccess) -> Vec<ScriptError> {
+        let mut errors = Vec::new();
+        for hook in self.hooks.iter().filter(|hook| hook.event == event.name()) {
+            let mut vars = event.vars();
+            let mut locals: Vec<(String, String)> = Vec::new();
+            vars.push(("gametic", game.gametic().to_string()));
+            if !vars.iter().any(|(name, _)| *name == "map") {
+                vars.push(("map", game.map()));
+            }
+            if let Some(condition) = &hook.condition {
+                let expand = |word: &str| substitute(word, &vars, &[]);
+                if !compare(
+                    &expand(&condition.left),
+                    condition.op,
+                    &expand(&condition.right),
+                ) {
+                    continue;
+                }
+            }
+            for statement in &hook.body {
+                let words: Vec<String> = statement
+                    .words
+                    .iter()
+                    .map(|word| substitute(word, &vars, &locals))
+                    .collect();
+                if let Err(message) = run(&words, game, &mut locals) {
+                    errors.push(ScriptError {
+                        source: self.source.clone(),
+                        line: statement.line,
+                        message,
+                    });
+                    break;
+                }
+            }
+        }
+        errors
+    }
+}
+
+fn parse_op(op: &str) -> Option<Op> {
+    Some(match op {
+        "==" => Op::Eq,
+        "!=" => Op::Ne,
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ">" => Op::Gt,
+        ">=" => Op::Ge,
+        _ => return None,
+    })
+}
+
+/// Compares as numbers when both sides are numbers, as text otherwise.
+fn compare(left: &str, op: Op, right: &str) -> bool {
+    let ordering = match (left.parse::<i64>(), right.parse::<i64>()) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        _ => left.cmp(right),
+    };
+    match op {
+        Op::Eq => ordering.is_eq(),
+        Op::Ne => ordering.is_ne(),
+        Op::Lt => ordering.is_lt(),
+        Op::Le => ordering.is_le(),
+        Op::Gt => ordering.is_gt(),
+        Op::Ge => ordering.is_ge(),
+    }
+}
+
+/// Replaces `$name` with its value; names run to the first character that can't be in one.
+fn substitute(word: &str, vars: &[(&str, String)], locals: &[(String, String)]) -> String {
+    let mut out = String::new();
+    let mut rest = word;
+    while let Some(at) = rest.find('$') {
+        out.push_str(&rest[..at]);
+        let name_len = rest[at + 1..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len() - at - 1);
+        let name = &rest[at + 1..at + 1 + name_len];
+        let value = locals
+            .iter()
+            .rev()
+            .find(|(local, _)| local == name)
+            .map(|(_, value)| value.as_str())
+            .or_else(|| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, v)| v.as_str())
+            });
+        match value {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[at..at + 1 + name_len]),
+        }
+        rest = &rest[at + 1 + name_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn number(words: &[String], index: usize) -> Result<i32, String> {
+    let word = &words[index];
+    word.parse()
+        .map_err(|_| format!("'{word}' is not a number"))
+}
+
+fn ammo_type(words: &[String], index: usize) -> Result<usize, String> {
+    let kind = number(words, index)?;
+    usize::try_from(kind)
+        .ok()
+        .filter(|&kind| kind < NUMAMMO)
+        .ok_or_else(|| format!("no ammo type {kind}"))
+}
+
+/// The inventory of the player numbered by `words[index]`.
+fn inventory<'a>(
+    game: &'a mut dyn GameAccess,
+    words: &[String],
+    index: usize,
+) -> Result<&'a mut Inventory, String> {
+    let player = usize::try_from(number(words, index)?)
+        .map_err(|_| format!("'{}' is not a player", words[index]))?;
+    game.player(player)
+        .ok_or_else(|| format!("player {player} is not in the game"))
+}
+
+fn run(
+    words: &[String],
+    game: &mut dyn GameAccess,
+    locals: &mut Vec<(String, String)>,
+) -> Result<(), String> {
+    match words[0].as_str() {
+        "print" => game.print(&words[1]),
+        "let" => {
+            let player = inventory(game, words, 3)?;
+            let value = match words[2].as_str() {
+                "health" => player.health,
+                "armor" => player.armor_points,
+                "ammo" if words.len() == 5 => player.ammo[ammo_type(words, 4)?],
+                query => return Err(format!("can't read '{query}'")),
+            };
+            locals.push((words[1].clone(), value.to_string()));
+        }
+        "set_health" => {
+            let health = number(words, 2)?;
+            inventory(game, words, 1)?.health = health.max(1);
+        }
+        "give_health" => {
+            let (amount, max) = (number(words, 2)?, number(words, 3)?);
+            let player = inventory(game, words, 1)?;
+            player.health = (player.health + amount).min(max.max(player.health));
+        }
+        "give_armor" => {
+            let (amount, kind) = (number(words, 2)?, number(words, 3)?.clamp(1, 2));
+            let player = inventory(game, words, 1)?;
+            player.armor_points = (player.armor_points + amount).min(200);
+            player.armor_type = player.armor_type.max(kind);
+        }
+        "give_ammo" => {
+            let (kind, amount) = (ammo_type(words, 2)?, number(words, 3)?);
+            let player = inventory(game, words, 1)?;
+            player.ammo[kind] = (player.ammo[kind] + amount).min(player.max_ammo[kind]);
+        }
+        "spawn" => {
+            let kind = number(words, 1)?;
+            let kind = u16::try_from(kind).map_err(|_| format!("no thing type {kind}"))?;
+            let (x, y) = (number(words, 2)?, number(words, 3)?);
+            game.spawn_thing(kind, x * FRACUNIT, y * FRACUNIT)
+                .ok_or_else(|| format!("no thing type {kind}"))?;
+        }
+        "remove" => {
+            let thing = number(words, 1)?;
+            game.remove_thing(u32::try_from(thing).map_err(|_| format!("no thing {thing}"))?);
+        }
+        _ => unreachable!("checked when parsing"),
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptOptions {
+    pub enabled: bool,
+    pub folder: PathBuf,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            folder: PathBuf::from("scripts"),
+        }
+    }
+}
+
+impl Configurable for ScriptOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("scripts_enabled", true, Section::Extended);
+        config.register_str("scripts_folder", "scripts", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.get_bool("scripts_enabled"),
+            folder: PathBuf::from(config.get_str("scripts_folder")),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("scripts_enabled", self.enabled);
+        config.set_str("scripts_folder", &self.folder.to_string_lossy());
+    }
+}
+
+/// Every loaded script, fired in load order: the folder's files by name, then the lumps.
+#[derive(Clone, Debug, Default)]
+pub struct Scripts {
+    scripts: Vec<Script>,
+    enabled: bool,
+}
+
+impl Scripts {
+    /// Whether hooks may run at all. Recording a vanilla demo must give the same game as
+    /// playing it back in any other port, which scripts would break.
+    pub fn allowed(options: &ScriptOptions, recording: bool, complevel: CompLevel) -> bool {
+        options.enabled && !(recording && complevel == CompLevel::Vanilla)
+    }
+
+    /// Loads the folder's `*.ds` files and every `DSCRIPTS` lump. Scripts that fail to
+    /// read or parse are skipped and reported.
+    pub fn load(folder: &Path, wads: &Wads, enabled: bool) -> (Self, Vec<String>) {
+        let mut scripts = Self {
+            scripts: Vec::new(),
+            enabled,
+        };
+        let mut errors = Vec::new();
+        let mut files: Vec<PathBuf> = fs::read_dir(folder)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ds"))
+            .collect();
+        files.sort();
+        for path in files {
+            match fs::read_to_string(&path) {
+                Ok(text) => scripts.add(&path.display().to_string(), &text, &mut errors),
+                Err(error) => errors.push(format!("couldn't read {}: {error}", path.display())),
+            }
+        }
+        for (file, lump) in wads.lumps_named("DSCRIPTS") {
+            let source = format!("{}:DSCRIPTS", file.path.display());
+            scripts.add(&source, &String::from_utf8_lossy(&lump.data), &mut errors);
+        }
+        (scripts, errors)
+    }
+
+    fn add(&mut self, source: &str, text: &str, errors: &mut Vec<String>) {
+        match Script::parse(source, text) {
+            Ok(script) => self.scripts.push(script),
+            Err(error) => errors.push(error.to_string()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    pub fn fire(&self, event: &ScriptEvent, game: &mut dyn GameAccess) -> Vec<ScriptError> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let fired = self.scripts.iter().map(|script| script.fire(event, game));
+        fired.flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::GameInfo;
+    use crate::wad::file::{Lump, WadFile, WadKind};
+
+    #[derive(Default)]
+    struct Game {
+        printed: Vec<String>,
+        players: Vec<Inventory>,
+        spawned: Vec<(u16, Fixed, Fixed)>,
+    }
+
+    impl GameAccess for Game {
+        fn print(&mut self, message: &str) {
+            self.printed.push(message.to_string());
+        }
+        fn map(&self) -> String {
+            "MAP07".to_string()
+        }
+        fn gametic(&self) -> i32 {
+            350
+        }
+        fn player(&mut self, player: usize) -> Option<&mut Inventory> {
+            self.players.get_mut(player)
+        }
+        fn spawn_thing(&mut self, kind: u16, x: Fixed, y: Fixed) -> Option<ThingId> {
+            self.spawned.push((kind, x, y));
+            (kind != 0).then_some(self.spawned.len() as ThingId)
+        }
+        fn remove_thing(&mut self, _thing: ThingId) -> bool {
+            true
+        }
+    }
+
+    fn game() -> Game {
+        Game {
+            players: vec![Inventory::initial(&GameInfo::default())],
+            ..Game::default()
+        }
+    }
+
+    fn killed(kind: u16) -> ScriptEvent {
+        ScriptEvent::ThingKilled {
+            thing: 4,
+            kind,
+            x: 64 * FRACUNIT,
+            y: -32 * FRACUNIT,
+            killer: Some(0),
+        }
+    }
+
+    #[test]
+    fn hooks_run_for_their_event_when_the_condition_holds() {
+        let script = Script::parse(
+            "test.ds",
+            "# heal on sergeant kills\n\
+             on thing_killed if $type == 9\n\
+                 give_health $killer 5 100\n\
+                 let hp health $killer\n\
+                 print \"sergeant down at $x,$y on $map, health $hp\"\n\
+             end\n\
+             on level_start\n    spawn 2011 $gametic 0\nend\n",
+        )
+        .unwrap();
+        let mut game = game();
+        game.players[0].health = 98;
+        assert!(script.fire(&killed(3004), &mut game).is_empty());
+        assert!(game.printed.is_empty());
+        assert!(script.fire(&killed(9), &mut game).is_empty());
+        assert_eq!(game.players[0].health, 100);
+        assert_eq!(
+            game.printed,
+            ["sergeant down at 64,-32 on MAP07, health 100"]
+        );
+
+        let start = ScriptEvent::LevelStart {
+            map: "MAP01".to_string(),
+        };
+        script.fire(&start, &mut game);
+        assert_eq!(game.spawned, [(2011, 350 * FRACUNIT, 0)]);
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_their_line() {
+        let error = |text: &str| Script::parse("a.ds", text).unwrap_err().to_string();
+        assert_eq!(
+            error("on thing_hugged\nend"),
+            "a.ds:1: unknown event 'thing_hugged'"
+        );
+        assert_eq!(
+            error("on level_start\n  explode\nend"),
+            "a.ds:2: unknown command 'explode'"
+        );
+        assert_eq!(error("\non level_start\n"), "a.ds:2: 'on' without 'end'");
+        assert_eq!(
+            error("on level_start\n  give_ammo 0 1\nend"),
+            "a.ds:2: usage: give_ammo <player> <type> <amount>"
+        );
+
+        let script = Script::parse(
+            "b.ds",
+            "on player_damaged\n  give_ammo $player 7 10\n  print never\nend\n\
+             on player_damaged\n  give_ammo $player 0 500\nend",
+        )
+        .unwrap();
+        let mut game = game();
+        let hurt = ScriptEvent::PlayerDamaged {
+            player: 0,
+            damage: 10,
+            source: None,
+        };
+        let errors = script.fire(&hurt, &mut game);
+        assert_eq!(errors[0].to_string(), "b.ds:2: no ammo type 7");
+        assert!(game.printed.is_empty());
+        assert_eq!(game.players[0].ammo[0], game.players[0].max_ammo[0]);
+    }
+
+    #[test]
+    fn scripts_come_from_the_folder_and_lumps_and_can_be_disabled() {
+        let folder = std::env::temp_dir().join(format!("doom-rs-scripts-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("b.ds"), "on level_start\n  print b\nend\n").unwrap();
+        fs::write(folder.join("a.ds"), "on level_start\n  print a\nend\n").unwrap();
+        fs::write(folder.join("broken.ds"), "on\n").unwrap();
+        fs::write(folder.join("notes.txt"), "not a script").unwrap();
+        let mut wads = Wads::new();
+        wads.add(WadFile {
+            path: PathBuf::from("mod.wad"),
+            kind: WadKind::Pwad,
+            lumps: vec![Lump {
+                name: "DSCRIPTS".to_string(),
+                data: b"on level_start\n  print lump\nend\n".to_vec(),
+            }],
+        });
+
+        let (scripts, errors) = Scripts::load(&folder, &wads, true);
+        assert_eq!(scripts.len(), 3);
+        assert_eq!(errors.len(), 1);
+        let mut game = game();
+        let start = ScriptEvent::LevelStart { map: "E1M1".into() };
+        scripts.fire(&start, &mut game);
+        assert_eq!(game.printed, ["a", "b", "lump"]);
+
+        let (scripts, _) = Scripts::load(&folder, &wads, false);
+        scripts.fire(&start, &mut game);
+        assert_eq!(game.printed.len(), 3);
+        fs::remove_dir_all(folder).unwrap();
+
+        let options = ScriptOptions::default();
+        assert!(Scripts::allowed(&options, false, CompLevel::Vanilla));
+        assert!(Scripts::allowed(&options, true, CompLevel::Boom));
+        assert!(!Scripts::allowed(&options, true, CompLevel::Vanilla));
+    }
+}
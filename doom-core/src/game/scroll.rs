@@ -0,0 +1,498 @@
+//! Scrolling walls, floors and ceilings, and conveyors that carry things along.
+//!
+//! Vanilla has one scroller, line special 48, which moves its front sidedef's texture left a
+//! unit each tic. Boom adds the scroll to the right and a family driven by a control line:
+//! its direction and length, shifted down by `SCROLL_SHIFT`, give the speed. Displacement
+//! versions only move when the control line's front sector changes height, by as much as it
+//! changed, and accelerative ones add that to a speed they keep. Boom runs each one as a
+//! thinker spawned at level start, and `Scrolling` is those thinkers with the offsets they
+//! move. Conveyors move the things in a sector rather than a texture, so `Scrolling::tic`
+//! hands those back for `carry`.
+
+use super::compatibility::CompLevel;
+use super::info::{MF_NOCLIP, MF_NOGRAVITY};
+use super::monsters::Actor;
+use super::Trig;
+use crate::renderer::interpolate::SideOffsets;
+use crate::util::fixed::{fixed_div, fixed_mul, to_fixed, Fixed, FRACBITS, FRACUNIT};
+use crate::wad::map::MapData;
+use alloc::vec::Vec;
+
+/// Scrolls the front sidedef left, in vanilla too.
+pub const SCROLL_LEFT_LINE: u16 = 48;
+/// Boom's line specials, which vanilla treats as unknown.
+pub const SCROLL_RIGHT_LINE: u16 = 85;
+pub const SCROLL_CEILING_LINE: u16 = 250;
+pub const SCROLL_FLOOR_LINE: u16 = 251;
+pub const CARRY_LINE: u16 = 252;
+pub const SCROLL_FLOOR_CARRY_LINE: u16 = 253;
+/// Scrolls the tagged lines' walls along with the floors of the same control line.
+pub const SCROLL_WALLS_LINE: u16 = 254;
+/// Scrolls the front sidedef by its own offsets each tic.
+pub const SCROLL_BY_OFFSETS_LINE: u16 = 255;
+/// The first of the displacement versions of 250 to 254.
+const DISPLACE_BASE: u16 = 245;
+/// The first of the accelerative versions of 250 to 254.
+const ACCEL_BASE: u16 = 214;
+
+/// How far a control line's vector is shifted down for the speed.
+const SCROLL_SHIFT: u32 = 5;
+/// How much of a floor's scroll speed a conveyor gives the things on it.
+pub const CARRYFACTOR: Fixed = 0x1800;
+/// `tantoangle` has one entry per 32 steps of `FixedDiv`.
+const DBITS: u32 = FRACBITS - 11;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollKind {
+    /// A sidedef's texture offsets.
+    Side,
+    Floor,
+    Ceiling,
+    /// The things standing in a sector.
+    Carry,
+}
+
+/// One of Boom's `scroll_t` thinkers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scroller {
+    pub kind: ScrollKind,
+    /// The sidedef, or sector, it moves.
+    pub affectee: usize,
+    dx: Fixed,
+    dy: Fixed,
+    /// The sector whose height changes drive it, for displacement and accelerative ones.
+    control: Option<usize>,
+    /// The control sector's floor plus ceiling when it last ran.
+    last_height: Fixed,
+    accel: bool,
+    vdx: Fixed,
+    vdy: Fixed,
+}
+
+impl Scroller {
+    /// `Add_Scroller`, with the control sector's heights as the map has them.
+    fn new(
+        kind: ScrollKind,
+        (dx, dy): (Fixed, Fixed),
+        control: Option<usize>,
+        affectee: usize,
+        accel: bool,
+        map: &MapData,
+    ) -> Self {
+        let last_height = control
+            .and_then(|sector| map.sectors.get(sector))
+            .map_or(0, |sector| {
+                to_fixed(i32::from(sector.floor_height) + i32::from(sector.ceiling_height))
+            });
+        Self {
+            kind,
+            affectee,
+            dx,
+            dy,
+            control,
+            last_height,
+            accel,
+            vdx: 0,
+            vdy: 0,
+        }
+    }
+
+    /// `T_Scroll` up to the move: how far to scroll this tic, or `None` for not at all.
+    /// `control_height` is the floor plus ceiling of its control sector now, and is ignored
+    /// when it has none.
+    pub fn think(&mut self, control_height: Fixed) -> Option<(Fixed, Fixed)> {
+        let (mut dx, mut dy) = (self.dx, self.dy);
+        if self.control.is_some() {
+            let delta = control_height.wrapping_sub(self.last_height);
+            self.last_height = control_height;
+            dx = fixed_mul(dx, delta);
+            dy = fixed_mul(dy, delta);
+        }
+        if self.accel {
+            dx = dx.wrapping_add(self.vdx);
+            dy = dy.wrapping_add(self.vdy);
+            self.vdx = dx;
+            self.vdy = dy;
+        }
+        (dx | dy != 0).then_some((dx, dy))
+    }
+}
+
+/// `Add_WallScroller`: the sidedef scroll that moves a wall's texture the way floors
+/// scrolled by (`dx`, `dy`) move, whichever way the wall runs. Zero length lines have no
+/// direction and get none.
+fn wall_scroll(
+    (dx, dy): (Fixed, Fixed),
+    (line_dx, line_dy): (Fixed, Fixed),
+    trig: &impl Trig,
+) -> Option<(Fixed, Fixed)> {
+    let (mut x, mut y) = (line_dx.wrapping_abs(), line_dy.wrapping_abs());
+    if y > x {
+        (x, y) = (y, x);
+    }
+    if x == 0 {
+        return None;
+    }
+    let slope = fixed_div(y, x) >> DBITS;
+    let length = fixed_div(x, trig.fine(trig.tan_to_angle(slope as usize)).0);
+    let along = fixed_mul(dy, line_dy).wrapping_add(fixed_mul(dx, line_dx));
+    let across = fixed_mul(dx, line_dy).wrapping_sub(fixed_mul(dy, line_dx));
+    Some((
+        fixed_div(along, length).wrapping_neg(),
+        fixed_div(across, length).wrapping_neg(),
+    ))
+}
+
+/// A flat's texture offsets, which only Boom's scrollers move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlatOffsets {
+    pub floor_x: Fixed,
+    pub floor_y: Fixed,
+    pub ceiling_x: Fixed,
+    pub ceiling_y: Fixed,
+}
+
+/// A conveyor's push this tic on the things in `sector`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conveyor {
+    pub sector: usize,
+    pub dx: Fixed,
+    pub dy: Fixed,
+}
+
+/// The scrollers of a level and the offsets they have moved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scrolling {
+    pub scrollers: Vec<Scroller>,
+    /// Every sidedef's offsets, as `LevelInterpolation::end_tic` takes them.
+    pub sides: Vec<SideOffsets>,
+    /// Every sector's flat offsets.
+    pub flats: Vec<FlatOffsets>,
+}
+
+impl Scrolling {
+    /// `P_SpawnScrollers`: a scroller for each scrolling line, in line order. Below Boom only
+    /// line 48 scrolls.
+    pub fn new(map: &MapData, level: CompLevel, trig: &impl Trig) -> Self {
+        let sides = map
+            .sidedefs
+            .iter()
+            .map(|side| SideOffsets {
+                x: to_fixed(i32::from(side.x_offset)),
+                y: to_fixed(i32::from(side.y_offset)),
+            })
+            .collect();
+        let mut scrolling = Self {
+            scrollers: Vec::new(),
+            sides,
+            flats: alloc::vec![FlatOffsets::default(); map.sectors.len()],
+        };
+        for (index, line) in map.linedefs.iter().enumerate() {
+            if level.boom_specials() {
+                scrolling.spawn(map, index, trig);
+            } else if line.special == SCROLL_LEFT_LINE {
+                let side = usize::from(line.front);
+                scrolling.add(ScrollKind::Side, (FRACUNIT, 0), None, side, false, map);
+            }
+        }
+        scrolling
+    }
+
+    fn add(
+        &mut self,
+        kind: ScrollKind,
+        speed: (Fixed, Fixed),
+        control: Option<usize>,
+        affectee: usize,
+        accel: bool,
+        map: &MapData,
+    ) {
+        self.scrollers
+            .push(Scroller::new(kind, speed, control, affectee, accel, map));
+    }
+
+    /// Boom's scrollers for line `index`, if it is one.
+    fn spawn(&mut self, map: &MapData, index: usize, trig: &impl Trig) {
+        let line = &map.linedefs[index];
+        let vector = |line: &crate::wad::map::LineDef| {
+            let (Some(v1), Some(v2)) = (
+                map.vertexes.get(usize::from(line.v1)),
+                map.vertexes.get(usize::from(line.v2)),
+            ) else {
+                return (0, 0);
+            };
+            (
+                to_fixed(i32::from(v2.x) - i32::from(v1.x)),
+                to_fixed(i32::from(v2.y) - i32::from(v1.y)),
+            )
+        };
+        let (line_dx, line_dy) = vector(line);
+        let (dx, dy) = (line_dx >> SCROLL_SHIFT, line_dy >> SCROLL_SHIFT);
+        let front = usize::from(line.front);
+        let front_sector = map.sidedefs.get(front).map(|side| usize::from(side.sector));
+        let (special, control, accel) = match line.special {
+            s if (DISPLACE_BASE..DISPLACE_BASE + 5).contains(&s) => {
+                (s - DISPLACE_BASE + SCROLL_CEILING_LINE, front_sector, false)
+            }
+            s if (ACCEL_BASE..ACCEL_BASE + 5).contains(&s) => {
+                (s - ACCEL_BASE + SCROLL_CEILING_LINE, front_sector, true)
+            }
+            s => (s, None, false),
+        };
+        // `P_FindSectorFromLineTag` matches a tag of 0 like any other.
+        let tagged: Vec<usize> = map
+            .sectors
+            .iter()
+            .enumerate()
+            .filter(|(_, sector)| sector.tag as u16 == line.tag)
+            .map(|(sector, _)| sector)
+            .collect();
+        match special {
+            SCROLL_CEILING_LINE => {
+                for &sector in &tagged {
+                    self.add(ScrollKind::Ceiling, (-dx, dy), control, sector, accel, map);
+                }
+            }
+            SCROLL_FLOOR_LINE | CARRY_LINE | SCROLL_FLOOR_CARRY_LINE => {
+                if special != CARRY_LINE {
+                    for &sector in &tagged {
+                        self.add(ScrollKind::Floor, (-dx, dy), control, sector, accel, map);
+                    }
+                }
+                if special != SCROLL_FLOOR_LINE {
+                    let speed = (fixed_mul(dx, CARRYFACTOR), fixed_mul(dy, CARRYFACTOR));
+                    for &sector in &tagged {
+                        self.add(ScrollKind::Carry, speed, control, sector, accel, map);
+                    }
+                }
+            }
+            SCROLL_WALLS_LINE => {
+                for (other, wall) in map.linedefs.iter().enumerate() {
+                    if other == index || wall.tag != line.tag {
+                        continue;
+                    }
+                    if let Some(speed) = wall_scroll((dx, dy), vector(wall), trig) {
+                        let side = usize::from(wall.front);
+                        self.add(ScrollKind::Side, speed, control, side, accel, map);
+                    }
+                }
+            }
+            SCROLL_BY_OFFSETS_LINE => {
+                let offsets = self.sides.get(front).copied().unwrap_or_default();
+                let speed = (offsets.x.wrapping_neg(), offsets.y);
+                self.add(ScrollKind::Side, speed, None, front, accel, map);
+            }
+            SCROLL_LEFT_LINE => self.add(ScrollKind::Side, (FRACUNIT, 0), None, front, accel, map),
+            SCROLL_RIGHT_LINE => {
+                self.add(ScrollKind::Side, (-FRACUNIT, 0), None, front, accel, map)
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs every scroller for a tic. `control_height` gives a sector's floor plus ceiling
+    /// now. Texture offsets move here; the conveyors that moved come back for the caller to
+    /// `carry` the things in their sectors.
+    pub fn tic(&mut self, control_height: impl Fn(usize) -> Fixed) -> Vec<Conveyor> {
+        let mut conveyors = Vec::new();
+        for scroller in &mut self.scrollers {
+            let height = scroller.control.map_or(0, &control_height);
+            let Some((dx, dy)) = scroller.think(height) else {
+                continue;
+            };
+            let affectee = scroller.affectee;
+            match scroller.kind {
+                ScrollKind::Side => {
+                    if let Some(side) = self.sides.get_mut(affectee) {
+                        side.x = side.x.wrapping_add(dx);
+                        side.y = side.y.wrapping_add(dy);
+                    }
+                }
+                ScrollKind::Floor => {
+                    if let Some(flat) = self.flats.get_mut(affectee) {
+                        flat.floor_x = flat.floor_x.wrapping_add(dx);
+                        flat.floor_y = flat.floor_y.wrapping_add(dy);
+                    }
+                }
+                ScrollKind::Ceiling => {
+                    if let Some(flat) = self.flats.get_mut(affectee) {
+                        flat.ceiling_x = flat.ceiling_x.wrapping_add(dx);
+                        flat.ceiling_y = flat.ceiling_y.wrapping_add(dy);
+                    }
+                }
+                ScrollKind::Carry => conveyors.push(Conveyor {
+                    sector: affectee,
+                    dx,
+                    dy,
+                }),
+            }
+        }
+        conveyors
+    }
+}
+
+/// `T_Scroll` for a conveyor: pushes `thing`, one of the things touching the conveyor's
+/// sector, if it stands on `floor` or is under the water of a deep water sector whose
+/// surface is at `water`. Things that fly or pass through walls ride over it.
+pub fn carry(thing: &mut Actor, conveyor: &Conveyor, floor: Fixed, water: Option<Fixed>) {
+    let water = water.filter(|&water| water > floor).unwrap_or(Fixed::MIN);
+    let grounded = thing.flags & MF_NOGRAVITY == 0 && thing.z <= floor;
+    if thing.flags & MF_NOCLIP == 0 && (grounded || thing.z < water) {
+        thing.momx = thing.momx.wrapping_add(conveyor.dx);
+        thing.momy = thing.momy.wrapping_add(conveyor.dy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::angle::Angle;
+    use crate::wad::map::{LineDef, Sector, SideDef, Vertex, NO_SIDEDEF};
+    use alloc::string::String;
+
+    /// Enough of the tables for lines along the axes.
+    struct AxisTrig;
+
+    impl Trig for AxisTrig {
+        fn fine(&self, _angle: Angle) -> (Fixed, Fixed) {
+            (FRACUNIT, 0)
+        }
+
+        fn fine_tangent(&self, _index: usize) -> Fixed {
+            0
+        }
+
+        fn point_to_angle(&self, _dx: Fixed, _dy: Fixed) -> Angle {
+            0
+        }
+
+        fn tan_to_angle(&self, _index: usize) -> Angle {
+            0
+        }
+    }
+
+    /// A 64 unit control line running east with `special`, a 128 unit wall running north,
+    /// and a sector 128 high tagged 1.
+    fn level(special: u16) -> MapData {
+        let side = |x_offset| SideDef {
+            x_offset,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: String::new(),
+            sector: 0,
+        };
+        let line = |v1, v2, special, front| LineDef {
+            v1,
+            v2,
+            flags: 0,
+            special,
+            tag: 1,
+            front,
+            back: NO_SIDEDEF,
+        };
+        MapData {
+            vertexes: alloc::vec![
+                Vertex { x: 0, y: 0 },
+                Vertex { x: 64, y: 0 },
+                Vertex { x: 0, y: 128 },
+            ],
+            linedefs: alloc::vec![line(0, 1, special, 0), line(0, 2, 0, 1)],
+            sidedefs: alloc::vec![side(8), side(0)],
+            sectors: alloc::vec![Sector {
+                floor_height: 0,
+                ceiling_height: 128,
+                floor_pic: String::new(),
+                ceiling_pic: String::new(),
+                light: 160,
+                special: 0,
+                tag: 1,
+            }],
+            ..MapData::default()
+        }
+    }
+
+    #[test]
+    fn vanilla_only_scrolls_line_48() {
+        let map = level(SCROLL_LEFT_LINE);
+        let mut scrolling = Scrolling::new(&map, CompLevel::Vanilla, &AxisTrig);
+        scrolling.tic(|_| 0);
+        scrolling.tic(|_| 0);
+        assert_eq!(scrolling.sides[0].x, to_fixed(10));
+
+        let right = level(SCROLL_RIGHT_LINE);
+        assert!(Scrolling::new(&right, CompLevel::Vanilla, &AxisTrig)
+            .scrollers
+            .is_empty());
+        let mut boom = Scrolling::new(&right, CompLevel::Boom, &AxisTrig);
+        boom.tic(|_| 0);
+        assert_eq!(boom.sides[0].x, to_fixed(7));
+
+        // 255 moves by the sidedef's own offsets, to the left for a positive x.
+        let mut offsets =
+            Scrolling::new(&level(SCROLL_BY_OFFSETS_LINE), CompLevel::Boom, &AxisTrig);
+        offsets.tic(|_| 0);
+        assert_eq!(offsets.sides[0].x, 0);
+    }
+
+    #[test]
+    fn floors_walls_and_conveyors() {
+        let map = level(SCROLL_FLOOR_CARRY_LINE);
+        let mut scrolling = Scrolling::new(&map, CompLevel::Boom, &AxisTrig);
+        let conveyors = scrolling.tic(|_| 0);
+        assert_eq!(scrolling.flats[0].floor_x, -2 * FRACUNIT);
+        assert_eq!(
+            conveyors,
+            [Conveyor {
+                sector: 0,
+                dx: 0x3000,
+                dy: 0
+            }]
+        );
+
+        let mut thing = Actor::default();
+        carry(&mut thing, &conveyors[0], 0, None);
+        assert_eq!(thing.momx, 0x3000);
+        let mut flying = Actor {
+            flags: MF_NOGRAVITY,
+            ..Actor::default()
+        };
+        carry(&mut flying, &conveyors[0], 0, None);
+        assert_eq!(flying.momx, 0);
+        carry(&mut flying, &conveyors[0], 0, Some(to_fixed(32)));
+        assert_eq!(flying.momx, 0x3000, "swimming things are carried");
+
+        // The wall running north scrolls down as the floor scrolls east.
+        let mut walls = Scrolling::new(&level(SCROLL_WALLS_LINE), CompLevel::Boom, &AxisTrig);
+        walls.tic(|_| 0);
+        assert_eq!(
+            walls.sides[1],
+            SideOffsets {
+                x: 0,
+                y: -2 * FRACUNIT
+            }
+        );
+        assert_eq!(walls.sides[0].x, to_fixed(8), "not the control line itself");
+    }
+
+    #[test]
+    fn displacement_and_acceleration_follow_the_control_sector() {
+        let displace = level(DISPLACE_BASE + 1);
+        let mut scrolling = Scrolling::new(&displace, CompLevel::Boom, &AxisTrig);
+        scrolling.tic(|_| to_fixed(136));
+        assert_eq!(scrolling.flats[0].floor_x, to_fixed(-16));
+        scrolling.tic(|_| to_fixed(136));
+        assert_eq!(
+            scrolling.flats[0].floor_x,
+            to_fixed(-16),
+            "stops with the sector"
+        );
+
+        let accel = level(ACCEL_BASE + 1);
+        let mut scrolling = Scrolling::new(&accel, CompLevel::Boom, &AxisTrig);
+        scrolling.tic(|_| to_fixed(129));
+        scrolling.tic(|_| to_fixed(129));
+        scrolling.tic(|_| to_fixed(129));
+        assert_eq!(scrolling.flats[0].floor_x, to_fixed(-6), "keeps its speed");
+    }
+}
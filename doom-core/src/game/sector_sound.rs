@@ -0,0 +1,254 @@
+//! The sounds of moving sectors: doors, lifts, floors and crushers.
+//!
+//! A sector has no position of its own, so `P_GroupLines` gives each one a sound origin in
+//! the middle of the bounding box of its lines, and every mover in the sector plays from
+//! there. Doors sound once as they open or close. Lifts sound as they start and stop.
+//! Floors, ceilings and crushers grind with `stnmov` every eighth tic of `leveltime` while
+//! they move. A new sound at an origin replaces the one playing there, so the stop sound cuts
+//! the grinding off. Movers that stop without one send `Cue::Stop`, so a long sound doesn't
+//! outlast the movement.
+//!
+//! The origins share the channel allocator's numbering with the mobjs, so they are counted
+//! on from past the last map thing a level can have.
+
+use crate::util::fixed::{Fixed, FRACBITS};
+use crate::wad::map::{MapData, NO_SIDEDEF};
+use alloc::vec::Vec;
+
+/// Where the channel origins of sectors start: past every index a THINGS lump can give.
+const SECTOR_ORIGINS: usize = 0x1_0000;
+const GRINDING: &str = "stnmov";
+
+/// The channel allocator's origin for the sounds of `sector`.
+pub fn origin(sector: usize) -> usize {
+    SECTOR_ORIGINS + sector
+}
+
+/// `sfxinfo->priority` of a mover's sound: the grinding gives way to the rest.
+pub fn priority(sound: &str) -> i32 {
+    match sound {
+        GRINDING => 119,
+        _ => 100,
+    }
+}
+
+/// The sound origin of every sector: the center of the box around its lines' vertexes.
+/// Sectors no line touches get the origin of the map.
+pub fn sound_origins(map: &MapData) -> Vec<(Fixed, Fixed)> {
+    // Left, right, bottom, top, as vanilla's bbox but in map units.
+    let mut boxes = alloc::vec![None::<[i32; 4]>; map.sectors.len()];
+    for line in &map.linedefs {
+        let sides = [line.front, line.back]
+            .into_iter()
+            .filter(|&s| s != NO_SIDEDEF);
+        for side in sides {
+            let sector = usize::from(map.sidedefs[usize::from(side)].sector);
+            for vertex in [line.v1, line.v2] {
+                let vertex = map.vertexes[usize::from(vertex)];
+                let (x, y) = (i32::from(vertex.x), i32::from(vertex.y));
+                let bbox = boxes[sector].get_or_insert([x, x, y, y]);
+                *bbox = [
+                    bbox[0].min(x),
+                    bbox[1].max(x),
+                    bbox[2].min(y),
+                    bbox[3].max(y),
+                ];
+            }
+        }
+    }
+    boxes
+        .into_iter()
+        .map(|bbox| match bbox {
+            // Halved in fixed point, as vanilla does, so odd sizes keep their half unit.
+            Some([left, right, bottom, top]) => (
+                ((left << FRACBITS) + (right << FRACBITS)) / 2,
+                ((bottom << FRACBITS) + (top << FRACBITS)) / 2,
+            ),
+            None => (0, 0),
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoverKind {
+    Door {
+        blazing: bool,
+    },
+    /// `changes_texture` is the raise-and-change kind, which grinds like a floor instead of
+    /// sounding like a lift.
+    Plat {
+        changes_texture: bool,
+    },
+    Floor,
+    Ceiling {
+        silent: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Motion {
+    /// The mover started. `up` means opening for doors.
+    Started {
+        up: bool,
+    },
+    Moving,
+    /// Blocked, and heading back the way it came.
+    Reversed,
+    Finished,
+}
+
+/// What to do at the sector's sound origin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cue {
+    Start(&'static str),
+    Stop,
+}
+
+/// What a mover in `kind` sounds like on this tic, as the vanilla thinkers decide.
+pub fn cue(kind: MoverKind, motion: Motion, leveltime: i32) -> Option<Cue> {
+    let grinding = leveltime & 7 == 0;
+    let sound = match (kind, motion) {
+        (MoverKind::Door { blazing }, Motion::Started { up: true } | Motion::Reversed) => {
+            if blazing {
+                "bdopn"
+            } else {
+                "doropn"
+            }
+        }
+        (MoverKind::Door { blazing }, Motion::Started { up: false }) => {
+            if blazing {
+                "bdcls"
+            } else {
+                "dorcls"
+            }
+        }
+        (MoverKind::Door { .. }, _) => return None,
+
+        (
+            MoverKind::Plat {
+                changes_texture: true,
+            },
+            Motion::Started { .. },
+        ) => GRINDING,
+        (
+            MoverKind::Plat {
+                changes_texture: true,
+            },
+            Motion::Moving,
+        ) if grinding => GRINDING,
+        (MoverKind::Plat { .. }, Motion::Started { .. } | Motion::Reversed) => "pstart",
+        (MoverKind::Plat { .. } | MoverKind::Floor, Motion::Finished) => "pstop",
+
+        (MoverKind::Floor | MoverKind::Ceiling { silent: false }, Motion::Moving) if grinding => {
+            GRINDING
+        }
+        (MoverKind::Ceiling { silent: true }, Motion::Finished | Motion::Reversed) => "pstop",
+        (MoverKind::Ceiling { silent: false }, Motion::Finished) => return Some(Cue::Stop),
+        _ => return None,
+    };
+    Some(Cue::Start(sound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixed::FRACUNIT;
+    use crate::wad::map::{LineDef, Sector, SideDef, Vertex};
+    use alloc::string::String;
+    use alloc::vec;
+
+    #[test]
+    fn origins_are_the_middle_of_the_lines() {
+        let side = |sector| SideDef {
+            x_offset: 0,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: String::new(),
+            sector,
+        };
+        let line = |v1, v2, front, back| LineDef {
+            v1,
+            v2,
+            flags: 0,
+            special: 0,
+            tag: 0,
+            front,
+            back,
+        };
+        let sector = Sector {
+            floor_height: 0,
+            ceiling_height: 128,
+            floor_pic: String::new(),
+            ceiling_pic: String::new(),
+            light: 160,
+            special: 0,
+            tag: 0,
+        };
+        let map = MapData {
+            things: vec![],
+            vertexes: vec![
+                Vertex { x: -64, y: 0 },
+                Vertex { x: 64, y: 0 },
+                Vertex { x: 64, y: 129 },
+                Vertex { x: 200, y: 129 },
+            ],
+            // Sector 0 is bounded by lines 0 and 1; line 2 touches only sector 1.
+            linedefs: vec![
+                line(0, 1, 0, NO_SIDEDEF),
+                line(1, 2, 1, 0),
+                line(2, 3, 1, NO_SIDEDEF),
+            ],
+            sidedefs: vec![side(0), side(1)],
+            sectors: vec![sector.clone(), sector.clone(), sector],
+        };
+        let origins = sound_origins(&map);
+        assert_eq!(origins[0], (0, 129 * FRACUNIT / 2));
+        assert_eq!(origins[1], (132 * FRACUNIT, 129 * FRACUNIT / 2));
+        assert_eq!(origins[2], (0, 0));
+    }
+
+    #[test]
+    fn movers_sound_like_vanilla() {
+        let door = MoverKind::Door { blazing: false };
+        let blazing = MoverKind::Door { blazing: true };
+        assert_eq!(
+            cue(door, Motion::Started { up: true }, 3),
+            Some(Cue::Start("doropn"))
+        );
+        assert_eq!(cue(door, Motion::Reversed, 3), Some(Cue::Start("doropn")));
+        assert_eq!(
+            cue(blazing, Motion::Started { up: false }, 3),
+            Some(Cue::Start("bdcls"))
+        );
+        assert_eq!(cue(door, Motion::Finished, 0), None);
+
+        let lift = MoverKind::Plat {
+            changes_texture: false,
+        };
+        assert_eq!(
+            cue(lift, Motion::Started { up: false }, 1),
+            Some(Cue::Start("pstart"))
+        );
+        assert_eq!(cue(lift, Motion::Moving, 8), None);
+        assert_eq!(cue(lift, Motion::Finished, 1), Some(Cue::Start("pstop")));
+
+        for kind in [MoverKind::Floor, MoverKind::Ceiling { silent: false }] {
+            assert_eq!(cue(kind, Motion::Moving, 16), Some(Cue::Start("stnmov")));
+            assert_eq!(cue(kind, Motion::Moving, 17), None);
+        }
+        assert_eq!(
+            cue(MoverKind::Floor, Motion::Finished, 5),
+            Some(Cue::Start("pstop"))
+        );
+        let crusher = MoverKind::Ceiling { silent: false };
+        assert_eq!(cue(crusher, Motion::Finished, 5), Some(Cue::Stop));
+        let silent = MoverKind::Ceiling { silent: true };
+        assert_eq!(cue(silent, Motion::Moving, 8), None);
+        assert_eq!(cue(silent, Motion::Reversed, 8), Some(Cue::Start("pstop")));
+        assert!(
+            priority("stnmov") > priority("pstop"),
+            "grinding is cut first"
+        );
+    }
+}
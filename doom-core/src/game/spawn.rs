@@ -0,0 +1,294 @@
+//! Which map things spawn: the skill and multiplayer flags, `-nomonsters`, and the optional
+//! filters that go beyond vanilla.
+//!
+//! These are the checks at the top of `P_SpawnMapThing`. They run once as the level loads,
+//! so they only change a demo's sync through what the demo header records. `-nomonsters` is
+//! in the header and plays back as recorded. Co-op spawns and the thing filter aren't, so
+//! playback turns them off, and recording with them makes a demo that only plays back here
+//! with the same settings.
+
+use super::info::{GameInfo, MF_COUNTKILL, MF_NOTDMATCH};
+use super::Skill;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::wad::map::Thing;
+use std::fmt;
+use std::str::FromStr;
+
+pub const MTF_EASY: u16 = 1;
+pub const MTF_NORMAL: u16 = 2;
+pub const MTF_HARD: u16 = 4;
+pub const MTF_AMBUSH: u16 = 8;
+/// Only spawned in netgames: the extra monsters and items of co-op and deathmatch.
+pub const MTF_NOTSINGLE: u16 = 16;
+
+pub const DEATHMATCH_START: u16 = 11;
+/// `-nomonsters` removes the lost soul too, although it isn't counted as a kill.
+const LOST_SOUL: i32 = 3006;
+
+/// Zombieman, shotgun guy, chaingunner, Wolfenstein SS and spider mastermind.
+const HITSCANNERS: [i32; 5] = [3004, 9, 65, 84, 7];
+const WEAPONS: [i32; 7] = [2001, 82, 2002, 2003, 2004, 2005, 2006];
+/// Spheres, berserk, radiation suit, computer map and light amplification visor.
+const POWERUPS: [i32; 8] = [2022, 2023, 2024, 2025, 2026, 2045, 2013, 83];
+
+/// Groups of things the filter can name instead of listing editor numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThingClass {
+    /// What `-nomonsters` removes.
+    Monsters,
+    Hitscanners,
+    Weapons,
+    Powerups,
+}
+
+impl ThingClass {
+    const NAMES: [(&'static str, ThingClass); 4] = [
+        ("monsters", ThingClass::Monsters),
+        ("hitscanners", ThingClass::Hitscanners),
+        ("weapons", ThingClass::Weapons),
+        ("powerups", ThingClass::Powerups),
+    ];
+
+    pub fn contains(self, doomednum: i32, flags: u32) -> bool {
+        match self {
+            ThingClass::Monsters => flags & MF_COUNTKILL != 0 || doomednum == LOST_SOUL,
+            ThingClass::Hitscanners => HITSCANNERS.contains(&doomednum),
+            ThingClass::Weapons => WEAPONS.contains(&doomednum),
+            ThingClass::Powerups => POWERUPS.contains(&doomednum),
+        }
+    }
+}
+
+/// One entry of the `spawn_filter` option: a class name or an editor number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exclusion {
+    Class(ThingClass),
+    DoomEdNum(i32),
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exclusion::Class(class) => {
+                let (name, _) = ThingClass::NAMES.iter().find(|(_, c)| c == class).unwrap();
+                f.write_str(name)
+            }
+            Exclusion::DoomEdNum(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+impl FromStr for Exclusion {
+    type Err = String;
+
+    fn from_str(word: &str) -> Result<Self, String> {
+        if let Ok(number) = word.parse() {
+            return Ok(Exclusion::DoomEdNum(number));
+        }
+        ThingClass::NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(word))
+            .map(|&(_, class)| Exclusion::Class(class))
+            .ok_or_else(|| format!("unknown thing class '{word}'"))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpawnFilter {
+    pub nomonsters: bool,
+    /// Spawn the multiplayer-only things in single player too.
+    pub coop_spawns: bool,
+    pub exclude: Vec<Exclusion>,
+}
+
+impl SpawnFilter {
+    /// Parses a `spawn_filter` value such as `hitscanners, 2006`.
+    pub fn parse_exclusions(text: &str) -> Result<Vec<Exclusion>, String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// What a demo's playback spawns with: only what its header recorded.
+    pub fn for_playback(nomonsters: bool) -> Self {
+        Self {
+            nomonsters,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the filter goes beyond what a demo header can record.
+    pub fn changes_sync(&self) -> bool {
+        self.coop_spawns || !self.exclude.is_empty()
+    }
+
+    /// Whether `thing` spawns, checked in `P_SpawnMapThing`'s order. Player and deathmatch
+    /// starts are never filtered, and things with an editor number no mobj has don't spawn.
+    pub fn spawns(
+        &self,
+        thing: &Thing,
+        netgame: bool,
+        deathmatch: bool,
+        skill: Skill,
+        info: &GameInfo,
+    ) -> bool {
+        if matches!(thing.kind, 1..=4 | DEATHMATCH_START) {
+            return true;
+        }
+        if !netgame && thing.flags & MTF_NOTSINGLE != 0 && !self.coop_spawns {
+            return false;
+        }
+        let skill_bit = match skill {
+            Skill::Baby | Skill::Easy => MTF_EASY,
+            Skill::Medium => MTF_NORMAL,
+            Skill::Hard | Skill::Nightmare => MTF_HARD,
+        };
+        if thing.flags & skill_bit == 0 {
+            return false;
+        }
+        let doomednum = i32::from(thing.kind);
+        let Some(mobj) = info.mobjs.iter().find(|mobj| mobj.doomednum == doomednum) else {
+            return false;
+        };
+        if deathmatch && mobj.flags & MF_NOTDMATCH != 0 {
+            return false;
+        }
+        if self.nomonsters && ThingClass::Monsters.contains(doomednum, mobj.flags) {
+            return false;
+        }
+        !self.exclude.iter().any(|exclusion| match *exclusion {
+            Exclusion::Class(class) => class.contains(doomednum, mobj.flags),
+            Exclusion::DoomEdNum(number) => number == doomednum,
+        })
+    }
+}
+
+impl Configurable for SpawnFilter {
+    fn register(config: &mut Config) {
+        config.register_bool("coop_spawns", false, Section::Extended);
+        config.register_str("spawn_filter", "", Section::Extended);
+    }
+
+    /// An unparsable `spawn_filter` filters nothing, rather than the wrong things.
+    fn from_config(config: &Config) -> Self {
+        Self {
+            nomonsters: false,
+            coop_spawns: config.get_bool("coop_spawns"),
+            exclude: Self::parse_exclusions(&config.get_str("spawn_filter")).unwrap_or_default(),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("coop_spawns", self.coop_spawns);
+        let words: Vec<String> = self.exclude.iter().map(Exclusion::to_string).collect();
+        config.set_str("spawn_filter", &words.join(","));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::MobjInfo;
+
+    fn info() -> GameInfo {
+        let mobj = |doomednum, flags| MobjInfo {
+            doomednum,
+            flags,
+            ..MobjInfo::default()
+        };
+        GameInfo {
+            mobjs: vec![
+                mobj(3004, MF_COUNTKILL),
+                mobj(3001, MF_COUNTKILL),
+                mobj(LOST_SOUL, 0),
+                mobj(2006, 0),
+                mobj(2011, 0),
+                mobj(5, MF_NOTDMATCH),
+            ],
+            ..GameInfo::default()
+        }
+    }
+
+    fn thing(kind: u16, flags: u16) -> Thing {
+        Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind,
+            flags,
+        }
+    }
+
+    #[test]
+    fn vanilla_rules() {
+        let info = info();
+        let filter = SpawnFilter::default();
+        let all = MTF_EASY | MTF_NORMAL | MTF_HARD;
+        assert!(filter.spawns(&thing(3001, all), false, false, Skill::Medium, &info));
+        assert!(!filter.spawns(&thing(3001, MTF_HARD), false, false, Skill::Easy, &info));
+        assert!(filter.spawns(
+            &thing(3001, MTF_HARD),
+            false,
+            false,
+            Skill::Nightmare,
+            &info
+        ));
+        assert!(filter.spawns(&thing(3001, MTF_EASY), false, false, Skill::Baby, &info));
+        assert!(!filter.spawns(
+            &thing(3001, all | MTF_NOTSINGLE),
+            false,
+            false,
+            Skill::Hard,
+            &info
+        ));
+        assert!(filter.spawns(
+            &thing(3001, all | MTF_NOTSINGLE),
+            true,
+            false,
+            Skill::Hard,
+            &info
+        ));
+        assert!(!filter.spawns(&thing(1234, all), false, false, Skill::Hard, &info));
+        assert!(filter.spawns(&thing(1, 0), false, false, Skill::Hard, &info));
+        assert!(filter.spawns(&thing(5, all), true, false, Skill::Hard, &info));
+        assert!(!filter.spawns(&thing(5, all), true, true, Skill::Hard, &info));
+
+        let nomonsters = SpawnFilter::for_playback(true);
+        for monster in [3001, 3004, LOST_SOUL as u16] {
+            assert!(!nomonsters.spawns(&thing(monster, all), false, false, Skill::Hard, &info));
+        }
+        assert!(nomonsters.spawns(&thing(2011, all), false, false, Skill::Hard, &info));
+        assert!(!nomonsters.changes_sync());
+    }
+
+    #[test]
+    fn filters_and_coop_spawns() {
+        let info = info();
+        let all = MTF_EASY | MTF_NORMAL | MTF_HARD;
+        let filter = SpawnFilter {
+            nomonsters: false,
+            coop_spawns: true,
+            exclude: SpawnFilter::parse_exclusions("Hitscanners, 2006,").unwrap(),
+        };
+        assert!(filter.changes_sync());
+        assert!(filter.spawns(
+            &thing(3001, all | MTF_NOTSINGLE),
+            false,
+            false,
+            Skill::Hard,
+            &info
+        ));
+        assert!(!filter.spawns(&thing(3004, all), false, false, Skill::Hard, &info));
+        assert!(!filter.spawns(&thing(2006, all), false, false, Skill::Hard, &info));
+        assert!(filter.spawns(&thing(2011, all), false, false, Skill::Hard, &info));
+        assert!(SpawnFilter::parse_exclusions("snipers").is_err());
+
+        let mut config = Config::new();
+        SpawnFilter::register(&mut config);
+        filter.to_config(&mut config);
+        assert_eq!(config.get_str("spawn_filter"), "hitscanners,2006");
+        assert_eq!(SpawnFilter::from_config(&config), filter);
+    }
+}
@@ -0,0 +1,231 @@
+//! Spectating: watching through another player's eyes or flying a free camera.
+//!
+//! Vanilla's spy key steps `displayplayer` through the players in a demo or netgame. The
+//! free camera is a viewpoint owned by no mobj: it flies through walls and never touches the
+//! simulation, since it makes no ticcmds. That makes it safe during demo playback and for a
+//! dead player in a netgame, the two places it is offered. The renderer draws from any
+//! `ViewPoint`, so spectating only changes which one it is handed. The camera accelerates and
+//! coasts to a stop with the player's friction, so the motion stays smooth.
+
+use crate::engine::controls::{Control, Controls, HeldKeys};
+use crate::renderer::ViewPoint;
+use crate::util::angle::{sin_cos, Angle};
+use crate::util::fixed::{fixed_mul, Fixed, FRACUNIT};
+
+const ACCELERATION: Fixed = FRACUNIT; // Added to the momentum per tic a key is held.
+const FRICTION: Fixed = 0xe800; // Vanilla's ORIG_FRICTION.
+const STOPSPEED: Fixed = 0x1000;
+const TURN_SPEED: [Angle; 2] = [640 << 16, 1280 << 16]; // Vanilla's angleturn, walk and run.
+
+/// Which way the camera is pushed this tic, each -1, 0 or 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CameraInput {
+    pub forward: i32,
+    pub side: i32, // Positive is right.
+    pub up: i32,
+    pub turn: i32, // Positive is left, as angles grow counterclockwise.
+    pub run: bool,
+}
+
+impl CameraInput {
+    /// The player's own movement keys, with the fly keys for height.
+    pub fn from_keys(controls: &Controls, keys: &HeldKeys) -> Self {
+        Self {
+            forward: controls.axis(Control::Forward, Control::Back, keys),
+            side: controls.axis(Control::StrafeRight, Control::StrafeLeft, keys),
+            up: controls.axis(Control::FlyUp, Control::FlyDown, keys),
+            turn: controls.axis(Control::TurnLeft, Control::TurnRight, keys),
+            run: controls.held(Control::Speed, keys),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FreeCamera {
+    pub view: ViewPoint,
+    momentum: [Fixed; 3],
+}
+
+impl FreeCamera {
+    pub fn new(view: ViewPoint) -> Self {
+        Self {
+            view,
+            momentum: [0; 3],
+        }
+    }
+
+    pub fn ticker(&mut self, input: &CameraInput) {
+        let run = input.run as usize;
+        let turn = TURN_SPEED[run].wrapping_mul(input.turn as u32);
+        self.view.angle = self.view.angle.wrapping_add(turn);
+
+        let thrust = ACCELERATION << run;
+        let (sin, cos) = sin_cos(self.view.angle);
+        let forward = thrust * input.forward;
+        let side = thrust * input.side;
+        self.momentum[0] += fixed_mul(forward, cos) + fixed_mul(side, sin);
+        self.momentum[1] += fixed_mul(forward, sin) - fixed_mul(side, cos);
+        self.momentum[2] += thrust * input.up;
+
+        let position = [&mut self.view.x, &mut self.view.y, &mut self.view.z];
+        for (coordinate, momentum) in position.into_iter().zip(&mut self.momentum) {
+            *coordinate = coordinate.wrapping_add(*momentum);
+            *momentum = fixed_mul(*momentum, FRICTION);
+            if momentum.abs() < STOPSPEED {
+                *momentum = 0;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Camera {
+    /// Through the eyes of a player, vanilla's `displayplayer`.
+    Player(usize),
+    Free(FreeCamera),
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::Player(0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Spectator {
+    pub camera: Camera,
+    pub followed: usize, // The player the spy key is on, kept while the camera is free.
+}
+
+impl Spectator {
+    pub fn new(console_player: usize) -> Self {
+        Self {
+            camera: Camera::Player(console_player),
+            followed: console_player,
+        }
+    }
+
+    /// Whether the free camera is offered: in a demo, or once dead in a netgame.
+    pub fn free_camera_allowed(demo_playback: bool, netgame: bool, player_dead: bool) -> bool {
+        demo_playback || (netgame && player_dead)
+    }
+
+    pub fn is_free(&self) -> bool {
+        matches!(self.camera, Camera::Free(_))
+    }
+
+    /// The spy key: the next player in the game after the one followed. Pressed while the
+    /// camera is free, it snaps back to the followed player first.
+    pub fn spy(&mut self, in_game: &[bool]) {
+        if self.is_free() {
+            self.camera = Camera::Player(self.followed);
+            return;
+        }
+        let count = in_game.len();
+        if let Some(next) = (1..=count)
+            .map(|step| (self.followed + step) % count)
+            .find(|&player| in_game[player])
+        {
+            self.followed = next;
+        }
+        self.camera = Camera::Player(self.followed);
+    }
+
+    /// The free camera key: takes off from wherever the view is now, or lands back on the
+    /// followed player.
+    pub fn toggle_free(&mut self, current: ViewPoint) {
+        self.camera = match self.camera {
+            Camera::Player(_) => Camera::Free(FreeCamera::new(current)),
+            Camera::Free(_) => Camera::Player(self.followed),
+        };
+    }
+
+    /// Called every tic; in a demo this runs even while playback is paused.
+    pub fn ticker(&mut self, input: &CameraInput) {
+        if let Camera::Free(camera) = &mut self.camera {
+            camera.ticker(input);
+        }
+    }
+
+    /// The viewpoint to render from, given each player's eye position.
+    pub fn view(&self, players: &[ViewPoint]) -> ViewPoint {
+        match self.camera {
+            Camera::Player(player) => players.get(player).copied().unwrap_or_default(),
+            Camera::Free(camera) => camera.view,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::angle::ANG90;
+    use crate::util::fixed::to_fixed;
+
+    fn forward() -> CameraInput {
+        CameraInput {
+            forward: 1,
+            ..CameraInput::default()
+        }
+    }
+
+    #[test]
+    fn the_camera_accelerates_along_its_angle_and_coasts_to_a_stop() {
+        let mut camera = FreeCamera::new(ViewPoint {
+            angle: ANG90,
+            ..ViewPoint::default()
+        });
+        camera.ticker(&forward());
+        camera.ticker(&forward());
+        assert_eq!(camera.view.x, 0);
+        assert!(camera.view.y > FRACUNIT);
+        let coasting = camera.view.y;
+        for _ in 0..100 {
+            camera.ticker(&CameraInput::default());
+        }
+        assert!(camera.view.y > coasting);
+        assert_eq!(camera.momentum, [0; 3]);
+
+        camera.ticker(&CameraInput {
+            side: 1,
+            up: -1,
+            ..CameraInput::default()
+        });
+        assert!(camera.view.x > 0 && camera.view.z < 0);
+        camera.ticker(&CameraInput {
+            turn: -1,
+            run: true,
+            ..CameraInput::default()
+        });
+        assert_eq!(camera.view.angle, ANG90 - (1280 << 16));
+    }
+
+    #[test]
+    fn spy_cycles_players_and_snaps_back_from_the_free_camera() {
+        let players = [
+            ViewPoint::default(),
+            ViewPoint {
+                x: to_fixed(64),
+                ..ViewPoint::default()
+            },
+            ViewPoint::default(),
+        ];
+        let in_game = [true, true, false];
+        let mut spectator = Spectator::new(0);
+        spectator.spy(&in_game);
+        assert_eq!(spectator.camera, Camera::Player(1));
+        spectator.spy(&in_game);
+        assert_eq!(spectator.camera, Camera::Player(0));
+        spectator.spy(&in_game);
+
+        spectator.toggle_free(spectator.view(&players));
+        spectator.ticker(&forward());
+        assert!(spectator.view(&players).x > to_fixed(64));
+        spectator.spy(&in_game);
+        assert_eq!(spectator.view(&players), players[1]);
+
+        assert!(Spectator::free_camera_allowed(true, false, false));
+        assert!(!Spectator::free_camera_allowed(false, true, false));
+        assert!(Spectator::free_camera_allowed(false, true, true));
+    }
+}
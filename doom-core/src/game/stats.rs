@@ -0,0 +1,107 @@
+//! The level counters: kills, items, secrets and time.
+//!
+//! These are what vanilla hands to the intermission in `wbstartstruct_t` (the totals) and
+//! `wbplayerstruct_t` (the player's counts). Anything else that shows them, like the extended
+//! HUD, reads the same struct, so the numbers can never disagree with the tally screen.
+
+use super::TICRATE;
+use alloc::format;
+use alloc::string::String;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LevelStats {
+    pub kills: i32,
+    pub items: i32,
+    pub secrets: i32,
+    pub total_kills: i32, // Counted from the things spawned when the level loads.
+    pub total_items: i32,
+    pub total_secrets: i32,
+    pub level_time: i32, // Tics since the level started, vanilla's `leveltime`.
+    pub total_time: i32, // Tics spent in the levels finished before this one.
+}
+
+impl LevelStats {
+    /// Resets the counts for a new level, keeping the time of the previous ones.
+    pub fn start_level(&mut self, total_kills: i32, total_items: i32, total_secrets: i32) {
+        *self = Self {
+            total_kills,
+            total_items,
+            total_secrets,
+            total_time: self.total_time,
+            ..Self::default()
+        };
+    }
+
+    pub fn tic(&mut self) {
+        self.level_time += 1;
+    }
+
+    /// Adds the level's time to the total when it is exited.
+    pub fn finish_level(&mut self) {
+        self.total_time += self.level_time;
+    }
+
+    /// The time since the run started, including the level being played.
+    pub fn run_time(&self) -> i32 {
+        self.total_time + self.level_time
+    }
+
+    /// A count as the intermission shows it: the percentage of the total, where vanilla
+    /// divides by 1 when a level has none.
+    pub fn percent(count: i32, total: i32) -> i32 {
+        count * 100 / total.max(1)
+    }
+}
+
+/// `m:ss.cc`, rounded to hundredths as PrBoom+'s `"%d:%05.2f"` does, which is what
+/// speedrunning tools expect.
+pub fn format_time(tics: i32) -> String {
+    let tics = tics.max(0);
+    let minute = 60 * TICRATE;
+    // In integers, rounded to the nearest hundredth: a tic is never exactly half of one.
+    let hundredths = ((tics % minute) * 200 + TICRATE) / (2 * TICRATE);
+    format!(
+        "{}:{:02}.{:02}",
+        tics / minute,
+        hundredths / 100,
+        hundredths % 100
+    )
+}
+
+/// `m:ss`, for totals where the fraction is noise.
+pub fn format_seconds(tics: i32) -> String {
+    let seconds = tics.max(0) / TICRATE;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_reset_counts_and_accumulate_time() {
+        let mut stats = LevelStats::default();
+        stats.start_level(20, 5, 2);
+        stats.kills = 3;
+        for _ in 0..TICRATE * 2 {
+            stats.tic();
+        }
+        stats.finish_level();
+        stats.start_level(10, 0, 0);
+        stats.tic();
+        assert_eq!(stats.kills, 0);
+        assert_eq!(stats.total_kills, 10);
+        assert_eq!(stats.total_time, 70);
+        assert_eq!(stats.run_time(), 71);
+        assert_eq!(LevelStats::percent(3, 20), 15);
+        assert_eq!(LevelStats::percent(0, 0), 0);
+    }
+
+    #[test]
+    fn times_format_like_the_speedrun_tools() {
+        assert_eq!(format_time(0), "0:00.00");
+        assert_eq!(format_time(TICRATE * 75 + 7), "1:15.20");
+        assert_eq!(format_time(1), "0:00.03");
+        assert_eq!(format_seconds(TICRATE * 3600), "60:00");
+    }
+}
@@ -0,0 +1,195 @@
+//! Strict mode: nothing modern reaches the simulation.
+//!
+//! Competition demos have to be recorded with exactly the vanilla game. With strict mode on,
+//! every setting that can change what happens in a tic, or what the player can do with their
+//! input, is held at its vanilla value whatever the config says. Presentation options that
+//! only change how a frame looks stay available. Each feature that strict mode blocks asks
+//! `StrictMode::allows` before taking effect. Whatever was blocked is listed on the HUD,
+//! so the recorder can see nothing leaked in.
+
+use super::limits::{LimitOverrides, OverflowMode, OverflowOptions};
+use crate::engine::config::{Config, Configurable, Section};
+
+/// A non-vanilla feature that can affect the simulation or the player's input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    /// Uncapped frames draw between tics; the extrapolated positions must not feed back into
+    /// anything the game reads.
+    Interpolation,
+    /// Looking up and down, which vanilla's input can't express.
+    Freelook,
+    /// Aiming at what the crosshair is on rather than with vanilla's autoaim.
+    CrosshairAutoaim,
+    /// Per limit settings that win over the compatibility level.
+    LimitOverrides,
+    /// Removing the intercepts or spechit limits instead of emulating their overflow.
+    OverflowRemoval,
+    /// Going back to a keyframe, which a demo can't contain.
+    Rewind,
+    /// Running the tic clock slower or faster than real time.
+    GameSpeed,
+    /// Drawing spectres as anything but vanilla's fuzz, which makes them easier to see.
+    SpectreStyle,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 8] = [
+        Feature::Interpolation,
+        Feature::Freelook,
+        Feature::CrosshairAutoaim,
+        Feature::LimitOverrides,
+        Feature::OverflowRemoval,
+        Feature::Rewind,
+        Feature::GameSpeed,
+        Feature::SpectreStyle,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Interpolation => "interpolation",
+            Feature::Freelook => "freelook",
+            Feature::CrosshairAutoaim => "crosshair autoaim",
+            Feature::LimitOverrides => "limit overrides",
+            Feature::OverflowRemoval => "overflow removal",
+            Feature::Rewind => "rewind",
+            Feature::GameSpeed => "game speed",
+            Feature::SpectreStyle => "spectre style",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StrictMode {
+    pub enabled: bool,
+}
+
+impl StrictMode {
+    pub fn allows(&self, _feature: Feature) -> bool {
+        !self.enabled
+    }
+
+    /// The limit overrides to play with: none in strict mode, so the compatibility level
+    /// alone decides.
+    pub fn limit_overrides(&self, overrides: LimitOverrides) -> LimitOverrides {
+        match self.allows(Feature::LimitOverrides) {
+            true => overrides,
+            false => LimitOverrides::default(),
+        }
+    }
+
+    /// The overflow handling to play with: strict mode emulates the overflows that change
+    /// the simulation. The visplane and savegame limits only ever end the game, so their
+    /// setting is kept.
+    pub fn overflows(&self, overflows: OverflowOptions) -> OverflowOptions {
+        if self.allows(Feature::OverflowRemoval) {
+            return overflows;
+        }
+        let emulate = |mode| match mode {
+            OverflowMode::Remove => OverflowMode::Emulate,
+            mode => mode,
+        };
+        OverflowOptions {
+            intercepts: emulate(overflows.intercepts),
+            spechit: emulate(overflows.spechit),
+            ..overflows
+        }
+    }
+
+    /// Which of the features the settings ask for are held back.
+    pub fn blocked(&self, requested: &[Feature]) -> Vec<Feature> {
+        requested
+            .iter()
+            .copied()
+            .filter(|&feature| !self.allows(feature))
+            .collect()
+    }
+
+    /// The HUD line reporting strict mode, if it is on.
+    pub fn notice(&self, blocked: &[Feature]) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let mut notice = String::from("STRICT MODE");
+        if !blocked.is_empty() {
+            let names: Vec<&str> = blocked.iter().map(|feature| feature.name()).collect();
+            notice.push_str(": NO ");
+            notice.push_str(&names.join(", ").to_ascii_uppercase());
+        }
+        Some(notice)
+    }
+}
+
+/// The features the limit and overflow settings ask for.
+pub fn requested_by(overrides: &LimitOverrides, overflows: &OverflowOptions) -> Vec<Feature> {
+    let mut requested = Vec::new();
+    if *overrides != LimitOverrides::default() {
+        requested.push(Feature::LimitOverrides);
+    }
+    if overflows.intercepts == OverflowMode::Remove || overflows.spechit == OverflowMode::Remove {
+        requested.push(Feature::OverflowRemoval);
+    }
+    requested
+}
+
+impl Configurable for StrictMode {
+    fn register(config: &mut Config) {
+        config.register_bool("strict_mode", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.get_bool("strict_mode"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("strict_mode", self.enabled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_mode_holds_gameplay_settings_at_vanilla() {
+        let overrides = LimitOverrides {
+            lost_souls: Some(false),
+            ..LimitOverrides::default()
+        };
+        let overflows = OverflowOptions {
+            intercepts: OverflowMode::Remove,
+            ..OverflowOptions::default()
+        };
+        let off = StrictMode::default();
+        assert!(off.allows(Feature::Freelook));
+        assert_eq!(off.limit_overrides(overrides), overrides);
+        assert_eq!(off.overflows(overflows), overflows);
+        assert_eq!(off.notice(&[]), None);
+
+        let strict = StrictMode { enabled: true };
+        assert!(Feature::ALL.iter().all(|&feature| !strict.allows(feature)));
+        assert_eq!(strict.limit_overrides(overrides), LimitOverrides::default());
+        let held = strict.overflows(overflows);
+        assert_eq!(held.intercepts, OverflowMode::Emulate);
+        assert_eq!(held.visplanes, OverflowMode::Remove);
+    }
+
+    #[test]
+    fn blocked_features_are_reported() {
+        let overrides = LimitOverrides {
+            plats: Some(true),
+            ..LimitOverrides::default()
+        };
+        let requested = requested_by(&overrides, &OverflowOptions::default());
+        assert_eq!(requested, vec![Feature::LimitOverrides]);
+        let strict = StrictMode { enabled: true };
+        let blocked = strict.blocked(&requested);
+        assert_eq!(
+            strict.notice(&blocked).as_deref(),
+            Some("STRICT MODE: NO LIMIT OVERRIDES")
+        );
+        assert_eq!(strict.notice(&[]).as_deref(), Some("STRICT MODE"));
+        assert!(StrictMode::default().blocked(&requested).is_empty());
+    }
+}
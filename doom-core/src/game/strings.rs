@@ -0,0 +1,372 @@
+//! Every message, prompt and text screen the player reads, keyed by its `d_englsh.h` name.
+//!
+//! Code that shows text refers to an entry by name (`"GOTARMOR"`, `"STSTR_DQDON"`) and looks
+//! it up when drawing, so replacements apply everywhere at once. They come from three places,
+//! in increasing priority: a language pack, BEX `[STRINGS]`, and DeHackEd `Text` blocks, which
+//! match the original English text rather than a name. Language packs use the BEX
+//! `[STRINGS]` syntax and are read with the same parser; `lang/french.bex` is the French
+//! text of `d_french.h`.
+
+use super::dehacked::Dehacked;
+use super::info::GameInfo;
+use std::collections::BTreeMap;
+
+macro_rules! presskey {
+    ($text:literal) => {
+        concat!($text, "\n\npress a key.")
+    };
+}
+
+macro_rules! pressyn {
+    ($text:literal) => {
+        concat!($text, "\n\npress y or n.")
+    };
+}
+
+#[rustfmt::skip]
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("D_DEVSTR", "Development mode ON.\n"),
+    ("D_CDROM", "CD-ROM Version: default.cfg from c:\\doomdata\n"),
+    ("PRESSKEY", "press a key."),
+    ("PRESSYN", "press y or n."),
+    ("QUITMSG", "are you sure you want to\nquit this great game?"),
+    ("LOADNET", presskey!("you can't do load while in a net game!")),
+    ("QLOADNET", presskey!("you can't quickload during a netgame!")),
+    ("QSAVESPOT", presskey!("you haven't picked a quicksave slot yet!")),
+    ("SAVEDEAD", presskey!("you can't save if you aren't playing!")),
+    ("QSPROMPT", pressyn!("quicksave over your game named\n\n'%s'?")),
+    ("QLPROMPT", pressyn!("do you want to quickload the game named\n\n'%s'?")),
+    ("NEWGAME", presskey!("you can't start a new game\nwhile in a network game.")),
+    ("NIGHTMARE", pressyn!("are you sure? this skill level\nisn't even remotely fair.")),
+    ("SWSTRING", presskey!("this is the shareware version of doom.\n\nyou need to order the entire trilogy.")),
+    ("MSGOFF", "Messages OFF"),
+    ("MSGON", "Messages ON"),
+    ("NETEND", presskey!("you can't end a netgame!")),
+    ("ENDGAME", pressyn!("are you sure you want to end the game?")),
+    ("DOSY", "(press y to quit to dos.)"),
+    ("DETAILHI", "High detail"),
+    ("DETAILLO", "Low detail"),
+    ("GAMMALVL0", "Gamma correction OFF"),
+    ("GAMMALVL1", "Gamma correction level 1"),
+    ("GAMMALVL2", "Gamma correction level 2"),
+    ("GAMMALVL3", "Gamma correction level 3"),
+    ("GAMMALVL4", "Gamma correction level 4"),
+    ("EMPTYSTRING", "empty slot"),
+    ("GGSAVED", "game saved."),
+
+    // The quit prompts after QUITMSG, as BEX numbers them: 1 to 7 for Doom, 8 to 14 for Doom II.
+    ("QUITMSG1", "please don't leave, there's more\ndemons to toast!"),
+    ("QUITMSG2", "let's beat it -- this is turning\ninto a bloodbath!"),
+    ("QUITMSG3", "i wouldn't leave if i were you.\ndos is much worse."),
+    ("QUITMSG4", "you're trying to say you like dos\nbetter than me, right?"),
+    ("QUITMSG5", "don't leave yet -- there's a\ndemon around that corner!"),
+    ("QUITMSG6", "ya know, next time you come in here\ni'm gonna toast ya."),
+    ("QUITMSG7", "go ahead and leave. see if i care."),
+    ("QUITMSG8", "you want to quit?\nthen, thou hast lost an eighth!"),
+    ("QUITMSG9", "don't go now, there's a \ndimensional shambler waiting\nat the dos prompt!"),
+    ("QUITMSG10", "get outta here and go back\nto your boring programs."),
+    ("QUITMSG11", "if i were your boss, i'd \n deathmatch ya in a minute!"),
+    ("QUITMSG12", "look, bud. you leave now\nand you forfeit your body count!"),
+    ("QUITMSG13", "just leave. when you come\nback, i'll be waiting with a bat."),
+    ("QUITMSG14", "you're lucky i don't smack\nyou for thinking about leaving."),
+
+    ("GOTARMOR", "Picked up the armor."),
+    ("GOTMEGA", "Picked up the MegaArmor!"),
+    ("GOTHTHBONUS", "Picked up a health bonus."),
+    ("GOTARMBONUS", "Picked up an armor bonus."),
+    ("GOTSTIM", "Picked up a stimpack."),
+    ("GOTMEDINEED", "Picked up a medikit that you REALLY need!"),
+    ("GOTMEDIKIT", "Picked up a medikit."),
+    ("GOTSUPER", "Supercharge!"),
+    ("GOTBLUECARD", "Picked up a blue keycard."),
+    ("GOTYELWCARD", "Picked up a yellow keycard."),
+    ("GOTREDCARD", "Picked up a red keycard."),
+    ("GOTBLUESKUL", "Picked up a blue skull key."),
+    ("GOTYELWSKUL", "Picked up a yellow skull key."),
+    ("GOTREDSKULL", "Picked up a red skull key."),
+    ("GOTINVUL", "Invulnerability!"),
+    ("GOTBERSERK", "Berserk!"),
+    ("GOTINVIS", "Partial Invisibility"),
+    ("GOTSUIT", "Radiation Shielding Suit"),
+    ("GOTMAP", "Computer Area Map"),
+    ("GOTVISOR", "Light Amplification Visor"),
+    ("GOTMSPHERE", "MegaSphere!"),
+    ("GOTCLIP", "Picked up a clip."),
+    ("GOTCLIPBOX", "Picked up a box of bullets."),
+    ("GOTROCKET", "Picked up a rocket."),
+    ("GOTROCKBOX", "Picked up a box of rockets."),
+    ("GOTCELL", "Picked up an energy cell."),
+    ("GOTCELLBOX", "Picked up an energy cell pack."),
+    ("GOTSHELLS", "Picked up 4 shotgun shells."),
+    ("GOTSHELLBOX", "Picked up a box of shotgun shells."),
+    ("GOTBACKPACK", "Picked up a backpack full of ammo!"),
+    ("GOTBFG9000", "You got the BFG9000!  Oh, yes."),
+    ("GOTCHAINGUN", "You got the chaingun!"),
+    ("GOTCHAINSAW", "A chainsaw!  Find some meat!"),
+    ("GOTLAUNCHER", "You got the rocket launcher!"),
+    ("GOTPLASMA", "You got the plasma gun!"),
+    ("GOTSHOTGUN", "You got the shotgun!"),
+    ("GOTSHOTGUN2", "You got the super shotgun!"),
+
+    ("PD_BLUEO", "You need a blue key to activate this object"),
+    ("PD_REDO", "You need a red key to activate this object"),
+    ("PD_YELLOWO", "You need a yellow key to activate this object"),
+    ("PD_BLUEK", "You need a blue key to open this door"),
+    ("PD_REDK", "You need a red key to open this door"),
+    ("PD_YELLOWK", "You need a yellow key to open this door"),
+
+    ("HUSTR_MSGU", "[Message unsent]"),
+    ("HUSTR_MESSAGESENT", "[Message Sent]"),
+    ("HUSTR_CHATMACRO1", "I'm ready to kick butt!"),
+    ("HUSTR_CHATMACRO2", "I'm OK."),
+    ("HUSTR_CHATMACRO3", "I'm not looking too good!"),
+    ("HUSTR_CHATMACRO4", "Help!"),
+    ("HUSTR_CHATMACRO5", "You suck!"),
+    ("HUSTR_CHATMACRO6", "Next time, scumbag..."),
+    ("HUSTR_CHATMACRO7", "Come here!"),
+    ("HUSTR_CHATMACRO8", "I'll take care of it."),
+    ("HUSTR_CHATMACRO9", "Yes"),
+    ("HUSTR_CHATMACRO0", "No"),
+    ("HUSTR_TALKTOSELF1", "You mumble to yourself"),
+    ("HUSTR_TALKTOSELF2", "Who's there?"),
+    ("HUSTR_TALKTOSELF3", "You scare yourself"),
+    ("HUSTR_TALKTOSELF4", "You start to rave"),
+    ("HUSTR_TALKTOSELF5", "You've lost it..."),
+
+    ("STSTR_MUS", "Music Change"),
+    ("STSTR_NOMUS", "IMPOSSIBLE SELECTION"),
+    ("STSTR_DQDON", "Degreelessness Mode On"),
+    ("STSTR_DQDOFF", "Degreelessness Mode Off"),
+    ("STSTR_KFAADDED", "Very Happy Ammo Added"),
+    ("STSTR_FAADDED", "Ammo (no keys) Added"),
+    ("STSTR_NCON", "No Clipping Mode ON"),
+    ("STSTR_NCOFF", "No Clipping Mode OFF"),
+    ("STSTR_BEHOLD", "inVuln, Str, Inviso, Rad, Allmap, or Lite-amp"),
+    ("STSTR_BEHOLDX", "Power-up Toggled"),
+    ("STSTR_CHOPPERS", "... doesn't suck - GM"),
+    ("STSTR_CLEV", "Changing Level..."),
+
+    ("HUSTR_E1M1", "E1M1: Hangar"),
+    ("HUSTR_E1M2", "E1M2: Nuclear Plant"),
+    ("HUSTR_E1M3", "E1M3: Toxin Refinery"),
+    ("HUSTR_E1M4", "E1M4: Command Control"),
+    ("HUSTR_E1M5", "E1M5: Phobos Lab"),
+    ("HUSTR_E1M6", "E1M6: Central Processing"),
+    ("HUSTR_E1M7", "E1M7: Computer Station"),
+    ("HUSTR_E1M8", "E1M8: Phobos Anomaly"),
+    ("HUSTR_E1M9", "E1M9: Military Base"),
+    ("HUSTR_E2M1", "E2M1: Deimos Anomaly"),
+    ("HUSTR_E2M2", "E2M2: Containment Area"),
+    ("HUSTR_E2M3", "E2M3: Refinery"),
+    ("HUSTR_E2M4", "E2M4: Deimos Lab"),
+    ("HUSTR_E2M5", "E2M5: Command Center"),
+    ("HUSTR_E2M6", "E2M6: Halls of the Damned"),
+    ("HUSTR_E2M7", "E2M7: Spawning Vats"),
+    ("HUSTR_E2M8", "E2M8: Tower of Babel"),
+    ("HUSTR_E2M9", "E2M9: Fortress of Mystery"),
+    ("HUSTR_E3M1", "E3M1: Hell Keep"),
+    ("HUSTR_E3M2", "E3M2: Slough of Despair"),
+    ("HUSTR_E3M3", "E3M3: Pandemonium"),
+    ("HUSTR_E3M4", "E3M4: House of Pain"),
+    ("HUSTR_E3M5", "E3M5: Unholy Cathedral"),
+    ("HUSTR_E3M6", "E3M6: Mt. Erebus"),
+    ("HUSTR_E3M7", "E3M7: Limbo"),
+    ("HUSTR_E3M8", "E3M8: Dis"),
+    ("HUSTR_E3M9", "E3M9: Warrens"),
+    ("HUSTR_E4M1", "E4M1: Hell Beneath"),
+    ("HUSTR_E4M2", "E4M2: Perfect Hatred"),
+    ("HUSTR_E4M3", "E4M3: Sever The Wicked"),
+    ("HUSTR_E4M4", "E4M4: Unruly Evil"),
+    ("HUSTR_E4M5", "E4M5: They Will Repent"),
+    ("HUSTR_E4M6", "E4M6: Against Thee Wickedly"),
+    ("HUSTR_E4M7", "E4M7: And Hell Followed"),
+    ("HUSTR_E4M8", "E4M8: Unto The Cruel"),
+    ("HUSTR_E4M9", "E4M9: Fear"),
+
+    ("HUSTR_1", "level 1: entryway"),
+    ("HUSTR_2", "level 2: underhalls"),
+    ("HUSTR_3", "level 3: the gantlet"),
+    ("HUSTR_4", "level 4: the focus"),
+    ("HUSTR_5", "level 5: the waste tunnels"),
+    ("HUSTR_6", "level 6: the crusher"),
+    ("HUSTR_7", "level 7: dead simple"),
+    ("HUSTR_8", "level 8: tricks and traps"),
+    ("HUSTR_9", "level 9: the pit"),
+    ("HUSTR_10", "level 10: refueling base"),
+    ("HUSTR_11", "level 11: 'o' of destruction!"),
+    ("HUSTR_12", "level 12: the factory"),
+    ("HUSTR_13", "level 13: downtown"),
+    ("HUSTR_14", "level 14: the inmost dens"),
+    ("HUSTR_15", "level 15: industrial zone"),
+    ("HUSTR_16", "level 16: suburbs"),
+    ("HUSTR_17", "level 17: tenements"),
+    ("HUSTR_18", "level 18: the courtyard"),
+    ("HUSTR_19", "level 19: the citadel"),
+    ("HUSTR_20", "level 20: gotcha!"),
+    ("HUSTR_21", "level 21: nirvana"),
+    ("HUSTR_22", "level 22: the catacombs"),
+    ("HUSTR_23", "level 23: barrels o' fun"),
+    ("HUSTR_24", "level 24: the chasm"),
+    ("HUSTR_25", "level 25: bloodfalls"),
+    ("HUSTR_26", "level 26: the abandoned mines"),
+    ("HUSTR_27", "level 27: monster condo"),
+    ("HUSTR_28", "level 28: the spirit world"),
+    ("HUSTR_29", "level 29: the living end"),
+    ("HUSTR_30", "level 30: icon of sin"),
+    ("HUSTR_31", "level 31: wolfenstein"),
+    ("HUSTR_32", "level 32: grosse"),
+
+    ("E1TEXT", "Once you beat the big badasses and\nclean out the moon base you're supposed\nto win, aren't you? Aren't you? Where's\nyour fat reward and ticket home? What\nthe hell is this? It's not supposed to\nend this way!\n\nIt stinks like rotten meat, but looks\nlike the lost Deimos base.  Looks like\nyou're stuck on The Shores of Hell.\nThe only way out is through.\n\nTo continue the DOOM experience, play\nThe Shores of Hell and its amazing\nsequel, Inferno!\n"),
+    ("E2TEXT", "You've done it! The hideous cyber-\ndemon lord that ruled the lost Deimos\nmoon base has been slain and you\nare triumphant! But ... where are\nyou? You clamber to the edge of the\nmoon and look down to see the awful\ntruth.\n\nDeimos floats above Hell itself!\nYou've never heard of anyone escaping\nfrom Hell, but you'll make the bastards\nsorry they ever heard of you! Quickly,\nyou rappel down to  the surface of\nHell.\n\nNow, it's on to the final chapter of\nDOOM! -- Inferno."),
+    ("E3TEXT", "The loathsome spiderdemon that\nmasterminded the invasion of the moon\nbases and caused so much death has had\nits ass kicked for all time.\n\nA hidden doorway opens and you enter.\nYou've proven too tough for Hell to\ncontain, and now Hell at last plays\nfair -- for you emerge from the door\nto see the green fields of Earth!\nHome at last.\n\nYou wonder what's been happening on\nEarth while you were battling evil\nunleashed. It's good that no Hell-\nspawn could have come through that\ndoor with you ..."),
+    ("E4TEXT", "the spider mastermind must have sent forth\nits legions of hellspawn before your\nfinal confrontation with that terrible\nbeast from hell.  but you stepped forward\nand brought forth eternal damnation and\nsuffering upon the horde as a true hero\nwould in the face of something so evil.\n\nbesides, someone was gonna pay for what\nhappened to daisy, your pet rabbit.\n\nbut now, you see spread before you more\npotential pain and gibbitude as a nation\nof demons run amok among our cities.\n\nnext stop, hell on earth!"),
+    ("C1TEXT", "YOU HAVE ENTERED DEEPLY INTO THE INFESTED\nSTARPORT. BUT SOMETHING IS WRONG. THE\nMONSTERS HAVE BROUGHT THEIR OWN REALITY\nWITH THEM, AND THE STARPORT'S TECHNOLOGY\nIS BEING SUBVERTED BY THEIR PRESENCE.\n\nAHEAD, YOU SEE AN OUTPOST OF HELL, A\nFORTIFIED ZONE. IF YOU CAN GET PAST IT,\nYOU CAN PENETRATE INTO THE HAUNTED HEART\nOF THE STARBASE AND FIND THE CONTROLLING\nSWITCH WHICH HOLDS EARTH'S POPULATION\nHOSTAGE."),
+    ("C2TEXT", "YOU HAVE WON! YOUR VICTORY HAS ENABLED\nHUMANKIND TO EVACUATE EARTH AND ESCAPE\nTHE NIGHTMARE.  NOW YOU ARE THE ONLY\nHUMAN LEFT ON THE FACE OF THE PLANET.\nCANNIBAL MUTATIONS, CARNIVOROUS ALIENS,\nAND EVIL SPIRITS ARE YOUR ONLY NEIGHBORS.\nYOU SIT BACK AND WAIT FOR DEATH, CONTENT\nTHAT YOU HAVE SAVED YOUR SPECIES.\n\nBUT THEN, EARTH CONTROL BEAMS DOWN A\nMESSAGE FROM SPACE: \"SENSORS HAVE LOCATED\nTHE SOURCE OF THE ALIEN INVASION. IF YOU\nGO THERE, YOU MAY BE ABLE TO BLOCK THEIR\nENTRY.  THE ALIEN BASE IS IN THE HEART OF\nYOUR OWN HOME CITY, NOT FAR FROM THE\nSTARPORT.\" SLOWLY AND PAINFULLY YOU GET\nUP AND RETURN TO THE FRAY."),
+    ("C3TEXT", "YOU ARE AT THE CORRUPT HEART OF THE CITY,\nSURROUNDED BY THE CORPSES OF YOUR ENEMIES.\nYOU SEE NO WAY TO DESTROY THE CREATURES'\nENTRYWAY ON THIS SIDE, SO YOU CLENCH YOUR\nTEETH AND PLUNGE THROUGH IT.\n\nTHERE MUST BE A WAY TO CLOSE IT ON THE\nOTHER SIDE. WHAT DO YOU CARE IF YOU'VE\nGOT TO GO THROUGH HELL TO GET TO IT?"),
+    ("C4TEXT", "THE HORRENDOUS VISAGE OF THE BIGGEST\nDEMON YOU'VE EVER SEEN CRUMBLES BEFORE\nYOU, AFTER YOU PUMP YOUR ROCKETS INTO\nHIS EXPOSED BRAIN. THE MONSTER SHRIVELS\nUP AND DIES, ITS THRASHING LIMBS\nDEVASTATING UNTOLD MILES OF HELL'S\nSURFACE.\n\nYOU'VE DONE IT. THE INVASION IS OVER.\nEARTH IS SAVED. HELL IS A WRECK. YOU\nWONDER WHERE BAD FOLKS WILL GO WHEN THEY\nDIE, NOW. WIPING THE SWEAT FROM YOUR\nFOREHEAD YOU BEGIN THE LONG TREK BACK\nHOME. REBUILDING EARTH OUGHT TO BE A\nLOT MORE FUN THAN RUINING IT WAS.\n"),
+    ("C5TEXT", "CONGRATULATIONS, YOU'VE FOUND THE SECRET\nLEVEL! LOOKS LIKE IT'S BEEN BUILT BY\nHUMANS, RATHER THAN DEMONS. YOU WONDER\nWHO THE INMATES OF THIS CORNER OF HELL\nWILL BE."),
+    ("C6TEXT", "CONGRATULATIONS, YOU'VE FOUND THE\nSUPER SECRET LEVEL!  YOU'D BETTER\nBLAZE THROUGH THIS ONE!\n"),
+];
+
+pub struct Strings {
+    table: BTreeMap<&'static str, String>,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            table: ENGLISH
+                .iter()
+                .map(|&(name, text)| (name, text.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Strings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current text of an entry. Messages that aren't entries (the `IDMYPOS` readout,
+    /// chat lines) are already text and come back unchanged.
+    pub fn get<'a>(&'a self, name: &'a str) -> &'a str {
+        self.table.get(name).map_or(name, String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.table.contains_key(name)
+    }
+
+    /// Replaces entries by name, as BEX `[STRINGS]` does. Returns the names that aren't
+    /// entries, for warnings.
+    pub fn replace<'a>(
+        &mut self,
+        strings: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Vec<String> {
+        let mut unknown = Vec::new();
+        for (name, text) in strings {
+            match self.table.get_mut(name.as_str()) {
+                Some(entry) => *entry = text.clone(),
+                None => unknown.push(name.clone()),
+            }
+        }
+        unknown
+    }
+
+    /// DeHackEd `Text` replacements that aren't sprite or sound names: each one replaces the
+    /// entry whose English text it matches. Returns the ones that matched nothing.
+    pub fn replace_text<'a>(
+        &mut self,
+        texts: impl IntoIterator<Item = &'a (String, String)>,
+    ) -> Vec<&'a (String, String)> {
+        let mut unmatched = Vec::new();
+        for pair @ (old, new) in texts {
+            match ENGLISH.iter().find(|(_, text)| text == old) {
+                Some((name, _)) => {
+                    self.table.insert(name, new.clone());
+                }
+                None => unmatched.push(pair),
+            }
+        }
+        unmatched
+    }
+
+    /// Loads a language pack: a `[STRINGS]` section in BEX syntax. Returns the names it
+    /// sets that aren't entries.
+    pub fn load_language(&mut self, text: &str) -> Vec<String> {
+        let mut info = GameInfo::default();
+        let mut pack = Dehacked::new(&info);
+        pack.load(text, &mut info);
+        self.replace(&pack.strings)
+    }
+
+    /// Applies what the DeHackEd patches changed. Call after any language pack, so mods
+    /// keep their text.
+    pub fn apply_dehacked(&mut self, dehacked: &Dehacked) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .replace(&dehacked.strings)
+            .into_iter()
+            .map(|name| format!("unknown string '{name}'"))
+            .collect();
+        warnings.extend(
+            self.replace_text(&dehacked.text)
+                .into_iter()
+                .map(|(old, _)| format!("no string matches text '{}'", old.escape_debug())),
+        );
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_defaults_and_passthrough() {
+        let strings = Strings::new();
+        assert_eq!(strings.get("GOTARMOR"), "Picked up the armor.");
+        assert_eq!(
+            strings.get("NETEND"),
+            "you can't end a netgame!\n\npress a key."
+        );
+        assert_eq!(
+            strings.get("ang=0x0;x,y=(0x0,0x0)"),
+            "ang=0x0;x,y=(0x0,0x0)"
+        );
+        assert!(ENGLISH
+            .iter()
+            .all(|(name, _)| ENGLISH.iter().filter(|(n, _)| n == name).count() == 1));
+    }
+
+    #[test]
+    fn dehacked_replaces_by_name_and_by_text() {
+        let mut info = GameInfo::default();
+        let mut deh = Dehacked::new(&info);
+        deh.load(
+            "[STRINGS]\nGOTARMOR = Armor!\nNOSUCHSTRING = x\n",
+            &mut info,
+        );
+        deh.text
+            .push(("Picked up a clip.".to_string(), "Clip!".to_string()));
+        let mut strings = Strings::new();
+        let warnings = strings.apply_dehacked(&deh);
+        assert_eq!(strings.get("GOTARMOR"), "Armor!");
+        assert_eq!(strings.get("GOTCLIP"), "Clip!");
+        assert_eq!(warnings, vec!["unknown string 'NOSUCHSTRING'"]);
+    }
+
+    #[test]
+    fn the_french_pack_loads() {
+        let mut strings = Strings::new();
+        let pack = include_str!("../../../lang/french.bex");
+        assert_eq!(strings.load_language(pack), Vec::<String>::new());
+        assert_eq!(strings.get("GOTARMOR"), "ARMURE RECUPEREE.");
+        assert_eq!(
+            strings.get("QUITMSG"),
+            "VOUS VOULEZ VRAIMENT\nQUITTER CE SUPER JEU?"
+        );
+        // Entries the pack doesn't translate stay English.
+        assert_eq!(strings.get("HUSTR_E4M1"), "E4M1: Hell Beneath");
+    }
+}
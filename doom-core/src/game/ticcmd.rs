@@ -0,0 +1,31 @@
+//! `ticcmd_t`: one player's input for one tic, the only thing that reaches the simulation.
+//!
+//! Local input, the network and demos all produce these, and the game runs from nothing else.
+//! That is what keeps demos and netgames in sync: replaying the same commands gives the
+//! same game.
+
+pub const BT_ATTACK: u8 = 1;
+pub const BT_USE: u8 = 2;
+pub const BT_SPECIAL: u8 = 128; // The other bits are a special action, like pause or save.
+pub const BT_SPECIALMASK: u8 = 3;
+/// With `BT_SPECIAL`: pause or unpause the game.
+pub const BTS_PAUSE: u8 = 1;
+/// With `BT_SPECIAL`: a vote, with what kind in the bits above and its subject in
+/// `chatchar`. Vanilla's mask leaves this value unused.
+pub const BTS_VOTE: u8 = 3;
+pub const BT_CHANGE: u8 = 4; // With the weapon number in the three bits above it.
+pub const BT_WEAPONSHIFT: u8 = 3;
+pub const BT_WEAPONMASK: u8 = 0x38;
+/// With `BT_CHANGE`: step through the weapons instead of picking one, to the next for weapon
+/// number 0 and the previous for 1. Vanilla never sets this bit, see `weapons::to_vanilla`.
+pub const BT_CYCLE: u8 = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TicCmd {
+    pub forwardmove: i8, // *2048 for move.
+    pub sidemove: i8,    // *2048 for move.
+    pub angleturn: i16,  // <<16 for angle delta.
+    pub buttons: u8,
+    /// One character of a chat message on its way, or 0; see `chat`.
+    pub chatchar: u8,
+}
@@ -0,0 +1,607 @@
+//! `P_PathTraverse`: everything a line from one point to another crosses, nearest first, for
+//! hitscan attacks, autoaim and use lines.
+//!
+//! The trace walks the blockmap cells the line passes through. It collects the lines it
+//! crosses and the things whose diagonal it crosses as intercepts, then visits them in order
+//! of distance. Vanilla kept the intercepts in a static array of `MAXINTERCEPTS` and never
+//! checked it. A long trace through a detailed area wrote past the end into the variables
+//! after it, which turned monsters into ghosts and moved player starts. Here the intercepts
+//! are a vector that grows as needed, so a trace always sees everything it crosses, as the
+//! overflowed vanilla loop did too. With `overflow_intercepts` at `emulate`, which only
+//! holds at compatibility levels that emulate overflows, every intercept past the limit also
+//! records the values it would have written over, in `Intercepts::overruns`, for the game to
+//! apply. At `crash` the trace fails with vanilla's error instead.
+//!
+//! Everything else is vanilla's arithmetic, including its choice between two side tests by
+//! trace length and the leading 0 of every blockmap list, which demos depend on.
+
+use super::limits::{
+    intercepts_overrun, Admit, InterceptsVariable, Limit, LimitError, OverflowOptions,
+    DEFAULT_SPECHIT_MAGIC,
+};
+use crate::util::fixed::{fixed_div, fixed_mul, Fixed, FRACBITS, FRACUNIT};
+use crate::wad::map::{MapData, NO_SIDEDEF};
+
+pub const MAPBLOCKSHIFT: u32 = FRACBITS + 7;
+pub const MAPBLOCKSIZE: Fixed = 128 * FRACUNIT;
+const MAPBTOFRAC: u32 = MAPBLOCKSHIFT - FRACBITS;
+/// Vanilla's `sizeof(line_t)`, for the address an overflowing line intercept wrote.
+const LINE_SIZE: i32 = 0x3e;
+
+/// `P_PathTraverse` flags.
+pub const PT_ADDLINES: u32 = 1;
+pub const PT_ADDTHINGS: u32 = 2;
+/// Stop at the first one-sided line, for traces that can't go through walls.
+pub const PT_EARLYOUT: u32 = 4;
+
+/// A line through (`x`, `y`) along (`dx`, `dy`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DivLine {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub dx: Fixed,
+    pub dy: Fixed,
+}
+
+impl DivLine {
+    /// `P_PointOnDivlineSide`: 0 for the front (right) side, 1 for the back.
+    pub fn point_side(&self, x: Fixed, y: Fixed) -> i32 {
+        if self.dx == 0 {
+            return if x <= self.x {
+                (self.dy > 0) as i32
+            } else {
+                (self.dy < 0) as i32
+            };
+        }
+        if self.dy == 0 {
+            return if y <= self.y {
+                (self.dx < 0) as i32
+            } else {
+                (self.dx > 0) as i32
+            };
+        }
+        let dx = x.wrapping_sub(self.x);
+        let dy = y.wrapping_sub(self.y);
+        // Decide by the sign bits alone where they agree.
+        if (self.dy ^ self.dx ^ dx ^ dy) < 0 {
+            return ((self.dy ^ dx) < 0) as i32;
+        }
+        let left = fixed_mul(self.dy >> 8, dx >> 8);
+        let right = fixed_mul(dy >> 8, self.dx >> 8);
+        (right >= left) as i32
+    }
+
+    /// `P_InterceptVector`: how far along `self` it crosses `other`, as a fraction of its
+    /// length. Parallel lines give 0.
+    pub fn intercept(&self, other: &DivLine) -> Fixed {
+        let den = fixed_mul(other.dy >> 8, self.dx) - fixed_mul(other.dx >> 8, self.dy);
+        if den == 0 {
+            return 0;
+        }
+        let num = fixed_mul(other.x.wrapping_sub(self.x) >> 8, other.dy)
+            + fixed_mul(self.y.wrapping_sub(other.y) >> 8, other.dx);
+        fixed_div(num, den)
+    }
+}
+
+/// A linedef as the trace sees it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceLine {
+    pub line: DivLine,
+    pub two_sided: bool,
+}
+
+impl TraceLine {
+    pub fn from_map(map: &MapData) -> Vec<TraceLine> {
+        let vertex = |index: u16| {
+            let v = map.vertexes[usize::from(index)];
+            (Fixed::from(v.x) << FRACBITS, Fixed::from(v.y) << FRACBITS)
+        };
+        map.linedefs
+            .iter()
+            .map(|line| {
+                let (x1, y1) = vertex(line.v1);
+                let (x2, y2) = vertex(line.v2);
+                TraceLine {
+                    line: DivLine {
+                        x: x1,
+                        y: y1,
+                        dx: x2.wrapping_sub(x1),
+                        dy: y2.wrapping_sub(y1),
+                    },
+                    two_sided: line.back != NO_SIDEDEF,
+                }
+            })
+            .collect()
+    }
+
+    /// `P_PointOnLineSide`, vanilla's coarser test for short traces.
+    fn point_side(&self, x: Fixed, y: Fixed) -> i32 {
+        let line = &self.line;
+        if line.dx == 0 || line.dy == 0 {
+            return line.point_side(x, y);
+        }
+        let dx = x.wrapping_sub(line.x);
+        let dy = y.wrapping_sub(line.y);
+        let left = fixed_mul(line.dy >> FRACBITS, dx);
+        let right = fixed_mul(dy, line.dx >> FRACBITS);
+        (right >= left) as i32
+    }
+}
+
+/// A thing linked into a blockmap cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceThing {
+    /// The caller's index for the thing, returned in its intercepts.
+    pub id: usize,
+    pub x: Fixed,
+    pub y: Fixed,
+    pub radius: Fixed,
+    /// The address of its `mobj_t`, only used by the overflow emulation.
+    pub address: i32,
+}
+
+/// A BLOCKMAP lump: the grid and the list of lines in each cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blockmap {
+    pub origin_x: Fixed,
+    pub origin_y: Fixed,
+    pub columns: i32,
+    pub rows: i32,
+    words: Vec<u16>,
+}
+
+impl Blockmap {
+    pub fn parse(lump: &[u8]) -> Option<Self> {
+        let words: Vec<u16> = lump
+            .chunks_exact(2)
+            .map(|w| u16::from_le_bytes([w[0], w[1]]))
+            .collect();
+        let (columns, rows) = (*words.get(2)?, *words.get(3)?);
+        let cells = usize::from(columns).checked_mul(usize::from(rows))?;
+        if words.len() < cells.checked_add(4)? {
+            return None;
+        }
+        let (columns, rows) = (i32::from(columns), i32::from(rows));
+        Some(Self {
+            origin_x: Fixed::from(words[0] as i16) << FRACBITS,
+            origin_y: Fixed::from(words[1] as i16) << FRACBITS,
+            columns,
+            rows,
+            words,
+        })
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Option<usize> {
+        ((0..self.columns).contains(&x) && (0..self.rows).contains(&y))
+            .then(|| (y * self.columns + x) as usize)
+    }
+
+    /// The lines listed for a cell, leading 0 included, up to the -1 that ends the list.
+    pub fn lines(&self, x: i32, y: i32) -> impl Iterator<Item = usize> + '_ {
+        let start = self
+            .cell(x, y)
+            .map_or(self.words.len(), |cell| usize::from(self.words[4 + cell]));
+        self.words[start.min(self.words.len())..]
+            .iter()
+            .take_while(|&&word| word != 0xffff)
+            .map(|&word| usize::from(word))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterceptTarget {
+    Line(usize),
+    Thing(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Intercept {
+    /// How far along the trace, `FRACUNIT` being its end.
+    pub frac: Fixed,
+    pub target: InterceptTarget,
+}
+
+/// The intercepts of the last trace, kept to reuse the allocation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Intercepts {
+    pub list: Vec<Intercept>,
+    /// What the intercepts past vanilla's limit wrote over, in `Emulate` mode.
+    pub overruns: Vec<(InterceptsVariable, i32)>,
+    /// Lines already checked by this trace, vanilla's `validcount`.
+    checked: Vec<bool>,
+}
+
+/// What a trace works on: the level's lines and blockmap, and the things in each cell in
+/// `blocklinks` order.
+pub struct TraceWorld<'a> {
+    pub blockmap: &'a Blockmap,
+    pub lines: &'a [TraceLine],
+    /// Indexed by cell, `y * columns + x`. Cells past the end are empty.
+    pub blocklinks: &'a [Vec<TraceThing>],
+}
+
+impl Intercepts {
+    /// Adds an intercept. `pointer` is the address vanilla stored with it.
+    fn add(
+        &mut self,
+        options: &OverflowOptions,
+        intercept: Intercept,
+        pointer: i32,
+    ) -> Result<(), LimitError> {
+        let index = self.list.len();
+        if options.admit(Limit::Intercepts, index)? == Admit::Overflow {
+            let is_line = matches!(intercept.target, InterceptTarget::Line(_));
+            self.overruns
+                .extend(intercepts_overrun(index, intercept.frac, is_line, pointer));
+        }
+        // The overflowed vanilla loop still read the entries past the array, so they are
+        // kept either way.
+        self.list.push(intercept);
+        Ok(())
+    }
+
+    /// `P_PathTraverse` from (`x1`, `y1`) to (`x2`, `y2`). `visit` is called for each
+    /// intercept up to the end of the trace, nearest first, with the trace, and stops it by
+    /// returning false. Returns whether the trace ran to the end.
+    pub fn path_traverse(
+        &mut self,
+        world: &TraceWorld,
+        options: &OverflowOptions,
+        (x1, y1): (Fixed, Fixed),
+        (x2, y2): (Fixed, Fixed),
+        flags: u32,
+        mut visit: impl FnMut(&Intercept, &DivLine) -> bool,
+    ) -> Result<bool, LimitError> {
+        let blockmap = world.blockmap;
+        self.list.clear();
+        self.overruns.clear();
+        self.checked.clear();
+        self.checked.resize(world.lines.len(), false);
+
+        let (mut x1, mut y1) = (x1, y1);
+        // Don't start exactly on a cell edge.
+        if x1.wrapping_sub(blockmap.origin_x) & (MAPBLOCKSIZE - 1) == 0 {
+            x1 += FRACUNIT;
+        }
+        if y1.wrapping_sub(blockmap.origin_y) & (MAPBLOCKSIZE - 1) == 0 {
+            y1 += FRACUNIT;
+        }
+        let trace = DivLine {
+            x: x1,
+            y: y1,
+            dx: x2.wrapping_sub(x1),
+            dy: y2.wrapping_sub(y1),
+        };
+        let (x1, y1) = (
+            x1.wrapping_sub(blockmap.origin_x),
+            y1.wrapping_sub(blockmap.origin_y),
+        );
+        let (x2, y2) = (
+            x2.wrapping_sub(blockmap.origin_x),
+            y2.wrapping_sub(blockmap.origin_y),
+        );
+        let (xt1, yt1) = (x1 >> MAPBLOCKSHIFT, y1 >> MAPBLOCKSHIFT);
+        let (xt2, yt2) = (x2 >> MAPBLOCKSHIFT, y2 >> MAPBLOCKSHIFT);
+
+        // Where the trace crosses into the next column or row, and how far it goes between.
+        let step = |t1: i32, t2: i32, from: Fixed, along: Fixed, across: Fixed| {
+            let fraction = (from >> MAPBTOFRAC) & (FRACUNIT - 1);
+            let slope = || fixed_div(along, across.wrapping_abs());
+            match t2.cmp(&t1) {
+                core::cmp::Ordering::Greater => (1, FRACUNIT - fraction, slope()),
+                core::cmp::Ordering::Less => (-1, fraction, slope()),
+                core::cmp::Ordering::Equal => (0, FRACUNIT, 256 * FRACUNIT),
+            }
+        };
+        let (dx, dy) = (x2.wrapping_sub(x1), y2.wrapping_sub(y1));
+        let (map_x_step, partial, y_step) = step(xt1, xt2, x1, dy, dx);
+        let mut y_intercept = (y1 >> MAPBTOFRAC).wrapping_add(fixed_mul(partial, y_step));
+        let (map_y_step, partial, x_step) = step(yt1, yt2, y1, dx, dy);
+        let mut x_intercept = (x1 >> MAPBTOFRAC).wrapping_add(fixed_mul(partial, x_step));
+
+        let (mut map_x, mut map_y) = (xt1, yt1);
+        for _ in 0..64 {
+            if flags & PT_ADDLINES != 0
+                && !self.add_lines(world, options, &trace, map_x, map_y, flags)?
+            {
+                return Ok(false);
+            }
+            if flags & PT_ADDTHINGS != 0 {
+                self.add_things(world, options, &trace, map_x, map_y)?;
+            }
+            if map_x == xt2 && map_y == yt2 {
+                break;
+            }
+            if y_intercept >> FRACBITS == map_y {
+                y_intercept = y_intercept.wrapping_add(y_step);
+                map_x += map_x_step;
+            } else if x_intercept >> FRACBITS == map_x {
+                x_intercept = x_intercept.wrapping_add(x_step);
+                map_y += map_y_step;
+            }
+        }
+
+        // Vanilla picked the nearest remaining intercept each time, the earliest added on a
+        // tie, which a stable sort reproduces.
+        self.list.sort_by_key(|intercept| intercept.frac);
+        for intercept in &self.list {
+            if intercept.frac > FRACUNIT {
+                break;
+            }
+            if !visit(intercept, &trace) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// `PIT_AddLineIntercepts` over a cell. False when the trace hit a wall with
+    /// `PT_EARLYOUT`.
+    fn add_lines(
+        &mut self,
+        world: &TraceWorld,
+        options: &OverflowOptions,
+        trace: &DivLine,
+        map_x: i32,
+        map_y: i32,
+        flags: u32,
+    ) -> Result<bool, LimitError> {
+        let long = |d: Fixed| !(-16 * FRACUNIT..=16 * FRACUNIT).contains(&d);
+        for index in world.blockmap.lines(map_x, map_y) {
+            let Some(line) = world.lines.get(index) else {
+                continue;
+            };
+            if std::mem::replace(&mut self.checked[index], true) {
+                continue;
+            }
+            let (s1, s2) = if long(trace.dx) || long(trace.dy) {
+                let l = &line.line;
+                (
+                    trace.point_side(l.x, l.y),
+                    trace.point_side(l.x.wrapping_add(l.dx), l.y.wrapping_add(l.dy)),
+                )
+            } else {
+                (
+                    line.point_side(trace.x, trace.y),
+                    line.point_side(
+                        trace.x.wrapping_add(trace.dx),
+                        trace.y.wrapping_add(trace.dy),
+                    ),
+                )
+            };
+            if s1 == s2 {
+                continue;
+            }
+            let frac = trace.intercept(&line.line);
+            if frac < 0 {
+                continue;
+            }
+            if flags & PT_EARLYOUT != 0 && frac < FRACUNIT && !line.two_sided {
+                return Ok(false);
+            }
+            let target = InterceptTarget::Line(index);
+            let pointer =
+                DEFAULT_SPECHIT_MAGIC.wrapping_add((index as i32).wrapping_mul(LINE_SIZE));
+            self.add(options, Intercept { frac, target }, pointer)?;
+        }
+        Ok(true)
+    }
+
+    /// `PIT_AddThingIntercepts` over a cell: a thing is hit if the trace crosses the diagonal
+    /// of its box that faces it.
+    fn add_things(
+        &mut self,
+        world: &TraceWorld,
+        options: &OverflowOptions,
+        trace: &DivLine,
+        map_x: i32,
+        map_y: i32,
+    ) -> Result<(), LimitError> {
+        let Some(cell) = world.blockmap.cell(map_x, map_y) else {
+            return Ok(());
+        };
+        let things = world.blocklinks.get(cell).map_or(&[][..], Vec::as_slice);
+        let positive = (trace.dx ^ trace.dy) > 0;
+        for thing in things {
+            let (x1, x2) = (thing.x - thing.radius, thing.x + thing.radius);
+            let (y1, y2) = if positive {
+                (thing.y + thing.radius, thing.y - thing.radius)
+            } else {
+                (thing.y - thing.radius, thing.y + thing.radius)
+            };
+            if trace.point_side(x1, y1) == trace.point_side(x2, y2) {
+                continue;
+            }
+            let diagonal = DivLine {
+                x: x1,
+                y: y1,
+                dx: x2 - x1,
+                dy: y2 - y1,
+            };
+            let frac = trace.intercept(&diagonal);
+            if frac < 0 {
+                continue;
+            }
+            let target = InterceptTarget::Thing(thing.id);
+            self.add(options, Intercept { frac, target }, thing.address)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::limits::OverflowMode;
+    use crate::util::fixed::to_fixed;
+
+    /// One cell at the origin listing lines 0 and 1, after the leading 0.
+    fn blockmap() -> Blockmap {
+        let words: [u16; 9] = [0, 0, 1, 1, 5, 0, 0, 1, 0xffff];
+        Blockmap::parse(
+            &words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    /// A wall at x = 64 and a two-sided line at x = 32, both running up the cell.
+    fn lines() -> Vec<TraceLine> {
+        let line = |x: i32, two_sided| TraceLine {
+            line: DivLine {
+                x: to_fixed(x),
+                y: 0,
+                dx: 0,
+                dy: to_fixed(128),
+            },
+            two_sided,
+        };
+        vec![line(64, false), line(32, true)]
+    }
+
+    fn thing(id: usize, x: Fixed) -> TraceThing {
+        TraceThing {
+            id,
+            x,
+            y: to_fixed(60),
+            radius: FRACUNIT,
+            address: 0x1000 + id as i32,
+        }
+    }
+
+    fn trace(
+        intercepts: &mut Intercepts,
+        things: Vec<TraceThing>,
+        options: &OverflowOptions,
+        flags: u32,
+    ) -> Result<(bool, Vec<InterceptTarget>), LimitError> {
+        let (blockmap, lines, blocklinks) = (blockmap(), lines(), vec![things]);
+        let world = TraceWorld {
+            blockmap: &blockmap,
+            lines: &lines,
+            blocklinks: &blocklinks,
+        };
+        let mut visited = Vec::new();
+        let finished = intercepts.path_traverse(
+            &world,
+            options,
+            (to_fixed(8), to_fixed(60)),
+            (to_fixed(120), to_fixed(60)),
+            flags,
+            |intercept, _| {
+                visited.push(intercept.target);
+                true
+            },
+        )?;
+        Ok((finished, visited))
+    }
+
+    #[test]
+    fn grids_bigger_than_the_lump_are_rejected() {
+        let words: [u16; 5] = [0, 0, 0xffff, 0xffff, 0];
+        let lump: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        assert_eq!(Blockmap::parse(&lump), None);
+        assert_eq!(blockmap().lines(0, 0).collect::<Vec<_>>(), [0, 0, 1]);
+    }
+
+    #[test]
+    fn divlines_follow_vanilla() {
+        let trace = DivLine {
+            x: 0,
+            y: 0,
+            dx: to_fixed(100),
+            dy: 0,
+        };
+        assert_eq!(trace.point_side(to_fixed(50), to_fixed(-1)), 0);
+        assert_eq!(trace.point_side(to_fixed(50), to_fixed(1)), 1);
+        let wall = DivLine {
+            x: to_fixed(25),
+            y: to_fixed(-10),
+            dx: 0,
+            dy: to_fixed(20),
+        };
+        assert_eq!(trace.intercept(&wall), FRACUNIT / 4);
+        assert_eq!(trace.intercept(&trace), 0);
+        assert_eq!(blockmap().lines(0, 0).collect::<Vec<_>>(), [0, 0, 1]);
+        assert_eq!(blockmap().lines(1, 0).count(), 0);
+    }
+
+    #[test]
+    fn intercepts_are_visited_nearest_first() {
+        let options = OverflowOptions::default();
+        let mut intercepts = Intercepts::default();
+        let things = vec![thing(7, to_fixed(100)), thing(8, to_fixed(20))];
+        let (finished, visited) = trace(
+            &mut intercepts,
+            things.clone(),
+            &options,
+            PT_ADDLINES | PT_ADDTHINGS,
+        )
+        .unwrap();
+        assert!(finished);
+        assert_eq!(
+            visited,
+            [
+                InterceptTarget::Thing(8),
+                InterceptTarget::Line(1),
+                InterceptTarget::Line(0),
+                InterceptTarget::Thing(7),
+            ]
+        );
+        assert_eq!(
+            intercepts.list[1].frac,
+            fixed_div(to_fixed(24), to_fixed(112))
+        );
+
+        // The wall ends an early out trace before anything is visited.
+        let flags = PT_ADDLINES | PT_ADDTHINGS | PT_EARLYOUT;
+        assert_eq!(
+            trace(&mut intercepts, things, &options, flags),
+            Ok((false, vec![]))
+        );
+    }
+
+    #[test]
+    fn the_vanilla_limit_is_emulated_or_removed() {
+        let things: Vec<_> = (0..130)
+            .map(|i| thing(i, to_fixed(12) + i as i32 * FRACUNIT / 2))
+            .collect();
+        let mut intercepts = Intercepts::default();
+        let emulate = OverflowOptions::default();
+        let (_, visited) = trace(&mut intercepts, things.clone(), &emulate, PT_ADDTHINGS).unwrap();
+        assert_eq!(visited.len(), 130);
+        // The 130th intercept lands on `lowfloor`, `openbottom` and `opentop`.
+        let last = intercepts
+            .list
+            .iter()
+            .find(|i| i.target == InterceptTarget::Thing(129));
+        assert_eq!(
+            intercepts.overruns,
+            [
+                (InterceptsVariable::LowFloor, last.unwrap().frac),
+                (InterceptsVariable::OpenBottom, 0),
+                (InterceptsVariable::OpenTop, 0x1000 + 129),
+            ]
+        );
+
+        let remove = OverflowOptions {
+            intercepts: OverflowMode::Remove,
+            ..emulate
+        };
+        trace(&mut intercepts, things.clone(), &remove, PT_ADDTHINGS).unwrap();
+        assert_eq!(intercepts.list.len(), 130);
+        assert!(intercepts.overruns.is_empty());
+
+        let crash = OverflowOptions {
+            intercepts: OverflowMode::Crash,
+            ..emulate
+        };
+        assert_eq!(
+            trace(&mut intercepts, things, &crash, PT_ADDTHINGS),
+            Err(LimitError(Limit::Intercepts))
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! The `UMAPINFO` lump: per map metadata PWADs can set without DeHackEd.
+//!
+//! ```text
+//! MAP E1M1
+//! {
+//!     levelname = "The Hangar"
+//!     next = "E1M2"
+//!     partime = 30
+//!     intertext = "First line", "Second line"
+//!     bossaction = DoomImp, 23, 666
+//! }
+//! ```
+//!
+//! Keys are case insensitive and unknown keys are ignored, so lumps written for ports with
+//! more keys still load. Several keys accept `clear` to remove the default (no text screen, no
+//! boss actions, no episode menu entries). `next_map` is what the game consults for level
+//! progression; it falls back to the vanilla rules for anything UMAPINFO doesn't say.
+
+use std::fmt;
+
+/// A value that can also be explicitly cleared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Setting<T> {
+    Clear,
+    Set(T),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Episode {
+    pub patch: String,
+    pub name: String,
+    pub key: Option<char>,
+}
+
+/// When every monster of `thing` is dead, `special` is run on sectors tagged `tag`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BossAction {
+    pub thing: usize, // Index into the thing table.
+    pub special: u16,
+    pub tag: u16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MapEntry {
+    pub map: String,
+    pub level_name: Option<String>,
+    pub label: Option<Setting<String>>,
+    pub author: Option<String>,
+    pub next: Option<String>,
+    pub next_secret: Option<String>,
+    pub sky_texture: Option<String>,
+    pub music: Option<String>,
+    pub exit_pic: Option<String>,
+    pub enter_pic: Option<String>,
+    pub level_pic: Option<String>,
+    pub par_time: Option<i32>, // Seconds
+    pub end_game: Option<bool>,
+    pub end_pic: Option<String>,
+    pub end_bunny: bool,
+    pub end_cast: bool,
+    pub no_intermission: bool,
+    pub inter_text: Option<Setting<Vec<String>>>,
+    pub inter_text_secret: Option<Setting<Vec<String>>>,
+    pub inter_backdrop: Option<String>,
+    pub inter_music: Option<String>,
+    pub episode: Option<Setting<Episode>>,
+    pub boss_actions: Option<Setting<Vec<BossAction>>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UMAPINFO line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Thing names as `bossaction` spells them (ZDoom's class names), in thing table order.
+const THING_NAMES: &[&str] = &[
+    "DoomPlayer",
+    "ZombieMan",
+    "ShotgunGuy",
+    "Archvile",
+    "ArchvileFire",
+    "Revenant",
+    "RevenantTracer",
+    "RevenantTracerSmoke",
+    "Fatso",
+    "FatShot",
+    "ChaingunGuy",
+    "DoomImp",
+    "Demon",
+    "Spectre",
+    "Cacodemon",
+    "BaronOfHell",
+    "BaronBall",
+    "HellKnight",
+    "LostSoul",
+    "SpiderMastermind",
+    "Arachnotron",
+    "Cyberdemon",
+    "PainElemental",
+    "WolfensteinSS",
+    "CommanderKeen",
+    "BossBrain",
+    "BossEye",
+    "BossTarget",
+    "SpawnShot",
+    "SpawnFire",
+    "ExplosiveBarrel",
+    "DoomImpBall",
+    "CacodemonBall",
+    "Rocket",
+    "PlasmaBall",
+    "BFGBall",
+    "ArachnotronPlasma",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(i64),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            text.push(c);
+                        }
+                        None => {
+                            return Err(ParseError {
+                                line,
+                                message: "unterminated string".into(),
+                            })
+                        }
+                    }
+                }
+                tokens.push((Token::Str(text), line));
+            }
+            '{' | '}' | '=' | ',' => tokens.push((Token::Punct(c), line)),
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    number.push(c);
+                }
+                let value = number.parse().map_err(|_| ParseError {
+                    line,
+                    message: format!("bad number '{number}'"),
+                })?;
+                tokens.push((Token::Number(value), line));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                tokens.push((Token::Word(word), line));
+            }
+            c => {
+                return Err(ParseError {
+                    line,
+                    message: format!("unexpected '{c}'"),
+                })
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UMapInfo {
+    pub maps: Vec<MapEntry>,
+}
+
+impl UMapInfo {
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let mut info = UMapInfo::default();
+        while parser.pos < parser.tokens.len() {
+            let entry = parser.map()?;
+            // A later definition of the same map replaces the earlier one.
+            info.maps.retain(|m| m.map != entry.map);
+            info.maps.push(entry);
+        }
+        Ok(info)
+    }
+
+    pub fn get(&self, map: &str) -> Option<&MapEntry> {
+        self.maps.iter().find(|m| m.map.eq_ignore_ascii_case(map))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line(),
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == c => Ok(()),
+            _ => {
+                self.pos -= 1;
+                self.error(format!("expected '{c}'"))
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<MapEntry, ParseError> {
+        match self.next() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("map") => {}
+            _ => return self.error("expected 'MAP'"),
+        }
+        let map = match self.next() {
+            Some(Token::Word(name)) => name.to_ascii_uppercase(),
+            _ => return self.error("expected a map name"),
+        };
+        self.expect('{')?;
+        let mut entry = MapEntry {
+            map,
+            ..MapEntry::default()
+        };
+        loop {
+            match self.next() {
+                Some(Token::Punct('}')) => return Ok(entry),
+                Some(Token::Word(key)) => {
+                    self.expect('=')?;
+                    let line = self.line();
+                    let values = self.values()?;
+                    apply(&mut entry, &key.to_ascii_lowercase(), values)
+                        .map_err(|message| ParseError { line, message })?;
+                }
+                _ => return self.error("expected a key or '}'"),
+            }
+        }
+    }
+
+    /// A comma separated list of values.
+    fn values(&mut self) -> Result<Vec<Token>, ParseError> {
+        let mut values = Vec::new();
+        loop {
+            match self.next() {
+                Some(token @ (Token::Word(_) | Token::Str(_) | Token::Number(_))) => {
+                    values.push(token)
+                }
+                _ => return self.error("expected a value"),
+            }
+            if self.peek() != Some(&Token::Punct(',')) {
+                return Ok(values);
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+fn is_clear(values: &[Token]) -> bool {
+    matches!(values, [Token::Word(w)] if w.eq_ignore_ascii_case("clear"))
+}
+
+fn apply(entry: &mut MapEntry, key: &str, values: Vec<Token>) -> Result<(), String> {
+    let string = |values: &[Token]| match values {
+        [Token::Str(s)] => Ok(s.clone()),
+        _ => Err(format!("'{key}' takes a string")),
+    };
+    let lump = |values: &[Token]| string(values).map(|s| s.to_ascii_uppercase());
+    let boolean = |values: &[Token]| match values {
+        [Token::Word(w)] if w.eq_ignore_ascii_case("true") => Ok(true),
+        [Token::Word(w)] if w.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(format!("'{key}' takes true or false")),
+    };
+    let text = |values: &[Token]| -> Result<Setting<Vec<String>>, String> {
+        if is_clear(values) {
+            return Ok(Setting::Clear);
+        }
+        values
+            .iter()
+            .map(|v| match v {
+                Token::Str(s) => Ok(s.clone()),
+                _ => Err(format!("'{key}' takes strings")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Setting::Set)
+    };
+
+    match key {
+        "levelname" => entry.level_name = Some(string(&values)?),
+        "label" => {
+            entry.label = Some(if is_clear(&values) {
+                Setting::Clear
+            } else {
+                Setting::Set(string(&values)?)
+            })
+        }
+        "author" => entry.author = Some(string(&values)?),
+        "next" => entry.next = Some(lump(&values)?),
+        "nextsecret" => entry.next_secret = Some(lump(&values)?),
+        "skytexture" => entry.sky_texture = Some(lump(&values)?),
+        "music" => entry.music = Some(lump(&values)?),
+        "exitpic" => entry.exit_pic = Some(lump(&values)?),
+        "enterpic" => entry.enter_pic = Some(lump(&values)?),
+        "levelpic" => entry.level_pic = Some(lump(&values)?),
+        "partime" => match values[..] {
+            [Token::Number(n)] => entry.par_time = Some(n as i32),
+            _ => return Err("'partime' takes a number".into()),
+        },
+        "endgame" => entry.end_game = Some(boolean(&values)?),
+        "endpic" => entry.end_pic = Some(lump(&values)?),
+        "endbunny" => entry.end_bunny = boolean(&values)?,
+        "endcast" => entry.end_cast = boolean(&values)?,
+        "nointermission" => entry.no_intermission = boolean(&values)?,
+        "intertext" => entry.inter_text = Some(text(&values)?),
+        "intertextsecret" => entry.inter_text_secret = Some(text(&values)?),
+        "interbackdrop" => entry.inter_backdrop = Some(lump(&values)?),
+        "intermusic" => entry.inter_music = Some(lump(&values)?),
+        "episode" => {
+            entry.episode = Some(match &values[..] {
+                _ if is_clear(&values) => Setting::Clear,
+                [Token::Str(patch), Token::Str(name), rest @ ..] => Setting::Set(Episode {
+                    patch: patch.to_ascii_uppercase(),
+                    name: name.clone(),
+                    key: match rest {
+                        [Token::Str(key)] => key.chars().next().map(|c| c.to_ascii_lowercase()),
+                        _ => None,
+                    },
+                }),
+                _ => return Err("'episode' takes a patch, a name and a key".into()),
+            })
+        }
+        "bossaction" => {
+            if is_clear(&values) {
+                entry.boss_actions = Some(Setting::Clear);
+                return Ok(());
+            }
+            let [Token::Word(thing), Token::Number(special), Token::Number(tag)] = &values[..]
+            else {
+                return Err("'bossaction' takes a thing, a special and a tag".into());
+            };
+            let thing = THING_NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(thing))
+                .ok_or_else(|| format!("unknown thing '{thing}'"))?;
+            let action = BossAction {
+                thing,
+                special: *special as u16,
+                tag: *tag as u16,
+            };
+            match &mut entry.boss_actions {
+                Some(Setting::Set(actions)) => actions.push(action),
+                actions => *actions = Some(Setting::Set(vec![action])),
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NextMap {
+    Map(String),
+    /// The episode or game is over; show the end text or finale.
+    EndGame,
+}
+
+/// Parses `ExMy` or `MAPxx`.
+pub(crate) fn map_numbers(map: &str) -> Option<(Option<u8>, u8)> {
+    let map = map.to_ascii_uppercase();
+    if let Some(number) = map.strip_prefix("MAP") {
+        return Some((None, number.parse().ok()?));
+    }
+    let rest = map.strip_prefix('E')?;
+    let (episode, map) = rest.split_once('M')?;
+    Some((Some(episode.parse().ok()?), map.parse().ok()?))
+}
+
+/// The sky texture of `map`: UMAPINFO's `skytexture`, else vanilla's `SKY1` to `SKY4` by
+/// episode, or by thirds of Doom II from MAP12 and MAP21 on.
+pub fn sky_texture(info: &UMapInfo, map: &str) -> String {
+    if let Some(sky) = info.get(map).and_then(|entry| entry.sky_texture.as_ref()) {
+        return sky.clone();
+    }
+    let sky = match map_numbers(map) {
+        Some((Some(episode), _)) => episode.clamp(1, 4),
+        Some((None, map)) if map >= 21 => 3,
+        Some((None, map)) if map >= 12 => 2,
+        _ => 1,
+    };
+    format!("SKY{sky}")
+}
+
+/// Where exiting `current` leads. UMAPINFO's `next`/`nextsecret`/`endgame` win; otherwise the
+/// vanilla rules apply: secret exits go to ExM9 or MAP31/32, leaving a secret map returns to
+/// where the episode continues, and E?M8 and MAP30 end the game.
+pub fn next_map(info: &UMapInfo, current: &str, secret: bool) -> NextMap {
+    if let Some(entry) = info.get(current) {
+        let next = if secret {
+            entry.next_secret.as_ref().or(entry.next.as_ref())
+        } else {
+            entry.next.as_ref()
+        };
+        if let Some(next) = next {
+            return NextMap::Map(next.clone());
+        }
+        if entry.end_game == Some(true) {
+            return NextMap::EndGame;
+        }
+    }
+
+    let Some(numbers) = map_numbers(current) else {
+        return NextMap::EndGame;
+    };
+    match numbers {
+        (Some(episode), map) => {
+            let next = match (map, secret) {
+                (8, _) => return NextMap::EndGame,
+                (9, _) => [4, 6, 7, 3][usize::from(episode.clamp(1, 4) - 1)],
+                (_, true) => 9,
+                (map, false) => map + 1,
+            };
+            NextMap::Map(format!("E{episode}M{next}"))
+        }
+        (None, map) => {
+            let next = match (map, secret) {
+                (30, _) => return NextMap::EndGame,
+                (15, true) => 31,
+                (31, true) => 32,
+                (31 | 32, _) => 16,
+                (map, _) => map + 1,
+            };
+            NextMap::Map(format!("MAP{next:02}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LUMP: &str = r#"
+        // A comment
+        map MAP01
+        {
+            LevelName = "Entryway"
+            label = clear
+            next = "map03"
+            partime = 45
+            intertext = "You made it.", "Barely."
+            bossaction = Cacodemon, 23, 666
+            bossaction = DoomImp, 23, 667
+            somefutureport = 1, "x"
+        }
+
+        MAP MAP03 { endgame = true endcast = true  /* block comment */ }
+        MAP E1M1 { episode = "M_EPI1", "Knee-Deep in the Dead", "k" intertext = clear }
+    "#;
+
+    #[test]
+    fn parses_entries() {
+        let info = UMapInfo::parse(LUMP).unwrap();
+        let map01 = info.get("map01").unwrap();
+        assert_eq!(map01.level_name.as_deref(), Some("Entryway"));
+        assert_eq!(map01.label, Some(Setting::Clear));
+        assert_eq!(map01.next.as_deref(), Some("MAP03"));
+        assert_eq!(map01.par_time, Some(45));
+        assert_eq!(
+            map01.inter_text,
+            Some(Setting::Set(vec!["You made it.".into(), "Barely.".into()]))
+        );
+        assert_eq!(
+            map01.boss_actions,
+            Some(Setting::Set(vec![
+                BossAction {
+                    thing: 14,
+                    special: 23,
+                    tag: 666
+                },
+                BossAction {
+                    thing: 11,
+                    special: 23,
+                    tag: 667
+                },
+            ]))
+        );
+        assert!(info.get("MAP03").unwrap().end_cast);
+        let e1m1 = info.get("E1M1").unwrap();
+        assert_eq!(e1m1.inter_text, Some(Setting::Clear));
+        assert!(matches!(
+            &e1m1.episode,
+            Some(Setting::Set(Episode { key: Some('k'), .. }))
+        ));
+    }
+
+    #[test]
+    fn errors_carry_the_line() {
+        let error = UMapInfo::parse("map MAP01\n{\n  partime = \"soon\"\n}").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!(UMapInfo::parse("map MAP01 { bossaction = Nobody, 1, 2 }").is_err());
+        assert!(UMapInfo::parse("map MAP01 { levelname = \"x }").is_err());
+    }
+
+    #[test]
+    fn skies_follow_the_episode_unless_umapinfo_names_one() {
+        let info = UMapInfo::parse("map MAP05 { skytexture = \"SKYRED\" }").unwrap();
+        let skies = [
+            "E1M1", "E3M4", "E4M9", "MAP01", "MAP11", "MAP12", "MAP21", "MAP05",
+        ]
+        .map(|map| sky_texture(&info, map));
+        assert_eq!(
+            skies,
+            ["SKY1", "SKY3", "SKY4", "SKY1", "SKY1", "SKY2", "SKY3", "SKYRED"]
+        );
+    }
+
+    #[test]
+    fn progression_uses_umapinfo_then_vanilla_rules() {
+        let info = UMapInfo::parse(LUMP).unwrap();
+        assert_eq!(
+            next_map(&info, "MAP01", false),
+            NextMap::Map("MAP03".into())
+        );
+        // No nextsecret: the secret exit goes to the normal next map.
+        assert_eq!(next_map(&info, "MAP01", true), NextMap::Map("MAP03".into()));
+        assert_eq!(next_map(&info, "MAP03", false), NextMap::EndGame);
+
+        let none = UMapInfo::default();
+        assert_eq!(next_map(&none, "E1M3", true), NextMap::Map("E1M9".into()));
+        assert_eq!(next_map(&none, "E1M9", false), NextMap::Map("E1M4".into()));
+        assert_eq!(next_map(&none, "E3M8", false), NextMap::EndGame);
+        assert_eq!(next_map(&none, "MAP15", true), NextMap::Map("MAP31".into()));
+        assert_eq!(
+            next_map(&none, "MAP31", false),
+            NextMap::Map("MAP16".into())
+        );
+        assert_eq!(
+            next_map(&none, "MAP29", false),
+            NextMap::Map("MAP30".into())
+        );
+        assert_eq!(next_map(&none, "MAP30", false), NextMap::EndGame);
+    }
+}
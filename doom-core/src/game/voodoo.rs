@@ -0,0 +1,236 @@
+//! Player starts, and the voodoo dolls a map gets from having more than one per player.
+//!
+//! `P_SpawnMapThing` spawns a player mobj for every start of a player in the game, and
+//! `P_SpawnPlayer` points the player at each new one in turn, so the last start wins. The
+//! earlier mobjs still point back at the player. These are voodoo dolls: the player doesn't
+//! steer them, but whatever happens to them happens to the player. Damage comes off the
+//! player's health and armor, items they touch go to the player, and when a doll's own health
+//! runs out the player dies. Maps move them on conveyors and crushers to script events.
+//!
+//! Links are indexes both ways, like vanilla's pointers: a body names its player and a player
+//! names its body. A doll is simply a body whose player names another one. Reborn cuts the
+//! link from the corpse only, so dolls keep working after the player respawns.
+
+use super::cheat::Power;
+use super::demo::MAXPLAYERS;
+use super::player::Inventory;
+use crate::wad::map::Thing;
+
+/// Editor numbers 1 to 4 are the player starts.
+pub fn player_start(thing: &Thing) -> Option<usize> {
+    matches!(thing.kind, 1..=4).then(|| usize::from(thing.kind) - 1)
+}
+
+/// A player mobj: the player's own, a voodoo doll, or a corpse left by reborn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Body {
+    /// The start it was spawned at, an index into the map's things.
+    pub thing: usize,
+    pub player: Option<usize>,
+    /// The mobj's own health, which only starts out as the player's.
+    pub health: i32,
+}
+
+/// What damage to a body did to its player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hurt {
+    /// Taken off the player's health after armor.
+    pub damage: i32,
+    /// The body's health ran out, which kills the player whatever their own health says.
+    pub killed: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerBodies {
+    pub bodies: Vec<Body>,
+    /// The last start of each player, where reborn puts them back.
+    pub starts: [Option<usize>; MAXPLAYERS],
+    /// Each player's own body, vanilla's `player->mo`.
+    pub mo: [Option<usize>; MAXPLAYERS],
+}
+
+impl PlayerBodies {
+    /// Spawns the players at their starts, in thing order. Deathmatch spawns them at the
+    /// deathmatch starts instead, so no dolls are made.
+    pub fn spawn(
+        things: &[Thing],
+        in_game: &[bool; MAXPLAYERS],
+        deathmatch: bool,
+        inventories: &[Inventory; MAXPLAYERS],
+    ) -> Self {
+        let mut bodies = PlayerBodies::default();
+        for (index, thing) in things.iter().enumerate() {
+            let Some(player) = player_start(thing) else {
+                continue;
+            };
+            bodies.starts[player] = Some(index);
+            if !deathmatch && in_game[player] {
+                bodies.spawn_player(player, index, &inventories[player]);
+            }
+        }
+        bodies
+    }
+
+    /// `P_SpawnPlayer`: a new body the player now steers.
+    pub fn spawn_player(&mut self, player: usize, thing: usize, inventory: &Inventory) -> usize {
+        self.bodies.push(Body {
+            thing,
+            player: Some(player),
+            health: inventory.health,
+        });
+        self.mo[player] = Some(self.bodies.len() - 1);
+        self.bodies.len() - 1
+    }
+
+    /// `G_DoReborn`: the old body stays behind as a corpse and the player respawns at the
+    /// last start. Dolls are left linked.
+    pub fn reborn(&mut self, player: usize, inventory: &Inventory) -> Option<usize> {
+        self.leave_corpse(player);
+        let start = self.starts[player]?;
+        Some(self.spawn_player(player, start, inventory))
+    }
+
+    /// Cuts the player's body loose, leaving a corpse that nothing happens to the player through.
+    pub fn leave_corpse(&mut self, player: usize) {
+        if let Some(old) = self.mo[player] {
+            self.bodies[old].player = None;
+        }
+    }
+
+    /// Takes a body out for good, moving the players' links to the bodies after it down.
+    pub fn remove(&mut self, body: usize) {
+        self.bodies.remove(body);
+        for mo in &mut self.mo {
+            *mo = match *mo {
+                Some(own) if own == body => None,
+                Some(own) if own > body => Some(own - 1),
+                other => other,
+            };
+        }
+    }
+
+    pub fn is_doll(&self, body: usize) -> bool {
+        matches!(self.bodies[body].player, Some(player) if self.mo[player] != Some(body))
+    }
+
+    pub fn dolls(&self, player: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.bodies.len())
+            .filter(move |&body| self.bodies[body].player == Some(player) && self.is_doll(body))
+    }
+
+    /// Who gets an item `body` touches, as in `P_TouchSpecialThing`: its player, unless it
+    /// is a corpse or out of health.
+    pub fn toucher(&self, body: usize) -> Option<usize> {
+        let body = &self.bodies[body];
+        body.player.filter(|_| body.health > 0)
+    }
+
+    /// The player half of `P_DamageMobj`. God mode and invulnerability stop anything short
+    /// of a telefrag, armor takes its share, and the player's health stops at zero while the
+    /// body's goes as low as the damage takes it.
+    pub fn damage(
+        &mut self,
+        body: usize,
+        mut damage: i32,
+        inventory: &mut Inventory,
+        god: bool,
+    ) -> Hurt {
+        if self.bodies[body].player.is_some() {
+            if damage < 1000 && (god || inventory.powers[Power::Invulnerability as usize] != 0) {
+                return Hurt::default();
+            }
+            if inventory.armor_type != 0 {
+                let mut saved = if inventory.armor_type == 1 {
+                    damage / 3
+                } else {
+                    damage / 2
+                };
+                if inventory.armor_points <= saved {
+                    saved = inventory.armor_points;
+                    inventory.armor_type = 0;
+                }
+                inventory.armor_points -= saved;
+                damage -= saved;
+            }
+            inventory.health = (inventory.health - damage).max(0);
+        }
+        let body = &mut self.bodies[body];
+        body.health -= damage;
+        Hurt {
+            damage,
+            killed: body.health <= 0 && body.player.is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::GameInfo;
+
+    fn start(kind: u16) -> Thing {
+        Thing {
+            x: 0,
+            y: 0,
+            angle: 0,
+            kind,
+            flags: 7,
+        }
+    }
+
+    fn inventories() -> [Inventory; MAXPLAYERS] {
+        [Inventory::initial(&GameInfo::default()); MAXPLAYERS]
+    }
+
+    #[test]
+    fn the_last_start_wins_and_earlier_ones_become_dolls() {
+        let things = [start(1), start(2), start(3001), start(1), start(1)];
+        let in_game = [true, false, false, false];
+        let bodies = PlayerBodies::spawn(&things, &in_game, false, &inventories());
+        assert_eq!(bodies.bodies.len(), 3, "player 2 isn't playing");
+        assert_eq!(bodies.mo[0], Some(2));
+        assert_eq!(bodies.bodies[2].thing, 4);
+        assert_eq!(bodies.dolls(0).collect::<Vec<_>>(), [0, 1]);
+        assert_eq!(bodies.starts[1], Some(1));
+        assert!(PlayerBodies::spawn(&things, &in_game, true, &inventories())
+            .bodies
+            .is_empty());
+    }
+
+    #[test]
+    fn dolls_hurt_and_kill_the_player() {
+        let mut inventories = inventories();
+        let things = [start(1), start(1)];
+        let mut bodies = PlayerBodies::spawn(&things, &[true; 4], false, &inventories);
+        let player = &mut inventories[0];
+        player.armor_type = 1;
+        player.armor_points = 5;
+        let hurt = bodies.damage(0, 30, player, false);
+        assert_eq!(hurt.damage, 25, "armor ran out after saving 5");
+        assert_eq!((player.health, player.armor_type), (75, 0));
+        assert_eq!(bodies.damage(0, 30, player, true), Hurt::default());
+
+        // The doll's own health decides death: the player can be left at 0 and alive...
+        player.health = 20;
+        let hurt = bodies.damage(0, 40, player, false);
+        assert_eq!((player.health, hurt.killed), (0, false));
+        // ...or be killed while the player's health says otherwise.
+        player.health = 100;
+        assert!(bodies.damage(0, 60, player, false).killed);
+        assert_eq!(bodies.toucher(0), None, "a dead doll picks nothing up");
+        assert_eq!(bodies.toucher(1), Some(0));
+    }
+
+    #[test]
+    fn reborn_leaves_dolls_linked() {
+        let inventories = inventories();
+        let things = [start(1), start(1)];
+        let mut bodies = PlayerBodies::spawn(&things, &[true; 4], false, &inventories);
+        let new = bodies.reborn(0, &inventories[0]).unwrap();
+        assert_eq!((new, bodies.bodies[new].thing), (2, 1));
+        assert_eq!(bodies.bodies[1].player, None, "the corpse is cut loose");
+        assert!(bodies.is_doll(0));
+        assert_eq!(bodies.toucher(0), Some(0));
+        assert_eq!(bodies.dolls(0).collect::<Vec<_>>(), [0]);
+    }
+}
@@ -0,0 +1,384 @@
+//! Votes to change the map or kick a player, for long running netgames without an admin.
+//!
+//! A vote travels in the ticcmds like a pause: the tic after `callvote map MAP07` or
+//! `vote yes` is typed, the player's command carries `BT_SPECIAL | BTS_VOTE` with the kind of
+//! ballot in the bits above and its subject in `chatchar`, which chat passes over on that tic
+//! and demos keep with the chat. The movement stays the player's own, as on any special tic.
+//! Every node sees the same commands on the same tic and counts them with `Votes::ticker`, so
+//! they all agree on the result without a word passed outside the game.
+//!
+//! Calling a vote counts as voting yes. It passes once more than half the players in the game
+//! voted yes, and fails once half voted no or after `VOTE_TICS`. A player being kicked has no
+//! say. Calls while a vote is open are ignored, as are ballots from players not in the game.
+
+use super::demo::MAXPLAYERS;
+use super::ticcmd::{TicCmd, BTS_VOTE, BT_SPECIAL, BT_SPECIALMASK};
+use super::umapinfo::map_numbers;
+use super::TICRATE;
+use crate::engine::console::Commands;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// How long a vote stays open.
+pub const VOTE_TICS: i32 = 30 * TICRATE;
+
+const KIND_SHIFT: u8 = 2;
+const KIND_MAP: u8 = 1;
+const KIND_KICK: u8 = 2;
+const KIND_YES: u8 = 3;
+const KIND_NO: u8 = 4;
+/// A map vote for `ExMy`, with the episode and map in the high and low half of the subject.
+const KIND_EPISODE_MAP: u8 = 5;
+
+/// What a vote is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proposal {
+    /// Go to `ExMy`, or `MAPxx` without an episode.
+    Map {
+        episode: Option<u8>,
+        map: u8,
+    },
+    Kick {
+        player: usize,
+    },
+}
+
+impl Proposal {
+    /// Reads the arguments of `callvote`: `map MAP07` or `kick 2`, players counting from 1.
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        match args {
+            ["map", name] => match map_numbers(name) {
+                Some((Some(episode @ 0..=15), map @ 0..=15)) => Ok(Proposal::Map {
+                    episode: Some(episode),
+                    map,
+                }),
+                Some((None, map)) => Ok(Proposal::Map { episode: None, map }),
+                Some(_) => Err(format!("no vote past E15M15, not '{name}'")),
+                None => Err(format!("'{name}' isn't a map")),
+            },
+            ["kick", player] => match player.parse::<usize>() {
+                Ok(player @ 1..=MAXPLAYERS) => Ok(Proposal::Kick { player: player - 1 }),
+                _ => Err(format!("no player '{player}'")),
+            },
+            _ => Err("usage: callvote map <map> | callvote kick <player>".to_string()),
+        }
+    }
+
+    /// The map's lump name, for a map vote.
+    pub fn map_name(&self) -> Option<String> {
+        match *self {
+            Proposal::Map {
+                episode: Some(episode),
+                map,
+            } => Some(format!("E{episode}M{map}")),
+            Proposal::Map { episode: None, map } => Some(format!("MAP{map:02}")),
+            Proposal::Kick { .. } => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Proposal::Map { .. } => {
+                format!("change the map to {}", self.map_name().unwrap_or_default())
+            }
+            Proposal::Kick { player } => format!("kick player {}", player + 1),
+        }
+    }
+}
+
+/// One player's say, as it travels in a ticcmd.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ballot {
+    Call(Proposal),
+    Yes,
+    No,
+}
+
+/// `cmd` carrying `ballot` instead of its buttons and chat.
+pub fn with_ballot(cmd: TicCmd, ballot: Ballot) -> TicCmd {
+    let (kind, subject) = match ballot {
+        Ballot::Call(Proposal::Map {
+            episode: Some(episode),
+            map,
+        }) => (KIND_EPISODE_MAP, episode << 4 | map & 15),
+        Ballot::Call(Proposal::Map { episode: None, map }) => (KIND_MAP, map),
+        Ballot::Call(Proposal::Kick { player }) => (KIND_KICK, player as u8),
+        Ballot::Yes => (KIND_YES, 0),
+        Ballot::No => (KIND_NO, 0),
+    };
+    TicCmd {
+        buttons: BT_SPECIAL | BTS_VOTE | kind << KIND_SHIFT,
+        chatchar: subject,
+        ..cmd
+    }
+}
+
+/// Whether `cmd` is a vote tic, whose `chatchar` is no part of a chat message.
+pub fn is_ballot(cmd: &TicCmd) -> bool {
+    cmd.buttons & BT_SPECIAL != 0 && cmd.buttons & BT_SPECIALMASK == BTS_VOTE
+}
+
+/// The ballot `cmd` carries, if any.
+pub fn ballot(cmd: &TicCmd) -> Option<Ballot> {
+    if !is_ballot(cmd) {
+        return None;
+    }
+    let subject = cmd.chatchar;
+    match (cmd.buttons & !BT_SPECIAL) >> KIND_SHIFT {
+        KIND_MAP => Some(Ballot::Call(Proposal::Map {
+            episode: None,
+            map: subject,
+        })),
+        KIND_EPISODE_MAP => Some(Ballot::Call(Proposal::Map {
+            episode: Some(subject >> 4),
+            map: subject & 15,
+        })),
+        KIND_KICK => {
+            let player = usize::from(subject);
+            (player < MAXPLAYERS).then_some(Ballot::Call(Proposal::Kick { player }))
+        }
+        KIND_YES => Some(Ballot::Yes),
+        KIND_NO => Some(Ballot::No),
+        _ => None,
+    }
+}
+
+/// An open vote.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vote {
+    pub proposal: Proposal,
+    pub caller: usize,
+    /// The tic it was called on.
+    pub started: i32,
+    pub ballots: [Option<bool>; MAXPLAYERS],
+}
+
+impl Vote {
+    fn count(&self, in_game: &[bool; MAXPLAYERS]) -> (usize, usize, usize) {
+        let (mut yes, mut no, mut voters) = (0, 0, 0);
+        // Everyone in the game has a say, except a player being kicked.
+        let kicked = |player| self.proposal == Proposal::Kick { player };
+        for player in (0..MAXPLAYERS).filter(|&player| in_game[player] && !kicked(player)) {
+            voters += 1;
+            match self.ballots[player] {
+                Some(true) => yes += 1,
+                Some(false) => no += 1,
+                None => {}
+            }
+        }
+        (yes, no, voters)
+    }
+}
+
+/// How a vote ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The caller carries it out: loads the map, or drops the player.
+    Passed(Proposal),
+    Failed(Proposal),
+}
+
+impl Outcome {
+    /// The HUD message every player sees.
+    pub fn message(&self) -> String {
+        match self {
+            Outcome::Passed(proposal) => format!("vote passed: {}", proposal.describe()),
+            Outcome::Failed(proposal) => format!("vote failed: {}", proposal.describe()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Votes {
+    pub current: Option<Vote>,
+}
+
+impl Votes {
+    /// Takes tic `tic`'s commands, indexed by player, returning the outcome if the vote
+    /// ended on it.
+    pub fn ticker(
+        &mut self,
+        tic: i32,
+        cmds: &[TicCmd],
+        in_game: &[bool; MAXPLAYERS],
+    ) -> Option<Outcome> {
+        for (player, cmd) in cmds.iter().enumerate().take(MAXPLAYERS) {
+            if !in_game[player] {
+                continue;
+            }
+            match (ballot(cmd), &mut self.current) {
+                (Some(Ballot::Call(proposal)), None) => {
+                    if matches!(proposal, Proposal::Kick { player } if !in_game[player]) {
+                        continue;
+                    }
+                    let mut ballots = [None; MAXPLAYERS];
+                    ballots[player] = Some(true);
+                    self.current = Some(Vote {
+                        proposal,
+                        caller: player,
+                        started: tic,
+                        ballots,
+                    });
+                }
+                (Some(Ballot::Yes), Some(vote)) => vote.ballots[player] = Some(true),
+                (Some(Ballot::No), Some(vote)) => vote.ballots[player] = Some(false),
+                _ => {}
+            }
+        }
+        let vote = self.current?;
+        let (yes, no, voters) = vote.count(in_game);
+        let outcome = if yes * 2 > voters {
+            Outcome::Passed(vote.proposal)
+        } else if no * 2 >= voters || tic - vote.started >= VOTE_TICS {
+            Outcome::Failed(vote.proposal)
+        } else {
+            return None;
+        };
+        self.current = None;
+        Some(outcome)
+    }
+
+    /// The HUD line while a vote is open at `tic`.
+    pub fn prompt(&self, tic: i32, in_game: &[bool; MAXPLAYERS]) -> Option<String> {
+        let vote = self.current?;
+        let (yes, no, voters) = vote.count(in_game);
+        let left = (VOTE_TICS - (tic - vote.started)).max(0) / TICRATE;
+        Some(format!(
+            "player {} calls a vote to {} - yes {yes} no {no} of {voters} - {left}s left",
+            vote.caller + 1,
+            vote.proposal.describe()
+        ))
+    }
+}
+
+/// The ballot typed in the console, waiting for the next ticcmd to carry it.
+pub type PendingBallot = Rc<RefCell<Option<Ballot>>>;
+
+/// Registers `callvote map <map>`, `callvote kick <player>` and `vote yes|no`.
+pub fn register_commands(pending: PendingBallot, commands: &mut Commands) {
+    let calls = pending.clone();
+    commands.register(
+        "callvote",
+        "callvote map <map> | kick <player>: ask the other players",
+        move |context, args| {
+            let proposal = Proposal::parse(args)?;
+            context.print(format!("calling a vote to {}", proposal.describe()));
+            *calls.borrow_mut() = Some(Ballot::Call(proposal));
+            Ok(())
+        },
+    );
+    commands.register(
+        "vote",
+        "vote yes|no: answer the open vote",
+        move |_, args| {
+            let ballot = match args {
+                ["yes"] => Ballot::Yes,
+                ["no"] => Ballot::No,
+                _ => return Err("usage: vote yes|no".to_string()),
+            };
+            *pending.borrow_mut() = Some(ballot);
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::game::demo::Demo;
+
+    const MAP07: Proposal = Proposal::Map {
+        episode: None,
+        map: 7,
+    };
+
+    #[test]
+    fn ballots_ride_in_the_ticcmd() {
+        let cmd = TicCmd {
+            forwardmove: 25,
+            angleturn: 640,
+            buttons: 1,
+            ..TicCmd::default()
+        };
+        let e2m3 = Proposal::parse(&["map", "e2m3"]).unwrap();
+        let kick = Proposal::parse(&["kick", "4"]).unwrap();
+        for ballot_sent in [Ballot::Call(MAP07), Ballot::Call(e2m3), Ballot::Call(kick)]
+            .into_iter()
+            .chain([Ballot::Yes, Ballot::No])
+        {
+            let sent = with_ballot(cmd, ballot_sent);
+            assert_eq!((sent.forwardmove, sent.angleturn), (25, 640));
+            assert_eq!(ballot(&sent), Some(ballot_sent));
+            // Through a 1.9 demo, which keeps the subject with the chat.
+            let demo = Demo {
+                tics: vec![vec![sent]],
+                ..Demo::default()
+            };
+            let played = Demo::parse(&demo.to_bytes()).unwrap().tics[0][0];
+            assert_eq!(ballot(&played), Some(ballot_sent));
+        }
+        assert_eq!(e2m3.map_name().as_deref(), Some("E2M3"));
+        assert_eq!(kick.describe(), "kick player 4");
+        assert_eq!(ballot(&cmd), None);
+        assert_eq!(ballot(&crate::game::pause::with_pause(cmd)), None);
+        assert!(Proposal::parse(&["kick", "5"]).is_err());
+        assert!(Proposal::parse(&["map", "start"]).is_err());
+        assert!(Proposal::parse(&["map", "e1m16"]).is_err());
+    }
+
+    #[test]
+    fn every_node_counts_the_same_result() {
+        let idle = TicCmd::default();
+        let yes = with_ballot(idle, Ballot::Yes);
+        let no = with_ballot(idle, Ballot::No);
+        let in_game = [true, true, true, true];
+        let mut votes = Votes::default();
+
+        let call = with_ballot(idle, Ballot::Call(MAP07));
+        assert_eq!(votes.ticker(10, &[idle, call, idle, idle], &in_game), None);
+        let prompt = votes.prompt(45, &in_game).unwrap();
+        assert_eq!(
+            prompt,
+            "player 2 calls a vote to change the map to MAP07 - yes 1 no 0 of 4 - 29s left"
+        );
+        // A second call while this one is open is ignored; two yes of four isn't enough.
+        let other = with_ballot(idle, Ballot::Call(Proposal::Kick { player: 1 }));
+        assert_eq!(votes.ticker(11, &[other, idle, idle, idle], &in_game), None);
+        assert_eq!(votes.ticker(12, &[yes, idle, idle, idle], &in_game), None);
+        let passed = votes.ticker(13, &[idle, idle, yes, idle], &in_game);
+        assert_eq!(passed, Some(Outcome::Passed(MAP07)));
+        assert_eq!(votes.current, None);
+
+        // The player being kicked doesn't count: two no of three voters fail it.
+        let kick = Proposal::Kick { player: 3 };
+        let call = with_ballot(idle, Ballot::Call(kick));
+        votes.ticker(20, &[call, no, idle, no], &in_game);
+        let failed = votes.ticker(21, &[idle, idle, no, idle], &in_game);
+        assert_eq!(failed, Some(Outcome::Failed(kick)));
+        assert_eq!(failed.unwrap().message(), "vote failed: kick player 4");
+
+        // Nobody answers in time.
+        votes.ticker(100, &[call, idle, idle, idle], &in_game);
+        assert_eq!(votes.ticker(100 + VOTE_TICS - 1, &[], &in_game), None);
+        let timed_out = votes.ticker(100 + VOTE_TICS, &[], &in_game);
+        assert_eq!(timed_out, Some(Outcome::Failed(kick)));
+    }
+
+    #[test]
+    fn console_commands_queue_a_ballot() {
+        let pending = PendingBallot::default();
+        let commands = Commands::shared();
+        register_commands(pending.clone(), &mut commands.borrow_mut());
+        let mut config = Config::new();
+        let output = commands
+            .borrow_mut()
+            .execute("callvote map MAP07", &mut config);
+        assert_eq!(output, ["calling a vote to change the map to MAP07"]);
+        assert_eq!(pending.take(), Some(Ballot::Call(MAP07)));
+        commands.borrow_mut().execute("vote no", &mut config);
+        assert_eq!(pending.take(), Some(Ballot::No));
+        let output = commands.borrow_mut().execute("vote maybe", &mut config);
+        assert_eq!(output, ["vote: usage: vote yes|no"]);
+    }
+}
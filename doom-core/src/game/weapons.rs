@@ -0,0 +1,237 @@
+//! Which weapon a weapon change in a ticcmd asks for.
+//!
+//! Vanilla only has the number keys: `BT_CHANGE` with a slot number, which `P_PlayerThink`
+//! turns into the chainsaw or the super shotgun where those share a slot. The next and
+//! previous weapon actions set `BT_CYCLE` as well, and the direction goes where the slot
+//! number would. The player resolves it against what they carry when the command runs, like
+//! a slot, so every peer and a demo's playback pick the same weapon. Cycling skips weapons
+//! the player doesn't have and ones without the ammo for a shot, and stays put if nothing
+//! else qualifies.
+
+use super::info::GameInfo;
+use super::player::{Inventory, WP_FIST, WP_PISTOL};
+use super::ticcmd::{BT_CHANGE, BT_CYCLE, BT_WEAPONMASK, BT_WEAPONSHIFT};
+
+pub const WP_SHOTGUN: usize = 2;
+pub const WP_CHAINGUN: usize = 3;
+pub const WP_MISSILE: usize = 4;
+pub const WP_PLASMA: usize = 5;
+pub const WP_BFG: usize = 6;
+pub const WP_CHAINSAW: usize = 7;
+pub const WP_SUPERSHOTGUN: usize = 8;
+/// The ammo index of weapons that need none.
+pub const AM_NOAMMO: usize = 5;
+
+/// The order weapons cycle in: by slot, with each slot's pair in the order vanilla's key
+/// switches between them.
+pub const CYCLE_ORDER: [usize; 9] = [
+    WP_FIST,
+    WP_CHAINSAW,
+    WP_PISTOL,
+    WP_SHOTGUN,
+    WP_SUPERSHOTGUN,
+    WP_CHAINGUN,
+    WP_MISSILE,
+    WP_PLASMA,
+    WP_BFG,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeaponChange {
+    Slot(usize),
+    Next,
+    Previous,
+}
+
+impl WeaponChange {
+    pub fn decode(buttons: u8) -> Option<Self> {
+        if buttons & BT_CHANGE == 0 {
+            return None;
+        }
+        let number = usize::from((buttons & BT_WEAPONMASK) >> BT_WEAPONSHIFT);
+        Some(match (buttons & BT_CYCLE != 0, number) {
+            (false, slot) => WeaponChange::Slot(slot),
+            (true, 0) => WeaponChange::Next,
+            (true, _) => WeaponChange::Previous,
+        })
+    }
+
+    /// The button bits for this change.
+    pub fn encode(self) -> u8 {
+        let (cycle, number) = match self {
+            WeaponChange::Slot(slot) => (0, slot as u8 & 7),
+            WeaponChange::Next => (BT_CYCLE, 0),
+            WeaponChange::Previous => (BT_CYCLE, 1),
+        };
+        BT_CHANGE | cycle | number << BT_WEAPONSHIFT
+    }
+}
+
+/// Whether the player has `weapon` and the ammo to fire it once: two shells for the super
+/// shotgun and the patchable cell count for the BFG, as `P_CheckAmmo` counts.
+pub fn can_fire(inventory: &Inventory, info: &GameInfo, weapon: usize) -> bool {
+    if !inventory.weapons[weapon] {
+        return false;
+    }
+    let ammo = info.weapons.get(weapon).map_or(AM_NOAMMO, |w| w.ammo);
+    let Some(&have) = inventory.ammo.get(ammo) else {
+        return true;
+    };
+    let needed = match weapon {
+        WP_BFG => info.misc.bfg_cells_per_shot,
+        WP_SUPERSHOTGUN => 2,
+        _ => 1,
+    };
+    have >= needed
+}
+
+/// The next weapon from the ready one that the player can fire, going backwards for
+/// `previous`. `None` if there is no other.
+pub fn cycle(inventory: &Inventory, info: &GameInfo, previous: bool) -> Option<usize> {
+    let current = CYCLE_ORDER
+        .iter()
+        .position(|&weapon| weapon == inventory.ready_weapon)?;
+    let len = CYCLE_ORDER.len();
+    (1..len)
+        .map(|step| match previous {
+            true => CYCLE_ORDER[(current + len - step) % len],
+            false => CYCLE_ORDER[(current + step) % len],
+        })
+        .find(|&weapon| can_fire(inventory, info, weapon))
+}
+
+/// The weapon to switch to for a command's buttons: `P_PlayerThink`'s weapon change, with
+/// cycling. `None` if the command doesn't change weapon, or asks for the one that's ready or
+/// one the player doesn't have.
+pub fn pending_weapon(
+    buttons: u8,
+    inventory: &Inventory,
+    info: &GameInfo,
+    commercial: bool,
+    strength: bool,
+) -> Option<usize> {
+    let weapon = match WeaponChange::decode(buttons)? {
+        WeaponChange::Next => cycle(inventory, info, false)?,
+        WeaponChange::Previous => cycle(inventory, info, true)?,
+        WeaponChange::Slot(WP_FIST)
+            if inventory.weapons[WP_CHAINSAW]
+                && !(inventory.ready_weapon == WP_CHAINSAW && strength) =>
+        {
+            WP_CHAINSAW
+        }
+        WeaponChange::Slot(WP_SHOTGUN)
+            if commercial
+                && inventory.weapons[WP_SUPERSHOTGUN]
+                && inventory.ready_weapon != WP_SUPERSHOTGUN =>
+        {
+            WP_SUPERSHOTGUN
+        }
+        WeaponChange::Slot(slot) => slot,
+    };
+    (inventory.weapons[weapon] && weapon != inventory.ready_weapon).then_some(weapon)
+}
+
+/// The buttons with a cycle turned into the slot vanilla would need for the same weapon, for
+/// recording demos vanilla can play. The fist can't be picked over the chainsaw this way
+/// without berserk, as with vanilla's own keys.
+pub fn to_vanilla(buttons: u8, inventory: &Inventory, info: &GameInfo) -> u8 {
+    let previous = match WeaponChange::decode(buttons) {
+        Some(WeaponChange::Next) => false,
+        Some(WeaponChange::Previous) => true,
+        _ => return buttons,
+    };
+    let rest = buttons & !(BT_CHANGE | BT_CYCLE | BT_WEAPONMASK);
+    let Some(weapon) = cycle(inventory, info, previous) else {
+        return rest;
+    };
+    let slot = match weapon {
+        WP_SUPERSHOTGUN => WP_SHOTGUN,
+        weapon => weapon,
+    };
+    rest | WeaponChange::Slot(slot).encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::WeaponInfo;
+    use crate::game::ticcmd::BT_ATTACK;
+
+    /// Fists, pistol, shotgun, super shotgun and BFG, with one shell and no cells.
+    fn setup() -> (Inventory, GameInfo) {
+        let ammo = [5, 0, 1, 0, 3, 2, 2, 5, 1];
+        let info = GameInfo {
+            weapons: ammo
+                .iter()
+                .map(|&ammo| WeaponInfo {
+                    ammo,
+                    ..WeaponInfo::default()
+                })
+                .collect(),
+            ..GameInfo::default()
+        };
+        let mut inventory = Inventory::initial(&info);
+        for weapon in [WP_SHOTGUN, WP_SUPERSHOTGUN, WP_BFG] {
+            inventory.weapons[weapon] = true;
+        }
+        inventory.ammo = [50, 1, 0, 0];
+        (inventory, info)
+    }
+
+    #[test]
+    fn cycling_skips_what_cant_fire() {
+        let (mut inventory, info) = setup();
+        assert_eq!(cycle(&inventory, &info, false), Some(WP_SHOTGUN));
+        assert_eq!(cycle(&inventory, &info, true), Some(WP_FIST));
+        inventory.ready_weapon = WP_SHOTGUN;
+        assert_eq!(cycle(&inventory, &info, false), Some(WP_FIST));
+        inventory.ammo[1] = 2;
+        assert_eq!(cycle(&inventory, &info, false), Some(WP_SUPERSHOTGUN));
+        inventory.weapons = [false; 9];
+        inventory.weapons[WP_SHOTGUN] = true;
+        assert_eq!(cycle(&inventory, &info, false), None);
+    }
+
+    #[test]
+    fn changes_resolve_like_p_playerthink() {
+        let (mut inventory, info) = setup();
+        let next = WeaponChange::Next.encode() | BT_ATTACK;
+        assert_eq!(WeaponChange::decode(next), Some(WeaponChange::Next));
+        assert_eq!(
+            pending_weapon(next, &inventory, &info, true, false),
+            Some(2)
+        );
+        let prev = WeaponChange::Previous.encode();
+        assert_eq!(
+            pending_weapon(prev, &inventory, &info, true, false),
+            Some(0)
+        );
+
+        let shotgun = WeaponChange::Slot(WP_SHOTGUN).encode();
+        assert_eq!(
+            pending_weapon(shotgun, &inventory, &info, false, false),
+            Some(2)
+        );
+        inventory.ammo[1] = 2;
+        assert_eq!(
+            pending_weapon(shotgun, &inventory, &info, true, false),
+            Some(8)
+        );
+        assert_eq!(
+            pending_weapon(BT_ATTACK, &inventory, &info, true, false),
+            None
+        );
+        inventory.weapons[WP_CHAINSAW] = true;
+        let fist = WeaponChange::Slot(WP_FIST).encode();
+        assert_eq!(
+            pending_weapon(fist, &inventory, &info, true, false),
+            Some(7)
+        );
+
+        // For vanilla, the super shotgun goes through the shotgun's slot.
+        inventory.ready_weapon = WP_SHOTGUN;
+        let vanilla = to_vanilla(next | BT_ATTACK, &inventory, &info);
+        assert_eq!(vanilla, shotgun | BT_ATTACK);
+        assert_eq!(to_vanilla(fist, &inventory, &info), fist);
+    }
+}
@@ -0,0 +1,26 @@
+//! The engine without a platform: WAD loading, the game simulation and the software renderer.
+//!
+//! Nothing here opens a window, reads input devices or plays sound. `Engine` is the entry
+//! point for embedding; the `doom-rs` binary is one frontend built on it.
+//!
+//! With the default `std` feature turned off the crate is `no_std` and needs only `alloc`.
+//! What remains is what an embedded port builds on: fixed point math, `LumpSource` and
+//! `WadImage` for a WAD mapped into memory, map data, patches and the draw routines. Files,
+//! threads, the clock, config and the console all need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod audio;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod game;
+pub mod renderer;
+pub mod util;
+pub mod wad;
+
+#[cfg(feature = "std")]
+pub use engine::Engine;
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+/// Where one image lives inside the atlas, in texels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A square texture holding many WAD patches and flats, so the hardware renderer can draw a
+/// whole level with a handful of texture binds.
+///
+/// Images are packed into shelves: sorted tallest first, placed left to right, and a new shelf
+/// is started below the tallest image of the current one when a row is full. Doom textures come
+/// in a few standard heights (64, 128), which keeps the wasted space small.
+pub struct Atlas {
+    size: u32,
+    rects: HashMap<String, AtlasRect>,
+}
+
+impl Atlas {
+    /// Packs `(name, width, height)` images into a `size` x `size` atlas.
+    /// Returns `None` if they don't all fit.
+    pub fn pack(size: u32, images: &[(String, u32, u32)]) -> Option<Self> {
+        let mut order: Vec<_> = images.iter().collect();
+        order.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+
+        let mut rects = HashMap::with_capacity(images.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (name, width, height) in order {
+            if *width > size {
+                return None;
+            }
+            if x + width > size {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            if y + height > size {
+                return None;
+            }
+            rects.insert(
+                name.clone(),
+                AtlasRect {
+                    x,
+                    y,
+                    width: *width,
+                    height: *height,
+                },
+            );
+            x += width;
+            shelf_height = shelf_height.max(*height);
+        }
+        Some(Self { size, rects })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn get(&self, name: &str) -> Option<AtlasRect> {
+        self.rects.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(name: &str, width: u32, height: u32) -> (String, u32, u32) {
+        (name.to_string(), width, height)
+    }
+
+    #[test]
+    fn packs_into_shelves_without_overlap() {
+        let images = [
+            image("A", 64, 64),
+            image("B", 128, 128),
+            image("C", 64, 64),
+            image("D", 64, 64),
+        ];
+        let atlas = Atlas::pack(256, &images).unwrap();
+        assert_eq!(
+            atlas.get("B").unwrap(),
+            AtlasRect {
+                x: 0,
+                y: 0,
+                width: 128,
+                height: 128
+            }
+        );
+        assert_eq!(atlas.get("A").unwrap().x, 128);
+        assert_eq!(atlas.get("C").unwrap().x, 192);
+        // D no longer fits on the first shelf.
+        assert_eq!(atlas.get("D").unwrap().y, 128);
+    }
+
+    #[test]
+    fn fails_when_out_of_room() {
+        assert!(Atlas::pack(64, &[image("A", 64, 64), image("B", 64, 64)]).is_none());
+        assert!(Atlas::pack(64, &[image("A", 128, 8)]).is_none());
+    }
+}
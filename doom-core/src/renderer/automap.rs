@@ -0,0 +1,155 @@
+//! How the automap colors lines, shared by the in-game map and `doom-rs mapview`.
+//!
+//! The colors are vanilla's palette ranges: red for walls, brown where the floor height
+//! changes, yellow where the ceiling does, and gray for two-sided lines with no change,
+//! which only the `iddt` cheat shows.
+
+use crate::wad::map::{LineDef, MapData, ML_DONTDRAW, ML_MAPPED, ML_SECRET};
+
+pub const REDS: u8 = (256 - 5 * 16) as u8;
+pub const REDRANGE: u8 = 16;
+pub const GRAYS: u8 = 6 * 16;
+pub const BROWNS: u8 = 4 * 16;
+pub const YELLOWS: u8 = (256 - 32 + 7) as u8;
+pub const GREENS: u8 = 7 * 16;
+
+pub const THING_COLOR: u8 = GREENS;
+
+/// The line special of the walk-over teleporter, which the map marks.
+const TELEPORT: u16 = 39;
+
+/// What kind of line the automap sees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    Wall,
+    Teleporter,
+    Secret,
+    FloorChange,
+    CeilingChange,
+    NoChange,
+}
+
+impl LineKind {
+    pub fn classify(map: &MapData, line: &LineDef) -> Self {
+        let Some(back) = map.back_sector(line) else {
+            return LineKind::Wall;
+        };
+        let front = map.front_sector(line);
+        if line.special == TELEPORT {
+            LineKind::Teleporter
+        } else if line.flags & ML_SECRET != 0 {
+            LineKind::Secret
+        } else if back.floor_height != front.floor_height {
+            LineKind::FloorChange
+        } else if back.ceiling_height != front.ceiling_height {
+            LineKind::CeilingChange
+        } else {
+            LineKind::NoChange
+        }
+    }
+
+    /// The palette index to draw it in. `cheating` is `iddt`, which tells secret doors from
+    /// walls; secret lines otherwise look like walls on purpose.
+    pub fn color(self, cheating: bool) -> u8 {
+        match self {
+            LineKind::Wall => REDS,
+            LineKind::Teleporter => REDS + REDRANGE / 2,
+            LineKind::Secret if cheating => GRAYS,
+            LineKind::Secret => REDS,
+            LineKind::FloorChange => BROWNS,
+            LineKind::CeilingChange => YELLOWS,
+            LineKind::NoChange => GRAYS,
+        }
+    }
+}
+
+/// The color `line` is drawn in, or `None` if the map doesn't show it. `allmap` is the
+/// computer area map, which shows unseen lines in gray.
+pub fn line_color(map: &MapData, line: &LineDef, cheating: bool, allmap: bool) -> Option<u8> {
+    if line.flags & ML_DONTDRAW != 0 && !cheating {
+        return None;
+    }
+    if cheating || line.flags & ML_MAPPED != 0 {
+        let kind = LineKind::classify(map, line);
+        (kind != LineKind::NoChange || cheating).then(|| kind.color(cheating))
+    } else {
+        allmap.then_some(GRAYS + 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::map::{Sector, SideDef, NO_SIDEDEF};
+    use alloc::string::String;
+    use alloc::vec;
+
+    fn map() -> MapData {
+        let sector = |floor, ceiling| Sector {
+            floor_height: floor,
+            ceiling_height: ceiling,
+            floor_pic: String::new(),
+            ceiling_pic: String::new(),
+            light: 160,
+            special: 0,
+            tag: 0,
+        };
+        let side = |sector| SideDef {
+            x_offset: 0,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: String::new(),
+            sector,
+        };
+        MapData {
+            sidedefs: vec![side(0), side(1), side(2)],
+            sectors: vec![sector(0, 128), sector(16, 128), sector(0, 96)],
+            ..MapData::default()
+        }
+    }
+
+    fn line(back: u16, flags: u16, special: u16) -> LineDef {
+        LineDef {
+            v1: 0,
+            v2: 1,
+            flags,
+            special,
+            tag: 0,
+            front: 0,
+            back,
+        }
+    }
+
+    #[test]
+    fn lines_are_classified_like_vanilla() {
+        let map = map();
+        let kind = |back, flags, special| LineKind::classify(&map, &line(back, flags, special));
+        assert_eq!(kind(NO_SIDEDEF, 0, 0), LineKind::Wall);
+        assert_eq!(kind(1, 0, TELEPORT), LineKind::Teleporter);
+        assert_eq!(kind(1, ML_SECRET, 0), LineKind::Secret);
+        assert_eq!(kind(1, 0, 0), LineKind::FloorChange);
+        assert_eq!(kind(2, 0, 0), LineKind::CeilingChange);
+        assert_eq!(kind(0, 0, 0), LineKind::NoChange);
+    }
+
+    #[test]
+    fn only_seen_lines_are_drawn_without_cheats() {
+        let map = map();
+        let color = |line, cheating, allmap| line_color(&map, &line, cheating, allmap);
+        assert_eq!(color(line(1, 0, 0), false, false), None);
+        assert_eq!(color(line(1, ML_MAPPED, 0), false, false), Some(BROWNS));
+        assert_eq!(color(line(1, 0, 0), false, true), Some(GRAYS + 3));
+        assert_eq!(color(line(0, ML_MAPPED, 0), false, false), None);
+        assert_eq!(color(line(0, 0, 0), true, false), Some(GRAYS));
+        assert_eq!(
+            color(line(1, ML_MAPPED | ML_SECRET, 0), false, false),
+            Some(REDS)
+        );
+        assert_eq!(
+            color(line(1, ML_DONTDRAW | ML_MAPPED, 0), false, true),
+            None
+        );
+        assert_eq!(color(line(1, ML_DONTDRAW, 0), true, false), Some(BROWNS));
+    }
+}
@@ -0,0 +1,118 @@
+//! The server list: the netgames `lan::Browser` found, drawn in the HUD font at the menus'
+//! 320x200 layout.
+//!
+//! One row per server with its name, map, players and mode, the highlighted one in gold.
+//! While nothing has answered it says so, since a search takes a moment. Rows that don't fit
+//! are left off; a LAN with more than sixteen games running is not one this is for.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::LINE_HEIGHT;
+use super::text::{Text, TextColor};
+use crate::engine::lan::{FoundServer, ServerList};
+
+pub const TITLE: &str = "NETGAMES";
+pub const SEARCHING: &str = "SEARCHING THE NETWORK...";
+const TITLE_Y: i32 = 8;
+const ROWS_X: i32 = 16;
+const ROWS_Y: i32 = 28;
+
+/// Draws the list onto a 320 wide `canvas` the caller has cleared.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    text: &Text,
+    servers: &[FoundServer],
+    list: &ServerList,
+) {
+    text.draw_centered(format, canvas, TITLE_Y, TITLE);
+    if servers.is_empty() {
+        let gray = text.with_color(TextColor::Gray);
+        gray.draw_centered(format, canvas, ROWS_Y, SEARCHING);
+        return;
+    }
+    let bottom = canvas.height() as i32 - LINE_HEIGHT;
+    for (i, server) in servers.iter().enumerate() {
+        let y = ROWS_Y + i as i32 * LINE_HEIGHT;
+        if y > bottom {
+            break;
+        }
+        let row = match i == list.selected {
+            true => text.with_color(TextColor::Gold),
+            false => *text,
+        };
+        row.draw(format, canvas, ROWS_X, y, &server.info.summary());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::lan::ServerInfo;
+    use crate::game::reborn::GameMode;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::hud::HudFont;
+    use crate::renderer::patch::{Patch, Post};
+    use crate::renderer::text::TextColors;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+    use std::time::Instant;
+
+    /// Every glyph is a 4 pixel wide patch whose single pixel is the font's first red.
+    fn font() -> HudFont {
+        HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![0xb0],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn the_highlighted_server_is_gold() {
+        let (font, colors) = (font(), TextColors::default());
+        let text = Text::new(&font, &colors);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let server = |port| FoundServer {
+            address: SocketAddrV4::new(Ipv4Addr::LOCALHOST, port).into(),
+            info: ServerInfo {
+                name: "basement".to_string(),
+                map: "MAP01".to_string(),
+                players: 1,
+                max_players: 4,
+                mode: GameMode::Deathmatch,
+                port,
+            },
+            seen: Instant::now(),
+        };
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        let list = ServerList { selected: 1 };
+        draw(&format, &mut canvas, &text, &[server(1), server(2)], &list);
+        let row = |i: i32| ((ROWS_Y + i * LINE_HEIGHT) * 320 + ROWS_X) as usize;
+        assert_eq!(pixels[row(0)], 0xb0);
+        assert_eq!(pixels[row(1)], 0xa0, "gold");
+
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        canvas.pixels.fill(0);
+        draw(&format, &mut canvas, &text, &[], &list);
+        // 24 characters of 4 pixels, centered.
+        assert_eq!(pixels[ROWS_Y as usize * 320 + 112], 0x50, "gray");
+    }
+}
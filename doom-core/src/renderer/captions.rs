@@ -0,0 +1,84 @@
+//! Sound captions, drawn in the HUD font centered above the bottom of the view.
+//!
+//! Newest at the bottom, in gold so they don't read as the red pickup messages at the top.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::LINE_HEIGHT;
+use super::text::{Text, TextColor};
+use crate::audio::captions::Captions;
+
+/// Draws the captions with the last line's top `bottom` pixels from the top of `canvas`,
+/// normally just above the status bar.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    text: &Text,
+    captions: &Captions,
+    bottom: i32,
+) {
+    let text = text.with_color(TextColor::Gold);
+    let lines = captions.shown.len() as i32;
+    for (i, caption) in captions.shown.iter().enumerate() {
+        let y = bottom - (lines - 1 - i as i32) * LINE_HEIGHT;
+        text.draw_centered(format, canvas, y, &caption.line());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::captions::{Caption, Direction};
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::hud::HudFont;
+    use crate::renderer::patch::{Patch, Post};
+    use crate::renderer::text::TextColors;
+
+    #[test]
+    fn the_newest_caption_is_at_the_bottom() {
+        // Every glyph is a 4 pixel wide patch whose single pixel is the font's first red.
+        let font = HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![0xb0],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let caption = |text, direction| Caption {
+            text,
+            direction,
+            expires: 100,
+        };
+        let captions = Captions {
+            shown: vec![
+                caption("Door opens", Some(Direction::Left)),
+                caption("Grunt", None),
+            ],
+            ..Captions::default()
+        };
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &text, &captions, 150);
+        // "Grunt" is 20 pixels wide, centered; "< Door opens" 48.
+        assert_eq!(pixels[150 * 320 + 150], 0xa0);
+        assert_eq!(pixels[150 * 320 + 149], 0);
+        let above = (150 - LINE_HEIGHT) as usize;
+        assert_eq!(pixels[above * 320 + 136], 0xa0);
+    }
+}
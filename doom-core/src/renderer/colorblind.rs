@@ -0,0 +1,193 @@
+//! Color schemes for players who have trouble telling vanilla's colors apart.
+//!
+//! Vanilla leans on red: walls on the automap, the status bar numbers, blood, and one of the
+//! three keys. A `color_scheme` other than `vanilla` swaps those for colors from other palette
+//! ramps. `red-green` is for deuteranopia and protanopia, where red, green, brown and yellow
+//! run together and dark reds all but vanish; it moves them onto blue, yellow, white and
+//! gold. `blue-yellow` is for tritanopia, where blue meets gray and green and yellow meets
+//! white. Only what is drawn changes; sprites and map colors are translated on the way to
+//! the screen, like the player colors.
+
+use super::automap::{BROWNS, GREENS, REDRANGE, REDS, YELLOWS};
+use super::text::TextColor;
+use super::translation::Translation;
+use crate::engine::config::{Config, Configurable, Section};
+
+/// Palette index 4, the brightest white.
+const WHITE: u8 = 4;
+const BLUES: u8 = 0xc0;
+const ORANGES: u8 = 0xd0;
+const GOLDS: u8 = 0xa0;
+/// The red and blue ramps sprites are drawn in, and the grays a key can become.
+const RED_RAMP: (u8, u8) = (0xb0, 0xbf);
+const BLUE_RAMP: (u8, u8) = (0xc0, 0xcf);
+const GRAY_RAMP: (u8, u8) = (0x50, 0x5f);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    Vanilla,
+    RedGreen,
+    BlueYellow,
+}
+
+/// The keys, by color; a skull key is the same color as its card.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyColor {
+    Blue,
+    Yellow,
+    Red,
+}
+
+impl KeyColor {
+    /// The color of vanilla's `card_t`, blue card first and red skull last.
+    pub fn of_card(card: usize) -> Self {
+        match card % 3 {
+            0 => KeyColor::Blue,
+            1 => KeyColor::Yellow,
+            _ => KeyColor::Red,
+        }
+    }
+}
+
+/// A table taking the `from` ramp onto `to`, lightest to lightest.
+fn recolor(from: (u8, u8), to: (u8, u8)) -> Translation {
+    let mut table: Translation = core::array::from_fn(|i| i as u8);
+    let steps = i32::from(from.1 - from.0);
+    for step in 0..=steps {
+        let index = i32::from(to.0) + (i32::from(to.1) - i32::from(to.0)) * step / steps;
+        table[usize::from(from.0) + step as usize] = index as u8;
+    }
+    table
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 3] = [
+        ColorScheme::Vanilla,
+        ColorScheme::RedGreen,
+        ColorScheme::BlueYellow,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Vanilla => "vanilla",
+            ColorScheme::RedGreen => "red-green",
+            ColorScheme::BlueYellow => "blue-yellow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scheme| scheme.name() == name)
+    }
+
+    /// The color to draw an automap line or thing in, given the one vanilla uses; see
+    /// `automap::line_color`.
+    pub fn automap(self, color: u8) -> u8 {
+        let teleporter = REDS + REDRANGE / 2;
+        match (self, color) {
+            (ColorScheme::Vanilla, _) => color,
+            (ColorScheme::RedGreen, REDS) => BLUES + 4,
+            (ColorScheme::RedGreen, c) if c == teleporter => ORANGES + 4,
+            (ColorScheme::RedGreen, BROWNS) => YELLOWS,
+            (ColorScheme::RedGreen, YELLOWS) => WHITE,
+            (ColorScheme::RedGreen, GREENS) => GOLDS,
+            (ColorScheme::BlueYellow, YELLOWS) => ORANGES + 4,
+            (ColorScheme::BlueYellow, GREENS) => WHITE,
+            (_, color) => color,
+        }
+    }
+
+    /// What the status bar and HUD numbers are drawn in, red in vanilla.
+    pub fn numbers(self) -> TextColor {
+        match self {
+            ColorScheme::RedGreen => TextColor::Yellow,
+            _ => TextColor::Red,
+        }
+    }
+
+    /// The table blood sprites are drawn through, if they change.
+    pub fn blood(self) -> Option<Translation> {
+        match self {
+            ColorScheme::RedGreen => Some(recolor(RED_RAMP, BLUE_RAMP)),
+            _ => None,
+        }
+    }
+
+    /// The table a key's sprite and status bar icon are drawn through, if they change.
+    pub fn key(self, key: KeyColor) -> Option<Translation> {
+        match (self, key) {
+            (ColorScheme::RedGreen, KeyColor::Red) => Some(recolor(RED_RAMP, GRAY_RAMP)),
+            (ColorScheme::BlueYellow, KeyColor::Blue) => Some(recolor(BLUE_RAMP, GRAY_RAMP)),
+            _ => None,
+        }
+    }
+}
+
+impl Configurable for ColorScheme {
+    fn register(config: &mut Config) {
+        config.register_str("color_scheme", "vanilla", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self::from_name(&config.get_str("color_scheme")).unwrap_or_default()
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("color_scheme", self.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::automap::{LineKind, GRAYS};
+
+    #[test]
+    fn every_automap_color_stays_apart() {
+        let kinds = [
+            LineKind::Wall,
+            LineKind::Teleporter,
+            LineKind::FloorChange,
+            LineKind::CeilingChange,
+            LineKind::NoChange,
+        ];
+        for scheme in ColorScheme::ALL {
+            let mut colors: Vec<u8> = kinds
+                .iter()
+                .map(|kind| scheme.automap(kind.color(true)))
+                .chain([scheme.automap(GREENS)])
+                .collect();
+            colors.sort();
+            colors.dedup();
+            assert_eq!(colors.len(), kinds.len() + 1, "{}", scheme.name());
+        }
+        assert_eq!(ColorScheme::Vanilla.automap(REDS), REDS);
+        assert_eq!(ColorScheme::RedGreen.automap(GRAYS), GRAYS);
+        assert_ne!(ColorScheme::RedGreen.automap(REDS), REDS);
+    }
+
+    #[test]
+    fn sprites_are_recolored_by_ramp() {
+        let blood = ColorScheme::RedGreen.blood().unwrap();
+        assert_eq!((blood[0xb0], blood[0xbf]), (0xc0, 0xcf));
+        assert_eq!(blood[0x70], 0x70, "the rest stays");
+        assert!(ColorScheme::Vanilla.blood().is_none());
+
+        let scheme = ColorScheme::RedGreen;
+        assert!(scheme.key(KeyColor::of_card(5)).is_some(), "the red skull");
+        assert!(scheme.key(KeyColor::of_card(0)).is_none());
+        assert!(ColorScheme::BlueYellow.key(KeyColor::of_card(3)).is_some());
+    }
+
+    #[test]
+    fn the_scheme_is_kept_in_the_config() {
+        let mut config = Config::new();
+        ColorScheme::register(&mut config);
+        assert_eq!(ColorScheme::from_config(&config), ColorScheme::Vanilla);
+        ColorScheme::BlueYellow.to_config(&mut config);
+        assert_eq!(config.get_str("color_scheme"), "blue-yellow");
+        assert_eq!(ColorScheme::from_config(&config), ColorScheme::BlueYellow);
+        config.set_str("color_scheme", "sepia");
+        assert_eq!(ColorScheme::from_config(&config), ColorScheme::Vanilla);
+    }
+}
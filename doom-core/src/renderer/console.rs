@@ -0,0 +1,117 @@
+//! The drop-down console drawn over the top of the screen in the HUD font.
+//!
+//! It covers as many text lines as it has slid open: a dark background with a red edge at
+//! the bottom, the prompt and what is being typed on the last line, and the newest output
+//! above it. The background is blended over the screen by the format, so the game shows
+//! through when there is a translucency table and it is drawn solid when there isn't.
+
+use super::draw::{draw_raw, Canvas, PixelFormat};
+use super::hud::LINE_HEIGHT;
+use super::text::{Text, TextColor};
+use crate::engine::console::Console;
+
+const BACKGROUND: u8 = 0;
+const EDGE: u8 = 0xb0;
+const MARGIN: i32 = 2;
+pub const PROMPT: &str = "]";
+const CURSOR: &str = "_";
+
+/// Draws `console` onto `canvas` as far as it is open. Nothing while closed.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    text: &Text,
+    console: &Console,
+) {
+    let lines = console.height();
+    if lines == 0 {
+        return;
+    }
+    let width = canvas.pitch;
+    let bottom = lines as i32 * LINE_HEIGHT;
+    let background = format.shade(BACKGROUND, 0);
+    for pixel in canvas.pixels.iter_mut().take(width * bottom as usize) {
+        *pixel = format.translucent(*pixel, background);
+    }
+    draw_raw(format, canvas, 0, bottom, width, &vec![EDGE; width]);
+
+    let input_y = bottom - LINE_HEIGHT;
+    let gold = text.with_color(TextColor::Gold);
+    gold.draw(
+        format,
+        canvas,
+        MARGIN,
+        input_y,
+        &format!("{PROMPT}{}{CURSOR}", console.input()),
+    );
+    for (i, line) in console.visible_lines(lines - 1).iter().rev().enumerate() {
+        let y = input_y - (i as i32 + 1) * LINE_HEIGHT;
+        text.draw(format, canvas, MARGIN, y, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::engine::console::Commands;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::hud::HudFont;
+    use crate::renderer::patch::{Patch, Post};
+    use crate::renderer::text::TextColors;
+
+    #[test]
+    fn slides_down_with_output_above_the_prompt() {
+        // Every glyph is a 4 pixel wide patch whose single pixel is the font's first red.
+        let font = HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![0xb0],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32 + 1));
+        let mut console = Console::new(Commands::shared(), 4);
+        let mut config = Config::new();
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &text, &console);
+        assert!(pixels.iter().all(|&pixel| pixel == 0), "closed");
+
+        console.submit("echo hi", &mut config);
+        console.toggle();
+        for _ in 0..2 {
+            console.ticker();
+        }
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &text, &console);
+        let at = |x: i32, y: i32| pixels[(y * 320 + x) as usize];
+        let edge = 4 * LINE_HEIGHT;
+        assert_eq!(at(319, edge), EDGE as u32 + 1);
+        let background = format.translucent(0, format.shade(BACKGROUND, 0));
+        assert_eq!(at(319, edge - 1), background);
+        assert_eq!(at(319, edge + 1), 0, "the rest of the screen is left alone");
+        // The prompt in gold, the output in red on the line above it.
+        assert_eq!(at(MARGIN, edge - LINE_HEIGHT), 0xa1);
+        assert_eq!(at(MARGIN, edge - 2 * LINE_HEIGHT), 0xb1);
+    }
+}
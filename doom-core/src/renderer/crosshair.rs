@@ -0,0 +1,247 @@
+//! An optional crosshair in the middle of the 3D view.
+//!
+//! It is drawn in the overlay stage, after the player's weapon and before the status bar, in a
+//! palette color so it looks the same in both pixel formats. The shapes are small pixel
+//! patterns scaled with the frame height, so they stay the same size relative to the view.
+
+use super::draw::{Canvas, PixelFormat};
+use super::framebuffer::SCREENHEIGHT;
+use super::overlay::OverlayState;
+use super::view::ViewWindow;
+use crate::engine::config::{Config, Configurable, Section};
+use std::str::FromStr;
+
+/// Palette indices for the health-based colors, from the status bar font ramps.
+const RED: u8 = 176;
+const YELLOW: u8 = 231;
+const GREEN: u8 = 112;
+const BLUE: u8 = 200;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrosshairStyle {
+    #[default]
+    Off,
+    Cross,
+    Dot,
+    Angle,
+}
+
+impl CrosshairStyle {
+    fn name(self) -> &'static str {
+        match self {
+            CrosshairStyle::Off => "off",
+            CrosshairStyle::Cross => "cross",
+            CrosshairStyle::Dot => "dot",
+            CrosshairStyle::Angle => "angle",
+        }
+    }
+
+    /// The pixels to set, relative to the center of the view, at 1x scale.
+    #[rustfmt::skip]
+    fn pixels(self) -> &'static [(i32, i32)] {
+        match self {
+            CrosshairStyle::Off => &[],
+            CrosshairStyle::Cross => &[
+                (-4, 0), (-3, 0), (-2, 0), (2, 0), (3, 0), (4, 0),
+                (0, -4), (0, -3), (0, -2), (0, 2), (0, 3), (0, 4),
+            ],
+            CrosshairStyle::Dot => &[(0, 0)],
+            CrosshairStyle::Angle => &[(-3, 3), (-2, 2), (-1, 1), (0, 0), (1, 1), (2, 2), (3, 3)],
+        }
+    }
+}
+
+impl FromStr for CrosshairStyle {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(CrosshairStyle::Off),
+            "cross" => Ok(CrosshairStyle::Cross),
+            "dot" => Ok(CrosshairStyle::Dot),
+            "angle" => Ok(CrosshairStyle::Angle),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crosshair {
+    pub style: CrosshairStyle,
+    pub color: u8,          // Palette index, used unless `health_color` is set.
+    pub health_color: bool, // Red, yellow, green or blue as health goes up.
+    pub hide_on_automap: bool,
+}
+
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            style: CrosshairStyle::Off,
+            color: RED,
+            health_color: false,
+            hide_on_automap: true,
+        }
+    }
+}
+
+impl Crosshair {
+    /// The palette index to draw with for the player's current health.
+    pub fn color_for(&self, health: i32) -> u8 {
+        if !self.health_color {
+            return self.color;
+        }
+        match health {
+            ..=24 => RED,
+            25..=49 => YELLOW,
+            50..=100 => GREEN,
+            _ => BLUE,
+        }
+    }
+
+    pub fn visible(&self, state: &OverlayState) -> bool {
+        self.style != CrosshairStyle::Off && !(self.hide_on_automap && state.automap_active)
+    }
+
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        window: &ViewWindow,
+        state: &OverlayState,
+    ) {
+        if !self.visible(state) {
+            return;
+        }
+        let pixel = format.shade(self.color_for(state.health), 0);
+        let scale = (canvas.height() / SCREENHEIGHT).max(1) as i32;
+        let center_x = (window.x + window.width / 2) as i32;
+        let center_y = (window.y + window.height / 2) as i32;
+        let (width, height) = (canvas.pitch as i32, canvas.height() as i32);
+        for &(dx, dy) in self.style.pixels() {
+            for sy in 0..scale {
+                for sx in 0..scale {
+                    let x = center_x + dx * scale + sx;
+                    let y = center_y + dy * scale + sy;
+                    if (0..width).contains(&x) && (0..height).contains(&y) {
+                        canvas.pixels[(y * width + x) as usize] = pixel;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Configurable for Crosshair {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_str("crosshair", defaults.style.name(), Section::Extended);
+        config.register_int("crosshair_color", defaults.color as i32, Section::Extended);
+        config.register_bool("crosshair_health", defaults.health_color, Section::Extended);
+        config.register_bool(
+            "crosshair_hide_automap",
+            defaults.hide_on_automap,
+            Section::Extended,
+        );
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            style: config.get_str("crosshair").parse().unwrap_or_default(),
+            color: config.get_int("crosshair_color").clamp(0, 255) as u8,
+            health_color: config.get_bool("crosshair_health"),
+            hide_on_automap: config.get_bool("crosshair_hide_automap"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("crosshair", self.style.name());
+        config.set_int("crosshair_color", self.color as i32);
+        config.set_bool("crosshair_health", self.health_color);
+        config.set_bool("crosshair_hide_automap", self.hide_on_automap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::view::ViewSize;
+
+    fn draw(crosshair: &Crosshair, state: &OverlayState) -> Vec<u32> {
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let window = ViewSize::default().window(320, 200);
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        crosshair.draw(&format, &mut canvas, &window, state);
+        pixels
+    }
+
+    #[test]
+    fn styles_draw_around_the_view_center() {
+        let state = OverlayState {
+            health: 100,
+            ..OverlayState::default()
+        };
+        let center = 84 * 320 + 160;
+        let dot = Crosshair {
+            style: CrosshairStyle::Dot,
+            ..Crosshair::default()
+        };
+        let pixels = draw(&dot, &state);
+        assert_eq!(pixels[center], RED as u32);
+        assert_eq!(pixels.iter().filter(|&&p| p != 0).count(), 1);
+
+        let cross = Crosshair {
+            style: CrosshairStyle::Cross,
+            color: 4,
+            ..Crosshair::default()
+        };
+        let pixels = draw(&cross, &state);
+        assert_eq!(pixels[center], 0);
+        assert_eq!(pixels[center + 3], 4);
+        assert_eq!(pixels[center - 3 * 320], 4);
+        assert!(draw(&Crosshair::default(), &state).iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn health_colors_and_automap_hiding() {
+        let crosshair = Crosshair {
+            style: CrosshairStyle::Angle,
+            health_color: true,
+            ..Crosshair::default()
+        };
+        assert_eq!(crosshair.color_for(10), RED);
+        assert_eq!(crosshair.color_for(40), YELLOW);
+        assert_eq!(crosshair.color_for(100), GREEN);
+        assert_eq!(crosshair.color_for(150), BLUE);
+
+        let automap = OverlayState {
+            automap_active: true,
+            ..OverlayState::default()
+        };
+        assert!(!crosshair.visible(&automap));
+        let shown = Crosshair {
+            hide_on_automap: false,
+            ..crosshair
+        };
+        assert!(shown.visible(&automap));
+    }
+
+    #[test]
+    fn options_round_trip_through_config() {
+        let mut config = Config::new();
+        Crosshair::register(&mut config);
+        assert_eq!(Crosshair::from_config(&config), Crosshair::default());
+        let crosshair = Crosshair {
+            style: CrosshairStyle::Cross,
+            color: 112,
+            ..Crosshair::default()
+        };
+        crosshair.to_config(&mut config);
+        assert_eq!(config.get_str("crosshair"), "cross");
+        assert_eq!(Crosshair::from_config(&config), crosshair);
+    }
+}
@@ -0,0 +1,565 @@
+//! Debug overlays for working on the renderer and the physics, toggled with `r_debug`.
+//!
+//! Three of them draw over the 3D view from what the scene renderer recorded in a
+//! `DebugScene` as it drew the frame: the outline of every visplane, the screen quad of every
+//! drawseg, and the bounding box of every thing, projected as the renderer would. The other
+//! two are map-space lines for the automap: the blockmap cells around the player and the
+//! partition line of every BSP node, clipped to the node's box. The map loader doesn't keep
+//! NODES or BLOCKMAP, so they are read here, straight from the lumps.
+
+use super::automap::{GRAYS, GREENS, REDS, YELLOWS};
+use super::draw::{Canvas, PixelFormat};
+use super::view::ViewWindow;
+use super::ViewPoint;
+use crate::engine::console::Commands;
+use crate::util::angle::sin_cos;
+use crate::util::fixed::{Fixed, FRACBITS, FRACUNIT};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Vanilla's `MAPBLOCKSHIFT`: blockmap cells are 128 units square.
+const MAPBLOCKSHIFT: u32 = FRACBITS + 7;
+/// How many cells either side of the player's cell the blockmap overlay shows.
+const BLOCKMAP_REACH: i32 = 2;
+/// The blue range vanilla uses for the automap's thing arrows in multiplayer.
+const BLUES: u8 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugOverlay {
+    Visplanes,
+    Drawsegs,
+    Blockmap,
+    /// Thing bounding boxes.
+    Hitboxes,
+    Nodes,
+}
+
+impl DebugOverlay {
+    pub const ALL: [DebugOverlay; 5] = [
+        DebugOverlay::Visplanes,
+        DebugOverlay::Drawsegs,
+        DebugOverlay::Blockmap,
+        DebugOverlay::Hitboxes,
+        DebugOverlay::Nodes,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugOverlay::Visplanes => "visplanes",
+            DebugOverlay::Drawsegs => "drawsegs",
+            DebugOverlay::Blockmap => "blockmap",
+            DebugOverlay::Hitboxes => "hitboxes",
+            DebugOverlay::Nodes => "nodes",
+        }
+    }
+
+    /// The palette index it draws in.
+    pub fn color(self) -> u8 {
+        match self {
+            DebugOverlay::Visplanes => GREENS,
+            DebugOverlay::Drawsegs => REDS,
+            DebugOverlay::Blockmap => GRAYS + 8,
+            DebugOverlay::Hitboxes => YELLOWS,
+            DebugOverlay::Nodes => BLUES,
+        }
+    }
+}
+
+/// A visplane as the renderer left it: the rows it covers in every column from `min_x` to
+/// `max_x`, in render columns.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaneOutline {
+    pub min_x: i32,
+    pub top: Vec<i32>,
+    pub bottom: Vec<i32>,
+}
+
+/// A drawseg: the columns `x1` to `x2` of a wall, with its top and bottom rows at each end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SegOutline {
+    pub x1: i32,
+    pub x2: i32,
+    pub top: [i32; 2],
+    pub bottom: [i32; 2],
+}
+
+/// A thing's box in the world: `radius` either side of its position, `height` up from `z`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThingBox {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+    pub radius: Fixed,
+    pub height: Fixed,
+}
+
+/// What the scene renderer records for the overlays while drawing a frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugScene {
+    pub visplanes: Vec<PlaneOutline>,
+    pub drawsegs: Vec<SegOutline>,
+    pub things: Vec<ThingBox>,
+}
+
+/// A BSP node from the NODES lump.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Node {
+    pub x: i16,
+    pub y: i16,
+    pub dx: i16,
+    pub dy: i16,
+    /// Top, bottom, left and right of the right child, then the left child.
+    pub bbox: [[i16; 4]; 2],
+    pub children: [u16; 2],
+}
+
+fn i16_at(bytes: &[u8], at: usize) -> i16 {
+    i16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Reads a NODES lump. Returns `None` if it is truncated.
+pub fn parse_nodes(lump: &[u8]) -> Option<Vec<Node>> {
+    if !lump.len().is_multiple_of(28) {
+        return None;
+    }
+    let nodes = lump.chunks_exact(28).map(|r| Node {
+        x: i16_at(r, 0),
+        y: i16_at(r, 2),
+        dx: i16_at(r, 4),
+        dy: i16_at(r, 6),
+        bbox: [
+            std::array::from_fn(|i| i16_at(r, 8 + 2 * i)),
+            std::array::from_fn(|i| i16_at(r, 16 + 2 * i)),
+        ],
+        children: [i16_at(r, 24) as u16, i16_at(r, 26) as u16],
+    });
+    Some(nodes.collect())
+}
+
+/// The header of a BLOCKMAP lump, which is all the overlay needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockmapGrid {
+    pub origin_x: Fixed,
+    pub origin_y: Fixed,
+    pub columns: i32,
+    pub rows: i32,
+}
+
+impl BlockmapGrid {
+    pub fn parse(lump: &[u8]) -> Option<Self> {
+        (lump.len() >= 8).then(|| Self {
+            origin_x: i32::from(i16_at(lump, 0)) << FRACBITS,
+            origin_y: i32::from(i16_at(lump, 2)) << FRACBITS,
+            columns: i32::from(i16_at(lump, 4) as u16),
+            rows: i32::from(i16_at(lump, 6) as u16),
+        })
+    }
+}
+
+/// A line for the automap to draw, in map coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapLine {
+    pub from: (Fixed, Fixed),
+    pub to: (Fixed, Fixed),
+    pub color: u8,
+}
+
+/// A rectangle on the screen, edges included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenRect {
+    pub x1: i32,
+    pub y1: i32,
+    pub x2: i32,
+    pub y2: i32,
+}
+
+/// Things closer than this are cut by the view plane, and aren't boxed.
+const MIN_DEPTH: f64 = 4.0;
+
+/// Where `thing`'s box lands in the view window, in frame pixels relative to its corner, or
+/// `None` if part of it is behind the viewer.
+pub fn project_box(thing: &ThingBox, view: &ViewPoint, window: &ViewWindow) -> Option<ScreenRect> {
+    let projection = window.projection();
+    let scale = f64::from(1 << window.detail_shift);
+    let focal = f64::from(projection.projection) / f64::from(FRACUNIT);
+    let (sine, cosine) = sin_cos(view.angle);
+    let (sine, cosine) = (f64::from(sine), f64::from(cosine));
+    let units = |value: Fixed| f64::from(value) / f64::from(FRACUNIT);
+
+    let (mut x1, mut y1, mut x2, mut y2) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for (cx, cy) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+        let dx = units(thing.x.wrapping_sub(view.x)) + cx * units(thing.radius);
+        let dy = units(thing.y.wrapping_sub(view.y)) + cy * units(thing.radius);
+        let depth = (dx * cosine + dy * sine) / f64::from(FRACUNIT);
+        if depth < MIN_DEPTH {
+            return None;
+        }
+        let side = (dx * sine - dy * cosine) / f64::from(FRACUNIT);
+        let x = (f64::from(projection.center_x) + side * focal / depth) * scale;
+        x1 = x1.min(x);
+        x2 = x2.max(x);
+        for z in [thing.z, thing.z + thing.height] {
+            let y = f64::from(projection.center_y) - units(z - view.z) * focal / depth;
+            y1 = y1.min(y);
+            y2 = y2.max(y);
+        }
+    }
+    Some(ScreenRect {
+        x1: x1.round() as i32,
+        y1: y1.round() as i32,
+        x2: x2.round() as i32,
+        y2: y2.round() as i32,
+    })
+}
+
+/// Which overlays are on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugOverlays {
+    enabled: u8,
+}
+
+impl DebugOverlays {
+    pub fn is_on(&self, overlay: DebugOverlay) -> bool {
+        self.enabled & 1 << overlay as u8 != 0
+    }
+
+    pub fn set(&mut self, overlay: DebugOverlay, on: bool) {
+        if on {
+            self.enabled |= 1 << overlay as u8;
+        } else {
+            self.enabled &= !(1 << overlay as u8);
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.enabled != 0
+    }
+
+    /// Draws the view overlays that are on, clipped to `window`.
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        window: &ViewWindow,
+        view: &ViewPoint,
+        scene: &DebugScene,
+    ) {
+        let mut pen = Pen {
+            canvas,
+            window,
+            pixel: format.shade(0, 0),
+        };
+        // Render columns to frame columns, for low detail.
+        let column = |x: i32| x << window.detail_shift;
+        if self.is_on(DebugOverlay::Visplanes) {
+            pen.pixel = format.shade(DebugOverlay::Visplanes.color(), 0);
+            for plane in &scene.visplanes {
+                let columns = plane.top.iter().zip(&plane.bottom);
+                for (x, (&top, &bottom)) in (plane.min_x..).zip(columns) {
+                    pen.plot(column(x), top);
+                    pen.plot(column(x), bottom);
+                }
+                for index in [0, plane.top.len().saturating_sub(1)] {
+                    if let (Some(&top), Some(&bottom)) =
+                        (plane.top.get(index), plane.bottom.get(index))
+                    {
+                        let x = column(plane.min_x + index as i32);
+                        pen.line((x, top), (x, bottom));
+                    }
+                }
+            }
+        }
+        if self.is_on(DebugOverlay::Drawsegs) {
+            pen.pixel = format.shade(DebugOverlay::Drawsegs.color(), 0);
+            for seg in &scene.drawsegs {
+                let (x1, x2) = (column(seg.x1), column(seg.x2));
+                pen.line((x1, seg.top[0]), (x2, seg.top[1]));
+                pen.line((x1, seg.bottom[0]), (x2, seg.bottom[1]));
+                pen.line((x1, seg.top[0]), (x1, seg.bottom[0]));
+                pen.line((x2, seg.top[1]), (x2, seg.bottom[1]));
+            }
+        }
+        if self.is_on(DebugOverlay::Hitboxes) {
+            pen.pixel = format.shade(DebugOverlay::Hitboxes.color(), 0);
+            for thing in &scene.things {
+                if let Some(rect) = project_box(thing, view, window) {
+                    pen.line((rect.x1, rect.y1), (rect.x2, rect.y1));
+                    pen.line((rect.x1, rect.y2), (rect.x2, rect.y2));
+                    pen.line((rect.x1, rect.y1), (rect.x1, rect.y2));
+                    pen.line((rect.x2, rect.y1), (rect.x2, rect.y2));
+                }
+            }
+        }
+    }
+
+    /// The automap overlays that are on, as lines in map space.
+    pub fn map_lines(
+        &self,
+        nodes: &[Node],
+        blockmap: Option<&BlockmapGrid>,
+        player: (Fixed, Fixed),
+    ) -> Vec<MapLine> {
+        let mut lines = Vec::new();
+        if let (true, Some(grid)) = (self.is_on(DebugOverlay::Blockmap), blockmap) {
+            lines.extend(blockmap_lines(grid, player));
+        }
+        if self.is_on(DebugOverlay::Nodes) {
+            lines.extend(nodes.iter().filter_map(partition_line));
+        }
+        lines
+    }
+}
+
+/// The edges of the cells within `BLOCKMAP_REACH` of the player's, as far as the grid goes.
+fn blockmap_lines(grid: &BlockmapGrid, player: (Fixed, Fixed)) -> Vec<MapLine> {
+    let cell = |position: Fixed, origin: Fixed| position.wrapping_sub(origin) >> MAPBLOCKSHIFT;
+    let (column, row) = (cell(player.0, grid.origin_x), cell(player.1, grid.origin_y));
+    let columns =
+        (column - BLOCKMAP_REACH).max(0)..=(column + BLOCKMAP_REACH).min(grid.columns - 1);
+    let rows = (row - BLOCKMAP_REACH).max(0)..=(row + BLOCKMAP_REACH).min(grid.rows - 1);
+    if columns.is_empty() || rows.is_empty() {
+        return Vec::new();
+    }
+    let x = |column: i32| grid.origin_x + (column << MAPBLOCKSHIFT);
+    let y = |row: i32| grid.origin_y + (row << MAPBLOCKSHIFT);
+    let (left, right) = (x(*columns.start()), x(columns.end() + 1));
+    let (bottom, top) = (y(*rows.start()), y(rows.end() + 1));
+    let color = DebugOverlay::Blockmap.color();
+    let verticals = (*columns.start()..=columns.end() + 1).map(|column| MapLine {
+        from: (x(column), bottom),
+        to: (x(column), top),
+        color,
+    });
+    let horizontals = (*rows.start()..=rows.end() + 1).map(|row| MapLine {
+        from: (left, y(row)),
+        to: (right, y(row)),
+        color,
+    });
+    verticals.chain(horizontals).collect()
+}
+
+/// The node's partition line, extended across both children's boxes.
+fn partition_line(node: &Node) -> Option<MapLine> {
+    let [right, left] = node.bbox;
+    let (top, bottom) = (right[0].max(left[0]), right[1].min(left[1]));
+    let (min_x, max_x) = (right[2].min(left[2]), right[3].max(left[3]));
+    let (x, y) = (f64::from(node.x), f64::from(node.y));
+    let (dx, dy) = (f64::from(node.dx), f64::from(node.dy));
+    // Liang-Barsky: the range of t along the line that stays inside the box.
+    let (mut t0, mut t1) = (f64::MIN, f64::MAX);
+    for (delta, low, high, start) in [
+        (dx, f64::from(min_x), f64::from(max_x), x),
+        (dy, f64::from(bottom), f64::from(top), y),
+    ] {
+        if delta == 0.0 {
+            if start < low || start > high {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((low - start) / delta, (high - start) / delta);
+        t0 = t0.max(a.min(b));
+        t1 = t1.min(a.max(b));
+    }
+    if t0 > t1 || t0 == f64::MIN {
+        return None;
+    }
+    let point = |t: f64| {
+        (
+            ((x + t * dx) * f64::from(FRACUNIT)) as Fixed,
+            ((y + t * dy) * f64::from(FRACUNIT)) as Fixed,
+        )
+    };
+    Some(MapLine {
+        from: point(t0),
+        to: point(t1),
+        color: DebugOverlay::Nodes.color(),
+    })
+}
+
+/// Plots pixels inside the view window, from window-relative coordinates.
+struct Pen<'a, 'b, T> {
+    canvas: &'a mut Canvas<'b, T>,
+    window: &'a ViewWindow,
+    pixel: T,
+}
+
+impl<T: Copy> Pen<'_, '_, T> {
+    fn plot(&mut self, x: i32, y: i32) {
+        let inside = (0..self.window.width as i32).contains(&x)
+            && (0..self.window.height as i32).contains(&y);
+        if inside {
+            let (x, y) = (self.window.x + x as usize, self.window.y + y as usize);
+            if let Some(pixel) = self.canvas.pixels.get_mut(y * self.canvas.pitch + x) {
+                *pixel = self.pixel;
+            }
+        }
+    }
+
+    /// Bresenham, both ends included.
+    fn line(&mut self, (mut x, mut y): (i32, i32), (x2, y2): (i32, i32)) {
+        let (dx, dy) = ((x2 - x).abs(), -(y2 - y).abs());
+        let (sx, sy) = ((x2 - x).signum(), (y2 - y).signum());
+        let mut error = dx + dy;
+        loop {
+            self.plot(x, y);
+            if (x, y) == (x2, y2) {
+                break;
+            }
+            let twice = 2 * error;
+            if twice >= dy {
+                error += dy;
+                x += sx;
+            }
+            if twice <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+}
+
+/// Registers `r_debug [overlay]`, which toggles an overlay or lists them.
+pub fn register_command(overlays: Rc<RefCell<DebugOverlays>>, commands: &mut Commands) {
+    commands.register(
+        "r_debug",
+        "r_debug [visplanes|drawsegs|blockmap|hitboxes|nodes]: toggle a debug overlay",
+        move |context, args| {
+            let mut overlays = overlays.borrow_mut();
+            let Some(name) = args.first() else {
+                for overlay in DebugOverlay::ALL {
+                    let state = if overlays.is_on(overlay) { "on" } else { "off" };
+                    context.print(format!("{:<12}{state}", overlay.name()));
+                }
+                return Ok(());
+            };
+            let overlay = DebugOverlay::ALL
+                .into_iter()
+                .find(|overlay| overlay.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("unknown overlay '{name}'"))?;
+            let on = !overlays.is_on(overlay);
+            overlays.set(overlay, on);
+            let state = if on { "on" } else { "off" };
+            context.print(format!("{} overlay {state}", overlay.name()));
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::Config;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::view::ViewSize;
+
+    fn all_on() -> DebugOverlays {
+        let mut overlays = DebugOverlays::default();
+        for overlay in DebugOverlay::ALL {
+            overlays.set(overlay, true);
+        }
+        overlays
+    }
+
+    #[test]
+    fn view_overlays_draw_inside_the_window() {
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let window = ViewSize::default().window(320, 200);
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        let scene = DebugScene {
+            visplanes: vec![PlaneOutline {
+                min_x: 10,
+                top: vec![100, 101, 102],
+                bottom: vec![150, 150, 150],
+            }],
+            drawsegs: vec![SegOutline {
+                x1: 50,
+                x2: 400,
+                top: [20, 20],
+                bottom: [60, 60],
+            }],
+            things: vec![ThingBox {
+                x: 256 * FRACUNIT,
+                y: 0,
+                z: -41 * FRACUNIT,
+                radius: 20 * FRACUNIT,
+                height: 56 * FRACUNIT,
+            }],
+        };
+        let view = ViewPoint::default();
+        all_on().draw(&format, &mut canvas, &window, &view, &scene);
+        let at = |x: usize, y: usize| pixels[y * 320 + x];
+        let planes = u32::from(DebugOverlay::Visplanes.color());
+        assert_eq!((at(11, 101), at(11, 150), at(11, 120)), (planes, planes, 0));
+        assert_eq!(at(10, 125), planes, "the left edge is closed");
+        assert_eq!(at(12, 125), planes, "and so is the right");
+        let segs = u32::from(DebugOverlay::Drawsegs.color());
+        assert_eq!((at(50, 40), at(200, 20), at(319, 60)), (segs, segs, segs));
+
+        // Straight ahead and 256 units out: 20 units either side is 12.5 pixels at a focal
+        // length of 160, and the box's bottom is 41 units below the eye.
+        let rect = project_box(&scene.things[0], &view, &window).unwrap();
+        assert_eq!(rect.x1, 160 - 14);
+        assert_eq!(rect.x2, 160 + 14);
+        assert_eq!((rect.y1, rect.y2), (84 - 10, 84 + 28));
+        let hitboxes = u32::from(DebugOverlay::Hitboxes.color());
+        assert_eq!(at(160, 84 + 28), hitboxes);
+        let behind = ThingBox {
+            x: -256 * FRACUNIT,
+            ..scene.things[0]
+        };
+        assert_eq!(project_box(&behind, &view, &window), None);
+    }
+
+    #[test]
+    fn automap_lines_come_from_the_lumps() {
+        let mut lump = Vec::new();
+        for value in [0i16, 0, 0, 64, 64, -64, -128, 0, 64, -64, 0, 128, 0, 1] {
+            lump.extend_from_slice(&value.to_le_bytes());
+        }
+        let nodes = parse_nodes(&lump).unwrap();
+        assert_eq!(nodes[0].children, [0, 1]);
+        assert!(parse_nodes(&lump[1..]).is_none());
+        let grid = BlockmapGrid::parse(&[0x80, 0xff, 0, 0, 4, 0, 2, 0]).unwrap();
+        assert_eq!(
+            (grid.origin_x, grid.columns, grid.rows),
+            (-128 * FRACUNIT, 4, 2)
+        );
+
+        let lines = all_on().map_lines(&nodes, Some(&grid), (0, 0));
+        // Every column and both rows are in reach: five verticals, three horizontals.
+        assert_eq!(lines.len(), 5 + 3 + 1);
+        assert_eq!(lines[0].from, (-128 * FRACUNIT, 0));
+        assert_eq!(lines[0].to, (-128 * FRACUNIT, 256 * FRACUNIT));
+        let partition = lines[8];
+        assert_eq!(partition.from, (0, -64 * FRACUNIT));
+        assert_eq!(partition.to, (0, 64 * FRACUNIT));
+        assert!(DebugOverlays::default()
+            .map_lines(&nodes, Some(&grid), (0, 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn the_command_toggles_overlays() {
+        let overlays = Rc::new(RefCell::new(DebugOverlays::default()));
+        let mut commands = Commands::default();
+        register_command(overlays.clone(), &mut commands);
+        let mut config = Config::new();
+        assert_eq!(
+            commands.execute("r_debug Nodes", &mut config),
+            ["nodes overlay on"]
+        );
+        assert!(overlays.borrow().is_on(DebugOverlay::Nodes));
+        assert_eq!(commands.execute("r_debug", &mut config).len(), 5);
+        commands.execute("r_debug nodes", &mut config);
+        assert!(!overlays.borrow().any());
+        assert_eq!(
+            commands.execute("r_debug bsp", &mut config),
+            ["r_debug: unknown overlay 'bsp'"]
+        );
+    }
+}
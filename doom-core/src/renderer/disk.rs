@@ -0,0 +1,157 @@
+//! The disk icon: vanilla's `STDISK` (or its `-cdrom` `STCDROM`) in the bottom right corner
+//! while the game reads from the WADs.
+//!
+//! Vanilla drew it in `I_BeginRead` and erased it in `I_EndRead`, so it showed for as long as
+//! the floppy or CD took. Reads from memory are over long before the next frame, so the icon
+//! stays up for `SHOW_FRAMES` frames after the count from `wad::reads` last moved, which is
+//! long enough to see and short enough to still mean "just now". It is drawn last, over
+//! everything else, like vanilla's.
+
+use super::draw::{draw_patch, Canvas, PixelFormat};
+use super::patch::Patch;
+use crate::engine::config::{Config, Configurable, Section};
+
+/// Frames the icon stays up after a read.
+pub const SHOW_FRAMES: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskIconOptions {
+    pub show: bool,
+    /// Vanilla's `-cdrom` icon instead of the floppy.
+    pub cdrom: bool,
+}
+
+impl Default for DiskIconOptions {
+    fn default() -> Self {
+        Self {
+            show: true,
+            cdrom: false,
+        }
+    }
+}
+
+impl DiskIconOptions {
+    pub fn lump(&self) -> &'static str {
+        match self.cdrom {
+            true => "STCDROM",
+            false => "STDISK",
+        }
+    }
+}
+
+impl Configurable for DiskIconOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("show_diskicon", true, Section::Extended);
+        config.register_bool("diskicon_cdrom", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            show: config.get_bool("show_diskicon"),
+            cdrom: config.get_bool("diskicon_cdrom"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("show_diskicon", self.show);
+        config.set_bool("diskicon_cdrom", self.cdrom);
+    }
+}
+
+/// Follows the read count from frame to frame and draws the icon while it is recent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiskIcon {
+    /// `None` when the option is off or the WADs have no icon.
+    patch: Option<Patch>,
+    reads: usize,
+    frames_left: u32,
+}
+
+impl DiskIcon {
+    pub fn new(options: &DiskIconOptions, patch: Option<Patch>) -> Self {
+        Self {
+            patch: patch.filter(|_| options.show),
+            ..Self::default()
+        }
+    }
+
+    /// Takes the read count for this frame, returning whether the icon shows.
+    pub fn update(&mut self, reads: usize) -> bool {
+        if reads != self.reads {
+            self.reads = reads;
+            self.frames_left = SHOW_FRAMES;
+        }
+        let shown = self.frames_left > 0 && self.patch.is_some();
+        self.frames_left = self.frames_left.saturating_sub(1);
+        shown
+    }
+
+    /// Draws the icon in the bottom right corner of `canvas`, whatever its offsets say.
+    pub fn draw<F: PixelFormat>(&self, format: &F, canvas: &mut Canvas<F::Pixel>) {
+        let Some(patch) = &self.patch else {
+            return;
+        };
+        let x = canvas.pitch as i32 - i32::from(patch.width) + i32::from(patch.left_offset);
+        let y = canvas.height() as i32 - i32::from(patch.height) + i32::from(patch.top_offset);
+        draw_patch(format, canvas, x, y, patch, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::Post;
+
+    fn icon() -> Patch {
+        Patch {
+            width: 2,
+            height: 2,
+            left_offset: -5,
+            top_offset: 3,
+            columns: vec![
+                vec![Post {
+                    top: 0,
+                    pixels: vec![1, 2],
+                }],
+                vec![Post {
+                    top: 1,
+                    pixels: vec![3],
+                }],
+            ],
+        }
+    }
+
+    #[test]
+    fn the_icon_shows_for_a_few_frames_after_a_read() {
+        let mut disk = DiskIcon::new(&DiskIconOptions::default(), Some(icon()));
+        assert!(!disk.update(0));
+        let shown: Vec<bool> = [1, 1, 1, 1, 1, 2, 2]
+            .map(|reads| disk.update(reads))
+            .to_vec();
+        assert_eq!(shown, [true, true, true, true, false, true, true]);
+
+        let off = DiskIconOptions {
+            show: false,
+            ..DiskIconOptions::default()
+        };
+        let mut disk = DiskIcon::new(&off, Some(icon()));
+        assert!(!disk.update(1));
+        assert_eq!(DiskIconOptions { cdrom: true, ..off }.lump(), "STCDROM");
+    }
+
+    #[test]
+    fn the_icon_sits_in_the_corner() {
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 8 * 4];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 8,
+        };
+        DiskIcon::new(&DiskIconOptions::default(), Some(icon())).draw(&format, &mut canvas);
+        assert_eq!(pixels[2 * 8 + 6], 1);
+        assert_eq!(pixels[3 * 8 + 6], 2);
+        assert_eq!(pixels[3 * 8 + 7], 3);
+        assert_eq!(pixels.iter().filter(|&&pixel| pixel != 0).count(), 3);
+    }
+}
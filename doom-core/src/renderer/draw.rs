@@ -0,0 +1,579 @@
+//! The low level draw routines, written once and instantiated per output format.
+//!
+//! Vanilla draws palette indices and does all lighting through COLORMAP lookups. The true-color
+//! path instead multiplies the palette color by a real light factor and blends translucency
+//! exactly. Both are expressed through `PixelFormat`, so every routine below (columns, spans,
+//! fuzz, patches) works with either one, and the indexed path stays bit-exact with vanilla.
+
+use super::patch::Patch;
+use super::tranmap::TranMap;
+#[cfg(feature = "std")]
+use crate::engine::profile::{self, Subsystem};
+use crate::util::fixed::{Fixed, FRACBITS, FRACUNIT};
+
+/// Number of light levels in COLORMAP, before the invulnerability map.
+pub const NUMCOLORMAPS: usize = 32;
+/// Index of the inverse greyscale map used while invulnerable.
+pub const INVERSECOLORMAP: usize = 32;
+/// The colormap vanilla uses to darken the pixels behind a fuzzy sprite.
+const FUZZCOLORMAP: usize = 6;
+
+/// Which pixel format the software renderer draws in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Indexed,
+    TrueColor,
+}
+
+/// How a texel becomes an output pixel.
+///
+/// Light is passed as a fixed point colormap position: `0` is full brightness, `31 << FRACBITS`
+/// the darkest map and `INVERSECOLORMAP << FRACBITS` selects the invulnerability effect. The
+/// indexed format only looks at the integer part; the true-color format keeps the fraction,
+/// which is what makes smooth diminishing possible.
+pub trait PixelFormat {
+    type Pixel: Copy;
+    fn shade(&self, texel: u8, light: Fixed) -> Self::Pixel;
+    /// Draws `over` at 50% opacity on top of `under`.
+    fn translucent(&self, under: Self::Pixel, over: Self::Pixel) -> Self::Pixel;
+    /// Darkens a pixel that is already in the frame, for the spectre fuzz effect.
+    fn fuzz(&self, under: Self::Pixel) -> Self::Pixel;
+}
+
+/// Vanilla palette index output, lit through the COLORMAP lump.
+pub struct Indexed<'a> {
+    colormaps: &'a [u8],
+    tranmap: Option<&'a TranMap>,
+    doubled: bool, // Twice vanilla's light maps, as `lighting::double_colormaps` makes them.
+}
+
+impl<'a> Indexed<'a> {
+    /// Wraps a COLORMAP lump. Returns `None` if it is missing the invulnerability map.
+    pub fn new(colormaps: &'a [u8]) -> Option<Self> {
+        (colormaps.len() >= (INVERSECOLORMAP + 1) * 256).then_some(Self {
+            colormaps,
+            tranmap: None,
+            doubled: false,
+        })
+    }
+
+    /// Wraps colormaps with 64 light levels, a half step apart, then the invulnerability map.
+    pub fn doubled(colormaps: &'a [u8]) -> Option<Self> {
+        (colormaps.len() >= (2 * INVERSECOLORMAP + 1) * 256).then_some(Self {
+            colormaps,
+            tranmap: None,
+            doubled: true,
+        })
+    }
+
+    /// Blends translucent pixels through `tranmap` instead of drawing them solid.
+    pub fn with_tranmap(self, tranmap: &'a TranMap) -> Self {
+        Self {
+            tranmap: Some(tranmap),
+            ..self
+        }
+    }
+
+    fn map(&self, index: usize) -> &[u8] {
+        &self.colormaps[index * 256..(index + 1) * 256]
+    }
+}
+
+impl PixelFormat for Indexed<'_> {
+    type Pixel = u8;
+
+    fn shade(&self, texel: u8, light: Fixed) -> u8 {
+        let map = ((light >> FRACBITS).max(0) as usize).min(INVERSECOLORMAP);
+        if self.doubled {
+            // The half step below the map, unless this is the invulnerability map.
+            let half = ((light >> (FRACBITS - 1)) & 1) as usize;
+            let map = 2 * map + half * usize::from(map < INVERSECOLORMAP);
+            return self.map(map)[texel as usize];
+        }
+        self.map(map)[texel as usize]
+    }
+
+    /// Without a translucency table the top pixel simply wins.
+    fn translucent(&self, under: u8, over: u8) -> u8 {
+        self.tranmap
+            .map_or(over, |tranmap| tranmap.blend(under, over))
+    }
+
+    fn fuzz(&self, under: u8) -> u8 {
+        let map = FUZZCOLORMAP << usize::from(self.doubled);
+        self.map(map)[under as usize]
+    }
+}
+
+/// `0x00RRGGBB` output with real light multiplication.
+pub struct TrueColor {
+    palette: [u32; 256],
+    banded: bool,
+}
+
+impl TrueColor {
+    pub fn new(palette: [u32; 256]) -> Self {
+        Self {
+            palette,
+            banded: false,
+        }
+    }
+
+    /// Keeps light in vanilla's 32 steps instead of attenuating it smoothly.
+    pub fn banded(self) -> Self {
+        Self {
+            banded: true,
+            ..self
+        }
+    }
+}
+
+impl PixelFormat for TrueColor {
+    type Pixel = u32;
+
+    fn shade(&self, texel: u8, light: Fixed) -> u32 {
+        let color = self.palette[texel as usize];
+        if light >> FRACBITS >= INVERSECOLORMAP as i32 {
+            let grey = 255 - ((color >> 16 & 0xff) + (color >> 8 & 0xff) + (color & 0xff)) / 3;
+            return grey << 16 | grey << 8 | grey;
+        }
+        let light = match self.banded {
+            true => light & !(FRACUNIT - 1),
+            false => light,
+        };
+        // Map position 0..32 to a brightness of 256..0.
+        let dark = NUMCOLORMAPS as i64 * FRACUNIT as i64;
+        let factor = ((dark - light.max(0) as i64).max(0) * 256 / dark) as u32;
+        scale(color, factor)
+    }
+
+    fn translucent(&self, under: u32, over: u32) -> u32 {
+        // Average each channel without letting carries leak into the neighbouring one.
+        (under & over) + ((under ^ over) >> 1 & 0x7f7f7f)
+    }
+
+    fn fuzz(&self, under: u32) -> u32 {
+        scale(
+            under,
+            ((NUMCOLORMAPS - FUZZCOLORMAP) * 256 / NUMCOLORMAPS) as u32,
+        )
+    }
+}
+
+/// Multiplies every channel by `factor / 256`.
+fn scale(color: u32, factor: u32) -> u32 {
+    let r = ((color >> 16 & 0xff) * factor) >> 8;
+    let g = ((color >> 8 & 0xff) * factor) >> 8;
+    let b = ((color & 0xff) * factor) >> 8;
+    r << 16 | g << 8 | b
+}
+
+/// A destination buffer for the draw routines: `pixels[y * pitch + x]`.
+pub struct Canvas<'a, T> {
+    pub pixels: &'a mut [T],
+    pub pitch: usize,
+}
+
+impl<T> Canvas<'_, T> {
+    pub fn height(&self) -> usize {
+        self.pixels.len() / self.pitch
+    }
+}
+
+/// Everything needed to draw one vertical wall or sprite column.
+pub struct Column<'a> {
+    pub x: usize,
+    pub yl: i32,
+    pub yh: i32,
+    pub center_y: i32,
+    pub texture_mid: Fixed,
+    pub iscale: Fixed, // Texture rows per screen row.
+    pub light: Fixed,
+    pub source: &'a [u8],
+}
+
+/// Draws a texture-mapped column. Like vanilla, texture rows wrap at 128.
+pub fn draw_column<F: PixelFormat>(format: &F, canvas: &mut Canvas<F::Pixel>, column: &Column) {
+    if column.yh < column.yl || column.source.is_empty() {
+        return;
+    }
+    #[cfg(feature = "std")]
+    let _scope = profile::scope(Subsystem::Columns);
+    let mut frac = column.texture_mid + (column.yl - column.center_y) * column.iscale;
+    for y in column.yl..=column.yh {
+        let row = ((frac >> FRACBITS) & 127) as usize % column.source.len();
+        canvas.pixels[y as usize * canvas.pitch + column.x] =
+            format.shade(column.source[row], column.light);
+        frac += column.iscale;
+    }
+}
+
+/// Draws a column at 50% opacity over what is already in the canvas.
+pub fn draw_translucent_column<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    column: &Column,
+) {
+    if column.yh < column.yl || column.source.is_empty() {
+        return;
+    }
+    #[cfg(feature = "std")]
+    let _scope = profile::scope(Subsystem::Columns);
+    let mut frac = column.texture_mid + (column.yl - column.center_y) * column.iscale;
+    for y in column.yl..=column.yh {
+        let row = ((frac >> FRACBITS) & 127) as usize % column.source.len();
+        let at = y as usize * canvas.pitch + column.x;
+        let over = format.shade(column.source[row], column.light);
+        canvas.pixels[at] = format.translucent(canvas.pixels[at], over);
+        frac += column.iscale;
+    }
+}
+
+/// Draws a column with its texels remapped through `translation` first, for the player
+/// colors.
+pub fn draw_translated_column<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    column: &Column,
+    translation: &[u8; 256],
+) {
+    if column.yh < column.yl || column.source.is_empty() {
+        return;
+    }
+    #[cfg(feature = "std")]
+    let _scope = profile::scope(Subsystem::Columns);
+    let mut frac = column.texture_mid + (column.yl - column.center_y) * column.iscale;
+    for y in column.yl..=column.yh {
+        let row = ((frac >> FRACBITS) & 127) as usize % column.source.len();
+        let texel = translation[column.source[row] as usize];
+        canvas.pixels[y as usize * canvas.pitch + column.x] = format.shade(texel, column.light);
+        frac += column.iscale;
+    }
+}
+
+/// Everything needed to draw one horizontal floor or ceiling span from a 64x64 flat.
+pub struct Span<'a> {
+    pub y: usize,
+    pub x1: usize,
+    pub x2: usize,
+    pub xfrac: Fixed,
+    pub yfrac: Fixed,
+    pub xstep: Fixed,
+    pub ystep: Fixed,
+    pub light: Fixed,
+    pub source: &'a [u8; 4096],
+}
+
+pub fn draw_span<F: PixelFormat>(format: &F, canvas: &mut Canvas<F::Pixel>, span: &Span) {
+    #[cfg(feature = "std")]
+    let _scope = profile::scope(Subsystem::Spans);
+    let (mut xfrac, mut yfrac) = (span.xfrac, span.yfrac);
+    let row = &mut canvas.pixels[span.y * canvas.pitch..];
+    for pixel in &mut row[span.x1..=span.x2] {
+        let spot = ((yfrac >> (FRACBITS - 6)) & (63 * 64)) + ((xfrac >> FRACBITS) & 63);
+        *pixel = format.shade(span.source[spot as usize], span.light);
+        xfrac += span.xstep;
+        yfrac += span.ystep;
+    }
+}
+
+/// Row offsets vanilla samples from to build the shimmering spectre effect.
+#[rustfmt::skip]
+const FUZZOFFSET: [i8; 50] = [
+    1, -1, 1, -1, 1, 1, -1, 1, 1, -1, 1, 1, 1, -1, 1, 1, 1, -1, -1, -1, -1, 1, -1, -1, 1, 1,
+    1, 1, -1, 1, -1, 1, 1, -1, -1, 1, 1, -1, -1, -1, -1, 1, 1, 1, 1, -1, 1, 1, -1, 1,
+];
+
+/// Position in the fuzz table. Vanilla keeps this running across every fuzz column in a frame.
+#[derive(Default)]
+pub struct Fuzz {
+    pos: usize,
+}
+
+impl Fuzz {
+    /// Darkens the column `yl..=yh` by sampling the pixel above or below each row. The first
+    /// and last row of the canvas are never touched so the samples stay in bounds.
+    pub fn draw_column<F: PixelFormat>(
+        &mut self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: usize,
+        yl: i32,
+        yh: i32,
+    ) {
+        #[cfg(feature = "std")]
+        let _scope = profile::scope(Subsystem::Columns);
+        let yl = yl.max(1);
+        let yh = yh.min(canvas.height() as i32 - 2);
+        for y in yl..=yh {
+            let at = y as usize * canvas.pitch + x;
+            let sample =
+                (at as isize + FUZZOFFSET[self.pos] as isize * canvas.pitch as isize) as usize;
+            canvas.pixels[at] = format.fuzz(canvas.pixels[sample]);
+            self.pos = (self.pos + 1) % FUZZOFFSET.len();
+        }
+    }
+}
+
+/// Darkens the column `yl..=yh` evenly, the fuzz colormap without the shimmer: a spectre
+/// drawn as a shadow.
+pub fn draw_shadow_column<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    x: usize,
+    yl: i32,
+    yh: i32,
+) {
+    #[cfg(feature = "std")]
+    let _scope = profile::scope(Subsystem::Columns);
+    let yh = yh.min(canvas.height() as i32 - 1);
+    for y in yl.max(0)..=yh {
+        let at = y as usize * canvas.pitch + x;
+        canvas.pixels[at] = format.fuzz(canvas.pixels[at]);
+    }
+}
+
+/// Draws a patch unscaled with its offsets applied, clipped to the canvas.
+pub fn draw_patch<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    x: i32,
+    y: i32,
+    patch: &Patch,
+    light: Fixed,
+) {
+    blit_patch(canvas, x, y, patch, |texel| format.shade(texel, light));
+}
+
+/// Draws a patch unlit with its texels remapped through `translation` first, for text in
+/// colors other than the font's own.
+pub fn draw_translated_patch<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    x: i32,
+    y: i32,
+    patch: &Patch,
+    translation: &[u8; 256],
+) {
+    blit_patch(canvas, x, y, patch, |texel| {
+        format.shade(translation[texel as usize], 0)
+    });
+}
+
+fn blit_patch<P: Copy>(
+    canvas: &mut Canvas<P>,
+    x: i32,
+    y: i32,
+    patch: &Patch,
+    pixel: impl Fn(u8) -> P,
+) {
+    let x = x - patch.left_offset as i32;
+    let y = y - patch.top_offset as i32;
+    let height = canvas.height() as i32;
+    for (column, posts) in patch.columns.iter().enumerate() {
+        let cx = x + column as i32;
+        if cx < 0 || cx >= canvas.pitch as i32 {
+            continue;
+        }
+        for post in posts {
+            for (row, &texel) in post.pixels.iter().enumerate() {
+                let cy = y + post.top as i32 + row as i32;
+                if (0..height).contains(&cy) {
+                    canvas.pixels[cy as usize * canvas.pitch + cx as usize] = pixel(texel);
+                }
+            }
+        }
+    }
+}
+
+/// Draws a `width` wide block of palette indices unlit with its top left corner at (`x`, `y`),
+/// clipped to the canvas. `pixels` holds the rows one after another.
+pub fn draw_raw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    x: i32,
+    y: i32,
+    width: usize,
+    pixels: &[u8],
+) {
+    let height = canvas.height() as i32;
+    for (row, texels) in pixels.chunks_exact(width).enumerate() {
+        let cy = y + row as i32;
+        if !(0..height).contains(&cy) {
+            continue;
+        }
+        for (column, &texel) in texels.iter().enumerate() {
+            let cx = x + column as i32;
+            if (0..canvas.pitch as i32).contains(&cx) {
+                canvas.pixels[cy as usize * canvas.pitch + cx as usize] = format.shade(texel, 0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::patch::Post;
+    use alloc::{vec, vec::Vec};
+
+    /// Colormaps where map `n` adds `n` to every index, so the light used is visible.
+    fn colormaps() -> Vec<u8> {
+        (0..=INVERSECOLORMAP)
+            .flat_map(|map| (0..=255u8).map(move |i| i.wrapping_add(map as u8)))
+            .collect()
+    }
+
+    #[test]
+    fn indexed_column_uses_the_light_level_colormap() {
+        let maps = colormaps();
+        let format = Indexed::new(&maps).unwrap();
+        let mut pixels = [0u8; 4];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 1,
+        };
+        let source = [10, 20, 30, 40];
+        let column = Column {
+            x: 0,
+            yl: 0,
+            yh: 3,
+            center_y: 0,
+            texture_mid: 0,
+            iscale: FRACUNIT,
+            light: 2 * FRACUNIT,
+            source: &source,
+        };
+        draw_column(&format, &mut canvas, &column);
+        assert_eq!(pixels, [12, 22, 32, 42]);
+    }
+
+    #[test]
+    fn translated_column_remaps_before_lighting() {
+        let maps = colormaps();
+        let format = Indexed::new(&maps).unwrap();
+        let mut pixels = [0u8; 2];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 1,
+        };
+        let mut translation: [u8; 256] = core::array::from_fn(|i| i as u8);
+        translation[0x70] = 0x20;
+        let source = [0x70, 0x50];
+        let column = Column {
+            x: 0,
+            yl: 0,
+            yh: 1,
+            center_y: 0,
+            texture_mid: 0,
+            iscale: FRACUNIT,
+            light: FRACUNIT,
+            source: &source,
+        };
+        draw_translated_column(&format, &mut canvas, &column, &translation);
+        assert_eq!(pixels, [0x21, 0x51]);
+    }
+
+    #[test]
+    fn true_color_light_is_smooth() {
+        let mut palette = [0; 256];
+        palette[1] = 0xffffff;
+        let format = TrueColor::new(palette);
+        assert_eq!(format.shade(1, 0), 0xffffff);
+        assert_eq!(format.shade(1, 16 * FRACUNIT), 0x7f7f7f);
+        // Half way between two vanilla colormaps still changes the result.
+        assert_ne!(format.shade(1, 16 * FRACUNIT + FRACUNIT / 2), 0x7f7f7f);
+        assert_eq!(format.shade(1, INVERSECOLORMAP as Fixed * FRACUNIT), 0);
+    }
+
+    #[test]
+    fn banding_keeps_vanilla_steps() {
+        let mut palette = [0; 256];
+        palette[1] = 0xffffff;
+        let format = TrueColor::new(palette).banded();
+        assert_eq!(format.shade(1, 16 * FRACUNIT + FRACUNIT / 2), 0x7f7f7f);
+
+        // Doubled maps where map `n` adds `n`, with the inverse map at 64.
+        let maps: Vec<u8> = (0..=2 * INVERSECOLORMAP)
+            .flat_map(|map| (0..=255u8).map(move |i| i.wrapping_add(map as u8)))
+            .collect();
+        let format = Indexed::doubled(&maps).unwrap();
+        assert_eq!(format.shade(0, 3 * FRACUNIT), 6);
+        assert_eq!(format.shade(0, 3 * FRACUNIT + FRACUNIT / 2), 7);
+        assert_eq!(format.shade(0, INVERSECOLORMAP as Fixed * FRACUNIT), 64);
+        assert_eq!(format.fuzz(0), 2 * FUZZCOLORMAP as u8);
+        assert!(Indexed::doubled(&colormaps()).is_none());
+    }
+
+    #[test]
+    fn true_color_translucency_averages_channels() {
+        let format = TrueColor::new([0; 256]);
+        assert_eq!(format.translucent(0xff0000, 0x00ff00), 0x7f7f00);
+        assert_eq!(format.translucent(0x808080, 0x808080), 0x808080);
+    }
+
+    #[test]
+    fn fuzz_never_samples_outside_the_canvas() {
+        let format = TrueColor::new([0; 256]);
+        let mut pixels = [0xffffffu32; 8];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 2,
+        };
+        Fuzz::default().draw_column(&format, &mut canvas, 0, 0, 3);
+        assert_eq!(pixels[0], 0xffffff);
+        assert_ne!(pixels[2], 0xffffff);
+        assert_eq!(pixels[6], 0xffffff);
+    }
+
+    #[test]
+    fn indexed_translucency_goes_through_the_tranmap() {
+        let maps = colormaps();
+        let tranmap = TranMap::generate(&Default::default(), 50);
+        let solid = Indexed::new(&maps).unwrap();
+        assert_eq!(solid.translucent(0, 200), 200);
+        let blended = Indexed::new(&maps).unwrap().with_tranmap(&tranmap);
+        assert_eq!(blended.translucent(0, 200), 100);
+
+        let mut pixels = [10u8, 20, 30];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 1,
+        };
+        draw_shadow_column(&blended, &mut canvas, 0, 1, 5);
+        assert_eq!(
+            pixels,
+            [10, 20 + FUZZCOLORMAP as u8, 30 + FUZZCOLORMAP as u8]
+        );
+    }
+
+    #[test]
+    fn patch_is_clipped_and_offset() {
+        let maps = colormaps();
+        let format = Indexed::new(&maps).unwrap();
+        let patch = Patch {
+            width: 2,
+            height: 2,
+            left_offset: 1,
+            top_offset: 0,
+            columns: vec![
+                vec![Post {
+                    top: 0,
+                    pixels: vec![5, 6],
+                }],
+                vec![Post {
+                    top: 1,
+                    pixels: vec![7],
+                }],
+            ],
+        };
+        let mut pixels = [0u8; 4];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 2,
+        };
+        draw_patch(&format, &mut canvas, 0, 0, &patch, 0);
+        // Column 0 lands off the left edge, column 1 at x = 0.
+        assert_eq!(pixels, [0, 0, 7, 0]);
+    }
+}
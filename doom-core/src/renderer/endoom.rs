@@ -0,0 +1,437 @@
+//! ENDOOM: the text mode screen DOS Doom left behind when it quit.
+//!
+//! The lump is the 80x25 VGA text buffer as it was: a code page 437 character and an
+//! attribute byte per cell, the attribute holding the foreground color in its low four bits,
+//! the background in the next three and blinking in the top one. On quit it is printed to the
+//! terminal with ANSI colors, or drawn in 8x16 cells for a frontend with a window to show it
+//! in, unless `show_endoom` is off.
+//!
+//! The drawn font is the public domain 8x8 one of the PC BIOS, doubled in height, for
+//! printable ASCII. The shades, blocks and box drawing lines ENDOOM screens are made of are
+//! drawn over the whole cell so they join their neighbours, with plain lines that cross where
+//! they meet rather than VGA's corners; the accented letters and symbols are left blank. The terminal gets all of code page 437 as Unicode.
+
+use super::framebuffer::{Framebuffer, Palette};
+use crate::engine::config::{Config, Configurable, Section};
+
+pub const ENDOOM_LUMP: &str = "ENDOOM";
+pub const ENDOOM_WIDTH: usize = 80;
+pub const ENDOOM_HEIGHT: usize = 25;
+pub const CELL_WIDTH: usize = 8;
+pub const CELL_HEIGHT: usize = 16;
+
+/// The 16 text mode colors, the palette `render` draws with.
+const VGA_COLORS: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0xaa],
+    [0x00, 0xaa, 0x00],
+    [0x00, 0xaa, 0xaa],
+    [0xaa, 0x00, 0x00],
+    [0xaa, 0x00, 0xaa],
+    [0xaa, 0x55, 0x00],
+    [0xaa, 0xaa, 0xaa],
+    [0x55, 0x55, 0x55],
+    [0x55, 0x55, 0xff],
+    [0x55, 0xff, 0x55],
+    [0x55, 0xff, 0xff],
+    [0xff, 0x55, 0x55],
+    [0xff, 0x55, 0xff],
+    [0xff, 0xff, 0x55],
+    [0xff, 0xff, 0xff],
+];
+
+/// VGA orders its colors blue, green, red; ANSI red, green, blue.
+const VGA_TO_ANSI: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+/// Code page 437 from 0x80 up. Below that it is ASCII, apart from the symbols in `LOW`.
+const HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+                    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+                    αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+/// 0x01 to 0x1f.
+const LOW: &str = "☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+
+/// The character a code page 437 byte shows.
+pub fn cp437(byte: u8) -> char {
+    match byte {
+        0 => ' ',
+        0x01..=0x1f => LOW.chars().nth(byte as usize - 1).unwrap_or(' '),
+        0x7f => '⌂',
+        0x80.. => HIGH.chars().nth(byte as usize - 0x80).unwrap_or(' '),
+        _ => byte as char,
+    }
+}
+
+/// `!` to `~`, eight rows each, the leftmost pixel in the lowest bit.
+const FONT: [[u8; 8]; 94] = [
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00],
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00],
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00],
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00],
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00],
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00],
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00],
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00],
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06],
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00],
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00],
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00],
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00],
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00],
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00],
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00],
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00],
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00],
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00],
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00],
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00],
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00],
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06],
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00],
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00],
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00],
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00],
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00],
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00],
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00],
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00],
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00],
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00],
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00],
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00],
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00],
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00],
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00],
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00],
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00],
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00],
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00],
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00],
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00],
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00],
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00],
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00],
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00],
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00],
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00],
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00],
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff],
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00],
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00],
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00],
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00],
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f],
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00],
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e],
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00],
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00],
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00],
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f],
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78],
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00],
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00],
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00],
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00],
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f],
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00],
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00],
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00],
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00],
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+/// The arms of the box drawing characters 0xb3 to 0xda: up, down, left and right, each
+/// none, single or double.
+const BOX: [[u8; 4]; 40] = [
+    [1, 1, 0, 0],
+    [1, 1, 1, 0],
+    [1, 1, 2, 0],
+    [2, 2, 1, 0],
+    [0, 2, 1, 0],
+    [0, 1, 2, 0],
+    [2, 2, 2, 0],
+    [2, 2, 0, 0],
+    [0, 2, 2, 0],
+    [2, 0, 2, 0],
+    [2, 0, 1, 0],
+    [1, 0, 2, 0],
+    [0, 1, 1, 0],
+    [1, 0, 0, 1],
+    [1, 0, 1, 1],
+    [0, 1, 1, 1],
+    [1, 1, 0, 1],
+    [0, 0, 1, 1],
+    [1, 1, 1, 1],
+    [1, 1, 0, 2],
+    [2, 2, 0, 1],
+    [2, 0, 0, 2],
+    [0, 2, 0, 2],
+    [2, 0, 2, 2],
+    [0, 2, 2, 2],
+    [2, 2, 0, 2],
+    [0, 0, 2, 2],
+    [2, 2, 2, 2],
+    [1, 0, 2, 2],
+    [2, 0, 1, 1],
+    [0, 1, 2, 2],
+    [0, 2, 1, 1],
+    [2, 0, 0, 1],
+    [1, 0, 0, 2],
+    [0, 1, 0, 2],
+    [0, 2, 0, 1],
+    [2, 2, 1, 1],
+    [1, 1, 2, 2],
+    [1, 0, 1, 0],
+    [0, 1, 0, 1],
+];
+
+/// The rows of a character's cell, the leftmost pixel in the highest bit.
+fn glyph(byte: u8) -> [u8; CELL_HEIGHT] {
+    let mut rows = [0; CELL_HEIGHT];
+    match byte {
+        b'!'..=b'~' => {
+            for (y, row) in rows.iter_mut().enumerate() {
+                *row = FONT[(byte - b'!') as usize][y / 2].reverse_bits();
+            }
+        }
+        0xb0..=0xb2 => {
+            let pattern = [[0x88, 0x22], [0xaa, 0x55], [0x77, 0xdd]][(byte - 0xb0) as usize];
+            for (y, row) in rows.iter_mut().enumerate() {
+                *row = pattern[y % 2];
+            }
+        }
+        0xb3..=0xda => {
+            let [up, down, left, right] = BOX[(byte - 0xb3) as usize];
+            let middle = CELL_HEIGHT / 2;
+            // Single lines run through the middle and double ones either side of it, each
+            // reaching across the middle so that lines of either kind meet.
+            let vertical = |arm: u8| [(0, 0), (0x10, 0), (0x48, 2)][arm as usize];
+            for (y, row) in rows.iter_mut().enumerate() {
+                let (bits, reach) = vertical(up);
+                if y <= middle + reach {
+                    *row |= bits;
+                }
+                let (bits, reach) = vertical(down);
+                if y + reach >= middle {
+                    *row |= bits;
+                }
+            }
+            for (arm, single, double) in [(left, 0xf0, 0xf8), (right, 0x1f, 0x7f)] {
+                match arm {
+                    1 => rows[middle] |= single,
+                    2 => {
+                        rows[middle - 2] |= double;
+                        rows[middle + 2] |= double;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        0xdb => rows = [0xff; CELL_HEIGHT],
+        0xdc => rows[CELL_HEIGHT / 2..].fill(0xff),
+        0xdd => rows = [0xf0; CELL_HEIGHT],
+        0xde => rows = [0x0f; CELL_HEIGHT],
+        0xdf => rows[..CELL_HEIGHT / 2].fill(0xff),
+        0xf9 | 0xfa => rows[7..9].fill(0x18),
+        0xfe => rows[4..12].fill(0x3c),
+        _ => {}
+    }
+    rows
+}
+
+/// The palette `render` draws with: the 16 text mode colors and black after them.
+pub fn palette() -> Palette {
+    let mut palette = Palette::from_playpal(&[0; 768], 0).unwrap_or_default();
+    for (index, rgb) in VGA_COLORS.into_iter().enumerate() {
+        palette.set_rgb(index as u8, rgb);
+    }
+    palette
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endoom {
+    /// Character and attribute pairs, row by row.
+    data: Vec<u8>,
+}
+
+impl Endoom {
+    /// Reads the lump, which must hold the whole screen.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..ENDOOM_WIDTH * ENDOOM_HEIGHT * 2)?;
+        Some(Self {
+            data: data.to_vec(),
+        })
+    }
+
+    /// The character and attribute at (`x`, `y`).
+    pub fn cell(&self, x: usize, y: usize) -> (u8, u8) {
+        let i = (y * ENDOOM_WIDTH + x) * 2;
+        (self.data[i], self.data[i + 1])
+    }
+
+    /// The screen as text, without colors or trailing spaces.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for y in 0..ENDOOM_HEIGHT {
+            let row: String = (0..ENDOOM_WIDTH)
+                .map(|x| cp437(self.cell(x, y).0))
+                .collect();
+            text += row.trim_end();
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The screen with ANSI escapes for a terminal, each row reset at its end.
+    pub fn ansi(&self) -> String {
+        let mut text = String::new();
+        for y in 0..ENDOOM_HEIGHT {
+            let mut current = None;
+            for x in 0..ENDOOM_WIDTH {
+                let (character, attribute) = self.cell(x, y);
+                if current != Some(attribute) {
+                    current = Some(attribute);
+                    let foreground = VGA_TO_ANSI[usize::from(attribute & 7)];
+                    let background = VGA_TO_ANSI[usize::from(attribute >> 4 & 7)];
+                    let bright = match attribute & 8 {
+                        0 => 30,
+                        _ => 90,
+                    };
+                    let blink = match attribute & 0x80 {
+                        0 => "",
+                        _ => ";5",
+                    };
+                    text += &format!("\x1b[0;{};{}{blink}m", bright + foreground, 40 + background);
+                }
+                text.push(cp437(character));
+            }
+            text += "\x1b[0m\n";
+        }
+        text
+    }
+
+    /// Draws the screen in `CELL_WIDTH` by `CELL_HEIGHT` cells, in the colors of `palette`.
+    /// Blinking characters are shown only while `blink_on`.
+    pub fn render(&self, blink_on: bool) -> Framebuffer {
+        let mut frame = Framebuffer::new(ENDOOM_WIDTH * CELL_WIDTH, ENDOOM_HEIGHT * CELL_HEIGHT);
+        let pitch = frame.width();
+        let pixels = frame.pixels_mut();
+        for y in 0..ENDOOM_HEIGHT {
+            for x in 0..ENDOOM_WIDTH {
+                let (character, attribute) = self.cell(x, y);
+                let foreground = attribute & 0x0f;
+                let background = attribute >> 4 & 7;
+                let hidden = attribute & 0x80 != 0 && !blink_on;
+                for (row, bits) in glyph(character).into_iter().enumerate() {
+                    let start = (y * CELL_HEIGHT + row) * pitch + x * CELL_WIDTH;
+                    for (column, pixel) in pixels[start..start + CELL_WIDTH].iter_mut().enumerate()
+                    {
+                        let set = bits & (0x80 >> column) != 0 && !hidden;
+                        *pixel = if set { foreground } else { background };
+                    }
+                }
+            }
+        }
+        frame
+    }
+}
+
+/// Whether the screen is shown on quit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndoomOptions {
+    pub show: bool,
+}
+
+impl Default for EndoomOptions {
+    fn default() -> Self {
+        Self { show: true }
+    }
+}
+
+impl Configurable for EndoomOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("show_endoom", true, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            show: config.get_bool("show_endoom"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("show_endoom", self.show);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Blue background, yellow `HI` with a blinking red box corner, then spaces.
+    fn screen() -> Endoom {
+        let mut data = [b' ', 0x07].repeat(ENDOOM_WIDTH * ENDOOM_HEIGHT);
+        data[..6].copy_from_slice(&[b'H', 0x1e, b'I', 0x1e, 0xc9, 0x84]);
+        Endoom::parse(&data).unwrap()
+    }
+
+    #[test]
+    fn the_screen_prints_with_ansi_colors() {
+        assert!(Endoom::parse(&[0; 3999]).is_none());
+        assert_eq!(HIGH.chars().count(), 128);
+        assert_eq!(LOW.chars().count(), 31);
+        assert_eq!((cp437(0xdb), cp437(0x03), cp437(b'A')), ('█', '♥', 'A'));
+
+        let screen = screen();
+        assert_eq!(screen.cell(1, 0), (b'I', 0x1e));
+        assert_eq!(screen.text().lines().next(), Some("HI╔"));
+        let first = screen.ansi();
+        let first = first.lines().next().unwrap();
+        // Yellow is bright brown, which ANSI calls yellow; blue is 4 in ANSI.
+        assert!(first.starts_with("\x1b[0;93;44mHI\x1b[0;31;40;5m╔\x1b[0;37;40m "));
+        assert!(first.ends_with(" \x1b[0m"));
+    }
+
+    #[test]
+    fn the_screen_draws_with_the_bios_font() {
+        let screen = screen();
+        let frame = screen.render(true);
+        assert_eq!((frame.width(), frame.height()), (640, 400));
+        // `H` on a blue background, each row of the BIOS font drawn twice.
+        assert_eq!(&frame.row(0)[..8], &[14, 14, 1, 1, 14, 14, 1, 1]);
+        assert_eq!(&frame.row(1)[..8], &[14, 14, 1, 1, 14, 14, 1, 1]);
+        // The double corner's lines cross where they meet.
+        let corner = |frame: &Framebuffer, y: usize| frame.row(y)[16..24].to_vec();
+        assert_eq!(corner(&frame, 5), vec![0; 8]);
+        assert_eq!(corner(&frame, 6), vec![0, 4, 4, 4, 4, 4, 4, 4]);
+        assert_eq!(corner(&frame, 8), vec![0, 4, 0, 0, 4, 0, 0, 0]);
+        assert_eq!(corner(&frame, 10), vec![0, 4, 4, 4, 4, 4, 4, 4]);
+        assert_eq!(corner(&screen.render(false), 8), vec![0; 8]);
+        assert_eq!(palette().rgb(14), [0xff, 0xff, 0x55]);
+
+        let mut config = Config::new();
+        EndoomOptions::register(&mut config);
+        assert!(EndoomOptions::from_config(&config).show);
+        EndoomOptions { show: false }.to_config(&mut config);
+        assert!(!EndoomOptions::from_config(&config).show);
+    }
+}
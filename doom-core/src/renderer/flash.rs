@@ -0,0 +1,269 @@
+//! Screen flashes: the palette `ST_doPaletteStuff` picks, and gentler ways to show the same.
+//!
+//! Vanilla tints the whole screen by switching PLAYPAL palettes: red when hurt or berserk,
+//! gold on picking something up, green in a radiation suit. For players who find that hard
+//! to look at, `screen_flashes` can cap the tint at a few steps (`reduced`), draw it as a
+//! colored frame around the edge of the screen instead (`border`), or leave it out (`off`).
+//! `steady_lights` draws sectors with blinking, strobing or glowing lights at their steady
+//! level. Both are only in how the frame is drawn, so demos and netgames are unaffected.
+
+use super::draw::{Canvas, PixelFormat};
+use super::framebuffer::SCREENWIDTH;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::cheat::Power;
+use crate::game::player::NUMPOWERS;
+
+pub const STARTREDPALS: usize = 1;
+pub const NUMREDPALS: usize = 8;
+pub const STARTBONUSPALS: usize = 9;
+pub const NUMBONUSPALS: usize = 4;
+pub const RADIATIONPAL: usize = 13;
+/// The strongest red and gold steps the `reduced` style shows.
+const REDUCED_RED: usize = 3;
+const REDUCED_BONUS: usize = 1;
+/// How much thicker the border gets per step, in 320x200 pixels.
+const BORDER_STEP: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlashKind {
+    Damage,
+    Bonus,
+    Radiation,
+}
+
+impl FlashKind {
+    /// The palette index the border is drawn in.
+    fn color(self) -> u8 {
+        match self {
+            FlashKind::Damage => 0xb0,
+            FlashKind::Bonus => 0xa0,
+            FlashKind::Radiation => 0x70,
+        }
+    }
+}
+
+/// A tint, and how strong: 1 up to one less than the number of palettes its kind has. As in
+/// vanilla, the first red and gold palettes are never used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flash {
+    pub kind: FlashKind,
+    pub step: usize,
+}
+
+impl Flash {
+    /// The tint `ST_doPaletteStuff` shows for the player's damage and bonus counts and
+    /// powers. Berserk fades out of red over its first seconds.
+    pub fn vanilla(damage_count: i32, bonus_count: i32, powers: &[i32; NUMPOWERS]) -> Option<Self> {
+        let mut count = damage_count;
+        let strength = powers[Power::Strength as usize];
+        if strength != 0 {
+            count = count.max(12 - (strength >> 6));
+        }
+        let step = |count: i32, steps: usize| (((count + 7) >> 3) as usize).min(steps - 1);
+        let ironfeet = powers[Power::IronFeet as usize];
+        if count > 0 {
+            Some(Flash {
+                kind: FlashKind::Damage,
+                step: step(count, NUMREDPALS),
+            })
+        } else if bonus_count > 0 {
+            Some(Flash {
+                kind: FlashKind::Bonus,
+                step: step(bonus_count, NUMBONUSPALS),
+            })
+        } else if ironfeet > 4 * 32 || ironfeet & 8 != 0 {
+            Some(Flash {
+                kind: FlashKind::Radiation,
+                step: 1,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The PLAYPAL palette that shows it.
+    pub fn palette(self) -> usize {
+        match self.kind {
+            FlashKind::Damage => STARTREDPALS + self.step,
+            FlashKind::Bonus => STARTBONUSPALS + self.step,
+            FlashKind::Radiation => RADIATIONPAL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlashStyle {
+    #[default]
+    Full,
+    Reduced,
+    Border,
+    Off,
+}
+
+impl FlashStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            FlashStyle::Full => "full",
+            FlashStyle::Reduced => "reduced",
+            FlashStyle::Border => "border",
+            FlashStyle::Off => "off",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlashOptions {
+    pub style: FlashStyle,
+    pub steady_lights: bool,
+}
+
+impl FlashOptions {
+    /// The palette to show with `flash` under way.
+    pub fn palette(&self, flash: Option<Flash>) -> usize {
+        let Some(flash) = flash else {
+            return 0;
+        };
+        match (self.style, flash.kind) {
+            (FlashStyle::Full, _) => flash.palette(),
+            (FlashStyle::Reduced, FlashKind::Damage) => Flash {
+                step: flash.step.min(REDUCED_RED),
+                ..flash
+            }
+            .palette(),
+            (FlashStyle::Reduced, FlashKind::Bonus) => Flash {
+                step: flash.step.min(REDUCED_BONUS),
+                ..flash
+            }
+            .palette(),
+            (FlashStyle::Reduced, FlashKind::Radiation) => flash.palette(),
+            (FlashStyle::Border | FlashStyle::Off, _) => 0,
+        }
+    }
+
+    /// The flash to draw with `draw_border`, when that is the style.
+    pub fn border(&self, flash: Option<Flash>) -> Option<Flash> {
+        flash.filter(|_| self.style == FlashStyle::Border)
+    }
+
+    /// The light level to draw a sector at. `steady` is the level its light effect, if it has
+    /// one, holds when not changing: the bright level of a blinking or strobing light.
+    pub fn shown_light(&self, level: i16, steady: Option<i16>) -> i16 {
+        match steady {
+            Some(steady) if self.steady_lights => steady,
+            _ => level,
+        }
+    }
+}
+
+impl Configurable for FlashOptions {
+    fn register(config: &mut Config) {
+        config.register_str("screen_flashes", "full", Section::Extended);
+        config.register_bool("steady_lights", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            style: match config.get_str("screen_flashes").as_str() {
+                "reduced" => FlashStyle::Reduced,
+                "border" => FlashStyle::Border,
+                "off" => FlashStyle::Off,
+                _ => FlashStyle::Full,
+            },
+            steady_lights: config.get_bool("steady_lights"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("screen_flashes", self.style.name());
+        config.set_bool("steady_lights", self.steady_lights);
+    }
+}
+
+/// Draws `flash` as a see-through frame around the edge of the canvas, thicker the stronger
+/// it is.
+pub fn draw_border<F: PixelFormat>(format: &F, canvas: &mut Canvas<F::Pixel>, flash: Flash) {
+    let (width, height) = (canvas.pitch, canvas.height());
+    let thickness = (flash.step * BORDER_STEP * width / SCREENWIDTH).min(height / 2);
+    let color = format.shade(flash.kind.color(), 0);
+    for y in 0..height {
+        let edge_row = y < thickness || y >= height - thickness;
+        let row = &mut canvas.pixels[y * width..][..width];
+        for (x, pixel) in row.iter_mut().enumerate() {
+            if edge_row || x < thickness || x >= width - thickness {
+                *pixel = format.translucent(*pixel, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+
+    #[test]
+    fn palettes_are_picked_like_vanilla() {
+        let mut powers = [0; NUMPOWERS];
+        assert_eq!(Flash::vanilla(0, 0, &powers), None);
+        let hurt = Flash::vanilla(100, 6, &powers).unwrap();
+        assert_eq!((hurt.kind, hurt.palette()), (FlashKind::Damage, 8));
+        assert_eq!(Flash::vanilla(0, 6, &powers).unwrap().palette(), 10);
+        powers[Power::IronFeet as usize] = 8;
+        assert_eq!(
+            Flash::vanilla(0, 0, &powers).unwrap().palette(),
+            RADIATIONPAL
+        );
+        powers[Power::IronFeet as usize] = 7;
+        assert_eq!(Flash::vanilla(0, 0, &powers), None, "blinking out");
+        // Berserk starts out fully red and fades over 12 << 6 tics.
+        powers[Power::Strength as usize] = 1;
+        assert_eq!(Flash::vanilla(0, 0, &powers).unwrap().step, 2);
+        powers[Power::Strength as usize] = 12 << 6;
+        assert_eq!(
+            Flash::vanilla(0, 9, &powers).unwrap().kind,
+            FlashKind::Bonus
+        );
+    }
+
+    #[test]
+    fn the_styles_cap_or_replace_the_palette() {
+        let hurt = Flash::vanilla(100, 0, &[0; NUMPOWERS]);
+        let style = |style| FlashOptions {
+            style,
+            ..FlashOptions::default()
+        };
+        assert_eq!(style(FlashStyle::Full).palette(hurt), 8);
+        assert_eq!(style(FlashStyle::Reduced).palette(hurt), 4);
+        assert_eq!(style(FlashStyle::Border).palette(hurt), 0);
+        assert_eq!(style(FlashStyle::Border).border(hurt), hurt);
+        assert_eq!(style(FlashStyle::Off).border(hurt), None);
+
+        let steady = FlashOptions {
+            steady_lights: true,
+            ..FlashOptions::default()
+        };
+        assert_eq!(steady.shown_light(96, Some(200)), 200);
+        assert_eq!(steady.shown_light(96, None), 96);
+        assert_eq!(FlashOptions::default().shown_light(96, Some(200)), 96);
+    }
+
+    #[test]
+    fn the_border_grows_with_the_flash() {
+        let format = TrueColor::new(std::array::from_fn(|i| (i as u32) << 1));
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        let flash = Flash {
+            kind: FlashKind::Damage,
+            step: 3,
+        };
+        draw_border(&format, &mut canvas, flash);
+        let at = |x: usize, y: usize| pixels[y * 320 + x];
+        assert_ne!(at(5, 100), 0);
+        assert_ne!(at(160, 199), 0);
+        assert_eq!(at(6, 100), 0);
+        assert_eq!(at(160, 100), 0);
+    }
+}
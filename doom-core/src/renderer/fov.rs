@@ -0,0 +1,155 @@
+//! The field of view and the angle tables that follow from it.
+//!
+//! Vanilla's 90 degrees is built into `R_InitTextureMapping`: the focal length, the column
+//! each view angle lands on (`viewangletox`), the angle each column looks along
+//! (`xtoviewangle`) and the widest angle still on screen (`clipangle`). All of them are made
+//! again from the projection whenever the field of view or the view window changes.
+//!
+//! What the player can see decides what they aim at, and vanilla's autoaim is tuned to what
+//! a 90 degree view shows. Recording a demo at the vanilla complevel holds the field of view
+//! there, so the demo is played the way its format assumes.
+
+use super::projection::Projection;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::compatibility::CompLevel;
+use crate::util::angle::{Angle, ANG90};
+use crate::util::fixed::FRACUNIT;
+
+pub const DEFAULT_FOV: u32 = 90;
+pub const MIN_FOV: u32 = 75;
+pub const MAX_FOV: u32 = 120;
+/// Vanilla's `FINEANGLES`: the fine angle tables have this many entries for a full turn.
+pub const FINEANGLES: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldOfView {
+    pub degrees: u32,
+}
+
+impl Default for FieldOfView {
+    fn default() -> Self {
+        Self {
+            degrees: DEFAULT_FOV,
+        }
+    }
+}
+
+impl FieldOfView {
+    pub fn new(degrees: u32) -> Self {
+        Self {
+            degrees: degrees.clamp(MIN_FOV, MAX_FOV),
+        }
+    }
+
+    /// The field of view to play with: 90 while recording a vanilla demo.
+    pub fn held(self, recording: bool, level: CompLevel) -> Self {
+        if recording && level == CompLevel::Vanilla {
+            Self::default()
+        } else {
+            self
+        }
+    }
+}
+
+impl Configurable for FieldOfView {
+    fn register(config: &mut Config) {
+        config.register_int("fov", DEFAULT_FOV as i32, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self::new(config.get_int("fov").max(0) as u32)
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("fov", self.degrees as i32);
+    }
+}
+
+/// Radians to a binary angle.
+fn to_angle(radians: f64) -> Angle {
+    (radians / std::f64::consts::TAU * 4_294_967_296.0).round() as i64 as Angle
+}
+
+/// The tables `R_InitTextureMapping` makes, for one projection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViewAngles {
+    /// For the fine angles of the front half circle, from 90 degrees left of the view to 90
+    /// right, the column they land on, clamped to `0..=width`.
+    pub view_angle_to_x: Vec<i32>,
+    /// For every column and the edge past the last, the angle from the view direction.
+    pub x_to_view_angle: Vec<Angle>,
+    /// The angle of the left edge; anything further out either way is off screen.
+    pub clip_angle: Angle,
+}
+
+impl ViewAngles {
+    pub fn new(projection: &Projection) -> Self {
+        let focal = f64::from(projection.projection) / f64::from(FRACUNIT);
+        let center = f64::from(projection.center_x_frac) / f64::from(FRACUNIT);
+        let width = projection.width as i32;
+        let step = std::f64::consts::TAU / FINEANGLES as f64;
+        let view_angle_to_x = (0..FINEANGLES / 2)
+            .map(|fine| {
+                // Vanilla's `finetangent` samples the middle of each fine angle.
+                let tangent = ((fine as f64 + 0.5) * step - std::f64::consts::FRAC_PI_2).tan();
+                let x = (center - tangent * focal).ceil();
+                x.clamp(0.0, f64::from(width)) as i32
+            })
+            .collect();
+        let x_to_view_angle: Vec<Angle> = (0..=width)
+            .map(|x| to_angle(((center - f64::from(x)) / focal).atan()))
+            .collect();
+        Self {
+            view_angle_to_x,
+            clip_angle: x_to_view_angle[0],
+            x_to_view_angle,
+        }
+    }
+
+    /// The column an angle from the view direction lands on, for angles within 90 degrees
+    /// of it either way.
+    pub fn angle_to_x(&self, angle: Angle) -> i32 {
+        let fine = angle.wrapping_add(ANG90) >> 19;
+        self.view_angle_to_x[(fine as usize).min(FINEANGLES / 2 - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+    use crate::util::angle::ANG45;
+
+    #[test]
+    fn vanilla_tables_span_ninety_degrees() {
+        let projection = Projection::new(SCREENWIDTH, SCREENHEIGHT);
+        let angles = ViewAngles::new(&projection);
+        assert_eq!(angles.x_to_view_angle.len(), SCREENWIDTH + 1);
+        assert_eq!(angles.clip_angle, ANG45);
+        assert_eq!(angles.x_to_view_angle[160], 0);
+        assert_eq!(angles.x_to_view_angle[320], ANG45.wrapping_neg());
+        assert_eq!(angles.angle_to_x(0), 160);
+        assert_eq!(angles.angle_to_x(ANG45), 0);
+        assert_eq!(angles.angle_to_x(ANG45.wrapping_neg()), 320);
+    }
+
+    #[test]
+    fn wider_views_see_further_round() {
+        let projection = Projection::new(SCREENWIDTH, SCREENHEIGHT).with_fov(120);
+        let angles = ViewAngles::new(&projection);
+        let sixty = ANG90 / 3 * 2;
+        assert!(angles.clip_angle.abs_diff(sixty) < 1 << 20);
+        assert_eq!(angles.angle_to_x(ANG45), 68);
+
+        assert_eq!(FieldOfView::new(200).degrees, MAX_FOV);
+        let wide = FieldOfView::new(110);
+        assert_eq!(wide.held(true, CompLevel::Vanilla), FieldOfView::default());
+        assert_eq!(wide.held(true, CompLevel::Boom), wide);
+        assert_eq!(wide.held(false, CompLevel::Vanilla), wide);
+
+        let mut config = Config::new();
+        FieldOfView::register(&mut config);
+        wide.to_config(&mut config);
+        assert_eq!(FieldOfView::from_config(&config), wide);
+    }
+}
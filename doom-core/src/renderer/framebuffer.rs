@@ -0,0 +1,147 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Width of the vanilla software renderer's output, in pixels.
+pub const SCREENWIDTH: usize = 320;
+/// Height of the vanilla software renderer's output, in pixels.
+pub const SCREENHEIGHT: usize = 200;
+
+/// Number of bytes taken by a single palette inside the PLAYPAL lump.
+pub const PALETTE_BYTES: usize = 256 * 3;
+
+/// The 8-bit indexed frame the software renderer draws into.
+///
+/// Every byte is an index into the currently active `Palette`. The buffer is laid out row by
+/// row with no padding, so `pixels()[y * width + x]` is the pixel at `(x, y)`. Converting the
+/// indices to real colors is left to the presentation stage, which lets the palette change
+/// (damage flashes, gamma) without touching the rendered frame.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn fill(&mut self, index: u8) {
+        self.pixels.fill(index);
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new(SCREENWIDTH, SCREENHEIGHT)
+    }
+}
+
+/// One of the 14 palettes stored in PLAYPAL, expanded to RGB triples.
+#[derive(Clone)]
+pub struct Palette {
+    colors: [[u8; 3]; 256],
+}
+
+impl Palette {
+    /// Reads palette number `index` out of a raw PLAYPAL lump.
+    pub fn from_playpal(playpal: &[u8], index: usize) -> Option<Self> {
+        let start = index * PALETTE_BYTES;
+        let bytes = playpal.get(start..start + PALETTE_BYTES)?;
+        let mut colors = [[0; 3]; 256];
+        for (color, rgb) in colors.iter_mut().zip(bytes.chunks_exact(3)) {
+            color.copy_from_slice(rgb);
+        }
+        Some(Self { colors })
+    }
+
+    pub fn rgb(&self, index: u8) -> [u8; 3] {
+        self.colors[index as usize]
+    }
+
+    pub fn set_rgb(&mut self, index: u8, rgb: [u8; 3]) {
+        self.colors[index as usize] = rgb;
+    }
+
+    /// The index whose color is closest to `rgb`, for building lookup tables.
+    pub fn nearest(&self, rgb: [i32; 3]) -> u8 {
+        let distance = |color: &[u8; 3]| {
+            (0..3)
+                .map(|c| (i32::from(color[c]) - rgb[c]).pow(2))
+                .sum::<i32>()
+        };
+        let closest = self
+            .colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, color)| distance(color));
+        closest.map_or(0, |(index, _)| index as u8)
+    }
+
+    /// Packs the palette as `0x00RRGGBB` words, the layout the presenters upload.
+    pub fn to_rgb32(&self) -> [u32; 256] {
+        let mut packed = [0; 256];
+        for (word, [r, g, b]) in packed.iter_mut().zip(self.colors) {
+            *word = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+        }
+        packed
+    }
+}
+
+impl Default for Palette {
+    /// A greyscale ramp, used until PLAYPAL has been read.
+    fn default() -> Self {
+        let mut colors = [[0; 3]; 256];
+        for (i, color) in colors.iter_mut().enumerate() {
+            *color = [i as u8; 3];
+        }
+        Self { colors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_framebuffer_is_vanilla_resolution() {
+        let frame = Framebuffer::default();
+        assert_eq!(frame.width(), 320);
+        assert_eq!(frame.height(), 200);
+        assert_eq!(frame.pixels().len(), 320 * 200);
+    }
+
+    #[test]
+    fn palette_is_read_from_the_requested_offset() {
+        let mut playpal = vec![0; PALETTE_BYTES * 2];
+        playpal[PALETTE_BYTES + 3..PALETTE_BYTES + 6].copy_from_slice(&[10, 20, 30]);
+        let palette = Palette::from_playpal(&playpal, 1).unwrap();
+        assert_eq!(palette.rgb(1), [10, 20, 30]);
+        assert_eq!(palette.to_rgb32()[1], 0x000a141e);
+        assert!(Palette::from_playpal(&playpal, 2).is_none());
+    }
+}
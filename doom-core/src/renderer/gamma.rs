@@ -0,0 +1,192 @@
+//! Gamma correction, applied where palette indices become RGB.
+//!
+//! The five vanilla levels (F11) are the original `gammatable`, which id generated as
+//! `255 * ((i + 1) / 256) ^ (1 - level / 8)`; computing it reproduces every entry. On top
+//! of that, continuous gamma, brightness and contrast settings give the true-color path
+//! finer control than five steps. Their defaults are neutral, so an untouched config
+//! looks exactly like vanilla.
+
+use super::framebuffer::Palette;
+use crate::engine::config::{Config, Configurable, Section};
+
+pub const GAMMA_LEVELS: usize = 5;
+
+/// The `strings` entries vanilla shows when F11 changes the level.
+pub const GAMMA_MESSAGES: [&str; GAMMA_LEVELS] = [
+    "GAMMALVL0",
+    "GAMMALVL1",
+    "GAMMALVL2",
+    "GAMMALVL3",
+    "GAMMALVL4",
+];
+
+pub type GammaTable = [u8; 256];
+
+/// `gammatable[level]`.
+pub fn vanilla_table(level: usize) -> GammaTable {
+    let exponent = 1.0 - level.min(GAMMA_LEVELS - 1) as f64 / 8.0;
+    std::array::from_fn(|i| {
+        let value = 255.0 * ((i + 1) as f64 / 256.0).powf(exponent);
+        (value + 0.5).min(255.0) as u8
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GammaOptions {
+    pub level: usize,    // 0 to 4, vanilla's `usegamma`.
+    pub gamma: i32,      // Percent: 100 is unchanged, higher is brighter.
+    pub brightness: i32, // -100 to 100, added after contrast.
+    pub contrast: i32,   // Percent around mid grey.
+}
+
+impl Default for GammaOptions {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            gamma: 100,
+            brightness: 0,
+            contrast: 100,
+        }
+    }
+}
+
+impl GammaOptions {
+    /// F11: steps to the next level, wrapping to off, and returns the message to show,
+    /// as a `strings` entry name.
+    pub fn cycle(&mut self) -> &'static str {
+        self.level = (self.level + 1) % GAMMA_LEVELS;
+        GAMMA_MESSAGES[self.level]
+    }
+
+    /// The vanilla level followed by the continuous settings, as one lookup table.
+    pub fn table(&self) -> GammaTable {
+        let vanilla = vanilla_table(self.level);
+        if (self.gamma, self.brightness, self.contrast) == (100, 0, 100) {
+            return vanilla;
+        }
+        let exponent = 100.0 / self.gamma.max(10) as f64;
+        let contrast = self.contrast.max(0) as f64 / 100.0;
+        let brightness = self.brightness.clamp(-100, 100) as f64 / 100.0;
+        vanilla.map(|value| {
+            let v = (value as f64 / 255.0).powf(exponent);
+            let v = (v - 0.5) * contrast + 0.5 + brightness;
+            (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+        })
+    }
+}
+
+impl Configurable for GammaOptions {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_int("usegamma", defaults.level as i32, Section::Vanilla);
+        config.register_int("video_gamma", defaults.gamma, Section::Extended);
+        config.register_int("video_brightness", defaults.brightness, Section::Extended);
+        config.register_int("video_contrast", defaults.contrast, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            level: (config.get_int("usegamma").max(0) as usize).min(GAMMA_LEVELS - 1),
+            gamma: config.get_int("video_gamma"),
+            brightness: config.get_int("video_brightness"),
+            contrast: config.get_int("video_contrast"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("usegamma", self.level as i32);
+        config.set_int("video_gamma", self.gamma);
+        config.set_int("video_brightness", self.brightness);
+        config.set_int("video_contrast", self.contrast);
+    }
+}
+
+/// Runs each channel of a `0x00RRGGBB` pixel through `table`, for true-color frames.
+pub fn correct_rgb32(table: &GammaTable, color: u32) -> u32 {
+    let r = table[(color >> 16 & 0xff) as usize] as u32;
+    let g = table[(color >> 8 & 0xff) as usize] as u32;
+    let b = table[(color & 0xff) as usize] as u32;
+    r << 16 | g << 8 | b
+}
+
+impl Palette {
+    /// The palette as it should be shown with `table` applied.
+    pub fn corrected(&self, table: &GammaTable) -> Palette {
+        let mut palette = self.clone();
+        for index in 0..=255 {
+            palette.set_rgb(index, self.rgb(index).map(|c| table[c as usize]));
+        }
+        palette
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_match_the_original() {
+        let off = vanilla_table(0);
+        assert_eq!(&off[..4], &[1, 2, 3, 4]);
+        assert_eq!(off[255], 255);
+        assert_eq!(&vanilla_table(1)[..8], &[2, 4, 5, 7, 8, 10, 11, 12]);
+        assert_eq!(&vanilla_table(4)[..8], &[16, 23, 28, 32, 36, 39, 42, 45]);
+        assert_eq!(vanilla_table(4)[128], 181);
+        assert_eq!(vanilla_table(4)[255], 255);
+    }
+
+    #[test]
+    fn f11_cycles_and_wraps() {
+        let mut options = GammaOptions {
+            level: 3,
+            ..GammaOptions::default()
+        };
+        assert_eq!(options.cycle(), "GAMMALVL4");
+        assert_eq!(options.cycle(), "GAMMALVL0");
+        assert_eq!(options.level, 0);
+    }
+
+    #[test]
+    fn continuous_settings() {
+        let neutral = GammaOptions::default();
+        assert_eq!(neutral.table(), vanilla_table(0));
+        let bright = GammaOptions {
+            gamma: 150,
+            ..GammaOptions::default()
+        };
+        assert!(bright.table()[64] > vanilla_table(0)[64]);
+        let flat = GammaOptions {
+            contrast: 0,
+            ..GammaOptions::default()
+        };
+        assert!(flat.table().iter().all(|&v| v == 128));
+        let dark = GammaOptions {
+            brightness: -100,
+            ..GammaOptions::default()
+        };
+        assert_eq!(dark.table()[200], 0);
+    }
+
+    #[test]
+    fn correction_applies_to_palettes_and_pixels() {
+        let table = vanilla_table(4);
+        let palette = Palette::default().corrected(&table);
+        assert_eq!(palette.rgb(1), [23; 3]);
+        assert_eq!(correct_rgb32(&table, 0x000100), 0x101710);
+    }
+
+    #[test]
+    fn options_round_trip_through_config() {
+        let mut config = Config::new();
+        GammaOptions::register(&mut config);
+        assert_eq!(GammaOptions::from_config(&config), GammaOptions::default());
+        let options = GammaOptions {
+            level: 2,
+            contrast: 120,
+            ..GammaOptions::default()
+        };
+        options.to_config(&mut config);
+        assert_eq!(config.get_int("usegamma"), 2);
+        assert_eq!(GammaOptions::from_config(&config), options);
+    }
+}
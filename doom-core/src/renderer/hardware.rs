@@ -0,0 +1,259 @@
+use super::atlas::{Atlas, AtlasRect};
+use super::{Renderer, ViewPoint};
+use crate::engine::profile::{self, Subsystem};
+use crate::wad::map::{MapData, SideDef, NO_SIDEDEF};
+use std::collections::BTreeMap;
+
+/// A vertex as handed to the GPU. World units, with `z` pointing up like in the map format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2], // Texel coordinates inside the texture; the shader wraps them.
+    pub tile: [f32; 4], // The texture's rectangle in the atlas, normalized (x, y, w, h).
+    pub light: f32,   // Sector light level as a 0..1 vertex color.
+}
+
+/// All triangles that sample from the same texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawBatch {
+    pub texture: String,
+    pub vertices: Vec<GpuVertex>,
+}
+
+/// The device specific part of the hardware renderer (OpenGL, wgpu). None is built yet, so
+/// `HardwareRenderer` only gets as far as the batches it would hand one.
+pub trait GpuBackend {
+    fn resize(&mut self, width: usize, height: usize);
+    /// Draws the frame's triangle lists from `view`, with depth testing.
+    fn draw(&mut self, view: &ViewPoint, batches: &[DrawBatch]);
+}
+
+/// One textured wall section before it is split into triangles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WallQuad {
+    pub texture: String,
+    /// Bottom left, bottom right, top right, top left, seen from the front.
+    pub corners: [[f32; 3]; 4],
+    pub u: [f32; 2],
+    pub v: [f32; 2], // Top, bottom.
+    pub light: f32,
+}
+
+/// Builds the wall geometry of every sidedef in the map.
+///
+/// One-sided lines get their middle texture from floor to ceiling; the two sides of a two-sided
+/// line get the lower texture below the higher floor and the upper texture above the lower
+/// ceiling. Missing textures (`-`) are skipped.
+pub fn build_walls(map: &MapData) -> Vec<WallQuad> {
+    let mut quads = Vec::new();
+    for line in &map.linedefs {
+        let front = &map.sidedefs[line.front as usize];
+        let back = (line.back != NO_SIDEDEF).then(|| &map.sidedefs[line.back as usize]);
+        let (v1, v2) = (
+            map.vertexes[line.v1 as usize],
+            map.vertexes[line.v2 as usize],
+        );
+        let start = [v1.x as f32, v1.y as f32];
+        let end = [v2.x as f32, v2.y as f32];
+
+        match back {
+            None => {
+                let sector = &map.sectors[front.sector as usize];
+                let (floor, ceiling) = (sector.floor_height, sector.ceiling_height);
+                quads.extend(wall_quad(
+                    front,
+                    &front.middle,
+                    start,
+                    end,
+                    (floor, ceiling),
+                    sector.light,
+                ));
+            }
+            Some(back) => {
+                let sides = [(front, back, start, end), (back, front, end, start)];
+                for (side, other, start, end) in sides {
+                    let sector = &map.sectors[side.sector as usize];
+                    let other_sector = &map.sectors[other.sector as usize];
+                    if other_sector.floor_height > sector.floor_height {
+                        let (bottom, top) = (sector.floor_height, other_sector.floor_height);
+                        quads.extend(wall_quad(
+                            side,
+                            &side.lower,
+                            start,
+                            end,
+                            (bottom, top),
+                            sector.light,
+                        ));
+                    }
+                    if other_sector.ceiling_height < sector.ceiling_height {
+                        let (bottom, top) = (other_sector.ceiling_height, sector.ceiling_height);
+                        quads.extend(wall_quad(
+                            side,
+                            &side.upper,
+                            start,
+                            end,
+                            (bottom, top),
+                            sector.light,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    quads
+}
+
+fn wall_quad(
+    side: &SideDef,
+    texture: &str,
+    start: [f32; 2],
+    end: [f32; 2],
+    (bottom, top): (i16, i16),
+    light: i16,
+) -> Option<WallQuad> {
+    if texture.is_empty() || texture == "-" || top <= bottom {
+        return None;
+    }
+    let (bottom, top) = (bottom as f32, top as f32);
+    let length = (end[0] - start[0]).hypot(end[1] - start[1]);
+    let (u, v) = (side.x_offset as f32, side.y_offset as f32);
+    Some(WallQuad {
+        texture: texture.to_string(),
+        corners: [
+            [start[0], start[1], bottom],
+            [end[0], end[1], bottom],
+            [end[0], end[1], top],
+            [start[0], start[1], top],
+        ],
+        u: [u, u + length],
+        v: [v, v + top - bottom],
+        light: (light.clamp(0, 255) as f32) / 255.0,
+    })
+}
+
+/// Renderer that draws the level as triangles on the GPU instead of column by column.
+///
+/// Geometry is rebuilt from the sectors every frame, so moving floors and ceilings need no
+/// special handling. Flats and sprites are not drawn yet: flats require subsector polygons
+/// from the node builder output, sprites require the thing list of the running level.
+pub struct HardwareRenderer<B: GpuBackend> {
+    backend: B,
+    atlas: Atlas,
+}
+
+impl<B: GpuBackend> HardwareRenderer<B> {
+    pub fn new(backend: B, atlas: Atlas) -> Self {
+        Self { backend, atlas }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Groups the wall quads into one triangle list per texture. Walls whose texture is not in
+    /// the atlas are dropped.
+    pub fn batches(&self, map: &MapData) -> Vec<DrawBatch> {
+        let mut batches: BTreeMap<String, Vec<GpuVertex>> = BTreeMap::new();
+        for quad in build_walls(map) {
+            let Some(rect) = self.atlas.get(&quad.texture) else {
+                continue;
+            };
+            let tile = self.normalize(rect);
+            let uvs = [
+                [quad.u[0], quad.v[1]],
+                [quad.u[1], quad.v[1]],
+                [quad.u[1], quad.v[0]],
+                [quad.u[0], quad.v[0]],
+            ];
+            let vertex = |i: usize| GpuVertex {
+                position: quad.corners[i],
+                uv: uvs[i],
+                tile,
+                light: quad.light,
+            };
+            batches
+                .entry(quad.texture.clone())
+                .or_default()
+                .extend([0, 1, 2, 0, 2, 3].map(vertex));
+        }
+        batches
+            .into_iter()
+            .map(|(texture, vertices)| DrawBatch { texture, vertices })
+            .collect()
+    }
+
+    fn normalize(&self, rect: AtlasRect) -> [f32; 4] {
+        let size = self.atlas.size() as f32;
+        [
+            rect.x as f32 / size,
+            rect.y as f32 / size,
+            rect.width as f32 / size,
+            rect.height as f32 / size,
+        ]
+    }
+}
+
+impl<B: GpuBackend> Renderer for HardwareRenderer<B> {
+    fn resize(&mut self, width: usize, height: usize) {
+        self.backend.resize(width, height);
+    }
+
+    fn render(&mut self, map: &MapData, view: &ViewPoint) {
+        let batches = {
+            let _scope = profile::scope(Subsystem::Walls);
+            self.batches(map)
+        };
+        let _scope = profile::scope(Subsystem::Present);
+        self.backend.draw(view, &batches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::map::tests::two_room_map;
+
+    #[derive(Default)]
+    struct RecordingBackend {
+        frames: Vec<Vec<DrawBatch>>,
+    }
+
+    impl GpuBackend for RecordingBackend {
+        fn resize(&mut self, _width: usize, _height: usize) {}
+        fn draw(&mut self, _view: &ViewPoint, batches: &[DrawBatch]) {
+            self.frames.push(batches.to_vec());
+        }
+    }
+
+    #[test]
+    fn walls_follow_sector_heights() {
+        let walls = build_walls(&two_room_map());
+        // One middle wall, one lower step and one upper wall on the two-sided line.
+        assert_eq!(walls.len(), 3);
+        assert_eq!(walls[0].texture, "STARTAN3");
+        assert_eq!(walls[0].corners[2][2], 128.0);
+        assert_eq!(walls[1].texture, "STEP1");
+        assert_eq!(
+            (walls[1].corners[0][2], walls[1].corners[2][2]),
+            (0.0, 32.0)
+        );
+        assert_eq!(walls[2].corners[0][2], 96.0);
+        assert!((walls[0].light - 160.0 / 255.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn renders_one_batch_per_texture() {
+        let atlas = Atlas::pack(
+            256,
+            &[("STARTAN3".into(), 128, 128), ("STEP1".into(), 32, 8)],
+        )
+        .unwrap();
+        let mut renderer = HardwareRenderer::new(RecordingBackend::default(), atlas);
+        renderer.render(&two_room_map(), &ViewPoint::default());
+        let frame = &renderer.backend().frames[0];
+        assert_eq!(frame.len(), 2);
+        assert_eq!(frame[0].texture, "STARTAN3");
+        assert_eq!(frame[0].vertices.len(), 12);
+        assert_eq!(frame[1].vertices[0].tile[2], 32.0 / 256.0);
+    }
+}
@@ -0,0 +1,307 @@
+//! PNG replacements turned into something the renderer draws.
+//!
+//! A replacement can be any size. When it replaces a lump it takes that lump's place on
+//! screen: a 640x400 `TITLEPIC` covers the same 320x200 as the original, at twice the detail.
+//! In indexed mode that detail has nowhere to go, so the image is sampled down to the
+//! original's size and every pixel mapped to the nearest palette color, giving a `Patch` the
+//! rest of the renderer takes as it is. In true color `HiResPatch::draw` puts the image's own
+//! pixels on a canvas scaled up from 320x200, alpha and all.
+//!
+//! Offsets come from the image's `grAb` chunk, scaled the same way, or else from the lump it
+//! replaces. Pixels less than half opaque are holes in the indexed patch.
+
+use super::draw::Canvas;
+use super::framebuffer::Palette;
+use super::patch::{Patch, Post};
+use crate::util::png::{self, Image};
+use crate::wad::replace::Graphic;
+use std::collections::HashMap;
+
+/// A replacement image with the size and offsets it is drawn at, in 320x200 pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HiResPatch {
+    pub image: Image,
+    pub width: u16,
+    pub height: u16,
+    pub left_offset: i16,
+    pub top_offset: i16,
+}
+
+impl HiResPatch {
+    /// Sizes `image` to `original`, the patch it replaces, or to itself if there is none.
+    pub fn new(image: Image, original: Option<&Patch>) -> Self {
+        let (width, height) = original.map_or((image.width as u16, image.height as u16), |patch| {
+            (patch.width, patch.height)
+        });
+        let (left_offset, top_offset) = match (image.offsets, original) {
+            (Some((left, top)), _) => (
+                (left * i32::from(width) / image.width as i32) as i16,
+                (top * i32::from(height) / image.height as i32) as i16,
+            ),
+            (None, Some(patch)) => (patch.left_offset, patch.top_offset),
+            (None, None) => (0, 0),
+        };
+        Self {
+            image,
+            width,
+            height,
+            left_offset,
+            top_offset,
+        }
+    }
+
+    /// The image pixel under pixel (`x`, `y`) of a `width` by `height` rendition.
+    fn sample(&self, x: usize, y: usize, width: usize, height: usize) -> u32 {
+        let sx = x * self.image.width / width;
+        let sy = y * self.image.height / height;
+        self.image.pixels[sy * self.image.width + sx]
+    }
+
+    /// The indexed version, at the size of the graphic it replaces. Returns `None` if that
+    /// is too tall for a patch's posts.
+    pub fn to_patch(&self, palette: &Palette) -> Option<Patch> {
+        let (width, height) = (usize::from(self.width), usize::from(self.height));
+        if height > 256 {
+            return None;
+        }
+        let mut nearest = NearestColor::new(palette);
+        let mut columns = Vec::with_capacity(width);
+        for x in 0..width {
+            let mut posts: Vec<Post> = Vec::new();
+            let mut open = false;
+            for y in 0..height {
+                let argb = self.sample(x, y, width, height);
+                if argb >> 24 < 0x80 {
+                    open = false;
+                    continue;
+                }
+                let index = nearest.get(argb);
+                match posts.last_mut() {
+                    Some(post) if open => post.pixels.push(index),
+                    _ => posts.push(Post {
+                        top: y as u8,
+                        pixels: vec![index],
+                    }),
+                }
+                open = true;
+            }
+            columns.push(posts);
+        }
+        Some(Patch {
+            width: self.width,
+            height: self.height,
+            left_offset: self.left_offset,
+            top_offset: self.top_offset,
+            columns,
+        })
+    }
+
+    /// Draws the image with its own colors on a canvas `scale` times 320x200, with (`x`, `y`)
+    /// in 320x200 pixels as `draw_patch` takes them. Partly transparent pixels are blended.
+    pub fn draw(&self, canvas: &mut Canvas<u32>, x: i32, y: i32, scale: usize) {
+        let (width, height) = (
+            usize::from(self.width) * scale,
+            usize::from(self.height) * scale,
+        );
+        let left = (x - i32::from(self.left_offset)) * scale as i32;
+        let top = (y - i32::from(self.top_offset)) * scale as i32;
+        let rows = canvas.height() as i32;
+        for row in 0..height {
+            let cy = top + row as i32;
+            if !(0..rows).contains(&cy) {
+                continue;
+            }
+            for column in 0..width {
+                let cx = left + column as i32;
+                if !(0..canvas.pitch as i32).contains(&cx) {
+                    continue;
+                }
+                let argb = self.sample(column, row, width, height);
+                let out = &mut canvas.pixels[cy as usize * canvas.pitch + cx as usize];
+                *out = blend(*out, argb);
+            }
+        }
+    }
+}
+
+/// Decodes a graphic from `Wads::graphic` to an indexed patch, converting a replacement with
+/// `palette`.
+pub fn load_patch(graphic: Graphic, palette: &Palette) -> Option<Patch> {
+    match graphic {
+        Graphic::Lump(lump) => Patch::parse(lump),
+        Graphic::Png { png, lump } => {
+            let original = lump.and_then(Patch::parse);
+            HiResPatch::new(png::decode(png)?, original.as_ref()).to_patch(palette)
+        }
+    }
+}
+
+/// A 64x64 flat from a replacement of any size, in palette indices.
+pub fn to_flat(image: &Image, palette: &Palette) -> Vec<u8> {
+    let mut nearest = NearestColor::new(palette);
+    let mut flat = Vec::with_capacity(64 * 64);
+    for y in 0..64 {
+        for x in 0..64 {
+            let sx = x * image.width / 64;
+            let sy = y * image.height / 64;
+            flat.push(nearest.get(image.pixels[sy * image.width + sx]));
+        }
+    }
+    flat
+}
+
+/// `Palette::nearest` searches all 256 colors, and a big image has the same few colors over
+/// and over, so answers are remembered.
+struct NearestColor<'a> {
+    palette: &'a Palette,
+    known: HashMap<u32, u8>,
+}
+
+impl<'a> NearestColor<'a> {
+    fn new(palette: &'a Palette) -> Self {
+        Self {
+            palette,
+            known: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, argb: u32) -> u8 {
+        let rgb = argb & 0xffffff;
+        *self.known.entry(rgb).or_insert_with(|| {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            self.palette
+                .nearest([i32::from(r), i32::from(g), i32::from(b)])
+        })
+    }
+}
+
+/// `over`, an `0xAARRGGBB` pixel, on top of `under`, an `0x00RRGGBB` one.
+fn blend(under: u32, over: u32) -> u32 {
+    let alpha = over >> 24;
+    let channel = |shift: u32| {
+        let (below, above) = (under >> shift & 0xff, over >> shift & 0xff);
+        ((above * alpha + below * (255 - alpha)) / 255) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let mut palette = Palette::default();
+        palette.set_rgb(1, [255, 0, 0]);
+        palette.set_rgb(2, [0, 0, 255]);
+        palette
+    }
+
+    /// A 4x4 image: red on the left half, blue on the right, with the bottom right clear.
+    fn image(offsets: Option<(i32, i32)>) -> Image {
+        let pixels = (0..16)
+            .map(|i| match (i % 4, i / 4) {
+                (2..=3, 2..=3) => 0,
+                (0..=1, _) => 0xffff_0000,
+                _ => 0xff00_00ff,
+            })
+            .collect();
+        Image {
+            width: 4,
+            height: 4,
+            pixels,
+            offsets,
+        }
+    }
+
+    #[test]
+    fn indexed_replacements_shrink_to_the_original() {
+        let original = Patch {
+            width: 2,
+            height: 2,
+            left_offset: 3,
+            top_offset: 4,
+            columns: vec![vec![], vec![]],
+        };
+        let patch = HiResPatch::new(image(None), Some(&original))
+            .to_patch(&palette())
+            .unwrap();
+        assert_eq!((patch.width, patch.height), (2, 2));
+        assert_eq!((patch.left_offset, patch.top_offset), (3, 4));
+        assert_eq!(
+            patch.columns,
+            [
+                vec![Post {
+                    top: 0,
+                    pixels: vec![1, 1]
+                }],
+                vec![Post {
+                    top: 0,
+                    pixels: vec![2]
+                }],
+            ]
+        );
+
+        // Without an original it keeps its own size, and grAb offsets.
+        let patch = HiResPatch::new(image(Some((-2, 8))), None)
+            .to_patch(&palette())
+            .unwrap();
+        assert_eq!(
+            (patch.width, patch.left_offset, patch.top_offset),
+            (4, -2, 8)
+        );
+        assert_eq!(patch.columns[3][0].pixels, [2, 2]);
+    }
+
+    #[test]
+    fn true_color_replacements_keep_their_detail() {
+        let hires = HiResPatch::new(image(Some((2, 0))), None);
+        // Drawn as a 2x2 graphic at (1, 0) on a 2x canvas.
+        let hires = HiResPatch {
+            width: 2,
+            height: 2,
+            ..hires
+        };
+        assert_eq!(hires.left_offset, 2);
+        let mut pixels = vec![0x0012_3456u32; 8 * 4];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 8,
+        };
+        hires.draw(&mut canvas, 3, 0, 2);
+        assert_eq!(&pixels[2..6], &[0xff0000, 0xff0000, 0x0000ff, 0x0000ff]);
+        assert_eq!(
+            pixels[3 * 8 + 4],
+            0x0012_3456,
+            "clear pixels leave the canvas"
+        );
+        assert_eq!(pixels[0], 0x0012_3456);
+
+        assert_eq!(blend(0x00_0000, 0x80_ffffff), 0x80_8080);
+    }
+
+    #[test]
+    fn replacements_load_through_the_graphic_lookup() {
+        let png = png::encode_rgba(4, 4, &image(None).pixels);
+        let patch = load_patch(
+            Graphic::Png {
+                png: &png,
+                lump: None,
+            },
+            &palette(),
+        )
+        .unwrap();
+        assert_eq!(patch.columns[0][0].pixels, [1, 1, 1, 1]);
+        assert!(load_patch(
+            Graphic::Png {
+                png: b"",
+                lump: None
+            },
+            &palette()
+        )
+        .is_none());
+
+        let flat = to_flat(&image(None), &palette());
+        assert_eq!(flat.len(), 64 * 64);
+        assert_eq!((flat[0], flat[63], flat[64 * 63 + 63]), (1, 2, 0));
+    }
+}
@@ -0,0 +1,301 @@
+//! The extended HUD: kills, items, secrets, how much of the map was explored, level and total
+//! time, and optionally the player's coordinates, for speedrun practice.
+//!
+//! Drawn in the overlay stage in the small HUD font (`STCFN033` to `STCFN095`, `!` to `_`),
+//! in the bottom left corner of the view. A key cycles off, stats and stats with coordinates.
+//! The numbers come from `LevelStats`, the same counters the intermission tallies, and are
+//! drawn in the color scheme's color for numbers.
+
+use super::draw::{draw_patch, draw_translated_patch, Canvas, PixelFormat};
+use super::overlay::OverlayState;
+use super::patch::Patch;
+use super::translation::Translation;
+use super::view::ViewWindow;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::stats::{format_seconds, format_time};
+use crate::util::fixed::to_int;
+
+/// The first and last characters the font has patches for.
+pub const HU_FONTSTART: u8 = b'!';
+pub const HU_FONTEND: u8 = b'_';
+/// How far a space advances, as vanilla's `HUlib_drawTextLine`.
+const SPACE_WIDTH: i32 = 4;
+pub const LINE_HEIGHT: i32 = 9;
+
+/// The STCFN patches, indexed from `HU_FONTSTART`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HudFont {
+    pub glyphs: Vec<Patch>,
+}
+
+impl HudFont {
+    pub fn load(mut patch: impl FnMut(&str) -> Option<Patch>) -> Option<Self> {
+        let glyphs = (HU_FONTSTART..=HU_FONTEND)
+            .map(|c| patch(&format!("STCFN{c:03}")))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { glyphs })
+    }
+
+    /// The patch `c` is drawn with; lowercase uses the uppercase one.
+    pub fn glyph(&self, c: char) -> Option<&Patch> {
+        let c = u8::try_from(c.to_ascii_uppercase()).ok()?;
+        self.glyphs.get(c.checked_sub(HU_FONTSTART)? as usize)
+    }
+
+    /// How far drawing `c` moves along the line.
+    pub fn advance(&self, c: char) -> i32 {
+        self.glyph(c)
+            .map_or(SPACE_WIDTH, |patch| patch.width as i32)
+    }
+
+    /// How wide `text` is drawn, in unscaled pixels.
+    pub fn text_width(&self, text: &str) -> i32 {
+        text.chars().map(|c| self.advance(c)).sum()
+    }
+
+    /// Draws one line of text with its top left corner at (`x`, `y`), as vanilla does:
+    /// lowercase is shown as uppercase and anything without a patch advances like a space.
+    pub fn draw_text<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: i32,
+        y: i32,
+        text: &str,
+    ) {
+        self.draw_glyphs(x, text, |x, patch| {
+            draw_patch(format, canvas, x, y, patch, 0)
+        });
+    }
+
+    /// `draw_text` through a translation table, for text in another color.
+    pub fn draw_translated_text<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: i32,
+        y: i32,
+        text: &str,
+        table: &Translation,
+    ) {
+        self.draw_glyphs(x, text, |x, patch| {
+            draw_translated_patch(format, canvas, x, y, patch, table)
+        });
+    }
+
+    fn draw_glyphs(&self, mut x: i32, text: &str, mut draw: impl FnMut(i32, &Patch)) {
+        for c in text.chars() {
+            if let Some(patch) = self.glyph(c) {
+                draw(x, patch);
+            }
+            x += self.advance(c);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HudMode {
+    #[default]
+    Off,
+    Stats,
+    StatsAndCoordinates,
+}
+
+impl HudMode {
+    const ALL: [HudMode; 3] = [HudMode::Off, HudMode::Stats, HudMode::StatsAndCoordinates];
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExtendedHud {
+    pub mode: HudMode,
+}
+
+impl ExtendedHud {
+    /// The key: steps to the next mode, wrapping to off.
+    pub fn cycle(&mut self) {
+        self.mode = HudMode::ALL[(self.mode as usize + 1) % HudMode::ALL.len()];
+    }
+
+    /// The text to show, top line first.
+    pub fn lines(&self, state: &OverlayState) -> Vec<String> {
+        let stats = &state.stats;
+        let mut lines = Vec::new();
+        if self.mode == HudMode::StatsAndCoordinates {
+            let view = &state.view;
+            let degrees = ((view.angle as u64 * 360) >> 32) as i32;
+            lines.push(format!(
+                "X {}  Y {}  Z {}  A {degrees}",
+                to_int(view.x),
+                to_int(view.y),
+                to_int(view.z)
+            ));
+        }
+        if self.mode != HudMode::Off {
+            let mut counts = format!(
+                "K {}/{}  I {}/{}  S {}/{}",
+                stats.kills,
+                stats.total_kills,
+                stats.items,
+                stats.total_items,
+                stats.secrets,
+                stats.total_secrets
+            );
+            if let Some(percent) = state.explored {
+                counts += &format!("  E {percent}%");
+            }
+            lines.push(counts);
+            lines.push(format!(
+                "TIME {}  TOTAL {}",
+                format_time(stats.level_time),
+                format_seconds(stats.run_time())
+            ));
+        }
+        lines
+    }
+
+    /// Draws the lines, through `numbers` if the color scheme colors numbers differently.
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        window: &ViewWindow,
+        state: &OverlayState,
+        font: &HudFont,
+        numbers: Option<&Translation>,
+    ) {
+        let lines = self.lines(state);
+        let bottom = (window.y + window.height) as i32 - 1;
+        let top = bottom - lines.len() as i32 * LINE_HEIGHT;
+        for (i, line) in lines.iter().enumerate() {
+            let (x, y) = (window.x as i32 + 2, top + i as i32 * LINE_HEIGHT);
+            match numbers {
+                Some(table) => font.draw_translated_text(format, canvas, x, y, line, table),
+                None => font.draw_text(format, canvas, x, y, line),
+            }
+        }
+    }
+}
+
+impl Configurable for ExtendedHud {
+    fn register(config: &mut Config) {
+        config.register_int("hud_extended", 0, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let mode = config.get_int("hud_extended").clamp(0, 2) as usize;
+        Self {
+            mode: HudMode::ALL[mode],
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("hud_extended", self.mode as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stats::LevelStats;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::Post;
+    use crate::renderer::view::ViewSize;
+    use crate::renderer::ViewPoint;
+    use crate::util::angle::ANG90;
+    use crate::util::fixed::to_fixed;
+
+    fn state() -> OverlayState {
+        let mut stats = LevelStats::default();
+        stats.start_level(20, 5, 2);
+        stats.kills = 3;
+        stats.level_time = 35 * 12 + 7;
+        stats.total_time = 35 * 60;
+        OverlayState {
+            stats,
+            view: ViewPoint {
+                x: to_fixed(1056),
+                y: to_fixed(-3616),
+                z: 0,
+                angle: ANG90,
+            },
+            ..OverlayState::default()
+        }
+    }
+
+    #[test]
+    fn modes_cycle_and_show_the_intermission_counters() {
+        let mut hud = ExtendedHud::default();
+        assert!(hud.lines(&state()).is_empty());
+        hud.cycle();
+        assert_eq!(
+            hud.lines(&state()),
+            vec!["K 3/20  I 0/5  S 0/2", "TIME 0:12.20  TOTAL 1:12"]
+        );
+        let explored = OverlayState {
+            explored: Some(42),
+            ..state()
+        };
+        assert_eq!(hud.lines(&explored)[0], "K 3/20  I 0/5  S 0/2  E 42%");
+        hud.cycle();
+        assert_eq!(hud.lines(&state())[0], "X 1056  Y -3616  Z 0  A 90");
+        hud.cycle();
+        assert_eq!(hud.mode, HudMode::Off);
+    }
+
+    #[test]
+    fn text_uses_the_font_patches_and_uppercases() {
+        // Every glyph is a 2 pixel wide patch whose single pixel is its character code.
+        let font = HudFont::load(|name| {
+            let code: u8 = name[5..].parse().ok()?;
+            Some(Patch {
+                width: 2,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![code],
+                    }],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        font.draw_text(&format, &mut canvas, 0, 0, "a b");
+        assert_eq!(&pixels[..8], &[b'A' as u32, 0, 0, 0, 0, 0, b'B' as u32, 0]);
+
+        let hud = ExtendedHud {
+            mode: HudMode::Stats,
+        };
+        let window = ViewSize::default().window(320, 200);
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        hud.draw(&format, &mut canvas, &window, &state(), &font, None);
+        assert_eq!(pixels[(167 - 18) * 320 + 2], b'K' as u32);
+
+        let mut numbers: Translation = core::array::from_fn(|i| i as u8);
+        numbers[b'K' as usize] = b'k';
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        hud.draw(
+            &format,
+            &mut canvas,
+            &window,
+            &state(),
+            &font,
+            Some(&numbers),
+        );
+        assert_eq!(pixels[(167 - 18) * 320 + 2], b'k' as u32);
+    }
+}
@@ -0,0 +1,179 @@
+//! Moving sectors drawn between tics: doors, lifts, crushers and scrolling walls.
+//!
+//! The game moves floors and ceilings and scrolls texture offsets once a tic, so a frame
+//! drawn between tics at a higher framerate would show them in steps while everything else
+//! glides. After each tic the loop hands `LevelInterpolation::end_tic` the heights and
+//! offsets the tic left, and frames read them back from `sector` and `side` at
+//! `Interpolation`'s fraction for `Category::Sectors`, part of the way from the tic before.
+//! The simulation keeps its own values and never sees these.
+//!
+//! Anything that moved by more than `MAX_STEP` in one tic jumped rather than moved, such as
+//! a sector raised by a script or a loaded game, and is drawn where it is. So is everything
+//! after `snap`, which the loop calls when a level starts.
+
+use crate::engine::pacing::lerp;
+use crate::util::fixed::{to_fixed, Fixed};
+use crate::wad::map::MapData;
+
+/// The most a surface moves in a tic and still counts as moving: faster than any door, lift
+/// or scroller, short of a teleport.
+pub const MAX_STEP: Fixed = 64 << 16;
+
+/// A sector's floor and ceiling as a frame draws them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SectorHeights {
+    pub floor: Fixed,
+    pub ceiling: Fixed,
+}
+
+/// A sidedef's texture offsets as a frame draws them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SideOffsets {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+/// The sectors and sidedefs of a level after the last tic and the one before it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelInterpolation {
+    sectors: Vec<[SectorHeights; 2]>,
+    sides: Vec<[SideOffsets; 2]>,
+}
+
+impl LevelInterpolation {
+    /// Starts from the level as the map has it, standing still.
+    pub fn new(map: &MapData) -> Self {
+        let sectors = map.sectors.iter().map(|sector| SectorHeights {
+            floor: to_fixed(i32::from(sector.floor_height)),
+            ceiling: to_fixed(i32::from(sector.ceiling_height)),
+        });
+        let sides = map.sidedefs.iter().map(|side| SideOffsets {
+            x: to_fixed(i32::from(side.x_offset)),
+            y: to_fixed(i32::from(side.y_offset)),
+        });
+        Self {
+            sectors: sectors.map(|heights| [heights; 2]).collect(),
+            sides: sides.map(|offsets| [offsets; 2]).collect(),
+        }
+    }
+
+    /// Takes the state a tic left, indexed as the map's sectors and sidedefs.
+    pub fn end_tic(&mut self, sectors: &[SectorHeights], sides: &[SideOffsets]) {
+        for (saved, now) in self.sectors.iter_mut().zip(sectors) {
+            *saved = [saved[1], *now];
+        }
+        for (saved, now) in self.sides.iter_mut().zip(sides) {
+            *saved = [saved[1], *now];
+        }
+    }
+
+    /// Draws everything where it is, from the next frame until it moves again.
+    pub fn snap(&mut self) {
+        for saved in &mut self.sectors {
+            saved[0] = saved[1];
+        }
+        for saved in &mut self.sides {
+            saved[0] = saved[1];
+        }
+    }
+
+    /// Sector `index` at `fraction` of the way through the last tic.
+    pub fn sector(&self, index: usize, fraction: Fixed) -> Option<SectorHeights> {
+        let [before, after] = *self.sectors.get(index)?;
+        Some(SectorHeights {
+            floor: between(before.floor, after.floor, fraction),
+            ceiling: between(before.ceiling, after.ceiling, fraction),
+        })
+    }
+
+    /// Sidedef `index` at `fraction` of the way through the last tic.
+    pub fn side(&self, index: usize, fraction: Fixed) -> Option<SideOffsets> {
+        let [before, after] = *self.sides.get(index)?;
+        Some(SideOffsets {
+            x: between(before.x, after.x, fraction),
+            y: between(before.y, after.y, fraction),
+        })
+    }
+}
+
+fn between(before: Fixed, after: Fixed, fraction: Fixed) -> Fixed {
+    match after.wrapping_sub(before).unsigned_abs() > MAX_STEP as u32 {
+        true => after,
+        false => lerp(before, after, fraction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixed::FRACUNIT;
+    use crate::wad::map::{Sector, SideDef};
+
+    fn map() -> MapData {
+        MapData {
+            sectors: vec![Sector {
+                floor_height: 0,
+                ceiling_height: 128,
+                floor_pic: "FLOOR4_8".to_string(),
+                ceiling_pic: "CEIL3_5".to_string(),
+                light: 160,
+                special: 0,
+                tag: 0,
+            }],
+            sidedefs: vec![SideDef {
+                x_offset: 16,
+                y_offset: 0,
+                upper: "-".to_string(),
+                lower: "-".to_string(),
+                middle: "STARTAN3".to_string(),
+                sector: 0,
+            }],
+            ..MapData::default()
+        }
+    }
+
+    #[test]
+    fn moving_surfaces_are_drawn_between_tics() {
+        let mut level = LevelInterpolation::new(&map());
+        let door = SectorHeights {
+            floor: 0,
+            ceiling: to_fixed(128) - to_fixed(2),
+        };
+        let scrolled = SideOffsets {
+            x: to_fixed(17),
+            y: 0,
+        };
+        level.end_tic(&[door], &[scrolled]);
+
+        let half = level.sector(0, FRACUNIT / 2).unwrap();
+        assert_eq!(half.ceiling, to_fixed(127));
+        assert_eq!(level.sector(0, FRACUNIT), Some(door));
+        assert_eq!(
+            level.side(0, FRACUNIT / 4).unwrap().x,
+            to_fixed(16) + FRACUNIT / 4
+        );
+        assert_eq!(level.side(0, 0).unwrap().x, to_fixed(16));
+        assert_eq!(level.sector(1, 0), None);
+    }
+
+    #[test]
+    fn jumps_and_new_levels_are_not_smoothed() {
+        let mut level = LevelInterpolation::new(&map());
+        let raised = SectorHeights {
+            floor: to_fixed(512),
+            ceiling: to_fixed(640),
+        };
+        level.end_tic(&[raised], &[]);
+        assert_eq!(level.sector(0, 0), Some(raised));
+        // Sidedefs that weren't passed keep standing still.
+        assert_eq!(level.side(0, 0).unwrap().x, to_fixed(16));
+
+        let lowered = SectorHeights {
+            floor: to_fixed(504),
+            ..raised
+        };
+        level.end_tic(&[lowered], &[]);
+        level.snap();
+        assert_eq!(level.sector(0, 0), Some(lowered));
+    }
+}
@@ -0,0 +1,93 @@
+//! How finely light diminishes with distance.
+//!
+//! Vanilla has 32 light levels, so light falls off in visible bands across floors and down
+//! long corridors. The true-color renderer can attenuate every pixel exactly and does so
+//! unless `banded_light` asks for vanilla's steps back. The indexed renderer has to light
+//! through colormaps, so `double_light_bands` builds one more map between every two of
+//! COLORMAP's at startup, each color the palette entry nearest the average of the two, which
+//! halves the size of the bands.
+
+use super::draw::{INVERSECOLORMAP, NUMCOLORMAPS};
+use super::framebuffer::Palette;
+use crate::engine::config::{Config, Configurable, Section};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LightOptions {
+    /// Vanilla's 32 bands in the true-color renderer.
+    pub banded: bool,
+    /// 64 bands in the indexed renderer.
+    pub double_bands: bool,
+}
+
+impl Configurable for LightOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("banded_light", false, Section::Extended);
+        config.register_bool("double_light_bands", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            banded: config.get_bool("banded_light"),
+            double_bands: config.get_bool("double_light_bands"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("banded_light", self.banded);
+        config.set_bool("double_light_bands", self.double_bands);
+    }
+}
+
+/// The light maps of a COLORMAP lump with a map between each pair, then its invulnerability
+/// map, the layout `Indexed::doubled` reads. The darkest map is followed by a copy of
+/// itself. Returns `None` if the lump is too short.
+pub fn double_colormaps(colormaps: &[u8], palette: &Palette) -> Option<Vec<u8>> {
+    let map = |index: usize| colormaps.get(index * 256..(index + 1) * 256);
+    let inverse = map(INVERSECOLORMAP)?;
+    let mut doubled = Vec::with_capacity((2 * NUMCOLORMAPS + 1) * 256);
+    for level in 0..NUMCOLORMAPS {
+        let light = map(level)?;
+        let darker = map((level + 1).min(NUMCOLORMAPS - 1))?;
+        doubled.extend_from_slice(light);
+        doubled.extend(light.iter().zip(darker).map(|(&a, &b)| {
+            let (a, b) = (palette.rgb(a), palette.rgb(b));
+            palette.nearest(core::array::from_fn(|c| {
+                (i32::from(a[c]) + i32::from(b[c]) + 1) / 2
+            }))
+        }));
+    }
+    doubled.extend_from_slice(inverse);
+    Some(doubled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubled_maps_fall_between_the_originals() {
+        // On the greyscale default palette, map `n` darkens every index by `4n`.
+        let colormaps: Vec<u8> = (0..=INVERSECOLORMAP)
+            .flat_map(|map| (0..=255u8).map(move |i| i.saturating_sub(4 * map as u8)))
+            .collect();
+        let doubled = double_colormaps(&colormaps, &Palette::default()).unwrap();
+        assert_eq!(doubled.len(), (2 * NUMCOLORMAPS + 1) * 256);
+        let at = |map: usize, index: usize| doubled[map * 256 + index];
+        assert_eq!((at(0, 200), at(1, 200), at(2, 200)), (200, 198, 196));
+        assert_eq!((at(62, 200), at(63, 200)), (76, 76));
+        assert_eq!(at(64, 200), colormaps[INVERSECOLORMAP * 256 + 200]);
+        assert_eq!(
+            double_colormaps(&colormaps[..256 * 32], &Palette::default()),
+            None
+        );
+
+        let mut config = Config::new();
+        LightOptions::register(&mut config);
+        let options = LightOptions {
+            banded: true,
+            double_bands: true,
+        };
+        options.to_config(&mut config);
+        assert_eq!(LightOptions::from_config(&config), options);
+    }
+}
@@ -0,0 +1,112 @@
+//! The loading screen: the map's name over a bar that fills as `game::loading` reports.
+//!
+//! Drawn at the unscaled 320x200 layout the menus use, in palette colors so it looks the same
+//! in both pixel formats. The caller clears the canvas or leaves the last frame under it.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::HudFont;
+
+const WHITE: u8 = 4;
+const RED: u8 = 176;
+const BAR_WIDTH: i32 = 200;
+const BAR_HEIGHT: i32 = 8;
+const BAR_Y: i32 = 100;
+const TITLE_Y: i32 = 84;
+
+/// Draws "LOADING" and the map's name centered above a bar `percent` full.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    font: &HudFont,
+    map: &str,
+    percent: u32,
+) {
+    let title = format!("LOADING {}", map.to_ascii_uppercase());
+    let x = (canvas.pitch as i32 - font.text_width(&title)) / 2;
+    font.draw_text(format, canvas, x, TITLE_Y, &title);
+
+    let left = (canvas.pitch as i32 - BAR_WIDTH) / 2;
+    let filled = (BAR_WIDTH - 2) * percent.min(100) as i32 / 100;
+    let (white, red) = (format.shade(WHITE, 0), format.shade(RED, 0));
+    for y in BAR_Y..BAR_Y + BAR_HEIGHT {
+        for x in left..left + BAR_WIDTH {
+            let edge =
+                y == BAR_Y || y == BAR_Y + BAR_HEIGHT - 1 || x == left || x == left + BAR_WIDTH - 1;
+            let pixel = if edge {
+                white
+            } else if x - left - 1 < filled {
+                red
+            } else {
+                continue;
+            };
+            if let Some(out) = canvas
+                .pixels
+                .get_mut((y * canvas.pitch as i32 + x) as usize)
+            {
+                *out = pixel;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::{Patch, Post};
+
+    fn draw_at(percent: u32) -> Vec<u32> {
+        // Every glyph is a 4 pixel wide patch whose single pixel is its character code.
+        let font = HudFont::load(|name| {
+            let code: u8 = name[5..].parse().ok()?;
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![code],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &font, "map07", percent);
+        pixels
+    }
+
+    #[test]
+    fn the_bar_fills_with_progress() {
+        let middle = (BAR_Y + BAR_HEIGHT / 2) as usize * 320;
+        let empty = draw_at(0);
+        assert_eq!(empty[middle + 60], WHITE as u32);
+        assert_eq!(empty[middle + 61], 0);
+
+        let half = draw_at(50);
+        assert_eq!(half[middle + 61], RED as u32);
+        assert_eq!(half[middle + 159], RED as u32);
+        assert_eq!(half[middle + 160], 0);
+        assert_eq!(half[middle + 259], WHITE as u32);
+
+        let full = draw_at(250);
+        assert_eq!(full[middle + 258], RED as u32);
+    }
+
+    #[test]
+    fn the_title_is_centered() {
+        // "LOADING MAP07" is 13 characters of 4 pixels.
+        let pixels = draw_at(0);
+        assert_eq!(pixels[TITLE_Y as usize * 320 + 134], b'L' as u32);
+    }
+}
@@ -0,0 +1,427 @@
+//! A top-down picture of a whole map, for `doom-rs mapview`.
+//!
+//! Lines are drawn in their automap colors as the `iddt` cheat shows them, things as small
+//! arrows facing their angle, and each sector is filled with a gray that gets lighter the
+//! higher its floor is. SVG keeps the geometry exact and names every sector and thing in a
+//! tooltip; PNG is for a quick look.
+
+use super::automap::{LineKind, THING_COLOR};
+use super::framebuffer::Palette;
+use crate::util::png;
+use crate::wad::map::{MapData, Vertex};
+
+/// Margin around the map, in pixels.
+const MARGIN: f64 = 16.0;
+
+/// How thing arrows are drawn, in pixels.
+const THING_RADIUS: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapView {
+    pub size: usize, // Length of the longer side of the picture, in pixels.
+    pub things: bool,
+    pub heights: bool,
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        Self {
+            size: 1024,
+            things: true,
+            heights: true,
+        }
+    }
+}
+
+/// Map units to pixels, with y pointing down.
+struct Projection {
+    min_x: f64,
+    max_y: f64,
+    scale: f64,
+    width: usize,
+    height: usize,
+}
+
+impl Projection {
+    fn new(map: &MapData, size: usize) -> Self {
+        let xs = map.vertexes.iter().map(|v| f64::from(v.x));
+        let ys = map.vertexes.iter().map(|v| f64::from(v.y));
+        let (min_x, max_x) = xs.fold((f64::MAX, f64::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        let (min_y, max_y) = ys.fold((f64::MAX, f64::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+        if map.vertexes.is_empty() {
+            return Self {
+                min_x: 0.0,
+                max_y: 0.0,
+                scale: 1.0,
+                width: size,
+                height: size,
+            };
+        }
+        let span = (max_x - min_x).max(max_y - min_y).max(1.0);
+        let scale = (size as f64 - 2.0 * MARGIN).max(1.0) / span;
+        Self {
+            min_x,
+            max_y,
+            scale,
+            width: ((max_x - min_x) * scale + 2.0 * MARGIN).ceil() as usize,
+            height: ((max_y - min_y) * scale + 2.0 * MARGIN).ceil() as usize,
+        }
+    }
+
+    fn point(&self, x: i16, y: i16) -> (f64, f64) {
+        (
+            (f64::from(x) - self.min_x) * self.scale + MARGIN,
+            (self.max_y - f64::from(y)) * self.scale + MARGIN,
+        )
+    }
+
+    fn vertex(&self, vertex: Vertex) -> (f64, f64) {
+        self.point(vertex.x, vertex.y)
+    }
+}
+
+/// The edges of every sector: each line that has a side in it.
+fn sector_edges(map: &MapData) -> Vec<Vec<(Vertex, Vertex)>> {
+    let mut edges = vec![Vec::new(); map.sectors.len()];
+    for line in &map.linedefs {
+        let ends = (
+            map.vertexes[line.v1 as usize],
+            map.vertexes[line.v2 as usize],
+        );
+        let front = map.sidedefs[line.front as usize].sector as usize;
+        edges[front].push(ends);
+        if let Some(back) = map.sidedefs.get(line.back as usize) {
+            if back.sector as usize != front {
+                edges[back.sector as usize].push(ends);
+            }
+        }
+    }
+    edges
+}
+
+/// Floor height as a gray level, 40 for the lowest floor of the map to 140 for the highest.
+fn floor_shades(map: &MapData) -> Vec<u8> {
+    let floors = map.sectors.iter().map(|sector| sector.floor_height);
+    let (low, high) = floors.fold((i16::MAX, i16::MIN), |(lo, hi), f| (lo.min(f), hi.max(f)));
+    let range = f64::from(high) - f64::from(low);
+    map.sectors
+        .iter()
+        .map(|sector| match range > 0.0 {
+            true => {
+                (40.0 + 100.0 * (f64::from(sector.floor_height) - f64::from(low)) / range) as u8
+            }
+            false => 90,
+        })
+        .collect()
+}
+
+/// The three corners of a thing's arrow.
+fn arrow(center: (f64, f64), angle: i16) -> [(f64, f64); 3] {
+    let angle = f64::from(angle).to_radians();
+    let corner = |turn: f64, length: f64| {
+        let a = angle + turn;
+        (center.0 + a.cos() * length, center.1 - a.sin() * length)
+    };
+    [
+        corner(0.0, THING_RADIUS * 1.5),
+        corner(2.5, THING_RADIUS),
+        corner(-2.5, THING_RADIUS),
+    ]
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+impl MapView {
+    pub fn svg(&self, map: &MapData, palette: &Palette) -> String {
+        let view = Projection::new(map, self.size);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\">\n<rect width=\"100%\" height=\"100%\" fill=\"#000000\"/>\n",
+            w = view.width,
+            h = view.height
+        );
+        if self.heights {
+            let shades = floor_shades(map);
+            for (index, edges) in sector_edges(map).iter().enumerate() {
+                let sector = &map.sectors[index];
+                let shade = shades[index];
+                svg.push_str(&format!(
+                    "<path d=\"{}\" fill=\"{}\" fill-rule=\"evenodd\"><title>sector {index}: \
+                     floor {} ceiling {} light {}</title></path>\n",
+                    loops(edges, &view),
+                    hex([shade; 3]),
+                    sector.floor_height,
+                    sector.ceiling_height,
+                    sector.light
+                ));
+            }
+        }
+        for line in &map.linedefs {
+            let color = palette.rgb(LineKind::classify(map, line).color(true));
+            let (x1, y1) = view.vertex(map.vertexes[line.v1 as usize]);
+            let (x2, y2) = view.vertex(map.vertexes[line.v2 as usize]);
+            svg.push_str(&format!(
+                "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" stroke=\"{}\"/>\n",
+                hex(color)
+            ));
+        }
+        if self.things {
+            let color = hex(palette.rgb(THING_COLOR));
+            for (index, thing) in map.things.iter().enumerate() {
+                let corners = arrow(view.point(thing.x, thing.y), thing.angle);
+                let points: Vec<String> = corners
+                    .iter()
+                    .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                    .collect();
+                svg.push_str(&format!(
+                    "<polygon points=\"{}\" fill=\"{color}\"><title>thing {index}: type {} at \
+                     ({}, {}) angle {}</title></polygon>\n",
+                    points.join(" "),
+                    thing.kind,
+                    thing.x,
+                    thing.y,
+                    thing.angle
+                ));
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    pub fn png(&self, map: &MapData, palette: &Palette) -> Vec<u8> {
+        let view = Projection::new(map, self.size);
+        let (width, height) = (view.width, view.height);
+        let mut pixels = vec![0u32; width * height];
+        let rgb = |[r, g, b]: [u8; 3]| u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+        if self.heights {
+            let shades = floor_shades(map);
+            for (index, edges) in sector_edges(map).iter().enumerate() {
+                let shade = u32::from(shades[index]) * 0x01_0101;
+                fill(&mut pixels, width, height, edges, &view, shade);
+            }
+        }
+        for line in &map.linedefs {
+            let color = rgb(palette.rgb(LineKind::classify(map, line).color(true)));
+            let a = view.vertex(map.vertexes[line.v1 as usize]);
+            let b = view.vertex(map.vertexes[line.v2 as usize]);
+            draw_line(&mut pixels, width, height, a, b, color);
+        }
+        if self.things {
+            let color = rgb(palette.rgb(THING_COLOR));
+            for thing in &map.things {
+                let [a, b, c] = arrow(view.point(thing.x, thing.y), thing.angle);
+                for (from, to) in [(a, b), (b, c), (c, a)] {
+                    draw_line(&mut pixels, width, height, from, to, color);
+                }
+            }
+        }
+        png::encode_rgb(width, height, &pixels)
+    }
+}
+
+/// The edges of a sector as closed loops for an SVG path, chaining each edge onto one that
+/// starts where it ends. Broken sectors leave open chains, which SVG closes on its own.
+fn loops(edges: &[(Vertex, Vertex)], view: &Projection) -> String {
+    let mut left: Vec<(Vertex, Vertex)> = edges.to_vec();
+    let mut path = String::new();
+    while let Some((start, mut end)) = left.pop() {
+        let (x, y) = view.vertex(start);
+        path.push_str(&format!("M{x:.1} {y:.1}"));
+        loop {
+            let (x, y) = view.vertex(end);
+            path.push_str(&format!("L{x:.1} {y:.1}"));
+            if end == start {
+                break;
+            }
+            let Some(next) = left.iter().position(|&(a, b)| a == end || b == end) else {
+                break;
+            };
+            let (a, b) = left.swap_remove(next);
+            end = if a == end { b } else { a };
+        }
+        path.push('Z');
+    }
+    path
+}
+
+/// Fills the inside of `edges` with even-odd scanlines, sampling each pixel at its center.
+fn fill(
+    pixels: &mut [u32],
+    width: usize,
+    height: usize,
+    edges: &[(Vertex, Vertex)],
+    view: &Projection,
+    color: u32,
+) {
+    let edges: Vec<((f64, f64), (f64, f64))> = edges
+        .iter()
+        .map(|&(a, b)| (view.vertex(a), view.vertex(b)))
+        .collect();
+    let mut crossings = Vec::new();
+    for y in 0..height {
+        let sample = y as f64 + 0.5;
+        crossings.clear();
+        for &((x1, y1), (x2, y2)) in &edges {
+            if (y1 <= sample) != (y2 <= sample) {
+                crossings.push(x1 + (sample - y1) / (y2 - y1) * (x2 - x1));
+            }
+        }
+        crossings.sort_by(f64::total_cmp);
+        for span in crossings.chunks_exact(2) {
+            let from = (span[0] - 0.5).ceil().max(0.0) as usize;
+            let to = ((span[1] - 0.5).floor() + 1.0).clamp(0.0, width as f64) as usize;
+            for pixel in pixels[y * width..][..width].iter_mut().take(to).skip(from) {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+fn draw_line(
+    pixels: &mut [u32],
+    width: usize,
+    height: usize,
+    (x1, y1): (f64, f64),
+    (x2, y2): (f64, f64),
+    color: u32,
+) {
+    let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let t = step as f64 / steps as f64;
+        let (x, y) = (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+        if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+            pixels[y as usize * width + x as usize] = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::automap::{BROWNS, REDS};
+    use crate::wad::map::{LineDef, Sector, SideDef, Thing, NO_SIDEDEF};
+
+    /// A 256 unit square room with a raised 64 unit square platform in the middle.
+    fn room() -> MapData {
+        let v = |x, y| Vertex { x, y };
+        let line = |v1, v2, front, back| LineDef {
+            v1,
+            v2,
+            flags: 0,
+            special: 0,
+            tag: 0,
+            front,
+            back,
+        };
+        let side = |sector| SideDef {
+            x_offset: 0,
+            y_offset: 0,
+            upper: String::new(),
+            lower: String::new(),
+            middle: String::new(),
+            sector,
+        };
+        let sector = |floor| Sector {
+            floor_height: floor,
+            ceiling_height: 128,
+            floor_pic: String::new(),
+            ceiling_pic: String::new(),
+            light: 160,
+            special: 0,
+            tag: 0,
+        };
+        MapData {
+            things: vec![Thing {
+                x: 32,
+                y: 32,
+                angle: 90,
+                kind: 1,
+                flags: 7,
+            }],
+            vertexes: vec![
+                v(0, 0),
+                v(256, 0),
+                v(256, 256),
+                v(0, 256),
+                v(96, 96),
+                v(160, 96),
+                v(160, 160),
+                v(96, 160),
+            ],
+            linedefs: vec![
+                line(0, 1, 0, NO_SIDEDEF),
+                line(1, 2, 0, NO_SIDEDEF),
+                line(2, 3, 0, NO_SIDEDEF),
+                line(3, 0, 0, NO_SIDEDEF),
+                line(4, 5, 1, 2),
+                line(5, 6, 1, 2),
+                line(6, 7, 1, 2),
+                line(7, 4, 1, 2),
+            ],
+            sidedefs: vec![side(0), side(1), side(0)],
+            sectors: vec![sector(0), sector(32)],
+        }
+    }
+
+    fn palette() -> Palette {
+        let playpal: Vec<u8> = (0..=255u8).flat_map(|i| [i, i, i]).collect();
+        Palette::from_playpal(&playpal, 0).unwrap()
+    }
+
+    #[test]
+    fn svg_shows_lines_sectors_and_things() {
+        let view = MapView {
+            size: 288,
+            ..MapView::default()
+        };
+        let svg = view.svg(&room(), &palette());
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"288\" height=\"288\""));
+        assert_eq!(svg.matches("<line ").count(), 8);
+        assert!(svg.contains(&format!("stroke=\"{}\"", hex([REDS; 3]))));
+        assert!(svg.contains(&format!("stroke=\"{}\"", hex([BROWNS; 3]))));
+        assert!(svg.contains("<title>sector 1: floor 32 ceiling 128 light 160</title>"));
+        assert!(svg.contains("<title>thing 0: type 1 at (32, 32) angle 90</title>"));
+        // The platform is lighter than the floor around it.
+        assert!(svg.contains("fill=\"#282828\"") && svg.contains("fill=\"#8c8c8c\""));
+
+        let bare = MapView {
+            things: false,
+            heights: false,
+            ..view
+        };
+        let svg = bare.svg(&room(), &palette());
+        assert!(!svg.contains("<polygon") && !svg.contains("<path"));
+    }
+
+    #[test]
+    fn png_fills_sectors_by_height() {
+        let view = MapView {
+            size: 288,
+            things: false,
+            heights: true,
+        };
+        let png = view.png(&room(), &palette());
+        assert_eq!(&png[16..24], &[0, 0, 1, 0x20, 0, 0, 1, 0x20]);
+
+        // Decode the stored blocks back into rows to look at single pixels.
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        let stream = &png[idat + 2..];
+        let (mut data, mut at) = (Vec::<u8>::new(), 0);
+        loop {
+            let len = usize::from(u16::from_le_bytes([stream[at + 1], stream[at + 2]]));
+            data.extend(&stream[at + 5..at + 5 + len]);
+            if stream[at] == 1 {
+                break;
+            }
+            at += 5 + len;
+        }
+        let pixel = |x: usize, y: usize| data[y * (1 + 288 * 3) + 1 + x * 3];
+        assert_eq!(pixel(144, 144), 140, "the platform");
+        assert_eq!(pixel(40, 144), 40, "the room");
+        assert_eq!(pixel(4, 4), 0, "outside");
+        assert_eq!(pixel(16, 144), REDS, "the west wall");
+    }
+}
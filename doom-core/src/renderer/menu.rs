@@ -0,0 +1,205 @@
+//! Menu pages drawn on a canvas of any size.
+//!
+//! The page is laid out at 320x200 as in vanilla, then scaled up to fit the canvas and
+//! centered, keeping its proportions. Each source pixel covers a block of canvas pixels, so
+//! graphics stay sharp; at sizes that aren't a whole multiple of 320x200 some blocks are a
+//! pixel wider than others, and `menu_integer_scale` trades that for a border by rounding
+//! the scale down. `MenuScale::to_menu` goes the other way, for the mouse.
+
+use super::draw::{Canvas, PixelFormat};
+use super::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+use super::patch::Patch;
+use crate::engine::menu::{ItemKind, Menu, LINEHEIGHT, SKULLXOFF, THERM_STEP};
+
+const SKULLS: [&str; 2] = ["M_SKULL1", "M_SKULL2"];
+/// How long each skull is shown before the other.
+const SKULL_TICS: i32 = 8;
+
+/// How the 320x200 layout lands on a canvas: scaled by `num / den` and moved to (`x`, `y`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MenuScale {
+    pub x: i32,
+    pub y: i32,
+    num: i32,
+    den: i32,
+}
+
+impl MenuScale {
+    /// The largest scale that fits a `width` x `height` canvas, a whole one if `integer`.
+    pub fn fit(width: usize, height: usize, integer: bool) -> Self {
+        let (width, height) = (width as i32, height as i32);
+        let (screen_width, screen_height) = (SCREENWIDTH as i32, SCREENHEIGHT as i32);
+        let (num, den) = if integer {
+            ((width / screen_width).min(height / screen_height).max(1), 1)
+        } else if width * screen_height <= height * screen_width {
+            (width, screen_width)
+        } else {
+            (height, screen_height)
+        };
+        Self {
+            x: (width - screen_width * num / den).max(0) / 2,
+            y: (height - screen_height * num / den).max(0) / 2,
+            num,
+            den,
+        }
+    }
+
+    /// The canvas position of the layout's (`x`, `y`).
+    pub fn to_canvas(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            self.x + (x * self.num).div_euclid(self.den),
+            self.y + (y * self.num).div_euclid(self.den),
+        )
+    }
+
+    /// The layout position a canvas pixel shows, for placing the menu's `Pointer`.
+    pub fn to_menu(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x - self.x) * self.den).div_euclid(self.num),
+            ((y - self.y) * self.den).div_euclid(self.num),
+        )
+    }
+}
+
+/// Draws a patch at (`x`, `y`) in the layout, each of its pixels a block of the canvas.
+pub fn draw_scaled_patch<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    scale: &MenuScale,
+    x: i32,
+    y: i32,
+    patch: &Patch,
+) {
+    let x = x - i32::from(patch.left_offset);
+    let y = y - i32::from(patch.top_offset);
+    let height = canvas.height() as i32;
+    for (column, posts) in patch.columns.iter().enumerate() {
+        let (left, _) = scale.to_canvas(x + column as i32, 0);
+        let (right, _) = scale.to_canvas(x + column as i32 + 1, 0);
+        let (left, right) = (left.max(0), right.min(canvas.pitch as i32));
+        for post in posts {
+            for (row, &texel) in post.pixels.iter().enumerate() {
+                let source_y = y + i32::from(post.top) + row as i32;
+                let (_, top) = scale.to_canvas(0, source_y);
+                let (_, bottom) = scale.to_canvas(0, source_y + 1);
+                let pixel = format.shade(texel, 0);
+                for cy in top.max(0)..bottom.min(height) {
+                    let line = cy as usize * canvas.pitch;
+                    for cx in left..right {
+                        canvas.pixels[line + cx as usize] = pixel;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws `menu` as `M_Drawer` does: each item's graphic, a thermometer under each slider and
+/// the skull by the selected item, blinking with `tic`. `patch` looks graphics up by name.
+pub fn draw<'a, F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    scale: &MenuScale,
+    menu: &Menu,
+    tic: i32,
+    patch: impl Fn(&str) -> Option<&'a Patch>,
+) {
+    let mut put = |x: i32, y: i32, name: &str| {
+        if let Some(patch) = patch(name) {
+            draw_scaled_patch(format, canvas, scale, x, y, patch);
+        }
+    };
+    for (i, item) in menu.items.iter().enumerate() {
+        let y = menu.y + LINEHEIGHT * i as i32;
+        if item.kind != ItemKind::Blank {
+            put(menu.x, y, item.patch);
+        }
+        if let ItemKind::Slider { steps, value } = item.kind {
+            let y = y + LINEHEIGHT;
+            put(menu.x, y, "M_THERML");
+            for step in 0..steps {
+                put(menu.x + THERM_STEP * (step + 1), y, "M_THERMM");
+            }
+            put(menu.x + THERM_STEP * (steps + 1), y, "M_THERMR");
+            put(menu.x + THERM_STEP * (value + 1), y, "M_THERMO");
+        }
+    }
+    let skull = SKULLS[(tic / SKULL_TICS) as usize % SKULLS.len()];
+    let y = menu.y - 5 + LINEHEIGHT * menu.selected as i32;
+    put(menu.x + SKULLXOFF, y, skull);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::menu::MenuItem;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::Post;
+
+    fn square(size: u16, texel: u8) -> Patch {
+        Patch {
+            width: size,
+            height: size,
+            left_offset: 0,
+            top_offset: 0,
+            columns: (0..size)
+                .map(|_| {
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![texel; usize::from(size)],
+                    }]
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scales_fit_the_canvas() {
+        let scale = MenuScale::fit(1920, 1080, false);
+        assert_eq!((scale.x, scale.y), (96, 0));
+        assert_eq!(scale.to_canvas(320, 200), (1824, 1080));
+        assert_eq!(scale.to_menu(96 + 27, 1079), (5, 199));
+
+        let whole = MenuScale::fit(1920, 1080, true);
+        assert_eq!((whole.x, whole.y), (160, 40));
+        assert_eq!(whole.to_canvas(1, 1), (165, 45));
+        assert_eq!(whole.to_menu(159, 40), (-1, 0), "left of the page");
+        let native = MenuScale::fit(320, 200, false);
+        assert_eq!(native.to_canvas(319, 199), (319, 199));
+    }
+
+    #[test]
+    fn pages_are_drawn_scaled() {
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 640 * 400];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 640,
+        };
+        let (item, skull, thermo) = (square(4, 0xb0), square(2, 0xa0), square(8, 0x70));
+        let menu = Menu::new(
+            80,
+            64,
+            vec![
+                MenuItem::button("M_NGAME"),
+                MenuItem::slider("M_SFXVOL", 4, 2),
+            ],
+        );
+        let scale = MenuScale::fit(640, 400, true);
+        draw(&format, &mut canvas, &scale, &menu, 0, |name| match name {
+            "M_NGAME" => Some(&item),
+            "M_SKULL1" => Some(&skull),
+            "M_THERMO" => Some(&thermo),
+            _ => None,
+        });
+        let at = |x: usize, y: usize| pixels[y * 640 + x];
+        // The first item's 4x4 square covers 8x8 canvas pixels from (160, 128).
+        assert_eq!(at(160, 128), 0xb0);
+        assert_eq!(at(167, 135), 0xb0);
+        assert_eq!(at(168, 128), 0);
+        // The skull sits 32 to the left and 5 up.
+        assert_eq!(at(96, 118), 0xa0);
+        // The slider's dot is on the row below it, three steps in.
+        assert_eq!(at(160 + 16 * 3, 2 * (64 + 32)), 0x70);
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "std")]
+pub mod atlas;
+pub mod automap;
+#[cfg(feature = "std")]
+pub mod browser;
+#[cfg(feature = "std")]
+pub mod captions;
+#[cfg(feature = "std")]
+pub mod colorblind;
+#[cfg(feature = "std")]
+pub mod console;
+#[cfg(feature = "std")]
+pub mod crosshair;
+#[cfg(feature = "std")]
+pub mod debug;
+#[cfg(feature = "std")]
+pub mod disk;
+pub mod draw;
+#[cfg(feature = "std")]
+pub mod endoom;
+#[cfg(feature = "std")]
+pub mod flash;
+#[cfg(feature = "std")]
+pub mod fov;
+pub mod framebuffer;
+#[cfg(feature = "std")]
+pub mod gamma;
+#[cfg(feature = "std")]
+pub mod hardware;
+#[cfg(feature = "std")]
+pub mod hires;
+#[cfg(feature = "std")]
+pub mod hud;
+#[cfg(feature = "std")]
+pub mod interpolate;
+#[cfg(feature = "std")]
+pub mod lighting;
+#[cfg(feature = "std")]
+pub mod loading;
+#[cfg(feature = "std")]
+pub mod mapview;
+#[cfg(feature = "std")]
+pub mod menu;
+#[cfg(feature = "std")]
+pub mod options;
+#[cfg(feature = "std")]
+pub mod overlay;
+pub mod patch;
+#[cfg(feature = "std")]
+pub mod perf;
+#[cfg(feature = "std")]
+pub mod present;
+pub mod projection;
+#[cfg(feature = "std")]
+pub mod results;
+#[cfg(feature = "std")]
+pub mod screen;
+#[cfg(feature = "std")]
+pub mod screenshot;
+#[cfg(feature = "std")]
+pub mod spectre;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "std")]
+pub mod text_entry;
+pub mod thumbnail;
+pub mod tranmap;
+pub mod translation;
+#[cfg(feature = "std")]
+pub mod translucency;
+#[cfg(feature = "std")]
+pub mod view;
+
+use crate::util::angle::Angle;
+use crate::util::fixed::Fixed;
+use crate::wad::map::MapData;
+use core::str::FromStr;
+
+/// Where the scene is seen from. Usually a player's eyes, but any point works.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ViewPoint {
+    pub x: Fixed,
+    pub y: Fixed,
+    pub z: Fixed,
+    pub angle: Angle,
+}
+
+/// A way of turning the current level state into a frame.
+pub trait Renderer {
+    /// Called when the output resolution changes, before the next `render`.
+    fn resize(&mut self, width: usize, height: usize);
+    fn render(&mut self, map: &MapData, view: &ViewPoint);
+}
+
+/// The renderer picked at startup with `--renderer`. `Hardware` is refused until there is a
+/// `hardware::GpuBackend` to draw with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RendererKind {
+    #[default]
+    Software,
+    Hardware,
+}
+
+impl FromStr for RendererKind {
+    type Err = ();
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "software" => Ok(RendererKind::Software),
+            "gl" => Ok(RendererKind::Hardware),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! The options menu drawn in the HUD font at the menus' 320x200 layout.
+//!
+//! The page tabs run along the top with the open one in gold. Below them each row has its
+//! label on the left and its value from `VALUE_X`; sliders are drawn as a bar with a knob,
+//! their number after it. The selected row is gold, and a key waiting to be bound says so.
+//! When the selected control shares a binding with another, a line at the bottom says which.
+
+use super::draw::{draw_raw, Canvas, PixelFormat};
+use super::text::{Text, TextColor};
+use crate::engine::config::Config;
+use crate::engine::options::{
+    OptionsMenu, Page, Widget, LABEL_X, PRESS_A_KEY, SLIDER_WIDTH, STATUS_Y, VALUE_X,
+};
+
+const BAR_COLOR: u8 = 0x60;
+const KNOB_COLOR: u8 = 0xa0;
+const BAR_HEIGHT: usize = 3;
+const KNOB_WIDTH: usize = 3;
+const KNOB_HEIGHT: usize = 7;
+
+/// Draws `menu` with the values in `config` onto a 320 wide `canvas` the caller has cleared.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    text: &Text,
+    menu: &OptionsMenu,
+    config: &Config,
+) {
+    let gold = text.with_color(TextColor::Gold);
+    for page in Page::ALL {
+        let tab = page.tab_rect();
+        let color = match page == menu.page {
+            true => gold,
+            false => text.with_color(TextColor::Gray),
+        };
+        let x = tab.x + (tab.width - color.width(page.title())) / 2;
+        color.draw(format, canvas, x, tab.y, page.title());
+    }
+    for (i, item) in menu.visible() {
+        let Some(row) = menu.row_rect(i) else {
+            continue;
+        };
+        let color = match i == menu.selected {
+            true => gold,
+            false => *text,
+        };
+        color.draw(format, canvas, LABEL_X, row.y, item.label);
+        if menu.capturing && i == menu.selected {
+            color.draw(format, canvas, VALUE_X, row.y, PRESS_A_KEY);
+            continue;
+        }
+        let value = item.value_text(config);
+        let Widget::Slider { min, max, .. } = item.widget else {
+            color.draw(format, canvas, VALUE_X, row.y, &value);
+            continue;
+        };
+        let bar = vec![BAR_COLOR; SLIDER_WIDTH as usize * BAR_HEIGHT];
+        draw_raw(
+            format,
+            canvas,
+            VALUE_X,
+            row.y + 2,
+            SLIDER_WIDTH as usize,
+            &bar,
+        );
+        let along = (config.get_int(item.name).clamp(min, max) - min) * (SLIDER_WIDTH - 1)
+            / (max - min).max(1);
+        let knob = vec![KNOB_COLOR; KNOB_WIDTH * KNOB_HEIGHT];
+        let knob_x = VALUE_X + along - KNOB_WIDTH as i32 / 2;
+        draw_raw(format, canvas, knob_x, row.y, KNOB_WIDTH, &knob);
+        color.draw(format, canvas, VALUE_X + SLIDER_WIDTH + 4, row.y, &value);
+    }
+    if let Some(conflict) = menu.conflict(config).filter(|_| !menu.capturing) {
+        let orange = text.with_color(TextColor::Orange);
+        orange.draw_centered(format, canvas, STATUS_Y, &conflict);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::config::{Configurable, Section};
+    use crate::engine::controls::Controls;
+    use crate::engine::input::KEY_RCTRL;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::hud::HudFont;
+    use crate::renderer::patch::{Patch, Post};
+    use crate::renderer::text::TextColors;
+
+    #[test]
+    fn rows_show_labels_values_and_sliders() {
+        // Every glyph is a 4 pixel wide patch whose single pixel is the font's first red.
+        let font = HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![0xb0],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut config = Config::new();
+        config.register_int("fov", 120, Section::Extended);
+        let mut menu = OptionsMenu::new(Page::Display);
+        menu.selected = 7;
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &text, &menu, &config);
+        let at = |x: i32, y: i32| pixels[(y * 320 + x) as usize];
+        // The open tab is gold and the others gray.
+        let display = Page::Display.tab_rect();
+        assert_eq!(at(display.x + (display.width - 28) / 2, display.y), 0xa0);
+        let hud = Page::Hud.tab_rect();
+        assert_eq!(at(hud.x + (hud.width - 12) / 2, hud.y), 0x50);
+        // The first row is red and its value after it; the selected one is gold.
+        let first = menu.row_rect(0).unwrap();
+        assert_eq!(at(LABEL_X, first.y), 0xb0);
+        assert_eq!(at(VALUE_X, first.y), 0xb0);
+        let fov = menu.row_rect(7).unwrap();
+        assert_eq!(at(LABEL_X, fov.y), 0xa0);
+        // The knob is at the right end of the bar.
+        assert_eq!(at(VALUE_X + SLIDER_WIDTH - 1, fov.y), KNOB_COLOR as u32);
+        assert_eq!(at(VALUE_X, fov.y + 2), BAR_COLOR as u32);
+        assert!((0..320).all(|x| at(x, STATUS_Y) == 0), "no conflicts");
+    }
+
+    #[test]
+    fn a_shared_binding_is_pointed_out() {
+        let font = HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![vec![Post {
+                    top: 0,
+                    pixels: vec![0xb0],
+                }]],
+            })
+        })
+        .unwrap();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut config = Config::new();
+        Controls::register(&mut config);
+        config.set_int("key_use", KEY_RCTRL);
+        let mut menu = OptionsMenu::new(Page::Input);
+        menu.selected = 11;
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        draw(&format, &mut canvas, &text, &menu, &config);
+        // "CTRL ALSO FIRE" is 14 glyphs, 56 pixels, centered.
+        let line = &pixels[STATUS_Y as usize * 320..][..320];
+        assert_eq!(line[132], 0xd0);
+        assert_eq!(line[131], 0);
+    }
+}
@@ -0,0 +1,74 @@
+//! The overlay stage: what is drawn over the finished 3D view, after the player's weapon
+//! sprites and before the status bar and menus.
+//!
+//! Overlays only read game state, through `OverlayState`, and draw in screen space inside
+//! the view window, so the scene renderer and the game never need to know about them.
+
+use super::crosshair::Crosshair;
+use super::draw::{Canvas, PixelFormat};
+use super::hud::{ExtendedHud, HudFont, LINE_HEIGHT};
+use super::perf::{PerfCounters, PerfHud};
+use super::translation::Translation;
+use super::view::ViewWindow;
+use super::ViewPoint;
+use crate::game::stats::LevelStats;
+
+/// Shown while always run is on.
+pub const AUTORUN_INDICATOR: &str = "RUN";
+
+/// What the overlays need from the game for the frame being drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OverlayState {
+    pub health: i32,
+    pub automap_active: bool,
+    pub stats: LevelStats,
+    pub explored: Option<i32>, // Percent of the map seen, when it is tracked.
+    pub always_run: bool,
+    pub view: ViewPoint,
+    pub perf: PerfCounters,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overlays {
+    pub crosshair: Crosshair,
+    pub hud: ExtendedHud,
+    pub perf: PerfHud,
+    pub font: Option<HudFont>, // Text overlays are skipped until the font is loaded.
+    /// What the extended HUD's numbers are drawn through, when the color scheme changes them.
+    pub numbers: Option<Translation>,
+    pub strict_notice: Option<String>,
+}
+
+impl Overlays {
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        window: &ViewWindow,
+        state: &OverlayState,
+    ) {
+        self.crosshair.draw(format, canvas, window, state);
+        if let Some(font) = &self.font {
+            let numbers = self.numbers.as_ref();
+            self.hud.draw(format, canvas, window, state, font, numbers);
+            self.perf.draw(format, canvas, window, state, font);
+            if let Some(notice) = &self.strict_notice {
+                // Below the message line, which vanilla draws at the very top.
+                font.draw_text(
+                    format,
+                    canvas,
+                    window.x as i32,
+                    window.y as i32 + 10,
+                    notice,
+                );
+            }
+            if state.always_run {
+                // Bottom right, clear of the extended HUD and the performance overlay.
+                let right = (window.x + window.width) as i32 - 2;
+                let x = right - font.text_width(AUTORUN_INDICATOR);
+                let y = (window.y + window.height) as i32 - 1 - LINE_HEIGHT;
+                font.draw_text(format, canvas, x, y, AUTORUN_INDICATOR);
+            }
+        }
+    }
+}
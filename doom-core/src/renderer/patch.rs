@@ -0,0 +1,91 @@
+use alloc::vec::Vec;
+
+/// A vertical run of opaque pixels inside a patch column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Post {
+    pub top: u8,         // Row of the first pixel, relative to the top of the patch.
+    pub pixels: Vec<u8>, // Palette indices, top to bottom.
+}
+
+/// A decoded picture in the WAD patch format (sprites, wall patches, menu and HUD graphics).
+///
+/// On disk a patch is a small header, a table of column offsets and, for every column, a list
+/// of posts terminated by a `0xff` top delta. Transparent pixels are simply not covered by any
+/// post, which is why columns are stored as posts rather than a flat bitmap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    pub width: u16,
+    pub height: u16,
+    pub left_offset: i16,
+    pub top_offset: i16,
+    pub columns: Vec<Vec<Post>>,
+}
+
+impl Patch {
+    /// Decodes a patch lump. Returns `None` if any offset points outside of the lump.
+    pub fn parse(lump: &[u8]) -> Option<Self> {
+        let read_u16 = |at: usize| -> Option<u16> {
+            Some(u16::from_le_bytes(lump.get(at..at + 2)?.try_into().ok()?))
+        };
+        let width = read_u16(0)?;
+        let height = read_u16(2)?;
+        let left_offset = read_u16(4)? as i16;
+        let top_offset = read_u16(6)? as i16;
+
+        let mut columns = Vec::with_capacity(width as usize);
+        for column in 0..width as usize {
+            let at = 8 + column * 4;
+            let mut offset = u32::from_le_bytes(lump.get(at..at + 4)?.try_into().ok()?) as usize;
+            let mut posts = Vec::new();
+            loop {
+                let top = *lump.get(offset)?;
+                if top == 0xff {
+                    break;
+                }
+                let length = *lump.get(offset + 1)? as usize;
+                // One padding byte sits on either side of the pixel data.
+                let pixels = lump.get(offset + 3..offset + 3 + length)?.to_vec();
+                posts.push(Post { top, pixels });
+                offset += length + 4;
+            }
+            columns.push(posts);
+        }
+
+        Some(Self {
+            width,
+            height,
+            left_offset,
+            top_offset,
+            columns,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn parses_posts_per_column() {
+        #[rustfmt::skip]
+        let lump = [
+            2, 0, 4, 0, 1, 0, 3, 0, // width 2, height 4, offsets (1, 3)
+            16, 0, 0, 0, 23, 0, 0, 0, // column offsets
+            0, 2, 0, 7, 8, 0, 0xff, // column 0: one post of two pixels
+            3, 1, 0, 9, 0, 0xff, // column 1: one pixel at row 3
+        ];
+        let patch = Patch::parse(&lump).unwrap();
+        assert_eq!((patch.width, patch.height), (2, 4));
+        assert_eq!((patch.left_offset, patch.top_offset), (1, 3));
+        assert_eq!(
+            patch.columns[0],
+            vec![Post {
+                top: 0,
+                pixels: vec![7, 8]
+            }]
+        );
+        assert_eq!(patch.columns[1][0].top, 3);
+        assert!(Patch::parse(&lump[..20]).is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! The performance overlay: frame rate, a frame time graph, the time spent in game tics,
+//! what the renderer drew and how much memory is cached.
+//!
+//! Drawn in the overlay stage like the extended HUD, text in the top right corner of the view
+//! and the graph below it. The `hud_perf` cvar picks the mode and the `stat` console command
+//! cycles it. The overlay reads `PerfCounters` from the frame that was just finished. It
+//! keeps its own history for the graph and never reaches into the game, so turning it on
+//! can't change what happens in a tic.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::{HudFont, LINE_HEIGHT};
+use super::overlay::OverlayState;
+use super::view::ViewWindow;
+use crate::engine::config::{Config, Configurable, Section};
+use crate::engine::console::Commands;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frames in the graph, one pixel column each.
+pub const GRAPH_FRAMES: usize = 128;
+const GRAPH_HEIGHT: i32 = 33; // Two pixels per millisecond, so 60 fps is the top.
+const GREEN: u8 = 112;
+const YELLOW: u8 = 231;
+const RED: u8 = 176;
+
+/// What the last frame cost, filled in by the game loop and the renderer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfCounters {
+    pub frame_time: Duration,
+    pub tic_time: Duration, // Running the game tics of this frame.
+    pub segs: usize,
+    pub visplanes: usize,
+    pub vissprites: usize,
+    pub cache_bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PerfMode {
+    #[default]
+    Off,
+    Fps,
+    Full,
+}
+
+impl PerfMode {
+    const ALL: [PerfMode; 3] = [PerfMode::Off, PerfMode::Fps, PerfMode::Full];
+
+    fn name(self) -> &'static str {
+        match self {
+            PerfMode::Off => "off",
+            PerfMode::Fps => "fps",
+            PerfMode::Full => "full",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PerfHud {
+    pub mode: PerfMode,
+    history: VecDeque<Duration>,
+}
+
+impl PerfHud {
+    /// Called once per frame, whether the overlay is on or not, so the graph is full as soon
+    /// as it is turned on.
+    pub fn record(&mut self, counters: &PerfCounters) {
+        if self.history.len() == GRAPH_FRAMES {
+            self.history.pop_front();
+        }
+        self.history.push_back(counters.frame_time);
+    }
+
+    pub fn fps(&self) -> u32 {
+        let total: Duration = self.history.iter().sum();
+        match total.is_zero() {
+            true => 0,
+            false => (self.history.len() as f64 / total.as_secs_f64()).round() as u32,
+        }
+    }
+
+    /// The text to show, top line first.
+    pub fn lines(&self, state: &OverlayState) -> Vec<String> {
+        let perf = &state.perf;
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        match self.mode {
+            PerfMode::Off => Vec::new(),
+            PerfMode::Fps => vec![format!("FPS {}", self.fps())],
+            PerfMode::Full => vec![
+                format!("FPS {}  FRAME {:.1} MS", self.fps(), ms(perf.frame_time)),
+                format!("TICS {:.2} MS", ms(perf.tic_time)),
+                format!(
+                    "SEGS {}  PLANES {}  SPRITES {}",
+                    perf.segs, perf.visplanes, perf.vissprites
+                ),
+                format!("CACHE {} KB", perf.cache_bytes / 1024),
+            ],
+        }
+    }
+
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        window: &ViewWindow,
+        state: &OverlayState,
+        font: &HudFont,
+    ) {
+        let right = (window.x + window.width) as i32 - 2;
+        let mut y = window.y as i32 + 2;
+        for line in self.lines(state) {
+            font.draw_text(format, canvas, right - font.text_width(&line), y, &line);
+            y += LINE_HEIGHT;
+        }
+        if self.mode == PerfMode::Full {
+            self.draw_graph(
+                format,
+                canvas,
+                right - GRAPH_FRAMES as i32,
+                y + GRAPH_HEIGHT,
+            );
+        }
+    }
+
+    /// One bar per frame, newest on the right, with its bottom on `bottom`.
+    fn draw_graph<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        left: i32,
+        bottom: i32,
+    ) {
+        let (width, height) = (canvas.pitch as i32, canvas.height() as i32);
+        let start = left + (GRAPH_FRAMES - self.history.len()) as i32;
+        for (i, time) in self.history.iter().enumerate() {
+            let ms = time.as_secs_f64() * 1000.0;
+            let color = match ms {
+                ms if ms <= 1000.0 / 60.0 => GREEN,
+                ms if ms <= 1000.0 / 35.0 => YELLOW,
+                _ => RED,
+            };
+            let pixel = format.shade(color, 0);
+            let x = start + i as i32;
+            let bar = ((ms * 2.0).ceil() as i32).clamp(1, GRAPH_HEIGHT);
+            for y in bottom - bar..bottom {
+                if (0..width).contains(&x) && (0..height).contains(&y) {
+                    canvas.pixels[(y * width + x) as usize] = pixel;
+                }
+            }
+        }
+    }
+}
+
+impl Configurable for PerfHud {
+    fn register(config: &mut Config) {
+        config.register_int("hud_perf", 0, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let mode = config.get_int("hud_perf").clamp(0, 2) as usize;
+        Self {
+            mode: PerfMode::ALL[mode],
+            history: VecDeque::with_capacity(GRAPH_FRAMES),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("hud_perf", self.mode as i32);
+    }
+}
+
+/// Registers `stat [off|fps|full]`, which sets `hud_perf` or steps it to the next mode.
+pub fn register_command(commands: &mut Commands) {
+    commands.register(
+        "stat",
+        "stat [off|fps|full]: show the performance overlay",
+        |context, args| {
+            let current = context.config.get_int("hud_perf").clamp(0, 2) as usize;
+            let mode = match args.first() {
+                None => PerfMode::ALL[(current + 1) % PerfMode::ALL.len()],
+                Some(name) => *PerfMode::ALL
+                    .iter()
+                    .find(|mode| mode.name().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("unknown mode '{name}'"))?,
+            };
+            context.config.set_int("hud_perf", mode as i32);
+            context.print(format!("performance overlay {}", mode.name()));
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+
+    fn frames(hud: &mut PerfHud, ms: u64, count: usize) {
+        let counters = PerfCounters {
+            frame_time: Duration::from_millis(ms),
+            ..PerfCounters::default()
+        };
+        for _ in 0..count {
+            hud.record(&counters);
+        }
+    }
+
+    #[test]
+    fn fps_averages_the_graph_history() {
+        let mut hud = PerfHud::default();
+        assert_eq!(hud.fps(), 0);
+        frames(&mut hud, 10, 200);
+        assert_eq!(hud.history.len(), GRAPH_FRAMES);
+        assert_eq!(hud.fps(), 100);
+        hud.mode = PerfMode::Full;
+        let state = OverlayState {
+            perf: PerfCounters {
+                frame_time: Duration::from_micros(12_500),
+                segs: 300,
+                cache_bytes: 4096,
+                ..PerfCounters::default()
+            },
+            ..OverlayState::default()
+        };
+        let lines = hud.lines(&state);
+        assert_eq!(lines[0], "FPS 100  FRAME 12.5 MS");
+        assert_eq!(lines[2], "SEGS 300  PLANES 0  SPRITES 0");
+        assert_eq!(lines[3], "CACHE 4 KB");
+    }
+
+    #[test]
+    fn the_graph_colors_slow_frames() {
+        let mut hud = PerfHud {
+            mode: PerfMode::Full,
+            ..PerfHud::default()
+        };
+        frames(&mut hud, 5, 1);
+        frames(&mut hud, 40, 1);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        hud.draw_graph(&format, &mut canvas, 100, 50);
+        let at = |x: usize, y: usize| pixels[y * 320 + x];
+        assert_eq!(at(100 + GRAPH_FRAMES - 2, 49), GREEN as u32);
+        assert_eq!(at(100 + GRAPH_FRAMES - 2, 39), 0);
+        assert_eq!(at(100 + GRAPH_FRAMES - 1, 50 - 33), RED as u32);
+    }
+
+    #[test]
+    fn stat_cycles_and_sets_the_cvar() {
+        let mut config = Config::new();
+        PerfHud::register(&mut config);
+        let mut commands = Commands::default();
+        register_command(&mut commands);
+        commands.execute("stat", &mut config);
+        assert_eq!(PerfHud::from_config(&config).mode, PerfMode::Fps);
+        commands.execute("stat off", &mut config);
+        assert_eq!(config.get_int("hud_perf"), 0);
+        assert_eq!(
+            commands.execute("stat max", &mut config),
+            ["stat: unknown mode 'max'"]
+        );
+    }
+}
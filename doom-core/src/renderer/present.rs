@@ -0,0 +1,390 @@
+use super::framebuffer::{Framebuffer, Palette, SCREENHEIGHT, SCREENWIDTH};
+use super::gamma::{self, GammaTable};
+use crate::engine::config::{Config, Configurable, Section};
+
+/// WGSL source for GPU presentation backends. The renderer keeps drawing 8-bit indices; the
+/// shader performs the palette lookup and post effects, so a backend only has to upload the
+/// index buffer (and the palette when it changes) as textures each frame. No such backend is
+/// built yet; `SoftwarePresenter` is the CPU reference it has to match.
+pub const PRESENT_SHADER: &str = include_str!("shaders/present.wgsl");
+
+/// Post-processing effect applied while presenting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PostEffect {
+    None = 0,
+    Scanlines = 1,
+    Crt = 2,
+}
+
+/// Options the user controls for how the finished frame reaches the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PresentOptions {
+    pub aspect_correct: bool, // Stretch the 320x200 buffer to 4:3, like a CRT did.
+    pub integer_scale: bool,  // Only scale by whole multiples, leaving a border.
+    pub vsync: bool,          // Honored by backends that own a swap chain.
+    pub widescreen: bool,     // Render a wider buffer that fills the display horizontally.
+    pub effect: PostEffect,
+}
+
+impl Default for PresentOptions {
+    fn default() -> Self {
+        Self {
+            aspect_correct: true,
+            integer_scale: false,
+            vsync: true,
+            widescreen: false,
+            effect: PostEffect::None,
+        }
+    }
+}
+
+impl PresentOptions {
+    /// Width of the buffer the renderer should draw, for a `height` lines high frame shown on a
+    /// `display_width` x `display_height` surface. Without widescreen this is always the 4:3
+    /// width; with it the buffer grows until it matches the display's aspect ratio.
+    pub fn render_width(
+        &self,
+        height: usize,
+        display_width: usize,
+        display_height: usize,
+    ) -> usize {
+        let base = height * SCREENWIDTH / SCREENHEIGHT;
+        if !self.widescreen || display_height == 0 {
+            return base;
+        }
+        let display_lines = if self.aspect_correct {
+            height * 6 / 5
+        } else {
+            height
+        };
+        // Keep it even so the picture stays centered.
+        (display_lines * display_width / display_height).max(base) & !1
+    }
+}
+
+impl Configurable for PresentOptions {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_bool(
+            "video_aspect_correct",
+            defaults.aspect_correct,
+            Section::Extended,
+        );
+        config.register_bool(
+            "video_integer_scale",
+            defaults.integer_scale,
+            Section::Extended,
+        );
+        config.register_bool("video_vsync", defaults.vsync, Section::Extended);
+        config.register_bool("video_widescreen", defaults.widescreen, Section::Extended);
+        config.register_str("video_effect", "none", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            aspect_correct: config.get_bool("video_aspect_correct"),
+            integer_scale: config.get_bool("video_integer_scale"),
+            vsync: config.get_bool("video_vsync"),
+            widescreen: config.get_bool("video_widescreen"),
+            effect: match config.get_str("video_effect").as_str() {
+                "scanlines" => PostEffect::Scanlines,
+                "crt" => PostEffect::Crt,
+                _ => PostEffect::None,
+            },
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("video_aspect_correct", self.aspect_correct);
+        config.set_bool("video_integer_scale", self.integer_scale);
+        config.set_bool("video_vsync", self.vsync);
+        config.set_bool("video_widescreen", self.widescreen);
+        let effect = match self.effect {
+            PostEffect::None => "none",
+            PostEffect::Scanlines => "scanlines",
+            PostEffect::Crt => "crt",
+        };
+        config.set_str("video_effect", effect);
+    }
+}
+
+/// The area of the output surface the frame is drawn into, centered with black borders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    /// Fits a `src_width` x `src_height` frame into a `dst_width` x `dst_height` surface.
+    pub fn fit(
+        src_width: usize,
+        src_height: usize,
+        dst_width: usize,
+        dst_height: usize,
+        options: &PresentOptions,
+    ) -> Self {
+        // With aspect correction every source pixel is 1.2 times taller than it is wide.
+        let display_height = if options.aspect_correct {
+            src_height * 6 / 5
+        } else {
+            src_height
+        };
+
+        let (width, height) = if options.integer_scale {
+            let scale = (dst_width / src_width)
+                .min(dst_height / display_height)
+                .max(1);
+            (src_width * scale, display_height * scale)
+        } else if dst_width * display_height <= dst_height * src_width {
+            (dst_width, dst_width * display_height / src_width)
+        } else {
+            (dst_height * src_width / display_height, dst_height)
+        };
+
+        Self {
+            x: dst_width.saturating_sub(width) / 2,
+            y: dst_height.saturating_sub(height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// The pixel of a `src_width` x `src_height` frame shown at (`x`, `y`) on the surface, for
+    /// the mouse. Points on the border are outside the frame.
+    pub fn to_frame(&self, x: i32, y: i32, src_width: usize, src_height: usize) -> (i32, i32) {
+        let (width, height) = (self.width.max(1) as i64, self.height.max(1) as i64);
+        let x = (i64::from(x) - self.x as i64) * src_width as i64;
+        let y = (i64::from(y) - self.y as i64) * src_height as i64;
+        (x.div_euclid(width) as i32, y.div_euclid(height) as i32)
+    }
+}
+
+/// A backend that turns the indexed frame into something visible.
+pub trait Presenter {
+    fn options(&self) -> &PresentOptions;
+    fn set_options(&mut self, options: PresentOptions);
+    /// Called when the output surface changes size.
+    fn resize(&mut self, width: usize, height: usize);
+    /// The gamma correction to apply while converting to RGB, from `GammaOptions::table`.
+    fn set_gamma(&mut self, table: GammaTable);
+    fn present(&mut self, frame: &Framebuffer, palette: &Palette);
+    /// Presents a frame drawn by the true-color path, `0x00RRGGBB` per pixel.
+    fn present_true_color(&mut self, frame: &[u32], width: usize, height: usize);
+}
+
+/// Presenter that performs the palette conversion on the CPU into a `0x00RRGGBB` buffer.
+///
+/// This is the reference the GPU shader is expected to match, and the fallback when no GPU
+/// device is available. The CRT effect has no CPU equivalent and is drawn as scanlines.
+pub struct SoftwarePresenter {
+    options: PresentOptions,
+    width: usize,
+    height: usize,
+    target: Vec<u32>,
+    gamma: Option<GammaTable>,
+}
+
+impl SoftwarePresenter {
+    pub fn new(width: usize, height: usize, options: PresentOptions) -> Self {
+        Self {
+            options,
+            width,
+            height,
+            target: vec![0; width * height],
+            gamma: None,
+        }
+    }
+
+    /// The last presented image, `width * height` packed pixels.
+    pub fn target(&self) -> &[u32] {
+        &self.target
+    }
+}
+
+impl Presenter for SoftwarePresenter {
+    fn options(&self) -> &PresentOptions {
+        &self.options
+    }
+
+    fn set_options(&mut self, options: PresentOptions) {
+        self.options = options;
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.target = vec![0; width * height];
+    }
+
+    fn set_gamma(&mut self, table: GammaTable) {
+        self.gamma = Some(table);
+    }
+
+    fn present(&mut self, frame: &Framebuffer, palette: &Palette) {
+        let colors = match &self.gamma {
+            Some(table) => palette.corrected(table).to_rgb32(),
+            None => palette.to_rgb32(),
+        };
+        self.blit(frame.width(), frame.height(), |x, y| {
+            colors[frame.row(y)[x] as usize]
+        });
+    }
+
+    fn present_true_color(&mut self, frame: &[u32], width: usize, height: usize) {
+        let table = self.gamma;
+        self.blit(width, height, |x, y| {
+            let color = frame[y * width + x];
+            table.map_or(color, |table| gamma::correct_rgb32(&table, color))
+        });
+    }
+}
+
+impl SoftwarePresenter {
+    /// Scales a `src_width` x `src_height` image into the target, reading pixels from `source`.
+    fn blit(&mut self, src_width: usize, src_height: usize, source: impl Fn(usize, usize) -> u32) {
+        let view = Viewport::fit(
+            src_width,
+            src_height,
+            self.width,
+            self.height,
+            &self.options,
+        );
+        let scanlines = self.options.effect != PostEffect::None && view.height >= src_height * 2;
+
+        self.target.fill(0);
+        for y in 0..view.height.min(self.height - view.y) {
+            // Position inside the source row in 1/256ths, to find the lower half for scanlines.
+            let src_y256 = y * src_height * 256 / view.height;
+            let dim = scanlines && src_y256 % 256 >= 128;
+            let out = &mut self.target[(view.y + y) * self.width..][..self.width];
+            for x in 0..view.width.min(self.width - view.x) {
+                let color = source(x * src_width / view.width, src_y256 / 256);
+                out[view.x + x] = if dim { darken(color) } else { color };
+            }
+        }
+    }
+}
+
+/// Scales each channel of a packed pixel to 3/4 intensity.
+fn darken(color: u32) -> u32 {
+    let r = (color >> 16 & 0xff) * 3 / 4;
+    let g = (color >> 8 & 0xff) * 3 / 4;
+    let b = (color & 0xff) * 3 / 4;
+    r << 16 | g << 8 | b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aspect_corrected_fit_is_4_by_3() {
+        let view = Viewport::fit(320, 200, 1920, 1080, &PresentOptions::default());
+        assert_eq!((view.width, view.height), (1440, 1080));
+        assert_eq!((view.x, view.y), (240, 0));
+    }
+
+    #[test]
+    fn integer_scale_leaves_a_border() {
+        let options = PresentOptions {
+            integer_scale: true,
+            ..PresentOptions::default()
+        };
+        let view = Viewport::fit(320, 200, 1920, 1080, &options);
+        // 240 * 4 = 960 is the largest whole multiple that fits in 1080 lines.
+        assert_eq!((view.width, view.height), (1280, 960));
+        assert_eq!((view.x, view.y), (320, 60));
+        assert_eq!(view.to_frame(320 + 8, 60 + 9, 320, 200), (2, 1));
+        assert_eq!(view.to_frame(0, 0, 320, 200), (-80, -13));
+    }
+
+    #[test]
+    fn widescreen_render_width_matches_the_display() {
+        let mut options = PresentOptions::default();
+        assert_eq!(options.render_width(200, 1920, 1080), 320);
+        options.widescreen = true;
+        assert_eq!(options.render_width(200, 1920, 1080), 426);
+        // A widescreen buffer fills a 16:9 display edge to edge, give or take rounding.
+        let view = Viewport::fit(426, 200, 1920, 1080, &options);
+        assert_eq!(view.height, 1080);
+        assert!(view.x <= 2);
+        // Never narrower than 4:3.
+        assert_eq!(options.render_width(200, 1024, 1280), 320);
+    }
+
+    #[test]
+    fn options_round_trip_through_the_config() {
+        let mut config = Config::new();
+        PresentOptions::register(&mut config);
+        assert_eq!(
+            PresentOptions::from_config(&config),
+            PresentOptions::default()
+        );
+        let options = PresentOptions {
+            integer_scale: true,
+            effect: PostEffect::Crt,
+            ..PresentOptions::default()
+        };
+        options.to_config(&mut config);
+        assert_eq!(PresentOptions::from_config(&config), options);
+    }
+
+    #[test]
+    fn software_presenter_maps_indices_through_the_palette() {
+        let mut frame = Framebuffer::new(2, 1);
+        frame.pixels_mut().copy_from_slice(&[0, 255]);
+        let options = PresentOptions {
+            aspect_correct: false,
+            ..PresentOptions::default()
+        };
+        let mut presenter = SoftwarePresenter::new(4, 2, options);
+        presenter.present(&frame, &Palette::default());
+        assert_eq!(
+            presenter.target(),
+            &[0, 0, 0xffffff, 0xffffff, 0, 0, 0xffffff, 0xffffff]
+        );
+    }
+
+    #[test]
+    fn software_presenter_applies_gamma() {
+        let mut frame = Framebuffer::new(1, 1);
+        frame.pixels_mut()[0] = 1;
+        let mut presenter = SoftwarePresenter::new(1, 1, PresentOptions::default());
+        presenter.set_gamma(gamma::vanilla_table(4));
+        presenter.present(&frame, &Palette::default());
+        assert_eq!(presenter.target(), &[0x171717]);
+        presenter.present_true_color(&[0x000001], 1, 1);
+        assert_eq!(presenter.target(), &[0x101017]);
+    }
+
+    #[test]
+    fn scanlines_darken_the_lower_half_of_each_row() {
+        let mut frame = Framebuffer::new(1, 1);
+        frame.fill(255);
+        let options = PresentOptions {
+            aspect_correct: false,
+            effect: PostEffect::Scanlines,
+            ..PresentOptions::default()
+        };
+        let mut presenter = SoftwarePresenter::new(2, 2, options);
+        presenter.present(&frame, &Palette::default());
+        assert_eq!(
+            presenter.target(),
+            &[0xffffff, 0xffffff, 0xbfbfbf, 0xbfbfbf]
+        );
+    }
+
+    #[test]
+    fn true_color_frames_are_scaled_unchanged() {
+        let options = PresentOptions {
+            aspect_correct: false,
+            ..PresentOptions::default()
+        };
+        let mut presenter = SoftwarePresenter::new(2, 2, options);
+        presenter.present_true_color(&[0x123456], 1, 1);
+        assert_eq!(presenter.target(), &[0x123456; 4]);
+    }
+}
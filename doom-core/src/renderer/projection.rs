@@ -0,0 +1,120 @@
+use super::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+use crate::util::fixed::{Fixed, FRACBITS};
+
+/// Height of the status bar graphics at 1x scale.
+pub const ST_HEIGHT: usize = 32;
+
+/// The screen-space projection the 3D view is rendered with.
+///
+/// Vanilla uses `centerxfrac` as the projection constant, tying the horizontal field of view to
+/// a 320 pixel wide screen. Here the constant is derived from the width a 4:3 screen of the same
+/// height would have, so a wider buffer keeps the vanilla vertical field of view and simply
+/// shows more of the world at the sides instead of stretching it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Projection {
+    pub width: usize,
+    pub height: usize,
+    pub center_x: i32,
+    pub center_y: i32,
+    pub center_x_frac: Fixed,
+    pub center_y_frac: Fixed,
+    pub projection: Fixed,
+    /// The projection at vanilla's 90 degrees. Weapon sprites are scaled by this whatever the
+    /// field of view, so a wide one doesn't shrink the gun and a narrow one doesn't blow it up.
+    pub base_projection: Fixed,
+}
+
+impl Projection {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_base(width, height, height * SCREENWIDTH / SCREENHEIGHT)
+    }
+
+    /// A projection for a view that isn't the whole screen, where `base_width` is the width of
+    /// its 4:3 part: a shrunken window keeps the field of view of the full screen.
+    pub fn with_base(width: usize, height: usize, base_width: usize) -> Self {
+        let center_x = width as i32 / 2;
+        let center_y = height as i32 / 2;
+        let projection = (base_width as i32 / 2) << FRACBITS;
+        Self {
+            width,
+            height,
+            center_x,
+            center_y,
+            center_x_frac: center_x << FRACBITS,
+            center_y_frac: center_y << FRACBITS,
+            projection,
+            base_projection: projection,
+        }
+    }
+
+    /// The same projection with a horizontal field of view of `degrees` across the 4:3 part
+    /// of the view, instead of 90.
+    #[cfg(feature = "std")]
+    pub fn with_fov(self, degrees: u32) -> Self {
+        let half = f64::from(degrees).to_radians() / 2.0;
+        let projection = f64::from(self.base_projection) / half.tan();
+        Self {
+            projection: projection.round() as Fixed,
+            ..self
+        }
+    }
+
+    /// What a weapon sprite's pixels are scaled by: 1.0 on a 320 pixel wide 4:3 view.
+    pub fn weapon_scale(&self) -> Fixed {
+        self.base_projection / (SCREENWIDTH as i32 / 2)
+    }
+
+    /// Whether the buffer is wider than 4:3.
+    pub fn is_widescreen(&self) -> bool {
+        self.width * SCREENHEIGHT > self.height * SCREENWIDTH
+    }
+
+    /// Left edge of a 320 pixel wide element (status bar, intermission, menus) centered on the
+    /// screen. The space either side is filled with the border flat.
+    pub fn centered_x(&self) -> usize {
+        self.width.saturating_sub(SCREENWIDTH) / 2
+    }
+
+    /// Top edge of the status bar.
+    pub fn status_bar_y(&self) -> usize {
+        self.height.saturating_sub(ST_HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanilla_projection_is_centerxfrac() {
+        let projection = Projection::new(SCREENWIDTH, SCREENHEIGHT);
+        assert_eq!(projection.projection, projection.center_x_frac);
+        assert!(!projection.is_widescreen());
+        assert_eq!(projection.centered_x(), 0);
+        let short = Projection::new(SCREENWIDTH, ST_HEIGHT / 2);
+        assert_eq!(short.status_bar_y(), 0);
+    }
+
+    #[test]
+    fn widescreen_keeps_the_vertical_field_of_view() {
+        let projection = Projection::new(426, SCREENHEIGHT);
+        assert_eq!(projection.projection, 160 << FRACBITS);
+        assert_eq!(projection.center_x, 213);
+        assert!(projection.is_widescreen());
+        assert_eq!(projection.centered_x(), 53);
+        assert_eq!(projection.status_bar_y(), 168);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn field_of_view_changes_only_the_projection() {
+        let projection = Projection::new(SCREENWIDTH, SCREENHEIGHT);
+        assert_eq!(projection.with_fov(90), projection);
+        let wide = projection.with_fov(120);
+        // tan(60) is about 1.732.
+        assert_eq!(wide.projection >> FRACBITS, 92);
+        assert_eq!(wide.weapon_scale(), projection.weapon_scale());
+        assert_eq!(projection.weapon_scale(), 1 << FRACBITS);
+        assert!(projection.with_fov(75).projection > projection.projection);
+    }
+}
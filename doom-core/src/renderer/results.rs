@@ -0,0 +1,81 @@
+//! The results screen: the lifetime statistics in the HUD font, shown after the last level's
+//! text screen and from the menu.
+//!
+//! A title centered at the top and one line per statistic below it, at the unscaled 320x200
+//! layout the menus use. Lines that don't fit are left off rather than scrolled, which only
+//! happens with many kinds of monster killed.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::{HudFont, LINE_HEIGHT};
+
+pub const TITLE: &str = "LIFETIME STATISTICS";
+const TITLE_Y: i32 = 8;
+const LINES_X: i32 = 24;
+const LINES_Y: i32 = 28;
+
+/// Draws the screen onto a 320 wide `canvas`, which the caller has cleared or filled with a
+/// background flat.
+pub fn draw<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    font: &HudFont,
+    lines: &[String],
+) {
+    let x = (canvas.pitch as i32 - font.text_width(TITLE)) / 2;
+    font.draw_text(format, canvas, x, TITLE_Y, TITLE);
+    let bottom = canvas.height() as i32 - LINE_HEIGHT;
+    for (i, line) in lines.iter().enumerate() {
+        let y = LINES_Y + i as i32 * LINE_HEIGHT;
+        if y > bottom {
+            break;
+        }
+        font.draw_text(format, canvas, LINES_X, y, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::{Patch, Post};
+
+    #[test]
+    fn lines_are_drawn_below_a_centered_title() {
+        // Every glyph is a 4 pixel wide patch whose single pixel is its character code.
+        let font = HudFont::load(|name| {
+            let code: u8 = name[5..].parse().ok()?;
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![code],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap();
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        let lines: Vec<String> = (0..40).map(|i| format!("line {i}")).collect();
+        draw(&format, &mut canvas, &font, &lines);
+
+        // 19 characters of 4 pixels make the title 76 wide.
+        assert_eq!(pixels[TITLE_Y as usize * 320 + 122], b'L' as u32);
+        let row = |line: usize| (LINES_Y as usize + line * LINE_HEIGHT as usize) * 320;
+        assert_eq!(pixels[row(0) + LINES_X as usize], b'L' as u32);
+        assert_eq!(pixels[row(18) + LINES_X as usize], b'L' as u32);
+        // The 20th line would only have its top row on screen, so it is left off.
+        assert_eq!(pixels[row(19) + LINES_X as usize], 0);
+    }
+}
@@ -0,0 +1,158 @@
+use super::fov::{FieldOfView, ViewAngles};
+use super::framebuffer::{Framebuffer, SCREENHEIGHT};
+use super::present::PresentOptions;
+use super::projection::Projection;
+use super::view::{ViewSize, ViewWindow};
+
+/// The render buffer and everything derived from its size.
+///
+/// When the window changes size the buffer may need a different width (widescreen) and the
+/// projection tables must follow; `resize` does both at once so the renderer never draws with
+/// tables that don't match its buffer. The same goes for the view size and detail level,
+/// which decide the window inside the buffer the projection is for, and the field of view.
+pub struct Screen {
+    pub frame: Framebuffer,
+    pub window: ViewWindow,
+    pub projection: Projection,
+    pub angles: ViewAngles,
+    options: PresentOptions,
+    view: ViewSize,
+    fov: FieldOfView,
+    display: (usize, usize),
+}
+
+impl Screen {
+    pub fn new(options: PresentOptions, display_width: usize, display_height: usize) -> Self {
+        let width = options.render_width(SCREENHEIGHT, display_width, display_height);
+        let view = ViewSize::default();
+        let window = view.window(width, SCREENHEIGHT);
+        let projection = window.projection();
+        Self {
+            frame: Framebuffer::new(width, SCREENHEIGHT),
+            window,
+            projection,
+            angles: ViewAngles::new(&projection),
+            options,
+            view,
+            fov: FieldOfView::default(),
+            display: (display_width, display_height),
+        }
+    }
+
+    pub fn options(&self) -> &PresentOptions {
+        &self.options
+    }
+
+    pub fn set_options(&mut self, options: PresentOptions) {
+        self.options = options;
+        let (width, height) = self.display;
+        self.reallocate(width, height);
+    }
+
+    pub fn view_size(&self) -> &ViewSize {
+        &self.view
+    }
+
+    /// Applies a new screen size or detail level, recomputing the window and the projection.
+    /// Call between frames, as vanilla defers `R_ExecuteSetViewSize` to the next one.
+    pub fn set_view_size(&mut self, view: ViewSize) {
+        self.view = view;
+        self.update_window();
+    }
+
+    pub fn fov(&self) -> FieldOfView {
+        self.fov
+    }
+
+    /// Applies a new field of view, regenerating the projection and the angle tables.
+    pub fn set_fov(&mut self, fov: FieldOfView) {
+        if fov != self.fov {
+            self.fov = fov;
+            self.update_window();
+        }
+    }
+
+    pub fn display_size(&self) -> (usize, usize) {
+        self.display
+    }
+
+    /// Handles a new output surface size. Returns true if the render buffer was reallocated.
+    pub fn resize(&mut self, display_width: usize, display_height: usize) -> bool {
+        self.display = (display_width, display_height);
+        self.reallocate(display_width, display_height)
+    }
+
+    fn reallocate(&mut self, display_width: usize, display_height: usize) -> bool {
+        let width = self
+            .options
+            .render_width(SCREENHEIGHT, display_width, display_height);
+        if width == self.frame.width() {
+            return false;
+        }
+        self.frame = Framebuffer::new(width, SCREENHEIGHT);
+        self.update_window();
+        true
+    }
+
+    fn update_window(&mut self) {
+        self.window = self.view.window(self.frame.width(), self.frame.height());
+        self.projection = self.window.projection().with_fov(self.fov.degrees);
+        self.angles = ViewAngles::new(&self.projection);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizing_a_widescreen_display_reallocates_buffer_and_projection() {
+        let options = PresentOptions {
+            widescreen: true,
+            ..PresentOptions::default()
+        };
+        let mut screen = Screen::new(options, 960, 720);
+        assert_eq!(screen.frame.width(), 320);
+        assert!(screen.resize(1920, 1080));
+        assert_eq!(screen.frame.width(), 426);
+        assert_eq!(screen.projection.width, 426);
+        // Same aspect ratio, bigger window: nothing to do.
+        assert!(!screen.resize(3840, 2160));
+    }
+
+    #[test]
+    fn turning_widescreen_off_shrinks_the_buffer() {
+        let options = PresentOptions {
+            widescreen: true,
+            ..PresentOptions::default()
+        };
+        let mut screen = Screen::new(options, 1920, 1080);
+        screen.set_options(PresentOptions::default());
+        assert_eq!(screen.frame.width(), 320);
+    }
+
+    #[test]
+    fn changing_the_view_size_follows_the_buffer() {
+        let mut screen = Screen::new(PresentOptions::default(), 640, 480);
+        let mut view = *screen.view_size();
+        view.shrink();
+        screen.set_view_size(view);
+        assert_eq!(screen.window.width, 288);
+        assert_eq!(screen.projection.width, 288);
+        assert_eq!(screen.projection.height, 144);
+    }
+
+    #[test]
+    fn the_field_of_view_survives_resizes() {
+        let mut screen = Screen::new(PresentOptions::default(), 640, 480);
+        let vanilla = screen.angles.clone();
+        screen.set_fov(FieldOfView::new(110));
+        assert!(screen.projection.projection < screen.projection.base_projection);
+        assert!(screen.angles.clip_angle > vanilla.clip_angle);
+        let mut view = *screen.view_size();
+        view.shrink();
+        screen.set_view_size(view);
+        assert_eq!(screen.projection, screen.window.projection().with_fov(110));
+        assert_eq!(screen.angles.x_to_view_angle.len(), 289);
+    }
+}
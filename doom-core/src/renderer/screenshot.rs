@@ -0,0 +1,275 @@
+use super::framebuffer::{Framebuffer, Palette};
+use crate::engine::config::{Config, Configurable, Section};
+use crate::engine::input::{Event, Key, KEY_PRTSCR};
+use crate::util::png;
+use crate::util::time::timestamp;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    Png,
+    /// `DOOMnn.PCX`, exactly as vanilla writes them with `-devparm`.
+    Pcx,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScreenshotConfig {
+    pub key: Key,
+    pub folder: PathBuf,
+    pub format: ScreenshotFormat,
+    pub burst_interval: u32, // Capture every Nth tic while burst mode is on.
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            key: KEY_PRTSCR,
+            folder: PathBuf::from("screenshots"),
+            format: ScreenshotFormat::Png,
+            burst_interval: 35,
+        }
+    }
+}
+
+impl Configurable for ScreenshotConfig {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_int("key_screenshot", defaults.key, Section::Extended);
+        config.register_str("screenshot_folder", "screenshots", Section::Extended);
+        config.register_str("screenshot_format", "png", Section::Extended);
+        config.register_int("screenshot_burst_interval", 35, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            key: config.get_int("key_screenshot"),
+            folder: PathBuf::from(config.get_str("screenshot_folder")),
+            format: match config.get_str("screenshot_format").as_str() {
+                "pcx" => ScreenshotFormat::Pcx,
+                _ => ScreenshotFormat::Png,
+            },
+            burst_interval: config.get_int("screenshot_burst_interval").max(0) as u32,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("key_screenshot", self.key);
+        config.set_str("screenshot_folder", &self.folder.to_string_lossy());
+        let format = match self.format {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Pcx => "pcx",
+        };
+        config.set_str("screenshot_format", format);
+        config.set_int("screenshot_burst_interval", self.burst_interval as i32);
+    }
+}
+
+/// Captures the finished, palette-applied frame to disk on demand or at a fixed tic interval.
+pub struct Screenshots {
+    config: ScreenshotConfig,
+    pending: bool,
+    burst: bool,
+}
+
+impl Screenshots {
+    pub fn new(config: ScreenshotConfig) -> Self {
+        Self {
+            config,
+            pending: false,
+            burst: false,
+        }
+    }
+
+    /// Requests a capture of the next finished frame when the bound key is pressed.
+    pub fn responder(&mut self, event: &Event) -> bool {
+        if *event == Event::KeyDown(self.config.key) {
+            self.pending = true;
+            return true;
+        }
+        false
+    }
+
+    pub fn request(&mut self) {
+        self.pending = true;
+    }
+
+    pub fn set_burst(&mut self, burst: bool) {
+        self.burst = burst;
+    }
+
+    pub fn wants_capture(&self, tic: u32) -> bool {
+        self.pending
+            || (self.burst
+                && self.config.burst_interval > 0
+                && tic.is_multiple_of(self.config.burst_interval))
+    }
+
+    /// Writes the frame if a capture is due on this tic, returning the file written.
+    pub fn capture(
+        &mut self,
+        frame: &Framebuffer,
+        palette: &Palette,
+        tic: u32,
+    ) -> io::Result<Option<PathBuf>> {
+        if !self.wants_capture(tic) {
+            return Ok(None);
+        }
+        let burst = self.burst && !self.pending;
+        self.pending = false;
+        fs::create_dir_all(&self.config.folder)?;
+
+        let (path, data) = match self.config.format {
+            ScreenshotFormat::Png => {
+                let name = if burst {
+                    format!("burst-{tic:06}.png")
+                } else {
+                    format!("doom-{}.png", timestamp(SystemTime::now()))
+                };
+                let colors: [[u8; 3]; 256] = std::array::from_fn(|i| palette.rgb(i as u8));
+                let data =
+                    png::encode_indexed(frame.width(), frame.height(), frame.pixels(), &colors);
+                (unused_path(&self.config.folder, &name), data)
+            }
+            ScreenshotFormat::Pcx => (
+                next_pcx_path(&self.config.folder)?,
+                encode_pcx(frame, palette),
+            ),
+        };
+        fs::write(&path, data)?;
+        Ok(Some(path))
+    }
+}
+
+/// Encodes a frame the way vanilla's `WritePCXfile` does: run length encoding that only ever
+/// escapes bytes, never compresses runs, followed by the palette.
+pub fn encode_pcx(frame: &Framebuffer, palette: &Palette) -> Vec<u8> {
+    let (width, height) = (frame.width() as u16, frame.height() as u16);
+    let mut out = vec![0x0a, 5, 1, 8];
+    for value in [0, 0, width - 1, height - 1, width, height] {
+        out.extend(value.to_le_bytes());
+    }
+    out.extend([0; 49]); // EGA palette and a reserved byte
+    out.push(1); // color planes
+    out.extend(width.to_le_bytes()); // bytes per line
+    out.extend(2u16.to_le_bytes()); // palette type
+    out.extend([0; 58]);
+
+    for &pixel in frame.pixels() {
+        if pixel & 0xc0 == 0xc0 {
+            out.push(0xc1);
+        }
+        out.push(pixel);
+    }
+    out.push(0x0c);
+    for index in 0..=255 {
+        out.extend(palette.rgb(index));
+    }
+    out
+}
+
+/// Finds the first free `DOOMnn.PCX`, like vanilla. Fails once all 100 are taken.
+fn next_pcx_path(folder: &Path) -> io::Result<PathBuf> {
+    (0..100)
+        .map(|n| folder.join(format!("DOOM{n:02}.PCX")))
+        .find(|path| !path.exists())
+        .ok_or_else(|| io::Error::other("M_ScreenShot: Couldn't create a PCX"))
+}
+
+/// Appends `-1`, `-2`, ... to the file stem until the name is unused.
+fn unused_path(folder: &Path, name: &str) -> PathBuf {
+    let path = folder.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    (1..)
+        .map(|n| folder.join(format!("{stem}-{n}.{extension}")))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("doom-rs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        folder
+    }
+
+    #[test]
+    fn key_press_captures_the_next_frame() {
+        let folder = temp_folder("shot");
+        let mut shots = Screenshots::new(ScreenshotConfig {
+            folder: folder.clone(),
+            ..ScreenshotConfig::default()
+        });
+        let frame = Framebuffer::new(4, 2);
+        assert!(shots
+            .capture(&frame, &Palette::default(), 1)
+            .unwrap()
+            .is_none());
+        assert!(shots.responder(&Event::KeyDown(KEY_PRTSCR)));
+        let path = shots
+            .capture(&frame, &Palette::default(), 2)
+            .unwrap()
+            .unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"\x89PNG"));
+        assert!(shots
+            .capture(&frame, &Palette::default(), 3)
+            .unwrap()
+            .is_none());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn burst_mode_captures_every_nth_tic() {
+        let folder = temp_folder("burst");
+        let mut shots = Screenshots::new(ScreenshotConfig {
+            folder: folder.clone(),
+            burst_interval: 10,
+            ..ScreenshotConfig::default()
+        });
+        shots.set_burst(true);
+        let frame = Framebuffer::new(1, 1);
+        let written: Vec<_> = (0..25)
+            .filter_map(|tic| shots.capture(&frame, &Palette::default(), tic).unwrap())
+            .collect();
+        assert_eq!(written.len(), 3);
+        assert!(written[2].ends_with("burst-000020.png"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn pcx_escapes_high_bytes_and_numbers_files() {
+        let mut frame = Framebuffer::new(2, 1);
+        frame.pixels_mut().copy_from_slice(&[0x10, 0xc5]);
+        let pcx = encode_pcx(&frame, &Palette::default());
+        assert_eq!(pcx.len(), 128 + 3 + 1 + 768);
+        assert_eq!(&pcx[128..131], &[0x10, 0xc1, 0xc5]);
+
+        let folder = temp_folder("pcx");
+        let mut shots = Screenshots::new(ScreenshotConfig {
+            folder: folder.clone(),
+            format: ScreenshotFormat::Pcx,
+            ..ScreenshotConfig::default()
+        });
+        shots.request();
+        let first = shots
+            .capture(&frame, &Palette::default(), 0)
+            .unwrap()
+            .unwrap();
+        shots.request();
+        let second = shots
+            .capture(&frame, &Palette::default(), 0)
+            .unwrap()
+            .unwrap();
+        assert!(first.ends_with("DOOM00.PCX"));
+        assert!(second.ends_with("DOOM01.PCX"));
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
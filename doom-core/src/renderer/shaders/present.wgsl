@@ -0,0 +1,52 @@
+// Presents the 8-bit indexed framebuffer: palette lookup and post effects run per output pixel.
+//
+// `frame` holds the palette indices exactly as the software renderer wrote them, `palette` is a
+// 256x1 texture with the active PLAYPAL entry (already gamma corrected). The vertex stage draws a
+// single oversized triangle; the viewport set by the host does the aspect correction and scaling.
+
+struct Params {
+    // Output pixels per source pixel, vertically. Drives the scanline pattern.
+    scale: f32,
+    // 0 = none, 1 = scanlines, 2 = crt
+    effect: u32,
+}
+
+@group(0) @binding(0) var frame: texture_2d<u32>;
+@group(0) @binding(1) var palette: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(frame));
+    let texel = vec2<i32>(min(in.uv * size, size - vec2<f32>(1.0)));
+    let index = textureLoad(frame, texel, 0).r;
+    var color = textureLoad(palette, vec2<i32>(i32(index), 0), 0).rgb;
+
+    if (params.effect != 0u && params.scale >= 2.0) {
+        // Darken the lower half of every source row.
+        let sub_row = fract(in.uv.y * size.y);
+        if (sub_row >= 0.5) {
+            color = color * 0.75;
+        }
+    }
+    if (params.effect == 2u) {
+        // Slight barrel-style vignette on top of the scanlines.
+        let centered = in.uv * 2.0 - vec2<f32>(1.0);
+        color = color * (1.0 - 0.15 * dot(centered, centered));
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,157 @@
+//! How spectres and partially invisible players are drawn.
+//!
+//! Vanilla's fuzz smears the pixels behind the sprite up and down and darkens them, which at
+//! high resolutions turns into a fine shimmer that is hard to read. Two alternatives draw the
+//! same sprites differently: `translucent` draws the sprite itself see-through, through the
+//! `TranMap` in the indexed renderer, and `shadow` darkens the sprite's silhouette evenly.
+//! Either makes a spectre easier to see than vanilla allows, so strict mode and demo playback
+//! always draw the fuzz.
+
+use super::draw::{self, Canvas, Column, Fuzz, PixelFormat};
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::strict::{Feature, StrictMode};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FuzzStyle {
+    #[default]
+    Vanilla,
+    Translucent,
+    Shadow,
+}
+
+impl FuzzStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            FuzzStyle::Vanilla => "vanilla",
+            FuzzStyle::Translucent => "translucent",
+            FuzzStyle::Shadow => "shadow",
+        }
+    }
+
+    /// The style to draw with: vanilla whenever strict mode blocks it or a demo is playing.
+    pub fn held(self, strict: &StrictMode, demo: bool) -> Self {
+        if demo || !strict.allows(Feature::SpectreStyle) {
+            FuzzStyle::Vanilla
+        } else {
+            self
+        }
+    }
+}
+
+impl Configurable for FuzzStyle {
+    fn register(config: &mut Config) {
+        config.register_str("fuzz_style", "vanilla", Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        match config.get_str("fuzz_style").as_str() {
+            "translucent" => FuzzStyle::Translucent,
+            "shadow" => FuzzStyle::Shadow,
+            _ => FuzzStyle::Vanilla,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_str("fuzz_style", self.name());
+    }
+}
+
+/// The spectre column drawer, keeping vanilla's fuzz position between columns.
+#[derive(Default)]
+pub struct Spectres {
+    pub style: FuzzStyle,
+    fuzz: Fuzz,
+}
+
+impl Spectres {
+    pub fn new(style: FuzzStyle) -> Self {
+        Self {
+            style,
+            fuzz: Fuzz::default(),
+        }
+    }
+
+    /// Draws one column of a fuzzy sprite. Only the translucent style reads the texels.
+    pub fn draw_column<F: PixelFormat>(
+        &mut self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        column: &Column,
+    ) {
+        match self.style {
+            FuzzStyle::Vanilla => self
+                .fuzz
+                .draw_column(format, canvas, column.x, column.yl, column.yh),
+            FuzzStyle::Translucent => draw::draw_translucent_column(format, canvas, column),
+            FuzzStyle::Shadow => {
+                draw::draw_shadow_column(format, canvas, column.x, column.yl, column.yh)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::util::fixed::FRACUNIT;
+
+    fn draw(style: FuzzStyle) -> [u32; 4] {
+        let mut palette = [0; 256];
+        palette[1] = 0x0000ff;
+        let format = TrueColor::new(palette);
+        let mut pixels = [0x808080, 0xff0000, 0x00ff00, 0x808080];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 1,
+        };
+        let source = [1; 4];
+        let column = Column {
+            x: 0,
+            yl: 1,
+            yh: 2,
+            center_y: 0,
+            texture_mid: 0,
+            iscale: FRACUNIT,
+            light: 0,
+            source: &source,
+        };
+        Spectres::new(style).draw_column(&format, &mut canvas, &column);
+        pixels
+    }
+
+    #[test]
+    fn styles_draw_differently() {
+        // The fuzz samples the row below, then the row above it just darkened.
+        assert_eq!(
+            draw(FuzzStyle::Vanilla),
+            [0x808080, 0x00cf00, 0x00a800, 0x808080]
+        );
+        assert_eq!(
+            draw(FuzzStyle::Translucent),
+            [0x808080, 0x7f007f, 0x007f7f, 0x808080]
+        );
+        assert_eq!(
+            draw(FuzzStyle::Shadow),
+            [0x808080, 0xcf0000, 0x00cf00, 0x808080]
+        );
+    }
+
+    #[test]
+    fn strict_mode_and_demos_hold_the_fuzz() {
+        let strict = StrictMode { enabled: true };
+        let relaxed = StrictMode::default();
+        assert_eq!(FuzzStyle::Shadow.held(&relaxed, false), FuzzStyle::Shadow);
+        assert_eq!(FuzzStyle::Shadow.held(&relaxed, true), FuzzStyle::Vanilla);
+        assert_eq!(
+            FuzzStyle::Translucent.held(&strict, false),
+            FuzzStyle::Vanilla
+        );
+
+        let mut config = Config::new();
+        FuzzStyle::register(&mut config);
+        assert_eq!(FuzzStyle::from_config(&config), FuzzStyle::Vanilla);
+        FuzzStyle::Translucent.to_config(&mut config);
+        assert_eq!(FuzzStyle::from_config(&config), FuzzStyle::Translucent);
+    }
+}
@@ -0,0 +1,393 @@
+//! Text in the HUD font: colors, measuring, wrapping and alignment, for the HUD, the menus, the
+//! finale text screens and the console.
+//!
+//! The STCFN patches are drawn in the palette's red ramp. Other colors are translations of
+//! that ramp, Boom's ten `CR_BRICK` to `CR_YELLOW`. A WAD can supply any of them as a 256 byte
+//! `CR*` lump, as Boom's own WAD does; the rest are spread over a matching ramp of the
+//! palette. Inside a string `COLOR_ESCAPE` and a digit switch color for the rest of it, like
+//! Boom's messages, and wrapping carries the color over to the next line.
+
+use super::draw::{draw_translated_patch, Canvas, PixelFormat};
+use super::hud::{HudFont, LINE_HEIGHT};
+use super::translation::Translation;
+
+/// Followed by a `TextColor` digit, switches color.
+pub const COLOR_ESCAPE: char = '\x1b';
+
+/// The ramp the font patches are drawn in.
+const FONT_RAMP: (u8, u8) = (0xb0, 0xbf);
+
+/// Boom's `CR_*` colors, in their order, which is also their escape digit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextColor {
+    Brick,
+    Tan,
+    Gray,
+    Green,
+    Brown,
+    Gold,
+    #[default]
+    Red,
+    Blue,
+    Orange,
+    Yellow,
+}
+
+impl TextColor {
+    pub const ALL: [TextColor; 10] = [
+        TextColor::Brick,
+        TextColor::Tan,
+        TextColor::Gray,
+        TextColor::Green,
+        TextColor::Brown,
+        TextColor::Gold,
+        TextColor::Red,
+        TextColor::Blue,
+        TextColor::Orange,
+        TextColor::Yellow,
+    ];
+
+    /// The lump that replaces the table, and the palette ramp it is made from without one.
+    fn source(self) -> (&'static str, u8, u8) {
+        match self {
+            TextColor::Brick => ("CRBRICK", 0x20, 0x2f),
+            TextColor::Tan => ("CRTAN", 0x30, 0x3f),
+            TextColor::Gray => ("CRGRAY", 0x50, 0x6f),
+            TextColor::Green => ("CRGREEN", 0x70, 0x7f),
+            TextColor::Brown => ("CRBROWN", 0x40, 0x4f),
+            TextColor::Gold => ("CRGOLD", 0xa0, 0xa7),
+            TextColor::Red => ("CRRED", FONT_RAMP.0, FONT_RAMP.1),
+            TextColor::Blue => ("CRBLUE", 0xc0, 0xcf),
+            TextColor::Orange => ("CRORANGE", 0xd0, 0xdf),
+            TextColor::Yellow => ("CRYELLOW", 0xe0, 0xe7),
+        }
+    }
+
+    /// The escape that switches to this color.
+    pub fn escape(self) -> String {
+        format!("{COLOR_ESCAPE}{}", self as u8)
+    }
+}
+
+/// The translation table of each `TextColor`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextColors {
+    tables: [Translation; TextColor::ALL.len()],
+}
+
+impl Default for TextColors {
+    fn default() -> Self {
+        Self {
+            tables: TextColor::ALL.map(|color| {
+                let (_, first, last) = color.source();
+                let mut table: Translation = core::array::from_fn(|i| i as u8);
+                let steps = i32::from(FONT_RAMP.1 - FONT_RAMP.0);
+                for step in 0..=steps {
+                    let index =
+                        i32::from(first) + (i32::from(last) - i32::from(first)) * step / steps;
+                    table[usize::from(FONT_RAMP.0) + step as usize] = index as u8;
+                }
+                table
+            }),
+        }
+    }
+}
+
+impl TextColors {
+    /// The built-in tables, with the `CR*` lumps `lump` finds in their place.
+    pub fn load<'a>(mut lump: impl FnMut(&str) -> Option<&'a [u8]>) -> Self {
+        let mut colors = Self::default();
+        for (color, table) in TextColor::ALL.iter().zip(&mut colors.tables) {
+            if let Some(data) = lump(color.source().0).and_then(|data| data.get(..256)) {
+                table.copy_from_slice(data);
+            }
+        }
+        colors
+    }
+
+    pub fn table(&self, color: TextColor) -> &Translation {
+        &self.tables[color as usize]
+    }
+}
+
+/// Splits text into characters and whole color escapes.
+fn units(text: &str) -> impl Iterator<Item = &str> + '_ {
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        let mut chars = rest.chars();
+        let c = chars.next()?;
+        let mut len = c.len_utf8();
+        if c == COLOR_ESCAPE {
+            len += chars.next().map_or(0, char::len_utf8);
+        }
+        let (unit, tail) = rest.split_at(len);
+        rest = tail;
+        Some(unit)
+    })
+}
+
+/// The color an escape unit switches to, or `None` for anything else.
+fn escape_color(unit: &str) -> Option<Option<TextColor>> {
+    let digit = unit.strip_prefix(COLOR_ESCAPE)?;
+    Some(
+        digit
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| TextColor::ALL.get(i).copied()),
+    )
+}
+
+/// The text without its color escapes.
+pub fn plain(text: &str) -> String {
+    units(text)
+        .filter(|unit| escape_color(unit).is_none())
+        .collect()
+}
+
+/// The HUD font with its colors, and the color text starts in.
+#[derive(Clone, Copy, Debug)]
+pub struct Text<'a> {
+    pub font: &'a HudFont,
+    pub colors: &'a TextColors,
+    pub color: TextColor,
+}
+
+impl<'a> Text<'a> {
+    pub fn new(font: &'a HudFont, colors: &'a TextColors) -> Self {
+        Self {
+            font,
+            colors,
+            color: TextColor::default(),
+        }
+    }
+
+    pub fn with_color(self, color: TextColor) -> Self {
+        Self { color, ..self }
+    }
+
+    /// How wide `text` is drawn, escapes taking no room.
+    pub fn width(&self, text: &str) -> i32 {
+        self.font.text_width(&plain(text))
+    }
+
+    /// How tall `lines` lines are.
+    pub fn height(&self, lines: usize) -> i32 {
+        lines as i32 * LINE_HEIGHT
+    }
+
+    /// Breaks `text` into lines no wider than `width`: at its newlines, between words, and
+    /// inside a word that is too wide by itself. A line starts with the color the one before
+    /// it ended in.
+    pub fn wrap(&self, text: &str, width: i32) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut color = String::new();
+        for paragraph in text.split('\n') {
+            let mut line = color.clone();
+            for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+                let joined = match plain(&line).is_empty() {
+                    true => format!("{line}{word}"),
+                    false => format!("{line} {word}"),
+                };
+                if self.width(&joined) <= width {
+                    line = joined;
+                    continue;
+                }
+                if !plain(&line).is_empty() {
+                    color = last_escape(&line).unwrap_or(color);
+                    lines.push(core::mem::replace(&mut line, color.clone()));
+                }
+                for unit in units(word) {
+                    let longer = format!("{line}{unit}");
+                    if self.width(&longer) > width && !plain(&line).is_empty() {
+                        color = last_escape(&line).unwrap_or(color);
+                        lines.push(core::mem::replace(&mut line, format!("{color}{unit}")));
+                    } else {
+                        line = longer;
+                    }
+                }
+            }
+            color = last_escape(&line).unwrap_or(color);
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Draws one line with its top left corner at (`x`, `y`) and returns where it ended.
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: i32,
+        y: i32,
+        text: &str,
+    ) -> i32 {
+        let mut x = x;
+        let mut table = self.colors.table(self.color);
+        for unit in units(text) {
+            if let Some(color) = escape_color(unit) {
+                if let Some(color) = color {
+                    table = self.colors.table(color);
+                }
+                continue;
+            }
+            for c in unit.chars() {
+                if let Some(patch) = self.font.glyph(c) {
+                    draw_translated_patch(format, canvas, x, y, patch, table);
+                }
+                x += self.font.advance(c);
+            }
+        }
+        x
+    }
+
+    /// Draws one line centered across the canvas.
+    pub fn draw_centered<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        y: i32,
+        text: &str,
+    ) {
+        let x = (canvas.pitch as i32 - self.width(text)) / 2;
+        self.draw(format, canvas, x, y, text);
+    }
+
+    /// Draws one line ending at `right`.
+    pub fn draw_right<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        right: i32,
+        y: i32,
+        text: &str,
+    ) {
+        self.draw(format, canvas, right - self.width(text), y, text);
+    }
+
+    /// Wraps `text` to `width` and draws it from (`x`, `y`) down, returning the `y` below the
+    /// last line.
+    pub fn draw_wrapped<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: i32,
+        y: i32,
+        width: i32,
+        text: &str,
+    ) -> i32 {
+        let mut y = y;
+        for line in self.wrap(text, width) {
+            self.draw(format, canvas, x, y, &line);
+            y += LINE_HEIGHT;
+        }
+        y
+    }
+}
+
+/// The last color escape in `line`, to start the next line with.
+fn last_escape(line: &str) -> Option<String> {
+    units(line)
+        .filter(|unit| matches!(escape_color(unit), Some(Some(_))))
+        .last()
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::renderer::patch::{Patch, Post};
+
+    /// Every glyph is a 4 pixel wide patch whose single pixel is the font's first red.
+    fn font() -> HudFont {
+        HudFont::load(|_| {
+            Some(Patch {
+                width: 4,
+                height: 1,
+                left_offset: 0,
+                top_offset: 0,
+                columns: vec![
+                    vec![Post {
+                        top: 0,
+                        pixels: vec![FONT_RAMP.0],
+                    }],
+                    vec![],
+                    vec![],
+                    vec![],
+                ],
+            })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn colors_translate_the_red_ramp() {
+        let colors = TextColors::default();
+        let red = colors.table(TextColor::Red);
+        assert!((0..=255u8).all(|i| red[i as usize] == i));
+        let gray = colors.table(TextColor::Gray);
+        assert_eq!((gray[0xb0], gray[0xbf], gray[0x70]), (0x50, 0x6f, 0x70));
+
+        let mut lump = [7u8; 300];
+        lump[0xb0] = 9;
+        let colors = TextColors::load(|name| (name == "CRBLUE").then_some(&lump[..]));
+        assert_eq!(colors.table(TextColor::Blue)[0xb0], 9);
+        assert_eq!(colors.table(TextColor::Gold)[0xb0], 0xa0);
+
+        assert_eq!(
+            plain(&format!("A{}B{COLOR_ESCAPE}", TextColor::Blue.escape())),
+            "AB"
+        );
+    }
+
+    #[test]
+    fn text_wraps_by_word_and_keeps_its_color() {
+        let font = font();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors);
+        let blue = TextColor::Blue.escape();
+        assert_eq!(text.width(&format!("{blue}AB C")), 16);
+        assert_eq!(
+            text.wrap(&format!("THE {blue}QUICK FOX\n\nABCDEFGHIJ"), 24),
+            vec![
+                "THE".to_string(),
+                format!("{blue}QUICK"),
+                format!("{blue}FOX"),
+                blue.clone(),
+                format!("{blue}ABCDEF"),
+                format!("{blue}GHIJ"),
+            ]
+        );
+        assert_eq!(text.height(3), 3 * LINE_HEIGHT);
+    }
+
+    #[test]
+    fn escapes_switch_color_while_drawing() {
+        let font = font();
+        let colors = TextColors::default();
+        let text = Text::new(&font, &colors).with_color(TextColor::Green);
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let mut pixels = vec![0u32; 40];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 40,
+        };
+        let end = text.draw(
+            &format,
+            &mut canvas,
+            0,
+            0,
+            &format!("A {}B", TextColor::Red.escape()),
+        );
+        assert_eq!(end, 12);
+        assert_eq!((pixels[0], pixels[4], pixels[8]), (0x70, 0, 0xb0));
+
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 40,
+        };
+        text.draw_centered(&format, &mut canvas, 0, "AB");
+        text.draw_right(&format, &mut canvas, 40, 0, "A");
+        assert_eq!((pixels[16], pixels[20], pixels[36]), (0x70, 0x70, 0x70));
+    }
+}
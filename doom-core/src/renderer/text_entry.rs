@@ -0,0 +1,135 @@
+//! A line of text typed in the HUD font: savegame names and chat messages.
+//!
+//! Keys are handled as vanilla's save string entry and `HUlib_keyInIText` do: letters are
+//! stored uppercase, anything the font has no patch for is ignored, Backspace deletes, Enter
+//! accepts and Escape cancels. Text the entry starts with, such as a suggested savegame name,
+//! is replaced by the first character typed, so it can be accepted or typed over directly.
+
+use super::draw::{Canvas, PixelFormat};
+use super::hud::{HudFont, HU_FONTEND, HU_FONTSTART};
+use crate::engine::input::{Key, KEY_BACKSPACE, KEY_ENTER, KEY_ESCAPE};
+
+/// Drawn after the text while typing.
+const CURSOR: &str = "_";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryEvent {
+    Accepted(String),
+    Cancelled,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEntry {
+    text: String,
+    max_len: usize,
+    /// The text is still what the entry started with.
+    suggested: bool,
+}
+
+impl TextEntry {
+    pub fn new(max_len: usize) -> Self {
+        Self::with_text("", max_len)
+    }
+
+    /// An entry starting with `text`, which typing replaces and Backspace edits.
+    pub fn with_text(text: &str, max_len: usize) -> Self {
+        Self {
+            text: text
+                .chars()
+                .take(max_len)
+                .collect::<String>()
+                .to_uppercase(),
+            max_len,
+            suggested: !text.is_empty(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Handles a key press. Every key is taken while typing; an event is returned when the
+    /// entry is finished. Enter on an empty line does nothing, as in vanilla.
+    pub fn responder(&mut self, key: Key) -> Option<EntryEvent> {
+        match key {
+            KEY_ESCAPE => return Some(EntryEvent::Cancelled),
+            KEY_ENTER if !self.text.is_empty() => {
+                return Some(EntryEvent::Accepted(self.text.clone()));
+            }
+            KEY_BACKSPACE => {
+                self.text.pop();
+            }
+            _ => {
+                let c = u8::try_from(key).ok()?.to_ascii_uppercase();
+                if c != b' ' && !(HU_FONTSTART..=HU_FONTEND).contains(&c) {
+                    return None;
+                }
+                if self.suggested {
+                    self.text.clear();
+                }
+                if self.text.len() < self.max_len {
+                    self.text.push(char::from(c));
+                }
+            }
+        }
+        self.suggested = false;
+        None
+    }
+
+    /// Draws the text with a cursor after it.
+    pub fn draw<F: PixelFormat>(
+        &self,
+        format: &F,
+        canvas: &mut Canvas<F::Pixel>,
+        x: i32,
+        y: i32,
+        font: &HudFont,
+    ) {
+        font.draw_text(format, canvas, x, y, &self.text);
+        font.draw_text(format, canvas, x + font.text_width(&self.text), y, CURSOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(entry: &mut TextEntry, keys: &[u8]) -> Option<EntryEvent> {
+        keys.iter()
+            .filter_map(|&key| entry.responder(Key::from(key)))
+            .last()
+    }
+
+    #[test]
+    fn typing_is_uppercased_and_limited() {
+        let mut entry = TextEntry::new(5);
+        assert_eq!(entry.responder(KEY_ENTER), None);
+        assert_eq!(type_keys(&mut entry, b"ab{c de"), None);
+        assert_eq!(entry.text(), "ABC D");
+        assert_eq!(entry.responder(KEY_BACKSPACE), None);
+        assert_eq!(
+            entry.responder(KEY_ENTER),
+            Some(EntryEvent::Accepted("ABC ".to_owned()))
+        );
+        assert_eq!(entry.responder(KEY_ESCAPE), Some(EntryEvent::Cancelled));
+    }
+
+    #[test]
+    fn suggestions_are_kept_or_typed_over() {
+        let mut kept = TextEntry::with_text("map01 2026", 24);
+        assert_eq!(kept.text(), "MAP01 2026");
+        assert_eq!(
+            kept.responder(KEY_ENTER),
+            Some(EntryEvent::Accepted("MAP01 2026".to_owned()))
+        );
+
+        let mut replaced = TextEntry::with_text("MAP01", 24);
+        type_keys(&mut replaced, b"x");
+        assert_eq!(replaced.text(), "X");
+
+        let mut edited = TextEntry::with_text("MAP01", 24);
+        edited.responder(KEY_BACKSPACE);
+        type_keys(&mut edited, b"2");
+        assert_eq!(edited.text(), "MAP02");
+    }
+}
@@ -0,0 +1,129 @@
+//! Small pictures of the game saved with each savegame, for the load and save menus.
+//!
+//! When the game is saved, the finished frame is shrunk to `THUMBNAIL_WIDTH` by
+//! `THUMBNAIL_HEIGHT`. Each thumbnail pixel is the palette entry nearest the average color of
+//! the block of frame pixels it covers, whatever the resolution. It is stored as palette
+//! indices, like the frame, and drawn back unlit with `draw_raw`.
+
+use super::draw::{draw_raw, Canvas, PixelFormat};
+use super::framebuffer::{Framebuffer, Palette};
+use crate::game::savegame::{Archive, SaveError, SaveReader, SaveWriter};
+use alloc::format;
+use alloc::vec::Vec;
+
+/// A quarter of 320x200.
+pub const THUMBNAIL_WIDTH: usize = 80;
+pub const THUMBNAIL_HEIGHT: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Shrinks a finished frame. Frames smaller than a thumbnail repeat pixels instead.
+    pub fn capture(frame: &Framebuffer, palette: &Palette) -> Self {
+        let (width, height) = (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT);
+        let span = |i: usize, size: usize, total: usize| {
+            let start = i * total / size;
+            start..((i + 1) * total / size).max(start + 1)
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        for ty in 0..height {
+            let rows = span(ty, height, frame.height());
+            for tx in 0..width {
+                let columns = span(tx, width, frame.width());
+                let mut sum = [0i32; 3];
+                let mut count = 0;
+                for y in rows.clone() {
+                    for &index in &frame.row(y)[columns.clone()] {
+                        let rgb = palette.rgb(index);
+                        for c in 0..3 {
+                            sum[c] += i32::from(rgb[c]);
+                        }
+                        count += 1;
+                    }
+                }
+                pixels.push(palette.nearest(sum.map(|total| (total + count / 2) / count)));
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Draws the thumbnail with its top left corner at (`x`, `y`).
+    pub fn draw<F: PixelFormat>(&self, format: &F, canvas: &mut Canvas<F::Pixel>, x: i32, y: i32) {
+        draw_raw(format, canvas, x, y, self.width, &self.pixels);
+    }
+}
+
+impl Archive for Thumbnail {
+    fn archive(&self, writer: &mut SaveWriter) {
+        writer.write_i16(self.width as i16);
+        writer.write_i16(self.height as i16);
+        writer.write_bytes(&self.pixels);
+    }
+
+    fn unarchive(reader: &mut SaveReader) -> Result<Self, SaveError> {
+        let width = reader.read_i16()?;
+        let height = reader.read_i16()?;
+        if !(1..=THUMBNAIL_WIDTH as i16).contains(&width)
+            || !(1..=THUMBNAIL_HEIGHT as i16).contains(&height)
+        {
+            return Err(reader.error(format!("bad thumbnail size {width}x{height}")));
+        }
+        let (width, height) = (width as usize, height as usize);
+        Ok(Self {
+            width,
+            height,
+            pixels: reader.read_bytes(width * height)?.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+
+    #[test]
+    fn frames_are_averaged_down() {
+        // On the greyscale default palette the frame is 0 left of x = 162 and 200 from there,
+        // so the thumbnail column covering 160 to 163 averages the two.
+        let mut frame = Framebuffer::new(320, 200);
+        for y in 0..200 {
+            frame.pixels_mut()[y * 320 + 162..(y + 1) * 320].fill(200);
+        }
+        let thumbnail = Thumbnail::capture(&frame, &Palette::default());
+        assert_eq!(thumbnail.pixels.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT);
+        let row = &thumbnail.pixels[..THUMBNAIL_WIDTH];
+        assert_eq!((row[39], row[40], row[41]), (0, 100, 200));
+
+        assert_eq!(Thumbnail::from_bytes(&thumbnail.to_bytes()), Ok(thumbnail));
+        let mut huge = Thumbnail::capture(&frame, &Palette::default()).to_bytes();
+        huge[0] = 200;
+        assert!(Thumbnail::from_bytes(&huge).is_err());
+    }
+
+    #[test]
+    fn thumbnails_are_drawn_unlit_and_clipped() {
+        let thumbnail = Thumbnail {
+            width: 2,
+            height: 2,
+            pixels: alloc::vec![1, 2, 3, 4],
+        };
+        let format = TrueColor::new(core::array::from_fn(|i| i as u32));
+        let mut pixels = [0u32; 6];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 3,
+        };
+        thumbnail.draw(&format, &mut canvas, 2, 1);
+        assert_eq!(pixels, [0, 0, 0, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,74 @@
+//! Translucency tables for the indexed renderer.
+//!
+//! An indexed frame can't blend two colors directly, so like Boom the blend is looked up: for
+//! every pair of palette indices the table holds the palette entry closest to their mix. It
+//! has Boom's `TRANMAP` layout, a row of 256 entries for each index already in the frame,
+//! indexed by the one drawn over it. Building one means searching the palette for each of the
+//! 65536 pairs, which is done once when the palette is known. A WAD can also bring its own
+//! in a `TRANMAP` lump.
+
+use super::framebuffer::Palette;
+use alloc::vec::Vec;
+
+pub const TRANMAP_SIZE: usize = 256 * 256;
+pub const TRANMAP_LUMP: &str = "TRANMAP";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranMap {
+    table: Vec<u8>,
+}
+
+impl TranMap {
+    /// Builds the table for `palette`, with what is drawn over at `percent` opacity.
+    pub fn generate(palette: &Palette, percent: u8) -> Self {
+        let percent = i32::from(percent.min(100));
+        let mut table = alloc::vec![0; TRANMAP_SIZE];
+        for under in 0..=255u8 {
+            let below = palette.rgb(under);
+            for over in 0..=255u8 {
+                let above = palette.rgb(over);
+                let mix: [i32; 3] = core::array::from_fn(|c| {
+                    (i32::from(above[c]) * percent + i32::from(below[c]) * (100 - percent)) / 100
+                });
+                table[usize::from(under) << 8 | usize::from(over)] = palette.nearest(mix);
+            }
+        }
+        Self { table }
+    }
+
+    /// Takes a table as stored in a `TRANMAP` lump. Returns `None` if it is the wrong size.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == TRANMAP_SIZE).then(|| Self {
+            table: bytes.to_vec(),
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.table
+    }
+
+    /// The index that shows when `over` is drawn on top of `under`.
+    pub fn blend(&self, under: u8, over: u8) -> u8 {
+        self.table[usize::from(under) << 8 | usize::from(over)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blends_find_the_nearest_color() {
+        // The default palette is a greyscale ramp, so index and brightness are the same.
+        let palette = Palette::default();
+        let half = TranMap::generate(&palette, 50);
+        assert_eq!(half.blend(0, 200), 100);
+        assert_eq!(half.blend(200, 0), 100);
+        assert_eq!(half.blend(77, 77), 77);
+        let mostly_over = TranMap::generate(&palette, 75);
+        assert_eq!(mostly_over.blend(0, 200), 150);
+
+        assert_eq!(TranMap::from_bytes(half.as_bytes()), Some(half));
+        assert_eq!(TranMap::from_bytes(&[0; 256]), None);
+    }
+}
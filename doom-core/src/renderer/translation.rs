@@ -0,0 +1,165 @@
+//! Player color translation: the tables that repaint the green of the player sprites.
+//!
+//! Vanilla's `R_InitTranslationTables` makes three, which swap the green ramp (palette
+//! indices 0x70 to 0x7f) for gray, brown and red; the rest of the palette maps to itself. A
+//! mobj picks one with the two `MF_TRANSLATION` bits, which is how players 2 to 4 get their
+//! colors. Mods can add more colors with a `PCOLORS` text lump, one per line:
+//!
+//! ```text
+//! // name  first  last
+//! blue     200    207
+//! ```
+//!
+//! which spreads the green ramp over palette indices 200 to 207. Those are picked by name,
+//! since the mobj flags only have room for vanilla's three.
+
+use crate::game::info::MF_TRANSLATION;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// The palette range that gets translated: the player's green.
+pub const TRANSLATION_START: u8 = 0x70;
+pub const TRANSLATION_LENGTH: u8 = 16;
+/// Vanilla's `MF_TRANSSHIFT`.
+pub const MF_TRANSSHIFT: u32 = 26;
+pub const COLORS_LUMP: &str = "PCOLORS";
+
+pub type Translation = [u8; 256];
+
+/// The green ramp spread linearly over `first..=last`, either way round.
+pub fn ramp(first: u8, last: u8) -> Translation {
+    let mut table: Translation = core::array::from_fn(|i| i as u8);
+    let steps = i32::from(TRANSLATION_LENGTH) - 1;
+    for step in 0..=steps {
+        let index = i32::from(first) + (i32::from(last) - i32::from(first)) * step / steps;
+        table[usize::from(TRANSLATION_START) + step as usize] = index as u8;
+    }
+    table
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Translations {
+    /// Names and tables, vanilla's three first.
+    colors: Vec<(String, Translation)>,
+}
+
+impl Default for Translations {
+    fn default() -> Self {
+        let vanilla = |name: &str, start: u8| {
+            let mut table: Translation = core::array::from_fn(|i| i as u8);
+            for i in 0..TRANSLATION_LENGTH {
+                table[usize::from(TRANSLATION_START + i)] = start + i;
+            }
+            (name.to_string(), table)
+        };
+        Self {
+            colors: alloc::vec![
+                vanilla("gray", 0x60),
+                vanilla("brown", 0x40),
+                vanilla("red", 0x20)
+            ],
+        }
+    }
+}
+
+impl Translations {
+    /// The table the `MF_TRANSLATION` bits of `flags` select, or `None` for untranslated.
+    pub fn for_flags(&self, flags: u32) -> Option<&Translation> {
+        let index = ((flags & MF_TRANSLATION) >> MF_TRANSSHIFT) as usize;
+        index.checked_sub(1).map(|index| &self.colors[index].1)
+    }
+
+    /// The table of a color by name. `green` is the untranslated sprite.
+    pub fn by_name(&self, name: &str) -> Option<Option<&Translation>> {
+        if name.eq_ignore_ascii_case("green") {
+            return Some(None);
+        }
+        self.colors
+            .iter()
+            .find(|(color, _)| color.eq_ignore_ascii_case(name))
+            .map(|(_, table)| Some(table))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        core::iter::once("green").chain(self.colors.iter().map(|(name, _)| name.as_str()))
+    }
+
+    /// Adds the colors of a `PCOLORS` lump. A color with the name of an existing one
+    /// replaces it. Returns an error naming the first bad line, having added none.
+    pub fn add_lump(&mut self, text: &str) -> Result<(), String> {
+        let mut added = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match words.as_slice() {
+                [name, first, last] => first.parse().ok().zip(last.parse().ok()).map(|r| (name, r)),
+                _ => None,
+            };
+            let Some((name, (first, last))) = parsed else {
+                return Err(format!(
+                    "{COLORS_LUMP} line {}: expected 'name first last'",
+                    number + 1
+                ));
+            };
+            if name.eq_ignore_ascii_case("green") {
+                return Err(format!(
+                    "{COLORS_LUMP} line {}: green can't be redefined",
+                    number + 1
+                ));
+            }
+            added.push((name.to_ascii_lowercase(), ramp(first, last)));
+        }
+        for (name, table) in added {
+            match self.colors.iter_mut().find(|(color, _)| *color == name) {
+                Some(color) => color.1 = table,
+                None => self.colors.push((name, table)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanilla_tables_swap_the_green_ramp() {
+        let translations = Translations::default();
+        assert!(translations.for_flags(0).is_none());
+        let gray = translations.for_flags(1 << MF_TRANSSHIFT).unwrap();
+        assert_eq!((gray[0x70], gray[0x7f]), (0x60, 0x6f));
+        assert_eq!((gray[0x6f], gray[0x80]), (0x6f, 0x80));
+        let red = translations.for_flags(MF_TRANSLATION).unwrap();
+        assert_eq!(red[0x75], 0x25);
+        assert_eq!(translations.by_name("Brown").unwrap().unwrap()[0x70], 0x40);
+        assert_eq!(translations.by_name("green"), Some(None));
+        assert_eq!(translations.by_name("teal"), None);
+    }
+
+    #[test]
+    fn lumps_add_and_replace_colors() {
+        let mut translations = Translations::default();
+        translations
+            .add_lump("// mod colors\nblue 200 207\n\nRED 191 176 // darker\n")
+            .unwrap();
+        let blue = translations.by_name("blue").unwrap().unwrap();
+        assert_eq!((blue[0x70], blue[0x77], blue[0x7f]), (200, 203, 207));
+        let red = translations.for_flags(MF_TRANSLATION).unwrap();
+        assert_eq!((red[0x70], red[0x7f]), (191, 176));
+        assert_eq!(
+            translations.names().collect::<Vec<_>>(),
+            ["green", "gray", "brown", "red", "blue"]
+        );
+        assert_eq!(
+            translations.add_lump("teal 1 2\nbad line"),
+            Err("PCOLORS line 2: expected 'name first last'".to_string())
+        );
+        assert!(translations.by_name("teal").is_none());
+        assert!(translations.add_lump("green 1 2").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! The translucency table the indexed renderer blends with, and what is drawn with it.
+//!
+//! The table is built from the palette at startup, as Boom does. That search takes a while in
+//! a debug build, so the result is kept on disk together with the palette and opacity it was
+//! made for, and a cache that doesn't match is made again. Boom's translucent lines,
+//! the translucent spectres and things with `MF_TRANSLUCENT` all share it.
+//!
+//! Vanilla draws projectiles and explosions solid. With `translucent_projectiles` every thing
+//! type that is a missile gets `MF_TRANSLUCENT`, which its explosion keeps, since exploding
+//! only clears `MF_MISSILE` from the mobj.
+
+use super::framebuffer::{Palette, PALETTE_BYTES};
+use super::tranmap::{TranMap, TRANMAP_SIZE};
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::info::{GameInfo, MF_MISSILE, MF_TRANSLUCENT};
+use std::io;
+use std::path::Path;
+
+/// Boom's `tran_filter_pct`.
+pub const DEFAULT_PERCENT: u8 = 66;
+pub const CACHE_FILE: &str = "tranmap.dat";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TranslucencyOptions {
+    /// Opacity of what is drawn over, in percent.
+    pub percent: u8,
+    pub projectiles: bool,
+}
+
+impl Default for TranslucencyOptions {
+    fn default() -> Self {
+        Self {
+            percent: DEFAULT_PERCENT,
+            projectiles: false,
+        }
+    }
+}
+
+impl TranslucencyOptions {
+    /// Marks the projectile types translucent if asked to.
+    pub fn apply(&self, info: &mut GameInfo) {
+        if !self.projectiles {
+            return;
+        }
+        for mobj in info.mobjs.iter_mut().filter(|m| m.flags & MF_MISSILE != 0) {
+            mobj.flags |= MF_TRANSLUCENT;
+        }
+    }
+}
+
+impl Configurable for TranslucencyOptions {
+    fn register(config: &mut Config) {
+        config.register_int("tran_filter_pct", DEFAULT_PERCENT.into(), Section::Extended);
+        config.register_bool("translucent_projectiles", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            percent: config.get_int("tran_filter_pct").clamp(0, 100) as u8,
+            projectiles: config.get_bool("translucent_projectiles"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("tran_filter_pct", self.percent.into());
+        config.set_bool("translucent_projectiles", self.projectiles);
+    }
+}
+
+/// What the cache starts with: the palette and the opacity.
+fn cache_key(palette: &Palette, percent: u8) -> Vec<u8> {
+    let mut key: Vec<u8> = (0..=255u8).flat_map(|index| palette.rgb(index)).collect();
+    key.push(percent);
+    key
+}
+
+/// The table cached at `path`, if there is one made for `palette` and `percent`.
+pub fn read_cache(palette: &Palette, percent: u8, path: &Path) -> Option<TranMap> {
+    let cache = std::fs::read(path).ok()?;
+    let key = cache_key(palette, percent);
+    if cache.len() != PALETTE_BYTES + 1 + TRANMAP_SIZE || !cache.starts_with(&key) {
+        return None;
+    }
+    TranMap::from_bytes(&cache[key.len()..])
+}
+
+/// Caches `tranmap` at `path` for the next run.
+pub fn write_cache(
+    tranmap: &TranMap,
+    palette: &Palette,
+    percent: u8,
+    path: &Path,
+) -> io::Result<()> {
+    let mut cache = cache_key(palette, percent);
+    cache.extend_from_slice(tranmap.as_bytes());
+    std::fs::write(path, cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::info::MobjInfo;
+
+    #[test]
+    fn the_cache_is_reused_only_for_its_palette() {
+        let dir = std::env::temp_dir().join(format!("doom-tranmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CACHE_FILE);
+        let palette = Palette::default();
+        assert_eq!(read_cache(&palette, 50, &path), None);
+        let tranmap = TranMap::generate(&palette, 50);
+        write_cache(&tranmap, &palette, 50, &path).unwrap();
+        assert_eq!(read_cache(&palette, 50, &path), Some(tranmap));
+        assert_eq!(read_cache(&palette, 75, &path), None);
+        let mut other = Palette::default();
+        other.set_rgb(1, [0, 0, 255]);
+        assert_eq!(read_cache(&other, 50, &path), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn projectiles_become_translucent_on_request() {
+        let missile = MobjInfo {
+            flags: MF_MISSILE,
+            ..MobjInfo::default()
+        };
+        let mut info = GameInfo {
+            mobjs: vec![MobjInfo::default(), missile],
+            ..GameInfo::default()
+        };
+        TranslucencyOptions::default().apply(&mut info);
+        assert_eq!(info.mobjs[1].flags, MF_MISSILE);
+        let options = TranslucencyOptions {
+            projectiles: true,
+            ..TranslucencyOptions::default()
+        };
+        options.apply(&mut info);
+        assert_eq!(info.mobjs[0].flags, 0);
+        assert_eq!(info.mobjs[1].flags, MF_MISSILE | MF_TRANSLUCENT);
+
+        let mut config = Config::new();
+        TranslucencyOptions::register(&mut config);
+        assert_eq!(
+            TranslucencyOptions::from_config(&config),
+            TranslucencyOptions::default()
+        );
+        options.to_config(&mut config);
+        assert_eq!(TranslucencyOptions::from_config(&config), options);
+    }
+}
@@ -0,0 +1,343 @@
+//! The 3D view window: screen size (`+`/`-`), detail level (F5) and the border around it.
+//!
+//! As in vanilla's `R_ExecuteSetViewSize`, sizes 3 to 9 shrink the view inside a tiled border
+//! above the status bar, 10 fills the width above the bar and 11 drops the bar. Low detail
+//! renders half as many columns and doubles them afterwards. The overlay option draws the
+//! status bar over the bottom of a full height view instead of next to it.
+
+use super::draw::{draw_patch, Canvas, PixelFormat};
+use super::framebuffer::{SCREENHEIGHT, SCREENWIDTH};
+use super::patch::Patch;
+use super::projection::{Projection, ST_HEIGHT};
+use crate::engine::config::{Config, Configurable, Section};
+use crate::game::GameMode;
+
+pub const MIN_BLOCKS: i32 = 3;
+pub const MAX_BLOCKS: i32 = 11;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Detail {
+    #[default]
+    High,
+    Low,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewSize {
+    pub blocks: i32,
+    pub detail: Detail,
+    pub overlay_status_bar: bool,
+}
+
+impl Default for ViewSize {
+    fn default() -> Self {
+        Self {
+            blocks: 10,
+            detail: Detail::High,
+            overlay_status_bar: false,
+        }
+    }
+}
+
+impl ViewSize {
+    /// `+`: returns false if already at the largest size.
+    pub fn grow(&mut self) -> bool {
+        let grown = self.blocks < MAX_BLOCKS;
+        self.blocks = (self.blocks + 1).min(MAX_BLOCKS);
+        grown
+    }
+
+    /// `-`: returns false if already at the smallest size.
+    pub fn shrink(&mut self) -> bool {
+        let shrunk = self.blocks > MIN_BLOCKS;
+        self.blocks = (self.blocks - 1).max(MIN_BLOCKS);
+        shrunk
+    }
+
+    /// F5: switches detail and returns the message to show, as a `strings` entry name.
+    pub fn toggle_detail(&mut self) -> &'static str {
+        match self.detail {
+            Detail::High => {
+                self.detail = Detail::Low;
+                "DETAILLO"
+            }
+            Detail::Low => {
+                self.detail = Detail::High;
+                "DETAILHI"
+            }
+        }
+    }
+
+    pub fn status_bar_visible(&self) -> bool {
+        self.blocks < MAX_BLOCKS
+    }
+
+    /// Where the view goes in a `frame_width` x `frame_height` buffer.
+    pub fn window(&self, frame_width: usize, frame_height: usize) -> ViewWindow {
+        let scale = (frame_height / SCREENHEIGHT).max(1);
+        let bar = ST_HEIGHT * scale;
+        let base_width = frame_height * SCREENWIDTH / SCREENHEIGHT;
+        let detail_shift = match self.detail {
+            Detail::High => 0,
+            Detail::Low => 1,
+        };
+        let blocks = self.blocks.clamp(MIN_BLOCKS, MAX_BLOCKS) as usize;
+        let full_height = blocks == 11 || (blocks == 10 && self.overlay_status_bar);
+        let (width, height, base) = match blocks {
+            _ if full_height => (frame_width, frame_height, base_width),
+            10 => (frame_width, frame_height - bar, base_width),
+            _ => (
+                (blocks * frame_width / 10) & !1,
+                (blocks * (frame_height - bar) / 10) & !7,
+                blocks * base_width / 10,
+            ),
+        };
+        ViewWindow {
+            x: (frame_width - width) / 2,
+            y: if width == frame_width {
+                0
+            } else {
+                (frame_height - bar - height) / 2
+            },
+            width,
+            height,
+            base_width: base,
+            detail_shift,
+        }
+    }
+}
+
+impl Configurable for ViewSize {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_int("screenblocks", defaults.blocks, Section::Vanilla);
+        config.register_int("detaillevel", 0, Section::Vanilla);
+        config.register_bool(
+            "video_statusbar_overlay",
+            defaults.overlay_status_bar,
+            Section::Extended,
+        );
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            blocks: config.get_int("screenblocks").clamp(MIN_BLOCKS, MAX_BLOCKS),
+            detail: match config.get_int("detaillevel") {
+                0 => Detail::High,
+                _ => Detail::Low,
+            },
+            overlay_status_bar: config.get_bool("video_statusbar_overlay"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_int("screenblocks", self.blocks);
+        config.set_int("detaillevel", (self.detail == Detail::Low) as i32);
+        config.set_bool("video_statusbar_overlay", self.overlay_status_bar);
+    }
+}
+
+/// The rectangle of the frame the 3D view is drawn into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewWindow {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize, // In frame pixels; low detail renders half as many columns.
+    pub height: usize,
+    base_width: usize, // The width of the 4:3 part, which sets the field of view.
+    pub detail_shift: u32,
+}
+
+impl ViewWindow {
+    /// How many columns the renderer draws.
+    pub fn render_width(&self) -> usize {
+        self.width >> self.detail_shift
+    }
+
+    /// Whether the view covers the whole frame width, leaving no border to draw.
+    pub fn fills_width(&self, frame_width: usize) -> bool {
+        self.width == frame_width
+    }
+
+    pub fn projection(&self) -> Projection {
+        Projection::with_base(
+            self.render_width(),
+            self.height,
+            self.base_width >> self.detail_shift,
+        )
+    }
+}
+
+/// The flat tiled around a shrunken view.
+pub fn border_flat(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Commercial => "GRNROCK",
+        _ => "FLOOR7_2",
+    }
+}
+
+/// The bevel patches drawn along the border, in the order of `BorderPatches::NAMES`.
+pub struct BorderPatches {
+    pub patches: [Patch; 8],
+}
+
+impl BorderPatches {
+    pub const NAMES: [&'static str; 8] = [
+        "BRDR_T", "BRDR_B", "BRDR_L", "BRDR_R", "BRDR_TL", "BRDR_TR", "BRDR_BL", "BRDR_BR",
+    ];
+
+    pub fn load(mut patch: impl FnMut(&str) -> Option<Patch>) -> Option<Self> {
+        let mut patches = Vec::with_capacity(8);
+        for name in Self::NAMES {
+            patches.push(patch(name)?);
+        }
+        Some(Self {
+            patches: patches.try_into().ok()?,
+        })
+    }
+}
+
+/// `R_FillBackScreen`: tiles `flat` over everything above the status bar that isn't the view,
+/// then draws the bevel around the view.
+pub fn fill_border<F: PixelFormat>(
+    format: &F,
+    canvas: &mut Canvas<F::Pixel>,
+    window: &ViewWindow,
+    flat: &[u8; 4096],
+    bevel: Option<&BorderPatches>,
+) {
+    if window.fills_width(canvas.pitch) {
+        return;
+    }
+    let view_x = window.x..window.x + window.width;
+    let view_y = window.y..window.y + window.height;
+    let bottom = (window.y * 2 + window.height).min(canvas.height());
+    for y in 0..bottom {
+        let row = &mut canvas.pixels[y * canvas.pitch..][..canvas.pitch];
+        for (x, pixel) in row.iter_mut().enumerate() {
+            if !(view_y.contains(&y) && view_x.contains(&x)) {
+                *pixel = format.shade(flat[(y & 63) * 64 + (x & 63)], 0);
+            }
+        }
+    }
+
+    let Some(bevel) = bevel else {
+        return;
+    };
+    let [top, bottom, left, right, top_left, top_right, bottom_left, bottom_right] = &bevel.patches;
+    let (x, y) = (window.x as i32, window.y as i32);
+    let (w, h) = (window.width as i32, window.height as i32);
+    for dx in (0..w).step_by(8) {
+        draw_patch(format, canvas, x + dx, y - 8, top, 0);
+        draw_patch(format, canvas, x + dx, y + h, bottom, 0);
+    }
+    for dy in (0..h).step_by(8) {
+        draw_patch(format, canvas, x - 8, y + dy, left, 0);
+        draw_patch(format, canvas, x + w, y + dy, right, 0);
+    }
+    draw_patch(format, canvas, x - 8, y - 8, top_left, 0);
+    draw_patch(format, canvas, x + w, y - 8, top_right, 0);
+    draw_patch(format, canvas, x - 8, y + h, bottom_left, 0);
+    draw_patch(format, canvas, x + w, y + h, bottom_right, 0);
+}
+
+/// Low detail: the renderer drew `render_width` columns from the window's left edge; this
+/// doubles each one across the full window width.
+pub fn expand_low_detail<T: Copy>(canvas: &mut Canvas<T>, window: &ViewWindow) {
+    if window.detail_shift == 0 {
+        return;
+    }
+    for y in window.y..window.y + window.height {
+        let row = &mut canvas.pixels[y * canvas.pitch + window.x..][..window.width];
+        for i in (0..window.render_width()).rev() {
+            let pixel = row[i];
+            row[2 * i] = pixel;
+            row[2 * i + 1] = pixel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::draw::TrueColor;
+    use crate::util::fixed::FRACBITS;
+
+    #[test]
+    fn vanilla_window_sizes() {
+        let mut size = ViewSize::default();
+        let window = size.window(320, 200);
+        assert_eq!(
+            (window.x, window.y, window.width, window.height),
+            (0, 0, 320, 168)
+        );
+        assert_eq!(window.projection().projection, 160 << FRACBITS);
+
+        size.blocks = 9;
+        let window = size.window(320, 200);
+        assert_eq!(
+            (window.x, window.y, window.width, window.height),
+            (16, 12, 288, 144)
+        );
+        assert_eq!(window.projection().projection, 144 << FRACBITS);
+
+        size.blocks = 11;
+        assert_eq!(size.window(320, 200).height, 200);
+        assert!(!size.grow());
+        assert!(size.shrink());
+    }
+
+    #[test]
+    fn low_detail_halves_columns_and_projection() {
+        let mut size = ViewSize::default();
+        assert_eq!(size.toggle_detail(), "DETAILLO");
+        let window = size.window(320, 200);
+        assert_eq!(window.render_width(), 160);
+        let projection = window.projection();
+        assert_eq!(projection.center_x, 80);
+        assert_eq!(projection.projection, 80 << FRACBITS);
+        assert_eq!(size.toggle_detail(), "DETAILHI");
+    }
+
+    #[test]
+    fn overlay_gives_a_full_height_view_with_the_bar() {
+        let size = ViewSize {
+            overlay_status_bar: true,
+            ..ViewSize::default()
+        };
+        let window = size.window(426, 200);
+        assert_eq!((window.width, window.height), (426, 200));
+        assert!(size.status_bar_visible());
+        // The field of view stays 4:3 based in widescreen.
+        assert_eq!(window.projection().projection, 160 << FRACBITS);
+    }
+
+    #[test]
+    fn border_tiles_around_the_view_and_expansion_doubles_columns() {
+        let format = TrueColor::new(std::array::from_fn(|i| i as u32));
+        let flat: [u8; 4096] = std::array::from_fn(|i| (i % 64) as u8 + 1);
+        let size = ViewSize {
+            blocks: 5,
+            detail: Detail::Low,
+            ..ViewSize::default()
+        };
+        let window = size.window(320, 200);
+        let mut pixels = vec![0u32; 320 * 200];
+        let mut canvas = Canvas {
+            pixels: &mut pixels,
+            pitch: 320,
+        };
+        fill_border(&format, &mut canvas, &window, &flat, None);
+        assert_eq!(canvas.pixels[0], 1);
+        assert_eq!(canvas.pixels[65], 2);
+        let inside = window.y * 320 + window.x;
+        assert_eq!(canvas.pixels[inside], 0);
+        // The status bar area is left alone.
+        assert_eq!(canvas.pixels[199 * 320], 0);
+
+        canvas.pixels[inside] = 7;
+        canvas.pixels[inside + 1] = 9;
+        expand_low_detail(&mut canvas, &window);
+        assert_eq!(&canvas.pixels[inside..inside + 4], &[7, 7, 9, 9]);
+    }
+}
@@ -0,0 +1,19 @@
+#[cfg(feature = "std")]
+use super::fixed::Fixed;
+
+/// Binary angle measurement: the full circle is the whole `u32` range, so angles wrap for free.
+pub type Angle = u32;
+
+pub const ANG45: Angle = 0x2000_0000;
+pub const ANG90: Angle = 0x4000_0000;
+pub const ANG180: Angle = 0x8000_0000;
+pub const ANG270: Angle = 0xc000_0000;
+
+/// Sine and cosine as 16.16 fixed point. Worked out in floating point, so it is for code
+/// outside the simulation, like the free camera. The game needs vanilla's exact tables.
+#[cfg(feature = "std")]
+pub fn sin_cos(angle: Angle) -> (Fixed, Fixed) {
+    let radians = angle as f64 * std::f64::consts::TAU / 4_294_967_296.0;
+    let fixed = |value: f64| (value * 65536.0).round() as i32;
+    (fixed(radians.sin()), fixed(radians.cos()))
+}
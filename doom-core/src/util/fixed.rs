@@ -0,0 +1,130 @@
+/// 16.16 fixed point, the number format used by the whole simulation and renderer.
+pub type Fixed = i32;
+
+pub const FRACBITS: u32 = 16;
+pub const FRACUNIT: Fixed = 1 << FRACBITS;
+
+pub fn fixed_mul(a: Fixed, b: Fixed) -> Fixed {
+    ((a as i64 * b as i64) >> FRACBITS) as Fixed
+}
+
+/// Divides with the same saturation the original `FixedDiv` performs when the quotient
+/// would not fit, which some renderer and physics code relies on.
+pub fn fixed_div(a: Fixed, b: Fixed) -> Fixed {
+    if (a.unsigned_abs() >> 14) >= b.unsigned_abs() {
+        if (a ^ b) < 0 {
+            Fixed::MIN
+        } else {
+            Fixed::MAX
+        }
+    } else {
+        (((a as i64) << FRACBITS) / b as i64) as Fixed
+    }
+}
+
+pub fn to_fixed(value: i32) -> Fixed {
+    value << FRACBITS
+}
+
+/// Integer part of a fixed point value, rounding towards negative infinity.
+pub fn to_int(value: Fixed) -> i32 {
+    value >> FRACBITS
+}
+
+/// `P_AproxDistance`: the octagonal distance estimate vanilla uses for ranges and momentum.
+/// Results depend on it exactly, so don't replace it with a real square root.
+pub fn approx_distance(dx: Fixed, dy: Fixed) -> Fixed {
+    let (dx, dy) = (dx.wrapping_abs(), dy.wrapping_abs());
+    dx.wrapping_add(dy).wrapping_sub(dx.min(dy) >> 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::prop::{check, ensure, CASES};
+    use alloc::format;
+
+    #[test]
+    fn multiply_and_divide() {
+        assert_eq!(fixed_mul(to_fixed(3), FRACUNIT / 2), to_fixed(3) / 2);
+        assert_eq!(fixed_div(to_fixed(3), to_fixed(2)), to_fixed(3) / 2);
+        assert_eq!(to_int(-FRACUNIT / 2), -1);
+    }
+
+    #[test]
+    fn division_saturates_on_overflow() {
+        assert_eq!(fixed_div(to_fixed(30000), 1), Fixed::MAX);
+        assert_eq!(fixed_div(to_fixed(-30000), 1), Fixed::MIN);
+        assert_eq!(fixed_div(1, 0), Fixed::MAX);
+    }
+
+    #[test]
+    fn approximate_distance() {
+        assert_eq!(approx_distance(to_fixed(3), 0), to_fixed(3));
+        assert_eq!(
+            approx_distance(to_fixed(-3), to_fixed(4)),
+            to_fixed(7) - to_fixed(3) / 2
+        );
+    }
+
+    #[test]
+    fn multiplication_properties() {
+        check("fixed_mul", CASES, |gen| {
+            let (a, b) = (gen.i32(), gen.i32());
+            ensure(fixed_mul(a, b) == fixed_mul(b, a), || {
+                format!("{a} * {b} commutes")
+            })?;
+            ensure(fixed_mul(a, FRACUNIT) == a, || format!("{a} * 1"))?;
+            ensure(fixed_mul(a, 0) == 0, || format!("{a} * 0"))
+        });
+    }
+
+    #[test]
+    fn division_undoes_multiplication() {
+        check("fixed_div", CASES, |gen| {
+            let (a, b) = (gen.i32(), gen.i32());
+            let quotient = fixed_div(a, b);
+            if (a.unsigned_abs() >> 14) >= b.unsigned_abs() {
+                let expected = if (a ^ b) < 0 { Fixed::MIN } else { Fixed::MAX };
+                return ensure(quotient == expected, || format!("{a} / {b} saturates"));
+            }
+            // The quotient is truncated, so multiplying back is off by at most |b| / FRACUNIT
+            // plus one for the truncation in fixed_mul.
+            let back = (i64::from(quotient) * i64::from(b)) >> FRACBITS;
+            let error = (back - i64::from(a)).abs();
+            ensure(error <= (i64::from(b).abs() >> FRACBITS) + 1, || {
+                format!("{a} / {b} = {quotient}, times {b} is {back}")
+            })
+        });
+    }
+
+    #[test]
+    fn integer_conversion_round_trips() {
+        check("to_fixed", CASES, |gen| {
+            let n = gen.range(-32768, 32767);
+            ensure(to_int(to_fixed(n)) == n, || format!("{n}"))?;
+            let frac = gen.range(0, FRACUNIT - 1);
+            ensure(to_int(to_fixed(n) + frac) == n, || {
+                format!("{n} + {frac}/65536")
+            })
+        });
+    }
+
+    #[test]
+    fn approximate_distance_is_bounded() {
+        check("approx_distance", CASES, |gen| {
+            let (dx, dy) = (
+                gen.range(-(1 << 29), 1 << 29),
+                gen.range(-(1 << 29), 1 << 29),
+            );
+            let distance = approx_distance(dx, dy);
+            let (x, y) = (dx.abs(), dy.abs());
+            ensure(distance == approx_distance(-dy, dx), || {
+                format!("{dx},{dy} symmetric")
+            })?;
+            ensure(x.max(y) <= distance && distance <= x + y, || {
+                format!("{dx},{dy} gave {distance}")
+            })
+        });
+    }
+}
@@ -0,0 +1,308 @@
+//! A deflate decoder, for PNG images and ZIP archives.
+//!
+//! Written for clarity over speed, in the manner of zlib's `puff`: Huffman codes are decoded a
+//! bit at a time by counting codes of each length, with no lookup tables. Graphics and
+//! archives are read once at startup, so this is never on a hot path. Every malformed stream,
+//! including one that ends early, gives `None` rather than a panic.
+
+use alloc::vec::Vec;
+
+const MAXBITS: usize = 15;
+
+/// Base lengths and extra bits of the length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distances and extra bits of the distance codes 0 to 29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order code length code lengths are sent in by a dynamic block.
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct Bits<'a> {
+    data: &'a [u8],
+    at: usize,
+    bit: u32,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.data.get(self.at)?;
+        let value = u32::from(byte >> self.bit) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.at += 1;
+        }
+        Some(value)
+    }
+
+    /// `count` bits, least significant first.
+    fn bits(&mut self, count: u8) -> Option<u32> {
+        (0..count).try_fold(0, |value, i| Some(value | self.bit()? << i))
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.at += 1;
+        }
+    }
+}
+
+/// A canonical Huffman code: how many codes there are of each length, and the symbols in
+/// code order.
+struct Huffman {
+    counts: [u16; MAXBITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code from each symbol's code length, 0 for unused. Returns `None` if the
+    /// lengths ask for more codes than fit.
+    fn new(lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0u16; MAXBITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return None;
+            }
+        }
+        let mut offsets = [0u16; MAXBITS + 1];
+        for length in 1..MAXBITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = alloc::vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Some(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bit()? as i32;
+            let count = i32::from(count);
+            if code - count < first {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+/// Decodes a raw deflate stream, as ZIP archives store it.
+pub fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    blocks(&mut Bits {
+        data,
+        at: 0,
+        bit: 0,
+    })
+}
+
+/// Decodes a zlib stream, as PNG's `IDAT` holds it, checking the header and the checksum.
+pub fn zlib(data: &[u8]) -> Option<Vec<u8>> {
+    let (cmf, flg) = (*data.first()?, *data.get(1)?);
+    let header_ok = cmf & 0x0f == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0;
+    // A preset dictionary can't be supplied; nothing that writes PNGs uses one.
+    if !header_ok || flg & 0x20 != 0 {
+        return None;
+    }
+    let mut bits = Bits {
+        data: &data[2..],
+        at: 0,
+        bit: 0,
+    };
+    let out = blocks(&mut bits)?;
+    bits.align();
+    let at = 2 + bits.at;
+    let expected = u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?);
+    (super::png::adler32(&out) == expected).then_some(out)
+}
+
+/// Decodes blocks up to and including the last one, leaving `bits` just after it.
+fn blocks(bits: &mut Bits) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let last = bits.bit()? == 1;
+        match bits.bits(2)? {
+            0 => stored(bits, &mut out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(bits, &mut out, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(bits)?;
+                codes(bits, &mut out, &lengths, &distances)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+fn stored(bits: &mut Bits, out: &mut Vec<u8>) -> Option<()> {
+    bits.align();
+    let at = bits.at;
+    let header = bits.data.get(at..at + 4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return None;
+    }
+    out.extend_from_slice(bits.data.get(at + 4..at + 4 + len as usize)?);
+    bits.at = at + 4 + len as usize;
+    Some(())
+}
+
+fn fixed_codes() -> Option<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Some((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Option<(Huffman, Huffman)> {
+    let nlen = bits.bits(5)? as usize + 257;
+    let ndist = bits.bits(5)? as usize + 1;
+    let ncode = bits.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return None;
+    }
+    let mut clens = [0u8; 19];
+    for &symbol in &CLEN_ORDER[..ncode] {
+        clens[symbol] = bits.bits(3)? as u8;
+    }
+    let clen_code = Huffman::new(&clens)?;
+
+    let mut lengths = Vec::with_capacity(nlen + ndist);
+    while lengths.len() < nlen + ndist {
+        let (value, repeat) = match clen_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last()?, 3 + bits.bits(2)?),
+            17 => (0, 3 + bits.bits(3)?),
+            18 => (0, 11 + bits.bits(7)?),
+            _ => return None,
+        };
+        if lengths.len() + repeat as usize > nlen + ndist {
+            return None;
+        }
+        lengths.extend(core::iter::repeat_n(value, repeat as usize));
+    }
+    // Without an end of block code the block could never finish.
+    if lengths[256] == 0 {
+        return None;
+    }
+    Some((
+        Huffman::new(&lengths[..nlen])?,
+        Huffman::new(&lengths[nlen..])?,
+    ))
+}
+
+fn codes(bits: &mut Bits, out: &mut Vec<u8>, lengths: &Huffman, distances: &Huffman) -> Option<()> {
+    loop {
+        let symbol = lengths.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Some(()),
+            _ => {
+                let code = symbol - 257;
+                let length =
+                    *LENGTH_BASE.get(code)? as usize + bits.bits(LENGTH_EXTRA[code])? as usize;
+                let code = distances.decode(bits)? as usize;
+                let distance =
+                    *DIST_BASE.get(code)? as usize + bits.bits(*DIST_EXTRA.get(code)?)? as usize;
+                if distance > out.len() {
+                    return None;
+                }
+                let start = out.len() - distance;
+                // The copy may overlap what it writes, which repeats the last `distance` bytes.
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::png::zlib_stored;
+
+    #[test]
+    fn stored_blocks_come_back_unchanged() {
+        let data: Vec<u8> = (0..70_000u32).map(|i| (i * 7) as u8).collect();
+        assert_eq!(zlib(&zlib_stored(&data)), Some(data));
+        assert_eq!(zlib(&zlib_stored(&[])), Some(Vec::new()));
+
+        let mut corrupt = zlib_stored(b"hello");
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        assert_eq!(zlib(&corrupt), None, "the checksum is checked");
+    }
+
+    #[test]
+    fn compressed_streams_decode() {
+        // zlib.compress(b"hello hello hello hello\n"): fixed codes with a match.
+        let fixed = [
+            120, 156, 203, 72, 205, 201, 201, 87, 200, 64, 39, 185, 0, 112, 190, 8, 187,
+        ];
+        assert_eq!(
+            zlib(&fixed).as_deref(),
+            Some(&b"hello hello hello hello\n"[..])
+        );
+        // The same from a raw stream with a dynamic block.
+        let text = b"ccabdcbdbaabaababaabdabcbad baaacadbabaaaaa aabdabbabab acba";
+        let dynamic = [
+            37, 138, 9, 13, 0, 48, 12, 2, 173, 212, 218, 1, 254, 53, 140, 118, 132, 16, 62, 27,
+            197, 138, 224, 184, 18, 100, 145, 169, 199, 228, 202, 98, 254, 180, 81, 67, 31, 15,
+        ];
+        assert_eq!(dynamic[0] >> 1 & 3, 2);
+        assert_eq!(inflate(&dynamic).as_deref(), Some(&text[..]));
+        // Runs longer than their distance repeat what they copy.
+        assert_eq!(
+            inflate(&[75, 76, 74, 28, 22, 48, 25, 0]),
+            Some([b"ab".repeat(100), b"c".to_vec()].concat())
+        );
+    }
+
+    #[test]
+    fn broken_streams_are_rejected() {
+        assert_eq!(inflate(&[]), None);
+        // A reserved block type.
+        assert_eq!(inflate(&[0x07]), None);
+        // A stored block whose length check fails.
+        assert_eq!(inflate(&[0x01, 0x05, 0x00, 0x00, 0x00]), None);
+        // A match reaching back before the start of the output.
+        assert_eq!(inflate(&[0x03, 0x02]), None);
+        assert_eq!(zlib(&[0x78, 0x9d, 0x03, 0x00]), None);
+    }
+}
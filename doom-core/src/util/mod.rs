@@ -0,0 +1,7 @@
+pub mod angle;
+pub mod fixed;
+pub mod inflate;
+pub mod png;
+pub mod prop;
+#[cfg(feature = "std")]
+pub mod time;
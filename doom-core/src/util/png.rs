@@ -0,0 +1,423 @@
+//! A minimal PNG writer: 8-bit paletted, RGB or RGBA images, stored with uncompressed deflate blocks.
+//!
+//! The output is larger than a real compressor would produce but is valid PNG that every
+//! viewer reads, and it avoids pulling in a compression library for screenshots and exports.
+//!
+//! `decode` reads them back, along with whatever else an image editor saves: every color type
+//! and bit depth, transparency from `tRNS`, and ZDoom's `grAb` chunk, which carries a
+//! graphic's offsets the way a patch header does. Interlaced images are the one thing it
+//! turns down.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Encodes an indexed image. `palette` supplies the RGB value of each index.
+pub fn encode_indexed(
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+    palette: &[[u8; 3]; 256],
+) -> Vec<u8> {
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header(width, height, 3));
+    let plte: Vec<u8> = palette.iter().flatten().copied().collect();
+    write_chunk(&mut out, b"PLTE", &plte);
+    let rows = pixels.chunks_exact(width).map(|row| row.to_vec());
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&filtered(rows)));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Encodes a true-color image from `0x00RRGGBB` pixels.
+pub fn encode_rgb(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header(width, height, 2));
+    let rows = pixels.chunks_exact(width).map(|row| {
+        row.iter()
+            .flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8])
+            .collect::<Vec<u8>>()
+    });
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&filtered(rows)));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Encodes a true-color image with alpha from `0xAARRGGBB` pixels.
+pub fn encode_rgba(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header(width, height, 6));
+    let rows = pixels.chunks_exact(width).map(|row| {
+        row.iter()
+            .flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8, (p >> 24) as u8])
+            .collect::<Vec<u8>>()
+    });
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&filtered(rows)));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// A decoded image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// `0xAARRGGBB`, row by row.
+    pub pixels: Vec<u32>,
+    /// The `grAb` offsets, left and top like a patch's.
+    pub offsets: Option<(i32, i32)>,
+}
+
+/// Decodes a PNG file to ARGB pixels. Returns `None` if it is broken, interlaced, or uses a
+/// color type and bit depth that don't go together.
+pub fn decode(bytes: &[u8]) -> Option<Image> {
+    if bytes.get(..8)? != SIGNATURE {
+        return None;
+    }
+    let be32 = |data: &[u8], at: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+    };
+    let (mut ihdr, mut palette, mut trns, mut offsets) = (None, Vec::new(), None, None);
+    let mut idat = Vec::new();
+    let mut at = 8;
+    loop {
+        let len = be32(bytes, at)? as usize;
+        let kind = bytes.get(at + 4..at + 8)?;
+        let data = bytes.get(at + 8..at + 8 + len)?;
+        at += 12 + len;
+        match kind {
+            b"IHDR" => ihdr = Some(data),
+            b"PLTE" => {
+                palette = data
+                    .chunks_exact(3)
+                    .map(|c| [c[0], c[1], c[2], 255])
+                    .collect()
+            }
+            b"tRNS" => trns = Some(data),
+            b"grAb" => offsets = Some((be32(data, 0)? as i32, be32(data, 4)? as i32)),
+            b"IDAT" => idat.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let ihdr = ihdr?;
+    let (width, height) = (be32(ihdr, 0)? as usize, be32(ihdr, 4)? as usize);
+    let (depth, color, interlace) = (*ihdr.get(8)?, *ihdr.get(9)?, *ihdr.get(12)?);
+    let channels = match (color, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => return None,
+    };
+    if interlace != 0 || width == 0 || height == 0 {
+        return None;
+    }
+    if let (3, Some(alpha)) = (color, trns) {
+        for (entry, &alpha) in palette.iter_mut().zip(alpha) {
+            entry[3] = alpha;
+        }
+    }
+    // A color that stands for transparent, at the image's own depth.
+    let key: Option<Vec<u16>> = match color {
+        0 | 2 => trns.map(|data| {
+            data.chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect()
+        }),
+        _ => None,
+    };
+
+    let bits = channels * depth as usize;
+    let stride = (width * bits).div_ceil(8);
+    let rows = unfilter(
+        &super::inflate::zlib(&idat)?,
+        stride,
+        height,
+        bits.div_ceil(8),
+    )?;
+    let max = (1u32 << depth.min(8)) - 1;
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in rows.chunks_exact(stride) {
+        for x in 0..width {
+            let raw = |channel: usize| -> u16 {
+                let sample = x * channels + channel;
+                match depth {
+                    16 => u16::from_be_bytes([row[sample * 2], row[sample * 2 + 1]]),
+                    8 => u16::from(row[sample]),
+                    _ => {
+                        let bit = sample * depth as usize;
+                        u16::from(row[bit / 8] >> (8 - depth as usize - bit % 8)) & max as u16
+                    }
+                }
+            };
+            // Every sample brought to 8 bits.
+            let value = |channel: usize| -> u32 {
+                match depth {
+                    16 => u32::from(raw(channel) >> 8),
+                    _ => u32::from(raw(channel)) * 255 / max,
+                }
+            };
+            let keyed = |count: usize| {
+                key.as_ref()
+                    .is_some_and(|key| key.len() == count && (0..count).all(|c| raw(c) == key[c]))
+            };
+            let [r, g, b, a] = match color {
+                3 => *palette.get(raw(0) as usize)?,
+                0 | 4 => {
+                    let grey = value(0) as u8;
+                    let alpha = if color == 4 { value(1) as u8 } else { 255 };
+                    [grey, grey, grey, if keyed(1) { 0 } else { alpha }]
+                }
+                _ => {
+                    let alpha = if color == 6 { value(3) as u8 } else { 255 };
+                    let [r, g, b] = [0, 1, 2].map(|c| value(c) as u8);
+                    [r, g, b, if keyed(3) { 0 } else { alpha }]
+                }
+            };
+            pixels.push(u32::from_be_bytes([a, r, g, b]));
+        }
+    }
+    Some(Image {
+        width,
+        height,
+        pixels,
+        offsets,
+    })
+}
+
+/// Undoes the per-row filters, returning the rows without their filter bytes. `bpp` is the
+/// distance in bytes to the pixel on the left, at least 1.
+fn unfilter(data: &[u8], stride: usize, height: usize, bpp: usize) -> Option<Vec<u8>> {
+    let mut out = vec![0u8; stride * height];
+    for y in 0..height {
+        let line = data.get(y * (stride + 1)..(y + 1) * (stride + 1))?;
+        let (filter, line) = (line[0], &line[1..]);
+        let (done, rest) = out.split_at_mut(y * stride);
+        let up = done
+            .get(done.len().saturating_sub(stride)..)
+            .filter(|_| y > 0);
+        let row = &mut rest[..stride];
+        for x in 0..stride {
+            let a = if x >= bpp { row[x - bpp] } else { 0 };
+            let b = up.map_or(0, |up| up[x]);
+            let c = match up {
+                Some(up) if x >= bpp => up[x - bpp],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return None,
+            };
+            row[x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Some(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn header(width: usize, height: usize, color_type: u8) -> Vec<u8> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend((width as u32).to_be_bytes());
+    ihdr.extend((height as u32).to_be_bytes());
+    // Bit depth 8, then compression, filter and interlace methods 0.
+    ihdr.extend([8, color_type, 0, 0, 0]);
+    ihdr
+}
+
+/// Prefixes every scanline with filter type 0 (none).
+fn filtered(rows: impl Iterator<Item = Vec<u8>>) -> Vec<u8> {
+    let mut data = Vec::new();
+    for row in rows {
+        data.push(0);
+        data.extend(row);
+    }
+    data
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream made of stored (uncompressed) deflate blocks.
+pub fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn rgb_png_has_expected_layout() {
+        let png = encode_rgb(2, 1, &[0xff0000, 0x00ff00]);
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // Filter byte then the two pixels, inside the single stored block.
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        assert_eq!(&png[idat + 7..idat + 14], &[0, 255, 0, 0, 0, 255, 0]);
+    }
+
+    #[test]
+    fn rgba_png_keeps_alpha_last() {
+        let png = encode_rgba(1, 1, &[0x80_112233]);
+        assert_eq!(png[25], 6);
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        assert_eq!(&png[idat + 7..idat + 12], &[0, 0x11, 0x22, 0x33, 0x80]);
+    }
+
+    #[test]
+    fn large_data_is_split_into_stored_blocks() {
+        let stream = zlib_stored(&vec![7; 0x10000]);
+        // Header, two block headers, payload and checksum.
+        assert_eq!(stream.len(), 2 + 5 + 0xffff + 5 + 1 + 4);
+        assert_eq!(stream[2], 0);
+        assert_eq!(stream[2 + 5 + 0xffff], 1);
+    }
+
+    fn png(ihdr: [u8; 13], chunks: &[(&[u8; 4], &[u8])], data: &[u8]) -> Vec<u8> {
+        let mut out = SIGNATURE.to_vec();
+        write_chunk(&mut out, b"IHDR", &ihdr);
+        for (kind, chunk) in chunks {
+            write_chunk(&mut out, kind, chunk);
+        }
+        write_chunk(&mut out, b"IDAT", &zlib_stored(data));
+        write_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn written_images_read_back() {
+        let argb = [0x80_112233, 0xff_445566, 0x00_778899, 0x12_345678];
+        let image = decode(&encode_rgba(2, 2, &argb)).unwrap();
+        assert_eq!((image.width, image.height, image.offsets), (2, 2, None));
+        assert_eq!(image.pixels, argb);
+
+        let rgb = decode(&encode_rgb(2, 1, &[0xff0000, 0x00ff00])).unwrap();
+        assert_eq!(rgb.pixels, [0xffff_0000, 0xff00_ff00]);
+
+        let mut palette = [[0; 3]; 256];
+        palette[7] = [1, 2, 3];
+        let indexed = decode(&encode_indexed(1, 1, &[7], &palette)).unwrap();
+        assert_eq!(indexed.pixels, [0xff01_0203]);
+    }
+
+    #[test]
+    fn filters_depths_and_chunks_are_understood() {
+        let mut ihdr = header(2, 4, 2);
+        #[rustfmt::skip]
+        let rows = [
+            1, 10, 20, 30, 5, 5, 5, // sub
+            2, 1, 1, 1, 0, 0, 0, // up
+            3, 0, 0, 0, 0, 0, 0, // average
+            4, 0, 0, 0, 0, 0, 0, // paeth
+        ];
+        let image = decode(&png(ihdr.clone().try_into().unwrap(), &[], &rows)).unwrap();
+        let rgb: Vec<u32> = image.pixels.iter().map(|p| p & 0xffffff).collect();
+        assert_eq!(
+            rgb,
+            [0x0a141e, 0x0f1923, 0x0b151f, 0x0f1923, 0x050a0f, 0x0a1119, 0x050a0f, 0x0a1119]
+        );
+
+        // Three 1-bit grey pixels, with black keyed out and offsets.
+        ihdr = header(3, 1, 0);
+        ihdr[8] = 1;
+        let grab = [0, 0, 0, 5, 0xff, 0xff, 0xff, 0xfe];
+        let chunks: [(&[u8; 4], &[u8]); 2] = [(b"tRNS", &[0, 0]), (b"grAb", &grab)];
+        let image = decode(&png(ihdr.try_into().unwrap(), &chunks, &[0, 0b1010_0000])).unwrap();
+        assert_eq!(image.pixels, [0xffff_ffff, 0, 0xffff_ffff]);
+        assert_eq!(image.offsets, Some((5, -2)));
+    }
+
+    #[test]
+    fn unreadable_images_are_rejected() {
+        let good = encode_rgb(1, 1, &[0]);
+        assert!(decode(&good).is_some());
+        assert!(decode(&good[..good.len() - 20]).is_none());
+        assert!(decode(b"GIF89a").is_none());
+
+        let mut interlaced = header(1, 1, 2);
+        interlaced[12] = 1;
+        assert!(decode(&png(interlaced.try_into().unwrap(), &[], &[0, 0, 0, 0])).is_none());
+        let mut four_bit_rgb = header(1, 1, 2);
+        four_bit_rgb[8] = 4;
+        assert!(decode(&png(four_bit_rgb.try_into().unwrap(), &[], &[0, 0, 0])).is_none());
+        // A filter type that doesn't exist.
+        assert!(decode(&png(
+            header(1, 1, 2).try_into().unwrap(),
+            &[],
+            &[5, 0, 0, 0]
+        ))
+        .is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! A small property tester for the code whose exact results the game depends on.
+//!
+//! Each property runs against a few hundred generated inputs. Generation is seeded from the
+//! property's name and the case number, so a failure shows up on every run and every
+//! machine, and the panic message names the seed that `check_seed` needs to reproduce it.
+//! Generated numbers lean towards the edges (zero, one, the extremes, `FRACUNIT`) where
+//! fixed point and wrapping arithmetic usually break.
+
+use crate::util::fixed::FRACUNIT;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub const CASES: u32 = 500;
+
+const EDGES: [i32; 9] = [
+    0,
+    1,
+    -1,
+    FRACUNIT,
+    -FRACUNIT,
+    i32::MAX,
+    i32::MIN,
+    i32::MAX - 1,
+    i32::MIN + 1,
+];
+
+/// Deterministic input generator (splitmix64).
+#[derive(Clone, Debug)]
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Any `i32`, an edge value one time in four.
+    pub fn i32(&mut self) -> i32 {
+        if self.next_u64().is_multiple_of(4) {
+            self.choose(&EDGES)
+        } else {
+            self.next_u64() as i32
+        }
+    }
+
+    /// A value in `low..=high`.
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        let span = (i64::from(high) - i64::from(low) + 1) as u64;
+        (i64::from(low) + (self.next_u64() % span) as i64) as i32
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u64() & 1 != 0
+    }
+
+    pub fn choose<T: Copy>(&mut self, items: &[T]) -> T {
+        items[(self.next_u64() % items.len() as u64) as usize]
+    }
+
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = (self.next_u64() % (max_len as u64 + 1)) as usize;
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+/// Runs `property` on `cases` generated inputs, panicking on the first one it rejects.
+pub fn check(name: &str, cases: u32, mut property: impl FnMut(&mut Gen) -> Result<(), String>) {
+    let base = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+    });
+    for case in 0..u64::from(cases) {
+        let seed = base ^ case;
+        if let Err(message) = property(&mut Gen::new(seed)) {
+            panic!("property {name} failed on case {case} (seed {seed:#x}): {message}");
+        }
+    }
+}
+
+/// Runs `property` once with the seed from a failure message.
+pub fn check_seed(seed: u64, mut property: impl FnMut(&mut Gen) -> Result<(), String>) {
+    if let Err(message) = property(&mut Gen::new(seed)) {
+        panic!("seed {seed:#x}: {message}");
+    }
+}
+
+/// `Ok` when `condition` holds, otherwise the message `describe` builds.
+pub fn ensure(condition: bool, describe: impl FnOnce() -> String) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(describe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_is_reproducible() {
+        let (mut a, mut b) = (Gen::new(7), Gen::new(7));
+        for _ in 0..100 {
+            assert_eq!(a.i32(), b.i32());
+            let n = a.range(-3, 3);
+            assert_eq!(n, b.range(-3, 3));
+            assert!((-3..=3).contains(&n));
+        }
+        let mut gen = Gen::new(1);
+        assert_eq!(
+            gen.range(i32::MIN, i32::MAX),
+            Gen::new(1).range(i32::MIN, i32::MAX)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn failures_name_their_seed() {
+        use alloc::format;
+
+        let result = std::panic::catch_unwind(|| {
+            check("always fails", 10, |gen| {
+                let n = gen.i32();
+                ensure(false, || format!("got {n}"))
+            })
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.starts_with("property always fails failed on case 0 (seed 0x"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Decoded lumps, shared between threads.
+//!
+//! Vanilla's zone memory kept each cached lump behind a purge tag and decoded it on first use,
+//! which is where the mid-game hitches came from. A `LumpCache` holds one kind of decoded
+//! resource (patches, flats, sounds) by lump name. It is `Sync`, so `fill` can decode a
+//! whole list on worker threads while the level loads, and the game reads it afterwards
+//! without caring who decoded what.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug)]
+pub struct LumpCache<T> {
+    entries: Mutex<HashMap<String, Option<Arc<T>>>>, // `None` remembers a lump that didn't decode.
+}
+
+impl<T> Default for LumpCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Send + Sync> LumpCache<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// What the decoded lumps take up, as `size` measures each.
+    pub fn bytes(&self, size: impl Fn(&T) -> usize) -> usize {
+        let entries = self.entries.lock().unwrap();
+        entries.values().flatten().map(|entry| size(entry)).sum()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.lock().unwrap().contains_key(name)
+    }
+
+    /// The decoded lump, decoding it now if nothing has yet. The lock isn't held while
+    /// decoding, so two threads may decode the same lump; the first one stored wins.
+    pub fn get(&self, name: &str, decode: impl FnOnce(&str) -> Option<T>) -> Option<Arc<T>> {
+        if let Some(entry) = self.entries.lock().unwrap().get(name) {
+            return entry.clone();
+        }
+        super::note_read();
+        let decoded = decode(name).map(Arc::new);
+        let mut entries = self.entries.lock().unwrap();
+        entries.entry(name.to_string()).or_insert(decoded).clone()
+    }
+
+    /// Decodes every name not cached yet on `threads` workers, returning how many were new.
+    pub fn fill<S: AsRef<str> + Sync>(
+        &self,
+        names: &[S],
+        threads: usize,
+        decode: impl Fn(&str) -> Option<T> + Sync,
+    ) -> usize {
+        let next = AtomicUsize::new(0);
+        let decoded = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..threads.clamp(1, names.len().max(1)) {
+                scope.spawn(|| {
+                    while let Some(name) = names.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let name = name.as_ref();
+                        if !self.contains(name) {
+                            self.get(name, &decode);
+                            decoded.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        decoded.into_inner()
+    }
+
+    /// Drops everything, for the next level.
+    /// Forgets the decoded `name`, so the next `get` decodes it again. Returns whether it
+    /// was cached.
+    pub fn remove(&self, name: &str) -> bool {
+        self.entries.lock().unwrap().remove(name).is_some()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workers_fill_the_cache_once_per_name() {
+        let cache = LumpCache::new();
+        let names: Vec<String> = (0..200).map(|i| format!("LUMP{i}")).collect();
+        let calls = AtomicUsize::new(0);
+        let decode = |name: &str| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            name.strip_prefix("LUMP")?
+                .parse::<u32>()
+                .ok()
+                .filter(|n| n % 7 != 0)
+        };
+        assert_eq!(cache.fill(&names, 4, decode), 200);
+        assert_eq!(calls.load(Ordering::Relaxed), 200);
+        assert_eq!(cache.len(), 200);
+        assert_eq!(
+            cache.get("LUMP12", |_| unreachable!()).as_deref(),
+            Some(&12)
+        );
+        assert_eq!(cache.get("LUMP14", |_| unreachable!()), None);
+
+        assert_eq!(cache.fill(&names, 4, decode), 0);
+        assert!(cache.remove("LUMP12") && !cache.remove("LUMP12"));
+        assert_eq!(cache.fill(&names, 4, decode), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+//! Converting lumps to files other programs open, for `doom-rs extract`.
+//!
+//! Graphics become PNG through the palette, sounds WAV and music MIDI, using the same
+//! decoders the game plays them with. What a lump is can't always be told from its data, so
+//! the name and the marker section it sits in decide, the way the engine finds them: flats
+//! between `F_START` and `F_END`, sounds named `DS*`, music `D_*`, and everything else that
+//! parses as a patch is a patch.
+
+use super::inspect::MAP_LUMP_NAMES;
+use super::source::WadImage;
+use crate::audio::mus::{self, MIDI_MAGIC, MUS_MAGIC};
+use crate::audio::sound::Sound;
+use crate::renderer::framebuffer::Palette;
+use crate::renderer::patch::Patch;
+use crate::util::png;
+
+/// Lumps that are never pictures, even when their bytes happen to parse as a patch.
+const NOT_GRAPHICS: [&str; 14] = [
+    "PLAYPAL", "COLORMAP", "ENDOOM", "TEXTURE1", "TEXTURE2", "PNAMES", "GENMIDI", "DMXGUS",
+    "DMXGUSC", "DEHACKED", "UMAPINFO", "TRANMAP", "SWITCHES", "ANIMATED",
+];
+
+/// The file a lump turned into.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exported {
+    pub name: String,
+    pub extension: &'static str,
+    pub data: Vec<u8>,
+}
+
+impl Exported {
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", self.name, self.extension)
+    }
+}
+
+/// Converts every lump it knows how to, or only the ones in `names` when that isn't empty.
+/// Graphics need `palette`; without one they are skipped.
+pub fn extract(image: &WadImage, palette: Option<&Palette>, names: &[String]) -> Vec<Exported> {
+    let wanted =
+        |name: &str| names.is_empty() || names.iter().any(|n| n.eq_ignore_ascii_case(name));
+    let mut exported = Vec::new();
+    let mut in_flats = false;
+    let mut in_map = false;
+    let lumps: Vec<(&str, &[u8])> = image.lumps().map(|(name, _, data)| (name, data)).collect();
+    for (index, &(name, data)) in lumps.iter().enumerate() {
+        match name {
+            "F_START" | "FF_START" => in_flats = true,
+            "F_END" | "FF_END" => in_flats = false,
+            _ => {}
+        }
+        in_map &= MAP_LUMP_NAMES.contains(&name);
+        if matches!(lumps.get(index + 1), Some(("THINGS" | "TEXTMAP", _))) {
+            in_map = true;
+        }
+        if in_map {
+            continue;
+        }
+        if data.is_empty() || !wanted(name) {
+            continue;
+        }
+        let converted = match () {
+            _ if in_flats => {
+                palette.and_then(|palette| flat(data, palette).map(|png| ("png", png)))
+            }
+            _ if name.starts_with("DS") => Sound::parse(data).map(|sound| ("wav", sound.to_wav())),
+            _ if name.starts_with("D_") => music(data).map(|midi| ("mid", midi)),
+            _ if NOT_GRAPHICS.contains(&name) || name.starts_with("DEMO") => None,
+            _ => palette.and_then(|palette| patch(data, palette).map(|png| ("png", png))),
+        };
+        if let Some((extension, data)) = converted {
+            exported.push(Exported {
+                name: name.to_string(),
+                extension,
+                data,
+            });
+        }
+    }
+    exported
+}
+
+/// A flat is a square of raw palette indices, 64x64 in every IWAD.
+pub fn flat(data: &[u8], palette: &Palette) -> Option<Vec<u8>> {
+    let side = (data.len() as f64).sqrt() as usize;
+    if side == 0 || side * side != data.len() {
+        return None;
+    }
+    Some(png::encode_indexed(side, side, data, &colors(palette)))
+}
+
+/// A patch, with the pixels no post covers left transparent.
+pub fn patch(data: &[u8], palette: &Palette) -> Option<Vec<u8>> {
+    let patch = Patch::parse(data)?;
+    let (width, height) = (usize::from(patch.width), usize::from(patch.height));
+    if width == 0 || height == 0 || width > 4096 || height > 4096 {
+        return None;
+    }
+    let mut pixels = vec![0u32; width * height];
+    for (x, posts) in patch.columns.iter().enumerate() {
+        for post in posts {
+            for (dy, &index) in post.pixels.iter().enumerate() {
+                let y = usize::from(post.top) + dy;
+                if y < height {
+                    let [r, g, b] = palette.rgb(index);
+                    pixels[y * width + x] =
+                        0xff00_0000 | u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+                }
+            }
+        }
+    }
+    Some(png::encode_rgba(width, height, &pixels))
+}
+
+/// A music lump as MIDI: MUS is converted and MIDI, which some PWADs use, kept as it is.
+pub fn music(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(MIDI_MAGIC) {
+        Some(data.to_vec())
+    } else if data.starts_with(MUS_MAGIC) {
+        mus::to_midi(data).ok()
+    } else {
+        None
+    }
+}
+
+fn colors(palette: &Palette) -> [[u8; 3]; 256] {
+    core::array::from_fn(|index| palette.rgb(index as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+
+    fn palette() -> Palette {
+        let playpal: Vec<u8> = (0..=255u8).flat_map(|i| [i, 0, 255 - i]).collect();
+        Palette::from_playpal(&playpal, 0).unwrap()
+    }
+
+    #[test]
+    fn lumps_are_converted_by_kind() {
+        #[rustfmt::skip]
+        let patch_lump = [
+            1, 0, 2, 0, 0, 0, 0, 0, // 1x2
+            12, 0, 0, 0,
+            1, 1, 0, 9, 0, 0xff, // one pixel on the second row
+        ];
+        let mut sound = vec![3, 0, 0x11, 0x2b, 1, 0, 0, 0];
+        sound.push(0x80);
+        let mut mus_lump = MUS_MAGIC.to_vec();
+        mus_lump.extend([1, 0, 16, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0x60]);
+        let bytes = build_wad(
+            b"PWAD",
+            &[
+                ("PLAYPAL", &[0; 768]),
+                ("MAP01", &[]),
+                ("THINGS", &[1, 2, 3, 4]),
+                ("TITLEPIC", &patch_lump),
+                ("DSPISTOL", &sound),
+                ("D_E1M1", &mus_lump),
+                ("F_START", &[]),
+                ("FLOOR4_8", &[7; 4096]),
+                ("F_END", &[]),
+                ("DEMO1", &[1; 12]),
+            ],
+        );
+        let image = WadImage::parse(&bytes).unwrap();
+        let all = extract(&image, Some(&palette()), &[]);
+        let files: Vec<String> = all.iter().map(Exported::file_name).collect();
+        assert_eq!(
+            files,
+            ["TITLEPIC.png", "DSPISTOL.wav", "D_E1M1.mid", "FLOOR4_8.png"]
+        );
+        assert_eq!(all[1].data[44..], [0x80]);
+        assert!(all[2].data.starts_with(MIDI_MAGIC));
+
+        let only = extract(
+            &image,
+            None,
+            &["d_e1m1".to_string(), "TITLEPIC".to_string()],
+        );
+        assert_eq!(only.len(), 1, "graphics need a palette");
+    }
+
+    #[test]
+    fn patches_keep_their_transparency() {
+        #[rustfmt::skip]
+        let lump = [
+            1, 0, 2, 0, 0, 0, 0, 0,
+            12, 0, 0, 0,
+            1, 1, 0, 9, 0, 0xff,
+        ];
+        let png = patch(&lump, &palette()).unwrap();
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        // Two rows: a transparent pixel, then color 9 opaque.
+        assert_eq!(
+            &png[idat + 7..idat + 17],
+            &[0, 0, 0, 0, 0, 0, 9, 0, 246, 0xff]
+        );
+        assert!(flat(&[0; 100], &palette()).is_some());
+        assert!(flat(&[0; 99], &palette()).is_none());
+    }
+}
@@ -0,0 +1,289 @@
+//! WAD files and the lump directory built from all the loaded ones.
+//!
+//! A WAD is a 12 byte header (`IWAD` or `PWAD`, lump count, directory offset) followed by the
+//! lump data and a directory of 16 byte entries (offset, size, 8 byte name). Lumps loaded later
+//! replace earlier ones of the same name, which is what makes PWADs work.
+
+use super::lump_name;
+use super::replace::{Graphic, Replacements};
+use super::source::{LumpSource, MAP_LUMPS};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WadKind {
+    Iwad,
+    Pwad,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lump {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WadFile {
+    pub path: PathBuf,
+    pub kind: WadKind,
+    pub lumps: Vec<Lump>,
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+impl WadFile {
+    /// Parses a whole WAD held in memory. Returns `None` if the header or directory is broken.
+    pub fn parse(path: &Path, bytes: &[u8]) -> Option<Self> {
+        let kind = match bytes.get(..4)? {
+            b"IWAD" => WadKind::Iwad,
+            b"PWAD" => WadKind::Pwad,
+            _ => return None,
+        };
+        let count = usize::try_from(read_i32(bytes, 4)?).ok()?;
+        let directory = usize::try_from(read_i32(bytes, 8)?).ok()?;
+        // The whole directory has to be in the file before anything is sized by its count.
+        let end = count.checked_mul(16)?.checked_add(directory)?;
+        let entries = bytes.get(directory..end)?;
+        let mut lumps = Vec::with_capacity(count);
+        for entry in entries.chunks_exact(16) {
+            let offset = usize::try_from(read_i32(entry, 0)?).ok()?;
+            let size = usize::try_from(read_i32(entry, 4)?).ok()?;
+            lumps.push(Lump {
+                name: lump_name(&entry[8..16]),
+                data: bytes.get(offset..offset.checked_add(size)?)?.to_vec(),
+            });
+        }
+        Some(Self {
+            path: path.to_path_buf(),
+            kind,
+            lumps,
+        })
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        super::note_read();
+        Self::parse(path, &bytes).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a valid WAD file", path.display()),
+            )
+        })
+    }
+}
+
+/// The WADs of the current game, IWAD first, in load order.
+#[derive(Clone, Debug, Default)]
+pub struct Wads {
+    files: Vec<WadFile>,
+    /// PNG replacements, each with the number of WADs loaded before it.
+    replacements: Vec<(usize, Replacements)>,
+}
+
+impl Wads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, file: WadFile) {
+        self.files.push(file);
+    }
+
+    /// Adds PNG replacements on top of the WADs loaded so far.
+    pub fn add_replacements(&mut self, replacements: Replacements) {
+        self.replacements.push((self.files.len(), replacements));
+    }
+
+    /// Puts `file` in place of the loaded file with the same path, and `replacements` in
+    /// place of the ones added right after it. Returns the file replaced, or `None` if no
+    /// file has that path.
+    pub fn replace(&mut self, file: WadFile, replacements: Replacements) -> Option<WadFile> {
+        let index = self
+            .files
+            .iter()
+            .position(|loaded| loaded.path == file.path)?;
+        let old = std::mem::replace(&mut self.files[index], file);
+        let after = index + 1;
+        match self.replacements.iter_mut().find(|(at, _)| *at == after) {
+            Some((_, old)) => *old = replacements,
+            None => {
+                let at = self.replacements.partition_point(|(at, _)| *at <= after);
+                self.replacements.insert(at, (after, replacements));
+            }
+        }
+        Some(old)
+    }
+
+    pub fn files(&self) -> &[WadFile] {
+        &self.files
+    }
+
+    /// The graphic that wins for `name`, the lump or a PNG replacing it, whichever was loaded
+    /// last.
+    pub fn graphic(&self, name: &str) -> Option<Graphic<'_>> {
+        let name = name.to_ascii_uppercase();
+        let lump = self.lump(&name).map(|lump| lump.data.as_slice());
+        let png = self
+            .replacements
+            .iter()
+            .rev()
+            .find_map(|(after, replacements)| Some((*after, replacements.png(&name)?)));
+        let Some((after, png)) = png else {
+            return lump.map(Graphic::Lump);
+        };
+        let lump_from = self
+            .files
+            .iter()
+            .rposition(|file| file.lumps.iter().any(|lump| lump.name == name));
+        match lump_from {
+            Some(file) if file >= after => lump.map(Graphic::Lump),
+            _ => Some(Graphic::Png { png, lump }),
+        }
+    }
+
+    /// The lump that wins for `name`: the last one loaded.
+    pub fn lump(&self, name: &str) -> Option<&Lump> {
+        let name = name.to_ascii_uppercase();
+        self.files
+            .iter()
+            .rev()
+            .flat_map(|file| file.lumps.iter().rev())
+            .find(|lump| lump.name == name)
+    }
+
+    /// Every lump called `name` with the file it came from, in load order. For lumps that
+    /// stack instead of replacing each other, like `DEHACKED`.
+    pub fn lumps_named<'a>(&'a self, name: &str) -> impl Iterator<Item = (&'a WadFile, &'a Lump)> {
+        let name = name.to_ascii_uppercase();
+        self.files.iter().flat_map(move |file| {
+            let name = name.clone();
+            file.lumps
+                .iter()
+                .filter(move |lump| lump.name == name)
+                .map(move |lump| (file, lump))
+        })
+    }
+}
+
+impl LumpSource for Wads {
+    fn lump_data(&self, name: &str) -> Option<&[u8]> {
+        self.lump(name).map(|lump| lump.data.as_slice())
+    }
+
+    fn map_lump(&self, map: &str, lump: &str) -> Option<&[u8]> {
+        let map = map.to_ascii_uppercase();
+        let lumps = self.files.iter().rev().find_map(|file| {
+            let marker = file.lumps.iter().position(|lump| lump.name == map)?;
+            Some(&file.lumps[marker + 1..])
+        })?;
+        lumps
+            .iter()
+            .take(MAP_LUMPS)
+            .find(|candidate| candidate.name == lump)
+            .map(|lump| lump.data.as_slice())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a WAD in memory: directory at the end, lumps in order.
+    pub(crate) fn build_wad(magic: &[u8; 4], lumps: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for (name, bytes) in lumps {
+            directory.extend_from_slice(&(12 + data.len() as i32).to_le_bytes());
+            directory.extend_from_slice(&(bytes.len() as i32).to_le_bytes());
+            let mut padded = [0u8; 8];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            directory.extend_from_slice(&padded);
+            data.extend_from_slice(bytes);
+        }
+        let mut wad = magic.to_vec();
+        wad.extend_from_slice(&(lumps.len() as i32).to_le_bytes());
+        wad.extend_from_slice(&(12 + data.len() as i32).to_le_bytes());
+        wad.extend(data);
+        wad.extend(directory);
+        wad
+    }
+
+    #[test]
+    fn parses_header_and_directory() {
+        let bytes = build_wad(b"IWAD", &[("PLAYPAL", &[1, 2, 3]), ("e1m1", &[])]);
+        let wad = WadFile::parse(Path::new("doom.wad"), &bytes).unwrap();
+        assert_eq!(wad.kind, WadKind::Iwad);
+        assert_eq!(wad.lumps[0].data, vec![1, 2, 3]);
+        assert_eq!(wad.lumps[1].name, "E1M1");
+        assert!(WadFile::parse(Path::new("x"), b"WAD2\0\0\0\0").is_none());
+        // Directory pointing past the end of the file.
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 4);
+        assert!(WadFile::parse(Path::new("x"), &truncated).is_none());
+    }
+
+    #[test]
+    fn bogus_counts_and_sizes_are_rejected() {
+        let mut huge = build_wad(b"PWAD", &[("PLAYPAL", &[1, 2, 3])]);
+        huge[4..8].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(WadFile::parse(Path::new("x"), &huge).is_none());
+        // A directory offset and lump size that would wrap around.
+        let mut wrapping = build_wad(b"PWAD", &[("PLAYPAL", &[1, 2, 3])]);
+        let entry = wrapping.len() - 16;
+        wrapping[entry..entry + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+        wrapping[entry + 4..entry + 8].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(WadFile::parse(Path::new("x"), &wrapping).is_none());
+        wrapping[8..12].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(WadFile::parse(Path::new("x"), &wrapping).is_none());
+    }
+
+    #[test]
+    fn later_wads_override_and_stacking_lumps_keep_order() {
+        let mut wads = Wads::new();
+        for (path, magic, data) in [("doom2.wad", b"IWAD", b"a"), ("mod.wad", b"PWAD", b"b")] {
+            let bytes = build_wad(magic, &[("DEHACKED", data), ("TITLEPIC", data)]);
+            wads.add(WadFile::parse(Path::new(path), &bytes).unwrap());
+        }
+        assert_eq!(wads.lump("titlepic").unwrap().data, b"b");
+        let stacked: Vec<_> = wads
+            .lumps_named("DEHACKED")
+            .map(|(file, lump)| (file.path.clone(), lump.data.clone()))
+            .collect();
+        assert_eq!(
+            stacked,
+            vec![
+                (PathBuf::from("doom2.wad"), b"a".to_vec()),
+                (PathBuf::from("mod.wad"), b"b".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn replacements_win_over_the_wads_before_them() {
+        let png = crate::util::png::encode_rgb(1, 1, &[0]);
+        let mut pack = Replacements::new();
+        pack.add("TITLEPIC.png", png.clone());
+        pack.add("STBAR.png", png.clone());
+        let mut wads = Wads::new();
+        let iwad = build_wad(b"IWAD", &[("TITLEPIC", b"a"), ("STBAR", b"a")]);
+        wads.add(WadFile::parse(Path::new("doom2.wad"), &iwad).unwrap());
+        wads.add_replacements(pack);
+        let pwad = build_wad(b"PWAD", &[("STBAR", b"b")]);
+        wads.add(WadFile::parse(Path::new("mod.wad"), &pwad).unwrap());
+
+        assert_eq!(
+            wads.graphic("titlepic"),
+            Some(Graphic::Png {
+                png: &png,
+                lump: Some(b"a")
+            })
+        );
+        assert_eq!(wads.graphic("STBAR"), Some(Graphic::Lump(b"b")));
+        assert_eq!(wads.graphic("M_PAUSE"), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Folders mounted as WADs, so a mod can be worked on without packing it after every change.
+//!
+//! A folder is laid out the way `pk3` lays out an archive of the same files: file names
+//! become lump names and `sprites/`, `flats/`, `patches/` and `maps/` are namespaces. Files
+//! and folders whose names start with a dot are skipped, which keeps out `.git` and editor
+//! droppings.
+//!
+//! With `hot_reload` on, `reload_changed` checks the mounted folders between levels. A folder
+//! where a file was added, removed or saved since it was read is read again, and its lumps
+//! take the place of the old ones in the load order. Lumps decoded from the old ones are
+//! still in the caches, so the caller clears them when anything was reloaded.
+
+use super::file::Wads;
+use super::pk3::Pk3;
+use crate::engine::config::{Config, Configurable, Section};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FolderOptions {
+    pub hot_reload: bool,
+}
+
+impl Configurable for FolderOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("hot_reload", false, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            hot_reload: config.get_bool("hot_reload"),
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("hot_reload", self.hot_reload);
+    }
+}
+
+/// What a file looked like when it was read: its path in the folder, when it was last
+/// written and its size. The size catches a save within the clock's resolution.
+type Stamp = (String, Option<SystemTime>, u64);
+
+/// A mounted folder and the state of its files when it was last read.
+#[derive(Clone, Debug)]
+pub struct Folder {
+    root: PathBuf,
+    stamps: Vec<Stamp>,
+}
+
+impl Folder {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            stamps: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Reads every file in the folder as lumps.
+    pub fn load(&mut self) -> io::Result<Pk3> {
+        let files = walk(&self.root)?;
+        let mut contents = Vec::with_capacity(files.len());
+        for (name, _, _) in &files {
+            contents.push((name.clone(), fs::read(self.root.join(name))?));
+        }
+        super::note_read();
+        self.stamps = files;
+        Ok(Pk3::from_files(&self.root, contents))
+    }
+
+    /// Whether a file was added, removed or changed since `load`.
+    pub fn changed(&self) -> io::Result<bool> {
+        Ok(walk(&self.root)? != self.stamps)
+    }
+}
+
+/// Every file under `root` not hidden behind a dot, sorted by path.
+fn walk(root: &Path) -> io::Result<Vec<Stamp>> {
+    let mut files = Vec::new();
+    let mut folders = vec![String::new()];
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(root.join(&folder))? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = match folder.is_empty() {
+                true => name,
+                false => format!("{folder}/{name}"),
+            };
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                folders.push(path);
+            } else {
+                files.push((path, metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads again every folder in `folders` that changed since it was read, putting its lumps
+/// where the old ones were in `wads`. Returns how many were.
+pub fn reload_changed(folders: &mut [Folder], wads: &mut Wads) -> io::Result<usize> {
+    let mut reloaded = 0;
+    for folder in folders {
+        if folder.changed()? {
+            let pk3 = folder.load()?;
+            if wads.replace(pk3.file, pk3.replacements).is_some() {
+                reloaded += 1;
+            }
+        }
+    }
+    Ok(reloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::file::WadFile;
+    use crate::wad::source::LumpSource;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("doom-folder-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sprites")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn folders_mount_like_archives() {
+        let dir = scratch("mount");
+        fs::write(dir.join("sprites/TROOA1.lmp"), b"s").unwrap();
+        fs::write(dir.join("DEHACKED.deh"), b"d").unwrap();
+        fs::write(dir.join(".git/HEAD"), b"ref").unwrap();
+        let pk3 = Folder::new(&dir).load();
+        fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<String> = pk3
+            .unwrap()
+            .file
+            .lumps
+            .into_iter()
+            .map(|lump| lump.name)
+            .collect();
+        assert_eq!(names, ["DEHACKED", "SS_START", "TROOA1", "SS_END"]);
+        assert!(Folder::new(&dir).load().is_err());
+    }
+
+    #[test]
+    fn changed_folders_reload_in_place() {
+        let dir = scratch("reload");
+        fs::write(dir.join("M_PAUSE.lmp"), b"old").unwrap();
+        let mut wads = Wads::new();
+        let iwad = build_wad(b"IWAD", &[("M_PAUSE", b"a"), ("TITLEPIC", b"a")]);
+        wads.add(WadFile::parse(Path::new("doom2.wad"), &iwad).unwrap());
+        let mut folders = vec![Folder::new(&dir)];
+        folders[0].load().unwrap().mount(&mut wads);
+        let pwad = build_wad(b"PWAD", &[("TITLEPIC", b"b")]);
+        wads.add(WadFile::parse(Path::new("mod.wad"), &pwad).unwrap());
+
+        let unchanged = reload_changed(&mut folders, &mut wads).unwrap();
+        fs::write(dir.join("M_PAUSE.lmp"), b"newer").unwrap();
+        fs::write(dir.join("TITLEPIC.lmp"), b"c").unwrap();
+        let changed = reload_changed(&mut folders, &mut wads).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!((unchanged, changed), (0, 1));
+        assert_eq!(wads.files().len(), 3);
+        assert_eq!(wads.lump_data("M_PAUSE"), Some(&b"newer"[..]));
+        // The folder kept its place, under the PWAD loaded after it.
+        assert_eq!(wads.lump_data("TITLEPIC"), Some(&b"b"[..]));
+    }
+}
@@ -0,0 +1,471 @@
+//! What's inside a WAD, for `doom-rs wadinfo`.
+//!
+//! Most "my PWAD doesn't load" reports come down to a handful of things: a lump missing or
+//! misnamed, flats or sprites outside their markers, or a map saved without nodes or with a
+//! node format the engine doesn't read. Everything here works on a `WadImage`, so it sees the
+//! directory exactly as it is on disk, duplicates and empty markers included.
+
+use super::source::WadImage;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The directory as a table: index, name, offset and size of every lump.
+pub fn lump_table(image: &WadImage) -> String {
+    let magic = String::from_utf8_lossy(image.magic());
+    let mut text = format!("{magic}, {} lumps\n", image.len());
+    text.push_str(&format!(
+        "{:>5}  {:<8} {:>10} {:>9}\n",
+        "#", "name", "offset", "size"
+    ));
+    for (index, (name, offset, data)) in image.lumps().enumerate() {
+        text.push_str(&format!(
+            "{index:>5}  {name:<8} {offset:>10} {:>9}\n",
+            data.len()
+        ));
+    }
+    text
+}
+
+/// The data of the last lump called `name`, as the game would see it.
+pub fn find<'a>(image: &WadImage<'a>, name: &str) -> Option<&'a [u8]> {
+    let name = name.to_ascii_uppercase();
+    image
+        .lumps()
+        .filter(|(lump, _, _)| *lump == name)
+        .last()
+        .map(|(_, _, data)| data)
+}
+
+/// The graphics a WAD brings, by kind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inventory {
+    pub textures: Vec<String>,            // From TEXTURE1 and TEXTURE2.
+    pub patches: usize,                   // Names in PNAMES.
+    pub flats: Vec<String>,               // Between F_START and F_END.
+    pub sprites: BTreeMap<String, usize>, // Frames of each four letter sprite name.
+    pub stray_markers: Vec<String>,       // An _END without its _START, or the reverse.
+}
+
+impl Inventory {
+    pub fn read(image: &WadImage) -> Self {
+        let mut inventory = Inventory::default();
+        for name in ["TEXTURE1", "TEXTURE2"] {
+            if let Some(data) = find(image, name) {
+                inventory.textures.extend(texture_names(data));
+            }
+        }
+        inventory.patches = find(image, "PNAMES")
+            .and_then(|data| data.get(..4))
+            .map_or(0, |count| {
+                i32::from_le_bytes(count.try_into().unwrap()).max(0) as usize
+            });
+
+        let mut section = None;
+        for (name, _, _) in image.lumps() {
+            match marker(name) {
+                Some((_, _, true)) if section.is_some() => {} // F1_START and friends.
+                Some((kind, true, false)) if section.is_none() => section = Some(kind),
+                Some((kind, false, false)) if section == Some(kind) => section = None,
+                Some(_) => inventory.stray_markers.push(name.to_string()),
+                None => match section {
+                    Some('F') => inventory.flats.push(name.to_string()),
+                    Some('S') if name.len() >= 6 => {
+                        *inventory.sprites.entry(name[..4].to_string()).or_default() += 1;
+                    }
+                    _ => {}
+                },
+            }
+        }
+        if let Some(kind) = section {
+            inventory
+                .stray_markers
+                .push(format!("{kind}_START (never closed)"));
+        }
+        inventory
+    }
+}
+
+impl fmt::Display for Inventory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "textures: {} ({} patches in PNAMES)",
+            self.textures.len(),
+            self.patches
+        )?;
+        list(f, &self.textures)?;
+        writeln!(f, "flats: {}", self.flats.len())?;
+        list(f, &self.flats)?;
+        let frames: usize = self.sprites.values().sum();
+        writeln!(f, "sprites: {} ({frames} frames)", self.sprites.len())?;
+        let sprites: Vec<String> = self
+            .sprites
+            .iter()
+            .map(|(name, frames)| format!("{name}({frames})"))
+            .collect();
+        list(f, &sprites)?;
+        for marker in &self.stray_markers {
+            writeln!(f, "warning: stray marker {marker}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Names eight to a line, indented.
+fn list(f: &mut fmt::Formatter, names: &[String]) -> fmt::Result {
+    for line in names.chunks(8) {
+        let line: Vec<String> = line.iter().map(|name| format!("{name:<9}")).collect();
+        writeln!(f, "  {}", line.concat().trim_end())?;
+    }
+    Ok(())
+}
+
+/// `F_START`, `FF_END`, `F1_START` and so on: the section letter, whether it opens the
+/// section, and whether it's one of the numbered markers nested inside.
+fn marker(name: &str) -> Option<(char, bool, bool)> {
+    let (prefix, opens) = match name.rsplit_once('_')? {
+        (prefix, "START") => (prefix, true),
+        (prefix, "END") => (prefix, false),
+        _ => return None,
+    };
+    let kind = prefix
+        .chars()
+        .next()
+        .filter(|kind| matches!(kind, 'F' | 'S' | 'P'))?;
+    match &prefix[1..] {
+        "" => Some((kind, opens, false)),
+        rest if rest.len() == 1 && rest.starts_with(kind) => Some((kind, opens, false)),
+        rest if rest.chars().all(|c| c.is_ascii_digit()) => Some((kind, opens, true)),
+        _ => None,
+    }
+}
+
+/// The names in a TEXTURE1 or TEXTURE2 lump: a count, offsets, then a `maptexture_t` at each
+/// offset starting with its name.
+fn texture_names(data: &[u8]) -> Vec<String> {
+    let read = |at: usize| {
+        data.get(at..at + 4)
+            .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()).max(0) as usize)
+    };
+    let count = read(0).unwrap_or(0);
+    (0..count)
+        .map_while(|i| read(4 + i * 4))
+        .map_while(|offset| data.get(offset..offset + 8))
+        .map(super::lump_name)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapFormat {
+    Doom,
+    Hexen, // Has a BEHAVIOR lump.
+    Udmf,  // TEXTMAP through ENDMAP.
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Nodes {
+    Missing,
+    Vanilla(usize),   // Node count.
+    Extended(String), // The format's signature, like XNOD or ZGLN.
+}
+
+/// What a map marker is followed by and whether the engine can play it as it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapReport {
+    pub name: String,
+    pub format: MapFormat,
+    pub nodes: Nodes,
+    pub gl_nodes: bool,
+    pub blockmap: usize, // Bytes, 0 when it has to be built at load time.
+    pub reject: usize,
+}
+
+/// Vanilla stores blockmap offsets as signed 16 bit words.
+const VANILLA_BLOCKMAP: usize = 0x8000 * 2;
+
+impl fmt::Display for MapReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self.format {
+            MapFormat::Doom => "doom",
+            MapFormat::Hexen => "hexen",
+            MapFormat::Udmf => "udmf",
+        };
+        write!(f, "{:<8} {format:<5}  nodes: ", self.name)?;
+        match &self.nodes {
+            Nodes::Missing => write!(f, "missing, run a node builder")?,
+            Nodes::Vanilla(count) => write!(f, "{count}")?,
+            Nodes::Extended(kind) => write!(f, "{kind}")?,
+        }
+        if self.gl_nodes {
+            write!(f, " + GL nodes")?;
+        }
+        match self.blockmap {
+            0 => write!(f, "  blockmap: missing, built at load")?,
+            size if size > VANILLA_BLOCKMAP => {
+                write!(f, "  blockmap: {size} bytes, too big for vanilla")?
+            }
+            size => write!(f, "  blockmap: {size} bytes")?,
+        }
+        match self.reject {
+            0 => write!(f, "  reject: empty"),
+            size => write!(f, "  reject: {size} bytes"),
+        }
+    }
+}
+
+/// Every map in the WAD, in directory order.
+pub fn maps(image: &WadImage) -> Vec<MapReport> {
+    let lumps: Vec<(&str, &[u8])> = image.lumps().map(|(name, _, data)| (name, data)).collect();
+    let mut reports = Vec::new();
+    for (index, &(name, _)) in lumps.iter().enumerate() {
+        let after = &lumps[index + 1..];
+        let format = match after.first() {
+            Some(("THINGS", _)) => MapFormat::Doom,
+            Some(("TEXTMAP", _)) => MapFormat::Udmf,
+            _ => continue,
+        };
+        let end = match format {
+            MapFormat::Udmf => after.iter().position(|(name, _)| *name == "ENDMAP"),
+            _ => after
+                .iter()
+                .position(|(name, _)| !MAP_LUMP_NAMES.contains(name)),
+        };
+        let own = &after[..end.unwrap_or(after.len())];
+        let lump = |wanted: &str| own.iter().find(|(name, _)| *name == wanted).map(|l| l.1);
+        let format = match (format, lump("BEHAVIOR")) {
+            (MapFormat::Doom, Some(_)) => MapFormat::Hexen,
+            (format, _) => format,
+        };
+        let nodes = lump("NODES").or_else(|| lump("ZNODES")).unwrap_or_default();
+        let nodes = match nodes.get(..4) {
+            None => Nodes::Missing,
+            Some(
+                magic @ (b"XNOD" | b"ZNOD" | b"XGLN" | b"XGL2" | b"XGL3" | b"ZGLN" | b"ZGL2"
+                | b"ZGL3"),
+            ) => Nodes::Extended(String::from_utf8_lossy(magic).into_owned()),
+            Some(_) => Nodes::Vanilla(nodes.len() / 28),
+        };
+        let gl_marker = format!("GL_{name}");
+        let gl_marker = &gl_marker[..gl_marker.len().min(8)];
+        reports.push(MapReport {
+            name: name.to_string(),
+            format,
+            nodes,
+            gl_nodes: after.iter().any(|(name, _)| *name == gl_marker),
+            blockmap: lump("BLOCKMAP").map_or(0, <[u8]>::len),
+            reject: lump("REJECT").map_or(0, <[u8]>::len),
+        });
+    }
+    reports
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added(String, usize),
+    Removed(String, usize),
+    Changed {
+        name: String,
+        old: usize,
+        new: usize,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added(name, size) => write!(f, "+ {name} ({size} bytes)"),
+            Change::Removed(name, size) => write!(f, "- {name} ({size} bytes)"),
+            Change::Changed { name, old, new } => write!(f, "~ {name} ({old} -> {new} bytes)"),
+        }
+    }
+}
+
+/// The lumps added, removed or changed from `old` to `new`. Map lumps are named after their
+/// map (`MAP01/THINGS`), and a name that repeats outside of a map gets its occurrence
+/// (`DEHACKED#2`), so each one is compared with its counterpart.
+pub fn diff(old: &WadImage, new: &WadImage) -> Vec<Change> {
+    let (old, new) = (keyed(old), keyed(new));
+    let mut changes = Vec::new();
+    for (name, data) in &old {
+        match new.iter().find(|(other, _)| other == name) {
+            None => changes.push(Change::Removed(name.clone(), data.len())),
+            Some((_, other)) if other != data => changes.push(Change::Changed {
+                name: name.clone(),
+                old: data.len(),
+                new: other.len(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (name, data) in &new {
+        if !old.iter().any(|(other, _)| other == name) {
+            changes.push(Change::Added(name.clone(), data.len()));
+        }
+    }
+    changes
+}
+
+/// The lumps that can follow a map marker as part of the map.
+pub(crate) const MAP_LUMP_NAMES: [&str; 16] = [
+    "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS", "REJECT",
+    "BLOCKMAP", "BEHAVIOR", "SCRIPTS", "TEXTMAP", "ZNODES", "DIALOGUE", "ENDMAP",
+];
+
+fn keyed<'a>(image: &WadImage<'a>) -> Vec<(String, &'a [u8])> {
+    let lumps: Vec<(&str, &[u8])> = image.lumps().map(|(name, _, data)| (name, data)).collect();
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut map = None;
+    let mut keyed = Vec::new();
+    for (index, &(name, data)) in lumps.iter().enumerate() {
+        let key = match map {
+            Some(marker) if MAP_LUMP_NAMES.contains(&name) => format!("{marker}/{name}"),
+            _ => {
+                let next = lumps.get(index + 1).map(|lump| lump.0);
+                map = matches!(next, Some("THINGS" | "TEXTMAP")).then_some(name);
+                let count = seen.entry(name).or_default();
+                *count += 1;
+                match *count {
+                    1 => name.to_string(),
+                    n => format!("{name}#{n}"),
+                }
+            }
+        };
+        keyed.push((key, data));
+    }
+    keyed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+
+    fn texture1(names: &[&str]) -> Vec<u8> {
+        let mut data = (names.len() as i32).to_le_bytes().to_vec();
+        for i in 0..names.len() {
+            data.extend((4 + names.len() as i32 * 4 + i as i32 * 8).to_le_bytes());
+        }
+        for name in names {
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.resize(8, 0);
+            data.extend(bytes);
+        }
+        data
+    }
+
+    #[test]
+    fn lumps_and_inventory() {
+        let textures = texture1(&["STARTAN3", "BIGDOOR1"]);
+        let bytes = build_wad(
+            b"PWAD",
+            &[
+                ("TEXTURE1", &textures),
+                ("PNAMES", &[3, 0, 0, 0]),
+                ("F_START", &[]),
+                ("F1_START", &[]),
+                ("NUKAGE1", &[0; 4096]),
+                ("F1_END", &[]),
+                ("F_END", &[]),
+                ("S_START", &[]),
+                ("TROOA1", &[1]),
+                ("TROOB1", &[1]),
+                ("POSSA1", &[1]),
+                ("S_END", &[]),
+                ("FF_END", &[]),
+            ],
+        );
+        let image = WadImage::parse(&bytes).unwrap();
+        let table = lump_table(&image);
+        assert!(table.starts_with("PWAD, 13 lumps\n"));
+        assert!(table.contains("    4  NUKAGE1"));
+        assert_eq!(find(&image, "pnames"), Some(&[3u8, 0, 0, 0][..]));
+
+        let inventory = Inventory::read(&image);
+        assert_eq!(inventory.textures, ["STARTAN3", "BIGDOOR1"]);
+        assert_eq!(inventory.patches, 3);
+        assert_eq!(inventory.flats, ["NUKAGE1"]);
+        assert_eq!(inventory.sprites.get("TROO"), Some(&2));
+        assert_eq!(inventory.stray_markers, ["FF_END"]);
+        let text = inventory.to_string();
+        assert!(text.contains("sprites: 2 (3 frames)\n  POSS(1)  TROO(2)\n"));
+        assert!(text.contains("warning: stray marker FF_END"));
+    }
+
+    #[test]
+    fn maps_report_their_nodes_and_blockmap() {
+        let map = |name: &'static str, nodes: &'static [u8], blockmap: usize| {
+            let mut lumps = vec![(name, Vec::new())];
+            for lump in [
+                "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS",
+            ] {
+                lumps.push((lump, vec![1]));
+            }
+            lumps.push(("NODES", nodes.to_vec()));
+            lumps.extend([("SECTORS", vec![1]), ("REJECT", vec![])]);
+            lumps.push(("BLOCKMAP", vec![0; blockmap]));
+            lumps
+        };
+        let mut lumps = map("MAP01", &[0; 56], 100);
+        lumps.extend(map("MAP02", &[], 0));
+        lumps.extend(map("MAP03", b"XNOD....", VANILLA_BLOCKMAP + 2));
+        lumps.push(("GL_MAP03", vec![]));
+        lumps.extend([
+            ("MAP04", vec![]),
+            ("TEXTMAP", vec![1]),
+            ("ZNODES", b"ZGLN".to_vec()),
+        ]);
+        lumps.push(("ENDMAP", vec![]));
+        let lumps: Vec<(&str, &[u8])> = lumps.iter().map(|(n, d)| (*n, d.as_slice())).collect();
+        let bytes = build_wad(b"PWAD", &lumps);
+        let reports = maps(&WadImage::parse(&bytes).unwrap());
+
+        let names: Vec<&str> = reports.iter().map(|report| report.name.as_str()).collect();
+        assert_eq!(names, ["MAP01", "MAP02", "MAP03", "MAP04"]);
+        assert_eq!(reports[0].nodes, Nodes::Vanilla(2));
+        assert_eq!(
+            reports[1].to_string(),
+            "MAP02    doom   nodes: missing, run a node builder  blockmap: missing, built at load  reject: empty"
+        );
+        assert!(reports[2].gl_nodes);
+        assert!(reports[2]
+            .to_string()
+            .contains("XNOD + GL nodes  blockmap: 65538 bytes, too big"));
+        assert_eq!(reports[3].format, MapFormat::Udmf);
+        assert_eq!(reports[3].nodes, Nodes::Extended("ZGLN".into()));
+    }
+
+    #[test]
+    fn diffs_pair_up_map_lumps_and_repeats() {
+        let old = build_wad(
+            b"PWAD",
+            &[
+                ("MAP01", &[]),
+                ("THINGS", &[1]),
+                ("DEHACKED", &[1]),
+                ("DEHACKED", &[2]),
+            ],
+        );
+        let new = build_wad(
+            b"PWAD",
+            &[
+                ("MAP01", &[]),
+                ("THINGS", &[1, 2]),
+                ("DEHACKED", &[1]),
+                ("PLAYPAL", &[0; 3]),
+            ],
+        );
+        let changes = diff(
+            &WadImage::parse(&old).unwrap(),
+            &WadImage::parse(&new).unwrap(),
+        );
+        let changes: Vec<String> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            [
+                "~ MAP01/THINGS (1 -> 2 bytes)",
+                "- DEHACKED#2 (1 bytes)",
+                "+ PLAYPAL (3 bytes)"
+            ]
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Finding the IWAD when the user didn't say where it is.
+//!
+//! This mirrors what Chocolate Doom does: look in `DOOMWADDIR` and `DOOMWADPATH`, next to the
+//! executable, in the current directory, the XDG data directories, and the folders the Steam
+//! and GOG releases install to on each platform. Any file with a known IWAD name and an `IWAD`
+//! header counts.
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMission {
+    Doom,
+    Doom2,
+    PackTnt,
+    PackPlutonia,
+    Chex,
+    Hacx,
+    Freedoom1,
+    Freedoom2,
+    FreeDm,
+}
+
+#[derive(Debug)]
+pub struct IwadInfo {
+    pub file_name: &'static str,
+    pub mission: GameMission,
+    pub description: &'static str,
+}
+
+/// Known IWADs, in the order they are preferred when several are installed.
+pub static IWADS: &[IwadInfo] = &[
+    iwad("doom2.wad", GameMission::Doom2, "Doom II"),
+    iwad(
+        "plutonia.wad",
+        GameMission::PackPlutonia,
+        "Final Doom: Plutonia Experiment",
+    ),
+    iwad(
+        "tnt.wad",
+        GameMission::PackTnt,
+        "Final Doom: TNT: Evilution",
+    ),
+    iwad("doom.wad", GameMission::Doom, "Doom"),
+    iwad("doom1.wad", GameMission::Doom, "Doom Shareware"),
+    iwad("chex.wad", GameMission::Chex, "Chex Quest"),
+    iwad("hacx.wad", GameMission::Hacx, "Hacx"),
+    iwad("freedoom2.wad", GameMission::Freedoom2, "Freedoom: Phase 2"),
+    iwad("freedoom1.wad", GameMission::Freedoom1, "Freedoom: Phase 1"),
+    iwad("freedm.wad", GameMission::FreeDm, "FreeDM"),
+];
+
+const fn iwad(
+    file_name: &'static str,
+    mission: GameMission,
+    description: &'static str,
+) -> IwadInfo {
+    IwadInfo {
+        file_name,
+        mission,
+        description,
+    }
+}
+
+/// Install folders of the commercial releases, relative to a Steam library or GOG root.
+const STEAM_APPS: &[&str] = &[
+    "Ultimate Doom/rerelease",
+    "Ultimate Doom/base",
+    "Doom 2/rerelease",
+    "Doom 2/base",
+    "Doom 2/finaldoombase",
+    "Final Doom/base",
+    "DOOM 3 BFG Edition/base/wads",
+];
+
+const GOG_GAMES: &[&str] = &["DOOM", "DOOM 2", "Final Doom", "Ultimate Doom", "DOOM II"];
+
+#[derive(Clone, Debug)]
+pub struct FoundIwad {
+    pub path: PathBuf,
+    pub info: &'static IwadInfo,
+}
+
+#[derive(Debug)]
+pub enum IwadError {
+    NotFound { searched: Vec<PathBuf> },
+    RequestedMissing(PathBuf),
+    NotAnIwad(PathBuf),
+}
+
+impl fmt::Display for IwadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IwadError::NotFound { searched } => {
+                writeln!(
+                    f,
+                    "no IWAD found. Put doom.wad or doom2.wad in one of these folders,"
+                )?;
+                writeln!(f, "set DOOMWADDIR, or name the file with -iwad:")?;
+                for dir in searched {
+                    writeln!(f, "  {}", dir.display())?;
+                }
+                Ok(())
+            }
+            IwadError::RequestedMissing(path) => write!(f, "IWAD '{}' not found", path.display()),
+            IwadError::NotAnIwad(path) => {
+                write!(
+                    f,
+                    "'{}' is not an IWAD (it may be a PWAD, load it with -file)",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IwadError {}
+
+/// The folders to search, most specific first. `env` reads an environment variable; it is a
+/// parameter so tests don't depend on the machine they run on.
+pub fn search_dirs(env: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let separator = if cfg!(windows) { ';' } else { ':' };
+
+    if let Some(dir) = env("DOOMWADDIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(path) = env("DOOMWADPATH") {
+        dirs.extend(
+            path.split(separator)
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(dir);
+    }
+    dirs.push(PathBuf::from("."));
+
+    let home = env("HOME")
+        .or_else(|| env("USERPROFILE"))
+        .map(PathBuf::from);
+    if cfg!(windows) {
+        for root in ["ProgramFiles(x86)", "ProgramFiles"]
+            .iter()
+            .filter_map(|v| env(v))
+        {
+            let steam = Path::new(&root).join("Steam/steamapps/common");
+            dirs.extend(STEAM_APPS.iter().map(|app| steam.join(app)));
+            dirs.extend(
+                GOG_GAMES
+                    .iter()
+                    .map(|game| Path::new(&root).join("GOG Galaxy/Games").join(game)),
+            );
+        }
+        dirs.extend(
+            GOG_GAMES
+                .iter()
+                .map(|game| Path::new("C:\\GOG Games").join(game)),
+        );
+    } else {
+        let data_home = env("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+        if let Some(data_home) = &data_home {
+            dirs.push(data_home.join("games/doom"));
+        }
+        let data_dirs =
+            env("XDG_DATA_DIRS").unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+        for dir in data_dirs.split(':').filter(|d| !d.is_empty()) {
+            dirs.push(Path::new(dir).join("games/doom"));
+            dirs.push(Path::new(dir).join("doom"));
+        }
+
+        let mut steam_roots = Vec::new();
+        if let Some(home) = &home {
+            if cfg!(target_os = "macos") {
+                steam_roots.push(home.join("Library/Application Support/Steam"));
+            } else {
+                steam_roots.push(home.join(".steam/steam"));
+                steam_roots.push(home.join(".local/share/Steam"));
+                steam_roots.push(home.join(".var/app/com.valvesoftware.Steam/data/Steam"));
+            }
+            dirs.extend(
+                GOG_GAMES
+                    .iter()
+                    .map(|game| home.join("GOG Games").join(game)),
+            );
+        }
+        for root in steam_roots {
+            let common = root.join("steamapps/common");
+            dirs.extend(STEAM_APPS.iter().map(|app| common.join(app)));
+        }
+    }
+    dirs
+}
+
+/// Whether the file starts with the `IWAD` magic.
+pub fn is_iwad(path: &Path) -> bool {
+    let mut magic = [0; 4];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && &magic == b"IWAD"
+}
+
+fn info_for(file_name: &str) -> Option<&'static IwadInfo> {
+    let lower = file_name.to_ascii_lowercase();
+    IWADS.iter().find(|info| info.file_name == lower)
+}
+
+/// Every IWAD in `dirs`, in preference order. File names match case insensitively and the same
+/// file reached through two folders is only listed once.
+pub fn find_iwads(dirs: &[PathBuf]) -> Vec<FoundIwad> {
+    let mut found: Vec<FoundIwad> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+        entries.sort();
+        for path in entries {
+            let Some(info) = path.file_name().and_then(|n| n.to_str()).and_then(info_for) else {
+                continue;
+            };
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            let duplicate = found
+                .iter()
+                .any(|f| fs::canonicalize(&f.path).unwrap_or_else(|_| f.path.clone()) == canonical);
+            if !duplicate && is_iwad(&path) {
+                found.push(FoundIwad { path, info });
+            }
+        }
+    }
+    // Stable, so equally preferred IWADs keep the folder search order.
+    found.sort_by_key(|f| IWADS.iter().position(|info| std::ptr::eq(info, f.info)));
+    found
+}
+
+/// Picks the IWAD to load.
+///
+/// An explicit `-iwad` wins: a path is used as is, a bare file name is also looked up in the
+/// search folders. Otherwise, with several IWADs installed `choose` is asked to pick one (a menu
+/// or a prompt); returning `None` takes the most preferred one.
+pub fn locate(
+    requested: Option<&Path>,
+    dirs: &[PathBuf],
+    choose: impl FnOnce(&[FoundIwad]) -> Option<usize>,
+) -> Result<FoundIwad, IwadError> {
+    if let Some(requested) = requested {
+        let candidates = std::iter::once(requested.to_path_buf()).chain(
+            (requested.components().count() == 1)
+                .then(|| dirs.iter().map(|dir| dir.join(requested)))
+                .into_iter()
+                .flatten(),
+        );
+        for path in candidates {
+            if path.is_file() {
+                if !is_iwad(&path) {
+                    return Err(IwadError::NotAnIwad(path));
+                }
+                let info = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(info_for)
+                    .unwrap_or(&IWADS[0]);
+                return Ok(FoundIwad { path, info });
+            }
+        }
+        return Err(IwadError::RequestedMissing(requested.to_path_buf()));
+    }
+
+    let mut found = find_iwads(dirs);
+    match found.len() {
+        0 => Err(IwadError::NotFound {
+            searched: dirs.to_vec(),
+        }),
+        1 => Ok(found.remove(0)),
+        _ => {
+            let index = choose(&found).filter(|&i| i < found.len()).unwrap_or(0);
+            Ok(found.swap_remove(index))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("doom-rs-iwad-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn search_dirs_honor_environment_and_steam() {
+        let env = |name: &str| match name {
+            "DOOMWADDIR" => Some("/wads".to_string()),
+            "DOOMWADPATH" => Some(if cfg!(windows) { "/a;/b" } else { "/a:/b" }.to_string()),
+            "HOME" => Some("/home/marine".to_string()),
+            _ => None,
+        };
+        let dirs = search_dirs(&env);
+        assert_eq!(
+            &dirs[..3],
+            &[
+                PathBuf::from("/wads"),
+                PathBuf::from("/a"),
+                PathBuf::from("/b")
+            ]
+        );
+        if cfg!(target_os = "linux") {
+            assert!(dirs.contains(&PathBuf::from("/home/marine/.local/share/games/doom")));
+            assert!(dirs.contains(&PathBuf::from(
+                "/home/marine/.steam/steam/steamapps/common/Doom 2/base"
+            )));
+        }
+    }
+
+    #[test]
+    fn finds_iwads_by_name_and_magic_in_preference_order() {
+        let dir = temp_dir("find");
+        fs::write(dir.join("DOOM.WAD"), b"IWAD....").unwrap();
+        fs::write(dir.join("doom2.wad"), b"IWAD....").unwrap();
+        fs::write(dir.join("tnt.wad"), b"PWAD....").unwrap();
+        fs::write(dir.join("other.wad"), b"IWAD....").unwrap();
+
+        let found = find_iwads(&[dir.clone(), dir.clone()]);
+        let names: Vec<_> = found.iter().map(|f| f.info.file_name).collect();
+        assert_eq!(names, vec!["doom2.wad", "doom.wad"]);
+
+        let picked = locate(None, std::slice::from_ref(&dir), |choices| {
+            assert_eq!(choices.len(), 2);
+            Some(1)
+        })
+        .unwrap();
+        assert_eq!(picked.info.mission, GameMission::Doom);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn explicit_iwad_is_looked_up_in_search_dirs() {
+        let dir = temp_dir("explicit");
+        fs::write(dir.join("plutonia.wad"), b"IWAD....").unwrap();
+        fs::write(dir.join("mod.wad"), b"PWAD....").unwrap();
+        let dirs = [dir.clone()];
+
+        let found = locate(Some(Path::new("plutonia.wad")), &dirs, |_| None).unwrap();
+        assert_eq!(found.info.mission, GameMission::PackPlutonia);
+        assert!(matches!(
+            locate(Some(Path::new("mod.wad")), &dirs, |_| None),
+            Err(IwadError::NotAnIwad(_))
+        ));
+        assert!(matches!(
+            locate(Some(Path::new("missing.wad")), &dirs, |_| None),
+            Err(IwadError::RequestedMissing(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nothing_found_lists_searched_folders() {
+        let dir = temp_dir("empty");
+        let error = locate(None, std::slice::from_ref(&dir), |_| None).unwrap_err();
+        assert!(error.to_string().contains(&dir.display().to_string()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,296 @@
+use super::lump_name;
+use super::source::LumpSource;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Marks a missing sidedef in a linedef, i.e. a one-sided line.
+pub const NO_SIDEDEF: u16 = 0xffff;
+
+/// Linedef flags the automap reads.
+pub const ML_SECRET: u16 = 0x20; // Drawn as a one-sided wall.
+pub const ML_DONTDRAW: u16 = 0x80; // Never drawn.
+pub const ML_MAPPED: u16 = 0x100; // Seen by the player, or in the savegame as seen.
+
+/// Set in a node's child when it is a subsector rather than another node.
+pub const NF_SUBSECTOR: u16 = 0x8000;
+
+/// The size of a NODES record.
+pub const NODE_SIZE: usize = 28;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vertex {
+    pub x: i16,
+    pub y: i16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thing {
+    pub x: i16,
+    pub y: i16,
+    pub angle: i16,
+    pub kind: u16, // The editor number (doomednum), e.g. 1 for player 1 start.
+    pub flags: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineDef {
+    pub v1: u16,
+    pub v2: u16,
+    pub flags: u16,
+    pub special: u16,
+    pub tag: u16,
+    pub front: u16,
+    pub back: u16, // `NO_SIDEDEF` for one-sided lines.
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SideDef {
+    pub x_offset: i16,
+    pub y_offset: i16,
+    pub upper: String,
+    pub lower: String,
+    pub middle: String,
+    pub sector: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sector {
+    pub floor_height: i16,
+    pub ceiling_height: i16,
+    pub floor_pic: String,
+    pub ceiling_pic: String,
+    pub light: i16,
+    pub special: i16,
+    pub tag: i16,
+}
+
+/// A BSP node: the partition line and what lies on either side of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub x: i16,
+    pub y: i16,
+    pub dx: i16,
+    pub dy: i16,
+    /// Top, bottom, left and right of the right child, then of the left child.
+    pub bbox: [[i16; 4]; 2],
+    /// Right and left child, `NF_SUBSECTOR` marking a subsector.
+    pub children: [u16; 2],
+}
+
+/// The geometry lumps of a single map, as stored in the WAD.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MapData {
+    pub things: Vec<Thing>,
+    pub vertexes: Vec<Vertex>,
+    pub linedefs: Vec<LineDef>,
+    pub sidedefs: Vec<SideDef>,
+    pub sectors: Vec<Sector>,
+}
+
+fn i16_at(bytes: &[u8], at: usize) -> i16 {
+    i16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Splits a lump into fixed size records. Returns `None` if the size is not a multiple.
+fn records(lump: &[u8], size: usize) -> Option<core::slice::ChunksExact<'_, u8>> {
+    lump.len().is_multiple_of(size).then(|| lump.chunks_exact(size))
+}
+
+impl MapData {
+    /// Builds a map from the raw THINGS, VERTEXES, LINEDEFS, SIDEDEFS and SECTORS lumps.
+    /// Returns `None` if a lump is truncated or a reference points past the end of its table.
+    pub fn parse(
+        things: &[u8],
+        vertexes: &[u8],
+        linedefs: &[u8],
+        sidedefs: &[u8],
+        sectors: &[u8],
+    ) -> Option<Self> {
+        let map = Self {
+            things: records(things, 10)?
+                .map(|r| Thing {
+                    x: i16_at(r, 0),
+                    y: i16_at(r, 2),
+                    angle: i16_at(r, 4),
+                    kind: u16_at(r, 6),
+                    flags: u16_at(r, 8),
+                })
+                .collect(),
+            vertexes: records(vertexes, 4)?
+                .map(|r| Vertex {
+                    x: i16_at(r, 0),
+                    y: i16_at(r, 2),
+                })
+                .collect(),
+            linedefs: records(linedefs, 14)?
+                .map(|r| LineDef {
+                    v1: u16_at(r, 0),
+                    v2: u16_at(r, 2),
+                    flags: u16_at(r, 4),
+                    special: u16_at(r, 6),
+                    tag: u16_at(r, 8),
+                    front: u16_at(r, 10),
+                    back: u16_at(r, 12),
+                })
+                .collect(),
+            sidedefs: records(sidedefs, 30)?
+                .map(|r| SideDef {
+                    x_offset: i16_at(r, 0),
+                    y_offset: i16_at(r, 2),
+                    upper: lump_name(&r[4..12]),
+                    lower: lump_name(&r[12..20]),
+                    middle: lump_name(&r[20..28]),
+                    sector: u16_at(r, 28),
+                })
+                .collect(),
+            sectors: records(sectors, 26)?
+                .map(|r| Sector {
+                    floor_height: i16_at(r, 0),
+                    ceiling_height: i16_at(r, 2),
+                    floor_pic: lump_name(&r[4..12]),
+                    ceiling_pic: lump_name(&r[12..20]),
+                    light: i16_at(r, 20),
+                    special: i16_at(r, 22),
+                    tag: i16_at(r, 24),
+                })
+                .collect(),
+        };
+
+        let lines_valid = map.linedefs.iter().all(|line| {
+            (line.v1 as usize) < map.vertexes.len()
+                && (line.v2 as usize) < map.vertexes.len()
+                && (line.front as usize) < map.sidedefs.len()
+                && (line.back == NO_SIDEDEF || (line.back as usize) < map.sidedefs.len())
+        });
+        let sides_valid = map
+            .sidedefs
+            .iter()
+            .all(|side| (side.sector as usize) < map.sectors.len());
+        (lines_valid && sides_valid).then_some(map)
+    }
+
+    /// The map named `name` (`E1M1`, `MAP01`) from the last WAD that has it.
+    pub fn load(wads: &impl LumpSource, name: &str) -> Option<Self> {
+        let find = |lump: &str| wads.map_lump(name, lump);
+        Self::parse(
+            find("THINGS")?,
+            find("VERTEXES")?,
+            find("LINEDEFS")?,
+            find("SIDEDEFS")?,
+            find("SECTORS")?,
+        )
+    }
+
+    /// Reads a NODES lump, or any run of whole records from one.
+    pub fn parse_nodes(nodes: &[u8]) -> Option<Vec<Node>> {
+        let nodes = records(nodes, NODE_SIZE)?
+            .map(|r| Node {
+                x: i16_at(r, 0),
+                y: i16_at(r, 2),
+                dx: i16_at(r, 4),
+                dy: i16_at(r, 6),
+                bbox: core::array::from_fn(|side| {
+                    core::array::from_fn(|i| i16_at(r, 8 + side * 8 + i * 2))
+                }),
+                children: [u16_at(r, 24), u16_at(r, 26)],
+            })
+            .collect();
+        Some(nodes)
+    }
+
+    pub fn front_sector(&self, line: &LineDef) -> &Sector {
+        &self.sectors[self.sidedefs[line.front as usize].sector as usize]
+    }
+
+    pub fn back_sector(&self, line: &LineDef) -> Option<&Sector> {
+        (line.back != NO_SIDEDEF)
+            .then(|| &self.sectors[self.sidedefs[line.back as usize].sector as usize])
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn name(s: &str) -> [u8; 8] {
+        let mut out = [0; 8];
+        out[..s.len()].copy_from_slice(s.as_bytes());
+        out
+    }
+
+    /// A square room split in two by a step: sector 0 is 0..128 high, sector 1 is 32..96.
+    pub(crate) fn two_room_map() -> MapData {
+        let vertexes: Vec<u8> = [(0i16, 0i16), (64, 0), (64, 64), (0, 64)]
+            .iter()
+            .flat_map(|(x, y)| [x.to_le_bytes(), y.to_le_bytes()].concat())
+            .collect();
+        let line = |v1: u16, v2: u16, front: u16, back: u16| {
+            [v1, v2, 0, 0, 0, front, back]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>()
+        };
+        let linedefs = [line(0, 1, 0, NO_SIDEDEF), line(1, 3, 1, 2)].concat();
+        let side = |middle: &str, lower: &str, sector: u16| {
+            let mut bytes = vec![0; 4];
+            bytes.extend(name("STARTAN3"));
+            bytes.extend(name(lower));
+            bytes.extend(name(middle));
+            bytes.extend(sector.to_le_bytes());
+            bytes
+        };
+        let sidedefs = [
+            side("STARTAN3", "", 0),
+            side("", "STEP1", 0),
+            side("", "", 1),
+        ]
+        .concat();
+        let sector = |floor: i16, ceiling: i16, light: i16| {
+            let mut bytes = [floor.to_le_bytes(), ceiling.to_le_bytes()].concat();
+            bytes.extend(name("FLOOR4_8"));
+            bytes.extend(name("CEIL3_5"));
+            bytes.extend([light.to_le_bytes(), [0, 0], [0, 0]].concat());
+            bytes
+        };
+        let sectors = [sector(0, 128, 160), sector(32, 96, 255)].concat();
+        MapData::parse(&[], &vertexes, &linedefs, &sidedefs, &sectors).unwrap()
+    }
+
+    #[test]
+    fn parses_map_lumps() {
+        let map = two_room_map();
+        assert_eq!(map.vertexes[2], Vertex { x: 64, y: 64 });
+        assert_eq!(map.linedefs[1].back, 2);
+        assert_eq!(map.sidedefs[1].lower, "STEP1");
+        assert_eq!(map.sectors[1].floor_pic, "FLOOR4_8");
+        assert_eq!(map.back_sector(&map.linedefs[1]).unwrap().floor_height, 32);
+        assert!(map.back_sector(&map.linedefs[0]).is_none());
+    }
+
+    #[test]
+    fn rejects_dangling_references() {
+        let linedef: Vec<u8> = [0u16, 1, 0, 0, 0, 0, NO_SIDEDEF]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        assert!(MapData::parse(&[], &[], &linedef, &[], &[]).is_none());
+        assert!(MapData::parse(&[0; 9], &[], &[], &[], &[]).is_none());
+    }
+
+    #[test]
+    fn parses_nodes() {
+        let words: [i16; 14] = [64, 0, 0, 64, 64, 0, 0, 64, 64, 0, 64, 128, 0, 0];
+        let mut lump: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        lump[26..28].copy_from_slice(&(NF_SUBSECTOR | 1).to_le_bytes());
+        let nodes = MapData::parse_nodes(&lump).unwrap();
+        assert_eq!(nodes[0].dy, 64);
+        assert_eq!(nodes[0].bbox, [[64, 0, 0, 64], [64, 0, 64, 128]]);
+        assert_eq!(nodes[0].children, [0, NF_SUBSECTOR | 1]);
+        assert!(MapData::parse_nodes(&lump[..27]).is_none());
+    }
+}
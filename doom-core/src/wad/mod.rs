@@ -0,0 +1,56 @@
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod file;
+#[cfg(feature = "std")]
+pub mod folder;
+#[cfg(feature = "std")]
+pub mod inspect;
+#[cfg(feature = "std")]
+pub mod iwad;
+pub mod map;
+#[cfg(feature = "std")]
+pub mod pk3;
+#[cfg(feature = "std")]
+pub mod replace;
+pub mod source;
+#[cfg(feature = "std")]
+pub mod watch;
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Reads from the WADs so far, for the disk icon.
+static READS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a read from a WAD: opening one, or a lump decoded because it wasn't cached yet.
+/// WADs are held in memory whole, so a cache miss is what vanilla's `W_ReadLump` was.
+pub fn note_read() {
+    READS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// How many reads `note_read` has counted. Only changes matter; it wraps.
+pub fn reads() -> usize {
+    READS.load(Ordering::Relaxed)
+}
+
+/// Decodes an 8 byte, NUL padded lump or texture name. Names are case insensitive, so they
+/// are normalized to upper case.
+pub fn lump_name(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_ascii_uppercase()
+}
+
+/// The lump a file in a folder or archive stands for: its name without the folders or the
+/// extension, if that fits in 8 characters. A `^` stands for the `\` some sprite names have,
+/// as in ZDoom, since file names can't have one.
+pub fn file_lump_name(path: &str) -> Option<String> {
+    let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let stem = file.split_once('.').map_or(file, |(stem, _)| stem);
+    if !(1..=8).contains(&stem.len()) {
+        return None;
+    }
+    Some(lump_name(stem.replace('^', "\\").as_bytes()))
+}
@@ -0,0 +1,340 @@
+//! PK3 archives: ZIP files laid out in folders, loaded as if they were WADs.
+//!
+//! Each file in the archive becomes a lump named after it, and its folder says what kind of
+//! lump it is, the way marker lumps do in a WAD. Files in `sprites/`, `flats/` and `patches/`
+//! are put between `SS_START`/`SS_END`, `FF_START`/`FF_END` and `PP_START`/`PP_END`, the
+//! markers PWADs use to add to those lists. A WAD in `maps/` holds one map: its lumps are
+//! added under a marker named after the file, so `maps/MAP01.wad` is `MAP01` whatever its
+//! own marker says. Every other file, at the top or in `graphics/`, `sounds/`, `music/` and
+//! the rest, is a plain lump. Files whose names don't fit in 8 characters are left out, since
+//! no lookup could find them.
+//!
+//! The result is one `WadFile` taking its place in the load order like any PWAD, so a PK3
+//! loaded after a WAD replaces its lumps and one loaded before is replaced by it. PNG files
+//! are lumps too, for the namespaces, and also `Replacements` so they are drawn as images.
+
+use super::file::{Lump, WadFile, WadKind, Wads};
+use super::file_lump_name;
+use super::replace::Replacements;
+use crate::util::inflate::inflate;
+use crate::util::png::crc32;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// The folders that are namespaces, with the markers around their lumps.
+const NAMESPACES: [(&str, &str, &str); 3] = [
+    ("sprites", "SS_START", "SS_END"),
+    ("flats", "FF_START", "FF_END"),
+    ("patches", "PP_START", "PP_END"),
+];
+
+/// An archive's lumps and the PNG replacements among them.
+#[derive(Clone, Debug)]
+pub struct Pk3 {
+    pub file: WadFile,
+    pub replacements: Replacements,
+}
+
+impl Pk3 {
+    /// Reads a ZIP archive held in memory. Returns `None` if its directory is broken, an
+    /// entry is compressed in a way other than deflate, or a file's checksum is wrong.
+    pub fn parse(path: &Path, bytes: &[u8]) -> Option<Self> {
+        Some(Self::from_files(path, unzip(bytes)?))
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        super::note_read();
+        Self::parse(path, &bytes).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a valid PK3 or ZIP file", path.display()),
+            )
+        })
+    }
+
+    /// Lays out `files`, paths relative to the top of the archive with `/` between folders,
+    /// as lumps.
+    pub fn from_files(path: &Path, mut files: Vec<(String, Vec<u8>)>) -> Self {
+        // Archives list files in whatever order they were added; sorting gives the same lumps
+        // every time.
+        files.sort_by_key(|(name, _)| name.to_ascii_lowercase());
+        let mut global = Vec::new();
+        let mut namespaces: [Vec<Lump>; 3] = Default::default();
+        let mut maps = Vec::new();
+        let mut replacements = Replacements::new();
+        for (name, data) in files {
+            let Some(lump) = file_lump_name(&name) else {
+                continue;
+            };
+            let folder = name
+                .rsplit_once('/')
+                .map(|(folder, _)| folder.to_ascii_lowercase());
+            if folder.as_deref() == Some("maps") && data.starts_with(b"PWAD") {
+                if let Some(wad) = WadFile::parse(path, &data) {
+                    maps.extend(map_lumps(&lump, wad));
+                }
+                continue;
+            }
+            replacements.add(&name, data.clone());
+            let lump = Lump { name: lump, data };
+            match NAMESPACES
+                .iter()
+                .position(|(namespace, ..)| folder.as_deref() == Some(*namespace))
+            {
+                Some(namespace) => namespaces[namespace].push(lump),
+                None => global.push(lump),
+            }
+        }
+
+        let mut lumps = global;
+        for ((_, start, end), namespace) in NAMESPACES.iter().zip(namespaces) {
+            if !namespace.is_empty() {
+                lumps.push(marker(start));
+                lumps.extend(namespace);
+                lumps.push(marker(end));
+            }
+        }
+        lumps.extend(maps);
+        Self {
+            file: WadFile {
+                path: path.to_path_buf(),
+                kind: WadKind::Pwad,
+                lumps,
+            },
+            replacements,
+        }
+    }
+
+    /// Adds the archive to `wads`, after everything loaded so far.
+    pub fn mount(self, wads: &mut Wads) {
+        wads.add(self.file);
+        // Added after the archive's own lumps, so its PNGs are drawn as images.
+        wads.add_replacements(self.replacements);
+    }
+}
+
+fn marker(name: &str) -> Lump {
+    Lump {
+        name: name.to_string(),
+        data: Vec::new(),
+    }
+}
+
+/// A map WAD's lumps under a marker called `name`.
+fn map_lumps(name: &str, wad: WadFile) -> Vec<Lump> {
+    let mut lumps = wad.lumps.into_iter().skip(1).collect::<Vec<_>>();
+    lumps.insert(0, marker(name));
+    lumps
+}
+
+/// Every file in a ZIP archive, by path, uncompressed. Folders are left out.
+fn unzip(bytes: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let u16_at = |at: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+    };
+    // The end record is last, followed by a comment of up to 64K.
+    let end = (0..=bytes.len().checked_sub(22)?)
+        .rev()
+        .take(0x10000 + 22)
+        .find(|&at| u32_at(at) == Some(END_OF_DIRECTORY))?;
+    let count = u16_at(end + 10)? as usize;
+    let mut entry = u32_at(end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(count);
+    for _ in 0..count {
+        if u32_at(entry)? != DIRECTORY_ENTRY {
+            return None;
+        }
+        let method = u16_at(entry + 10)?;
+        let crc = u32_at(entry + 16)?;
+        let compressed = u32_at(entry + 20)? as usize;
+        let size = u32_at(entry + 24)? as usize;
+        let name_len = u16_at(entry + 28)? as usize;
+        let extra_len = u16_at(entry + 30)? as usize;
+        let comment_len = u16_at(entry + 32)? as usize;
+        let local = u32_at(entry + 42)? as usize;
+        let name = bytes.get(entry + 46..entry + 46 + name_len)?;
+        let name = String::from_utf8_lossy(name).replace('\\', "/");
+        entry += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+
+        if u32_at(local)? != LOCAL_HEADER {
+            return None;
+        }
+        let start = local + 30 + u16_at(local + 26)? as usize + u16_at(local + 28)? as usize;
+        let raw = bytes.get(start..start + compressed)?;
+        let data = match method {
+            STORED => raw.to_vec(),
+            DEFLATED => inflate(raw)?,
+            _ => return None,
+        };
+        if data.len() != size || crc32(&data) != crc {
+            return None;
+        }
+        files.push((name, data));
+    }
+    Some(files)
+}
+
+/// Whether `path` names an archive rather than a WAD, by its extension.
+pub fn is_pk3(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("pk3") || extension.eq_ignore_ascii_case("zip")
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::util::png::encode_rgb;
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::replace::Graphic;
+    use crate::wad::source::LumpSource;
+
+    /// A file for `build_zip`: its name, its data, and the data deflated if it is stored
+    /// compressed.
+    pub(crate) type ZipEntry<'a> = (&'a str, &'a [u8], Option<&'a [u8]>);
+
+    /// Builds a ZIP archive in memory: local headers and data, then the directory. Entries
+    /// given as `Some(deflated)` are stored compressed.
+    pub(crate) fn build_zip(files: &[ZipEntry]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for (name, data, deflated) in files {
+            let (method, stored) = match deflated {
+                Some(deflated) => (DEFLATED, *deflated),
+                None => (STORED, *data),
+            };
+            let mut common = Vec::new();
+            common.extend([20, 0, 0, 0]); // version needed, flags
+            common.extend(method.to_le_bytes());
+            common.extend([0; 4]); // time and date
+            common.extend(crc32(data).to_le_bytes());
+            common.extend((stored.len() as u32).to_le_bytes());
+            common.extend((data.len() as u32).to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            common.extend([0, 0]); // extra length
+
+            directory.extend(DIRECTORY_ENTRY.to_le_bytes());
+            directory.extend([20, 0]); // version made by
+            directory.extend(&common);
+            directory.extend([0; 10]); // comment length, disk, attributes
+            directory.extend((zip.len() as u32).to_le_bytes());
+            directory.extend(name.as_bytes());
+
+            zip.extend(LOCAL_HEADER.to_le_bytes());
+            zip.extend(&common);
+            zip.extend(name.as_bytes());
+            zip.extend(stored);
+        }
+        let offset = zip.len() as u32;
+        zip.extend(&directory);
+        zip.extend(END_OF_DIRECTORY.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(offset.to_le_bytes());
+        zip.extend([0, 0]);
+        zip
+    }
+
+    #[test]
+    fn folders_become_namespaces() {
+        let map = build_wad(b"PWAD", &[("MAP09", &[]), ("THINGS", &[1, 2])]);
+        let text = [b"ab".repeat(100), b"c".to_vec()].concat();
+        let zip = build_zip(&[
+            ("sprites/TROOA1.lmp", b"s", None),
+            ("flats/FLOOR0_1.lmp", b"f", None),
+            (
+                "DEHACKED.txt",
+                &text,
+                Some(&[75, 76, 74, 28, 22, 48, 25, 0]),
+            ),
+            ("maps/MAP01.wad", &map, None),
+            ("graphics/", b"", None),
+            ("graphics/TITLEPIC.lmp", b"t", None),
+            ("music/way_too_long_name.ogg", b"m", None),
+        ]);
+        let pk3 = Pk3::parse(Path::new("mod.pk3"), &zip).unwrap();
+        assert_eq!(pk3.file.kind, WadKind::Pwad);
+        let names: Vec<&str> = pk3
+            .file
+            .lumps
+            .iter()
+            .map(|lump| lump.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "DEHACKED", "TITLEPIC", "SS_START", "TROOA1", "SS_END", "FF_START", "FLOOR0_1",
+                "FF_END", "MAP01", "THINGS"
+            ]
+        );
+        assert_eq!(pk3.file.lumps[0].data, text);
+        assert!(pk3.replacements.is_empty());
+    }
+
+    #[test]
+    fn archives_take_their_place_in_the_load_order() {
+        let iwad = build_wad(
+            b"IWAD",
+            &[
+                ("TITLEPIC", b"a"),
+                ("M_PAUSE", b"a"),
+                ("MAP01", &[]),
+                ("THINGS", &[0]),
+            ],
+        );
+        let map = build_wad(b"PWAD", &[("MAP01", &[]), ("THINGS", &[7])]);
+        let png = encode_rgb(1, 1, &[0]);
+        let zip = build_zip(&[
+            ("graphics/TITLEPIC.png", &png, None),
+            ("graphics/M_PAUSE.lmp", b"b", None),
+            ("maps/map01.wad", &map, None),
+        ]);
+        let mut wads = Wads::new();
+        wads.add(WadFile::parse(Path::new("doom2.wad"), &iwad).unwrap());
+        Pk3::parse(Path::new("mod.pk3"), &zip)
+            .unwrap()
+            .mount(&mut wads);
+
+        assert_eq!(wads.lump_data("M_PAUSE"), Some(&b"b"[..]));
+        assert_eq!(wads.map_lump("MAP01", "THINGS"), Some(&[7u8][..]));
+        assert!(matches!(
+            wads.graphic("TITLEPIC"),
+            Some(Graphic::Png { lump: Some(lump), .. }) if lump == png
+        ));
+    }
+
+    #[test]
+    fn broken_archives_are_rejected() {
+        let zip = build_zip(&[("A.lmp", b"abc", None)]);
+        assert!(is_pk3(Path::new("mods/Mod.PK3")) && !is_pk3(Path::new("mod.wad")));
+        assert!(Pk3::parse(Path::new("x"), &zip).is_some());
+
+        let mut corrupt = zip.clone();
+        corrupt[30 + 5] = b'x';
+        assert!(
+            Pk3::parse(Path::new("x"), &corrupt).is_none(),
+            "bad checksum"
+        );
+        assert!(Pk3::parse(Path::new("x"), &zip[..zip.len() - 1]).is_none());
+        assert!(Pk3::parse(Path::new("x"), b"PWAD").is_none());
+    }
+}
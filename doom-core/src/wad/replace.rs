@@ -0,0 +1,81 @@
+//! PNG images standing in for graphics lumps, for high resolution and true color packs.
+//!
+//! A replacement is found by lump name, from the file name without its extension, whatever
+//! folder it is in: `graphics/TITLEPIC.png` and `sprites/TROOA1.png` replace `TITLEPIC` and
+//! `TROOA1`. The images are kept as PNG and only decoded when the graphic is loaded, since a
+//! pack can have thousands of them and a level uses a few hundred.
+//!
+//! `Wads::graphic` decides between a replacement and the lump of the same name by load
+//! order, so a PWAD's own graphics beat a pack loaded before it.
+
+use super::file_lump_name;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replacements {
+    pngs: HashMap<String, Vec<u8>>,
+}
+
+impl Replacements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file `file_name` if it is a PNG named after a lump, returning whether it was.
+    /// A later file of the same name replaces an earlier one.
+    pub fn add(&mut self, file_name: &str, bytes: Vec<u8>) -> bool {
+        let png = file_name.to_ascii_lowercase().ends_with(".png") && bytes.starts_with(b"\x89PNG");
+        match file_lump_name(file_name).filter(|_| png) {
+            Some(name) => {
+                self.pngs.insert(name, bytes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The PNG that replaces `name`.
+    pub fn png(&self, name: &str) -> Option<&[u8]> {
+        self.pngs.get(&name.to_ascii_uppercase()).map(Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pngs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pngs.is_empty()
+    }
+}
+
+/// Where a graphic comes from: a lump in the WAD format, or a PNG replacing it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Graphic<'a> {
+    Lump(&'a [u8]),
+    /// `lump` is the graphic being replaced, if the WADs have it, which gives the size the
+    /// image is drawn at.
+    Png {
+        png: &'a [u8],
+        lump: Option<&'a [u8]>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::png::encode_rgb;
+
+    #[test]
+    fn png_files_are_named_after_lumps() {
+        let png = encode_rgb(1, 1, &[0]);
+        let mut replacements = Replacements::new();
+        assert!(replacements.add("graphics/titlepic.png", png.clone()));
+        assert!(replacements.add("VILE^1.PNG", png.clone()));
+        assert!(!replacements.add("TOOLONGNAME.png", png.clone()));
+        assert!(!replacements.add("STBAR.bmp", png.clone()));
+        assert!(!replacements.add("STBAR.png", b"not a png".to_vec()));
+        assert_eq!(replacements.len(), 2);
+        assert_eq!(replacements.png("TitlePic"), Some(png.as_slice()));
+        assert!(replacements.png("VILE\\1").is_some());
+    }
+}
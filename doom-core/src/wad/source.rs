@@ -0,0 +1,145 @@
+//! Where lumps come from, without assuming a filesystem.
+//!
+//! On a desktop the lumps are read from WAD files into a `Wads`. On a microcontroller the IWAD
+//! is more likely to sit in flash, mapped into memory, where copying it into RAM isn't an
+//! option. `WadImage` reads the directory of such a WAD and hands out slices of it in place.
+//! Code that only needs lump data takes a `LumpSource`, so it works with either.
+
+use super::lump_name;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// THINGS to BLOCKMAP, the lumps that follow a map marker.
+pub const MAP_LUMPS: usize = 10;
+
+pub trait LumpSource {
+    /// The data of the lump that wins for `name`: the last one loaded.
+    fn lump_data(&self, name: &str) -> Option<&[u8]>;
+
+    /// The lump called `lump` among the ones after the marker of `map` (`E1M1`, `MAP01`),
+    /// from the last WAD that has the map. Map lumps have to be found by position, since
+    /// every map has lumps of the same names.
+    fn map_lump(&self, map: &str, lump: &str) -> Option<&[u8]>;
+}
+
+/// A whole WAD in memory, read in place. Only the directory is decoded.
+#[derive(Clone, Debug)]
+pub struct WadImage<'a> {
+    bytes: &'a [u8],
+    lumps: Vec<(String, Range<usize>)>,
+}
+
+impl<'a> WadImage<'a> {
+    /// Reads the directory. Returns `None` if the header is not a WAD or the directory
+    /// points outside of `bytes`.
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let read = |from: &[u8], at: usize| -> Option<usize> {
+            let value = i32::from_le_bytes(from.get(at..at + 4)?.try_into().ok()?);
+            usize::try_from(value).ok()
+        };
+        if !matches!(bytes.get(..4)?, b"IWAD" | b"PWAD") {
+            return None;
+        }
+        let (count, directory) = (read(bytes, 4)?, read(bytes, 8)?);
+        // A count the directory can't hold is rejected before it sizes anything.
+        let end = count.checked_mul(16)?.checked_add(directory)?;
+        let entries = bytes.get(directory..end)?;
+        let mut lumps = Vec::with_capacity(count);
+        for entry in entries.chunks_exact(16) {
+            let (offset, size) = (read(entry, 0)?, read(entry, 4)?);
+            let data = offset..offset.checked_add(size)?;
+            bytes.get(data.clone())?;
+            lumps.push((lump_name(&entry[8..16]), data));
+        }
+        Some(Self { bytes, lumps })
+    }
+
+    pub fn len(&self) -> usize {
+        self.lumps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lumps.is_empty()
+    }
+
+    /// `IWAD` or `PWAD`.
+    pub fn magic(&self) -> &'a [u8] {
+        &self.bytes[..4]
+    }
+
+    /// Every lump in directory order: its name, where its data starts in the file, and the
+    /// data.
+    pub fn lumps(&self) -> impl Iterator<Item = (&str, usize, &'a [u8])> + '_ {
+        let bytes = self.bytes;
+        self.lumps
+            .iter()
+            .map(move |(name, range)| (name.as_str(), range.start, &bytes[range.clone()]))
+    }
+
+    fn data(&self, index: usize) -> &'a [u8] {
+        &self.bytes[self.lumps[index].1.clone()]
+    }
+}
+
+impl LumpSource for WadImage<'_> {
+    fn lump_data(&self, name: &str) -> Option<&[u8]> {
+        let name = name.to_ascii_uppercase();
+        let index = self.lumps.iter().rposition(|(lump, _)| *lump == name)?;
+        Some(self.data(index))
+    }
+
+    fn map_lump(&self, map: &str, lump: &str) -> Option<&[u8]> {
+        let map = map.to_ascii_uppercase();
+        let marker = self.lumps.iter().rposition(|(name, _)| *name == map)?;
+        let index = (marker + 1..self.lumps.len().min(marker + 1 + MAP_LUMPS))
+            .find(|&index| self.lumps[index].0 == lump)?;
+        Some(self.data(index))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+
+    #[test]
+    fn lumps_are_read_in_place() {
+        let bytes = build_wad(
+            b"IWAD",
+            &[
+                ("things", &[1]),
+                ("E1M1", &[]),
+                ("THINGS", &[2, 3]),
+                ("playpal", &[4]),
+                ("PLAYPAL", &[5]),
+            ],
+        );
+        let image = WadImage::parse(&bytes).unwrap();
+        assert_eq!(image.len(), 5);
+        assert_eq!(image.magic(), b"IWAD");
+        let (name, offset, data) = image.lumps().nth(2).unwrap();
+        assert_eq!(
+            (name, &bytes[offset..offset + 2], data),
+            ("THINGS", &[2u8, 3][..], &[2u8, 3][..])
+        );
+        assert_eq!(image.lump_data("PlayPal"), Some(&[5u8][..]));
+        assert_eq!(image.map_lump("e1m1", "THINGS"), Some(&[2u8, 3][..]));
+        assert_eq!(image.map_lump("E1M2", "THINGS"), None);
+        assert_eq!(image.lump_data("COLORMAP"), None);
+
+        let mut truncated = bytes.clone();
+        truncated.truncate(bytes.len() - 4);
+        assert!(WadImage::parse(&truncated).is_none());
+        assert!(WadImage::parse(b"WAD2\0\0\0\0\0\0\0\0").is_none());
+
+        let mut huge = bytes.clone();
+        huge[4..8].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(WadImage::parse(&huge).is_none());
+        let mut wrapping = bytes.clone();
+        let entry = wrapping.len() - 16;
+        wrapping[entry..entry + 8]
+            .copy_from_slice(&[0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff, 0x7f]);
+        assert!(WadImage::parse(&wrapping).is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! Development mode: the loaded WADs, archives and folders watched for changes while the
+//! game runs, so an artist sees a saved texture or sound without restarting.
+//!
+//! The loop calls `Watcher::tic` between tics, the one point where nothing holds on to lump
+//! data. Every `watch_interval` tics it looks at each file's modification time and size, and
+//! reads whatever changed again into its old place in the load order. It returns the names of
+//! the lumps whose data is now different, added or gone, which is what the caches need to
+//! forget: decoded patches, flats and sounds, and the engine's own graphics. Lumps nothing
+//! had decoded yet are simply read fresh the next time.
+//!
+//! A file caught half written fails to load and is tried again at the next check, so a save
+//! that takes a while doesn't need a restart either.
+
+use super::file::{WadFile, Wads};
+use super::folder::Folder;
+use super::pk3::{self, Pk3};
+use super::replace::Replacements;
+use crate::engine::config::{Config, Configurable, Section};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchOptions {
+    pub enabled: bool,
+    /// Tics between checks; looking at every file every tic would cost more than it's worth.
+    pub interval: u32,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 35,
+        }
+    }
+}
+
+impl Configurable for WatchOptions {
+    fn register(config: &mut Config) {
+        config.register_bool("watch_assets", false, Section::Extended);
+        config.register_int("watch_interval", 35, Section::Extended);
+    }
+
+    fn from_config(config: &Config) -> Self {
+        Self {
+            enabled: config.get_bool("watch_assets"),
+            interval: config.get_int("watch_interval").max(1) as u32,
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("watch_assets", self.enabled);
+        config.set_int("watch_interval", self.interval as i32);
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Watched {
+    Folder(Folder),
+    /// A WAD or archive, with its modification time and size when it was read.
+    File {
+        path: PathBuf,
+        stamp: Option<(Option<SystemTime>, u64)>,
+    },
+}
+
+/// The mounted resources being watched. A default one watches nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Watcher {
+    watched: Vec<Watched>,
+    interval: u32,
+    countdown: u32,
+}
+
+impl Watcher {
+    /// Watches `folders` and the WADs and archives at `files`, if the options turn it on.
+    pub fn new(options: &WatchOptions, folders: Vec<Folder>, files: &[PathBuf]) -> Self {
+        if !options.enabled {
+            return Self::default();
+        }
+        let files = files.iter().map(|path| Watched::File {
+            path: path.clone(),
+            stamp: stamp(path),
+        });
+        Self {
+            watched: folders
+                .into_iter()
+                .map(Watched::Folder)
+                .chain(files)
+                .collect(),
+            interval: options.interval.max(1),
+            countdown: options.interval.max(1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watched.is_empty()
+    }
+
+    /// Counts a tic, checking for changes every `interval` of them. Returns the names of
+    /// the lumps that changed.
+    pub fn tic(&mut self, wads: &mut Wads) -> io::Result<Vec<String>> {
+        if self.watched.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return Ok(Vec::new());
+        }
+        self.countdown = self.interval;
+        self.check(wads)
+    }
+
+    /// Reloads whatever changed since it was last read, returning the names of the lumps
+    /// that are different now. Stops at the first resource that can't be read.
+    pub fn check(&mut self, wads: &mut Wads) -> io::Result<Vec<String>> {
+        let mut changed = Vec::new();
+        for watched in &mut self.watched {
+            let (file, replacements) = match watched {
+                Watched::Folder(folder) => {
+                    if !folder.changed()? {
+                        continue;
+                    }
+                    let pk3 = folder.load()?;
+                    (pk3.file, pk3.replacements)
+                }
+                Watched::File { path, stamp: seen } => {
+                    let now = stamp(path);
+                    if now == *seen {
+                        continue;
+                    }
+                    let loaded = load(path)?;
+                    *seen = now;
+                    loaded
+                }
+            };
+            let new = file.clone();
+            if let Some(old) = wads.replace(file, replacements) {
+                changed.extend(changed_lumps(&old, &new));
+            }
+        }
+        changed.sort();
+        changed.dedup();
+        Ok(changed)
+    }
+}
+
+fn stamp(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+fn load(path: &Path) -> io::Result<(WadFile, Replacements)> {
+    match pk3::is_pk3(path) {
+        true => Pk3::open(path).map(|pk3| (pk3.file, pk3.replacements)),
+        false => WadFile::open(path).map(|file| (file, Replacements::new())),
+    }
+}
+
+/// The names whose winning lump in `new` differs from the one in `old`, or that only one of
+/// them has.
+fn changed_lumps(old: &WadFile, new: &WadFile) -> Vec<String> {
+    fn winners(file: &WadFile) -> HashMap<&str, &[u8]> {
+        file.lumps
+            .iter()
+            .map(|lump| (lump.name.as_str(), lump.data.as_slice()))
+            .collect()
+    }
+    let (old, new) = (winners(old), winners(new));
+    let mut changed: Vec<String> = old
+        .iter()
+        .filter(|(name, data)| new.get(*name) != Some(*data))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    changed.extend(
+        new.keys()
+            .filter(|name| !old.contains_key(*name))
+            .map(|name| name.to_string()),
+    );
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad::file::tests::build_wad;
+    use crate::wad::source::LumpSource;
+
+    fn options() -> WatchOptions {
+        WatchOptions {
+            enabled: true,
+            interval: 3,
+        }
+    }
+
+    #[test]
+    fn changed_wads_are_reloaded_every_interval() {
+        let dir = std::env::temp_dir().join(format!("doom-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("art.wad");
+        let write = |lumps: &[(&str, &[u8])]| fs::write(&path, build_wad(b"PWAD", lumps));
+        write(&[("WALL", b"a"), ("DSPISTOL", b"s"), ("OLD", b"o")]).unwrap();
+        let mut wads = Wads::new();
+        wads.add(WadFile::open(&path).unwrap());
+        let mut watcher = Watcher::new(&options(), Vec::new(), std::slice::from_ref(&path));
+
+        write(&[("WALL", b"bb"), ("DSPISTOL", b"s"), ("NEW", b"n")]).unwrap();
+        let checks: Vec<_> = (0..3).map(|_| watcher.tic(&mut wads).unwrap()).collect();
+        let again = watcher.tic(&mut wads);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(checks[0].is_empty() && checks[1].is_empty());
+        assert_eq!(checks[2], ["NEW", "OLD", "WALL"]);
+        assert_eq!(wads.lump_data("WALL"), Some(&b"bb"[..]));
+        assert!(again.unwrap().is_empty());
+    }
+
+    #[test]
+    fn nothing_is_watched_unless_turned_on() {
+        let path = std::env::temp_dir().join(format!("doom-watch-{}.wad", std::process::id()));
+        let off = WatchOptions::default();
+        assert!(Watcher::new(&off, Vec::new(), std::slice::from_ref(&path)).is_empty());
+        let mut watcher = Watcher::new(&options(), Vec::new(), std::slice::from_ref(&path));
+        assert!(!watcher.is_empty());
+        assert!(watcher.check(&mut Wads::new()).unwrap().is_empty());
+
+        // A file that can't be read is an error, and is tried again next time.
+        fs::write(&path, b"half written").unwrap();
+        let first = watcher.check(&mut Wads::new());
+        let second = watcher.check(&mut Wads::new());
+        fs::remove_file(&path).unwrap();
+        assert!(first.is_err() && second.is_err());
+    }
+
+    #[test]
+    fn only_lumps_that_differ_count_as_changed() {
+        let file = |lumps: &[(&str, &[u8])]| {
+            WadFile::parse(Path::new("x.wad"), &build_wad(b"PWAD", lumps)).unwrap()
+        };
+        let old = file(&[("A", b"1"), ("B", b"2"), ("B", b"3")]);
+        let new = file(&[("A", b"1"), ("B", b"3"), ("C", b"")]);
+        let mut changed = changed_lumps(&old, &new);
+        changed.sort();
+        assert_eq!(changed, ["C"], "B's winning lump is the same");
+    }
+}
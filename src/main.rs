@@ -1,2 +1,804 @@
-pub mod engine;
-fn main() {}
+pub mod platform;
+
+use doom_core::audio::captions::CaptionOptions;
+use doom_core::audio::channels::MixerPolicy;
+use doom_core::audio::jukebox::{self, Jukebox, MusicFormat, Reply};
+use doom_core::audio::mus;
+use doom_core::engine::args::{self, Args, Command, WadQuery};
+use doom_core::engine::audit;
+use doom_core::engine::bench;
+use doom_core::engine::config::{Config, Configurable};
+use doom_core::engine::control;
+use doom_core::engine::controls::Controls;
+use doom_core::engine::crash::{self, CrashOptions};
+use doom_core::engine::hotkeys::Hotkeys;
+use doom_core::engine::log::{self, Level, LogOptions, LogSpec};
+use doom_core::engine::menu::MenuOptions;
+use doom_core::engine::pacing::{Interpolation, PacingOptions};
+use doom_core::engine::profiles::{ProfileOptions, Profiles};
+use doom_core::engine::session::{self, GameSetup};
+use doom_core::engine::speed::GameSpeed;
+use doom_core::engine::title::{self, Activity, WindowTitle};
+use doom_core::game::chat::ChatMacros;
+use doom_core::game::deathmatch::{DeathmatchRules, ExitRule};
+use doom_core::game::dehacked::Dehacked;
+use doom_core::game::demo::{
+    self, Demo, DemoHeader, DemoPlayback, DemoRecorder, JoinOptions, TicSource,
+};
+use doom_core::game::dropin::DropOptions;
+use doom_core::game::explored::RevealMode;
+use doom_core::game::fast::{self, FastMonsters};
+use doom_core::game::info::GameInfo;
+use doom_core::game::levelstat;
+use doom_core::game::lifetime::{self, LifetimeStats};
+use doom_core::game::limits::{LimitOverrides, OverflowOptions};
+use doom_core::game::pause::PauseOptions;
+use doom_core::game::player::PistolStart;
+use doom_core::game::precache::PrecacheOptions;
+use doom_core::game::quicksave::QuickSaveOptions;
+use doom_core::game::rewind::RewindOptions;
+#[cfg(feature = "scripting")]
+use doom_core::game::script::{ScriptOptions, Scripts};
+use doom_core::game::spawn::SpawnFilter;
+use doom_core::game::strict::{self, Feature, StrictMode};
+use doom_core::game::strings::Strings;
+use doom_core::game::umapinfo::UMapInfo;
+use doom_core::game::{GameMode, Skill, TICRATE};
+use doom_core::renderer::colorblind::ColorScheme;
+use doom_core::renderer::crosshair::Crosshair;
+use doom_core::renderer::disk::DiskIconOptions;
+use doom_core::renderer::endoom::{self, Endoom, EndoomOptions};
+use doom_core::renderer::flash::FlashOptions;
+use doom_core::renderer::fov::FieldOfView;
+use doom_core::renderer::framebuffer::Palette;
+use doom_core::renderer::gamma::GammaOptions;
+use doom_core::renderer::hud::ExtendedHud;
+use doom_core::renderer::lighting::LightOptions;
+use doom_core::renderer::mapview::MapView;
+use doom_core::renderer::perf::{PerfHud, PerfMode};
+use doom_core::renderer::present::PresentOptions;
+use doom_core::renderer::screenshot::ScreenshotConfig;
+use doom_core::renderer::spectre::FuzzStyle;
+use doom_core::renderer::tranmap::{self, TranMap};
+use doom_core::renderer::translation::{self, Translations};
+use doom_core::renderer::translucency::{self, TranslucencyOptions};
+use doom_core::renderer::view::ViewSize;
+use doom_core::renderer::RendererKind;
+use doom_core::wad::export;
+use doom_core::wad::file::{WadFile, Wads};
+use doom_core::wad::folder::{Folder, FolderOptions};
+use doom_core::wad::inspect::{self, Inventory};
+use doom_core::wad::iwad::{self, FoundIwad};
+use doom_core::wad::map::MapData;
+use doom_core::wad::pk3::{self, Pk3};
+use doom_core::wad::source::WadImage;
+use doom_core::wad::watch::{WatchOptions, Watcher};
+use doom_core::{error, info, warn, Engine};
+use platform::display::DisplayConfig;
+use platform::null::{NullAudio, NullVideo};
+use platform::{Audio, Pacing, SystemClock, Video, Window};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("doom-rs: {error}");
+            std::process::exit(1);
+        }
+    };
+    if args.help {
+        print!("{}", args::usage());
+        return;
+    }
+    if let Some(Command::WadInfo { wad, query }) = &args.command {
+        if let Err(error) = wadinfo(wad, query) {
+            eprintln!("doom-rs: {error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Extract { wad, to, lumps }) = &args.command {
+        if let Err(error) = extract(wad, to, lumps, args.iwad.as_deref()) {
+            eprintln!("doom-rs: {error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // The renderer is chosen up front, but only the software one exists so far.
+    if args.renderer != RendererKind::Software {
+        eprintln!("doom-rs: -renderer: only the software renderer is built so far");
+        std::process::exit(1);
+    }
+
+    let mut config = Config::new();
+    PresentOptions::register(&mut config);
+    DisplayConfig::register(&mut config);
+    ScreenshotConfig::register(&mut config);
+    OverflowOptions::register(&mut config);
+    LimitOverrides::register(&mut config);
+    Hotkeys::register(&mut config);
+    Controls::register(&mut config);
+    JoinOptions::register(&mut config);
+    PrecacheOptions::register(&mut config);
+    GammaOptions::register(&mut config);
+    LightOptions::register(&mut config);
+    FieldOfView::register(&mut config);
+    ViewSize::register(&mut config);
+    Crosshair::register(&mut config);
+    ExtendedHud::register(&mut config);
+    RevealMode::register(&mut config);
+    PerfHud::register(&mut config);
+    StrictMode::register(&mut config);
+    RewindOptions::register(&mut config);
+    QuickSaveOptions::register(&mut config);
+    PistolStart::register(&mut config);
+    SpawnFilter::register(&mut config);
+    DeathmatchRules::register(&mut config);
+    DropOptions::register(&mut config);
+    ChatMacros::register(&mut config);
+    LogOptions::register(&mut config);
+    CrashOptions::register(&mut config);
+    GameSpeed::register(&mut config);
+    PacingOptions::register(&mut config);
+    PauseOptions::register(&mut config);
+    MixerPolicy::register(&mut config);
+    CaptionOptions::register(&mut config);
+    FuzzStyle::register(&mut config);
+    FlashOptions::register(&mut config);
+    ColorScheme::register(&mut config);
+    MenuOptions::register(&mut config);
+    ProfileOptions::register(&mut config);
+    TranslucencyOptions::register(&mut config);
+    EndoomOptions::register(&mut config);
+    DiskIconOptions::register(&mut config);
+    FolderOptions::register(&mut config);
+    WatchOptions::register(&mut config);
+    #[cfg(feature = "scripting")]
+    ScriptOptions::register(&mut config);
+    let config_path = args
+        .config
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("default.cfg"));
+    // The main config names the profile to use, which is read over it.
+    let profiles = Profiles::new(&config_path);
+    let loaded = config
+        .load(&config_path)
+        .map_err(|error| (config_path.clone(), error))
+        .and_then(|()| {
+            let profile = args
+                .profile
+                .clone()
+                .unwrap_or_else(|| ProfileOptions::from_config(&config).name);
+            profiles
+                .open(&mut config, &profile)
+                .map_err(|error| (profiles.path(&profile), error))
+        });
+    log::set_spec(match &args.log {
+        Some(spec) => spec.parse::<LogSpec>().unwrap_or_else(|error| {
+            eprintln!("doom-rs: -log: {error}");
+            std::process::exit(1);
+        }),
+        None if args.debug => LogSpec {
+            default: Level::Debug,
+            targets: Vec::new(),
+        },
+        None => LogOptions::from_config(&config).spec,
+    });
+    if let Err((path, error)) = loaded {
+        warn!("config", "couldn't read {}: {error}", path.display());
+    }
+    crash::install(CrashOptions::from_config(&config));
+    if args.debug {
+        let mut perf = PerfHud::from_config(&config);
+        perf.mode = PerfMode::Full;
+        perf.to_config(&mut config);
+    }
+    let display = DisplayConfig::from_config(&config).overridden(args.fullscreen, args.resolution);
+
+    let mut strict_mode = StrictMode::from_config(&config);
+    strict_mode.enabled |= args.strict;
+    let limit_overrides = LimitOverrides::from_config(&config);
+    let overflows = OverflowOptions::from_config(&config);
+    let game_speed = GameSpeed::from_config(&config);
+    let mut requested = strict::requested_by(&limit_overrides, &overflows);
+    if !game_speed.is_normal() {
+        requested.push(Feature::GameSpeed);
+    }
+    let fuzz_style = FuzzStyle::from_config(&config);
+    if fuzz_style != FuzzStyle::Vanilla {
+        requested.push(Feature::SpectreStyle);
+    }
+    let pacing = PacingOptions::from_config(&config);
+    if pacing.interpolate {
+        requested.push(Feature::Interpolation);
+    }
+    let rewind = RewindOptions::from_config(&config);
+    if rewind.depth > 0 {
+        requested.push(Feature::Rewind);
+    }
+    let blocked = strict_mode.blocked(&requested);
+    if let Some(notice) = strict_mode.notice(&blocked) {
+        info!("game", "{}", notice.to_ascii_lowercase());
+    }
+    let limit_overrides = strict_mode.limit_overrides(limit_overrides);
+    let overflows = strict_mode.overflows(overflows);
+    if args.record.is_some() && !game_speed.is_normal() {
+        warn!("demo", "game_speed is held at 100% while recording");
+    }
+    let game_speed = game_speed.held(&strict_mode, args.record.is_some());
+    let pacing = pacing.held(&strict_mode);
+    let rewind = rewind.held(&strict_mode);
+    let fov = FieldOfView::from_config(&config);
+    let complevel = args.complevel.unwrap_or_default();
+    if args.record.is_some() && fov.held(true, complevel) != fov {
+        warn!("demo", "fov is held at 90 while recording a vanilla demo");
+    }
+    let fov = fov.held(args.record.is_some(), complevel);
+    let demo = args.playdemo.is_some() || args.timedemo.is_some();
+    let fuzz_style = fuzz_style.held(&strict_mode, demo);
+    let mixer_policy = MixerPolicy::from_config(&config);
+    let pistol_start = PistolStart {
+        enabled: args.pistolstart || PistolStart::from_config(&config).enabled,
+    };
+
+    let mut precache = PrecacheOptions::from_config(&config);
+    precache.enabled &= !args.nodemoprecache;
+
+    let dirs = iwad::search_dirs(&|name| std::env::var(name).ok());
+    let iwad = match iwad::locate(args.iwad.as_deref(), &dirs, prompt_for_iwad) {
+        Ok(iwad) => iwad,
+        Err(error) => {
+            error!("wad", "{error}");
+            std::process::exit(1);
+        }
+    };
+    info!("main", "{}", log::banner(iwad.info.description));
+
+    info!("wad", "W_Init: Init WADfiles.");
+    let mut wads = Wads::new();
+    let mut folders = Vec::new();
+    for path in std::iter::once(&iwad.path).chain(&args.files) {
+        match add_resource(&mut wads, path) {
+            Ok(folder) => folders.extend(folder),
+            Err(error) => {
+                error!("wad", "couldn't load {}: {error}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    if FolderOptions::from_config(&config).hot_reload && !folders.is_empty() {
+        info!(
+            "wad",
+            "reloading {} folders when their files change",
+            folders.len()
+        );
+    }
+    let files: Vec<PathBuf> = std::iter::once(&iwad.path)
+        .chain(&args.files)
+        .filter(|path| !path.is_dir())
+        .cloned()
+        .collect();
+    let mut watcher = Watcher::new(&WatchOptions::from_config(&config), folders, &files);
+    if !watcher.is_empty() {
+        info!("wad", "watching the loaded files for changes");
+    }
+    let lumps: usize = wads.files().iter().map(|file| file.lumps.len()).sum();
+    info!("wad", "{lumps} lumps in {} files", wads.files().len());
+    crash::update(|snapshot| {
+        snapshot.wads = wads.files().iter().map(|file| file.path.clone()).collect();
+        snapshot.complevel = args.complevel.unwrap_or_default();
+    });
+
+    if let Some(Command::MapView { map, to, size }) = &args.command {
+        let Some(data) = MapData::load(&wads, map) else {
+            error!("wad", "no map {map} in the loaded WADs");
+            std::process::exit(1);
+        };
+        let palette = wads
+            .lump("PLAYPAL")
+            .and_then(|playpal| Palette::from_playpal(&playpal.data, 0))
+            .unwrap_or_default();
+        let view = MapView {
+            size: size.unwrap_or(MapView::default().size),
+            ..MapView::default()
+        };
+        let picture = match to.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => view.png(&data, &palette),
+            _ => view.svg(&data, &palette).into_bytes(),
+        };
+        if let Err(error) = std::fs::write(to, picture) {
+            error!("main", "couldn't write {}: {error}", to.display());
+            std::process::exit(1);
+        }
+        info!("main", "wrote {map} to {}", to.display());
+        return;
+    }
+
+    if let Some(Command::Audit { demo, configs }) = &args.command {
+        match run_audit(demo, configs, &wads) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(error) => {
+                error!("demo", "{error}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.jukebox {
+        play_jukebox(Jukebox::scan(&wads), &mut NullAudio::new());
+        return;
+    }
+
+    if let Some(Command::Bench { map }) = &args.command {
+        let Some(data) = MapData::load(&wads, map) else {
+            error!("wad", "no map {map} in the loaded WADs");
+            std::process::exit(1);
+        };
+        let path = bench::camera_path(&data);
+        let report = bench::run(map, &mut bench::headless_renderer(&data), &data, &path);
+        println!("{}", report.to_json());
+        return;
+    }
+
+    let mut info = GameInfo::default();
+    let mut dehacked = Dehacked::new(&info);
+    if let Err(error) = dehacked.load_startup_patches(&args.deh, &wads, args.nodeh, &mut info) {
+        error!("dehacked", "couldn't read DeHackEd patch: {error}");
+        std::process::exit(1);
+    }
+    for warning in &dehacked.warnings {
+        warn!("dehacked", "{warning}");
+    }
+
+    let mut strings = Strings::new();
+    if let Some(path) = &args.language {
+        match std::fs::read_to_string(path) {
+            Ok(pack) => {
+                for name in strings.load_language(&pack) {
+                    warn!("strings", "{}: unknown string '{name}'", path.display());
+                }
+            }
+            Err(error) => warn!("strings", "couldn't read {}: {error}", path.display()),
+        }
+    }
+    for warning in strings.apply_dehacked(&dehacked) {
+        warn!("strings", "{warning}");
+    }
+
+    let skill = args.skill.and_then(Skill::from_number).unwrap_or_default();
+    let mut fast_monsters = FastMonsters::new(&info);
+    fast_monsters.set(&mut info, fast::fast_monsters(args.fast, skill));
+    let translucency = TranslucencyOptions::from_config(&config);
+    translucency.apply(&mut info);
+
+    let playback = args.playdemo.as_deref().map(|name| {
+        let demo = demo::read(name, &wads)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| Demo::parse(&bytes).map_err(|error| error.to_string()))
+            .unwrap_or_else(|error| {
+                error!("demo", "couldn't play demo {name}: {error}");
+                std::process::exit(1);
+            });
+        let mut playback = DemoPlayback::new(demo);
+        if let Some(tic) = args.skiptic {
+            playback.skip_to(tic as usize);
+        }
+        playback
+    });
+    let spawn_filter = match &playback {
+        Some(playback) => SpawnFilter::for_playback(playback.demo.header.nomonsters),
+        None => {
+            let configured = SpawnFilter::from_config(&config);
+            SpawnFilter {
+                nomonsters: args.nomonsters,
+                coop_spawns: args.coopspawns || configured.coop_spawns,
+                ..configured
+            }
+        }
+    };
+    if args.record.is_some() && spawn_filter.changes_sync() {
+        warn!(
+            "demo",
+            "co-op spawns and spawn_filter aren't recorded; the demo will only play back with them"
+        );
+    }
+
+    let configured = DeathmatchRules::from_config(&config);
+    let deathmatch_rules = DeathmatchRules {
+        frag_limit: args
+            .fraglimit
+            .map_or(configured.frag_limit, |frags| frags as i32),
+        time_limit: args
+            .timer
+            .map_or(configured.time_limit, |minutes| minutes as i32),
+        spawn_protection: args
+            .spawnprotect
+            .map_or(configured.spawn_protection, |seconds| {
+                seconds as i32 * TICRATE
+            }),
+        exit: if args.exitkills {
+            ExitRule::Kill
+        } else {
+            configured.exit
+        },
+    };
+    if args.record.is_some() && args.deathmatch && deathmatch_rules.changes_sync() {
+        warn!(
+            "demo",
+            "deathmatch rules aren't recorded; the demo will only play back with the same ones"
+        );
+    }
+
+    let mut tic_source = match playback {
+        Some(playback) => TicSource::playback(
+            playback,
+            JoinOptions::from_config(&config),
+            args.record.is_some(),
+        ),
+        None => TicSource::local(args.record.as_ref().map(|_| {
+            DemoRecorder::new(DemoHeader {
+                skill,
+                deathmatch: args.deathmatch as u8,
+                respawn: args.respawn,
+                fast: args.fast,
+                nomonsters: args.nomonsters,
+                ..DemoHeader::default()
+            })
+        })),
+    };
+
+    #[cfg(feature = "scripting")]
+    let scripts = {
+        let options = ScriptOptions::from_config(&config);
+        let complevel = args.complevel.unwrap_or_default();
+        let allowed = Scripts::allowed(&options, args.record.is_some(), complevel);
+        let (scripts, errors) = Scripts::load(&options.folder, &wads, allowed);
+        for error in errors {
+            warn!("script", "{error}");
+        }
+        scripts
+    };
+
+    let map_info = match wads.lump("UMAPINFO") {
+        Some(lump) => {
+            UMapInfo::parse(&String::from_utf8_lossy(&lump.data)).unwrap_or_else(|error| {
+                warn!("umapinfo", "{error}");
+                UMapInfo::default()
+            })
+        }
+        None => UMapInfo::default(),
+    };
+
+    let mut translations = Translations::default();
+    if let Some(lump) = wads.lump(translation::COLORS_LUMP) {
+        if let Err(error) = translations.add_lump(&String::from_utf8_lossy(&lump.data)) {
+            warn!("renderer", "{error}");
+        }
+    }
+    let tranmap = load_tranmap(&wads, translucency.percent);
+
+    let mut engine = Engine::new(wads, config);
+    engine.set_info(info);
+    dehacked.apply_cheats(engine.cheats());
+    engine.set_fov(fov);
+    engine.set_tranmap(tranmap);
+    engine.set_translations(translations);
+    engine.set_fuzz_style(fuzz_style);
+    engine.set_rewind(rewind);
+    engine.set_map_info(map_info);
+    engine.set_mixer_policy(mixer_policy);
+    engine.set_precache(precache);
+    #[cfg(feature = "scripting")]
+    engine.set_scripts(scripts);
+    let lifetime_path = Path::new(lifetime::LIFETIME_FILE);
+    match LifetimeStats::load(lifetime_path) {
+        Ok(stats) => *engine.lifetime_stats().borrow_mut() = stats,
+        Err(error) => warn!(
+            "lifetime",
+            "couldn't read {}: {error}",
+            lifetime_path.display()
+        ),
+    }
+    if let Some(turbo) = args.turbo {
+        info!("game", "turbo scale: {turbo}%");
+        engine.set_turbo(turbo);
+    }
+    let mode = GameMode::identify(|name| engine.wads().lump(name).is_some());
+    let setup = GameSetup {
+        skill,
+        // There are no flags for joining a co-op game, so only deathmatch is a netgame.
+        netgame: args.deathmatch,
+        deathmatch: args.deathmatch,
+        spawn_filter,
+        pistol_start,
+        deathmatch_rules,
+        complevel,
+        demo_playback: demo,
+        limit_overrides,
+        overflows,
+    };
+    let map = session::start_map(mode, args.warp, args.episode);
+    if !engine.new_game(setup, &map) {
+        warn!("game", "couldn't load {map}");
+    }
+    // No one sees a headless run's frames, so none are drawn between its tics.
+    if !args.headless {
+        engine.set_interpolation(Interpolation::from_options(&pacing));
+    }
+    if let Some(address) = &args.control {
+        info!("control", "waiting for an agent on {address}");
+        if let Err(error) = control::listen(&mut engine, address.as_str()) {
+            error!("control", "{address}: {error}");
+            std::process::exit(1);
+        }
+    }
+    let mut window = Window::new(WindowTitle::new(iwad.info.description), display);
+    if let Some(demo) = &args.playdemo {
+        window.title.activity = Activity::Demo(demo.clone());
+    } else if let Some(demo) = &args.record {
+        window.title.activity = Activity::Recording(demo.clone());
+    }
+    if args.headless {
+        let mut video = NullVideo::new();
+        video.set_icon(&title::program_icon());
+        // A demo being checked plays back flat out; anything else, such as a server, keeps
+        // time.
+        let mut pacing = args
+            .playdemo
+            .is_none()
+            .then(|| Pacing::new(&pacing, game_speed, Box::new(SystemClock::new())));
+        let tics = platform::run_loop(
+            &mut engine,
+            &mut tic_source,
+            &mut window,
+            &mut watcher,
+            &mut video,
+            &mut NullAudio::new(),
+            pacing.as_mut(),
+        );
+        info!("main", "ran {tics} tics headless");
+    }
+    // A recording that never started, as with -record on a demo nobody joined, is left out.
+    if let (Some(name), Some(recorder)) = (&args.record, &tic_source.recorder) {
+        match demo::write(name, &recorder.demo) {
+            Ok(path) => info!("demo", "wrote {}", path.display()),
+            Err(error) => error!("demo", "couldn't write {name}.lmp: {error}"),
+        }
+    }
+    if args.levelstat {
+        let path = Path::new(levelstat::LEVELSTAT_FILE);
+        if let Err(error) = engine.level_stats().borrow().write(path) {
+            warn!("levelstat", "couldn't write {}: {error}", path.display());
+        }
+    }
+    if let Err(error) = engine.lifetime_stats().borrow().write(lifetime_path) {
+        warn!(
+            "lifetime",
+            "couldn't write {}: {error}",
+            lifetime_path.display()
+        );
+    }
+    show_endoom(&engine);
+}
+
+/// Prints the WAD's ENDOOM screen on the way out, when the terminal can show its colors.
+fn show_endoom(engine: &Engine) {
+    if !EndoomOptions::from_config(engine.config()).show || !std::io::stdout().is_terminal() {
+        return;
+    }
+    let screen = engine.wads().lump(endoom::ENDOOM_LUMP);
+    if let Some(screen) = screen.and_then(|lump| Endoom::parse(&lump.data)) {
+        print!("{}", screen.ansi());
+    }
+}
+
+/// Adds a `-file` argument to `wads`: a WAD, a PK3 or ZIP archive, or a folder. A folder is
+/// returned so it can be reloaded.
+fn add_resource(wads: &mut Wads, path: &Path) -> std::io::Result<Option<Folder>> {
+    info!("wad", " adding {}", path.display());
+    if path.is_dir() {
+        let mut folder = Folder::new(path);
+        folder.load()?.mount(wads);
+        return Ok(Some(folder));
+    }
+    match pk3::is_pk3(path) {
+        true => Pk3::open(path)?.mount(wads),
+        false => wads.add(WadFile::open(path)?),
+    }
+    Ok(None)
+}
+
+/// The WAD's `TRANMAP` if it has one, otherwise the table for its palette, cached between runs.
+fn load_tranmap(wads: &Wads, percent: u8) -> TranMap {
+    if let Some(lump) = wads.lump(tranmap::TRANMAP_LUMP) {
+        match TranMap::from_bytes(&lump.data) {
+            Some(tranmap) => return tranmap,
+            None => warn!("renderer", "{} is the wrong size, ignoring it", lump.name),
+        }
+    }
+    let palette = wads
+        .lump("PLAYPAL")
+        .and_then(|playpal| Palette::from_playpal(&playpal.data, 0))
+        .unwrap_or_default();
+    let path = Path::new(translucency::CACHE_FILE);
+    if let Some(tranmap) = translucency::read_cache(&palette, percent, path) {
+        return tranmap;
+    }
+    info!("renderer", "generating the translucency table");
+    let tranmap = TranMap::generate(&palette, percent);
+    if let Err(error) = translucency::write_cache(&tranmap, &palette, percent, path) {
+        warn!("renderer", "couldn't write {}: {error}", path.display());
+    }
+    tranmap
+}
+
+/// Plays `demo` under both configs and prints where they part ways. Returns whether they
+/// agreed to the end.
+fn run_audit(demo: &str, configs: &[PathBuf; 2], wads: &Wads) -> Result<bool, String> {
+    let data = demo::read(demo, wads).map_err(|error| format!("couldn't read {demo}: {error}"))?;
+    let parsed = Demo::parse(&data).map_err(|error| format!("{demo}: {error}"))?;
+    for path in configs {
+        Config::new()
+            .load(path)
+            .map_err(|error| format!("couldn't read {}: {error}", path.display()))?;
+    }
+    // Each bisection step starts both runs over, so the configs are read for every engine.
+    let engine = |path: &Path| {
+        let mut config = Config::new();
+        let _ = config.load(path);
+        Engine::new(wads.clone(), config)
+    };
+    let result = audit::audit(
+        &parsed,
+        || engine(&configs[0]),
+        || engine(&configs[1]),
+        audit::CHECK_INTERVAL,
+    );
+    match result.divergence {
+        Some(divergence) => {
+            println!("{divergence}");
+            Ok(false)
+        }
+        None => {
+            println!("{} tics, no divergence", result.tics);
+            Ok(true)
+        }
+    }
+}
+
+/// The `-jukebox` loop: one command per line on stdin until `q` or end of input.
+fn play_jukebox(mut jukebox: Jukebox, audio: &mut dyn Audio) {
+    if jukebox.tracks().is_empty() {
+        error!("main", "no music lumps in the loaded WADs");
+        std::process::exit(1);
+    }
+    // There is no music device backend yet, so tracks go to the null backend; converting
+    // MUS to MIDI here still exercises the decoder a real synth would be fed.
+    warn!(
+        "sound",
+        "no music device available, playing through the null backend"
+    );
+    print!("{}", jukebox.listing());
+    println!("{}", jukebox::HELP);
+    let mut reply = Reply::Play;
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        match reply {
+            Reply::Play => {
+                let track = jukebox.current().expect("the list isn't empty");
+                if track.format == MusicFormat::Mus {
+                    if let Err(error) = mus::to_midi(&track.data) {
+                        warn!("sound", "{}: {error}", track.lump);
+                    }
+                }
+                audio.play_music(&track.data, true);
+                println!("playing {track}");
+            }
+            Reply::List => print!("{}", jukebox.listing()),
+            Reply::Quit => break,
+            Reply::Error(message) => println!("{message}"),
+        }
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        reply = jukebox.command(&line);
+    }
+    audio.stop_music();
+}
+
+/// Prints what `query` asks about the WAD at `path`, for `doom-rs wadinfo`.
+fn wadinfo(path: &Path, query: &WadQuery) -> Result<(), String> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))
+    };
+    let bytes = read(path)?;
+    let image = WadImage::parse(&bytes)
+        .ok_or_else(|| format!("{} is not a valid WAD file", path.display()))?;
+    match query {
+        WadQuery::Lumps => print!("{}", inspect::lump_table(&image)),
+        WadQuery::Dump { lump, to } => {
+            let data = inspect::find(&image, lump)
+                .ok_or_else(|| format!("no lump {lump} in {}", path.display()))?;
+            std::fs::write(to, data)
+                .map_err(|error| format!("couldn't write {}: {error}", to.display()))?;
+            println!("wrote {} bytes to {}", data.len(), to.display());
+        }
+        WadQuery::Inventory => print!("{}", Inventory::read(&image)),
+        WadQuery::Maps => {
+            for map in inspect::maps(&image) {
+                println!("{map}");
+            }
+        }
+        WadQuery::Diff(other) => {
+            let other_bytes = read(other)?;
+            let other_image = WadImage::parse(&other_bytes)
+                .ok_or_else(|| format!("{} is not a valid WAD file", other.display()))?;
+            let changes = inspect::diff(&image, &other_image);
+            if changes.is_empty() {
+                println!("no differences");
+            }
+            for change in changes {
+                println!("{change}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the lumps of the WAD at `path` as PNG, WAV and MIDI files into `to`, for
+/// `doom-rs extract`. Graphics use the WAD's PLAYPAL, or the one in `iwad` for PWADs that
+/// don't bring their own.
+fn extract(path: &Path, to: &Path, lumps: &[String], iwad: Option<&Path>) -> Result<(), String> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|error| format!("couldn't read {}: {error}", path.display()))
+    };
+    let bytes = read(path)?;
+    let image = WadImage::parse(&bytes)
+        .ok_or_else(|| format!("{} is not a valid WAD file", path.display()))?;
+    let playpal = match inspect::find(&image, "PLAYPAL") {
+        Some(playpal) => Some(playpal.to_vec()),
+        None => match iwad {
+            Some(iwad) => WadImage::parse(&read(iwad)?)
+                .and_then(|image| inspect::find(&image, "PLAYPAL").map(<[u8]>::to_vec)),
+            None => None,
+        },
+    };
+    let palette = playpal.and_then(|playpal| Palette::from_playpal(&playpal, 0));
+    if palette.is_none() {
+        eprintln!("doom-rs: no PLAYPAL, skipping graphics (give one with -iwad)");
+    }
+    std::fs::create_dir_all(to)
+        .map_err(|error| format!("couldn't create {}: {error}", to.display()))?;
+    let exported = export::extract(&image, palette.as_ref(), lumps);
+    for file in &exported {
+        let out = to.join(file.file_name());
+        std::fs::write(&out, &file.data)
+            .map_err(|error| format!("couldn't write {}: {error}", out.display()))?;
+    }
+    println!("wrote {} files to {}", exported.len(), to.display());
+    Ok(())
+}
+
+/// Asks on the terminal which IWAD to play when several are installed. Until the menu exists
+/// this is the selection screen; an empty or invalid answer takes the first one.
+fn prompt_for_iwad(found: &[FoundIwad]) -> Option<usize> {
+    println!("Several IWADs were found:");
+    for (i, iwad) in found.iter().enumerate() {
+        println!(
+            "  {}) {} - {}",
+            i + 1,
+            iwad.info.description,
+            iwad.path.display()
+        );
+    }
+    print!("Choose one [1]: ");
+    std::io::stdout().flush().ok()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line).ok()?;
+    line.trim().parse::<usize>().ok()?.checked_sub(1)
+}
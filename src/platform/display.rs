@@ -0,0 +1,340 @@
+use super::{MonitorInfo, Rect, Video, WindowEvent, WindowMode};
+use doom_core::engine::config::{Config, Configurable, Section};
+use doom_core::engine::input::{Event, KEY_ENTER, KEY_LALT};
+
+/// The persisted display settings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayConfig {
+    pub mode: WindowMode,
+    /// Which fullscreen flavour Alt+Enter switches to.
+    pub fullscreen_mode: WindowMode,
+    pub monitor: usize,
+    /// Last size and position of the window while it was windowed.
+    pub window: Rect,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            mode: WindowMode::Windowed,
+            fullscreen_mode: WindowMode::Borderless,
+            monitor: 0,
+            window: Rect {
+                x: 64,
+                y: 64,
+                width: 960,
+                height: 720,
+            },
+        }
+    }
+}
+
+impl Configurable for DisplayConfig {
+    fn register(config: &mut Config) {
+        let defaults = Self::default();
+        config.register_bool("video_fullscreen", false, Section::Extended);
+        config.register_str("video_fullscreen_mode", "borderless", Section::Extended);
+        config.register_int("video_monitor", 0, Section::Extended);
+        config.register_int("window_x", defaults.window.x, Section::Extended);
+        config.register_int("window_y", defaults.window.y, Section::Extended);
+        config.register_int(
+            "window_width",
+            defaults.window.width as i32,
+            Section::Extended,
+        );
+        config.register_int(
+            "window_height",
+            defaults.window.height as i32,
+            Section::Extended,
+        );
+    }
+
+    fn from_config(config: &Config) -> Self {
+        let fullscreen_mode = match config.get_str("video_fullscreen_mode").as_str() {
+            "exclusive" => WindowMode::Exclusive,
+            _ => WindowMode::Borderless,
+        };
+        Self {
+            mode: if config.get_bool("video_fullscreen") {
+                fullscreen_mode
+            } else {
+                WindowMode::Windowed
+            },
+            fullscreen_mode,
+            monitor: config.get_int("video_monitor").max(0) as usize,
+            window: Rect {
+                x: config.get_int("window_x"),
+                y: config.get_int("window_y"),
+                width: config.get_int("window_width").max(1) as u32,
+                height: config.get_int("window_height").max(1) as u32,
+            },
+        }
+    }
+
+    fn to_config(&self, config: &mut Config) {
+        config.set_bool("video_fullscreen", self.mode != WindowMode::Windowed);
+        let fullscreen_mode = match self.fullscreen_mode {
+            WindowMode::Exclusive => "exclusive",
+            _ => "borderless",
+        };
+        config.set_str("video_fullscreen_mode", fullscreen_mode);
+        config.set_int("video_monitor", self.monitor as i32);
+        config.set_int("window_x", self.window.x);
+        config.set_int("window_y", self.window.y);
+        config.set_int("window_width", self.window.width as i32);
+        config.set_int("window_height", self.window.height as i32);
+    }
+}
+
+impl DisplayConfig {
+    /// The settings with `-fullscreen` and `-resolution` from the command line laid over them.
+    pub fn overridden(mut self, fullscreen: bool, resolution: Option<(u32, u32)>) -> Self {
+        if fullscreen {
+            self.mode = self.fullscreen_mode;
+        }
+        if let Some((width, height)) = resolution {
+            self.window.width = width;
+            self.window.height = height;
+        }
+        self
+    }
+}
+
+/// Owns the window mode at runtime: Alt+Enter toggling, remembering where the window was, and
+/// placing fullscreen windows on the configured monitor.
+pub struct DisplayManager {
+    config: DisplayConfig,
+    alt_down: bool,
+    dirty: bool, // The mode must be (re)applied to the video backend.
+}
+
+impl DisplayManager {
+    pub fn new(config: DisplayConfig) -> Self {
+        Self {
+            config,
+            alt_down: false,
+            dirty: true,
+        }
+    }
+
+    pub fn config(&self) -> &DisplayConfig {
+        &self.config
+    }
+
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        if mode != self.config.mode {
+            self.config.mode = mode;
+            self.dirty = true;
+        }
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let next = if self.config.mode == WindowMode::Windowed {
+            match self.config.fullscreen_mode {
+                WindowMode::Windowed => WindowMode::Borderless,
+                mode => mode,
+            }
+        } else {
+            WindowMode::Windowed
+        };
+        self.set_mode(next);
+    }
+
+    pub fn set_monitor(&mut self, monitor: usize) {
+        if monitor != self.config.monitor {
+            self.config.monitor = monitor;
+            self.dirty |= self.config.mode != WindowMode::Windowed;
+        }
+    }
+
+    /// Watches for Alt+Enter. Returns true if the event was eaten.
+    pub fn responder(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::KeyDown(KEY_LALT) => self.alt_down = true,
+            Event::KeyUp(KEY_LALT) => self.alt_down = false,
+            Event::KeyDown(KEY_ENTER) if self.alt_down => {
+                self.toggle_fullscreen();
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Remembers the window geometry, but only while windowed so that leaving fullscreen
+    /// restores what the user had before.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        if self.config.mode != WindowMode::Windowed {
+            return;
+        }
+        match *event {
+            WindowEvent::Resized { width, height } => {
+                self.config.window.width = width;
+                self.config.window.height = height;
+            }
+            WindowEvent::Moved { x, y } => {
+                self.config.window.x = x;
+                self.config.window.y = y;
+            }
+            WindowEvent::CloseRequested => {}
+        }
+    }
+
+    /// Where the window should be for the current mode. A configured monitor that is no longer
+    /// connected falls back to the first one.
+    pub fn target_bounds(&self, monitors: &[MonitorInfo]) -> Rect {
+        if self.config.mode == WindowMode::Windowed {
+            return self.config.window;
+        }
+        monitors
+            .get(self.config.monitor)
+            .or_else(|| monitors.first())
+            .map_or(self.config.window, |monitor| monitor.bounds)
+    }
+
+    /// Pushes a pending mode change to the backend.
+    pub fn apply(&mut self, video: &mut dyn Video) {
+        if !self.dirty {
+            return;
+        }
+        let bounds = self.target_bounds(&video.monitors());
+        video.set_mode(self.config.mode, bounds);
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doom_core::engine::title::Icon;
+    use doom_core::renderer::framebuffer::Palette;
+
+    #[derive(Default)]
+    struct FakeVideo {
+        applied: Vec<(WindowMode, Rect)>,
+    }
+
+    impl Video for FakeVideo {
+        fn monitors(&self) -> Vec<MonitorInfo> {
+            let monitor = |name: &str, x| MonitorInfo {
+                name: name.to_string(),
+                bounds: Rect {
+                    x,
+                    y: 0,
+                    width: 1920,
+                    height: 1080,
+                },
+            };
+            vec![monitor("left", 0), monitor("right", 1920)]
+        }
+
+        fn set_mode(&mut self, mode: WindowMode, bounds: Rect) {
+            self.applied.push((mode, bounds));
+        }
+
+        fn present(&mut self, _frame: &[u8], _width: usize, _palette: &Palette) {}
+
+        fn poll(&mut self, _input: &mut Vec<Event>, _window: &mut Vec<WindowEvent>) {}
+
+        fn set_title(&mut self, _title: &str) {}
+
+        fn set_icon(&mut self, _icon: &Icon) {}
+    }
+
+    #[test]
+    fn alt_enter_toggles_between_windowed_and_fullscreen() {
+        let mut display = DisplayManager::new(DisplayConfig::default());
+        assert!(!display.responder(&Event::KeyDown(KEY_ENTER)));
+        display.responder(&Event::KeyDown(KEY_LALT));
+        assert!(display.responder(&Event::KeyDown(KEY_ENTER)));
+        assert_eq!(display.config().mode, WindowMode::Borderless);
+        assert!(display.responder(&Event::KeyDown(KEY_ENTER)));
+        assert_eq!(display.config().mode, WindowMode::Windowed);
+    }
+
+    #[test]
+    fn fullscreen_uses_the_selected_monitor_and_windowed_restores_geometry() {
+        let mut video = FakeVideo::default();
+        let mut display = DisplayManager::new(DisplayConfig {
+            monitor: 1,
+            ..DisplayConfig::default()
+        });
+        display.handle_window_event(&WindowEvent::Resized {
+            width: 640,
+            height: 480,
+        });
+        display.toggle_fullscreen();
+        display.apply(&mut video);
+        // Resizes while fullscreen must not overwrite the remembered window size.
+        display.handle_window_event(&WindowEvent::Resized {
+            width: 1920,
+            height: 1080,
+        });
+        display.toggle_fullscreen();
+        display.apply(&mut video);
+
+        assert_eq!(video.applied[0].0, WindowMode::Borderless);
+        assert_eq!(video.applied[0].1.x, 1920);
+        assert_eq!(video.applied[1].0, WindowMode::Windowed);
+        assert_eq!(
+            (video.applied[1].1.width, video.applied[1].1.height),
+            (640, 480)
+        );
+    }
+
+    #[test]
+    fn config_round_trip_keeps_mode_and_geometry() {
+        let mut config = Config::new();
+        DisplayConfig::register(&mut config);
+        assert_eq!(
+            DisplayConfig::from_config(&config),
+            DisplayConfig::default()
+        );
+        let display = DisplayConfig {
+            mode: WindowMode::Exclusive,
+            fullscreen_mode: WindowMode::Exclusive,
+            monitor: 1,
+            window: Rect {
+                x: -10,
+                y: 20,
+                width: 800,
+                height: 600,
+            },
+        };
+        display.to_config(&mut config);
+        assert_eq!(DisplayConfig::from_config(&config), display);
+    }
+
+    #[test]
+    fn command_line_flags_override_the_config() {
+        let config = DisplayConfig {
+            fullscreen_mode: WindowMode::Exclusive,
+            ..DisplayConfig::default()
+        };
+        assert_eq!(config.clone().overridden(false, None), config);
+        let overridden = config.overridden(true, Some((1920, 1080)));
+        assert_eq!(overridden.mode, WindowMode::Exclusive);
+        assert_eq!(
+            (overridden.window.width, overridden.window.height),
+            (1920, 1080)
+        );
+    }
+
+    #[test]
+    fn missing_monitor_falls_back_to_the_first() {
+        let mut display = DisplayManager::new(DisplayConfig {
+            monitor: 5,
+            mode: WindowMode::Exclusive,
+            ..DisplayConfig::default()
+        });
+        let mut video = FakeVideo::default();
+        display.apply(&mut video);
+        assert_eq!(
+            video.applied,
+            vec![(WindowMode::Exclusive, video.monitors()[0].bounds)]
+        );
+        // Nothing changed, nothing to apply.
+        display.apply(&mut video);
+        assert_eq!(video.applied.len(), 1);
+    }
+}
@@ -0,0 +1,322 @@
+//! The boundary between the engine and the operating system: windows, input devices, audio.
+
+pub mod display;
+pub mod null;
+
+use display::{DisplayConfig, DisplayManager};
+use doom_core::audio::capture::MusicCapture;
+use doom_core::engine::config::Configurable;
+use doom_core::engine::hotkeys::Hotkey;
+use doom_core::engine::input::Event;
+use doom_core::engine::pacing::{Category, Pacer, PacingOptions};
+use doom_core::engine::speed::GameSpeed;
+use doom_core::engine::title::{Activity, Icon, WindowTitle};
+use doom_core::engine::Engine;
+use doom_core::game::demo::TicSource;
+use doom_core::game::dropin::PeerEvent;
+use doom_core::game::pause::PauseOptions;
+use doom_core::renderer::framebuffer::{Palette, SCREENHEIGHT, SCREENWIDTH};
+use doom_core::util::fixed::FRACUNIT;
+use doom_core::wad::watch::Watcher;
+use doom_core::warn;
+use std::time::{Duration, Instant};
+
+/// A rectangle in desktop coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorInfo {
+    pub name: String,
+    pub bounds: Rect,
+}
+
+/// Events about the window itself, as opposed to game input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowEvent {
+    Resized { width: u32, height: u32 },
+    Moved { x: i32, y: i32 },
+    CloseRequested,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// A window without decorations covering the whole monitor, at the desktop resolution.
+    Borderless,
+    /// Takes over the monitor and switches its video mode.
+    Exclusive,
+}
+
+/// The video half of a platform backend.
+pub trait Video {
+    fn monitors(&self) -> Vec<MonitorInfo>;
+    /// Puts the window into `mode`, covering `bounds` (the chosen monitor for the fullscreen
+    /// modes, the remembered window position for windowed mode).
+    fn set_mode(&mut self, mode: WindowMode, bounds: Rect);
+    /// Shows a finished frame of palette indices, `width` pixels to a row.
+    fn present(&mut self, frame: &[u8], width: usize, palette: &Palette);
+    /// Collects the input and window events that arrived since the last call.
+    fn poll(&mut self, input: &mut Vec<Event>, window: &mut Vec<WindowEvent>);
+    /// Sets the text of the title bar and task bar entry.
+    fn set_title(&mut self, title: &str);
+    fn set_icon(&mut self, icon: &Icon);
+}
+
+/// A sound started on the backend, for stopping it or asking if it is still playing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SoundHandle(pub u32);
+
+/// The audio half of a platform backend.
+pub trait Audio {
+    /// Starts 8-bit unsigned mono `samples` at `rate` Hz. `volume` is 0 to 127 and
+    /// `separation` 0 (left) to 255 (right), as vanilla passes them.
+    fn start_sound(&mut self, samples: &[u8], rate: u32, volume: u8, separation: u8)
+        -> SoundHandle;
+    fn stop_sound(&mut self, handle: SoundHandle);
+    fn is_playing(&self, handle: SoundHandle) -> bool;
+    /// Starts a MUS or MIDI lump, replacing whatever music was playing.
+    fn play_music(&mut self, data: &[u8], looping: bool);
+    fn stop_music(&mut self);
+    /// Holds the music where it is, or carries on from there.
+    fn pause_music(&mut self, paused: bool);
+    /// Called once per game tic, after the tic ran.
+    fn update(&mut self);
+    /// Whether music goes through an OPL synth, which `oplcapture` can record.
+    fn opl_synth(&self) -> bool {
+        false
+    }
+    /// Hands `capture` the OPL register writes made since the last call, each after the
+    /// samples generated before it.
+    fn capture_opl(&mut self, _capture: &mut MusicCapture) {}
+}
+
+/// The time the loop is paced by.
+pub trait Clock {
+    /// Time since some fixed starting point.
+    fn now(&self) -> Duration;
+    /// Returns once `now` has reached `time`.
+    fn sleep_until(&mut self, time: Duration);
+}
+
+/// The wall clock, counted from when it was made.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock(Instant);
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+
+    fn sleep_until(&mut self, time: Duration) {
+        std::thread::sleep(time.saturating_sub(self.now()));
+    }
+}
+
+/// What keeps `run_loop` in step with a clock: the tics `game_speed` makes due, no more than
+/// `max_catchup_tics` at a time.
+pub struct Pacing {
+    pacer: Pacer,
+    clock: Box<dyn Clock>,
+}
+
+impl Pacing {
+    pub fn new(options: &PacingOptions, speed: GameSpeed, clock: Box<dyn Clock>) -> Self {
+        Self {
+            pacer: Pacer::new(options, speed, clock.now()),
+            clock,
+        }
+    }
+
+    pub fn pacer(&self) -> &Pacer {
+        &self.pacer
+    }
+}
+
+/// The window as the game loop keeps it: the title it shows and the mode it is in.
+pub struct Window {
+    pub title: WindowTitle,
+    pub display: DisplayManager,
+}
+
+impl Window {
+    pub fn new(title: WindowTitle, display: DisplayConfig) -> Self {
+        Self {
+            title,
+            display: DisplayManager::new(display),
+        }
+    }
+}
+
+/// Runs the game until the tic source runs dry: input in, the tics that are due, sound, then
+/// a frame. Returns the number of tics run. With `pacing` the tics follow its clock and frames
+/// between them are drawn part of the way through the last one, as far as the engine's
+/// interpolation allows; without it every pass runs one tic and the loop goes as fast as it
+/// can, like `-timedemo`. A demo being played back runs as its playback controls say, and
+/// draws nothing while skipping ahead; the join key takes it over. Peers the tic source says
+/// dropped out or came back leave or rejoin the game, and asking to rejoin gets an answer. The window title is kept
+/// up to date along the way: a demo that is joined turns into play. Alt+Enter, resizing and
+/// moving the window and `video_monitor` go to the display manager, which keeps the config
+/// in step. Music the game switches to plays through `audio`, and pauses with the game
+/// unless `pause_music` keeps it going; it stops when the loop ends. What plays, and what an
+/// OPL synth writes, goes to the engine's music capture. A frame the
+/// screenshot key asked for is written out before it is shown, each frame is shown through
+/// the gamma correction and what it cost goes to the performance overlay. Files `watcher`
+/// sees change are reloaded after the tic, before the frame that shows them.
+pub fn run_loop(
+    engine: &mut Engine,
+    tics: &mut TicSource,
+    window: &mut Window,
+    watcher: &mut Watcher,
+    video: &mut dyn Video,
+    audio: &mut dyn Audio,
+    mut pacing: Option<&mut Pacing>,
+) -> u32 {
+    let (mut input, mut window_events) = (Vec::new(), Vec::new());
+    let mut frame = vec![0; SCREENWIDTH * SCREENHEIGHT];
+    let mut count = 0;
+    let music_pauses = !PauseOptions::from_config(engine.config()).music;
+    let mut paused = false;
+    let mut last_frame = Instant::now();
+    let capture = engine.music_capture();
+    capture.borrow_mut().opl_backend = audio.opl_synth();
+    let count = 'run: loop {
+        input.clear();
+        window_events.clear();
+        video.poll(&mut input, &mut window_events);
+        if window_events.contains(&WindowEvent::CloseRequested) {
+            break count;
+        }
+        let display = &mut window.display;
+        for event in &window_events {
+            display.handle_window_event(event);
+        }
+        display.set_monitor(engine.config().get_int("video_monitor").max(0) as usize);
+        for event in &input {
+            if !display.responder(event) {
+                engine.responder(event);
+            }
+        }
+        display.apply(video);
+        if DisplayConfig::from_config(engine.config()) != *display.config() {
+            display.config().to_config(engine.config_mut());
+        }
+        for hotkey in engine.take_hotkeys() {
+            if hotkey == Hotkey::JoinDemo {
+                tics.join();
+            } else if let Some(playback) = &mut tics.playback {
+                match hotkey {
+                    Hotkey::Pause => playback.toggle_pause(),
+                    Hotkey::DemoStep => playback.step(),
+                    Hotkey::DemoFaster => playback.faster(),
+                    Hotkey::DemoSlower => playback.slower(),
+                    _ => {}
+                }
+            }
+        }
+        for event in tics.take_peer_events() {
+            match event {
+                PeerEvent::Left(player) => {
+                    engine.player_left(player);
+                }
+                PeerEvent::Joined(player) => {
+                    engine.player_joined(player);
+                }
+                PeerEvent::Rejoin { session, player } => {
+                    let answer = engine.answer_rejoin((session, player), tics.session);
+                    tics.replies.push(answer);
+                }
+            }
+        }
+        if let Some(track) = engine.take_music() {
+            audio.play_music(&track.data, true);
+            capture.borrow_mut().music_started(&track.lump, &track.data);
+        }
+        let mut due = match &mut pacing {
+            Some(pacing) => pacing.pacer.tics_due(pacing.clock.now()),
+            None => 1,
+        };
+        let mut render = true;
+        if let Some(playback) = &mut tics.playback {
+            let frame = playback.frame(due as i32);
+            due = frame.tics.max(0) as u32;
+            render = frame.render;
+        }
+        let tics_started = Instant::now();
+        for _ in 0..due {
+            let Some(cmds) = tics.tic(engine.build_ticcmd()) else {
+                break 'run count;
+            };
+            if matches!(window.title.activity, Activity::Demo(_)) && !tics.playing_back() {
+                window.title.activity = Activity::Playing;
+            }
+            if let Some(text) = window.title.changed() {
+                video.set_title(&text);
+            }
+            engine.run_tic(&cmds);
+            match watcher.tic(engine.wads_mut()) {
+                Ok(changed) if !changed.is_empty() => {
+                    engine.assets_changed(&changed);
+                }
+                Ok(_) => {}
+                Err(error) => warn!("wad", "couldn't reload: {error}"),
+            }
+            if engine.pause().paused != paused {
+                paused = engine.pause().paused;
+                if music_pauses {
+                    audio.pause_music(paused);
+                }
+            }
+            audio.update();
+            audio.capture_opl(&mut capture.borrow_mut());
+            count += 1;
+        }
+        let tic_time = tics_started.elapsed();
+        if !render {
+            continue;
+        }
+        if let Some(pacing) = &mut pacing {
+            let now = pacing.clock.now();
+            if !pacing.pacer.should_render(now) {
+                continue;
+            }
+            engine.set_frame_time(pacing.pacer.fraction(now));
+        }
+        let now = Instant::now();
+        engine.record_frame(now - last_frame, tic_time);
+        last_frame = now;
+        engine.render_into(&mut frame);
+        engine.capture_screenshot();
+        let palette = engine.palette().corrected(&engine.gamma_table());
+        video.present(&frame, SCREENWIDTH, &palette);
+        if let Some(pacing) = &mut pacing {
+            // Nothing new to draw before the next tic unless frames are drawn between tics.
+            let between = |category| engine.frame_fraction(category) != FRACUNIT;
+            if !Category::ALL.into_iter().any(between) {
+                let next = pacing.pacer.next_tic();
+                pacing.clock.sleep_until(next);
+            }
+        }
+    };
+    audio.stop_music();
+    capture.borrow_mut().music_stopped();
+    count
+}
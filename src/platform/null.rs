@@ -0,0 +1,572 @@
+//! Backends that do nothing, for running without a display or a sound card.
+//!
+//! Determinism tests in CI, dedicated servers and bots all run the full game loop, and none
+//! of them have anyone watching. `NullVideo` reports one monitor and counts the frames it is
+//! given; `NullAudio` keeps sounds "playing" for as long as they would really last, so code
+//! that waits for a sound to end behaves the same as with real audio. Neither opens
+//! anything, so they work in a container with no devices at all.
+
+use super::{Audio, MonitorInfo, Rect, SoundHandle, Video, WindowEvent, WindowMode};
+use doom_core::engine::input::Event;
+use doom_core::engine::title::Icon;
+use doom_core::game::TICRATE;
+use doom_core::renderer::framebuffer::Palette;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Debug, Default)]
+pub struct NullVideo {
+    pub mode: WindowMode,
+    pub title: String,
+    pub icon: Option<Icon>,
+    frames: u64,
+    close_at: Option<u64>,
+    last_frame: Vec<u8>,
+    input: VecDeque<Event>,
+    window: VecDeque<WindowEvent>,
+}
+
+impl NullVideo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frames presented so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn last_frame(&self) -> &[u8] {
+        &self.last_frame
+    }
+
+    /// Queues an input event for the next `poll`, as if it was typed.
+    pub fn push_input(&mut self, event: Event) {
+        self.input.push_back(event);
+    }
+
+    /// Queues a window event for the next `poll`, as if the user had moved or resized it.
+    pub fn push_window_event(&mut self, event: WindowEvent) {
+        self.window.push_back(event);
+    }
+
+    /// Makes the next `poll` report that the window was closed, ending the game loop.
+    pub fn close(&mut self) {
+        self.window.push_back(WindowEvent::CloseRequested);
+    }
+
+    /// Makes `poll` report that the window was closed once `frames` frames were presented.
+    pub fn close_after(&mut self, frames: u64) {
+        self.close_at = Some(frames);
+    }
+}
+
+impl Video for NullVideo {
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        vec![MonitorInfo {
+            name: "null".to_string(),
+            bounds: Rect {
+                x: 0,
+                y: 0,
+                width: 640,
+                height: 480,
+            },
+        }]
+    }
+
+    fn set_mode(&mut self, mode: WindowMode, _bounds: Rect) {
+        self.mode = mode;
+    }
+
+    fn present(&mut self, frame: &[u8], _width: usize, _palette: &Palette) {
+        self.frames += 1;
+        self.last_frame.clear();
+        self.last_frame.extend_from_slice(frame);
+    }
+
+    fn poll(&mut self, input: &mut Vec<Event>, window: &mut Vec<WindowEvent>) {
+        input.extend(self.input.drain(..));
+        window.extend(self.window.drain(..));
+        if self.close_at.is_some_and(|frames| self.frames >= frames) {
+            window.push(WindowEvent::CloseRequested);
+        }
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    fn set_icon(&mut self, icon: &Icon) {
+        self.icon = Some(icon.clone());
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NullAudio {
+    next_handle: u32,
+    playing: HashMap<SoundHandle, u32>, // Tics left.
+    music: Option<bool>,                // Whether the current music loops.
+    music_paused: bool,
+}
+
+impl NullAudio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn music_playing(&self) -> bool {
+        self.music.is_some()
+    }
+
+    pub fn music_paused(&self) -> bool {
+        self.music_paused
+    }
+}
+
+impl Audio for NullAudio {
+    fn start_sound(&mut self, samples: &[u8], rate: u32, _volume: u8, _sep: u8) -> SoundHandle {
+        let handle = SoundHandle(self.next_handle);
+        self.next_handle = self.next_handle.wrapping_add(1);
+        let rate = u64::from(rate.max(1));
+        let tics = (samples.len() as u64 * TICRATE as u64).div_ceil(rate);
+        if tics > 0 {
+            self.playing.insert(handle, tics as u32);
+        }
+        handle
+    }
+
+    fn stop_sound(&mut self, handle: SoundHandle) {
+        self.playing.remove(&handle);
+    }
+
+    fn is_playing(&self, handle: SoundHandle) -> bool {
+        self.playing.contains_key(&handle)
+    }
+
+    fn play_music(&mut self, _data: &[u8], looping: bool) {
+        self.music = Some(looping);
+    }
+
+    fn stop_music(&mut self) {
+        self.music = None;
+    }
+
+    fn pause_music(&mut self, paused: bool) {
+        self.music_paused = paused;
+    }
+
+    fn update(&mut self) {
+        self.playing.retain(|_, tics| {
+            *tics -= 1;
+            *tics > 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::display::DisplayConfig;
+    use crate::platform::{run_loop, Clock, Pacing, Window};
+    use doom_core::audio::capture::{MusicCapture, VGM_RATE};
+    use doom_core::engine::config::Config;
+    use doom_core::engine::config::Configurable;
+    use doom_core::engine::input::{Key, KEY_ENTER, KEY_LALT, KEY_PAUSE, KEY_UPARROW};
+    use doom_core::engine::pacing::{Interpolation, PacingOptions};
+    use doom_core::engine::speed::GameSpeed;
+    use doom_core::engine::title::{Activity, WindowTitle};
+    use doom_core::engine::Engine;
+    use doom_core::game::demo::{DemoHeader, DemoPlayback, DemoRecorder, TicSource};
+    use doom_core::game::dropin::{Handshake, PeerEvent, Snapshot};
+    use doom_core::game::pause;
+    use doom_core::game::ticcmd::TicCmd;
+    use doom_core::wad::file::Wads;
+    use doom_core::wad::watch::Watcher;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    fn doom_window() -> Window {
+        Window::new(WindowTitle::new("Doom"), DisplayConfig::default())
+    }
+
+    /// A clock that moves on by `step` each time it is read and jumps when slept on.
+    struct StepClock {
+        now: Rc<Cell<Duration>>,
+        step: Duration,
+    }
+
+    impl Clock for StepClock {
+        fn now(&self) -> Duration {
+            self.now.set(self.now.get() + self.step);
+            self.now.get()
+        }
+
+        fn sleep_until(&mut self, time: Duration) {
+            self.now.set(self.now.get().max(time));
+        }
+    }
+
+    /// Plays 70 empty tics paced by a `StepClock`, returning the frames drawn and the time
+    /// it took.
+    fn play_paced(speed: GameSpeed, interpolate: bool) -> (u64, Duration) {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..70 {
+            recorder.record(&[TicCmd::default()]);
+        }
+        let playback = DemoPlayback::new(recorder.demo);
+        let mut tics = TicSource::playback(playback, Default::default(), false);
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let options = PacingOptions {
+            interpolate,
+            ..PacingOptions::default()
+        };
+        engine.set_interpolation(Interpolation::from_options(&options));
+        let now = Rc::new(Cell::new(Duration::ZERO));
+        let clock = StepClock {
+            now: Rc::clone(&now),
+            step: Duration::from_millis(5),
+        };
+        let mut pacing = Pacing::new(&options, speed, Box::new(clock));
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        let ran = run_loop(
+            &mut engine,
+            &mut tics,
+            &mut doom_window(),
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            Some(&mut pacing),
+        );
+        assert_eq!(ran, 70);
+        (video.frames(), now.get())
+    }
+
+    #[test]
+    fn sounds_last_as_long_as_their_samples() {
+        let mut audio = NullAudio::new();
+        let short = audio.start_sound(&[128; 11025 / 35], 11025, 127, 128);
+        let long = audio.start_sound(&[128; 11025], 11025, 127, 128);
+        let empty = audio.start_sound(&[], 11025, 127, 128);
+        assert!(audio.is_playing(short) && audio.is_playing(long));
+        assert!(!audio.is_playing(empty));
+        audio.update();
+        assert!(!audio.is_playing(short));
+        for _ in 1..35 {
+            assert!(audio.is_playing(long));
+            audio.update();
+        }
+        assert!(!audio.is_playing(long));
+
+        audio.play_music(b"MUS\x1a", true);
+        assert!(audio.music_playing());
+        audio.stop_music();
+        assert!(!audio.music_playing());
+    }
+
+    #[test]
+    fn a_demo_plays_through_the_whole_loop_headless() {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for n in 0..70 {
+            let cmd = TicCmd {
+                forwardmove: n,
+                ..TicCmd::default()
+            };
+            recorder.record(&[cmd]);
+        }
+        let playback = DemoPlayback::new(recorder.demo);
+        let mut tics = TicSource::playback(playback, Default::default(), false);
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        let mut window = doom_window();
+        window.title.activity = Activity::Demo("DEMO1".to_string());
+        let ran = run_loop(
+            &mut engine,
+            &mut tics,
+            &mut window,
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        assert_eq!(ran, 70);
+        assert_eq!(engine.gametic(), 70);
+        assert_eq!(video.frames(), 70);
+        assert_eq!(video.last_frame().len(), 320 * 200);
+        assert_eq!(video.title, "Doom - demo DEMO1 - doom-rs");
+    }
+
+    #[test]
+    fn a_paced_loop_keeps_to_the_clock() {
+        let (frames, took) = play_paced(GameSpeed::default(), false);
+        assert_eq!(
+            frames, 71,
+            "one frame a tic without interpolation, and one before"
+        );
+        assert!((Duration::from_secs(2)..Duration::from_millis(2050)).contains(&took));
+
+        let (frames, took) = play_paced(GameSpeed::default(), true);
+        assert!(frames > 70, "frames are drawn between tics");
+        assert!((Duration::from_secs(2)..Duration::from_millis(2050)).contains(&took));
+
+        let (_, took) = play_paced(GameSpeed::new(200), false);
+        assert!((Duration::from_secs(1)..Duration::from_millis(1050)).contains(&took));
+    }
+
+    #[test]
+    fn a_recorded_pause_stops_the_game_and_the_music() {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for n in 0..70 {
+            let cmd = TicCmd::default();
+            recorder.record(&[if n == 10 { pause::with_pause(cmd) } else { cmd }]);
+        }
+        for music in [false, true] {
+            let playback = DemoPlayback::new(recorder.demo.clone());
+            let mut tics = TicSource::playback(playback, Default::default(), false);
+            let mut config = Config::new();
+            config.set_bool("pause_music", music);
+            let mut engine = Engine::new(Wads::new(), config);
+            let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+            let mut window = doom_window();
+            run_loop(
+                &mut engine,
+                &mut tics,
+                &mut window,
+                &mut Watcher::default(),
+                &mut video,
+                &mut audio,
+                None,
+            );
+            assert_eq!((engine.gametic(), engine.leveltime()), (70, 10));
+            assert_eq!(video.frames(), 70);
+            assert_eq!(audio.music_paused(), !music);
+        }
+    }
+
+    #[test]
+    fn playback_skips_ahead_and_follows_its_keys() {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..70 {
+            recorder.record(&[TicCmd::default()]);
+        }
+        let play = |skip: usize, keys: &[u8]| {
+            let mut playback = DemoPlayback::new(recorder.demo.clone());
+            playback.skip_to(skip);
+            let mut tics = TicSource::playback(playback, Default::default(), false);
+            let mut engine = Engine::new(Wads::new(), Config::new());
+            let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+            for &key in keys {
+                video.push_input(Event::KeyDown(Key::from(key)));
+            }
+            video.close_after(100);
+            let ran = run_loop(
+                &mut engine,
+                &mut tics,
+                &mut doom_window(),
+                &mut Watcher::default(),
+                &mut video,
+                &mut audio,
+                None,
+            );
+            (ran, video.frames())
+        };
+        assert_eq!(play(0, b""), (70, 70));
+        assert_eq!(
+            play(50, b""),
+            (70, 20),
+            "nothing is drawn until the skip arrives"
+        );
+        assert_eq!(play(0, b"]"), (70, 35), "two tics a frame at double speed");
+        assert_eq!(play(0, b"]]["), (70, 35));
+        let pause = KEY_PAUSE as u8;
+        assert_eq!(
+            play(0, &[pause, b'\\']),
+            (1, 100),
+            "paused, one step and no more"
+        );
+    }
+
+    #[test]
+    fn the_join_key_takes_over_the_demo_and_records_from_there() {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..70 {
+            recorder.record(&[TicCmd::default()]);
+        }
+        let playback = DemoPlayback::new(recorder.demo);
+        let mut tics = TicSource::playback(playback, Default::default(), true);
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        video.push_input(Event::KeyDown(b'q' as Key));
+        video.push_input(Event::KeyDown(KEY_UPARROW));
+        video.close_after(100);
+        let ran = run_loop(
+            &mut engine,
+            &mut tics,
+            &mut doom_window(),
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        assert_eq!(ran, 100, "local play goes on past the end of the demo");
+        assert!(!tics.playing_back());
+        let recorded = &tics.recorder.as_ref().unwrap().demo;
+        assert_eq!(recorded.duration(), 100);
+        assert!(recorded.tics.iter().all(|cmds| cmds[0].forwardmove > 0));
+    }
+
+    #[test]
+    fn local_play_runs_until_the_window_closes() {
+        let mut tics = TicSource::local(None);
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        video.push_input(Event::KeyDown(KEY_UPARROW));
+        video.close();
+        let mut window = doom_window();
+        let ran = run_loop(
+            &mut engine,
+            &mut tics,
+            &mut window,
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        assert_eq!(ran, 0);
+
+        let (mut input, mut events) = (Vec::new(), Vec::new());
+        video.poll(&mut input, &mut events);
+        assert!(input.is_empty() && events.is_empty());
+    }
+
+    #[test]
+    fn alt_enter_and_resizing_reach_the_display_config() {
+        let mut config = Config::new();
+        DisplayConfig::register(&mut config);
+        let mut engine = Engine::new(Wads::new(), config);
+        let mut tics = TicSource::local(None);
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        video.push_window_event(WindowEvent::Resized {
+            width: 640,
+            height: 400,
+        });
+        video.push_input(Event::KeyDown(KEY_LALT));
+        video.push_input(Event::KeyDown(KEY_ENTER));
+        video.close_after(1);
+        run_loop(
+            &mut engine,
+            &mut tics,
+            &mut doom_window(),
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        assert_eq!(video.mode, WindowMode::Borderless);
+        assert!(engine.config().get_bool("video_fullscreen"));
+        // The size it had while windowed, to go back to.
+        assert_eq!(engine.config().get_int("window_width"), 640);
+        assert_eq!(engine.config().get_int("window_height"), 400);
+    }
+
+    #[test]
+    fn peers_asking_to_rejoin_are_answered() {
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let mut tics = TicSource::local(None);
+        tics.session = 7;
+        tics.peer_event(PeerEvent::Rejoin {
+            session: 7,
+            player: 1,
+        });
+        let (mut video, mut audio) = (NullVideo::new(), NullAudio::new());
+        video.close_after(1);
+        run_loop(
+            &mut engine,
+            &mut tics,
+            &mut doom_window(),
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        assert!(matches!(
+            tics.replies[..],
+            [Handshake::Snapshot(Snapshot { player: 1, .. })]
+        ));
+        assert!(tics.take_peer_events().is_empty());
+    }
+
+    /// `NullAudio` as an OPL synth that writes one register a tic.
+    #[derive(Default)]
+    struct OplAudio(NullAudio);
+
+    impl Audio for OplAudio {
+        fn start_sound(&mut self, samples: &[u8], rate: u32, volume: u8, sep: u8) -> SoundHandle {
+            self.0.start_sound(samples, rate, volume, sep)
+        }
+
+        fn stop_sound(&mut self, handle: SoundHandle) {
+            self.0.stop_sound(handle);
+        }
+
+        fn is_playing(&self, handle: SoundHandle) -> bool {
+            self.0.is_playing(handle)
+        }
+
+        fn play_music(&mut self, data: &[u8], looping: bool) {
+            self.0.play_music(data, looping);
+        }
+
+        fn stop_music(&mut self) {
+            self.0.stop_music();
+        }
+
+        fn pause_music(&mut self, paused: bool) {
+            self.0.pause_music(paused);
+        }
+
+        fn update(&mut self) {
+            self.0.update();
+        }
+
+        fn opl_synth(&self) -> bool {
+            true
+        }
+
+        fn capture_opl(&mut self, capture: &mut MusicCapture) {
+            capture.opl_wait(VGM_RATE / TICRATE as u32);
+            capture.opl_write(0xb0, 0x20);
+        }
+    }
+
+    #[test]
+    fn the_music_capture_hears_the_opl_synth_and_the_music_stopping() {
+        let mut recorder = DemoRecorder::new(DemoHeader::default());
+        for _ in 0..70 {
+            recorder.record(&[TicCmd::default()]);
+        }
+        let playback = DemoPlayback::new(recorder.demo);
+        let mut tics = TicSource::playback(playback, Default::default(), false);
+        let mut engine = Engine::new(Wads::new(), Config::new());
+        let capture = engine.music_capture();
+        capture.borrow_mut().music_started("d_runnin", b"MUS\x1a");
+        let (mut video, mut audio) = (NullVideo::new(), OplAudio::default());
+        audio.play_music(b"MUS\x1a", true);
+        // As `oplcapture` would, before the loop says the synth is there.
+        capture.borrow_mut().opl_backend = true;
+        capture.borrow_mut().start_opl().unwrap();
+        run_loop(
+            &mut engine,
+            &mut tics,
+            &mut doom_window(),
+            &mut Watcher::default(),
+            &mut video,
+            &mut audio,
+            None,
+        );
+        let mut capture = capture.borrow_mut();
+        assert!(capture.opl_backend);
+        assert_eq!(capture.stop_opl().unwrap().seconds(), 2);
+        assert_eq!(capture.playing(), None);
+        assert!(!audio.0.music_playing());
+    }
+}